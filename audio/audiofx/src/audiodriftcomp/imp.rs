@@ -0,0 +1,699 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use byte_slice_cast::*;
+
+use once_cell::sync::Lazy;
+
+use rubato::{
+    Resampler, SincFixedIn, SincInterpolationParameters, SincInterpolationType, WindowFunction,
+};
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "audiodriftcomp",
+        gst::DebugColorFlags::empty(),
+        Some("Audio Drift Compensation"),
+    )
+});
+
+/// Number of input frames passed to the resampler at once
+const CHUNK_SIZE: usize = 1024;
+
+const DEFAULT_WINDOW: gst::ClockTime = gst::ClockTime::from_seconds(10);
+const DEFAULT_MAX_DEVIATION: f64 = 0.005;
+
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    window: gst::ClockTime,
+    max_deviation: f64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            window: DEFAULT_WINDOW,
+            max_deviation: DEFAULT_MAX_DEVIATION,
+        }
+    }
+}
+
+/// Estimates how fast the stream position drifts away from the clock.
+///
+/// Each observation is the clock running time at which a buffer arrived and the
+/// offset between that and the running time of the end of the buffer according
+/// to the number of samples received so far. The slope of a linear fit over the
+/// window is the rate mismatch.
+struct DriftEstimator {
+    window: f64,
+    observations: VecDeque<(f64, f64)>,
+}
+
+impl DriftEstimator {
+    fn new(window: f64) -> Self {
+        DriftEstimator {
+            window,
+            observations: VecDeque::new(),
+        }
+    }
+
+    /// Returns the relative drift, if there are enough observations yet
+    fn observe(&mut self, clock_time: f64, offset: f64) -> Option<f64> {
+        self.observations.push_back((clock_time, offset));
+        while self
+            .observations
+            .front()
+            .map_or(false, |(t, _)| clock_time - t > self.window)
+        {
+            self.observations.pop_front();
+        }
+
+        let (first, _) = self.observations.front()?;
+        if self.observations.len() < 8 || clock_time - first < self.window / 4.0 {
+            return None;
+        }
+
+        let n = self.observations.len() as f64;
+        let mean_t = self.observations.iter().map(|(t, _)| t).sum::<f64>() / n;
+        let mean_o = self.observations.iter().map(|(_, o)| o).sum::<f64>() / n;
+        let (cov, var) = self
+            .observations
+            .iter()
+            .fold((0.0, 0.0), |(cov, var), (t, o)| {
+                (
+                    cov + (t - mean_t) * (o - mean_o),
+                    var + (t - mean_t).powi(2),
+                )
+            });
+
+        (var > 0.0).then(|| cov / var)
+    }
+}
+
+struct State {
+    info: gst_audio::AudioInfo,
+    resampler: SincFixedIn<f32>,
+    /// Deinterleaved input that was not passed to the resampler yet
+    pending: Vec<Vec<f32>>,
+    start_pts: Option<gst::ClockTime>,
+    /// Running time of the first buffer and number of frames received since
+    start_running_time: Option<gst::ClockTime>,
+    in_frames: u64,
+    out_frames: u64,
+    /// Number of output frames corresponding to the input passed to the resampler
+    expected_out_frames: f64,
+    estimator: DriftEstimator,
+    /// Offset of the output against the clock when the measurement started
+    target_offset: Option<f64>,
+    ratio: f64,
+}
+
+impl State {
+    fn new(settings: &Settings, info: gst_audio::AudioInfo) -> Result<Self, gst::LoggableError> {
+        let params = SincInterpolationParameters {
+            sinc_len: 128,
+            f_cutoff: 0.95,
+            interpolation: SincInterpolationType::Cubic,
+            oversampling_factor: 128,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let channels = info.channels() as usize;
+        let resampler = SincFixedIn::<f32>::new(
+            1.0,
+            1.0 + settings.max_deviation,
+            params,
+            CHUNK_SIZE,
+            channels,
+        )
+        .map_err(|err| gst::loggable_error!(CAT, "Failed to create resampler: {}", err))?;
+
+        Ok(State {
+            resampler,
+            pending: vec![Vec::with_capacity(2 * CHUNK_SIZE); channels],
+            start_pts: None,
+            start_running_time: None,
+            in_frames: 0,
+            out_frames: 0,
+            expected_out_frames: 0.0,
+            estimator: DriftEstimator::new(settings.window.nseconds() as f64 / 1_000_000_000.0),
+            target_offset: None,
+            ratio: 1.0,
+            info,
+        })
+    }
+
+    fn latency(&self) -> gst::ClockTime {
+        let frames = (CHUNK_SIZE + self.resampler.output_delay()) as u64;
+        gst::ClockTime::SECOND
+            .mul_div_ceil(frames, self.info.rate() as u64)
+            .unwrap()
+    }
+
+    /// Interleaves resampled output into a new buffer
+    fn output_buffer(
+        &mut self,
+        output: Vec<Vec<f32>>,
+        max_frames: Option<usize>,
+    ) -> Option<gst::Buffer> {
+        let frames = output.first().map_or(0, Vec::len);
+        let frames = max_frames.map_or(frames, |max| frames.min(max));
+        if frames == 0 {
+            return None;
+        }
+
+        let channels = output.len();
+        let rate = self.info.rate() as u64;
+        let num_frames = frames as u64;
+        let mut buffer =
+            gst::Buffer::with_size(frames * channels * std::mem::size_of::<f32>()).unwrap();
+        {
+            let buffer = buffer.get_mut().unwrap();
+            {
+                let mut map = buffer.map_writable().unwrap();
+                let data = map.as_mut_slice_of::<f32>().unwrap();
+                for (i, frame) in data.chunks_exact_mut(channels).enumerate() {
+                    for (sample, channel) in frame.iter_mut().zip(&output) {
+                        *sample = channel[i];
+                    }
+                }
+            }
+
+            let pts = self.start_pts.map(|start| {
+                start
+                    + gst::ClockTime::SECOND
+                        .mul_div_floor(self.out_frames, rate)
+                        .unwrap()
+            });
+            let end = self.start_pts.map(|start| {
+                start
+                    + gst::ClockTime::SECOND
+                        .mul_div_floor(self.out_frames + num_frames, rate)
+                        .unwrap()
+            });
+            buffer.set_pts(pts);
+            buffer.set_duration(end.opt_checked_sub(pts).ok().flatten());
+            buffer.set_offset(self.out_frames);
+            buffer.set_offset_end(self.out_frames + num_frames);
+            if self.out_frames == 0 {
+                buffer.set_flags(gst::BufferFlags::DISCONT);
+            }
+        }
+        self.out_frames += num_frames;
+
+        Some(buffer)
+    }
+
+    fn process(&mut self, imp: &AudioDriftComp) -> Result<Vec<gst::Buffer>, gst::FlowError> {
+        let mut outbufs = vec![];
+
+        loop {
+            let needed = self.resampler.input_frames_next();
+            if self.pending[0].len() < needed {
+                break;
+            }
+
+            let chunk = self
+                .pending
+                .iter_mut()
+                .map(|channel| channel.drain(..needed).collect::<Vec<_>>())
+                .collect::<Vec<_>>();
+            let output = self.resampler.process(&chunk, None).map_err(|err| {
+                gst::error!(CAT, imp: imp, "Failed to resample: {}", err);
+                gst::FlowError::Error
+            })?;
+
+            self.expected_out_frames += needed as f64 * self.ratio;
+
+            outbufs.extend(self.output_buffer(output, None));
+        }
+
+        Ok(outbufs)
+    }
+
+    /// Resamples everything that is left and flushes the resampler
+    fn drain(&mut self, imp: &AudioDriftComp) -> Result<Vec<gst::Buffer>, gst::FlowError> {
+        let mut outbufs = self.process(imp)?;
+
+        // The last chunk is padded with silence, only keep what corresponds to actual input
+        self.expected_out_frames += self.pending[0].len() as f64 * self.ratio;
+        let remaining = (self.expected_out_frames.round() as u64).saturating_sub(self.out_frames);
+
+        let mut output = self
+            .resampler
+            .process_partial(Some(&self.pending[..]), None)
+            .map_err(|err| {
+                gst::error!(CAT, imp: imp, "Failed to resample: {}", err);
+                gst::FlowError::Error
+            })?;
+        let flushed = self
+            .resampler
+            .process_partial::<Vec<f32>>(None, None)
+            .map_err(|err| {
+                gst::error!(CAT, imp: imp, "Failed to resample: {}", err);
+                gst::FlowError::Error
+            })?;
+        for (channel, flushed) in output.iter_mut().zip(flushed) {
+            channel.extend(flushed);
+        }
+        for channel in &mut self.pending {
+            channel.clear();
+        }
+
+        outbufs.extend(self.output_buffer(output, Some(remaining as usize)));
+
+        Ok(outbufs)
+    }
+}
+
+pub struct AudioDriftComp {
+    srcpad: gst::Pad,
+    sinkpad: gst::Pad,
+    settings: Mutex<Settings>,
+    state: Mutex<Option<State>>,
+    segment: Mutex<gst::FormattedSegment<gst::ClockTime>>,
+}
+
+impl AudioDriftComp {
+    /// Returns the current running time of the pipeline clock, if any
+    fn clock_running_time(&self) -> Option<gst::ClockTime> {
+        let obj = self.obj();
+        let clock = obj.clock()?;
+        let base_time = obj.base_time()?;
+
+        clock.time()?.checked_sub(base_time)
+    }
+
+    /// Measures the drift and updates the resampling ratio accordingly.
+    ///
+    /// The ratio compensates the measured rate mismatch, plus whatever offset the
+    /// output accumulated against the clock is corrected over the window.
+    fn update_ratio(&self, state: &mut State, settings: &Settings) {
+        let (Some(start), Some(now)) = (state.start_running_time, self.clock_running_time()) else {
+            return;
+        };
+
+        let rate = state.info.rate() as f64;
+        let start = start.nseconds() as f64 / 1_000_000_000.0;
+        let now = now.nseconds() as f64 / 1_000_000_000.0;
+
+        // A growing offset means that samples arrive slower than the clock runs,
+        // so more output samples than input samples have to be produced
+        let in_offset = now - (start + state.in_frames as f64 / rate);
+        let out_frames = state.expected_out_frames + state.pending[0].len() as f64 * state.ratio;
+        let out_offset = now - (start + out_frames / rate);
+        let target_offset = *state.target_offset.get_or_insert(out_offset);
+
+        let Some(drift) = state.estimator.observe(now, in_offset) else {
+            return;
+        };
+        let correction = (out_offset - target_offset) / state.estimator.window;
+        let ratio = (1.0 + drift + correction)
+            .clamp(1.0 - settings.max_deviation, 1.0 + settings.max_deviation);
+
+        if let Err(err) = state.resampler.set_resample_ratio_relative(ratio, true) {
+            gst::warning!(CAT, imp: self, "Failed to update ratio to {}: {}", ratio, err);
+            return;
+        }
+
+        gst::trace!(
+            CAT,
+            imp: self,
+            "Drift {:.6}, output offset {:.6}s, ratio {:.6}",
+            drift,
+            out_offset - target_offset,
+            ratio
+        );
+        state.ratio = ratio;
+    }
+
+    fn sink_chain(
+        &self,
+        _pad: &gst::Pad,
+        buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::log!(CAT, imp: self, "Handling buffer {:?}", buffer);
+
+        let settings = *self.settings.lock().unwrap();
+        let mut state_guard = self.state.lock().unwrap();
+        let state = match *state_guard {
+            None => {
+                gst::error!(CAT, imp: self, "Not negotiated yet");
+                return Err(gst::FlowError::NotNegotiated);
+            }
+            Some(ref mut state) => state,
+        };
+
+        let mut outbufs = vec![];
+        if buffer.flags().contains(gst::BufferFlags::DISCONT) && state.in_frames > 0 {
+            gst::debug!(CAT, imp: self, "Draining on discontinuity");
+            outbufs.append(&mut state.drain(self)?);
+
+            *state = State::new(&settings, state.info.clone()).map_err(|err| {
+                err.log_with_imp(self);
+                gst::FlowError::Error
+            })?;
+        }
+
+        if state.start_pts.is_none() {
+            state.start_pts = buffer.pts();
+            state.start_running_time = self.segment.lock().unwrap().to_running_time(buffer.pts());
+        }
+
+        let map = buffer.map_readable().map_err(|_| {
+            gst::error!(CAT, imp: self, "Failed to map buffer");
+            gst::FlowError::Error
+        })?;
+        let samples = map.as_slice_of::<f32>().map_err(|_| {
+            gst::error!(CAT, imp: self, "Invalid buffer size");
+            gst::FlowError::Error
+        })?;
+
+        let channels = state.pending.len();
+        for frame in samples.chunks_exact(channels) {
+            for (channel, sample) in state.pending.iter_mut().zip(frame) {
+                channel.push(*sample);
+            }
+        }
+        state.in_frames += (samples.len() / channels) as u64;
+        drop(map);
+
+        self.update_ratio(state, &settings);
+
+        outbufs.append(&mut state.process(self)?);
+        drop(state_guard);
+
+        for buffer in outbufs {
+            gst::log!(CAT, imp: self, "Outputting buffer {:?}", buffer);
+            self.srcpad.push(buffer)?;
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    fn drain(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let outbufs = match &mut *state {
+            Some(state) if state.in_frames > 0 => match state.drain(self) {
+                Ok(outbufs) => outbufs,
+                Err(_) => return false,
+            },
+            _ => vec![],
+        };
+        drop(state);
+
+        for buffer in outbufs {
+            gst::log!(CAT, imp: self, "Outputting buffer {:?}", buffer);
+            if let Err(err) = self.srcpad.push(buffer) {
+                gst::error!(CAT, imp: self, "Failed to push drained data: {}", err);
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn reset(&self, info: Option<gst_audio::AudioInfo>) -> bool {
+        let settings = *self.settings.lock().unwrap();
+        let mut state = self.state.lock().unwrap();
+
+        let Some(info) = info.or_else(|| state.as_ref().map(|s| s.info.clone())) else {
+            return true;
+        };
+
+        match State::new(&settings, info) {
+            Ok(new_state) => {
+                *state = Some(new_state);
+                true
+            }
+            Err(err) => {
+                err.log_with_imp(self);
+                *state = None;
+                false
+            }
+        }
+    }
+
+    fn sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::log!(CAT, obj: pad, "Handling event {:?}", event);
+
+        match event.view() {
+            EventView::Caps(c) => {
+                let caps = c.caps();
+                gst::info!(CAT, obj: pad, "Got caps {:?}", caps);
+
+                let info = match gst_audio::AudioInfo::from_caps(caps) {
+                    Ok(info) => info,
+                    Err(_) => {
+                        gst::error!(CAT, obj: pad, "Failed to parse caps");
+                        return false;
+                    }
+                };
+
+                if !self.drain() || !self.reset(Some(info)) {
+                    return false;
+                }
+            }
+            EventView::Segment(e) => {
+                let segment = match e.segment().clone().downcast::<gst::ClockTime>() {
+                    Ok(segment) => segment,
+                    Err(_) => {
+                        gst::error!(CAT, obj: pad, "Only time segments supported");
+                        return false;
+                    }
+                };
+
+                if !self.drain() || !self.reset(None) {
+                    return false;
+                }
+                *self.segment.lock().unwrap() = segment;
+            }
+            EventView::Eos(_) => {
+                if !self.drain() || !self.reset(None) {
+                    return false;
+                }
+            }
+            EventView::FlushStop(_) => {
+                self.reset(None);
+            }
+            _ => (),
+        }
+
+        gst::Pad::event_default(pad, Some(&*self.obj()), event)
+    }
+
+    #[allow(clippy::single_match)]
+    fn src_query(&self, pad: &gst::Pad, query: &mut gst::QueryRef) -> bool {
+        use gst::QueryViewMut;
+
+        gst::log!(CAT, obj: pad, "Handling query {:?}", query);
+        match query.view_mut() {
+            QueryViewMut::Latency(q) => {
+                let mut peer_query = gst::query::Latency::new();
+                if self.sinkpad.peer_query(&mut peer_query) {
+                    let (live, min_latency, max_latency) = peer_query.result();
+                    let latency = self
+                        .state
+                        .lock()
+                        .unwrap()
+                        .as_ref()
+                        .map_or(gst::ClockTime::ZERO, State::latency);
+                    q.set(live, min_latency + latency, max_latency.opt_add(latency));
+                    true
+                } else {
+                    false
+                }
+            }
+            _ => gst::Pad::query_default(pad, Some(&*self.obj()), query),
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for AudioDriftComp {
+    const NAME: &'static str = "GstAudioDriftComp";
+    type Type = super::AudioDriftComp;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("sink").unwrap();
+        let sinkpad = gst::Pad::builder_from_template(&templ)
+            .chain_function(|pad, parent, buffer| {
+                Self::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |this| this.sink_chain(pad, buffer),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                Self::catch_panic_pad_function(parent, || false, |this| this.sink_event(pad, event))
+            })
+            .flags(gst::PadFlags::PROXY_CAPS)
+            .build();
+
+        let templ = klass.pad_template("src").unwrap();
+        let srcpad = gst::Pad::builder_from_template(&templ)
+            .query_function(|pad, parent, query| {
+                Self::catch_panic_pad_function(parent, || false, |this| this.src_query(pad, query))
+            })
+            .flags(gst::PadFlags::PROXY_CAPS)
+            .build();
+
+        Self {
+            sinkpad,
+            srcpad,
+            settings: Mutex::new(Default::default()),
+            state: Mutex::new(None),
+            segment: Mutex::new(gst::FormattedSegment::new()),
+        }
+    }
+}
+
+impl ObjectImpl for AudioDriftComp {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecUInt64::builder("window")
+                    .nick("Window")
+                    .blurb("Duration over which the drift is measured, and in which accumulated drift is corrected")
+                    .minimum(gst::ClockTime::SECOND.nseconds())
+                    .default_value(DEFAULT_WINDOW.nseconds())
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecDouble::builder("max-deviation")
+                    .nick("Maximum Deviation")
+                    .blurb("Maximum relative deviation of the resampling ratio from 1.0")
+                    .minimum(0.0)
+                    .maximum(0.1)
+                    .default_value(DEFAULT_MAX_DEVIATION)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecDouble::builder("ratio")
+                    .nick("Ratio")
+                    .blurb("Current ratio of output to input samples")
+                    .default_value(1.0)
+                    .read_only()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.sinkpad).unwrap();
+        obj.add_pad(&self.srcpad).unwrap();
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "window" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.window =
+                    gst::ClockTime::from_nseconds(value.get().expect("type checked upstream"));
+            }
+            "max-deviation" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.max_deviation = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "window" => {
+                let settings = self.settings.lock().unwrap();
+                settings.window.nseconds().to_value()
+            }
+            "max-deviation" => {
+                let settings = self.settings.lock().unwrap();
+                settings.max_deviation.to_value()
+            }
+            "ratio" => {
+                let state = self.state.lock().unwrap();
+                state.as_ref().map_or(1.0, |state| state.ratio).to_value()
+            }
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for AudioDriftComp {}
+
+impl ElementImpl for AudioDriftComp {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Audio drift compensation",
+                "Filter/Effect/Audio",
+                "Resamples live audio to compensate drift against the pipeline clock",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst_audio::AudioCapsBuilder::new_interleaved()
+                .format(gst_audio::AUDIO_FORMAT_F32)
+                .build();
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    #[allow(clippy::single_match)]
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        let res = self.parent_change_state(transition);
+
+        match transition {
+            gst::StateChange::PausedToReady => {
+                // Drop state
+                *self.state.lock().unwrap() = None;
+                *self.segment.lock().unwrap() = gst::FormattedSegment::new();
+            }
+            _ => (),
+        }
+
+        res
+    }
+}
@@ -0,0 +1,48 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * element-audiodriftcomp:
+ *
+ * Compensates the drift between a live audio source and the pipeline clock.
+ *
+ * Sources like `appsrc` that are fed from an independently clocked device
+ * produce samples slightly faster or slower than the pipeline clock runs, which
+ * eventually leads to underruns or overruns in the audio sink. This element
+ * measures the rate at which samples arrive against the pipeline clock over
+ * `window` and resamples by the corresponding fractional amount, limited to
+ * `max-deviation`. Any offset accumulated before the measurement settled is
+ * corrected over the same window.
+ *
+ * The output is timestamped contiguously from the first input buffer. The
+ * current resampling ratio is available from the `ratio` property.
+ *
+ * ## Example launch line
+ * ```
+ * gst-launch-1.0 appsrc is-live=true format=time ! audioconvert ! audiodriftcomp ! audioconvert ! autoaudiosink
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct AudioDriftComp(ObjectSubclass<imp::AudioDriftComp>) @extends gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "audiodriftcomp",
+        gst::Rank::NONE,
+        AudioDriftComp::static_type(),
+    )
+}
@@ -0,0 +1,72 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! DTMF event codes and frequencies shared by `dtmfsend` and `dtmfdetect`.
+
+/// Row frequencies in Hz, indexed by the row of the keypad.
+pub const LOW_FREQS: [f64; 4] = [697.0, 770.0, 852.0, 941.0];
+/// Column frequencies in Hz, indexed by the column of the keypad.
+pub const HIGH_FREQS: [f64; 4] = [1209.0, 1336.0, 1477.0, 1633.0];
+
+/// Keypad layout, with the event names as used in messages and by the elements' API.
+const KEYPAD: [[char; 4]; 4] = [
+    ['1', '2', '3', 'A'],
+    ['4', '5', '6', 'B'],
+    ['7', '8', '9', 'C'],
+    ['*', '0', '#', 'D'],
+];
+
+/// A DTMF event as defined in RFC 4733 section 3.2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Event(u8);
+
+impl Event {
+    /// Parses an event name, i.e. one of `0-9`, `*`, `#` and `A-D`.
+    pub fn from_name(name: char) -> Option<Self> {
+        let number = match name.to_ascii_uppercase() {
+            c @ '0'..='9' => c as u8 - b'0',
+            '*' => 10,
+            '#' => 11,
+            c @ 'A'..='D' => c as u8 - b'A' + 12,
+            _ => return None,
+        };
+
+        Some(Event(number))
+    }
+
+    /// Returns the event for the given keypad position.
+    pub fn from_position(row: usize, column: usize) -> Self {
+        Self::from_name(KEYPAD[row][column]).unwrap()
+    }
+
+    /// Event code as transmitted in RFC 4733 packets.
+    pub fn number(self) -> u8 {
+        self.0
+    }
+
+    pub fn name(self) -> char {
+        match self.0 {
+            n @ 0..=9 => (b'0' + n) as char,
+            10 => '*',
+            11 => '#',
+            n => (b'A' + n - 12) as char,
+        }
+    }
+
+    /// Low and high frequency of the tone pair in Hz.
+    pub fn frequencies(self) -> (f64, f64) {
+        let name = self.name();
+        for (row, keys) in KEYPAD.iter().enumerate() {
+            if let Some(column) = keys.iter().position(|k| *k == name) {
+                return (LOW_FREQS[row], HIGH_FREQS[column]);
+            }
+        }
+
+        unreachable!()
+    }
+}
@@ -0,0 +1,319 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_audio::subclass::prelude::*;
+use gst_base::prelude::*;
+
+use once_cell::sync::Lazy;
+
+use byte_slice_cast::*;
+
+use atomic_refcell::AtomicRefCell;
+
+use crate::dtmf::{Event, HIGH_FREQS, LOW_FREQS};
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "dtmfdetect",
+        gst::DebugColorFlags::empty(),
+        Some("DTMF detector"),
+    )
+});
+
+/// Block size at 8kHz, which gives a good frequency resolution for the DTMF frequencies
+const BLOCK_SIZE_8K: u64 = 205;
+/// Minimum mean square of a block, about -40dBFS
+const MIN_ENERGY: f64 = 1e-4;
+/// Minimum part of the block's energy in the two detected frequencies
+const MIN_PURITY: f64 = 0.7;
+/// Maximum level difference between the frequencies if the high one is weaker, 8dB
+const MAX_TWIST: f64 = 6.31;
+/// Maximum level difference between the frequencies if the low one is weaker, 4dB
+const MAX_REVERSE_TWIST: f64 = 2.51;
+
+struct State {
+    info: gst_audio::AudioInfo,
+    block_size: usize,
+    /// Goertzel coefficients for the low and then high frequencies
+    coeffs: [f64; 8],
+    /// Samples of the current block
+    block: Vec<f64>,
+    /// Timestamp of the first sample of the current block
+    block_pts: Option<gst::ClockTime>,
+    /// Event detected in the previous block and its timestamp
+    candidate: Option<(Event, Option<gst::ClockTime>)>,
+    /// Event that was last reported and is still ongoing
+    reported: Option<Event>,
+}
+
+impl State {
+    fn new(info: &gst_audio::AudioInfo) -> Self {
+        let rate = info.rate() as f64;
+        let block_size = (info.rate() as u64)
+            .mul_div_round(BLOCK_SIZE_8K, 8_000)
+            .unwrap()
+            .max(1) as usize;
+
+        let mut coeffs = [0.0; 8];
+        for (coeff, freq) in coeffs.iter_mut().zip(LOW_FREQS.iter().chain(&HIGH_FREQS)) {
+            let k = (block_size as f64 * freq / rate).round();
+            *coeff = 2.0 * (2.0 * std::f64::consts::PI * k / block_size as f64).cos();
+        }
+
+        State {
+            info: info.clone(),
+            block_size,
+            coeffs,
+            block: Vec::with_capacity(block_size),
+            block_pts: None,
+            candidate: None,
+            reported: None,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.block.clear();
+        self.block_pts = None;
+        self.candidate = None;
+        self.reported = None;
+    }
+
+    /// Returns the power of the frequency with the given Goertzel coefficient in the block
+    fn goertzel(&self, coeff: f64) -> f64 {
+        let (mut s1, mut s2) = (0.0, 0.0);
+        for x in &self.block {
+            let s = x + coeff * s1 - s2;
+            s2 = s1;
+            s1 = s;
+        }
+
+        s1 * s1 + s2 * s2 - coeff * s1 * s2
+    }
+
+    /// Returns the event contained in the current block, if any
+    fn detect(&self) -> Option<Event> {
+        let n = self.block.len() as f64;
+        let energy = self.block.iter().map(|x| x * x).sum::<f64>();
+        if energy / n < MIN_ENERGY {
+            return None;
+        }
+
+        let powers = self.coeffs.map(|coeff| self.goertzel(coeff));
+        let strongest = |powers: &[f64]| {
+            powers
+                .iter()
+                .copied()
+                .enumerate()
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .unwrap()
+        };
+        let (row, low) = strongest(&powers[..4]);
+        let (column, high) = strongest(&powers[4..]);
+
+        // A sine with amplitude A has a Goertzel power of (A * n / 2)² and an energy of A² * n / 2
+        let tone_energy = (low + high) * 2.0 / n;
+        if tone_energy < MIN_PURITY * energy {
+            return None;
+        }
+
+        if high * MAX_TWIST < low || low * MAX_REVERSE_TWIST < high {
+            return None;
+        }
+
+        Some(Event::from_position(row, column))
+    }
+}
+
+#[derive(Default)]
+pub struct DtmfDetect {
+    state: AtomicRefCell<Option<State>>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for DtmfDetect {
+    const NAME: &'static str = "GstDtmfDetect";
+    type Type = super::DtmfDetect;
+    type ParentType = gst_audio::AudioFilter;
+}
+
+impl ObjectImpl for DtmfDetect {}
+
+impl GstObjectImpl for DtmfDetect {}
+
+impl ElementImpl for DtmfDetect {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "DTMF Detector",
+                "Filter/Analyzer/Audio",
+                "Detects DTMF tones",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+}
+
+impl BaseTransformImpl for DtmfDetect {
+    const MODE: gst_base::subclass::BaseTransformMode =
+        gst_base::subclass::BaseTransformMode::AlwaysInPlace;
+    const PASSTHROUGH_ON_SAME_CAPS: bool = true;
+    const TRANSFORM_IP_ON_PASSTHROUGH: bool = true;
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        // Drop state
+        let _ = self.state.borrow_mut().take();
+
+        gst::info!(CAT, imp: self, "Stopped");
+
+        Ok(())
+    }
+
+    fn sink_event(&self, event: gst::Event) -> bool {
+        if let gst::EventView::FlushStop(_) = event.view() {
+            if let Some(ref mut state) = *self.state.borrow_mut() {
+                state.reset();
+            }
+        }
+
+        self.parent_sink_event(event)
+    }
+
+    fn transform_ip_passthrough(
+        &self,
+        buf: &gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let mut state_guard = self.state.borrow_mut();
+        let state = state_guard.as_mut().ok_or_else(|| {
+            gst::element_imp_error!(self, gst::CoreError::Negotiation, ["Have no state yet"]);
+            gst::FlowError::NotNegotiated
+        })?;
+
+        if buf.flags().contains(gst::BufferFlags::DISCONT) {
+            gst::debug!(CAT, imp: self, "Discont, resetting");
+            state.reset();
+        }
+
+        let map = buf.map_readable().map_err(|_| {
+            gst::element_imp_error!(self, gst::ResourceError::Read, ["Failed to map buffer"]);
+            gst::FlowError::Error
+        })?;
+        let samples = map.as_slice_of::<i16>().map_err(|_| {
+            gst::element_imp_error!(self, gst::ResourceError::Read, ["Invalid buffer size"]);
+            gst::FlowError::Error
+        })?;
+
+        let rate = state.info.rate() as u64;
+        let samples_to_time = |samples: usize| {
+            (samples as u64)
+                .mul_div_floor(*gst::ClockTime::SECOND, rate)
+                .map(gst::ClockTime::from_nseconds)
+                .unwrap()
+        };
+
+        let mut detected = Vec::new();
+        let mut pos = 0;
+        while pos < samples.len() {
+            if state.block.is_empty() {
+                state.block_pts = buf.pts().opt_add(samples_to_time(pos));
+            }
+
+            let n = usize::min(state.block_size - state.block.len(), samples.len() - pos);
+            state.block.extend(
+                samples[pos..][..n]
+                    .iter()
+                    .map(|s| *s as f64 / -(i16::MIN as f64)),
+            );
+            pos += n;
+
+            if state.block.len() < state.block_size {
+                break;
+            }
+
+            let event = state.detect();
+            gst::trace!(CAT, imp: self, "Detected {:?} at {}", event, state.block_pts.display());
+
+            match (event, state.candidate) {
+                (Some(event), Some((candidate, pts))) if event == candidate => {
+                    if state.reported != Some(event) {
+                        gst::debug!(CAT, imp: self, "Detected event '{}'", event.name());
+                        state.reported = Some(event);
+                        detected.push((event, pts));
+                    }
+                }
+                (Some(event), _) => {
+                    if state.reported != Some(event) {
+                        state.reported = None;
+                    }
+                    state.candidate = Some((event, state.block_pts));
+                }
+                (None, _) => {
+                    state.candidate = None;
+                    state.reported = None;
+                }
+            }
+
+            state.block.clear();
+        }
+        drop(map);
+        drop(state_guard);
+
+        if detected.is_empty() {
+            return Ok(gst::FlowSuccess::Ok);
+        }
+
+        let segment = self.obj().segment().downcast::<gst::ClockTime>().ok();
+        for (event, timestamp) in detected {
+            let running_time = segment.as_ref().and_then(|s| s.to_running_time(timestamp));
+            let stream_time = segment.as_ref().and_then(|s| s.to_stream_time(timestamp));
+
+            let s = gst::Structure::builder("dtmf-event")
+                .field("event", event.name().to_string())
+                .field("number", event.number() as i32)
+                .field("timestamp", timestamp)
+                .field("running-time", running_time)
+                .field("stream-time", stream_time)
+                .build();
+
+            gst::debug!(CAT, imp: self, "Posting message {}", s);
+
+            let _ = self
+                .obj()
+                .post_message(gst::message::Element::builder(s).src(&*self.obj()).build());
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+}
+
+impl AudioFilterImpl for DtmfDetect {
+    fn allowed_caps() -> &'static gst::Caps {
+        static CAPS: Lazy<gst::Caps> = Lazy::new(|| {
+            gst_audio::AudioCapsBuilder::new_interleaved()
+                .format(gst_audio::AUDIO_FORMAT_S16)
+                // Needs to be able to represent the highest DTMF frequency
+                .rate_range(4_000..i32::MAX)
+                .channels(1)
+                .build()
+        });
+
+        &CAPS
+    }
+
+    fn setup(&self, info: &gst_audio::AudioInfo) -> Result<(), gst::LoggableError> {
+        gst::debug!(CAT, imp: self, "Configured for caps {:?}", info);
+
+        *self.state.borrow_mut() = Some(State::new(info));
+
+        Ok(())
+    }
+}
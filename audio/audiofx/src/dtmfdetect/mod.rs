@@ -0,0 +1,49 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * element-dtmfdetect:
+ *
+ * Detects in-band DTMF tones in an audio stream and posts a `dtmf-event` element message for
+ * every detected event. The audio itself is passed through unchanged.
+ *
+ * Detection runs the Goertzel algorithm for the eight DTMF frequencies over blocks of 205
+ * samples at 8kHz, or the same duration at other sample rates. A block is considered to contain
+ * an event if the strongest row and column frequencies contain most of the energy of the block,
+ * have a plausible level difference (twist) and the block is not too quiet. An event is reported
+ * once it was detected in two consecutive blocks, and a new event can be reported after a block
+ * without it.
+ *
+ * The message contains the `event` name (`0` to `9`, `*`, `#` and `A` to `D`), the RFC 4733
+ * event `number`, and the `timestamp`, `running-time` and `stream-time` at which the event was
+ * first detected.
+ *
+ * ## Example launch line
+ * ```
+ * gst-launch-1.0 -m udpsrc port=5004 caps='application/x-rtp,media=audio,clock-rate=8000,encoding-name=PCMU' ! rtpjitterbuffer ! rtppcmudepay2 ! mulawdec ! dtmfdetect ! fakesink
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct DtmfDetect(ObjectSubclass<imp::DtmfDetect>) @extends gst_audio::AudioFilter, gst_base::BaseTransform, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "dtmfdetect",
+        gst::Rank::NONE,
+        DtmfDetect::static_type(),
+    )
+}
@@ -0,0 +1,717 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::prelude::*;
+use gst_base::subclass::base_src::CreateSuccess;
+use gst_base::subclass::prelude::*;
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use byte_slice_cast::*;
+
+use once_cell::sync::Lazy;
+
+use crate::dtmf::Event;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "dtmfsend",
+        gst::DebugColorFlags::empty(),
+        Some("DTMF sender"),
+    )
+});
+
+/// Level of a full scale sine wave in dBm0, as per G.711
+const FULL_SCALE_DBM0: f64 = 3.14;
+/// Number of times the final packet of an event is sent, as per RFC 4733 section 2.5.1.4
+const END_PACKETS: usize = 3;
+
+const DEFAULT_TONE_DURATION: gst::ClockTime = gst::ClockTime::from_mseconds(100);
+const DEFAULT_GAP_DURATION: gst::ClockTime = gst::ClockTime::from_mseconds(50);
+const DEFAULT_VOLUME: u32 = 10;
+const DEFAULT_SAMPLES_PER_BUFFER: u32 = 160;
+const DEFAULT_IS_LIVE: bool = true;
+
+#[derive(Debug, Clone)]
+struct Settings {
+    tone_duration: gst::ClockTime,
+    gap_duration: gst::ClockTime,
+    volume: u32,
+    samples_per_buffer: u32,
+    is_live: bool,
+    events: Option<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            tone_duration: DEFAULT_TONE_DURATION,
+            gap_duration: DEFAULT_GAP_DURATION,
+            volume: DEFAULT_VOLUME,
+            samples_per_buffer: DEFAULT_SAMPLES_PER_BUFFER,
+            is_live: DEFAULT_IS_LIVE,
+            events: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RtpConfig {
+    clock_rate: u32,
+    pt: u8,
+    ssrc: u32,
+    seqnum: u16,
+    timestamp_offset: u32,
+}
+
+#[derive(Debug, Clone)]
+enum Output {
+    Audio(gst_audio::AudioInfo),
+    Rtp(RtpConfig),
+}
+
+/// Event that is currently being sent, with positions in samples
+#[derive(Debug, Clone, Copy)]
+struct Active {
+    event: Event,
+    start: u64,
+    end: u64,
+}
+
+#[derive(Default)]
+struct State {
+    output: Option<Output>,
+    sample_offset: u64,
+    active: Option<Active>,
+    /// Earliest position at which the next event can start, in samples
+    next_start: u64,
+}
+
+#[derive(Default)]
+struct ClockWait {
+    clock_id: Option<gst::SingleShotClockId>,
+    flushing: bool,
+}
+
+#[derive(Default)]
+pub struct DtmfSend {
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+    /// Events that were queued but not sent yet
+    queue: Mutex<VecDeque<Event>>,
+    clock_wait: Mutex<ClockWait>,
+}
+
+impl DtmfSend {
+    /// Queues all events of the string, or none if any of them is invalid
+    fn queue_events(&self, events: &str) -> bool {
+        let Some(events) = events
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(Event::from_name)
+            .collect::<Option<Vec<_>>>()
+        else {
+            gst::warning!(CAT, imp: self, "Invalid events '{}'", events);
+            return false;
+        };
+
+        gst::debug!(CAT, imp: self, "Queueing {} events", events.len());
+        self.queue.lock().unwrap().extend(events);
+
+        true
+    }
+
+    fn wait(&self, running_time: gst::ClockTime) -> Result<(), gst::FlowError> {
+        let obj = self.obj();
+        let Some((clock, base_time)) = Option::zip(obj.clock(), obj.base_time()) else {
+            return Ok(());
+        };
+
+        let mut clock_wait = self.clock_wait.lock().unwrap();
+        if clock_wait.flushing {
+            gst::debug!(CAT, imp: self, "Flushing");
+            return Err(gst::FlowError::Flushing);
+        }
+
+        let id = clock.new_single_shot_id(base_time + running_time);
+        clock_wait.clock_id = Some(id.clone());
+        drop(clock_wait);
+
+        gst::log!(
+            CAT,
+            imp: self,
+            "Waiting until {}, now {}",
+            base_time + running_time,
+            clock.time().display(),
+        );
+        let (res, jitter) = id.wait();
+        gst::log!(CAT, imp: self, "Waited res {:?} jitter {}", res, jitter);
+        self.clock_wait.lock().unwrap().clock_id.take();
+
+        if res == Err(gst::ClockError::Unscheduled) {
+            gst::debug!(CAT, imp: self, "Flushing");
+            return Err(gst::FlowError::Flushing);
+        }
+
+        Ok(())
+    }
+
+    /// Renders `n_samples` starting at `state.sample_offset` as audio
+    fn render_audio(
+        &self,
+        settings: &Settings,
+        state: &State,
+        info: &gst_audio::AudioInfo,
+        n_samples: u64,
+    ) -> gst::Buffer {
+        let rate = info.rate() as f64;
+        let channels = info.channels() as usize;
+        // Both frequencies together have the configured power level
+        let amplitude = 10f64.powf((-(settings.volume as f64) - FULL_SCALE_DBM0 - 3.0103) / 20.0);
+
+        let mut buffer = gst::Buffer::with_size(n_samples as usize * info.bpf() as usize).unwrap();
+        {
+            let buffer = buffer.get_mut().unwrap();
+            let mut map = buffer.map_writable().unwrap();
+            let data = map.as_mut_slice_of::<i16>().unwrap();
+
+            for (pos, frame) in (state.sample_offset..).zip(data.chunks_exact_mut(channels)) {
+                let value = match state.active {
+                    Some(ref active) if pos >= active.start && pos < active.end => {
+                        let (low, high) = active.event.frequencies();
+                        let t = (pos - active.start) as f64 / rate;
+                        let value = amplitude
+                            * ((2.0 * std::f64::consts::PI * low * t).sin()
+                                + (2.0 * std::f64::consts::PI * high * t).sin());
+                        (value * i16::MAX as f64) as i16
+                    }
+                    _ => 0,
+                };
+
+                for sample in frame {
+                    *sample = value;
+                }
+            }
+        }
+
+        buffer
+    }
+
+    /// Creates the RFC 4733 packets for the active event in the given range of samples
+    fn render_rtp(
+        &self,
+        settings: &Settings,
+        config: &mut RtpConfig,
+        active: &Active,
+        samples: std::ops::Range<u64>,
+        pts: gst::ClockTime,
+        duration: gst::ClockTime,
+    ) -> gst::BufferList {
+        let is_first = active.start == samples.start;
+        let is_last = samples.end >= active.end;
+        let event_duration =
+            (samples.end.min(active.end) - active.start).min(u16::MAX as u64) as u16;
+        let timestamp = config
+            .timestamp_offset
+            .wrapping_add((active.start & 0xffff_ffff) as u32);
+
+        let n_packets = if is_last { END_PACKETS } else { 1 };
+        let mut list = gst::BufferList::new_sized(n_packets);
+        {
+            let list = list.get_mut().unwrap();
+            for _ in 0..n_packets {
+                let mut data = Vec::with_capacity(16);
+                data.push(0x80);
+                data.push(if is_first { 0x80 } else { 0x00 } | (config.pt & 0x7f));
+                data.extend_from_slice(&config.seqnum.to_be_bytes());
+                data.extend_from_slice(&timestamp.to_be_bytes());
+                data.extend_from_slice(&config.ssrc.to_be_bytes());
+                data.push(active.event.number());
+                data.push(if is_last { 0x80 } else { 0x00 } | (settings.volume as u8 & 0x3f));
+                data.extend_from_slice(&event_duration.to_be_bytes());
+                config.seqnum = config.seqnum.wrapping_add(1);
+
+                let mut buffer = gst::Buffer::from_mut_slice(data);
+                {
+                    let buffer = buffer.get_mut().unwrap();
+                    buffer.set_pts(pts);
+                    buffer.set_duration(duration);
+                }
+                list.add(buffer);
+            }
+        }
+
+        list
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for DtmfSend {
+    const NAME: &'static str = "GstDtmfSend";
+    type Type = super::DtmfSend;
+    type ParentType = gst_base::PushSrc;
+}
+
+impl ObjectImpl for DtmfSend {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecUInt::builder("tone-duration")
+                    .nick("Tone Duration")
+                    .blurb("Duration of each event in milliseconds")
+                    .minimum(10)
+                    .maximum(5_000)
+                    .default_value(DEFAULT_TONE_DURATION.mseconds() as u32)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt::builder("gap-duration")
+                    .nick("Gap Duration")
+                    .blurb("Minimum pause between events in milliseconds")
+                    .minimum(10)
+                    .maximum(5_000)
+                    .default_value(DEFAULT_GAP_DURATION.mseconds() as u32)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt::builder("volume")
+                    .nick("Volume")
+                    .blurb("Power level of the tones in -dBm0")
+                    .maximum(36)
+                    .default_value(DEFAULT_VOLUME)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecString::builder("events")
+                    .nick("Events")
+                    .blurb("Events to send after starting, e.g. \"0123*#\"")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("samples-per-buffer")
+                    .nick("Samples Per Buffer")
+                    .blurb("Number of samples per output buffer or RTP packet")
+                    .minimum(1)
+                    .default_value(DEFAULT_SAMPLES_PER_BUFFER)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("is-live")
+                    .nick("Is Live")
+                    .blurb("Produce output in sync with the clock")
+                    .default_value(DEFAULT_IS_LIVE)
+                    .mutable_ready()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn signals() -> &'static [glib::subclass::Signal] {
+        static SIGNALS: Lazy<Vec<glib::subclass::Signal>> = Lazy::new(|| {
+            vec![glib::subclass::Signal::builder("send")
+                .action()
+                .param_types([String::static_type()])
+                .return_type::<bool>()
+                .class_handler(|_token, args| {
+                    let s = args[0].get::<super::DtmfSend>().expect("signal arg");
+                    let events = args[1].get::<&str>().expect("signal arg");
+
+                    Some(s.imp().queue_events(events).to_value())
+                })
+                .build()]
+        });
+
+        SIGNALS.as_ref()
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.set_live(DEFAULT_IS_LIVE);
+        obj.set_format(gst::Format::Time);
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "tone-duration" => {
+                settings.tone_duration = gst::ClockTime::from_mseconds(
+                    value.get::<u32>().expect("type checked upstream") as u64,
+                );
+            }
+            "gap-duration" => {
+                settings.gap_duration = gst::ClockTime::from_mseconds(
+                    value.get::<u32>().expect("type checked upstream") as u64,
+                );
+            }
+            "volume" => {
+                settings.volume = value.get().expect("type checked upstream");
+            }
+            "events" => {
+                settings.events = value.get().expect("type checked upstream");
+            }
+            "samples-per-buffer" => {
+                settings.samples_per_buffer = value.get().expect("type checked upstream");
+                drop(settings);
+
+                let _ = self
+                    .obj()
+                    .post_message(gst::message::Latency::builder().src(&*self.obj()).build());
+            }
+            "is-live" => {
+                settings.is_live = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "tone-duration" => (settings.tone_duration.mseconds() as u32).to_value(),
+            "gap-duration" => (settings.gap_duration.mseconds() as u32).to_value(),
+            "volume" => settings.volume.to_value(),
+            "events" => settings.events.to_value(),
+            "samples-per-buffer" => settings.samples_per_buffer.to_value(),
+            "is-live" => settings.is_live.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for DtmfSend {}
+
+impl ElementImpl for DtmfSend {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "DTMF Sender",
+                "Source/Audio/Network/RTP",
+                "Sends DTMF events as tones or RFC 4733 RTP packets",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let mut caps = gst_audio::AudioCapsBuilder::new_interleaved()
+                .format(gst_audio::AUDIO_FORMAT_S16)
+                .build();
+            caps.get_mut().unwrap().append_structure(
+                gst::Structure::builder("application/x-rtp")
+                    .field("media", "audio")
+                    .field("encoding-name", "TELEPHONE-EVENT")
+                    .field("payload", gst::IntRange::new(96i32, 127))
+                    .field("clock-rate", gst::IntRange::new(1i32, i32::MAX))
+                    .build(),
+            );
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        if let gst::StateChange::ReadyToPaused = transition {
+            self.obj().set_live(self.settings.lock().unwrap().is_live);
+        }
+
+        self.parent_change_state(transition)
+    }
+}
+
+impl BaseSrcImpl for DtmfSend {
+    fn set_caps(&self, caps: &gst::Caps) -> Result<(), gst::LoggableError> {
+        let s = caps
+            .structure(0)
+            .ok_or_else(|| gst::loggable_error!(CAT, "Empty caps"))?;
+
+        let output = if s.name() == "application/x-rtp" {
+            let clock_rate = s
+                .get::<i32>("clock-rate")
+                .map_err(|_| gst::loggable_error!(CAT, "No clock-rate in caps {}", caps))?;
+            let pt = s
+                .get::<i32>("payload")
+                .map_err(|_| gst::loggable_error!(CAT, "No payload in caps {}", caps))?;
+
+            Output::Rtp(RtpConfig {
+                clock_rate: clock_rate as u32,
+                pt: pt as u8,
+                ssrc: s.get::<u32>("ssrc").unwrap_or(0),
+                seqnum: s.get::<u32>("seqnum-offset").unwrap_or(0) as u16,
+                timestamp_offset: s.get::<u32>("timestamp-offset").unwrap_or(0),
+            })
+        } else {
+            let info = gst_audio::AudioInfo::from_caps(caps).map_err(|_| {
+                gst::loggable_error!(CAT, "Failed to build `AudioInfo` from caps {}", caps)
+            })?;
+
+            let samples_per_buffer = self.settings.lock().unwrap().samples_per_buffer;
+            self.obj().set_blocksize(info.bpf() * samples_per_buffer);
+
+            Output::Audio(info)
+        };
+
+        gst::debug!(CAT, imp: self, "Configuring for caps {}", caps);
+
+        let mut state = self.state.lock().unwrap();
+        state.output = Some(match (output, state.output.take()) {
+            // Continue the sequence numbers on renegotiation
+            (Output::Rtp(mut config), Some(Output::Rtp(old_config))) => {
+                config.seqnum = old_config.seqnum;
+                Output::Rtp(config)
+            }
+            (output, _) => output,
+        });
+        drop(state);
+
+        let _ = self
+            .obj()
+            .post_message(gst::message::Latency::builder().src(&*self.obj()).build());
+
+        Ok(())
+    }
+
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.lock().unwrap() = State::default();
+        self.unlock_stop()?;
+
+        let events = self.settings.lock().unwrap().events.clone();
+        if let Some(events) = events {
+            if !self.queue_events(&events) {
+                return Err(gst::error_msg!(
+                    gst::LibraryError::Settings,
+                    ["Invalid events '{}'", events]
+                ));
+            }
+        }
+
+        gst::info!(CAT, imp: self, "Started");
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.lock().unwrap() = State::default();
+        self.queue.lock().unwrap().clear();
+        self.unlock()?;
+
+        gst::info!(CAT, imp: self, "Stopped");
+
+        Ok(())
+    }
+
+    fn query(&self, query: &mut gst::QueryRef) -> bool {
+        match query.view_mut() {
+            gst::QueryViewMut::Latency(q) => {
+                let settings = self.settings.lock().unwrap().clone();
+                let state = self.state.lock().unwrap();
+
+                let rate = match state.output {
+                    Some(Output::Audio(ref info)) => info.rate(),
+                    Some(Output::Rtp(ref config)) => config.clock_rate,
+                    None => return false,
+                };
+
+                let latency = gst::ClockTime::SECOND
+                    .mul_div_floor(settings.samples_per_buffer as u64, rate as u64)
+                    .unwrap();
+                gst::debug!(CAT, imp: self, "Returning latency {}", latency);
+                q.set(settings.is_live, latency, gst::ClockTime::NONE);
+                true
+            }
+            _ => BaseSrcImplExt::parent_query(self, query),
+        }
+    }
+
+    fn fixate(&self, mut caps: gst::Caps) -> gst::Caps {
+        caps.truncate();
+        {
+            let caps = caps.make_mut();
+            let s = caps.structure_mut(0).unwrap();
+            if s.name() == "application/x-rtp" {
+                s.fixate_field_nearest_int("clock-rate", 8_000);
+                s.fixate_field_nearest_int("payload", 101);
+            } else {
+                s.fixate_field_nearest_int("rate", 8_000);
+                s.fixate_field_nearest_int("channels", 1);
+            }
+        }
+
+        self.parent_fixate(caps)
+    }
+
+    fn is_seekable(&self) -> bool {
+        false
+    }
+
+    fn unlock(&self) -> Result<(), gst::ErrorMessage> {
+        gst::debug!(CAT, imp: self, "Unlocking");
+        let mut clock_wait = self.clock_wait.lock().unwrap();
+        if let Some(clock_id) = clock_wait.clock_id.take() {
+            clock_id.unschedule();
+        }
+        clock_wait.flushing = true;
+
+        Ok(())
+    }
+
+    fn unlock_stop(&self) -> Result<(), gst::ErrorMessage> {
+        gst::debug!(CAT, imp: self, "Unlock stop");
+        self.clock_wait.lock().unwrap().flushing = false;
+
+        Ok(())
+    }
+}
+
+impl PushSrcImpl for DtmfSend {
+    fn create(
+        &self,
+        _buffer: Option<&mut gst::BufferRef>,
+    ) -> Result<CreateSuccess, gst::FlowError> {
+        loop {
+            let settings = self.settings.lock().unwrap().clone();
+
+            let mut state = self.state.lock().unwrap();
+            let Some(mut output) = state.output.clone() else {
+                gst::element_imp_error!(self, gst::CoreError::Negotiation, ["Have no caps yet"]);
+                return Err(gst::FlowError::NotNegotiated);
+            };
+
+            let rate = match output {
+                Output::Audio(ref info) => info.rate() as u64,
+                Output::Rtp(ref config) => config.clock_rate as u64,
+            };
+            let to_samples = |time: gst::ClockTime| {
+                time.nseconds()
+                    .mul_div_ceil(rate, *gst::ClockTime::SECOND)
+                    .unwrap()
+            };
+            let samples_to_time = |samples: u64| {
+                samples
+                    .mul_div_floor(*gst::ClockTime::SECOND, rate)
+                    .map(gst::ClockTime::from_nseconds)
+                    .unwrap()
+            };
+
+            let n_samples = settings.samples_per_buffer as u64;
+            let start = state.sample_offset;
+            let end = start + n_samples;
+
+            if state.active.is_none() && start >= state.next_start {
+                if let Some(event) = self.queue.lock().unwrap().pop_front() {
+                    gst::debug!(
+                        CAT,
+                        imp: self,
+                        "Starting event '{}' at sample {}",
+                        event.name(),
+                        start
+                    );
+                    state.active = Some(Active {
+                        event,
+                        start,
+                        end: start + to_samples(settings.tone_duration),
+                    });
+                } else if !self.obj().is_live() {
+                    gst::debug!(CAT, imp: self, "All events sent");
+                    return Err(gst::FlowError::Eos);
+                }
+            }
+
+            let pts = samples_to_time(start);
+            let duration = samples_to_time(end) - pts;
+
+            let res = match output {
+                Output::Audio(ref info) => {
+                    let mut buffer = self.render_audio(&settings, &state, info, n_samples);
+                    {
+                        let buffer = buffer.get_mut().unwrap();
+                        buffer.set_pts(pts);
+                        buffer.set_duration(duration);
+                    }
+                    Some(CreateSuccess::NewBuffer(buffer))
+                }
+                Output::Rtp(ref mut config) => state.active.map(|active| {
+                    CreateSuccess::NewBufferList(self.render_rtp(
+                        &settings,
+                        config,
+                        &active,
+                        start..end,
+                        pts,
+                        duration,
+                    ))
+                }),
+            };
+
+            // Keep the updated sequence number
+            state.output = Some(output);
+
+            let finished = match state.active {
+                Some(active) if end >= active.end => {
+                    state.active = None;
+                    state.next_start = active.end + to_samples(settings.gap_duration);
+                    Some(active)
+                }
+                _ => None,
+            };
+            state.sample_offset = end;
+            drop(state);
+
+            let segment = self
+                .obj()
+                .segment()
+                .downcast::<gst::format::Time>()
+                .unwrap();
+
+            if self.obj().is_live() {
+                if let Some(running_time) = segment.to_running_time(pts + duration) {
+                    self.wait(running_time)?;
+                }
+            } else if self.clock_wait.lock().unwrap().flushing {
+                return Err(gst::FlowError::Flushing);
+            }
+
+            if let Some(active) = finished {
+                let timestamp = samples_to_time(active.start);
+                let s = gst::Structure::builder("dtmf-event")
+                    .field("event", active.event.name().to_string())
+                    .field("number", active.event.number() as i32)
+                    .field("timestamp", timestamp)
+                    .field("running-time", segment.to_running_time(timestamp))
+                    .field("duration", samples_to_time(active.end) - timestamp)
+                    .build();
+                let _ = self
+                    .obj()
+                    .post_message(gst::message::Element::builder(s).src(&*self.obj()).build());
+            }
+
+            if let Some(res) = res {
+                gst::debug!(CAT, imp: self, "Produced output at {}", pts);
+                return Ok(res);
+            }
+        }
+    }
+}
@@ -0,0 +1,60 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * element-dtmfsend:
+ *
+ * Sends DTMF events, either as audible dual-tones or as [RFC 4733] telephone-event RTP packets,
+ * depending on the negotiated caps.
+ *
+ * Events are named after the keys of a telephone keypad, i.e. `0` to `9`, `*`, `#` and `A` to
+ * `D`. They are queued with the `send` action signal, which takes a string of event names and
+ * returns %FALSE if any of them is invalid, or with the `events` property before starting. Each
+ * event is sent for `tone-duration`, followed by at least `gap-duration` of silence.
+ *
+ * With `audio/x-raw` caps, each event is rendered as the sum of its two frequencies at the
+ * configured `volume`, with silence in between. With `application/x-rtp` caps, one
+ * `TELEPHONE-EVENT` packet is produced every `samples-per-buffer` while an event is active and
+ * the final packet of each event is sent three times as recommended by RFC 4733. The payload
+ * type, SSRC, initial sequence number and timestamp offset are taken from the `payload`, `ssrc`,
+ * `seqnum-offset` and `timestamp-offset` caps fields if present. Such a stream can be combined
+ * with the audio stream of a call, e.g. payloaded with `rtppcmupay2`, by `rtpdtmfmux`.
+ *
+ * For every event a `dtmf-event` element message is posted once it was sent completely,
+ * containing the `event` name, its RFC 4733 event `number`, and the `timestamp`, `running-time`
+ * and `duration` of the event.
+ *
+ * If not live, the element finishes with EOS once all queued events were sent.
+ *
+ * [RFC 4733]: https://www.rfc-editor.org/rfc/rfc4733.html
+ *
+ * ## Example launch lines
+ * ```
+ * gst-launch-1.0 dtmfsend is-live=false events=0123456789 ! audioconvert ! autoaudiosink
+ * gst-launch-1.0 dtmfsend events=5551 ! 'application/x-rtp,payload=101' ! udpsink host=127.0.0.1 port=5004
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct DtmfSend(ObjectSubclass<imp::DtmfSend>) @extends gst_base::PushSrc, gst_base::BaseSrc, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "dtmfsend",
+        gst::Rank::NONE,
+        DtmfSend::static_type(),
+    )
+}
@@ -14,18 +14,27 @@
  */
 use gst::glib;
 
+mod audiodriftcomp;
 mod audioecho;
 mod audioloudnorm;
 mod audiornnoise;
+mod dtmf;
+mod dtmfdetect;
+mod dtmfsend;
 mod ebur128level;
 mod hrtfrender;
+mod metronome;
 
 fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    audiodriftcomp::register(plugin)?;
     audioecho::register(plugin)?;
     audioloudnorm::register(plugin)?;
     audiornnoise::register(plugin)?;
+    dtmfdetect::register(plugin)?;
+    dtmfsend::register(plugin)?;
     ebur128level::register(plugin)?;
     hrtfrender::register(plugin)?;
+    metronome::register(plugin)?;
     Ok(())
 }
 
@@ -0,0 +1,584 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::prelude::*;
+use gst_base::subclass::base_src::CreateSuccess;
+use gst_base::subclass::prelude::*;
+
+use std::sync::Mutex;
+
+use byte_slice_cast::*;
+
+use once_cell::sync::Lazy;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "metronome",
+        gst::DebugColorFlags::empty(),
+        Some("Metronome"),
+    )
+});
+
+static TICK_CAPS: Lazy<gst::Caps> =
+    Lazy::new(|| gst::Caps::builder("timestamp/x-metronome-tick").build());
+
+const CLICK_DURATION: gst::ClockTime = gst::ClockTime::from_mseconds(20);
+const CLICK_FREQ: f64 = 880.0;
+const ACCENT_FREQ: f64 = 1760.0;
+
+const DEFAULT_BPM: f64 = 120.0;
+const DEFAULT_BEATS_PER_BAR: u32 = 4;
+const DEFAULT_JITTER: gst::ClockTime = gst::ClockTime::ZERO;
+const DEFAULT_SEED: u32 = 0;
+const DEFAULT_VOLUME: f64 = 0.8;
+const DEFAULT_SAMPLES_PER_BUFFER: u32 = 1024;
+const DEFAULT_IS_LIVE: bool = true;
+
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    bpm: f64,
+    beats_per_bar: u32,
+    jitter: gst::ClockTime,
+    seed: u32,
+    volume: f64,
+    samples_per_buffer: u32,
+    is_live: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            bpm: DEFAULT_BPM,
+            beats_per_bar: DEFAULT_BEATS_PER_BAR,
+            jitter: DEFAULT_JITTER,
+            seed: DEFAULT_SEED,
+            volume: DEFAULT_VOLUME,
+            samples_per_buffer: DEFAULT_SAMPLES_PER_BUFFER,
+            is_live: DEFAULT_IS_LIVE,
+        }
+    }
+}
+
+/// Click that is currently being output
+struct Click {
+    start: u64,
+    freq: f64,
+}
+
+struct State {
+    info: Option<gst_audio::AudioInfo>,
+    sample_offset: u64,
+    /// Number of the next tick
+    tick: u64,
+    /// Position of the next tick without jitter, in samples
+    next_nominal: f64,
+    /// Position of the next tick, in samples
+    next_tick: u64,
+    click: Option<Click>,
+    rng: u64,
+}
+
+impl State {
+    fn new(seed: u32) -> Self {
+        State {
+            info: None,
+            sample_offset: 0,
+            tick: 0,
+            next_nominal: 0.0,
+            next_tick: 0,
+            click: None,
+            // xorshift must not be seeded with zero
+            rng: seed as u64 ^ 0x9e37_79b9_7f4a_7c15,
+        }
+    }
+
+    /// Returns a uniformly distributed random number in [-1.0, 1.0)
+    fn random(&mut self) -> f64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+
+        (self.rng >> 11) as f64 / (1u64 << 52) as f64 - 1.0
+    }
+
+    /// Schedules the tick following the one at `current`
+    fn schedule_next_tick(&mut self, settings: &Settings, rate: u32, current: u64) {
+        self.tick += 1;
+        self.next_nominal += 60.0 / settings.bpm * rate as f64;
+
+        let jitter = settings.jitter.nseconds() as f64 / 1_000_000_000.0 * rate as f64;
+        let next = (self.next_nominal + jitter * self.random())
+            .round()
+            .max(0.0) as u64;
+        // Ticks never overtake each other, whatever the jitter
+        self.next_tick = next.max(current + 1);
+    }
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State::new(DEFAULT_SEED)
+    }
+}
+
+#[derive(Default)]
+struct ClockWait {
+    clock_id: Option<gst::SingleShotClockId>,
+    flushing: bool,
+}
+
+#[derive(Default)]
+pub struct Metronome {
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+    clock_wait: Mutex<ClockWait>,
+}
+
+impl Metronome {
+    fn wait(&self, running_time: gst::ClockTime) -> Result<(), gst::FlowError> {
+        let obj = self.obj();
+        let Some((clock, base_time)) = Option::zip(obj.clock(), obj.base_time()) else {
+            return Ok(());
+        };
+
+        let mut clock_wait = self.clock_wait.lock().unwrap();
+        if clock_wait.flushing {
+            gst::debug!(CAT, imp: self, "Flushing");
+            return Err(gst::FlowError::Flushing);
+        }
+
+        let id = clock.new_single_shot_id(base_time + running_time);
+        clock_wait.clock_id = Some(id.clone());
+        drop(clock_wait);
+
+        gst::log!(
+            CAT,
+            imp: self,
+            "Waiting until {}, now {}",
+            base_time + running_time,
+            clock.time().display(),
+        );
+        let (res, jitter) = id.wait();
+        gst::log!(CAT, imp: self, "Waited res {:?} jitter {}", res, jitter);
+        self.clock_wait.lock().unwrap().clock_id.take();
+
+        if res == Err(gst::ClockError::Unscheduled) {
+            gst::debug!(CAT, imp: self, "Flushing");
+            return Err(gst::FlowError::Flushing);
+        }
+
+        Ok(())
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for Metronome {
+    const NAME: &'static str = "GstMetronome";
+    type Type = super::Metronome;
+    type ParentType = gst_base::PushSrc;
+}
+
+impl ObjectImpl for Metronome {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecDouble::builder("bpm")
+                    .nick("BPM")
+                    .blurb("Tempo in beats per minute")
+                    .minimum(1.0)
+                    .maximum(600.0)
+                    .default_value(DEFAULT_BPM)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt::builder("beats-per-bar")
+                    .nick("Beats Per Bar")
+                    .blurb("Number of beats per bar, the first of which is accented (0 = no accents)")
+                    .maximum(64)
+                    .default_value(DEFAULT_BEATS_PER_BAR)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt64::builder("jitter")
+                    .nick("Jitter")
+                    .blurb("Maximum random offset of each tick from its nominal position in nanoseconds")
+                    .maximum(gst::ClockTime::SECOND.nseconds())
+                    .default_value(DEFAULT_JITTER.nseconds())
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt::builder("seed")
+                    .nick("Seed")
+                    .blurb("Seed for the random jitter")
+                    .default_value(DEFAULT_SEED)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecDouble::builder("volume")
+                    .nick("Volume")
+                    .blurb("Volume of the ticks")
+                    .maximum(1.0)
+                    .default_value(DEFAULT_VOLUME)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt::builder("samples-per-buffer")
+                    .nick("Samples Per Buffer")
+                    .blurb("Number of samples per output buffer")
+                    .minimum(1)
+                    .default_value(DEFAULT_SAMPLES_PER_BUFFER)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("is-live")
+                    .nick("Is Live")
+                    .blurb("Produce ticks in sync with the clock")
+                    .default_value(DEFAULT_IS_LIVE)
+                    .mutable_ready()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.set_live(DEFAULT_IS_LIVE);
+        obj.set_format(gst::Format::Time);
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "bpm" => {
+                let bpm = value.get().expect("type checked upstream");
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing bpm from {} to {}",
+                    settings.bpm,
+                    bpm
+                );
+                settings.bpm = bpm;
+            }
+            "beats-per-bar" => {
+                settings.beats_per_bar = value.get().expect("type checked upstream");
+            }
+            "jitter" => {
+                settings.jitter =
+                    gst::ClockTime::from_nseconds(value.get().expect("type checked upstream"));
+            }
+            "seed" => {
+                settings.seed = value.get().expect("type checked upstream");
+            }
+            "volume" => {
+                settings.volume = value.get().expect("type checked upstream");
+            }
+            "samples-per-buffer" => {
+                settings.samples_per_buffer = value.get().expect("type checked upstream");
+                drop(settings);
+
+                let _ = self
+                    .obj()
+                    .post_message(gst::message::Latency::builder().src(&*self.obj()).build());
+            }
+            "is-live" => {
+                settings.is_live = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "bpm" => settings.bpm.to_value(),
+            "beats-per-bar" => settings.beats_per_bar.to_value(),
+            "jitter" => settings.jitter.nseconds().to_value(),
+            "seed" => settings.seed.to_value(),
+            "volume" => settings.volume.to_value(),
+            "samples-per-buffer" => settings.samples_per_buffer.to_value(),
+            "is-live" => settings.is_live.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for Metronome {}
+
+impl ElementImpl for Metronome {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Metronome",
+                "Source/Audio",
+                "Produces ticks at a configurable tempo",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst_audio::AudioCapsBuilder::new_interleaved()
+                .format(gst_audio::AUDIO_FORMAT_F32)
+                .build();
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        if let gst::StateChange::ReadyToPaused = transition {
+            self.obj().set_live(self.settings.lock().unwrap().is_live);
+        }
+
+        self.parent_change_state(transition)
+    }
+}
+
+impl BaseSrcImpl for Metronome {
+    fn set_caps(&self, caps: &gst::Caps) -> Result<(), gst::LoggableError> {
+        let info = gst_audio::AudioInfo::from_caps(caps).map_err(|_| {
+            gst::loggable_error!(CAT, "Failed to build `AudioInfo` from caps {}", caps)
+        })?;
+
+        gst::debug!(CAT, imp: self, "Configuring for caps {}", caps);
+
+        let samples_per_buffer = self.settings.lock().unwrap().samples_per_buffer;
+        self.obj().set_blocksize(info.bpf() * samples_per_buffer);
+
+        let mut state = self.state.lock().unwrap();
+        if let Some(ref old_info) = state.info {
+            // Keep the tick positions when the rate changes
+            let scale = info.rate() as f64 / old_info.rate() as f64;
+            state.sample_offset = (state.sample_offset as f64 * scale).round() as u64;
+            state.next_nominal *= scale;
+            state.next_tick = (state.next_tick as f64 * scale).round() as u64;
+            state.click = None;
+        }
+        state.info = Some(info);
+        drop(state);
+
+        let _ = self
+            .obj()
+            .post_message(gst::message::Latency::builder().src(&*self.obj()).build());
+
+        Ok(())
+    }
+
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        let seed = self.settings.lock().unwrap().seed;
+        *self.state.lock().unwrap() = State::new(seed);
+        self.unlock_stop()?;
+
+        gst::info!(CAT, imp: self, "Started");
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.lock().unwrap() = State::default();
+        self.unlock()?;
+
+        gst::info!(CAT, imp: self, "Stopped");
+
+        Ok(())
+    }
+
+    fn query(&self, query: &mut gst::QueryRef) -> bool {
+        match query.view_mut() {
+            gst::QueryViewMut::Latency(q) => {
+                let settings = *self.settings.lock().unwrap();
+                let state = self.state.lock().unwrap();
+
+                if let Some(ref info) = state.info {
+                    let latency = gst::ClockTime::SECOND
+                        .mul_div_floor(settings.samples_per_buffer as u64, info.rate() as u64)
+                        .unwrap();
+                    gst::debug!(CAT, imp: self, "Returning latency {}", latency);
+                    q.set(settings.is_live, latency, gst::ClockTime::NONE);
+                    true
+                } else {
+                    false
+                }
+            }
+            _ => BaseSrcImplExt::parent_query(self, query),
+        }
+    }
+
+    fn fixate(&self, mut caps: gst::Caps) -> gst::Caps {
+        caps.truncate();
+        {
+            let caps = caps.make_mut();
+            let s = caps.structure_mut(0).unwrap();
+            s.fixate_field_nearest_int("rate", 48_000);
+            s.fixate_field_nearest_int("channels", 1);
+        }
+
+        self.parent_fixate(caps)
+    }
+
+    fn is_seekable(&self) -> bool {
+        false
+    }
+
+    fn unlock(&self) -> Result<(), gst::ErrorMessage> {
+        gst::debug!(CAT, imp: self, "Unlocking");
+        let mut clock_wait = self.clock_wait.lock().unwrap();
+        if let Some(clock_id) = clock_wait.clock_id.take() {
+            clock_id.unschedule();
+        }
+        clock_wait.flushing = true;
+
+        Ok(())
+    }
+
+    fn unlock_stop(&self) -> Result<(), gst::ErrorMessage> {
+        gst::debug!(CAT, imp: self, "Unlock stop");
+        self.clock_wait.lock().unwrap().flushing = false;
+
+        Ok(())
+    }
+}
+
+impl PushSrcImpl for Metronome {
+    fn create(
+        &self,
+        _buffer: Option<&mut gst::BufferRef>,
+    ) -> Result<CreateSuccess, gst::FlowError> {
+        let settings = *self.settings.lock().unwrap();
+
+        let mut state = self.state.lock().unwrap();
+        let Some(info) = state.info.clone() else {
+            gst::element_imp_error!(self, gst::CoreError::Negotiation, ["Have no caps yet"]);
+            return Err(gst::FlowError::NotNegotiated);
+        };
+
+        let rate = info.rate() as u64;
+        let channels = info.channels() as usize;
+        let n_samples = settings.samples_per_buffer as u64;
+        let click_samples = CLICK_DURATION
+            .nseconds()
+            .mul_div_floor(rate, *gst::ClockTime::SECOND)
+            .unwrap();
+        let samples_to_time = |samples: u64| {
+            samples
+                .mul_div_floor(*gst::ClockTime::SECOND, rate)
+                .map(gst::ClockTime::from_nseconds)
+                .unwrap()
+        };
+
+        // Tick number, beat in the bar and position
+        let mut ticks = Vec::new();
+
+        let mut buffer = gst::Buffer::with_size(n_samples as usize * info.bpf() as usize).unwrap();
+        {
+            let buffer = buffer.get_mut().unwrap();
+            let pts = samples_to_time(state.sample_offset);
+            let next_pts = samples_to_time(state.sample_offset + n_samples);
+            buffer.set_pts(pts);
+            buffer.set_duration(next_pts - pts);
+
+            let mut map = buffer.map_writable().unwrap();
+            let data = map.as_mut_slice_of::<f32>().unwrap();
+
+            for (pos, frame) in (state.sample_offset..).zip(data.chunks_exact_mut(channels)) {
+                while state.next_tick <= pos {
+                    let beat = if settings.beats_per_bar > 0 {
+                        (state.tick % settings.beats_per_bar as u64) as u32 + 1
+                    } else {
+                        1
+                    };
+                    let freq = if settings.beats_per_bar > 0 && beat == 1 {
+                        ACCENT_FREQ
+                    } else {
+                        CLICK_FREQ
+                    };
+
+                    gst::trace!(CAT, imp: self, "Tick {} at sample {}", state.tick, pos);
+                    ticks.push((state.tick, beat, pos));
+                    state.click = Some(Click { start: pos, freq });
+                    state.schedule_next_tick(&settings, rate as u32, pos);
+                }
+
+                let value = match state.click {
+                    Some(ref click) if pos - click.start < click_samples => {
+                        let t = (pos - click.start) as f64;
+                        let envelope = 1.0 - t / click_samples as f64;
+                        let phase = 2.0 * std::f64::consts::PI * click.freq * t / rate as f64;
+                        (settings.volume * envelope * envelope * phase.sin()) as f32
+                    }
+                    _ => 0.0,
+                };
+
+                for sample in frame {
+                    *sample = value;
+                }
+            }
+            drop(map);
+
+            for &(_, _, pos) in &ticks {
+                gst::ReferenceTimestampMeta::add(
+                    buffer,
+                    &TICK_CAPS,
+                    samples_to_time(pos),
+                    CLICK_DURATION,
+                );
+            }
+        }
+        state.sample_offset += n_samples;
+        drop(state);
+
+        let segment = self
+            .obj()
+            .segment()
+            .downcast::<gst::format::Time>()
+            .unwrap();
+
+        if self.obj().is_live() {
+            let end = buffer.pts().opt_add(buffer.duration());
+            if let Some(running_time) = segment.to_running_time(end) {
+                self.wait(running_time)?;
+            }
+        }
+
+        for (tick, beat, pos) in ticks {
+            let timestamp = samples_to_time(pos);
+            let s = gst::Structure::builder("metronome-tick")
+                .field("tick", tick)
+                .field("beat", beat)
+                .field("timestamp", timestamp)
+                .field("running-time", segment.to_running_time(timestamp))
+                .build();
+            let _ = self
+                .obj()
+                .post_message(gst::message::Element::builder(s).src(&*self.obj()).build());
+        }
+
+        gst::debug!(CAT, imp: self, "Produced buffer {:?}", buffer);
+
+        Ok(CreateSuccess::NewBuffer(buffer))
+    }
+}
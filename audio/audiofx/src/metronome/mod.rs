@@ -0,0 +1,53 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * element-metronome:
+ *
+ * Produces audible ticks at a fixed tempo, locked to the pipeline clock when
+ * live.
+ *
+ * Each tick is a short sine burst, with a higher pitched tick on the first beat
+ * of every bar of `beats-per-bar` beats. The tempo is configured with `bpm` and
+ * can be changed while playing. To test how downstream copes with irregular
+ * input, every tick can be moved by a random offset of up to `jitter`. The
+ * random sequence only depends on `seed`, so runs are reproducible.
+ *
+ * The exact position of every tick is attached to the buffer containing it as a
+ * `GstReferenceTimestampMeta` with `timestamp/x-metronome-tick` reference caps,
+ * whose timestamp is the PTS of the first sample of the tick. Additionally a
+ * `metronome-tick` element message is posted for every tick once it was
+ * produced, containing the `tick` number, the `beat` in the bar (starting at
+ * 1), the `timestamp` and the `running-time` of the tick. Comparing the latter
+ * with the time the tick is detected further downstream allows measuring the
+ * latency of a pipeline.
+ *
+ * ## Example launch line
+ * ```
+ * gst-launch-1.0 metronome bpm=90 beats-per-bar=3 ! audioconvert ! autoaudiosink
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct Metronome(ObjectSubclass<imp::Metronome>) @extends gst_base::PushSrc, gst_base::BaseSrc, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "metronome",
+        gst::Rank::NONE,
+        Metronome::static_type(),
+    )
+}
@@ -0,0 +1,94 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsaudiofx::plugin_register_static().expect("Failed to register rsaudiofx plugin");
+    });
+}
+
+const RATE: u64 = 48_000;
+const FRAMES_PER_BUFFER: u64 = 480;
+
+/// Pushes `num_buffers` buffers of 10ms while the clock advances by `clock_step`
+/// for each of them, and returns the number of output frames and the final ratio
+fn run_test(num_buffers: u64, clock_step: gst::ClockTime) -> (u64, f64) {
+    init();
+
+    let mut h = gst_check::Harness::new("audiodriftcomp");
+    h.use_testclock();
+    h.set_src_caps_str(if cfg!(target_endian = "little") {
+        "audio/x-raw,format=F32LE,rate=48000,channels=1,layout=interleaved"
+    } else {
+        "audio/x-raw,format=F32BE,rate=48000,channels=1,layout=interleaved"
+    });
+
+    let testclock = h.testclock().unwrap();
+    let mut out_frames = 0;
+    for i in 0..num_buffers {
+        testclock.set_time(clock_step * (i + 1));
+
+        let mut buffer =
+            gst::Buffer::with_size(FRAMES_PER_BUFFER as usize * std::mem::size_of::<f32>())
+                .unwrap();
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(
+                gst::ClockTime::SECOND
+                    .mul_div_floor(i * FRAMES_PER_BUFFER, RATE)
+                    .unwrap(),
+            );
+        }
+        h.push(buffer).unwrap();
+
+        while let Some(buffer) = h.try_pull() {
+            out_frames += buffer.size() as u64 / std::mem::size_of::<f32>() as u64;
+        }
+    }
+
+    let ratio = h.element().unwrap().property::<f64>("ratio");
+
+    h.push_event(gst::event::Eos::new());
+    while let Some(buffer) = h.try_pull() {
+        out_frames += buffer.size() as u64 / std::mem::size_of::<f32>() as u64;
+    }
+
+    (out_frames, ratio)
+}
+
+#[test]
+fn test_no_drift() {
+    let (out_frames, ratio) = run_test(500, gst::ClockTime::from_mseconds(10));
+
+    assert!((ratio - 1.0).abs() < 1e-4, "ratio {ratio}");
+    assert!(out_frames.abs_diff(500 * FRAMES_PER_BUFFER) <= 1);
+}
+
+#[test]
+fn test_slow_source() {
+    // The source runs 0.2% slower than the clock
+    let (out_frames, ratio) = run_test(2000, gst::ClockTime::from_useconds(10_020));
+
+    assert!((1.001..1.004).contains(&ratio), "ratio {ratio}");
+    assert!(out_frames > 2000 * FRAMES_PER_BUFFER);
+}
+
+#[test]
+fn test_fast_source() {
+    // The source runs 0.2% faster than the clock
+    let (out_frames, ratio) = run_test(2000, gst::ClockTime::from_useconds(9_980));
+
+    assert!((0.996..0.999).contains(&ratio), "ratio {ratio}");
+    assert!(out_frames < 2000 * FRAMES_PER_BUFFER);
+}
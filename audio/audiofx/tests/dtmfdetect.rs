@@ -0,0 +1,106 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsaudiofx::plugin_register_static().expect("Failed to register rsaudiofx plugin");
+    });
+}
+
+/// Runs the pipeline to EOS and returns the name of the element, event and timestamp of all
+/// posted DTMF events
+fn run(pipeline: &str) -> Vec<(String, String, gst::ClockTime)> {
+    init();
+
+    let pipeline = gst::parse::launch(pipeline)
+        .unwrap()
+        .downcast::<gst::Pipeline>()
+        .unwrap();
+    pipeline.set_state(gst::State::Playing).unwrap();
+
+    let bus = pipeline.bus().unwrap();
+    let mut events = vec![];
+    for msg in bus.iter_timed(gst::ClockTime::NONE) {
+        match msg.view() {
+            gst::MessageView::Element(m) => {
+                let s = m.structure().unwrap();
+                if s.name() == "dtmf-event" {
+                    events.push((
+                        msg.src().unwrap().name().to_string(),
+                        s.get::<String>("event").unwrap(),
+                        s.get::<gst::ClockTime>("timestamp").unwrap(),
+                    ));
+                }
+            }
+            gst::MessageView::Eos(_) => break,
+            gst::MessageView::Error(err) => panic!("{}", err.error()),
+            _ => (),
+        }
+    }
+
+    pipeline.set_state(gst::State::Null).unwrap();
+
+    events
+}
+
+fn test_detect(rate: u32) {
+    let launch = format!(
+        "dtmfsend name=send is-live=false events=0123456789*#ABCD ! audio/x-raw,rate={rate} ! dtmfdetect name=detect ! fakesink"
+    );
+    let events = run(&launch);
+    let from = |name: &str| {
+        events
+            .iter()
+            .filter(|(src, _, _)| src == name)
+            .map(|(_, event, timestamp)| (event.clone(), *timestamp))
+            .collect::<Vec<_>>()
+    };
+    let sent = from("send");
+    let detected = from("detect");
+
+    assert_eq!(sent.len(), 16);
+    assert_eq!(
+        detected.iter().map(|(e, _)| e).collect::<Vec<_>>(),
+        sent.iter().map(|(e, _)| e).collect::<Vec<_>>()
+    );
+
+    // Detected within two blocks of the start of the tone
+    for ((_, sent), (_, detected)) in sent.iter().zip(detected.iter()) {
+        assert!(detected >= sent);
+        assert!(*detected - *sent <= gst::ClockTime::from_nseconds(51_250_000));
+    }
+}
+
+#[test]
+fn test_detect_8khz() {
+    test_detect(8_000);
+}
+
+#[test]
+fn test_detect_48khz() {
+    test_detect(48_000);
+}
+
+#[test]
+fn test_no_false_positives() {
+    let detected = run(
+        "audiotestsrc num-buffers=50 freq=941 ! audio/x-raw,rate=8000,channels=1 ! dtmfdetect ! fakesink",
+    );
+    assert!(detected.is_empty());
+
+    let detected = run(
+        "audiotestsrc num-buffers=50 wave=white-noise ! audio/x-raw,rate=8000,channels=1 ! dtmfdetect ! fakesink",
+    );
+    assert!(detected.is_empty());
+}
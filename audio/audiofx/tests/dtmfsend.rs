@@ -0,0 +1,151 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+
+use byte_slice_cast::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsaudiofx::plugin_register_static().expect("Failed to register rsaudiofx plugin");
+    });
+}
+
+fn setup(events: &str, caps: &str) -> (gst_check::Harness, gst::Bus) {
+    init();
+
+    let mut h = gst_check::Harness::new("dtmfsend");
+    let bus = gst::Bus::new();
+    {
+        let src = h.element().unwrap();
+        src.set_property("is-live", false);
+        src.set_property("events", events);
+        src.set_bus(Some(&bus));
+    }
+    h.set_sink_caps_str(caps);
+    h.play();
+
+    (h, bus)
+}
+
+fn messages(bus: &gst::Bus) -> Vec<(String, gst::ClockTime, gst::ClockTime)> {
+    std::iter::from_fn(|| bus.pop_filtered(&[gst::MessageType::Element]))
+        .filter_map(|msg| {
+            let s = msg.structure()?;
+            (s.name() == "dtmf-event").then(|| {
+                (
+                    s.get::<String>("event").unwrap(),
+                    s.get::<gst::ClockTime>("timestamp").unwrap(),
+                    s.get::<gst::ClockTime>("duration").unwrap(),
+                )
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn test_tones() {
+    let (mut h, bus) = setup(
+        "1#",
+        if cfg!(target_endian = "little") {
+            "audio/x-raw,format=S16LE,rate=8000,channels=1,layout=interleaved"
+        } else {
+            "audio/x-raw,format=S16BE,rate=8000,channels=1,layout=interleaved"
+        },
+    );
+
+    // 100ms tones of 5 buffers each, separated by at least 50ms of silence and aligned to
+    // buffers, followed by a final pause
+    let mut silent = vec![];
+    for i in 0..16u64 {
+        let buffer = h.pull().unwrap();
+        assert_eq!(buffer.pts(), Some(gst::ClockTime::from_mseconds(i * 20)));
+        assert_eq!(buffer.duration(), Some(gst::ClockTime::from_mseconds(20)));
+        assert_eq!(buffer.size(), 320);
+
+        let map = buffer.map_readable().unwrap();
+        let samples = map.as_slice_of::<i16>().unwrap();
+        silent.push(samples.iter().all(|s| *s == 0));
+    }
+    assert_eq!(
+        silent,
+        [
+            false, false, false, false, false, true, true, true, false, false, false, false, false,
+            true, true, true
+        ]
+    );
+    assert_eq!(h.pull_until_eos().unwrap(), None);
+
+    assert_eq!(
+        messages(&bus),
+        [
+            (
+                String::from("1"),
+                gst::ClockTime::ZERO,
+                gst::ClockTime::from_mseconds(100)
+            ),
+            (
+                String::from("#"),
+                gst::ClockTime::from_mseconds(160),
+                gst::ClockTime::from_mseconds(100)
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_rtp() {
+    let (mut h, bus) = setup(
+        "5",
+        "application/x-rtp,media=audio,encoding-name=TELEPHONE-EVENT,clock-rate=8000,payload=101,ssrc=(uint)1234,seqnum-offset=(uint)10,timestamp-offset=(uint)1000",
+    );
+
+    // One packet every 20ms, with the final one sent three times
+    for i in 0..7u16 {
+        let buffer = h.pull().unwrap();
+        assert_eq!(
+            buffer.pts(),
+            Some(gst::ClockTime::from_mseconds(u64::min(i as u64, 4) * 20))
+        );
+
+        let map = buffer.map_readable().unwrap();
+        let is_last = i >= 4;
+        let duration = if is_last { 800 } else { (i + 1) * 160 };
+
+        let mut expected = vec![0x80, if i == 0 { 0x80 | 101 } else { 101 }];
+        expected.extend_from_slice(&(10 + i).to_be_bytes());
+        expected.extend_from_slice(&1000u32.to_be_bytes());
+        expected.extend_from_slice(&1234u32.to_be_bytes());
+        expected.extend_from_slice(&[5, if is_last { 0x80 | 10 } else { 10 }]);
+        expected.extend_from_slice(&duration.to_be_bytes());
+        assert_eq!(map.as_slice(), expected.as_slice());
+    }
+    assert_eq!(h.pull_until_eos().unwrap(), None);
+
+    assert_eq!(
+        messages(&bus),
+        [(
+            String::from("5"),
+            gst::ClockTime::ZERO,
+            gst::ClockTime::from_mseconds(100)
+        )]
+    );
+}
+
+#[test]
+fn test_send_signal() {
+    init();
+
+    let src = gst::ElementFactory::make("dtmfsend").build().unwrap();
+    assert!(!src.emit_by_name::<bool>("send", &[&"12x"]));
+    assert!(src.emit_by_name::<bool>("send", &[&"12 *#AbC"]));
+}
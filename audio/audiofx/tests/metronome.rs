@@ -0,0 +1,159 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+
+use byte_slice_cast::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsaudiofx::plugin_register_static().expect("Failed to register rsaudiofx plugin");
+    });
+}
+
+const SAMPLES_PER_BUFFER: u64 = 480;
+
+fn setup(props: &[(&str, &dyn ToValue)]) -> gst_check::Harness {
+    init();
+
+    let mut h = gst_check::Harness::new("metronome");
+    {
+        let src = h.element().unwrap();
+        src.set_property("samples-per-buffer", SAMPLES_PER_BUFFER as u32);
+        for (name, value) in props {
+            src.set_property_from_value(name, &value.to_value());
+        }
+    }
+    h.set_sink_caps_str(if cfg!(target_endian = "little") {
+        "audio/x-raw,format=F32LE,rate=48000,channels=1,layout=interleaved"
+    } else {
+        "audio/x-raw,format=F32BE,rate=48000,channels=1,layout=interleaved"
+    });
+    h.play();
+
+    h
+}
+
+/// Returns the timestamps of all ticks in the buffer and checks that the
+/// audio is silent before the first one
+fn ticks(buffer: &gst::Buffer) -> Vec<gst::ClockTime> {
+    let ticks = buffer
+        .iter_meta::<gst::ReferenceTimestampMeta>()
+        .filter(|meta| {
+            meta.reference()
+                .structure(0)
+                .map_or(false, |s| s.name() == "timestamp/x-metronome-tick")
+        })
+        .map(|meta| meta.timestamp())
+        .collect::<Vec<_>>();
+
+    let map = buffer.map_readable().unwrap();
+    let samples = map.as_slice_of::<f32>().unwrap();
+    let pts = buffer.pts().unwrap();
+    if let Some(first) = ticks.first() {
+        let first_sample = (*first - pts)
+            .nseconds()
+            .mul_div_floor(48_000, *gst::ClockTime::SECOND)
+            .unwrap() as usize;
+        assert!(samples[..first_sample].iter().all(|s| *s == 0.0));
+        assert!(samples[first_sample..].iter().any(|s| *s != 0.0));
+    }
+
+    ticks
+}
+
+#[test]
+fn test_ticks() {
+    let mut h = setup(&[("is-live", &false), ("bpm", &600.0f64)]);
+
+    let mut all_ticks = vec![];
+    for i in 0..100 {
+        let buffer = h.pull().unwrap();
+        assert_eq!(
+            buffer.pts(),
+            Some(
+                gst::ClockTime::SECOND
+                    .mul_div_floor(i * SAMPLES_PER_BUFFER, 48_000)
+                    .unwrap()
+            )
+        );
+        assert_eq!(buffer.size() as u64, SAMPLES_PER_BUFFER * 4);
+        all_ticks.extend(ticks(&buffer));
+    }
+
+    let expected = (0..10)
+        .map(|i| gst::ClockTime::from_mseconds(100 * i))
+        .collect::<Vec<_>>();
+    assert_eq!(all_ticks, expected);
+}
+
+#[test]
+fn test_jitter() {
+    let jitter = gst::ClockTime::from_mseconds(5);
+
+    let run = |seed: u32| {
+        let mut h = setup(&[
+            ("is-live", &false),
+            ("bpm", &600.0f64),
+            ("jitter", &jitter.nseconds()),
+            ("seed", &seed),
+        ]);
+
+        let mut all_ticks = vec![];
+        for _ in 0..100 {
+            all_ticks.extend(ticks(&h.pull().unwrap()));
+        }
+        all_ticks
+    };
+
+    let ticks = run(1);
+    assert_eq!(ticks.len(), 10);
+    let mut exact = 0;
+    for (i, tick) in ticks.iter().enumerate() {
+        let nominal = gst::ClockTime::from_mseconds(100 * i as u64);
+        assert!(
+            tick.nseconds().abs_diff(nominal.nseconds()) <= jitter.nseconds(),
+            "tick {i} at {tick} too far from {nominal}"
+        );
+        if *tick == nominal {
+            exact += 1;
+        }
+    }
+    assert!(exact < 5);
+
+    // Same seed gives the same ticks
+    assert_eq!(run(1), ticks);
+    assert_ne!(run(2), ticks);
+}
+
+#[test]
+fn test_live() {
+    let mut h = setup(&[("bpm", &120.0f64)]);
+    let testclock = h.testclock().unwrap();
+
+    // Every buffer is only output once the clock reached its end
+    for i in 1..=10 {
+        h.crank_single_clock_wait().unwrap();
+        let buffer = h.pull().unwrap();
+        assert_eq!(
+            testclock.time(),
+            Some(
+                gst::ClockTime::SECOND
+                    .mul_div_floor(i * SAMPLES_PER_BUFFER, 48_000)
+                    .unwrap()
+            )
+        );
+
+        let ticks = ticks(&buffer);
+        assert_eq!(ticks.is_empty(), i != 1);
+    }
+}
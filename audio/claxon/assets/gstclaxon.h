@@ -0,0 +1,32 @@
+/* Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+ *
+ * Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+ * http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+ * <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+ * option. This file may not be copied, modified, or distributed
+ * except according to those terms.
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+#ifndef __GST_CLAXON_H__
+#define __GST_CLAXON_H__
+
+#include <gst/gst.h>
+
+G_BEGIN_DECLS
+
+/* Registers the claxon plugin when it is linked statically into the
+ * application. Must be called after gst_init().
+ *
+ * Returns TRUE if the plugin was registered successfully. */
+gboolean gst_plugin_claxon_register_static (void);
+
+/* Same as GST_PLUGIN_STATIC_REGISTER(claxon) */
+void gst_plugin_claxon_register (void);
+
+const GstPluginDesc * gst_plugin_claxon_get_desc (void);
+
+G_END_DECLS
+
+#endif /* __GST_CLAXON_H__ */
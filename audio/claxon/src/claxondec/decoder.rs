@@ -0,0 +1,385 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! FLAC frame decoder for the frames that claxon can't decode.
+//!
+//! claxon 0.4 only decodes the sample sizes that can be signalled in the frame header of the
+//! original specification. 32 bits per sample were added in RFC 9639, and frames can also take
+//! their sample size from the STREAMINFO. Both are decoded here with 64 bit intermediate values,
+//! as the side channel of 32 bit stereo needs 33 bits.
+//!
+//! See <https://xiph.org/flac/format.html> for the details of the format.
+
+use crate::flac::{self, Crc16};
+
+/// Coefficients of the fixed predictors of each order
+const FIXED_COEFFICIENTS: [&[i64]; 5] = [&[], &[1], &[2, -1], &[3, -3, 1], &[4, -6, 4, -1]];
+
+/// Decoded samples of a frame.
+#[derive(Debug)]
+pub struct Block {
+    block_size: u32,
+    channels: u32,
+    /// Samples of one channel after another
+    buffer: Vec<i32>,
+}
+
+impl Block {
+    /// Number of samples per channel.
+    pub fn duration(&self) -> u32 {
+        self.block_size
+    }
+
+    pub fn channels(&self) -> u32 {
+        self.channels
+    }
+
+    pub fn channel(&self, ch: u32) -> &[i32] {
+        let start = (ch * self.block_size) as usize;
+        &self.buffer[start..start + self.block_size as usize]
+    }
+
+    pub fn sample(&self, ch: u32, sample: u32) -> i32 {
+        self.buffer[(ch * self.block_size + sample) as usize]
+    }
+
+    /// Returns the buffer for reusing it for the next frame.
+    pub fn into_buffer(self) -> Vec<i32> {
+        self.buffer
+    }
+}
+
+impl From<claxon::Block> for Block {
+    fn from(block: claxon::Block) -> Self {
+        Block {
+            block_size: block.duration(),
+            channels: block.channels(),
+            buffer: block.into_buffer(),
+        }
+    }
+}
+
+enum Error {
+    /// The data ends before the end of the frame
+    Truncated,
+    Format(&'static str),
+}
+
+/// Reads big endian bit fields.
+struct BitReader<'a> {
+    data: &'a [u8],
+    /// Position in bits
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        BitReader { data, pos: pos * 8 }
+    }
+
+    /// Reads `bits` bits, at most 64.
+    fn read(&mut self, bits: u32) -> Result<u64, Error> {
+        if self.pos + bits as usize > self.data.len() * 8 {
+            return Err(Error::Truncated);
+        }
+
+        let mut value = 0u64;
+        let mut remaining = bits;
+        while remaining > 0 {
+            let byte = self.data[self.pos / 8];
+            let offset = (self.pos % 8) as u32;
+            let n = remaining.min(8 - offset);
+            let part = (byte >> (8 - offset - n)) & (0xff >> (8 - n));
+            value = (value << n) | part as u64;
+            self.pos += n as usize;
+            remaining -= n;
+        }
+
+        Ok(value)
+    }
+
+    /// Reads a two's complement value of `bits` bits.
+    fn read_signed(&mut self, bits: u32) -> Result<i64, Error> {
+        if bits == 0 {
+            return Ok(0);
+        }
+
+        let value = self.read(bits)?;
+        Ok(((value << (64 - bits)) as i64) >> (64 - bits))
+    }
+
+    /// Reads the number of zero bits before the next one bit.
+    fn read_unary(&mut self) -> Result<u64, Error> {
+        let mut zeros = 0;
+        while self.read(1)? == 0 {
+            zeros += 1;
+        }
+
+        Ok(zeros)
+    }
+
+    fn align(&mut self) {
+        self.pos = (self.pos + 7) / 8 * 8;
+    }
+
+    /// Position in bytes, rounded down.
+    fn byte_pos(&self) -> usize {
+        self.pos / 8
+    }
+}
+
+/// Decodes a single frame into `buffer`, taking the sample size from the STREAMINFO if the frame
+/// header doesn't contain it.
+///
+/// Returns the decoded block, or `None` if the data ends before the end of the frame, and the
+/// number of bytes of the frame.
+pub fn decode_frame(
+    data: &[u8],
+    streaminfo_bits_per_sample: Option<u32>,
+    buffer: Vec<i32>,
+) -> (Result<Option<Block>, claxon::Error>, u64) {
+    match decode(data, streaminfo_bits_per_sample, buffer) {
+        Ok((block, used)) => (Ok(Some(block)), used as u64),
+        Err(Error::Truncated) => (Ok(None), data.len() as u64),
+        Err(Error::Format(err)) => (Err(claxon::Error::FormatError(err)), data.len() as u64),
+    }
+}
+
+fn decode(
+    data: &[u8],
+    streaminfo_bits_per_sample: Option<u32>,
+    mut buffer: Vec<i32>,
+) -> Result<(Block, usize), Error> {
+    let header = flac::FrameHeader::parse(data).ok_or(Error::Format("invalid frame header"))?;
+    let bits_per_sample = header
+        .bits_per_sample
+        .map(u32::from)
+        .or(streaminfo_bits_per_sample)
+        .ok_or(Error::Format("frame without sample size and no STREAMINFO"))?;
+    if !(4..=32).contains(&bits_per_sample) {
+        return Err(Error::Format("invalid sample size"));
+    }
+
+    let block_size = header.block_size as usize;
+    let channels = header.channels as usize;
+    let mut reader = BitReader::new(data, header.length);
+
+    // The side channel needs one more bit
+    let side_channel = match header.channel_assignment {
+        8 => Some(1),
+        9 => Some(0),
+        10 => Some(1),
+        _ => None,
+    };
+
+    let mut decoded = Vec::with_capacity(channels);
+    for c in 0..channels {
+        let bits = if side_channel == Some(c) {
+            bits_per_sample + 1
+        } else {
+            bits_per_sample
+        };
+        decoded.push(decode_subframe(&mut reader, bits, block_size)?);
+    }
+
+    reader.align();
+    let end = reader.byte_pos();
+    let crc = reader.read(16)?;
+    let mut expected = Crc16::default();
+    expected.update(&data[..end]);
+    if crc != expected.value() as u64 {
+        return Err(Error::Format("frame CRC-16 mismatch"));
+    }
+
+    if let [first, second] = decoded.as_mut_slice() {
+        match header.channel_assignment {
+            // Left and side
+            8 => {
+                for (left, side) in first.iter().zip(second.iter_mut()) {
+                    *side = left.wrapping_sub(*side);
+                }
+            }
+            // Side and right
+            9 => {
+                for (side, right) in first.iter_mut().zip(second.iter()) {
+                    *side = side.wrapping_add(*right);
+                }
+            }
+            // Mid and side
+            10 => {
+                for (mid, side) in first.iter_mut().zip(second.iter_mut()) {
+                    let sum = mid.wrapping_shl(1) | (*side & 1);
+                    *mid = sum.wrapping_add(*side) >> 1;
+                    *side = sum.wrapping_sub(*side) >> 1;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    buffer.clear();
+    buffer.reserve(channels * block_size);
+    for channel in &decoded {
+        buffer.extend(channel.iter().map(|s| *s as i32));
+    }
+
+    let block = Block {
+        block_size: block_size as u32,
+        channels: channels as u32,
+        buffer,
+    };
+
+    Ok((block, reader.byte_pos()))
+}
+
+fn decode_subframe(
+    reader: &mut BitReader,
+    bits_per_sample: u32,
+    block_size: usize,
+) -> Result<Vec<i64>, Error> {
+    if reader.read(1)? != 0 {
+        return Err(Error::Format("invalid subframe header"));
+    }
+    let subframe_type = reader.read(6)?;
+    let wasted_bits = if reader.read(1)? == 1 {
+        reader.read_unary()? as u32 + 1
+    } else {
+        0
+    };
+    if wasted_bits >= bits_per_sample {
+        return Err(Error::Format("invalid number of wasted bits"));
+    }
+    let bits = bits_per_sample - wasted_bits;
+
+    let mut samples = Vec::with_capacity(block_size);
+    match subframe_type {
+        // Constant
+        0 => {
+            let value = reader.read_signed(bits)?;
+            samples.resize(block_size, value);
+        }
+        // Verbatim
+        1 => {
+            for _ in 0..block_size {
+                samples.push(reader.read_signed(bits)?);
+            }
+        }
+        // Fixed predictor
+        8..=12 => {
+            let order = subframe_type as usize - 8;
+            read_warmup(reader, bits, order, block_size, &mut samples)?;
+            read_residual(reader, order, block_size, &mut samples)?;
+            predict(&mut samples, FIXED_COEFFICIENTS[order], 0);
+        }
+        // Linear predictor
+        32..=63 => {
+            let order = subframe_type as usize - 31;
+            read_warmup(reader, bits, order, block_size, &mut samples)?;
+
+            let precision = reader.read(4)? as u32 + 1;
+            if precision == 16 {
+                return Err(Error::Format("invalid LPC precision"));
+            }
+            let shift = reader.read_signed(5)?;
+            if shift < 0 {
+                return Err(Error::Format("negative LPC shift"));
+            }
+            let mut coefficients = Vec::with_capacity(order);
+            for _ in 0..order {
+                coefficients.push(reader.read_signed(precision)?);
+            }
+
+            read_residual(reader, order, block_size, &mut samples)?;
+            predict(&mut samples, &coefficients, shift as u32);
+        }
+        _ => return Err(Error::Format("reserved subframe type")),
+    }
+
+    if wasted_bits > 0 {
+        for sample in &mut samples {
+            *sample <<= wasted_bits;
+        }
+    }
+
+    Ok(samples)
+}
+
+fn read_warmup(
+    reader: &mut BitReader,
+    bits: u32,
+    order: usize,
+    block_size: usize,
+    samples: &mut Vec<i64>,
+) -> Result<(), Error> {
+    if order > block_size {
+        return Err(Error::Format("predictor order larger than block size"));
+    }
+    for _ in 0..order {
+        samples.push(reader.read_signed(bits)?);
+    }
+
+    Ok(())
+}
+
+/// Reads the Rice coded residual after the `order` warm-up samples.
+fn read_residual(
+    reader: &mut BitReader,
+    order: usize,
+    block_size: usize,
+    samples: &mut Vec<i64>,
+) -> Result<(), Error> {
+    let (parameter_bits, escape) = match reader.read(2)? {
+        0 => (4, 0x0f),
+        1 => (5, 0x1f),
+        _ => return Err(Error::Format("reserved residual coding method")),
+    };
+
+    let partition_order = reader.read(4)? as u32;
+    let partition_size = block_size >> partition_order;
+    if partition_size << partition_order != block_size || partition_size < order {
+        return Err(Error::Format("invalid partition order"));
+    }
+
+    for partition in 0..1usize << partition_order {
+        let len = if partition == 0 {
+            partition_size - order
+        } else {
+            partition_size
+        };
+
+        let parameter = reader.read(parameter_bits)? as u32;
+        if parameter == escape {
+            let bits = reader.read(5)? as u32;
+            for _ in 0..len {
+                samples.push(reader.read_signed(bits)?);
+            }
+        } else {
+            for _ in 0..len {
+                let quotient = reader.read_unary()?;
+                let value = quotient.wrapping_shl(parameter) | reader.read(parameter)?;
+                samples.push((value >> 1) as i64 ^ -((value & 1) as i64));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Replaces the residual after the warm-up samples with the predicted samples.
+fn predict(samples: &mut [i64], coefficients: &[i64], shift: u32) {
+    let order = coefficients.len();
+    for i in order..samples.len() {
+        let prediction = coefficients
+            .iter()
+            .zip(samples[i - order..i].iter().rev())
+            .fold(0i64, |acc, (c, s)| acc.wrapping_add(c.wrapping_mul(*s)));
+        samples[i] = samples[i].wrapping_add(prediction >> shift);
+    }
+}
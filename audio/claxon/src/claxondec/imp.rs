@@ -13,10 +13,19 @@ use gst::subclass::prelude::*;
 use gst_audio::prelude::*;
 use gst_audio::subclass::prelude::*;
 
+use std::collections::VecDeque;
 use std::io::Cursor;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 use atomic_refcell::AtomicRefCell;
 
+use super::decoder;
+use super::tags;
+use super::workers;
+use super::Concealment;
+use crate::flac;
+
 use byte_slice_cast::*;
 
 use once_cell::sync::Lazy;
@@ -29,13 +38,229 @@ static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
     )
 });
 
+const DEFAULT_CHANNEL_MASK: u64 = 0;
+const DEFAULT_LEGACY_CHANNEL_LAYOUT: bool = false;
+const DEFAULT_CHECK_CRC: bool = false;
+const DEFAULT_CHECK_MD5: bool = false;
+const DEFAULT_CONCEALMENT: Concealment = Concealment::None;
+const DEFAULT_STATS_INTERVAL: gst::ClockTime = gst::ClockTime::ZERO;
+const DEFAULT_NOTIFY_INTERVAL: gst::ClockTime = gst::ClockTime::ZERO;
+const DEFAULT_APPLY_REPLAYGAIN: bool = false;
+const DEFAULT_N_THREADS: u32 = 1;
+/// Threads can't be spawned on emscripten without pthreads support, which is not required for
+/// WebAssembly builds of GStreamer
+#[cfg(target_os = "emscripten")]
+const MAX_N_THREADS: u32 = 1;
+#[cfg(not(target_os = "emscripten"))]
+const MAX_N_THREADS: u32 = 64;
+
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    channel_mask: u64,
+    legacy_channel_layout: bool,
+    check_crc: bool,
+    check_md5: bool,
+    concealment: Concealment,
+    stats_interval: gst::ClockTime,
+    notify_interval: gst::ClockTime,
+    apply_replaygain: bool,
+    n_threads: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            channel_mask: DEFAULT_CHANNEL_MASK,
+            legacy_channel_layout: DEFAULT_LEGACY_CHANNEL_LAYOUT,
+            check_crc: DEFAULT_CHECK_CRC,
+            check_md5: DEFAULT_CHECK_MD5,
+            concealment: DEFAULT_CONCEALMENT,
+            stats_interval: DEFAULT_STATS_INTERVAL,
+            notify_interval: DEFAULT_NOTIFY_INTERVAL,
+            apply_replaygain: DEFAULT_APPLY_REPLAYGAIN,
+            n_threads: DEFAULT_N_THREADS,
+        }
+    }
+}
+
+/// Decoding statistics, which are also read from the `stats` property
+#[derive(Debug, Default)]
+struct Stats {
+    frames_decoded: u64,
+    /// Samples per channel of the decoded frames
+    frame_samples: u64,
+    /// Size of the decoded frames
+    frame_bytes: u64,
+    /// Duration of the decoded frames
+    frame_duration: gst::ClockTime,
+    samples_output: u64,
+    decode_errors: u64,
+    /// Frames that failed to decode or were corrupt and produced no output
+    frames_dropped: u64,
+    /// Duration of the decoded frames when the statistics were last posted
+    last_posted: gst::ClockTime,
+    /// When the counter properties were last notified, and their values at that time
+    last_notified: Option<(Instant, [u64; 3])>,
+}
+
+impl Stats {
+    /// Values of the `frames-decoded`, `frames-dropped` and `decode-errors` properties
+    fn counters(&self) -> [u64; 3] {
+        [self.frames_decoded, self.frames_dropped, self.decode_errors]
+    }
+
+    fn to_structure(&self) -> gst::Structure {
+        let average_block_size = self
+            .frame_samples
+            .checked_div(self.frames_decoded)
+            .unwrap_or(0);
+        let bitrate = (self.frame_bytes * 8)
+            .mul_div_floor(*gst::ClockTime::SECOND, *self.frame_duration)
+            .unwrap_or(0);
+
+        gst::Structure::builder("stats")
+            .field("frames-decoded", self.frames_decoded)
+            .field("samples-output", self.samples_output)
+            .field("decode-errors", self.decode_errors)
+            .field("frames-dropped", self.frames_dropped)
+            .field("average-block-size", average_block_size as u32)
+            .field("bitrate", bitrate)
+            .build()
+    }
+}
+
 struct State {
     audio_info: Option<gst_audio::AudioInfo>,
+    /// Whether the input buffers contain exactly one metadata block or frame each
+    framed: bool,
+    /// Splits the input into packets if it is not framed
+    framer: flac::Framer,
+    /// Whether the remaining unframed input is drained at EOS
+    eos: bool,
+    /// Tags from the metadata blocks
+    tags: gst::TagList,
+    /// Whether the metadata blocks were already handled from the caps, in which case the
+    /// in-band copies are ignored
+    headers_from_caps: bool,
+    /// Byte offset of the unframed input that is parsed next
+    offset: u64,
+    /// First sample to output after an accurate seek
+    seek_sample: Option<u64>,
+    /// Decoding buffers of previous frames, reused for the next ones. There is one per frame
+    /// that is decoded at the same time
+    block_buffers: Vec<Vec<i32>>,
+    /// MD5 of the decoded samples and their number, while all samples from the start of the
+    /// stream were decoded
+    md5: Option<(glib::Checksum, u64)>,
+    /// Samples of the previous frame in the format of the MD5, reused for the next one
+    md5_data: Vec<u8>,
+    /// Previous output buffer, if it is repeated for frames that fail to decode
+    last_output: Option<gst::Buffer>,
+    /// Whether frames were decoded since the last stream marker, in which case another one
+    /// starts the next stream of a chained stream
+    had_frames: bool,
+    /// APPLICATION metadata blocks that are sent downstream before the first frame
+    application_blocks: Vec<gst::Structure>,
+    /// Threads decoding frames in parallel, if enabled
+    workers: Option<workers::Workers>,
+}
+
+impl State {
+    fn new(framed: bool, audio_info: Option<gst_audio::AudioInfo>) -> Self {
+        State {
+            audio_info,
+            framed,
+            framer: flac::Framer::default(),
+            eos: false,
+            tags: gst::TagList::new(),
+            headers_from_caps: false,
+            offset: 0,
+            seek_sample: None,
+            block_buffers: Vec::new(),
+            md5: None,
+            md5_data: Vec::new(),
+            last_output: None,
+            had_frames: false,
+            application_blocks: Vec::new(),
+            workers: None,
+        }
+    }
+}
+
+/// Seek that was sent upstream in bytes and whose segment is pending
+#[derive(Debug, Clone, Copy)]
+struct PendingSeek {
+    seqnum: gst::Seqnum,
+    rate: f64,
+    start: gst::ClockTime,
+    stop: Option<gst::ClockTime>,
+    /// Sample corresponding to `start`
+    sample: u64,
+}
+
+/// Information for seeking in unframed input, which is also accessed from seek events and
+/// queries
+#[derive(Debug, Default)]
+struct SeekInfo {
+    streaminfo: Option<flac::StreamInfo>,
+    seektable: Vec<flac::SeekPoint>,
+    /// Byte offset of the first frame
+    first_frame_offset: Option<u64>,
+    pending_seek: Option<PendingSeek>,
+}
+
+/// Timestamps of output buffers that replace the ones from the base class
+#[derive(Debug, Default)]
+struct OutputTimestamps {
+    /// Whether the base class queues the output of reverse playback and pushes it in reverse
+    /// order
+    reverse: bool,
+    /// Timestamp and duration of each output buffer that was not pushed yet, in decoding order
+    pending: VecDeque<Option<(gst::ClockTime, gst::ClockTime)>>,
+    /// Stream position after the last pushed buffer, if its timestamps were replaced
+    position: Option<gst::ClockTime>,
+}
+
+impl OutputTimestamps {
+    fn push(&mut self, timestamps: Option<(gst::ClockTime, gst::ClockTime)>) {
+        // In forward playback each output buffer is pushed right away, unless it is clipped
+        if !self.reverse {
+            self.pending.clear();
+        }
+        self.pending.push_back(timestamps);
+    }
+
+    fn pop(&mut self) -> Option<(gst::ClockTime, gst::ClockTime)> {
+        let timestamps = if self.reverse {
+            self.pending.pop_back().flatten()
+        } else {
+            self.pending.pop_front().flatten()
+        };
+
+        self.position = match timestamps {
+            Some((pts, _)) if self.reverse => Some(pts),
+            Some((pts, duration)) => Some(pts + duration),
+            None => None,
+        };
+
+        timestamps
+    }
+
+    fn clear(&mut self) {
+        self.pending.clear();
+        self.position = None;
+    }
 }
 
 #[derive(Default)]
 pub struct ClaxonDec {
+    settings: Mutex<Settings>,
     state: AtomicRefCell<Option<State>>,
+    seek_info: Mutex<SeekInfo>,
+    /// Pool for the output buffers from the allocation query
+    pool: Mutex<Option<gst::BufferPool>>,
+    stats: Mutex<Stats>,
+    output_timestamps: Mutex<OutputTimestamps>,
 }
 
 #[glib::object_subclass]
@@ -45,7 +270,191 @@ impl ObjectSubclass for ClaxonDec {
     type ParentType = gst_audio::AudioDecoder;
 }
 
-impl ObjectImpl for ClaxonDec {}
+impl ObjectImpl for ClaxonDec {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecUInt64::builder("channel-mask")
+                    .nick("Channel Mask")
+                    .blurb("Channel mask to use instead of the FLAC channel layout, with the channels in the order of the mask bits (0 = FLAC channel layout)")
+                    .default_value(DEFAULT_CHANNEL_MASK)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("legacy-channel-layout")
+                    .nick("Legacy Channel Layout")
+                    .blurb("Use the 7 and 8 channel layouts of previous versions instead of the ones from the FLAC specification")
+                    .default_value(DEFAULT_LEGACY_CHANNEL_LAYOUT)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("check-crc")
+                    .nick("Check CRC")
+                    .blurb("Verify the CRCs of each frame and drop corrupt frames with a warning instead of failing to decode them")
+                    .default_value(DEFAULT_CHECK_CRC)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecBoolean::builder("check-md5")
+                    .nick("Check MD5")
+                    .blurb("Compare the MD5 of the decoded audio with the one from the STREAMINFO at EOS and post a warning if they differ")
+                    .default_value(DEFAULT_CHECK_MD5)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("concealment", DEFAULT_CONCEALMENT)
+                    .nick("Concealment")
+                    .blurb("How to handle frames that fail to decode")
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt64::builder("stats-interval")
+                    .nick("Statistics Interval")
+                    .blurb("Interval in nanoseconds of decoded audio between element messages with the statistics (0 = disabled)")
+                    .default_value(*DEFAULT_STATS_INTERVAL)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt64::builder("notify-interval")
+                    .nick("Notify Interval")
+                    .blurb("Minimum interval in nanoseconds between notifications of changes of the frames-decoded, frames-dropped and decode-errors properties (0 = disabled)")
+                    .default_value(*DEFAULT_NOTIFY_INTERVAL)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt64::builder("frames-decoded")
+                    .nick("Frames Decoded")
+                    .blurb("Number of frames decoded")
+                    .read_only()
+                    .build(),
+                glib::ParamSpecUInt64::builder("frames-dropped")
+                    .nick("Frames Dropped")
+                    .blurb("Number of frames that were dropped because they were corrupt or failed to decode")
+                    .read_only()
+                    .build(),
+                glib::ParamSpecUInt64::builder("decode-errors")
+                    .nick("Decode Errors")
+                    .blurb("Number of packets that failed to decode, including concealed frames")
+                    .read_only()
+                    .build(),
+                glib::ParamSpecBoxed::builder::<gst::Structure>("stats")
+                    .nick("Statistics")
+                    .blurb("Decoding statistics")
+                    .read_only()
+                    .build(),
+                glib::ParamSpecBoxed::builder::<gst::Structure>("streaminfo")
+                    .nick("STREAMINFO")
+                    .blurb("Parsed STREAMINFO of the current stream")
+                    .read_only()
+                    .build(),
+                glib::ParamSpecBoolean::builder("apply-replaygain")
+                    .nick("Apply ReplayGain")
+                    .blurb("Scale the decoded audio by the ReplayGain from the Vorbis comment, preferring the track gain and limited by the peak to prevent clipping")
+                    .default_value(DEFAULT_APPLY_REPLAYGAIN)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt::builder("n-threads")
+                    .nick("Number of Threads")
+                    .blurb("Number of threads decoding frames in parallel, which delays the output by one frame per thread (0 = number of processors)")
+                    .maximum(MAX_N_THREADS)
+                    .default_value(DEFAULT_N_THREADS)
+                    .mutable_ready()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "channel-mask" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.channel_mask = value.get().expect("type checked upstream");
+            }
+            "legacy-channel-layout" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.legacy_channel_layout = value.get().expect("type checked upstream");
+            }
+            "check-crc" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.check_crc = value.get().expect("type checked upstream");
+            }
+            "check-md5" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.check_md5 = value.get().expect("type checked upstream");
+            }
+            "concealment" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.concealment = value.get().expect("type checked upstream");
+            }
+            "stats-interval" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.stats_interval = value.get::<u64>().unwrap().nseconds();
+            }
+            "notify-interval" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.notify_interval = value.get::<u64>().unwrap().nseconds();
+            }
+            "apply-replaygain" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.apply_replaygain = value.get().expect("type checked upstream");
+            }
+            "n-threads" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.n_threads = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "channel-mask" => {
+                let settings = self.settings.lock().unwrap();
+                settings.channel_mask.to_value()
+            }
+            "legacy-channel-layout" => {
+                let settings = self.settings.lock().unwrap();
+                settings.legacy_channel_layout.to_value()
+            }
+            "check-crc" => {
+                let settings = self.settings.lock().unwrap();
+                settings.check_crc.to_value()
+            }
+            "check-md5" => {
+                let settings = self.settings.lock().unwrap();
+                settings.check_md5.to_value()
+            }
+            "concealment" => {
+                let settings = self.settings.lock().unwrap();
+                settings.concealment.to_value()
+            }
+            "stats-interval" => {
+                let settings = self.settings.lock().unwrap();
+                settings.stats_interval.nseconds().to_value()
+            }
+            "notify-interval" => {
+                let settings = self.settings.lock().unwrap();
+                settings.notify_interval.nseconds().to_value()
+            }
+            "frames-decoded" => self.stats.lock().unwrap().frames_decoded.to_value(),
+            "frames-dropped" => self.stats.lock().unwrap().frames_dropped.to_value(),
+            "decode-errors" => self.stats.lock().unwrap().decode_errors.to_value(),
+            "stats" => self.stats.lock().unwrap().to_structure().to_value(),
+            "streaminfo" => {
+                let seek_info = self.seek_info.lock().unwrap();
+                seek_info
+                    .streaminfo
+                    .as_ref()
+                    .map(streaminfo_to_structure)
+                    .to_value()
+            }
+            "apply-replaygain" => {
+                let settings = self.settings.lock().unwrap();
+                settings.apply_replaygain.to_value()
+            }
+            "n-threads" => {
+                let settings = self.settings.lock().unwrap();
+                settings.n_threads.to_value()
+            }
+            _ => unimplemented!(),
+        }
+    }
+}
 
 impl GstObjectImpl for ClaxonDec {}
 
@@ -65,9 +474,7 @@ impl ElementImpl for ClaxonDec {
 
     fn pad_templates() -> &'static [gst::PadTemplate] {
         static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
-            let sink_caps = gst::Caps::builder("audio/x-flac")
-                .field("framed", true)
-                .build();
+            let sink_caps = gst::Caps::builder("audio/x-flac").build();
             let sink_pad_template = gst::PadTemplate::new(
                 "sink",
                 gst::PadDirection::Sink,
@@ -76,15 +483,22 @@ impl ElementImpl for ClaxonDec {
             )
             .unwrap();
 
-            let src_caps = gst_audio::AudioCapsBuilder::new_interleaved()
+            let src_caps = gst_audio::AudioCapsBuilder::new()
+                .layout_list([
+                    gst_audio::AudioLayout::Interleaved,
+                    gst_audio::AudioLayout::NonInterleaved,
+                ])
                 .format_list([
                     gst_audio::AudioFormat::S8,
                     gst_audio::AUDIO_FORMAT_S16,
                     gst_audio::AUDIO_FORMAT_S2432,
+                    gst_audio::AudioFormat::S24le,
                     gst_audio::AUDIO_FORMAT_S32,
+                    gst_audio::AUDIO_FORMAT_F32,
                 ])
-                .rate_range(1..655_350)
-                .channels_range(1..8)
+                // Everything the STREAMINFO can signal and `gstaudioinfo()` accepts
+                .rate_range(1..=1_048_575)
+                .channels_range(1..=8)
                 .build();
             let src_pad_template = gst::PadTemplate::new(
                 "src",
@@ -104,22 +518,71 @@ impl ElementImpl for ClaxonDec {
 impl AudioDecoderImpl for ClaxonDec {
     fn stop(&self) -> Result<(), gst::ErrorMessage> {
         *self.state.borrow_mut() = None;
+        *self.seek_info.lock().unwrap() = SeekInfo::default();
+        if let Some(pool) = self.pool.lock().unwrap().take() {
+            let _ = pool.set_active(false);
+        }
 
         Ok(())
     }
 
     fn start(&self) -> Result<(), gst::ErrorMessage> {
-        *self.state.borrow_mut() = Some(State { audio_info: None });
+        *self.stats.lock().unwrap() = Stats::default();
+        *self.state.borrow_mut() = Some(State::new(true, None));
+        *self.seek_info.lock().unwrap() = SeekInfo::default();
+
+        // Lost packets are concealed if the plc property is enabled
+        self.obj().set_plc_aware(true);
 
         Ok(())
     }
 
+    fn flush(&self, hard: bool) {
+        gst::debug!(CAT, imp: self, "Flushing (hard: {})", hard);
+
+        if let Some(state) = self.state.borrow_mut().as_mut() {
+            // Soft flushes happen for DISCONT buffers after draining, and the base class drops
+            // the remaining partial input. Decoding continues at the next frame
+            state.framer.resync();
+            state.md5 = None;
+            state.last_output = None;
+
+            if hard {
+                state.eos = false;
+                state.seek_sample = None;
+                if let Some(workers) = state.workers.as_mut() {
+                    workers.clear();
+                }
+            }
+        }
+        self.output_timestamps.lock().unwrap().clear();
+
+        self.parent_flush(hard)
+    }
+
     fn set_format(&self, caps: &gst::Caps) -> Result<(), gst::LoggableError> {
         gst::debug!(CAT, imp: self, "Setting format {:?}", caps);
 
+        // Frames that are still being decoded belong to the previous format
+        if let Some(state) = self.state.borrow_mut().as_mut() {
+            if let Err(err) = self.finish_pending(state, 0) {
+                gst::debug!(
+                    CAT,
+                    imp: self,
+                    "Failed to finish pending frames: {:?}",
+                    err
+                );
+            }
+        }
+
         let mut audio_info: Option<gst_audio::AudioInfo> = None;
+        let mut tags = gst::TagList::new();
+        let mut application_blocks = Vec::new();
+        let mut headers_from_caps = false;
 
         let s = caps.structure(0).unwrap();
+        let mut framed = s.get::<bool>("framed").unwrap_or(false);
+
         if let Ok(Some(streamheaders)) = s.get_optional::<gst::ArrayRef>("streamheader") {
             let streamheaders = streamheaders.as_slice();
 
@@ -134,30 +597,431 @@ impl AudioDecoderImpl for ClaxonDec {
                     if inmap[0..7] != [0x7f, b'F', b'L', b'A', b'C', 0x01, 0x00] {
                         gst::debug!(CAT, imp: self, "Unknown streamheader format");
                     } else if let Ok(tstreaminfo) = claxon_streaminfo(&inmap[13..]) {
-                        if let Ok(taudio_info) = gstaudioinfo(&tstreaminfo) {
-                            // To speed up negotiation
-                            let element = self.obj();
-                            if element.set_output_format(&taudio_info).is_err()
-                                || element.negotiate().is_err()
-                            {
+                        // Headers of the Ogg mapping come with packets, e.g. from oggdemux
+                        framed = true;
+                        audio_info = self.negotiate_streaminfo(&tstreaminfo, &inmap[13..]);
+                    }
+                }
+
+                for header in &streamheaders[1..] {
+                    if let Ok(Some(header)) = header.get::<Option<gst::Buffer>>() {
+                        if let Ok(map) = header.map_readable() {
+                            self.handle_metadata_blocks(&mut tags, &mut application_blocks, &map);
+                            headers_from_caps = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        if audio_info.is_none() {
+            if let Ok(Some(codec_data)) = s.get_optional::<gst::Buffer>("codec_data") {
+                let map = codec_data
+                    .map_readable()
+                    .map_err(|_| gst::loggable_error!(CAT, "Failed to map codec_data readable"))?;
+                // The metadata blocks follow the stream marker in Matroska, and the version and
+                // flags of the dfLa box in ISOBMFF
+                let blocks = if map.starts_with(flac::STREAM_MARKER) || map.starts_with(&[0; 4]) {
+                    &map[4..]
+                } else {
+                    &map[..]
+                };
+
+                let streaminfo_end = flac::MetadataBlockHeader::parse(blocks)
+                    .filter(|header| header.block_type == flac::METADATA_STREAMINFO)
+                    .map(|header| 4 + header.length)
+                    .filter(|end| *end <= blocks.len());
+                match streaminfo_end.map(|end| (end, claxon_streaminfo(&blocks[..end]))) {
+                    Some((end, Ok(tstreaminfo))) => {
+                        gst::debug!(CAT, imp: self, "Got STREAMINFO from codec_data");
+                        // Demuxers of containers with codec_data output a frame per buffer
+                        framed = true;
+                        audio_info = self.negotiate_streaminfo(&tstreaminfo, &blocks[..end]);
+
+                        if end < blocks.len() {
+                            self.handle_metadata_blocks(
+                                &mut tags,
+                                &mut application_blocks,
+                                &blocks[end..],
+                            );
+                            headers_from_caps = true;
+                        }
+                    }
+                    _ => gst::debug!(CAT, imp: self, "Unknown codec_data format"),
+                }
+            }
+        }
+
+        let streaminfo = self.seek_info.lock().unwrap().streaminfo;
+        if let Some(streaminfo) = streaminfo.filter(|_| audio_info.is_some()) {
+            self.add_stream_tags(&mut tags, &streaminfo);
+        }
+
+        if !framed {
+            gst::debug!(CAT, imp: self, "Input is not framed, parsing frames");
+        }
+
+        let mut state_guard = self.state.borrow_mut();
+        let mut state = State::new(framed, audio_info);
+        state.tags = tags;
+        state.application_blocks = application_blocks;
+        state.headers_from_caps = headers_from_caps;
+        *state_guard = Some(state);
+
+        Ok(())
+    }
+
+    fn decide_allocation(
+        &self,
+        query: &mut gst::query::Allocation,
+    ) -> Result<(), gst::LoggableError> {
+        // A pool has to be inactive for configuring it, also if downstream provides it again
+        if let Some(pool) = self.pool.lock().unwrap().take() {
+            let _ = pool.set_active(false);
+        }
+
+        self.parent_decide_allocation(query)?;
+
+        let (caps, _) = query.get_owned();
+        let info = caps
+            .as_ref()
+            .and_then(|caps| gst_audio::AudioInfo::from_caps(caps).ok())
+            .ok_or_else(|| gst::loggable_error!(CAT, "Invalid caps in allocation query"))?;
+
+        // Large enough for the biggest frame
+        let max_block_size = self
+            .seek_info
+            .lock()
+            .unwrap()
+            .streaminfo
+            .map_or(flac::MAX_BLOCK_SIZE, |info| info.max_block_size);
+        let size = max_block_size * info.bpf();
+
+        let pools = query.allocation_pools();
+        let (pool, min, max) = match pools.first() {
+            Some((Some(pool), _, min, max)) => (pool.clone(), *min, *max),
+            _ => (gst::BufferPool::new(), 0, 0),
+        };
+
+        let mut config = pool.config();
+        config.set_params(caps.as_deref(), size, min, max);
+        if pool.set_config(config).is_err() {
+            // The base class falls back to the negotiated allocator
+            gst::warning!(CAT, imp: self, "Failed to configure buffer pool");
+            return Ok(());
+        }
+
+        if pools.is_empty() {
+            query.add_allocation_pool(Some(&pool), size, min, max);
+        } else {
+            query.set_nth_allocation_pool(0, Some(&pool), size, min, max);
+        }
+
+        pool.set_active(true)
+            .map_err(|_| gst::loggable_error!(CAT, "Failed to activate buffer pool"))?;
+
+        gst::debug!(CAT, imp: self, "Using buffer pool {:?} with size {}", pool, size);
+        *self.pool.lock().unwrap() = Some(pool);
+
+        Ok(())
+    }
+
+    fn sink_event(&self, event: gst::Event) -> bool {
+        // Non-serialized events like FLUSH_START arrive while the streaming thread might be
+        // decoding and holding the state
+        if !event.is_serialized() {
+            return self.parent_sink_event(event);
+        }
+
+        // Time segment replacing the byte segment after an accurate seek
+        let mut segment_event = None;
+
+        {
+            let mut state_guard = self.state.borrow_mut();
+            if let Some(state) = state_guard.as_mut() {
+                match event.view() {
+                    // Remaining input is drained by the base class while handling the event
+                    gst::EventView::Eos(_) => state.eos = true,
+                    gst::EventView::StreamStart(_) => {
+                        state.framer.reset();
+                        state.eos = false;
+                        state.offset = 0;
+                        *self.seek_info.lock().unwrap() = SeekInfo::default();
+                    }
+                    gst::EventView::Segment(ev) => {
+                        // Frames that are still being decoded belong to the previous segment
+                        if let Err(err) = self.finish_pending(state, 0) {
+                            gst::debug!(
+                                CAT,
+                                imp: self,
+                                "Failed to finish pending frames: {:?}",
+                                err
+                            );
+                        }
+
+                        // Decoding continues at a different position
+                        state.seek_sample = None;
+                        state.md5 = None;
+                        state.last_output = None;
+                        let mut rate = ev.segment().rate();
+
+                        if let Some(segment) = ev.segment().downcast_ref::<gst::format::Bytes>() {
+                            let offset = segment.start().map_or(0, |start| *start);
+                            if offset != state.offset {
                                 gst::debug!(
                                     CAT,
                                     imp: self,
-                                    "Error to negotiate output from based on in-caps streaminfo"
+                                    "Continuing at byte offset {}",
+                                    offset
                                 );
+                                // Data from the start is parsed like a new stream
+                                if offset == 0 {
+                                    state.framer.reset();
+                                } else {
+                                    state.framer.resync();
+                                }
                             }
+                            state.offset = offset;
+
+                            let pending_seek = self.seek_info.lock().unwrap().pending_seek.take();
+                            if let Some(seek) = pending_seek {
+                                gst::debug!(
+                                    CAT,
+                                    imp: self,
+                                    "Seeked to byte offset {}, skipping to sample {}",
+                                    state.offset,
+                                    seek.sample
+                                );
 
-                            audio_info = Some(taudio_info);
+                                let mut segment = gst::FormattedSegment::<gst::ClockTime>::new();
+                                segment.set_rate(seek.rate);
+                                segment.set_start(seek.start);
+                                segment.set_time(seek.start);
+                                segment.set_stop(seek.stop);
+                                if seek.rate < 0.0 {
+                                    segment.set_position(seek.stop);
+                                } else {
+                                    segment.set_position(seek.start);
+                                }
+
+                                rate = seek.rate;
+                                state.seek_sample = Some(seek.sample);
+                                segment_event = Some(
+                                    gst::event::Segment::builder(&segment)
+                                        .seqnum(seek.seqnum)
+                                        .build(),
+                                );
+                            }
                         }
+
+                        let mut output_timestamps = self.output_timestamps.lock().unwrap();
+                        output_timestamps.clear();
+                        output_timestamps.reverse = rate < 0.0;
                     }
+                    _ => (),
+                }
+            }
+        }
+
+        let is_eos = event.type_() == gst::EventType::Eos;
+        let res = self.parent_sink_event(segment_event.unwrap_or(event));
+
+        // The remaining input was drained by now
+        if is_eos {
+            let md5 = self
+                .state
+                .borrow_mut()
+                .as_mut()
+                .and_then(|state| state.md5.take());
+            if let Some((md5, n_samples)) = md5 {
+                self.check_md5(md5, n_samples);
+            }
+        }
+
+        res
+    }
+
+    fn transform_meta(
+        &self,
+        _outbuf: &mut gst::BufferRef,
+        meta: gst::MetaRef<gst::Meta>,
+        _inbuf: &gst::BufferRef,
+    ) -> bool {
+        // The base class only copies metas without tags other than the audio ones. Everything
+        // else, e.g. reference timestamps or custom metas, applies to the decoded samples as
+        // well unless it is about the memory of the encoded data
+        if meta.has_tag::<gst::meta::tags::Memory>()
+            || meta.has_tag::<gst::meta::tags::MemoryReference>()
+        {
+            return false;
+        }
+
+        gst::trace!(CAT, imp: self, "Copying meta {}", meta.api());
+        true
+    }
+
+    fn pre_push(&self, mut buffer: gst::Buffer) -> Result<Option<gst::Buffer>, gst::FlowError> {
+        if let Some((pts, duration)) = self.output_timestamps.lock().unwrap().pop() {
+            gst::trace!(CAT, imp: self, "Output timestamp {} from frame header", pts);
+            let buffer = buffer.make_mut();
+            buffer.set_pts(pts);
+            buffer.set_duration(duration);
+        }
+
+        Ok(Some(buffer))
+    }
+
+    fn src_event(&self, event: gst::Event) -> bool {
+        if let gst::EventView::Seek(ev) = event.view() {
+            if let Some(res) = self.seek(ev) {
+                return res;
+            }
+        }
+
+        self.parent_src_event(event)
+    }
+
+    fn src_query(&self, query: &mut gst::QueryRef) -> bool {
+        // The base class would answer from its own timestamps if upstream can't, instead of the
+        // ones from the frame headers
+        if query.type_() == gst::QueryType::Position {
+            if self.obj().sink_pad().peer_query(query) {
+                return true;
+            }
+
+            if let gst::QueryViewMut::Position(q) = query.view_mut() {
+                let position = self.output_timestamps.lock().unwrap().position;
+                if let Some(position) =
+                    position.and_then(|position| self.convert(position.into(), q.format()))
+                {
+                    q.set(position);
+                    return true;
+                }
+            }
+        }
+
+        let res = self.parent_src_query(query);
+
+        match query.view_mut() {
+            gst::QueryViewMut::Seeking(q) if q.format() == gst::Format::Time => {
+                if res && q.result().0 {
+                    return true;
+                }
+
+                let Some(duration) = self.seekable_duration() else {
+                    return res;
+                };
+
+                gst::debug!(CAT, imp: self, "Seekable with the seek table");
+                q.set(true, gst::ClockTime::ZERO, duration);
+                true
+            }
+            gst::QueryViewMut::Caps(q) => {
+                let Some(mut caps) = self.stream_caps() else {
+                    return res;
+                };
+
+                // Narrow down what the base class answered, which already takes the filter
+                // into account
+                if let Some(result) = q.result().filter(|_| res) {
+                    caps = result.intersect_with_mode(&caps, gst::CapsIntersectMode::First);
+                } else if let Some(filter) = q.filter() {
+                    caps = filter.intersect_with_mode(&caps, gst::CapsIntersectMode::First);
                 }
+
+                gst::log!(CAT, imp: self, "Returning caps {:?} of the stream", caps);
+                q.set_result(&caps);
+                true
+            }
+            gst::QueryViewMut::Convert(q) if !res => {
+                let (src, dest) = q.get();
+                let Some(dest) = self.convert(src, dest.format()) else {
+                    return false;
+                };
+
+                q.set(src, dest);
+                true
+            }
+            gst::QueryViewMut::Duration(q) if !res && q.format() == gst::Format::Time => {
+                let Some(duration) = self.duration() else {
+                    return false;
+                };
+
+                q.set(duration);
+                true
             }
+            _ => res,
         }
+    }
 
+    fn parse(&self, adapter: &gst_base::Adapter) -> Result<(u32, u32), gst::FlowError> {
         let mut state_guard = self.state.borrow_mut();
-        *state_guard = Some(State { audio_info });
+        let state = state_guard.as_mut().ok_or(gst::FlowError::NotNegotiated)?;
 
-        Ok(())
+        let available = adapter.available();
+        if available == 0 {
+            return Err(gst::FlowError::Eos);
+        }
+
+        let map = adapter.map(available).map_err(|_| {
+            gst::error!(CAT, imp: self, "Failed to map adapter");
+            gst::FlowError::Error
+        })?;
+        let data: &[u8] = &map;
+
+        if state.framed {
+            // Buffers of framed input end with a frame, but might still contain several of them
+            if let Some(size) = flac::frame_size(data, true).filter(|size| *size < available) {
+                gst::trace!(CAT, imp: self, "Splitting frame of {} bytes off buffer", size);
+                return Ok((0, size as u32));
+            }
+
+            drop(map);
+            drop(state_guard);
+            return self.parent_parse(adapter);
+        }
+
+        // Packets of the Ogg mapping without the caps saying so, each buffer is one packet
+        if state.offset == 0 && flac::parse_ident_header(data).is_some() {
+            gst::debug!(CAT, imp: self, "Input is in the Ogg mapping, assuming it is framed");
+            state.framed = true;
+            drop(map);
+            drop(state_guard);
+            return self.parent_parse(adapter);
+        }
+
+        // EOS signals the base class that more data is needed
+        let (offset, size) = state
+            .framer
+            .next_packet(data, state.eos)
+            .ok_or(gst::FlowError::Eos)?;
+
+        if offset > 0 {
+            gst::debug!(CAT, imp: self, "Skipping {} bytes to next frame", offset);
+        }
+
+        if size == 0 {
+            // The base class asserts that a packet is found if no error is returned, so the
+            // garbage is skipped here while waiting for more data instead of returning its size
+            drop(map);
+            adapter.flush(offset);
+            state.offset += offset as u64;
+            return Err(gst::FlowError::Eos);
+        }
+
+        gst::trace!(CAT, imp: self, "Found packet of {} bytes", size);
+
+        let packet_offset = state.offset + offset as u64;
+        if flac::FrameHeader::parse(&data[offset..]).is_some() {
+            let mut seek_info = self.seek_info.lock().unwrap();
+            if seek_info.first_frame_offset.is_none() {
+                gst::debug!(CAT, imp: self, "First frame at byte offset {}", packet_offset);
+                seek_info.first_frame_offset = Some(packet_offset);
+            }
+        }
+        state.offset = packet_offset + size as u64;
+
+        Ok((offset as u32, size as u32))
     }
 
     #[allow(clippy::verbose_bit_mask)]
@@ -167,8 +1031,16 @@ impl AudioDecoderImpl for ClaxonDec {
     ) -> Result<gst::FlowSuccess, gst::FlowError> {
         gst::debug!(CAT, imp: self, "Handling buffer {:?}", inbuf);
 
+        let mut state_guard = self.state.borrow_mut();
+
         let inbuf = match inbuf {
-            None => return Ok(gst::FlowSuccess::Ok),
+            // Draining
+            None => {
+                return match state_guard.as_mut() {
+                    Some(state) => self.finish_pending(state, 0),
+                    None => Ok(gst::FlowSuccess::Ok),
+                };
+            }
             Some(inbuf) => inbuf,
         };
 
@@ -177,25 +1049,70 @@ impl AudioDecoderImpl for ClaxonDec {
             gst::FlowError::Error
         })?;
 
-        let mut state_guard = self.state.borrow_mut();
         let state = state_guard.as_mut().ok_or(gst::FlowError::NotNegotiated)?;
 
-        if inmap.as_slice() == b"fLaC" {
+        // After a discontinuity the packet might start in the middle of a frame
+        let mut indata = inmap.as_slice();
+        if inbuf.flags().contains(gst::BufferFlags::DISCONT) && state.had_frames {
+            if let Some(offset) = flac::find_frame(indata).filter(|offset| *offset > 0) {
+                gst::debug!(CAT, imp: self, "Skipping {} bytes to next frame", offset);
+                indata = &indata[offset..];
+            }
+        }
+
+        // Everything else finishes its frame right away, after the frames that are still being
+        // decoded
+        let is_frame =
+            indata.len() >= 2 && indata[0] == 0b1111_1111 && indata[1] & 0b1111_1100 == 0b1111_1000;
+        if !is_frame {
+            self.finish_pending(state, 0)?;
+        }
+
+        if indata == b"fLaC" {
             gst::debug!(CAT, imp: self, "fLaC buffer received");
-        } else if inmap[0] & 0x7F == 0x00 {
+
+            if std::mem::take(&mut state.had_frames) {
+                gst::debug!(CAT, imp: self, "Next stream of a chained stream");
+                if let Some((md5, n_samples)) = state.md5.take() {
+                    self.check_md5(md5, n_samples);
+                }
+                state.tags = gst::TagList::new();
+                state.application_blocks.clear();
+                state.headers_from_caps = false;
+                self.obj().merge_tags(None, gst::TagMergeMode::Replace);
+            }
+        } else if indata.is_empty() && inbuf.duration().is_some() {
+            // Lost packet, handed over by the base class for a GAP event when PLC is enabled
+            return self.conceal_gap(state, inbuf.duration().unwrap());
+        } else if indata.len() < 2 {
+            return self.recoverable_error(&format!("Packet of {} bytes too short", indata.len()));
+        } else if let Some((n_headers, native)) = flac::parse_ident_header(indata) {
+            gst::debug!(
+                CAT,
+                imp: self,
+                "Ogg FLAC identification header received, {} header packets follow",
+                n_headers
+            );
+            return self.handle_streaminfo_header(state, &native[flac::STREAM_MARKER.len()..]);
+        } else if indata[0] & 0x7F == 0x00 {
             gst::debug!(CAT, imp: self, "Streaminfo header buffer received");
-            return self.handle_streaminfo_header(state, inmap.as_ref());
-        } else if inmap[0] == 0b1111_1111 && inmap[1] & 0b1111_1100 == 0b1111_1000 {
+            return self.handle_streaminfo_header(state, indata);
+        } else if is_frame {
             gst::debug!(CAT, imp: self, "Data buffer received");
-            return self.handle_data(state, inmap.as_ref());
+            return self.handle_data(state, indata, inbuf.pts());
+        } else if indata[0] & 0x7F == 0x7F {
+            return self.recoverable_error("Invalid metadata block type");
         } else {
             // info about other headers in flacparse and https://xiph.org/flac/format.html
             gst::debug!(
                 CAT,
                 imp: self,
                 "Other header buffer received {:?}",
-                inmap[0] & 0x7F
+                indata[0] & 0x7F
             );
+            if !state.headers_from_caps {
+                self.handle_metadata_blocks(&mut state.tags, &mut state.application_blocks, indata);
+            }
         }
 
         self.obj().finish_frame(None, 1)
@@ -203,33 +1120,841 @@ impl AudioDecoderImpl for ClaxonDec {
 }
 
 impl ClaxonDec {
-    fn handle_streaminfo_header(
-        &self,
-        state: &mut State,
-        indata: &[u8],
-    ) -> Result<gst::FlowSuccess, gst::FlowError> {
-        let streaminfo = claxon_streaminfo(indata).map_err(|e| {
-            gst::element_imp_error!(self, gst::StreamError::Decode, ["{e}"]);
-            gst::FlowError::Error
-        })?;
+    /// Seeks in unframed input by seeking upstream in bytes to the closest seek point before the
+    /// target, and dropping the samples before the target after decoding.
+    ///
+    /// For reverse playback the whole segment is read forwards from upstream. The base class
+    /// then queues the decoded frames until the end of the segment and pushes them in reverse
+    /// order.
+    ///
+    /// Returns `None` if the seek can't be handled here.
+    fn seek(&self, event: &gst::event::Seek) -> Option<bool> {
+        let (rate, flags, start_type, start, stop_type, stop) = event.get();
 
-        let audio_info = gstaudioinfo(&streaminfo).map_err(|e| {
-            gst::element_imp_error!(self, gst::StreamError::Decode, ["{e}"]);
-            gst::FlowError::Error
-        })?;
+        let (gst::GenericFormattedValue::Time(start), gst::GenericFormattedValue::Time(stop)) =
+            (start, stop)
+        else {
+            return None;
+        };
+
+        if (rate > 0.0 && rate != 1.0)
+            || start_type != gst::SeekType::Set
+            || !matches!(stop_type, gst::SeekType::Set | gst::SeekType::None)
+            || !flags.contains(gst::SeekFlags::FLUSH)
+        {
+            gst::debug!(CAT, imp: self, "Unsupported seek {:?}", event);
+            return None;
+        }
+        let stop = if stop_type == gst::SeekType::None {
+            None
+        } else {
+            stop
+        };
+
+        let mut seek_info = self.seek_info.lock().unwrap();
+        let streaminfo = seek_info.streaminfo?;
+        let first_frame_offset = seek_info.first_frame_offset?;
+
+        let duration = streaminfo.total_samples.and_then(|total_samples| {
+            total_samples
+                .mul_div_floor(*gst::ClockTime::SECOND, streaminfo.sample_rate as u64)
+                .map(gst::ClockTime::from_nseconds)
+        });
+        // Reverse playback starts at the end of the segment
+        let stop = if rate < 0.0 { stop.or(duration) } else { stop };
+        if rate < 0.0 && stop.is_none() {
+            gst::debug!(CAT, imp: self, "Reverse playback needs a stop position");
+            return None;
+        }
+
+        let start = start.unwrap_or(gst::ClockTime::ZERO);
+        let mut sample = start
+            .nseconds()
+            .mul_div_floor(streaminfo.sample_rate as u64, *gst::ClockTime::SECOND)?;
+        if let Some(total_samples) = streaminfo.total_samples {
+            sample = sample.min(total_samples);
+        }
+
+        // The first frame is an implicit seek point
+        let point = seek_info
+            .seektable
+            .iter()
+            .filter(|point| point.sample_number <= sample)
+            .max_by_key(|point| point.sample_number)
+            .copied()
+            .unwrap_or(flac::SeekPoint {
+                sample_number: 0,
+                offset: 0,
+            });
+        let offset = first_frame_offset + point.offset;
+
+        // All frames before the first seek point after the stop position are needed
+        let stop_offset = stop
+            .and_then(|stop| {
+                stop.nseconds()
+                    .mul_div_ceil(streaminfo.sample_rate as u64, *gst::ClockTime::SECOND)
+            })
+            .and_then(|stop_sample| {
+                seek_info
+                    .seektable
+                    .iter()
+                    .filter(|point| point.sample_number > stop_sample)
+                    .min_by_key(|point| point.sample_number)
+            })
+            .map(|point| gst::format::Bytes::from_u64(first_frame_offset + point.offset));
 
         gst::debug!(
             CAT,
             imp: self,
-            "Successfully parsed headers: {:?}",
-            audio_info
+            "Seeking to {} (sample {}) from sample {} at byte offset {} with rate {}",
+            start,
+            sample,
+            point.sample_number,
+            offset,
+            rate
         );
 
-        let element = self.obj();
-        element.set_output_format(&audio_info)?;
-        element.negotiate()?;
+        seek_info.pending_seek = Some(PendingSeek {
+            seqnum: event.seqnum(),
+            rate,
+            start,
+            stop,
+            sample,
+        });
+        drop(seek_info);
 
-        state.audio_info = Some(audio_info);
+        let seek = gst::event::Seek::builder(
+            1.0,
+            flags,
+            gst::SeekType::Set,
+            gst::format::Bytes::from_u64(offset),
+            if stop_offset.is_some() {
+                gst::SeekType::Set
+            } else {
+                gst::SeekType::None
+            },
+            stop_offset,
+        )
+        .seqnum(event.seqnum())
+        .build();
+
+        let res = self.obj().sink_pad().push_event(seek);
+        if !res {
+            gst::debug!(CAT, imp: self, "Upstream failed to seek");
+            self.seek_info.lock().unwrap().pending_seek = None;
+        }
+
+        Some(res)
+    }
+
+    /// Compares the MD5 of the `n_samples` decoded samples with the one from the STREAMINFO.
+    fn check_md5(&self, md5: glib::Checksum, n_samples: u64) {
+        let Some(streaminfo) = self.seek_info.lock().unwrap().streaminfo else {
+            return;
+        };
+        let Some(expected) = streaminfo.md5 else {
+            gst::debug!(CAT, imp: self, "No MD5 in STREAMINFO");
+            return;
+        };
+        if streaminfo
+            .total_samples
+            .is_some_and(|total| total != n_samples)
+        {
+            gst::debug!(
+                CAT,
+                imp: self,
+                "Can't check MD5 of incomplete stream with {} samples",
+                n_samples
+            );
+            return;
+        }
+
+        if md5.digest() == expected {
+            gst::debug!(CAT, imp: self, "MD5 of the decoded samples matches");
+        } else {
+            gst::element_imp_warning!(
+                self,
+                gst::StreamError::Decode,
+                ["MD5 of the decoded samples doesn't match the STREAMINFO"]
+            );
+        }
+    }
+
+    /// Returns the output channel of each decoded channel if they are in one of the legacy 7 and
+    /// 8 channel layouts, which are output in the channel order of the FLAC layouts.
+    fn legacy_reorder_map(&self, channels: usize) -> Option<[usize; 8]> {
+        let settings = *self.settings.lock().unwrap();
+        if !settings.legacy_channel_layout
+            || channels < 7
+            || mask_positions(settings.channel_mask, channels).is_some()
+        {
+            return None;
+        }
+
+        let mut reorder_map = [0; 8];
+        gst_audio::channel_reorder_map(
+            flac::channel_positions(channels, true),
+            flac::channel_positions(channels, false),
+            &mut reorder_map[..channels],
+        )
+        .ok()?;
+
+        Some(reorder_map)
+    }
+
+    /// Returns the layout preferred downstream, interleaved unless only non-interleaved is
+    /// accepted or it comes first.
+    fn output_layout(&self) -> gst_audio::AudioLayout {
+        let Some(mut caps) = self.obj().src_pad().allowed_caps() else {
+            return gst_audio::AudioLayout::Interleaved;
+        };
+        if caps.is_empty() {
+            return gst_audio::AudioLayout::Interleaved;
+        }
+
+        caps.fixate();
+        match caps.structure(0).and_then(|s| s.get::<&str>("layout").ok()) {
+            Some("non-interleaved") => gst_audio::AudioLayout::NonInterleaved,
+            _ => gst_audio::AudioLayout::Interleaved,
+        }
+    }
+
+    /// Returns which of `formats` downstream lists first.
+    fn preferred_format(
+        &self,
+        formats: &[gst_audio::AudioFormat],
+    ) -> Option<gst_audio::AudioFormat> {
+        let caps = self.obj().src_pad().allowed_caps()?;
+
+        for s in caps.iter() {
+            let names = match s.get::<gst::List>("format") {
+                Ok(list) => list
+                    .iter()
+                    .filter_map(|format| format.get::<String>().ok())
+                    .collect::<Vec<_>>(),
+                Err(_) => s.get::<String>("format").into_iter().collect(),
+            };
+
+            for name in names {
+                if let Some(format) = formats
+                    .iter()
+                    .find(|format| format.to_str().as_str() == name)
+                {
+                    return Some(*format);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Returns whether downstream prefers packed 24 bit samples over 24 bit samples in 32 bits,
+    /// i.e. if it lists them first.
+    fn prefers_packed_24(&self) -> bool {
+        self.preferred_format(&[gst_audio::AudioFormat::S24le, gst_audio::AUDIO_FORMAT_S2432])
+            == Some(gst_audio::AudioFormat::S24le)
+    }
+
+    /// Returns whether downstream only accepts normalized float samples. The integer formats are
+    /// used whenever downstream accepts any of them, as e.g. audioconvert lists F32 first.
+    fn needs_float(&self) -> bool {
+        self.preferred_format(&[
+            gst_audio::AudioFormat::S8,
+            gst_audio::AUDIO_FORMAT_S16,
+            gst_audio::AUDIO_FORMAT_S2432,
+            gst_audio::AudioFormat::S24le,
+            gst_audio::AUDIO_FORMAT_S32,
+        ])
+        .is_none()
+            && self
+                .preferred_format(&[gst_audio::AUDIO_FORMAT_F32])
+                .is_some()
+    }
+
+    /// Adds the samples in `range` of `block` to the MD5 of the decoded samples, using `data` for
+    /// converting them.
+    ///
+    /// The MD5 is only calculated if enabled and if all samples from the start of the stream are
+    /// decoded in order.
+    fn update_md5(
+        &self,
+        md5: &mut Option<(glib::Checksum, u64)>,
+        data: &mut Vec<u8>,
+        block: &decoder::Block,
+        sample_number: Option<u64>,
+        range: std::ops::Range<u64>,
+        bits_per_sample: u32,
+    ) {
+        if sample_number == Some(0) && self.settings.lock().unwrap().check_md5 {
+            *md5 = Some((glib::Checksum::new(glib::ChecksumType::Md5).unwrap(), 0));
+        }
+
+        let Some((checksum, n_samples)) = md5.as_mut() else {
+            return;
+        };
+        if sample_number != Some(*n_samples) || range.start != 0 {
+            gst::debug!(CAT, imp: self, "Discontinuity, not checking MD5");
+            *md5 = None;
+            return;
+        }
+
+        // Signed little endian samples with the smallest number of bytes, interleaved
+        let bytes = (bits_per_sample as usize + 7) / 8;
+        let channels = block.channels();
+        data.clear();
+        data.reserve(range.end as usize * channels as usize * bytes);
+        for o in range.clone() {
+            for c in 0..channels {
+                data.extend_from_slice(&block.sample(c, o as u32).to_le_bytes()[..bytes]);
+            }
+        }
+        checksum.update(data);
+        *n_samples += range.end;
+    }
+
+    /// Adds a decoded frame to the statistics and posts them if the interval passed.
+    fn count_frame(&self, size: usize, n_samples: u64, rate: u32) {
+        let interval = self.settings.lock().unwrap().stats_interval;
+
+        let mut stats = self.stats.lock().unwrap();
+        stats.frames_decoded += 1;
+        stats.frame_samples += n_samples;
+        stats.frame_bytes += size as u64;
+        stats.frame_duration += n_samples
+            .mul_div_floor(*gst::ClockTime::SECOND, rate as u64)
+            .map(gst::ClockTime::from_nseconds)
+            .unwrap_or(gst::ClockTime::ZERO);
+
+        if !interval.is_zero() && stats.frame_duration >= stats.last_posted + interval {
+            stats.last_posted = stats.frame_duration;
+            let structure = stats.to_structure();
+            drop(stats);
+
+            let element = self.obj();
+            let _ = element.post_message(
+                gst::message::Element::builder(structure)
+                    .src(&*element)
+                    .build(),
+            );
+        } else {
+            drop(stats);
+        }
+
+        self.notify_counters();
+    }
+
+    /// Notifies the counter properties that changed since the last notification, unless that
+    /// was less than `notify-interval` ago.
+    fn notify_counters(&self) {
+        let interval = self.settings.lock().unwrap().notify_interval;
+        if interval.is_zero() {
+            return;
+        }
+
+        let mut stats = self.stats.lock().unwrap();
+        let counters = stats.counters();
+        let previous = match stats.last_notified {
+            Some((_, previous)) if previous == counters => return,
+            Some((instant, _)) if instant.elapsed() < Duration::from_nanos(*interval) => return,
+            Some((_, previous)) => previous,
+            None => [0; 3],
+        };
+        stats.last_notified = Some((Instant::now(), counters));
+        drop(stats);
+
+        let element = self.obj();
+        for (name, (value, previous)) in ["frames-decoded", "frames-dropped", "decode-errors"]
+            .into_iter()
+            .zip(counters.into_iter().zip(previous))
+        {
+            if value != previous {
+                element.notify(name);
+            }
+        }
+    }
+
+    /// Drops the current packet after a recoverable error.
+    ///
+    /// The error is counted by the base class, which only fails after more than `max-errors`
+    /// errors and posts a warning otherwise.
+    fn recoverable_error(&self, err: &str) -> Result<gst::FlowSuccess, gst::FlowError> {
+        self.stats.lock().unwrap().decode_errors += 1;
+        self.notify_counters();
+        gst_audio::audio_decoder_error!(self.obj(), 1, gst::StreamError::Decode, ["{}", err])?;
+
+        self.obj().finish_frame(None, 1)
+    }
+
+    /// Drops the current frame after a recoverable error, see `recoverable_error()`.
+    fn drop_frame(&self, err: &str) -> Result<gst::FlowSuccess, gst::FlowError> {
+        self.stats.lock().unwrap().frames_dropped += 1;
+        self.recoverable_error(err)
+    }
+
+    /// Adds an audio meta for `samples` samples to the output buffer if the output is not
+    /// interleaved.
+    fn add_audio_meta(
+        &self,
+        outbuf: &mut gst::BufferRef,
+        audio_info: &gst_audio::AudioInfo,
+        samples: usize,
+    ) -> Result<(), gst::FlowError> {
+        if audio_info.layout() == gst_audio::AudioLayout::NonInterleaved {
+            gst_audio::AudioMeta::add(outbuf, audio_info, samples, &[]).map_err(|err| {
+                gst::error!(CAT, imp: self, "Failed to add audio meta: {}", err);
+                gst::FlowError::Error
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Outputs silence or repeats the previous output instead of a frame that failed to decode.
+    ///
+    /// The number of samples is taken from the frame header, or from the STREAMINFO if the
+    /// header itself is corrupt.
+    fn conceal(
+        &self,
+        state: &mut State,
+        indata: &[u8],
+        concealment: Concealment,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        self.stats.lock().unwrap().decode_errors += 1;
+        self.notify_counters();
+
+        let n_samples = flac::FrameHeader::parse(indata)
+            .map(|header| header.block_size)
+            .or_else(|| {
+                let streaminfo = self.seek_info.lock().unwrap().streaminfo;
+                streaminfo.map(|info| info.max_block_size)
+            })
+            .unwrap_or(0) as usize;
+
+        self.output_concealed(state, n_samples, concealment)
+    }
+
+    /// Fills the gap of `duration` left by a lost packet, if packet loss concealment is enabled
+    /// on the base class.
+    fn conceal_gap(
+        &self,
+        state: &mut State,
+        duration: gst::ClockTime,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let audio_info = state
+            .audio_info
+            .as_ref()
+            .ok_or(gst::FlowError::NotNegotiated)?;
+
+        let n_samples = duration
+            .nseconds()
+            .mul_div_round(audio_info.rate() as u64, *gst::ClockTime::SECOND)
+            .unwrap_or(0) as usize;
+        gst::debug!(
+            CAT,
+            imp: self,
+            "Concealing lost packet of {} with {} samples",
+            duration,
+            n_samples
+        );
+
+        // Without a concealment method configured the gap is still filled with silence, PLC was
+        // explicitly enabled after all
+        let concealment = self.settings.lock().unwrap().concealment;
+        self.output_concealed(state, n_samples, concealment)
+    }
+
+    /// Outputs `n_samples` of silence, or the previous output if it has the same size and
+    /// `concealment` asks for repeating it.
+    fn output_concealed(
+        &self,
+        state: &mut State,
+        n_samples: usize,
+        concealment: Concealment,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let audio_info = state
+            .audio_info
+            .as_ref()
+            .ok_or(gst::FlowError::NotNegotiated)?;
+
+        if n_samples == 0 {
+            return self.obj().finish_frame(None, 1);
+        }
+        let size = n_samples * audio_info.bpf() as usize;
+
+        // The samples of the MD5 are incomplete now
+        state.md5 = None;
+        self.output_timestamps.lock().unwrap().push(None);
+
+        self.stats.lock().unwrap().samples_output += n_samples as u64;
+
+        if concealment == Concealment::Repeat {
+            if let Some(last_output) = state
+                .last_output
+                .as_ref()
+                .filter(|buffer| buffer.size() == size)
+            {
+                gst::debug!(CAT, imp: self, "Repeating previous frame");
+                return self.obj().finish_frame(Some(last_output.clone()), 1);
+            }
+        }
+
+        gst::debug!(CAT, imp: self, "Outputting {} samples of silence", n_samples);
+
+        let mut outbuf = self.output_buffer(size)?;
+        {
+            let outbuf = outbuf.get_mut().unwrap();
+            let mut map = outbuf.map_writable().map_err(|_| {
+                gst::error!(CAT, imp: self, "Failed to map output buffer writable");
+                gst::FlowError::Error
+            })?;
+            // All output formats are signed
+            map.fill(0);
+            drop(map);
+
+            self.add_audio_meta(outbuf, audio_info, n_samples)?;
+        }
+
+        self.obj().finish_frame(Some(outbuf), 1)
+    }
+
+    /// Returns an output buffer of `size` bytes, from the negotiated buffer pool if there is
+    /// one.
+    fn output_buffer(&self, size: usize) -> Result<gst::Buffer, gst::FlowError> {
+        let pool = self.pool.lock().unwrap().clone();
+        let mut outbuf = match pool {
+            Some(pool) => {
+                let outbuf = pool.acquire_buffer(None)?;
+                if outbuf.size() < size {
+                    gst::debug!(CAT, imp: self, "Pool buffer too small for {} bytes", size);
+                    self.obj().allocate_output_buffer(size)
+                } else {
+                    outbuf
+                }
+            }
+            None => self.obj().allocate_output_buffer(size),
+        };
+        outbuf.get_mut().unwrap().set_size(size);
+
+        Ok(outbuf)
+    }
+
+    /// Returns the duration from the STREAMINFO.
+    fn duration(&self) -> Option<gst::ClockTime> {
+        let streaminfo = self.seek_info.lock().unwrap().streaminfo?;
+
+        streaminfo
+            .total_samples?
+            .mul_div_floor(*gst::ClockTime::SECOND, streaminfo.sample_rate as u64)
+            .map(gst::ClockTime::from_nseconds)
+    }
+
+    /// Converts between time and samples with the sample rate of the STREAMINFO.
+    fn convert(
+        &self,
+        src: gst::GenericFormattedValue,
+        dest_format: gst::Format,
+    ) -> Option<gst::GenericFormattedValue> {
+        if src.format() == dest_format {
+            return Some(src);
+        }
+
+        let rate = self.seek_info.lock().unwrap().streaminfo?.sample_rate as u64;
+        match (src, dest_format) {
+            (gst::GenericFormattedValue::Time(time), gst::Format::Default) => {
+                let samples = time?
+                    .nseconds()
+                    .mul_div_floor(rate, *gst::ClockTime::SECOND)?;
+                Some(gst::GenericFormattedValue::Default(Some(
+                    gst::format::Default::from_u64(samples),
+                )))
+            }
+            (gst::GenericFormattedValue::Default(samples), gst::Format::Time) => {
+                let time = (*samples?).mul_div_floor(*gst::ClockTime::SECOND, rate)?;
+                Some(gst::GenericFormattedValue::Time(Some(
+                    gst::ClockTime::from_nseconds(time),
+                )))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the output caps possible for the stream, once its STREAMINFO is known.
+    fn stream_caps(&self) -> Option<gst::Caps> {
+        let streaminfo = self.seek_info.lock().unwrap().streaminfo?;
+
+        // Same formats as in `gstaudioinfo()`, the 24 bit and float ones depending on
+        // downstream
+        let formats: &[gst_audio::AudioFormat] = match streaminfo.bits_per_sample {
+            4..=8 => &[gst_audio::AudioFormat::S8, gst_audio::AUDIO_FORMAT_F32],
+            9..=16 => &[gst_audio::AUDIO_FORMAT_S16, gst_audio::AUDIO_FORMAT_F32],
+            17..=24 => &[
+                gst_audio::AUDIO_FORMAT_S2432,
+                gst_audio::AudioFormat::S24le,
+                gst_audio::AUDIO_FORMAT_F32,
+            ],
+            25..=32 => &[gst_audio::AUDIO_FORMAT_S32],
+            _ => return None,
+        };
+
+        let caps = gst_audio::AudioCapsBuilder::new()
+            .layout_list([
+                gst_audio::AudioLayout::Interleaved,
+                gst_audio::AudioLayout::NonInterleaved,
+            ])
+            .format_list(formats.iter().copied())
+            .rate(streaminfo.sample_rate as i32)
+            .channels(streaminfo.channels as i32)
+            .build();
+
+        Some(caps)
+    }
+
+    /// Returns the duration, or `Some(None)` if it is unknown, if seeking in the unframed input
+    /// is possible.
+    fn seekable_duration(&self) -> Option<Option<gst::ClockTime>> {
+        {
+            let seek_info = self.seek_info.lock().unwrap();
+            if seek_info.streaminfo.is_none() || seek_info.first_frame_offset.is_none() {
+                return None;
+            }
+        }
+
+        let mut query = gst::query::Seeking::new(gst::Format::Bytes);
+        if !self.obj().sink_pad().peer_query(&mut query) || !query.result().0 {
+            return None;
+        }
+
+        Some(self.duration())
+    }
+
+    /// Negotiates the output for the STREAMINFO from the caps. `block` is the STREAMINFO
+    /// metadata block including its header.
+    fn negotiate_streaminfo(
+        &self,
+        streaminfo: &claxon::metadata::StreamInfo,
+        block: &[u8],
+    ) -> Option<gst_audio::AudioInfo> {
+        let settings = *self.settings.lock().unwrap();
+        let audio_info = gstaudioinfo(
+            streaminfo,
+            self.output_layout(),
+            self.prefers_packed_24(),
+            self.needs_float(),
+            settings.channel_mask,
+        )
+        .ok()?;
+
+        self.seek_info.lock().unwrap().streaminfo = flac::StreamInfo::parse(&block[4..]);
+        self.obj().notify("streaminfo");
+
+        // To speed up negotiation
+        let element = self.obj();
+        if element.set_output_format(&audio_info).is_err() || element.negotiate().is_err() {
+            gst::debug!(
+                CAT,
+                imp: self,
+                "Error to negotiate output from based on in-caps streaminfo"
+            );
+        }
+
+        Some(audio_info)
+    }
+
+    /// Handles the metadata blocks other than the STREAMINFO in a header buffer.
+    ///
+    /// The tags of the blocks are added to `tags`, which are then used as the decoder tags, and
+    /// APPLICATION blocks are added to `application_blocks`.
+    fn handle_metadata_blocks(
+        &self,
+        tags: &mut gst::TagList,
+        application_blocks: &mut Vec<gst::Structure>,
+        mut indata: &[u8],
+    ) {
+        let mut changed = false;
+        while let Some(header) = flac::MetadataBlockHeader::parse(indata) {
+            let Some(block) = indata.get(4..4 + header.length) else {
+                gst::warning!(CAT, imp: self, "Truncated metadata block");
+                break;
+            };
+
+            let (block_tags, mode) = match header.block_type {
+                flac::METADATA_VORBIS_COMMENT => {
+                    let block_tags = tags::vorbis_comment_to_tags(block);
+                    if block_tags.is_none() {
+                        gst::warning!(CAT, imp: self, "Invalid Vorbis comment");
+                    }
+                    (block_tags, gst::TagMergeMode::Replace)
+                }
+                flac::METADATA_PICTURE => {
+                    let block_tags = tags::picture_to_tags(block);
+                    if block_tags.is_none() {
+                        gst::debug!(CAT, imp: self, "Ignoring unsupported picture");
+                    }
+                    // Keep the pictures of previous blocks
+                    (block_tags, gst::TagMergeMode::Append)
+                }
+                flac::METADATA_SEEKTABLE => {
+                    let seektable = flac::parse_seektable(block);
+                    gst::debug!(CAT, imp: self, "Got seek table with {} points", seektable.len());
+                    self.seek_info.lock().unwrap().seektable = seektable;
+                    (None, gst::TagMergeMode::Append)
+                }
+                flac::METADATA_CUESHEET => {
+                    self.handle_cuesheet(block);
+                    (None, gst::TagMergeMode::Append)
+                }
+                flac::METADATA_APPLICATION if block.len() >= 4 => {
+                    let id = String::from_utf8_lossy(&block[..4]);
+                    gst::debug!(CAT, imp: self, "Got APPLICATION block with id {:?}", id);
+                    application_blocks.push(
+                        gst::Structure::builder("application-block")
+                            .field("id", id.as_ref())
+                            .field("data", gst::Buffer::from_slice(block[4..].to_vec()))
+                            .build(),
+                    );
+                    (None, gst::TagMergeMode::Append)
+                }
+                _ => (None, gst::TagMergeMode::Append),
+            };
+
+            if let Some(block_tags) = block_tags {
+                gst::debug!(CAT, imp: self, "Got tags {:?}", block_tags);
+                tags.make_mut().insert(&block_tags, mode);
+                changed = true;
+            }
+
+            indata = &indata[4 + header.length..];
+        }
+
+        if changed {
+            self.obj()
+                .merge_tags(Some(tags), gst::TagMergeMode::Replace);
+        }
+    }
+
+    /// Adds the codec and the average bitrate of the stream to `tags`, which are then used as the
+    /// decoder tags.
+    ///
+    /// The bitrate is only known if upstream knows the size of the stream and the STREAMINFO
+    /// its number of samples.
+    fn add_stream_tags(&self, tags: &mut gst::TagList, streaminfo: &flac::StreamInfo) {
+        let bitrate = streaminfo
+            .total_samples
+            .zip(
+                self.obj()
+                    .sink_pad()
+                    .peer_query_duration::<gst::format::Bytes>(),
+            )
+            .and_then(|(total_samples, size)| {
+                (*size * 8).mul_div_floor(streaminfo.sample_rate as u64, total_samples)
+            })
+            .and_then(|bitrate| u32::try_from(bitrate).ok());
+
+        gst::debug!(CAT, imp: self, "Stream has average bitrate {:?}", bitrate);
+
+        {
+            let tags = tags.make_mut();
+            tags.add::<gst::tags::AudioCodec>(&"FLAC", gst::TagMergeMode::Replace);
+            if let Some(bitrate) = bitrate {
+                tags.add::<gst::tags::Bitrate>(&bitrate, gst::TagMergeMode::Replace);
+            }
+        }
+
+        self.obj()
+            .merge_tags(Some(tags), gst::TagMergeMode::Replace);
+    }
+
+    fn handle_cuesheet(&self, block: &[u8]) {
+        let Some(rate) = self
+            .seek_info
+            .lock()
+            .unwrap()
+            .streaminfo
+            .map(|info| info.sample_rate)
+        else {
+            gst::warning!(CAT, imp: self, "Ignoring cue sheet before STREAMINFO");
+            return;
+        };
+
+        let Some(toc) =
+            flac::parse_cuesheet(block).and_then(|tracks| tags::cuesheet_to_toc(&tracks, rate))
+        else {
+            gst::warning!(CAT, imp: self, "Invalid cue sheet");
+            return;
+        };
+
+        gst::debug!(CAT, imp: self, "Got TOC {:?}", toc);
+
+        // The base class queues serialized events and sends them before the next buffer
+        self.parent_sink_event(gst::event::Toc::new(&toc, false));
+    }
+
+    /// Sends the APPLICATION metadata blocks downstream in a sticky custom event.
+    ///
+    /// The `flac-application-blocks` structure of the event contains a `blocks` array with one
+    /// `application-block` structure per block, with the application `id` and the `data` after
+    /// it as buffer.
+    fn send_application_blocks(&self, blocks: Vec<gst::Structure>) {
+        gst::debug!(CAT, imp: self, "Sending {} APPLICATION blocks", blocks.len());
+
+        let s = gst::Structure::builder("flac-application-blocks")
+            .field("blocks", gst::Array::new(blocks))
+            .build();
+        // The base class queues serialized events and sends them before the next buffer
+        self.parent_sink_event(gst::event::CustomDownstreamSticky::new(s));
+    }
+
+    fn handle_streaminfo_header(
+        &self,
+        state: &mut State,
+        indata: &[u8],
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let settings = *self.settings.lock().unwrap();
+        let audio_info = claxon_streaminfo(indata).and_then(|streaminfo| {
+            gstaudioinfo(
+                &streaminfo,
+                self.output_layout(),
+                self.prefers_packed_24(),
+                self.needs_float(),
+                settings.channel_mask,
+            )
+        });
+        let audio_info = match audio_info {
+            Ok(audio_info) => audio_info,
+            // Decoding can continue with the previous STREAMINFO
+            Err(err) if state.audio_info.is_some() => return self.recoverable_error(&err),
+            Err(err) => {
+                gst::element_imp_error!(self, gst::StreamError::Decode, ["{err}"]);
+                return Err(gst::FlowError::Error);
+            }
+        };
+
+        gst::debug!(
+            CAT,
+            imp: self,
+            "Successfully parsed headers: {:?}",
+            audio_info
+        );
+
+        // Also used for the size of the output buffers
+        let streaminfo = flac::StreamInfo::parse(&indata[4..]);
+        self.seek_info.lock().unwrap().streaminfo = streaminfo;
+        self.obj().notify("streaminfo");
+        if let Some(streaminfo) = streaminfo {
+            self.add_stream_tags(&mut state.tags, &streaminfo);
+        }
+
+        let element = self.obj();
+        if state.audio_info.as_ref() != Some(&audio_info) {
+            if state.audio_info.is_some() {
+                gst::debug!(CAT, imp: self, "Output format changed");
+            }
+
+            element.set_output_format(&audio_info)?;
+            element.negotiate()?;
+
+            state.audio_info = Some(audio_info);
+        }
 
         element.finish_frame(None, 1)
     }
@@ -238,6 +1963,7 @@ impl ClaxonDec {
         &self,
         state: &mut State,
         indata: &[u8],
+        pts: Option<gst::ClockTime>,
     ) -> Result<gst::FlowSuccess, gst::FlowError> {
         // TODO It's valid for FLAC to not have any streaminfo header at all, for a small subset
         // of possible FLAC configurations. (claxon does not actually support that)
@@ -245,95 +1971,364 @@ impl ClaxonDec {
             .audio_info
             .as_ref()
             .ok_or(gst::FlowError::NotNegotiated)?;
-        let depth = AudioDepth::validate(audio_info.depth())?;
 
-        let channels = audio_info.channels() as usize;
-        if channels > 8 {
-            unreachable!(
-                "FLAC only supports from 1 to 8 channels (audio contains {} channels)",
-                channels
+        // Both are already rejected by `gstaudioinfo()` when negotiating the output
+        let channels = audio_info.channels();
+        if !(1..=8).contains(&channels) {
+            gst::element_imp_error!(
+                self,
+                gst::StreamError::Format,
+                ["FLAC only supports from 1 to 8 channels (audio contains {channels} channels)"]
+            );
+            return Err(gst::FlowError::NotNegotiated);
+        }
+        if AudioDepth::validate(audio_info).is_err() {
+            gst::element_imp_error!(
+                self,
+                gst::StreamError::Format,
+                ["Unsupported output sample width {}", audio_info.width()]
             );
+            return Err(gst::FlowError::NotNegotiated);
         }
 
-        let buffer = Vec::new();
-        let mut cursor = Cursor::new(indata);
-        let mut reader = claxon::frame::FrameReader::new(&mut cursor);
-        let result = match reader.read_next_or_eof(buffer) {
+        state.had_frames = true;
+
+        if !state.application_blocks.is_empty() {
+            self.send_application_blocks(std::mem::take(&mut state.application_blocks));
+        }
+
+        // claxon also verifies the CRCs, but fails the frame with a decode error
+        if self.settings.lock().unwrap().check_crc {
+            if let Err(err) = flac::check_frame_crcs(indata) {
+                self.finish_pending(state, 0)?;
+                return self.drop_frame(&format!("Dropping corrupt frame: {err}"));
+            }
+        }
+
+        let buffer = state.block_buffers.pop().unwrap_or_default();
+        let bits_per_sample = self
+            .seek_info
+            .lock()
+            .unwrap()
+            .streaminfo
+            .map(|info| info.bits_per_sample);
+
+        if state.workers.is_none() {
+            let n_threads = match self.settings.lock().unwrap().n_threads {
+                0 => std::thread::available_parallelism()
+                    .map_or(1, |n| n.get())
+                    .min(MAX_N_THREADS as usize),
+                n => n as usize,
+            };
+            if n_threads > 1 {
+                state.workers = Some(self.start_workers(n_threads)?);
+            }
+        }
+
+        // The output of the frames decoded in parallel is finished once they are done, while
+        // keeping all threads busy
+        if let Some(workers) = state.workers.as_mut() {
+            workers
+                .submit(indata.to_vec(), bits_per_sample, buffer, pts)
+                .map_err(|err| self.workers_error(err))?;
+            let n_threads = workers.n_threads();
+            return self.finish_pending(state, n_threads);
+        }
+
+        let decoded = workers::decode_frame(indata, bits_per_sample, buffer);
+        self.output_frame(state, indata, pts, decoded)
+    }
+
+    /// Starts the threads for decoding frames in parallel and updates the latency accordingly.
+    fn start_workers(&self, n_threads: usize) -> Result<workers::Workers, gst::FlowError> {
+        gst::debug!(CAT, imp: self, "Decoding with {} threads", n_threads);
+
+        let workers = workers::Workers::new(n_threads).map_err(|err| {
+            gst::element_imp_error!(
+                self,
+                gst::CoreError::Failed,
+                ["Failed to start decoding threads: {}", err]
+            );
+            gst::FlowError::Error
+        })?;
+
+        // Output is delayed by up to one frame per thread
+        let streaminfo = self.seek_info.lock().unwrap().streaminfo;
+        if let Some(latency) = streaminfo.and_then(|info| {
+            (n_threads as u64 * info.max_block_size as u64)
+                .mul_div_ceil(*gst::ClockTime::SECOND, info.sample_rate as u64)
+                .map(gst::ClockTime::from_nseconds)
+        }) {
+            self.obj().set_latency(latency, Some(latency));
+        }
+
+        Ok(workers)
+    }
+
+    /// Outputs the frames decoded in parallel until at most `keep` frames are pending.
+    fn finish_pending(
+        &self,
+        state: &mut State,
+        keep: usize,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        while let Some(workers) = state
+            .workers
+            .as_mut()
+            .filter(|workers| workers.n_pending() > keep)
+        {
+            let next = workers.next().map_err(|err| self.workers_error(err))?;
+            let Some((data, pts, decoded)) = next else {
+                break;
+            };
+            self.output_frame(state, &data, pts, decoded)?;
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    fn workers_error(&self, err: &str) -> gst::FlowError {
+        gst::element_imp_error!(self, gst::CoreError::Failed, ["{}", err]);
+        gst::FlowError::Error
+    }
+
+    /// Outputs the decoded samples of a frame.
+    ///
+    /// If the input has no timestamp, the output timestamps are calculated from the sample
+    /// number of the frame instead of being interpolated by the base class.
+    fn output_frame(
+        &self,
+        state: &mut State,
+        indata: &[u8],
+        pts: Option<gst::ClockTime>,
+        (result, used): workers::DecodeResult,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let audio_info = state
+            .audio_info
+            .as_ref()
+            .ok_or(gst::FlowError::NotNegotiated)?;
+        let depth = AudioDepth::validate(audio_info)?;
+
+        let result = match result {
             Ok(Some(result)) => result,
-            Ok(None) => return self.obj().finish_frame(None, 1),
+            Ok(None) => return self.drop_frame("Truncated frame"),
             Err(err) => {
-                return gst_audio::audio_decoder_error!(
-                    self.obj(),
-                    1,
-                    gst::StreamError::Decode,
-                    ["Failed to decode packet: {:?}", err]
-                );
+                let concealment = self.settings.lock().unwrap().concealment;
+                if concealment != Concealment::None {
+                    gst::warning!(CAT, imp: self, "Failed to decode packet: {:?}", err);
+                    return self.conceal(state, indata, concealment);
+                }
+
+                return self.drop_frame(&format!("Failed to decode packet: {err:?}"));
             }
         };
 
-        assert_eq!(cursor.position(), indata.len() as u64);
+        if used != indata.len() as u64 {
+            gst::warning!(
+                CAT,
+                imp: self,
+                "Ignoring {} trailing bytes after frame",
+                indata.len() as u64 - used
+            );
+        }
 
-        let v = if channels != 1 {
-            let mut v: Vec<i32> = vec![0; result.len() as usize];
+        // The output buffer only has room for the channels of the STREAMINFO
+        if result.channels() != audio_info.channels() {
+            let err = format!(
+                "Frame has {} channels instead of {}",
+                result.channels(),
+                audio_info.channels()
+            );
+            state.block_buffers.push(result.into_buffer());
+            return self.drop_frame(&err);
+        }
 
-            for (o, i) in v.chunks_exact_mut(channels).enumerate() {
-                for (c, s) in i.iter_mut().enumerate() {
-                    *s = result.sample(c as u32, o as u32);
+        let (block_size, total_samples, bits_per_sample) = self
+            .seek_info
+            .lock()
+            .unwrap()
+            .streaminfo
+            .map_or((None, None, None), |info| {
+                (
+                    Some(info.max_block_size),
+                    info.total_samples,
+                    Some(info.bits_per_sample),
+                )
+            });
+        let sample_number = flac::FrameHeader::parse(indata)
+            .zip(block_size)
+            .map(|(header, block_size)| header.sample_number(block_size));
+        let n_samples = result.duration() as u64;
+        self.count_frame(indata.len(), n_samples, audio_info.rate());
+
+        // Samples before the target of an accurate seek are dropped
+        let mut skip = 0;
+        if let Some(seek_sample) = state.seek_sample {
+            match sample_number {
+                Some(sample_number) if sample_number + n_samples <= seek_sample => {
+                    gst::trace!(CAT, imp: self, "Dropping frame before seek position");
+                    state.block_buffers.push(result.into_buffer());
+                    return self.obj().finish_frame(None, 1);
+                }
+                Some(sample_number) if sample_number < seek_sample => {
+                    skip = seek_sample - sample_number;
+                    state.seek_sample = None;
                 }
+                _ => state.seek_sample = None,
             }
-            v
+        }
+
+        // Samples after the total number of samples from the STREAMINFO are padding, e.g. of
+        // the last block of an encoder that always uses the same block size
+        let mut keep = n_samples;
+        if let Some((sample_number, total_samples)) = sample_number.zip(total_samples) {
+            keep = total_samples.saturating_sub(sample_number).min(n_samples);
+            if keep < n_samples {
+                gst::debug!(
+                    CAT,
+                    imp: self,
+                    "Trimming {} samples after the end of the stream",
+                    n_samples - keep
+                );
+            }
+        }
+
+        if skip >= keep {
+            state.block_buffers.push(result.into_buffer());
+            return self.obj().finish_frame(None, 1);
+        }
+
+        if let Some(bits_per_sample) = bits_per_sample {
+            self.update_md5(
+                &mut state.md5,
+                &mut state.md5_data,
+                &result,
+                sample_number,
+                skip..keep,
+                bits_per_sample,
+            );
+        }
+
+        let bits_per_sample = bits_per_sample.unwrap_or(audio_info.depth());
+        let gain = if self.settings.lock().unwrap().apply_replaygain {
+            tags::replaygain_factor(&state.tags)
         } else {
-            result.into_buffer()
+            None
+        };
+        let scale = SampleScale {
+            // Samples with fewer bits than the output format are scaled up to use its full range
+            shift: audio_info.depth().saturating_sub(bits_per_sample),
+            gain,
+            max: ((1u64 << (bits_per_sample - 1)) - 1) as i32,
         };
 
-        let depth_adjusted = depth.adjust_samples(v);
-        let outbuf = gst::Buffer::from_mut_slice(depth_adjusted);
+        let timestamps = sample_number
+            .filter(|_| pts.is_none())
+            .and_then(|sample_number| {
+                let to_time = |sample: u64| {
+                    sample
+                        .mul_div_floor(*gst::ClockTime::SECOND, audio_info.rate() as u64)
+                        .map(gst::ClockTime::from_nseconds)
+                };
+                let start = to_time(sample_number + skip)?;
+                let end = to_time(sample_number + keep)?;
+                Some((start, end - start))
+            });
+
+        let range = skip as usize..keep as usize;
+        let mut outbuf = self.output_buffer(range.len() * audio_info.bpf() as usize)?;
+        {
+            let outbuf = outbuf.get_mut().unwrap();
+            if let Some((pts, duration)) = timestamps {
+                // Reverse playback needs a duration for every queued buffer, even if the base
+                // class has no timestamps of its own
+                outbuf.set_pts(pts);
+                outbuf.set_duration(duration);
+            }
+
+            let mut map = outbuf.map_writable().map_err(|_| {
+                gst::error!(CAT, imp: self, "Failed to map output buffer writable");
+                gst::FlowError::Error
+            })?;
+            let reorder_map = self.legacy_reorder_map(audio_info.channels() as usize);
+            depth
+                .write_samples(
+                    &result,
+                    range.clone(),
+                    scale,
+                    audio_info.layout(),
+                    reorder_map.as_ref().map(|reorder_map| &reorder_map[..]),
+                    &mut map,
+                )
+                .map_err(|err| {
+                    gst::error!(CAT, imp: self, "Failed to write samples: {}", err);
+                    gst::FlowError::Error
+                })?;
+            drop(map);
+
+            self.add_audio_meta(outbuf, audio_info, range.len())?;
+        }
+        state.block_buffers.push(result.into_buffer());
+
+        if self.settings.lock().unwrap().concealment == Concealment::Repeat {
+            state.last_output = Some(outbuf.clone());
+        } else {
+            state.last_output = None;
+        }
+
+        self.stats.lock().unwrap().samples_output += range.len() as u64;
+
+        self.output_timestamps.lock().unwrap().push(timestamps);
+
         self.obj().finish_frame(Some(outbuf), 1)
     }
 }
 
+/// Conversion of the decoded samples to the output format
+#[derive(Debug, Clone, Copy)]
+struct SampleScale {
+    /// Number of bits the samples are shifted left by
+    shift: u32,
+    /// Linear ReplayGain factor, if it is applied
+    gain: Option<f64>,
+    /// Largest sample value of the decoded bit depth, to which the gain is clipped
+    max: i32,
+}
+
+impl SampleScale {
+    fn apply(&self, x: i32) -> i32 {
+        let x = match self.gain {
+            Some(gain) => (x as f64 * gain)
+                .round()
+                .clamp(-(self.max as f64) - 1.0, self.max as f64) as i32,
+            None => x,
+        };
+
+        x << self.shift
+    }
+}
+
 /// Depth of audio samples
 enum AudioDepth {
     /// 8bits.
     I8,
     /// 16bits.
     I16,
-    /// 24bits.
+    /// 24bits, packed into 3 bytes.
     I24,
-    /// 32bits.
+    /// 32bits, also for 24bits in 32bits.
     I32,
+    /// 32bits float, normalized to [-1.0, 1.0].
+    F32,
 }
 
-enum ByteVec {
-    I8(Vec<i8>),
-    I16(Vec<i16>),
-    I32(Vec<i32>),
-}
-
-impl AsRef<[u8]> for ByteVec {
-    fn as_ref(&self) -> &[u8] {
-        match self {
-            ByteVec::I8(ref vec) => vec.as_byte_slice(),
-            ByteVec::I16(ref vec) => vec.as_byte_slice(),
-            ByteVec::I32(ref vec) => vec.as_byte_slice(),
-        }
-    }
-}
-
-impl AsMut<[u8]> for ByteVec {
-    fn as_mut(&mut self) -> &mut [u8] {
-        match self {
-            ByteVec::I8(ref mut vec) => vec.as_mut_byte_slice(),
-            ByteVec::I16(ref mut vec) => vec.as_mut_byte_slice(),
-            ByteVec::I32(ref mut vec) => vec.as_mut_byte_slice(),
+impl AudioDepth {
+    /// Validate output sample format.
+    fn validate(info: &gst_audio::AudioInfo) -> Result<Self, gst::FlowError> {
+        if info.format() == gst_audio::AUDIO_FORMAT_F32 {
+            return Ok(AudioDepth::F32);
         }
-    }
-}
 
-impl AudioDepth {
-    /// Validate input audio depth.
-    fn validate(input: u32) -> Result<Self, gst::FlowError> {
-        let depth = match input {
+        let depth = match info.width() {
             8 => AudioDepth::I8,
             16 => AudioDepth::I16,
             24 => AudioDepth::I24,
@@ -343,40 +2338,176 @@ impl AudioDepth {
         Ok(depth)
     }
 
-    /// Adjust samples depth.
+    /// Writes the samples in `range` of all channels of `block` converted with `scale` into
+    /// `output`, either interleaved or as one plane per channel. `reorder_map` contains the
+    /// output channel of each channel of `block`, if they are in a different order.
     ///
-    /// This takes a vector of 32bits samples, adjusts the depth of each,
-    /// and returns the adjusted bytes stream.
-    fn adjust_samples(&self, input: Vec<i32>) -> ByteVec {
+    /// Fails if `output` is not aligned for the output sample type.
+    fn write_samples(
+        &self,
+        block: &decoder::Block,
+        range: std::ops::Range<usize>,
+        scale: SampleScale,
+        layout: gst_audio::AudioLayout,
+        reorder_map: Option<&[usize]>,
+        output: &mut [u8],
+    ) -> Result<(), byte_slice_cast::Error> {
         match *self {
-            AudioDepth::I8 => ByteVec::I8(input.into_iter().map(|x| x as i8).collect::<Vec<_>>()),
-            AudioDepth::I16 => {
-                ByteVec::I16(input.into_iter().map(|x| x as i16).collect::<Vec<_>>())
-            }
-            AudioDepth::I24 | AudioDepth::I32 => ByteVec::I32(input),
+            AudioDepth::I8 => write_layout(block, range, layout, reorder_map, output, |x| {
+                scale.apply(x) as i8 as u8
+            }),
+            AudioDepth::I16 => write_layout(
+                block,
+                range,
+                layout,
+                reorder_map,
+                output.as_mut_slice_of()?,
+                |x| scale.apply(x) as i16,
+            ),
+            AudioDepth::I24 => write_layout(
+                block,
+                range,
+                layout,
+                reorder_map,
+                output.as_mut_slice_of()?,
+                |x| {
+                    let bytes = scale.apply(x).to_le_bytes();
+                    [bytes[0], bytes[1], bytes[2]]
+                },
+            ),
+            AudioDepth::I32 => write_layout(
+                block,
+                range,
+                layout,
+                reorder_map,
+                output.as_mut_slice_of()?,
+                |x| scale.apply(x),
+            ),
+            // The shift scales the samples up to 32 bits, which is exact in f32 for the at most
+            // 24 bits of the decoded samples
+            AudioDepth::F32 => write_layout(
+                block,
+                range,
+                layout,
+                reorder_map,
+                output.as_mut_slice_of()?,
+                |x| scale.apply(x) as f32 / 2_147_483_648.0,
+            ),
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes the samples in `range` of all channels of `block` into `output` in `layout`, with the
+/// channels in the order of `reorder_map`.
+fn write_layout<T>(
+    block: &decoder::Block,
+    range: std::ops::Range<usize>,
+    layout: gst_audio::AudioLayout,
+    reorder_map: Option<&[usize]>,
+    output: &mut [T],
+    convert: impl Fn(i32) -> T,
+) {
+    if layout == gst_audio::AudioLayout::NonInterleaved {
+        planar(block, range, reorder_map, output, convert);
+    } else {
+        interleave(block, range, reorder_map, output, convert);
+    }
+}
+
+/// Copies the samples in `range` of each channel of `block` into consecutive planes of
+/// `output`.
+fn planar<T>(
+    block: &decoder::Block,
+    range: std::ops::Range<usize>,
+    reorder_map: Option<&[usize]>,
+    output: &mut [T],
+    convert: impl Fn(i32) -> T,
+) {
+    let len = range.len();
+    for c in 0..block.channels() as usize {
+        let input = &block.channel(c as u32)[range.clone()];
+        let out_c = reorder_map.map_or(c, |map| map[c]);
+        let plane = &mut output[out_c * len..(out_c + 1) * len];
+        for (out, sample) in plane.iter_mut().zip(input) {
+            *out = convert(*sample);
         }
     }
 }
 
-fn claxon_streaminfo(indata: &[u8]) -> Result<claxon::metadata::StreamInfo, &'static str> {
+/// Interleaves the samples in `range` of all channels of `block` into `output`.
+fn interleave<T>(
+    block: &decoder::Block,
+    range: std::ops::Range<usize>,
+    reorder_map: Option<&[usize]>,
+    output: &mut [T],
+    convert: impl Fn(i32) -> T,
+) {
+    let channels = block.channels() as usize;
+    for c in 0..channels {
+        let input = &block.channel(c as u32)[range.clone()];
+        let out_c = reorder_map.map_or(c, |map| map[c]);
+        for (frame, sample) in output.chunks_exact_mut(channels).zip(input) {
+            frame[out_c] = convert(*sample);
+        }
+    }
+}
+
+/// Converts the STREAMINFO to the structure of the `streaminfo` property.
+///
+/// The total number of samples and the MD5 are only included if they are known.
+fn streaminfo_to_structure(streaminfo: &flac::StreamInfo) -> gst::Structure {
+    let mut s = gst::Structure::builder("streaminfo")
+        .field("rate", streaminfo.sample_rate)
+        .field("channels", streaminfo.channels)
+        .field("depth", streaminfo.bits_per_sample)
+        .field("min-block-size", streaminfo.min_block_size)
+        .field("max-block-size", streaminfo.max_block_size)
+        .build();
+
+    if let Some(total_samples) = streaminfo.total_samples {
+        s.set("total-samples", total_samples);
+    }
+    if let Some(md5) = streaminfo.md5 {
+        let md5 = md5.iter().map(|b| format!("{b:02x}")).collect::<String>();
+        s.set("md5", md5);
+    }
+
+    s
+}
+
+fn claxon_streaminfo(indata: &[u8]) -> Result<claxon::metadata::StreamInfo, String> {
     let mut cursor = Cursor::new(indata);
     let mut metadata_iter = claxon::metadata::MetadataBlockReader::new(&mut cursor);
     let streaminfo = match metadata_iter.next() {
         Some(Ok(claxon::metadata::MetadataBlock::StreamInfo(info))) => info,
-        _ => return Err("Failed to decode STREAMINFO"),
+        _ => return Err("Failed to decode STREAMINFO".to_string()),
     };
 
-    assert_eq!(cursor.position(), indata.len() as u64);
+    if cursor.position() != indata.len() as u64 {
+        return Err("Trailing data after STREAMINFO".to_string());
+    }
 
     Ok(streaminfo)
 }
 
-fn gstaudioinfo(streaminfo: &claxon::metadata::StreamInfo) -> Result<gst_audio::AudioInfo, String> {
+fn gstaudioinfo(
+    streaminfo: &claxon::metadata::StreamInfo,
+    layout: gst_audio::AudioLayout,
+    packed_24: bool,
+    float: bool,
+    channel_mask: u64,
+) -> Result<gst_audio::AudioInfo, String> {
+    // Depths without a matching format, e.g. 12 or 20 bits, use the next larger one
     let format = match streaminfo.bits_per_sample {
-        8 => gst_audio::AudioFormat::S8,
-        16 => gst_audio::AUDIO_FORMAT_S16,
-        24 => gst_audio::AUDIO_FORMAT_S2432,
-        32 => gst_audio::AUDIO_FORMAT_S32,
+        // f32 can't represent all 32 bit samples exactly
+        4..=24 if float => gst_audio::AUDIO_FORMAT_F32,
+        4..=8 => gst_audio::AudioFormat::S8,
+        9..=16 => gst_audio::AUDIO_FORMAT_S16,
+        17..=24 if packed_24 => gst_audio::AudioFormat::S24le,
+        17..=24 => gst_audio::AUDIO_FORMAT_S2432,
+        25..=32 => gst_audio::AUDIO_FORMAT_S32,
         _ => return Err("format not supported".to_string()),
     };
 
@@ -385,100 +2516,54 @@ fn gstaudioinfo(streaminfo: &claxon::metadata::StreamInfo) -> Result<gst_audio::
         n if n > 8 => return Err("more than 8 channels, not supported yet".to_string()),
         n => n,
     };
-    let to = &FLAC_CHANNEL_POSITIONS[index - 1][..index];
+    let positions = mask_positions(channel_mask, index);
+    if channel_mask != 0 && positions.is_none() {
+        gst::warning!(
+            CAT,
+            "Channel mask {:#x} doesn't match {} channels, using FLAC channel layout",
+            channel_mask,
+            index
+        );
+    }
+    // Samples in the legacy 7 and 8 channel layouts are reordered to the FLAC layouts when
+    // writing them
+    let to = positions
+        .as_ref()
+        .map_or(flac::channel_positions(index, false), |positions| {
+            &positions[..index]
+        });
     let info_builder =
         gst_audio::AudioInfo::builder(format, streaminfo.sample_rate, streaminfo.channels)
-            .positions(to);
+            .positions(to)
+            .layout(layout);
 
     let audio_info = info_builder
         .build()
         .map_err(|e| format!("failed to build audio info: {e}"))?;
+    // Also checked for every frame
+    AudioDepth::validate(&audio_info)
+        .map_err(|_| format!("unsupported output sample width {}", audio_info.width()))?;
 
     Ok(audio_info)
 }
 
-// http://www.xiph.org/vorbis/doc/Vorbis_I_spec.html#x1-800004.3.9
-// http://flac.sourceforge.net/format.html#frame_header
-const FLAC_CHANNEL_POSITIONS: [[gst_audio::AudioChannelPosition; 8]; 8] = [
-    [
-        gst_audio::AudioChannelPosition::Mono,
-        gst_audio::AudioChannelPosition::Invalid,
-        gst_audio::AudioChannelPosition::Invalid,
-        gst_audio::AudioChannelPosition::Invalid,
-        gst_audio::AudioChannelPosition::Invalid,
-        gst_audio::AudioChannelPosition::Invalid,
-        gst_audio::AudioChannelPosition::Invalid,
-        gst_audio::AudioChannelPosition::Invalid,
-    ],
-    [
-        gst_audio::AudioChannelPosition::FrontLeft,
-        gst_audio::AudioChannelPosition::FrontRight,
-        gst_audio::AudioChannelPosition::Invalid,
-        gst_audio::AudioChannelPosition::Invalid,
-        gst_audio::AudioChannelPosition::Invalid,
-        gst_audio::AudioChannelPosition::Invalid,
-        gst_audio::AudioChannelPosition::Invalid,
-        gst_audio::AudioChannelPosition::Invalid,
-    ],
-    [
-        gst_audio::AudioChannelPosition::FrontLeft,
-        gst_audio::AudioChannelPosition::FrontCenter,
-        gst_audio::AudioChannelPosition::FrontRight,
-        gst_audio::AudioChannelPosition::Invalid,
-        gst_audio::AudioChannelPosition::Invalid,
-        gst_audio::AudioChannelPosition::Invalid,
-        gst_audio::AudioChannelPosition::Invalid,
-        gst_audio::AudioChannelPosition::Invalid,
-    ],
-    [
-        gst_audio::AudioChannelPosition::FrontLeft,
-        gst_audio::AudioChannelPosition::FrontRight,
-        gst_audio::AudioChannelPosition::RearLeft,
-        gst_audio::AudioChannelPosition::RearRight,
-        gst_audio::AudioChannelPosition::Invalid,
-        gst_audio::AudioChannelPosition::Invalid,
-        gst_audio::AudioChannelPosition::Invalid,
-        gst_audio::AudioChannelPosition::Invalid,
-    ],
-    [
-        gst_audio::AudioChannelPosition::FrontLeft,
-        gst_audio::AudioChannelPosition::FrontCenter,
-        gst_audio::AudioChannelPosition::FrontRight,
-        gst_audio::AudioChannelPosition::RearLeft,
-        gst_audio::AudioChannelPosition::RearRight,
-        gst_audio::AudioChannelPosition::Invalid,
-        gst_audio::AudioChannelPosition::Invalid,
-        gst_audio::AudioChannelPosition::Invalid,
-    ],
-    [
-        gst_audio::AudioChannelPosition::FrontLeft,
-        gst_audio::AudioChannelPosition::FrontCenter,
-        gst_audio::AudioChannelPosition::FrontRight,
-        gst_audio::AudioChannelPosition::RearLeft,
-        gst_audio::AudioChannelPosition::RearRight,
-        gst_audio::AudioChannelPosition::Lfe1,
-        gst_audio::AudioChannelPosition::Invalid,
-        gst_audio::AudioChannelPosition::Invalid,
-    ],
-    // FIXME: 7/8 channel layouts are not defined in the FLAC specs
-    [
-        gst_audio::AudioChannelPosition::FrontLeft,
-        gst_audio::AudioChannelPosition::FrontCenter,
-        gst_audio::AudioChannelPosition::FrontRight,
-        gst_audio::AudioChannelPosition::SideLeft,
-        gst_audio::AudioChannelPosition::SideRight,
-        gst_audio::AudioChannelPosition::RearCenter,
-        gst_audio::AudioChannelPosition::Lfe1,
-        gst_audio::AudioChannelPosition::Invalid,
-    ],
-    [
-        gst_audio::AudioChannelPosition::FrontLeft,
-        gst_audio::AudioChannelPosition::FrontCenter,
-        gst_audio::AudioChannelPosition::FrontRight,
-        gst_audio::AudioChannelPosition::SideLeft,
-        gst_audio::AudioChannelPosition::SideRight,
-        gst_audio::AudioChannelPosition::RearLeft,
-        gst_audio::AudioChannelPosition::RearRight,
-        gst_audio::AudioChannelPosition::Lfe1,
-    ],
-];
+/// Returns the positions of the channels of `channel_mask`, if it is set and has `channels`
+/// channels.
+fn mask_positions(
+    channel_mask: u64,
+    channels: usize,
+) -> Option<[gst_audio::AudioChannelPosition; 8]> {
+    let mut positions = [gst_audio::AudioChannelPosition::Invalid; 8];
+    if channel_mask == 0
+        || channel_mask.count_ones() as usize != channels
+        || gst_audio::AudioChannelPosition::positions_from_mask(
+            channel_mask,
+            &mut positions[..channels],
+        )
+        .is_err()
+    {
+        return None;
+    }
+
+    Some(positions)
+}
@@ -11,13 +11,36 @@
 use gst::glib;
 use gst::prelude::*;
 
+mod decoder;
 mod imp;
+mod tags;
+mod workers;
+
+/// How frames that fail to decode are handled.
+#[derive(Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstClaxonDecConcealment")]
+pub enum Concealment {
+    #[default]
+    #[enum_value(name = "None: Fail with a decode error", nick = "none")]
+    None,
+    #[enum_value(name = "Silence: Output silence", nick = "silence")]
+    Silence,
+    #[enum_value(
+        name = "Repeat: Repeat the previous frame, or output silence",
+        nick = "repeat"
+    )]
+    Repeat,
+}
 
 glib::wrapper! {
     pub struct ClaxonDec(ObjectSubclass<imp::ClaxonDec>) @extends gst_audio::AudioDecoder, gst::Element, gst::Object;
 }
 
 pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    #[cfg(feature = "doc")]
+    Concealment::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+
     gst::Element::register(
         Some(plugin),
         "claxondec",
@@ -0,0 +1,337 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Conversion of FLAC metadata blocks to tags and TOCs.
+//!
+//! See <https://www.xiph.org/vorbis/doc/v-comment.html> for the Vorbis comment format and the
+//! field names, and <https://xiph.org/flac/format.html> for the other blocks.
+
+use gst::glib;
+use gst::prelude::*;
+
+use crate::flac;
+
+/// Reads a little endian length prefixed string, as used in Vorbis comments.
+fn read_string<'a>(data: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let len = u32::from_le_bytes(data.get(..4)?.try_into().unwrap()) as usize;
+    let s = data.get(4..4 + len)?;
+    *data = &data[4 + len..];
+    Some(s)
+}
+
+/// Parses the content of a VORBIS_COMMENT metadata block, without the block header, into the
+/// vendor string and the comments.
+pub fn parse_vorbis_comment(mut data: &[u8]) -> Option<(String, Vec<String>)> {
+    let vendor = String::from_utf8_lossy(read_string(&mut data)?).into_owned();
+
+    let n_comments = u32::from_le_bytes(data.get(..4)?.try_into().unwrap());
+    data = &data[4..];
+
+    // Don't trust the count for the allocation
+    let mut comments = Vec::new();
+    for _ in 0..n_comments {
+        comments.push(String::from_utf8_lossy(read_string(&mut data)?).into_owned());
+    }
+
+    Some((vendor, comments))
+}
+
+/// Parses a "number" or "number/total" field as used for track and disc numbers.
+fn parse_number(value: &str) -> (Option<u32>, Option<u32>) {
+    let mut parts = value.splitn(2, '/');
+    let number = parts.next().and_then(|n| n.trim().parse().ok());
+    let total = parts.next().and_then(|n| n.trim().parse().ok());
+
+    (number, total)
+}
+
+/// Parses a ReplayGain gain in dB or peak value, with or without the unit.
+fn parse_replaygain(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let value = if value.to_ascii_lowercase().ends_with("db") {
+        &value[..value.len() - 2]
+    } else {
+        value
+    };
+
+    value.trim().parse::<f64>().ok().filter(|v| v.is_finite())
+}
+
+/// Adds a ReplayGain field as tag `T`, or as extended comment if it is invalid.
+fn add_replaygain<T>(tags: &mut gst::TagListRef, value: &str, comment: &str)
+where
+    T: for<'a> gst::tags::Tag<'a, TagType = f64>,
+{
+    match parse_replaygain(value) {
+        Some(value) => tags.add::<T>(&value, gst::TagMergeMode::Replace),
+        None => tags.add::<gst::tags::ExtendedComment>(&comment, gst::TagMergeMode::Append),
+    }
+}
+
+/// Adds a single `FIELD=value` comment to the tag list.
+///
+/// Unknown fields are kept as extended comments.
+fn add_comment(tags: &mut gst::TagListRef, comment: &str) {
+    use gst::tags::*;
+
+    let Some((field, value)) = comment.split_once('=') else {
+        return;
+    };
+    if value.is_empty() {
+        return;
+    }
+
+    let mode = gst::TagMergeMode::Append;
+    match field.to_ascii_uppercase().as_str() {
+        "TITLE" => tags.add::<Title>(&value, mode),
+        "VERSION" => tags.add::<Version>(&value, mode),
+        "ALBUM" => tags.add::<Album>(&value, mode),
+        "ARTIST" => tags.add::<Artist>(&value, mode),
+        "ALBUMARTIST" | "ALBUM ARTIST" => tags.add::<AlbumArtist>(&value, mode),
+        "PERFORMER" => tags.add::<Performer>(&value, mode),
+        "COMPOSER" => tags.add::<Composer>(&value, mode),
+        "COPYRIGHT" => tags.add::<Copyright>(&value, mode),
+        "LICENSE" => tags.add::<License>(&value, mode),
+        "ORGANIZATION" => tags.add::<Organization>(&value, mode),
+        "DESCRIPTION" => tags.add::<Description>(&value, mode),
+        "COMMENT" => tags.add::<Comment>(&value, mode),
+        "GENRE" => tags.add::<Genre>(&value, mode),
+        "CONTACT" => tags.add::<Contact>(&value, mode),
+        "ISRC" => tags.add::<Isrc>(&value, mode),
+        "LOCATION" => tags.add::<Location>(&value, mode),
+        "LANGUAGE" => tags.add::<LanguageCode>(&value, mode),
+        "DATE" => match gst::DateTime::from_iso8601_string(value.trim()) {
+            Ok(date) => tags.add::<DateTime>(&date, gst::TagMergeMode::Replace),
+            Err(_) => tags.add::<ExtendedComment>(&comment, mode),
+        },
+        "TRACKNUMBER" => {
+            let (number, total) = parse_number(value);
+            if let Some(number) = number {
+                tags.add::<TrackNumber>(&number, gst::TagMergeMode::Replace);
+            }
+            if let Some(total) = total {
+                tags.add::<TrackCount>(&total, gst::TagMergeMode::Replace);
+            }
+        }
+        "TRACKTOTAL" | "TOTALTRACKS" => {
+            if let Ok(total) = value.trim().parse::<u32>() {
+                tags.add::<TrackCount>(&total, gst::TagMergeMode::Replace);
+            }
+        }
+        "DISCNUMBER" => {
+            let (number, total) = parse_number(value);
+            if let Some(number) = number {
+                tags.add::<AlbumVolumeNumber>(&number, gst::TagMergeMode::Replace);
+            }
+            if let Some(total) = total {
+                tags.add::<AlbumVolumeCount>(&total, gst::TagMergeMode::Replace);
+            }
+        }
+        "DISCTOTAL" | "TOTALDISCS" => {
+            if let Ok(total) = value.trim().parse::<u32>() {
+                tags.add::<AlbumVolumeCount>(&total, gst::TagMergeMode::Replace);
+            }
+        }
+        "REPLAYGAIN_TRACK_GAIN" => add_replaygain::<TrackGain>(tags, value, comment),
+        "REPLAYGAIN_TRACK_PEAK" => add_replaygain::<TrackPeak>(tags, value, comment),
+        "REPLAYGAIN_ALBUM_GAIN" => add_replaygain::<AlbumGain>(tags, value, comment),
+        "REPLAYGAIN_ALBUM_PEAK" => add_replaygain::<AlbumPeak>(tags, value, comment),
+        "REPLAYGAIN_REFERENCE_LOUDNESS" => add_replaygain::<ReferenceLevel>(tags, value, comment),
+        _ => tags.add::<ExtendedComment>(&comment, mode),
+    }
+}
+
+/// Converts the content of a VORBIS_COMMENT metadata block, without the block header, to a tag
+/// list.
+pub fn vorbis_comment_to_tags(data: &[u8]) -> Option<gst::TagList> {
+    let (vendor, comments) = parse_vorbis_comment(data)?;
+
+    let mut tags = gst::TagList::new();
+    {
+        let tags = tags.get_mut().unwrap();
+        if !vendor.is_empty() {
+            tags.add::<gst::tags::Encoder>(&vendor.as_str(), gst::TagMergeMode::Replace);
+        }
+        for comment in &comments {
+            add_comment(tags, comment);
+        }
+    }
+
+    Some(tags)
+}
+
+/// Returns the linear factor for applying the ReplayGain from the tags, using the track gain if
+/// there is one and the album gain otherwise.
+///
+/// The factor is limited so that the corresponding peak doesn't exceed full scale.
+pub fn replaygain_factor(tags: &gst::TagListRef) -> Option<f64> {
+    use gst::tags::*;
+
+    let (gain, peak) = match tags.get::<TrackGain>() {
+        Some(gain) => (gain.get(), tags.get::<TrackPeak>()),
+        None => (tags.get::<AlbumGain>()?.get(), tags.get::<AlbumPeak>()),
+    };
+
+    let factor = 10f64.powf(gain / 20.0);
+    match peak.map(|peak| peak.get()) {
+        Some(peak) if peak > 0.0 => Some(factor.min(1.0 / peak)),
+        _ => Some(factor),
+    }
+}
+
+/// Reads a big endian `u32`, as used in PICTURE blocks.
+fn read_u32(data: &mut &[u8]) -> Option<u32> {
+    let value = u32::from_be_bytes(data.get(..4)?.try_into().unwrap());
+    *data = &data[4..];
+    Some(value)
+}
+
+/// Reads a big endian length prefixed byte string, as used in PICTURE blocks.
+fn read_bytes<'a>(data: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let len = read_u32(data)? as usize;
+    let bytes = data.get(..len)?;
+    *data = &data[len..];
+    Some(bytes)
+}
+
+/// Returns the `GstTagImageType` value for an ID3v2 picture type, if the type is registered.
+///
+/// The enum is defined by the tag library, which might not be loaded.
+fn image_type_value(picture_type: u32) -> Option<glib::SendValue> {
+    // The image types start at the front cover, which is 3 in ID3v2. Everything before and
+    // unknown types are undefined
+    let image_type = if (3..=20).contains(&picture_type) {
+        picture_type as i32 - 2
+    } else {
+        0
+    };
+
+    let class = glib::EnumClass::with_type(glib::Type::from_name("GstTagImageType")?)?;
+    let value = class.to_value(image_type)?;
+
+    // SAFETY: Enum values are always `Send`
+    Some(unsafe { glib::SendValue::unsafe_from(value.into_raw()) })
+}
+
+/// Converts the content of a PICTURE metadata block, without the block header, to a tag list with
+/// an image or preview image sample.
+pub fn picture_to_tags(mut data: &[u8]) -> Option<gst::TagList> {
+    let picture_type = read_u32(&mut data)?;
+    let mime_type = std::str::from_utf8(read_bytes(&mut data)?).ok()?;
+    let _description = read_bytes(&mut data)?;
+    // Width, height, color depth and number of colors
+    data = data.get(16..)?;
+    let picture = read_bytes(&mut data)?;
+
+    // The picture is only linked by an URL
+    if mime_type == "-->" || picture.is_empty() {
+        return None;
+    }
+
+    let caps = if mime_type.is_empty() {
+        gst::Caps::builder("image/jpeg").build()
+    } else {
+        mime_type.to_ascii_lowercase().parse::<gst::Caps>().ok()?
+    };
+
+    let mut builder = gst::Sample::builder()
+        .buffer(&gst::Buffer::from_slice(picture.to_vec()))
+        .caps(&caps);
+    // File icons are exposed as preview image without image type
+    let is_icon = picture_type == 1 || picture_type == 2;
+    if !is_icon {
+        if let Some(image_type) = image_type_value(picture_type) {
+            builder = builder.info(
+                gst::Structure::builder("GstTagImageInfo")
+                    .field_value("image-type", image_type)
+                    .build(),
+            );
+        }
+    }
+    let sample = builder.build();
+
+    let mut tags = gst::TagList::new();
+    {
+        let tags = tags.get_mut().unwrap();
+        if is_icon {
+            tags.add::<gst::tags::PreviewImage>(&sample, gst::TagMergeMode::Append);
+        } else {
+            tags.add::<gst::tags::Image>(&sample, gst::TagMergeMode::Append);
+        }
+    }
+
+    Some(tags)
+}
+
+/// Converts the tracks of a CUESHEET metadata block to a TOC with one entry per track.
+///
+/// Each track starts at its index point 1 if it has one, and ends where the next track or the
+/// lead-out starts.
+pub fn cuesheet_to_toc(tracks: &[flac::CueSheetTrack], rate: u32) -> Option<gst::Toc> {
+    let (lead_out, tracks) = tracks.split_last()?;
+    if tracks.is_empty() || rate == 0 {
+        return None;
+    }
+
+    let to_time = |samples: u64| {
+        samples
+            .mul_div_floor(*gst::ClockTime::SECOND, rate as u64)
+            .and_then(|time| i64::try_from(time).ok())
+            .unwrap_or(-1)
+    };
+    let track_start = |track: &flac::CueSheetTrack| {
+        let index = track
+            .indices
+            .iter()
+            .find(|(_, number)| *number == 1)
+            .map_or(0, |(offset, _)| *offset);
+        track.offset + index
+    };
+
+    let mut toc = gst::Toc::new(gst::TocScope::Global);
+    {
+        let toc = toc.get_mut().unwrap();
+        for (i, track) in tracks.iter().enumerate() {
+            let stop = tracks.get(i + 1).unwrap_or(lead_out).offset;
+
+            let mut entry = gst::TocEntry::new(
+                gst::TocEntryType::Track,
+                &format!("track-{:02}", track.number),
+            );
+            {
+                let entry = entry.get_mut().unwrap();
+                entry.set_start_stop_times(to_time(track_start(track)), to_time(stop));
+
+                let mut tags = gst::TagList::new();
+                {
+                    let tags = tags.get_mut().unwrap();
+                    tags.add::<gst::tags::TrackNumber>(
+                        &(track.number as u32),
+                        gst::TagMergeMode::Replace,
+                    );
+                    tags.add::<gst::tags::TrackCount>(
+                        &(tracks.len() as u32),
+                        gst::TagMergeMode::Replace,
+                    );
+                    if !track.isrc.is_empty() {
+                        tags.add::<gst::tags::Isrc>(
+                            &track.isrc.as_str(),
+                            gst::TagMergeMode::Replace,
+                        );
+                    }
+                }
+                entry.set_tags(tags);
+            }
+            toc.append_entry(entry);
+        }
+    }
+
+    Some(toc)
+}
@@ -0,0 +1,164 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Worker threads decoding frames in parallel.
+//!
+//! FLAC frames are independent of each other, so they can be decoded in any order. The results
+//! are returned in the order the frames were submitted.
+
+use std::collections::VecDeque;
+use std::io::Cursor;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+use super::decoder::{self, Block};
+use crate::flac;
+
+/// Decoded block, and the number of bytes of the frame that were used
+pub type DecodeResult = (Result<Option<Block>, claxon::Error>, u64);
+
+/// Decodes a single frame, reusing `buffer` for the decoded samples.
+///
+/// `bits_per_sample` from the STREAMINFO is used for frames that don't contain their sample size.
+pub fn decode_frame(data: &[u8], bits_per_sample: Option<u32>, buffer: Vec<i32>) -> DecodeResult {
+    // claxon rejects both the 32 bit sample size and frames without sample size
+    if flac::FrameHeader::parse(data)
+        .is_some_and(|header| header.bits_per_sample.map_or(true, |bits| bits == 32))
+    {
+        return decoder::decode_frame(data, bits_per_sample, buffer);
+    }
+
+    let mut cursor = Cursor::new(data);
+    let mut reader = claxon::frame::FrameReader::new(&mut cursor);
+    let result = reader
+        .read_next_or_eof(buffer)
+        .map(|block| block.map(Block::from));
+
+    (result, cursor.position())
+}
+
+struct Job {
+    data: Vec<u8>,
+    bits_per_sample: Option<u32>,
+    buffer: Vec<i32>,
+    result: mpsc::Sender<(Vec<u8>, DecodeResult)>,
+}
+
+pub struct Workers {
+    jobs: Option<mpsc::Sender<Job>>,
+    threads: Vec<thread::JoinHandle<()>>,
+    /// Results of the submitted frames and their timestamps, in submission order
+    pending: VecDeque<(
+        mpsc::Receiver<(Vec<u8>, DecodeResult)>,
+        Option<gst::ClockTime>,
+    )>,
+}
+
+impl Workers {
+    pub fn new(n_threads: usize) -> std::io::Result<Self> {
+        let (jobs, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let mut workers = Workers {
+            jobs: Some(jobs),
+            threads: Vec::with_capacity(n_threads),
+            pending: VecDeque::new(),
+        };
+
+        for i in 0..n_threads {
+            let receiver = receiver.clone();
+            let thread = thread::Builder::new()
+                .name(format!("claxondec-{i}"))
+                .spawn(move || loop {
+                    // The lock is only held while waiting for the next job
+                    let job = match receiver.lock() {
+                        Ok(receiver) => receiver.recv(),
+                        Err(_) => break,
+                    };
+                    let Ok(job) = job else {
+                        break;
+                    };
+
+                    let result = decode_frame(&job.data, job.bits_per_sample, job.buffer);
+                    // The frame is gone if it was flushed in the meantime
+                    let _ = job.result.send((job.data, result));
+                })?;
+            workers.threads.push(thread);
+        }
+
+        Ok(workers)
+    }
+
+    pub fn n_threads(&self) -> usize {
+        self.threads.len()
+    }
+
+    pub fn n_pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Queues `data` for decoding into `buffer`, see [`decode_frame`]. The input timestamp `pts`
+    /// is returned with the result.
+    ///
+    /// Fails if all threads stopped, e.g. because decoding panicked.
+    pub fn submit(
+        &mut self,
+        data: Vec<u8>,
+        bits_per_sample: Option<u32>,
+        buffer: Vec<i32>,
+        pts: Option<gst::ClockTime>,
+    ) -> Result<(), &'static str> {
+        let (result, receiver) = mpsc::channel();
+        self.jobs
+            .as_ref()
+            .ok_or("decoding threads stopped")?
+            .send(Job {
+                data,
+                bits_per_sample,
+                buffer,
+                result,
+            })
+            .map_err(|_| "decoding threads stopped")?;
+        self.pending.push_back((receiver, pts));
+
+        Ok(())
+    }
+
+    /// Waits for the oldest submitted frame to be decoded and returns its data, timestamp and the
+    /// result, or `None` if no frame is pending.
+    ///
+    /// Fails if the thread decoding the frame panicked.
+    #[allow(clippy::type_complexity)]
+    pub fn next(
+        &mut self,
+    ) -> Result<Option<(Vec<u8>, Option<gst::ClockTime>, DecodeResult)>, &'static str> {
+        let Some((receiver, pts)) = self.pending.pop_front() else {
+            return Ok(None);
+        };
+        let (data, result) = receiver.recv().map_err(|_| "decoding thread panicked")?;
+
+        Ok(Some((data, pts, result)))
+    }
+
+    /// Discards the results of all submitted frames.
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+}
+
+impl Drop for Workers {
+    fn drop(&mut self) {
+        // Lets the threads finish once the remaining jobs are done
+        self.jobs = None;
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
@@ -0,0 +1,491 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! FLAC frame encoder using the fixed linear predictors and Rice coding of the residual.
+//!
+//! See <https://xiph.org/flac/format.html> for the details of the format.
+
+use crate::flac::{crc8, Crc16};
+
+const MAX_FIXED_ORDER: usize = 4;
+const MAX_PARTITION_ORDER: u32 = 8;
+/// Largest Rice parameter of the 4 bit parameter coding method, 15 is the escape code
+const MAX_RICE_PARAMETER: u32 = 14;
+/// Largest Rice parameter of the 5 bit parameter coding method, 31 is the escape code
+const MAX_RICE2_PARAMETER: u32 = 30;
+
+/// Writes big endian bit fields.
+#[derive(Default)]
+struct BitWriter {
+    data: Vec<u8>,
+    /// Bits that don't form a complete byte yet
+    acc: u64,
+    bits: u32,
+}
+
+impl BitWriter {
+    /// Writes the lowest `bits` bits of `value`, at most 32.
+    fn write(&mut self, value: u64, bits: u32) {
+        debug_assert!(bits <= 32);
+        if bits == 0 {
+            return;
+        }
+
+        self.acc = (self.acc << bits) | (value & ((1 << bits) - 1));
+        self.bits += bits;
+        while self.bits >= 8 {
+            self.bits -= 8;
+            self.data.push((self.acc >> self.bits) as u8);
+        }
+        self.acc &= (1 << self.bits) - 1;
+    }
+
+    fn write_signed(&mut self, value: i64, bits: u32) {
+        self.write(value as u64, bits);
+    }
+
+    fn write_unary(&mut self, mut zeros: u64) {
+        while zeros > 0 {
+            let n = zeros.min(32);
+            self.write(0, n as u32);
+            zeros -= n;
+        }
+        self.write(1, 1);
+    }
+
+    /// Writes the frame or sample number in the UTF-8 like coding of frame headers.
+    fn write_utf8(&mut self, value: u64) {
+        if value < 0x80 {
+            self.write(value, 8);
+            return;
+        }
+
+        let bits = 64 - value.leading_zeros();
+        let mut len = 2;
+        while bits > (7 - len) + 6 * (len - 1) {
+            len += 1;
+        }
+
+        let prefix = (0xff00u64 >> len) & 0xff;
+        self.write(prefix | (value >> (6 * (len - 1))), 8);
+        for i in (0..len - 1).rev() {
+            self.write(0x80 | ((value >> (6 * i)) & 0x3f), 8);
+        }
+    }
+
+    fn align(&mut self) {
+        if self.bits > 0 {
+            self.write(0, 8 - self.bits);
+        }
+    }
+}
+
+/// Rice coding of the residual of a subframe.
+struct Rice {
+    partition_order: u32,
+    parameters: Vec<u32>,
+}
+
+impl Rice {
+    fn parameter_bits(&self) -> u32 {
+        if self.parameters.iter().any(|k| *k > MAX_RICE_PARAMETER) {
+            5
+        } else {
+            4
+        }
+    }
+}
+
+enum Subframe {
+    Constant,
+    Verbatim,
+    Fixed {
+        order: usize,
+        residual: Vec<i64>,
+        rice: Rice,
+    },
+}
+
+/// Encoding of one channel with the number of bits it needs.
+struct Plan {
+    subframe: Subframe,
+    bits: u64,
+}
+
+fn zigzag(residual: i64) -> u64 {
+    ((residual << 1) ^ (residual >> 63)) as u64
+}
+
+fn fixed_residual(samples: &[i64], order: usize) -> Vec<i64> {
+    let s = samples;
+    (order..s.len())
+        .map(|i| match order {
+            0 => s[i],
+            1 => s[i] - s[i - 1],
+            2 => s[i] - 2 * s[i - 1] + s[i - 2],
+            3 => s[i] - 3 * s[i - 1] + 3 * s[i - 2] - s[i - 3],
+            _ => s[i] - 4 * s[i - 1] + 6 * s[i - 2] - 4 * s[i - 3] + s[i - 4],
+        })
+        .collect()
+}
+
+/// Returns the best Rice parameter for the values and the number of bits they need with it.
+fn rice_parameter(values: &[u64]) -> (u32, u64) {
+    let count = values.len() as u64;
+    if count == 0 {
+        return (0, 0);
+    }
+
+    let cost = |k: u32| count * (k as u64 + 1) + values.iter().map(|u| u >> k).sum::<u64>();
+
+    let mean = values.iter().sum::<u64>() / count;
+    let estimate = if mean > 0 {
+        63 - mean.leading_zeros()
+    } else {
+        0
+    };
+
+    (estimate.saturating_sub(1)..=estimate + 1)
+        .filter(|k| *k <= MAX_RICE2_PARAMETER)
+        .map(|k| (k, cost(k)))
+        .min_by_key(|(_, bits)| *bits)
+        .unwrap()
+}
+
+/// Finds the partition order and Rice parameters that need the fewest bits for the residual.
+fn plan_rice(residual: &[u64], block_size: usize, order: usize) -> (Rice, u64) {
+    let mut best: Option<(Rice, u64)> = None;
+
+    for partition_order in 0..=MAX_PARTITION_ORDER {
+        let partitions = 1 << partition_order;
+        if block_size % partitions != 0 || block_size / partitions <= order {
+            break;
+        }
+        let partition_size = block_size / partitions;
+
+        let mut parameters = Vec::with_capacity(partitions);
+        let mut bits = 0;
+        let mut start = 0;
+        for partition in 0..partitions {
+            let len = if partition == 0 {
+                partition_size - order
+            } else {
+                partition_size
+            };
+            let (k, partition_bits) = rice_parameter(&residual[start..start + len]);
+            parameters.push(k);
+            bits += partition_bits;
+            start += len;
+        }
+
+        let rice = Rice {
+            partition_order,
+            parameters,
+        };
+        bits += 2 + 4 + partitions as u64 * rice.parameter_bits() as u64;
+
+        if best
+            .as_ref()
+            .map_or(true, |(_, best_bits)| bits < *best_bits)
+        {
+            best = Some((rice, bits));
+        }
+    }
+
+    best.unwrap()
+}
+
+fn plan_subframe(samples: &[i64], bits_per_sample: u32) -> Plan {
+    // Subframe header
+    let header_bits = 8;
+
+    if samples.iter().all(|s| *s == samples[0]) {
+        return Plan {
+            subframe: Subframe::Constant,
+            bits: header_bits + bits_per_sample as u64,
+        };
+    }
+
+    let mut best = Plan {
+        subframe: Subframe::Verbatim,
+        bits: header_bits + samples.len() as u64 * bits_per_sample as u64,
+    };
+
+    for order in 0..=MAX_FIXED_ORDER.min(samples.len() - 1) {
+        let residual = fixed_residual(samples, order);
+        // The residual has to fit into 32 bits
+        if residual
+            .iter()
+            .any(|r| *r > i32::MAX as i64 || *r < -(i32::MAX as i64))
+        {
+            continue;
+        }
+
+        let zigzagged = residual.iter().map(|r| zigzag(*r)).collect::<Vec<_>>();
+        let (rice, residual_bits) = plan_rice(&zigzagged, samples.len(), order);
+        let bits = header_bits + order as u64 * bits_per_sample as u64 + residual_bits;
+
+        if bits < best.bits {
+            best = Plan {
+                subframe: Subframe::Fixed {
+                    order,
+                    residual,
+                    rice,
+                },
+                bits,
+            };
+        }
+    }
+
+    best
+}
+
+fn write_subframe(w: &mut BitWriter, samples: &[i64], bits_per_sample: u32, subframe: &Subframe) {
+    match subframe {
+        Subframe::Constant => {
+            w.write(0b0000_0000, 8);
+            w.write_signed(samples[0], bits_per_sample);
+        }
+        Subframe::Verbatim => {
+            w.write(0b0000_0010, 8);
+            for s in samples {
+                w.write_signed(*s, bits_per_sample);
+            }
+        }
+        Subframe::Fixed {
+            order,
+            residual,
+            rice,
+        } => {
+            w.write(0b0001_0000 | (*order as u64) << 1, 8);
+            for s in &samples[..*order] {
+                w.write_signed(*s, bits_per_sample);
+            }
+
+            let parameter_bits = rice.parameter_bits();
+            w.write(if parameter_bits == 4 { 0b00 } else { 0b01 }, 2);
+            w.write(rice.partition_order as u64, 4);
+
+            let partition_size = samples.len() >> rice.partition_order;
+            let mut start = 0;
+            for (partition, k) in rice.parameters.iter().enumerate() {
+                let len = if partition == 0 {
+                    partition_size - order
+                } else {
+                    partition_size
+                };
+
+                w.write(*k as u64, parameter_bits);
+                for r in &residual[start..start + len] {
+                    let u = zigzag(*r);
+                    w.write_unary(u >> k);
+                    w.write(u, *k);
+                }
+                start += len;
+            }
+        }
+    }
+}
+
+fn block_size_code(block_size: u32) -> (u64, Option<(u64, u32)>) {
+    match block_size {
+        192 => (1, None),
+        576 | 1152 | 2304 | 4608 => (2 + (block_size / 576).trailing_zeros() as u64, None),
+        256 | 512 | 1024 | 2048 | 4096 | 8192 | 16384 | 32768 => {
+            (8 + (block_size / 256).trailing_zeros() as u64, None)
+        }
+        1..=256 => (6, Some((block_size as u64 - 1, 8))),
+        _ => (7, Some((block_size as u64 - 1, 16))),
+    }
+}
+
+fn sample_rate_code(sample_rate: u32) -> (u64, Option<(u64, u32)>) {
+    match sample_rate {
+        88_200 => (1, None),
+        176_400 => (2, None),
+        192_000 => (3, None),
+        8_000 => (4, None),
+        16_000 => (5, None),
+        22_050 => (6, None),
+        24_000 => (7, None),
+        32_000 => (8, None),
+        44_100 => (9, None),
+        48_000 => (10, None),
+        96_000 => (11, None),
+        r if r % 1000 == 0 && r / 1000 <= 0xff => (12, Some((r as u64 / 1000, 8))),
+        r if r <= 0xffff => (13, Some((r as u64, 16))),
+        r if r % 10 == 0 && r / 10 <= 0xffff => (14, Some((r as u64 / 10, 16))),
+        // Taken from the STREAMINFO
+        _ => (0, None),
+    }
+}
+
+fn sample_size_code(bits_per_sample: u32) -> u64 {
+    match bits_per_sample {
+        8 => 1,
+        12 => 2,
+        16 => 4,
+        20 => 5,
+        24 => 6,
+        32 => 7,
+        // Taken from the STREAMINFO
+        _ => 0,
+    }
+}
+
+/// Encodes one frame of a stream with a fixed block size.
+///
+/// `channels` contains the samples of each channel, which all have the same length.
+pub fn encode_frame(
+    frame_number: u64,
+    sample_rate: u32,
+    bits_per_sample: u32,
+    channels: &[Vec<i64>],
+) -> Vec<u8> {
+    let mut channel_assignment = channels.len() as u64 - 1;
+    let mut subframes = channels
+        .iter()
+        .map(|samples| {
+            (
+                samples.as_slice(),
+                bits_per_sample,
+                plan_subframe(samples, bits_per_sample),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    // Stereo decorrelation, the side channel needs one more bit which 32 bit samples don't have
+    let side: Vec<i64>;
+    let mid: Vec<i64>;
+    if channels.len() == 2 && bits_per_sample < 32 {
+        let (left, right) = (&channels[0], &channels[1]);
+        side = left.iter().zip(right).map(|(l, r)| l - r).collect();
+        mid = left.iter().zip(right).map(|(l, r)| (l + r) >> 1).collect();
+
+        let side_plan = plan_subframe(&side, bits_per_sample + 1);
+        let mid_plan = plan_subframe(&mid, bits_per_sample);
+
+        let (left_bits, right_bits) = (subframes[0].2.bits, subframes[1].2.bits);
+        let candidates = [
+            (0b0001, left_bits + right_bits),
+            (0b1000, left_bits + side_plan.bits),
+            (0b1001, side_plan.bits + right_bits),
+            (0b1010, mid_plan.bits + side_plan.bits),
+        ];
+        channel_assignment = candidates.iter().min_by_key(|(_, bits)| *bits).unwrap().0;
+
+        match channel_assignment {
+            0b1000 => subframes[1] = (&side, bits_per_sample + 1, side_plan),
+            0b1001 => subframes[0] = (&side, bits_per_sample + 1, side_plan),
+            0b1010 => {
+                subframes[0] = (&mid, bits_per_sample, mid_plan);
+                subframes[1] = (&side, bits_per_sample + 1, side_plan);
+            }
+            _ => (),
+        }
+    }
+
+    let mut w = BitWriter::default();
+
+    let block_size = channels[0].len() as u32;
+    let (block_size_code, block_size_extra) = block_size_code(block_size);
+    let (sample_rate_code, sample_rate_extra) = sample_rate_code(sample_rate);
+
+    // Sync code and fixed block size
+    w.write(0xfff8, 16);
+    w.write(block_size_code, 4);
+    w.write(sample_rate_code, 4);
+    w.write(channel_assignment, 4);
+    w.write(sample_size_code(bits_per_sample), 3);
+    w.write(0, 1);
+    w.write_utf8(frame_number);
+    if let Some((value, bits)) = block_size_extra {
+        w.write(value, bits);
+    }
+    if let Some((value, bits)) = sample_rate_extra {
+        w.write(value, bits);
+    }
+    let crc = crc8(&w.data);
+    w.write(crc as u64, 8);
+
+    for (samples, bits, plan) in &subframes {
+        write_subframe(&mut w, samples, *bits, &plan.subframe);
+    }
+    w.align();
+
+    let mut crc = Crc16::default();
+    crc.update(&w.data);
+    w.write(crc.value() as u64, 16);
+
+    w.data
+}
+
+fn metadata_block(block_type: u8, is_last: bool, data: &[u8]) -> Vec<u8> {
+    let mut block = Vec::with_capacity(4 + data.len());
+    block.push(block_type | if is_last { 0x80 } else { 0x00 });
+    block.extend_from_slice(&(data.len() as u32).to_be_bytes()[1..]);
+    block.extend_from_slice(data);
+    block
+}
+
+/// Properties of the encoded stream that are only known once all frames are encoded.
+///
+/// Zero values are stored as unknown in the STREAMINFO.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StreamStats {
+    pub min_frame_size: u32,
+    pub max_frame_size: u32,
+    /// Total number of samples per channel
+    pub total_samples: u64,
+    /// MD5 of the unencoded samples
+    pub md5: [u8; 16],
+}
+
+/// Returns the STREAMINFO metadata block including its header.
+pub fn streaminfo_block(
+    block_size: u32,
+    sample_rate: u32,
+    channels: u32,
+    bits_per_sample: u32,
+    stats: &StreamStats,
+    is_last: bool,
+) -> Vec<u8> {
+    let mut w = BitWriter::default();
+
+    w.write(block_size as u64, 16);
+    w.write(block_size as u64, 16);
+    w.write(stats.min_frame_size as u64, 24);
+    w.write(stats.max_frame_size as u64, 24);
+    w.write(sample_rate as u64, 20);
+    w.write(channels as u64 - 1, 3);
+    w.write(bits_per_sample as u64 - 1, 5);
+    w.write(stats.total_samples >> 32, 4);
+    w.write(stats.total_samples, 32);
+    for chunk in stats.md5.chunks_exact(4) {
+        w.write(u32::from_be_bytes(chunk.try_into().unwrap()) as u64, 32);
+    }
+
+    metadata_block(0, is_last, &w.data)
+}
+
+/// Returns the VORBIS_COMMENT metadata block including its header.
+pub fn vorbis_comment_block(vendor: &str, comments: &[String], is_last: bool) -> Vec<u8> {
+    let mut data = Vec::new();
+
+    data.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    data.extend_from_slice(vendor.as_bytes());
+    data.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+    for comment in comments {
+        data.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+        data.extend_from_slice(comment.as_bytes());
+    }
+
+    metadata_block(4, is_last, &data)
+}
@@ -0,0 +1,432 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_audio::prelude::*;
+use gst_audio::subclass::prelude::*;
+
+use std::sync::Mutex;
+
+use atomic_refcell::AtomicRefCell;
+
+use byte_slice_cast::*;
+
+use once_cell::sync::Lazy;
+
+use super::encoder;
+use crate::flac;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "claxonenc",
+        gst::DebugColorFlags::empty(),
+        Some("Claxon FLAC encoder"),
+    )
+});
+
+const DEFAULT_BLOCK_SIZE: u32 = 4096;
+
+const VENDOR: &str = concat!("gst-plugin-claxon ", env!("CARGO_PKG_VERSION"));
+
+/// Offset of the STREAMINFO block in the identification header, which is the first buffer
+const STREAMINFO_OFFSET: u64 =
+    (flac::OGG_IDENT_MARKER.len() + 4 + flac::STREAM_MARKER.len()) as u64;
+
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    block_size: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            block_size: DEFAULT_BLOCK_SIZE,
+        }
+    }
+}
+
+struct State {
+    info: gst_audio::AudioInfo,
+    /// Output channel of each input channel, if the input has a different channel order
+    reorder_map: Option<Vec<usize>>,
+    frame_number: u64,
+    /// Frame sizes and number of samples encoded so far, the MD5 is only set at EOS
+    stats: encoder::StreamStats,
+    md5: glib::Checksum,
+    /// Set while draining at EOS, when the STREAMINFO is updated
+    eos: bool,
+}
+
+#[derive(Default)]
+pub struct ClaxonEnc {
+    settings: Mutex<Settings>,
+    state: AtomicRefCell<Option<State>>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for ClaxonEnc {
+    const NAME: &'static str = "GstClaxonEnc";
+    type Type = super::ClaxonEnc;
+    type ParentType = gst_audio::AudioEncoder;
+}
+
+impl ObjectImpl for ClaxonEnc {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![glib::ParamSpecUInt::builder("block-size")
+                .nick("Block Size")
+                .blurb("Number of samples per channel in each frame")
+                .minimum(16)
+                .maximum(65535)
+                .default_value(DEFAULT_BLOCK_SIZE)
+                .mutable_ready()
+                .build()]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "block-size" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.block_size = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "block-size" => {
+                let settings = self.settings.lock().unwrap();
+                settings.block_size.to_value()
+            }
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for ClaxonEnc {}
+
+impl ElementImpl for ClaxonEnc {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Claxon FLAC encoder",
+                "Codec/Encoder/Audio",
+                "Lossless FLAC encoder",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let sink_caps = gst_audio::AudioCapsBuilder::new_interleaved()
+                .format_list([
+                    gst_audio::AudioFormat::S8,
+                    gst_audio::AUDIO_FORMAT_S16,
+                    gst_audio::AUDIO_FORMAT_S2432,
+                    gst_audio::AUDIO_FORMAT_S32,
+                ])
+                .rate_range(1..655_350)
+                .channels_range(1..=8)
+                .build();
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &sink_caps,
+            )
+            .unwrap();
+
+            let src_caps = gst::Caps::builder("audio/x-flac")
+                .field("framed", true)
+                .build();
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &src_caps,
+            )
+            .unwrap();
+
+            vec![sink_pad_template, src_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl AudioEncoderImpl for ClaxonEnc {
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.borrow_mut() = None;
+
+        Ok(())
+    }
+
+    fn sink_event(&self, event: gst::Event) -> bool {
+        // Remaining input is drained by the base class while handling the event
+        if let gst::EventView::Eos(_) = event.view() {
+            if let Some(state) = self.state.borrow_mut().as_mut() {
+                state.eos = true;
+            }
+        }
+
+        self.parent_sink_event(event)
+    }
+
+    fn set_format(&self, info: &gst_audio::AudioInfo) -> Result<(), gst::LoggableError> {
+        gst::debug!(CAT, imp: self, "Setting format {:?}", info);
+
+        let block_size = self.settings.lock().unwrap().block_size;
+        let channels = info.channels() as usize;
+
+        let reorder_map = match info.positions() {
+            Some(positions) if !info.is_unpositioned() => {
+                let flac_positions = &flac::CHANNEL_POSITIONS[channels - 1][..channels];
+                if positions == flac_positions {
+                    None
+                } else {
+                    let mut reorder_map = vec![0; channels];
+                    gst_audio::channel_reorder_map(positions, flac_positions, &mut reorder_map)
+                        .map_err(|_| gst::loggable_error!(CAT, "Unsupported channel positions"))?;
+                    Some(reorder_map)
+                }
+            }
+            _ => None,
+        };
+
+        let streaminfo = encoder::streaminfo_block(
+            block_size,
+            info.rate(),
+            info.channels(),
+            info.depth(),
+            &encoder::StreamStats::default(),
+            false,
+        );
+        let vorbis_comment = encoder::vorbis_comment_block(VENDOR, &[], true);
+
+        // Identification header as used in Ogg FLAC, followed by the native header
+        let ident = flac::ident_header(&streaminfo, 1);
+
+        let headers = [ident, vorbis_comment]
+            .into_iter()
+            .map(|data| {
+                let mut buffer = gst::Buffer::from_mut_slice(data);
+                buffer
+                    .get_mut()
+                    .unwrap()
+                    .set_flags(gst::BufferFlags::HEADER);
+                buffer
+            })
+            .collect::<Vec<_>>();
+
+        let caps = gst::Caps::builder("audio/x-flac")
+            .field("framed", true)
+            .field("rate", info.rate() as i32)
+            .field("channels", info.channels() as i32)
+            .field("streamheader", gst::Array::new(headers.iter().cloned()))
+            .build();
+
+        let element = self.obj();
+        element
+            .set_output_format(&caps)
+            .map_err(|_| gst::loggable_error!(CAT, "Failed to set output format"))?;
+        element.set_headers(headers);
+        element.set_frame_samples_min(block_size as i32);
+        element.set_frame_samples_max(block_size as i32);
+        element.set_hard_min(false);
+
+        *self.state.borrow_mut() = Some(State {
+            info: info.clone(),
+            reorder_map,
+            frame_number: 0,
+            stats: encoder::StreamStats::default(),
+            md5: glib::Checksum::new(glib::ChecksumType::Md5).unwrap(),
+            eos: false,
+        });
+
+        Ok(())
+    }
+
+    fn handle_frame(
+        &self,
+        buffer: Option<&gst::Buffer>,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::debug!(CAT, imp: self, "Handling buffer {:?}", buffer);
+
+        let mut state_guard = self.state.borrow_mut();
+        let state = state_guard.as_mut().ok_or(gst::FlowError::NotNegotiated)?;
+
+        let buffer = match buffer {
+            None if std::mem::take(&mut state.eos) => {
+                let streaminfo = self.final_streaminfo(state);
+                drop(state_guard);
+                return self.push_streaminfo(streaminfo);
+            }
+            None => return Ok(gst::FlowSuccess::Ok),
+            Some(buffer) => buffer,
+        };
+
+        let map = buffer.map_readable().map_err(|_| {
+            gst::element_imp_error!(self, gst::ResourceError::Read, ["Failed to map buffer"]);
+            gst::FlowError::Error
+        })?;
+
+        let samples = match state.info.format() {
+            gst_audio::AudioFormat::S8 => map.iter().map(|s| *s as i8 as i64).collect::<Vec<_>>(),
+            gst_audio::AUDIO_FORMAT_S16 => map
+                .as_slice_of::<i16>()
+                .map_err(|_| self.unaligned_buffer())?
+                .iter()
+                .map(|s| *s as i64)
+                .collect(),
+            // Only the lower 24 bits are valid
+            gst_audio::AUDIO_FORMAT_S2432 => map
+                .as_slice_of::<i32>()
+                .map_err(|_| self.unaligned_buffer())?
+                .iter()
+                .map(|s| ((s << 8) >> 8) as i64)
+                .collect(),
+            _ => map
+                .as_slice_of::<i32>()
+                .map_err(|_| self.unaligned_buffer())?
+                .iter()
+                .map(|s| *s as i64)
+                .collect(),
+        };
+        drop(map);
+
+        let n_channels = state.info.channels() as usize;
+        let n_samples = samples.len() / n_channels;
+        if n_samples == 0 {
+            return Ok(gst::FlowSuccess::Ok);
+        }
+
+        let mut channels = vec![Vec::with_capacity(n_samples); n_channels];
+        for frame in samples.chunks_exact(n_channels) {
+            for (c, sample) in frame.iter().enumerate() {
+                let c = state.reorder_map.as_ref().map_or(c, |map| map[c]);
+                channels[c].push(*sample);
+            }
+        }
+
+        let data = encoder::encode_frame(
+            state.frame_number,
+            state.info.rate(),
+            state.info.depth(),
+            &channels,
+        );
+        state.frame_number += 1;
+        update_stats(state, &channels, data.len());
+
+        gst::trace!(
+            CAT,
+            imp: self,
+            "Encoded {} samples into {} bytes",
+            n_samples,
+            data.len()
+        );
+
+        drop(state_guard);
+        self.obj()
+            .finish_frame(Some(gst::Buffer::from_mut_slice(data)), n_samples as i32)
+    }
+}
+
+impl ClaxonEnc {
+    fn unaligned_buffer(&self) -> gst::FlowError {
+        gst::element_imp_error!(
+            self,
+            gst::StreamError::Format,
+            ["Buffer size is not a multiple of the sample size"]
+        );
+        gst::FlowError::Error
+    }
+
+    /// Returns the STREAMINFO block with the statistics of all encoded frames.
+    fn final_streaminfo(&self, state: &State) -> Option<Vec<u8>> {
+        if state.frame_number == 0 {
+            return None;
+        }
+
+        let stats = encoder::StreamStats {
+            md5: state.md5.clone().digest().try_into().unwrap(),
+            ..state.stats
+        };
+        gst::debug!(CAT, imp: self, "Updating STREAMINFO with {:?}", stats);
+
+        let block_size = self.settings.lock().unwrap().block_size;
+        Some(encoder::streaminfo_block(
+            block_size,
+            state.info.rate(),
+            state.info.channels(),
+            state.info.depth(),
+            &stats,
+            false,
+        ))
+    }
+
+    /// Rewrites the STREAMINFO in the first header by seeking back to it with a byte segment, like
+    /// flacenc does at EOS. Downstream that can't seek ignores it.
+    fn push_streaminfo(
+        &self,
+        streaminfo: Option<Vec<u8>>,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let Some(streaminfo) = streaminfo else {
+            return Ok(gst::FlowSuccess::Ok);
+        };
+
+        let mut segment = gst::FormattedSegment::<gst::format::Bytes>::new();
+        segment.set_start(gst::format::Bytes::from_u64(STREAMINFO_OFFSET));
+        segment.set_time(gst::format::Bytes::from_u64(STREAMINFO_OFFSET));
+
+        let srcpad = self.obj().src_pad();
+        if !srcpad.push_event(gst::event::Segment::new(&segment)) {
+            gst::debug!(CAT, imp: self, "Downstream didn't accept the byte segment");
+        }
+
+        let mut buffer = gst::Buffer::from_mut_slice(streaminfo);
+        buffer
+            .get_mut()
+            .unwrap()
+            .set_flags(gst::BufferFlags::HEADER);
+        srcpad.push(buffer)
+    }
+}
+
+/// Adds an encoded frame to the statistics of the STREAMINFO.
+///
+/// The MD5 is calculated over the signed little endian samples with the smallest number of bytes,
+/// interleaved in the FLAC channel order.
+fn update_stats(state: &mut State, channels: &[Vec<i64>], frame_size: usize) {
+    let stats = &mut state.stats;
+    let frame_size = frame_size as u32;
+    if stats.min_frame_size == 0 || frame_size < stats.min_frame_size {
+        stats.min_frame_size = frame_size;
+    }
+    stats.max_frame_size = stats.max_frame_size.max(frame_size);
+    stats.total_samples += channels[0].len() as u64;
+
+    let bytes = (state.info.depth() as usize + 7) / 8;
+    let mut data = Vec::with_capacity(channels.len() * channels[0].len() * bytes);
+    for o in 0..channels[0].len() {
+        for channel in channels {
+            data.extend_from_slice(&channel[o].to_le_bytes()[..bytes]);
+        }
+    }
+    state.md5.update(&data);
+}
@@ -0,0 +1,50 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+/**
+ * element-claxonenc:
+ *
+ * Lossless FLAC encoder written in Rust, the counterpart of `claxondec`.
+ *
+ * Every frame is encoded with the best of the fixed linear predictors of
+ * the FLAC format, and stereo input additionally with the best channel
+ * decorrelation. The STREAMINFO and a VORBIS_COMMENT header are sent as
+ * `streamheader` in the caps and in-band before the first frame.
+ *
+ * The frame sizes, total number of samples and MD5 checksum in the
+ * STREAMINFO are only known at EOS. The STREAMINFO is then sent again after
+ * a byte segment pointing at it, so that seekable downstream elements like
+ * `filesink` can rewrite it. 32 bit input is encoded as defined in RFC 9639.
+ *
+ * ## Example launch line
+ * ```
+ * gst-launch-1.0 audiotestsrc num-buffers=100 ! claxonenc ! filesink location=test.flac
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod encoder;
+mod imp;
+
+glib::wrapper! {
+    pub struct ClaxonEnc(ObjectSubclass<imp::ClaxonEnc>) @extends gst_audio::AudioEncoder, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "claxonenc",
+        gst::Rank::MARGINAL,
+        ClaxonEnc::static_type(),
+    )
+}
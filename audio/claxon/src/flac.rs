@@ -0,0 +1,726 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Helpers for finding metadata blocks and frames in unframed FLAC streams, and definitions
+//! shared by the decoder and the encoder.
+//!
+//! See <https://xiph.org/flac/format.html> for the details of the format.
+
+// Parsing is only used by the decoder and the parser
+#![cfg_attr(
+    not(any(feature = "claxondec", feature = "flacparse")),
+    allow(dead_code)
+)]
+
+/// Stream marker at the very beginning of a FLAC stream.
+pub const STREAM_MARKER: &[u8; 4] = b"fLaC";
+
+/// Start of the identification header of the Ogg FLAC mapping.
+pub const OGG_IDENT_MARKER: &[u8; 5] = b"\x7fFLAC";
+
+/// Maximum size of a frame header, including its CRC-8.
+pub const MAX_FRAME_HEADER_SIZE: usize = 16;
+
+/// Maximum number of samples per channel in a frame.
+pub const MAX_BLOCK_SIZE: u32 = 65535;
+
+/// Header of a metadata block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MetadataBlockHeader {
+    /// Whether this is the last metadata block before the frames.
+    pub is_last: bool,
+    pub block_type: u8,
+    /// Size of the block without this 4 byte header.
+    pub length: usize,
+}
+
+impl MetadataBlockHeader {
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let data = data.get(..4)?;
+
+        Some(MetadataBlockHeader {
+            is_last: data[0] & 0x80 != 0,
+            block_type: data[0] & 0x7f,
+            length: u32::from_be_bytes([0, data[1], data[2], data[3]]) as usize,
+        })
+    }
+}
+
+/// Metadata block types.
+pub const METADATA_STREAMINFO: u8 = 0;
+pub const METADATA_APPLICATION: u8 = 2;
+pub const METADATA_SEEKTABLE: u8 = 3;
+pub const METADATA_VORBIS_COMMENT: u8 = 4;
+pub const METADATA_CUESHEET: u8 = 5;
+pub const METADATA_PICTURE: u8 = 6;
+
+/// Parsed STREAMINFO metadata block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamInfo {
+    pub min_block_size: u32,
+    pub max_block_size: u32,
+    pub sample_rate: u32,
+    pub channels: u32,
+    pub bits_per_sample: u32,
+    /// Total number of samples per channel, if known.
+    pub total_samples: Option<u64>,
+    /// MD5 of the decoded samples, if known.
+    pub md5: Option<[u8; 16]>,
+}
+
+impl StreamInfo {
+    /// Parses the STREAMINFO from the metadata block data without the block header.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let md5 = data
+            .get(18..34)
+            .map(|md5| <[u8; 16]>::try_from(md5).unwrap())
+            .filter(|md5| md5.iter().any(|b| *b != 0));
+        let data = data.get(..18)?;
+
+        let packed = u64::from_be_bytes(data[10..18].try_into().unwrap());
+        let sample_rate = (packed >> 44) as u32;
+        let total_samples = packed & 0xf_ffff_ffff;
+
+        if sample_rate == 0 {
+            return None;
+        }
+
+        Some(StreamInfo {
+            min_block_size: u16::from_be_bytes([data[0], data[1]]) as u32,
+            max_block_size: u16::from_be_bytes([data[2], data[3]]) as u32,
+            sample_rate,
+            channels: ((packed >> 41) & 0x07) as u32 + 1,
+            bits_per_sample: ((packed >> 36) & 0x1f) as u32 + 1,
+            total_samples: if total_samples == 0 {
+                None
+            } else {
+                Some(total_samples)
+            },
+            md5,
+        })
+    }
+}
+
+/// Point of a SEEKTABLE metadata block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeekPoint {
+    /// Number of the first sample in the target frame.
+    pub sample_number: u64,
+    /// Offset of the target frame from the first frame header, in bytes.
+    pub offset: u64,
+}
+
+/// Parses the seek points of a SEEKTABLE metadata block without the block header.
+///
+/// Placeholder points are skipped.
+pub fn parse_seektable(data: &[u8]) -> Vec<SeekPoint> {
+    data.chunks_exact(18)
+        .map(|point| SeekPoint {
+            sample_number: u64::from_be_bytes(point[0..8].try_into().unwrap()),
+            offset: u64::from_be_bytes(point[8..16].try_into().unwrap()),
+        })
+        .filter(|point| point.sample_number != u64::MAX)
+        .collect()
+}
+
+/// Track of a CUESHEET metadata block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CueSheetTrack {
+    /// Offset of the track from the beginning of the stream, in samples.
+    pub offset: u64,
+    /// Track number, 170 or 255 for the lead-out track.
+    pub number: u8,
+    /// International Standard Recording Code, empty if unknown.
+    pub isrc: String,
+    /// Offset and number of the index points, with the offset relative to the track.
+    pub indices: Vec<(u64, u8)>,
+}
+
+/// Parses the tracks of a CUESHEET metadata block without the block header.
+///
+/// The last track is the lead-out track.
+pub fn parse_cuesheet(data: &[u8]) -> Option<Vec<CueSheetTrack>> {
+    // Media catalog number, lead-in samples, CD flag and reserved bytes
+    let n_tracks = *data.get(395)?;
+    let mut data = &data[396..];
+
+    let mut tracks = Vec::with_capacity(n_tracks as usize);
+    for _ in 0..n_tracks {
+        let track = data.get(..36)?;
+        let n_indices = track[35] as usize;
+        let indices = data.get(36..36 + 12 * n_indices)?;
+
+        tracks.push(CueSheetTrack {
+            offset: u64::from_be_bytes(track[0..8].try_into().unwrap()),
+            number: track[8],
+            isrc: String::from_utf8_lossy(&track[9..21])
+                .trim_end_matches('\0')
+                .to_string(),
+            indices: indices
+                .chunks_exact(12)
+                .map(|index| {
+                    (
+                        u64::from_be_bytes(index[0..8].try_into().unwrap()),
+                        index[8],
+                    )
+                })
+                .collect(),
+        });
+
+        data = &data[36 + 12 * n_indices..];
+    }
+
+    Some(tracks)
+}
+
+/// Returns the first `streamheader` buffer in caps, which is the identification header of the
+/// Ogg FLAC mapping followed by the stream marker and the STREAMINFO block including its header.
+///
+/// `n_headers` is the number of header buffers that follow.
+pub fn ident_header(streaminfo_block: &[u8], n_headers: u16) -> Vec<u8> {
+    let mut ident = OGG_IDENT_MARKER.to_vec();
+    // Version 1.0 of the mapping
+    ident.extend_from_slice(&[0x01, 0x00]);
+    ident.extend_from_slice(&n_headers.to_be_bytes());
+    ident.extend_from_slice(STREAM_MARKER);
+    ident.extend_from_slice(streaminfo_block);
+    ident
+}
+
+/// Parses the identification header of the Ogg FLAC mapping.
+///
+/// Returns the number of header packets that follow, 0 if unknown, and the rest of the packet
+/// starting with the stream marker.
+pub fn parse_ident_header(data: &[u8]) -> Option<(u16, &[u8])> {
+    if data.len() < 9 + STREAM_MARKER.len() || !data.starts_with(OGG_IDENT_MARKER) {
+        return None;
+    }
+
+    // Minor versions are backwards compatible
+    if data[5] != 0x01 {
+        return None;
+    }
+
+    let n_headers = u16::from_be_bytes([data[7], data[8]]);
+    let native = &data[9..];
+    if !native.starts_with(STREAM_MARKER) {
+        return None;
+    }
+
+    Some((n_headers, native))
+}
+
+/// Parsed frame header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHeader {
+    /// Whether the frame has a variable block size, in which case `number` is the number of
+    /// the first sample instead of the frame number.
+    pub variable_block_size: bool,
+    pub block_size: u32,
+    /// Sample rate, or `None` if it has to be taken from the STREAMINFO.
+    pub sample_rate: Option<u32>,
+    pub channels: u8,
+    /// Independent channels for 0 to 7, otherwise left/side, side/right or mid/side stereo.
+    pub channel_assignment: u8,
+    /// Bits per sample, or `None` if it has to be taken from the STREAMINFO.
+    pub bits_per_sample: Option<u8>,
+    /// Frame or sample number, depending on `variable_block_size`.
+    pub number: u64,
+    /// Size of the header including its CRC-8.
+    pub length: usize,
+}
+
+impl FrameHeader {
+    /// Parses and validates the frame header at the start of `data`.
+    ///
+    /// Returns `None` if `data` doesn't start with a valid frame header, or if it is too short
+    /// to contain the whole header.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let header = Self::parse_unchecked(data)?;
+        let crc_pos = header.length - 1;
+
+        (crc8(&data[..crc_pos]) == data[crc_pos]).then_some(header)
+    }
+
+    /// Parses the frame header at the start of `data` like [`FrameHeader::parse`], but without
+    /// validating its CRC-8.
+    fn parse_unchecked(data: &[u8]) -> Option<Self> {
+        if data.len() < 4 || data[0] != 0xff || data[1] & 0xfe != 0xf8 {
+            return None;
+        }
+        let variable_block_size = data[1] & 0x01 != 0;
+
+        let block_size_code = data[2] >> 4;
+        let sample_rate_code = data[2] & 0x0f;
+        let channel_code = data[3] >> 4;
+        let sample_size_code = (data[3] >> 1) & 0x07;
+
+        if block_size_code == 0
+            || sample_rate_code == 0x0f
+            || channel_code > 10
+            || sample_size_code == 3
+            || data[3] & 0x01 != 0
+        {
+            return None;
+        }
+
+        let mut pos = 4;
+        let number = read_utf8_number(data.get(pos..)?, &mut pos)?;
+
+        let mut read_be = |len: usize| -> Option<u32> {
+            let bytes = data.get(pos..pos + len)?;
+            pos += len;
+            Some(bytes.iter().fold(0, |acc, b| (acc << 8) | *b as u32))
+        };
+
+        let block_size = match block_size_code {
+            1 => 192,
+            2..=5 => 576 << (block_size_code - 2),
+            6 => read_be(1)? + 1,
+            7 => read_be(2)? + 1,
+            _ => 256 << (block_size_code - 8),
+        };
+
+        let sample_rate = match sample_rate_code {
+            0 => None,
+            1 => Some(88_200),
+            2 => Some(176_400),
+            3 => Some(192_000),
+            4 => Some(8_000),
+            5 => Some(16_000),
+            6 => Some(22_050),
+            7 => Some(24_000),
+            8 => Some(32_000),
+            9 => Some(44_100),
+            10 => Some(48_000),
+            11 => Some(96_000),
+            12 => Some(read_be(1)? * 1000),
+            13 => Some(read_be(2)?),
+            _ => Some(read_be(2)? * 10),
+        };
+
+        let channels = match channel_code {
+            0..=7 => channel_code + 1,
+            _ => 2,
+        };
+
+        let bits_per_sample = match sample_size_code {
+            0 => None,
+            1 => Some(8),
+            2 => Some(12),
+            4 => Some(16),
+            5 => Some(20),
+            6 => Some(24),
+            _ => Some(32),
+        };
+
+        // CRC-8
+        data.get(pos)?;
+
+        Some(FrameHeader {
+            variable_block_size,
+            block_size,
+            sample_rate,
+            channels,
+            channel_assignment: channel_code,
+            bits_per_sample,
+            number,
+            length: pos + 1,
+        })
+    }
+
+    /// Returns the number of the first sample in the frame.
+    ///
+    /// `fixed_block_size` is the block size of a fixed block size stream, for which the header
+    /// contains the frame number. Only the last frame of such a stream can be shorter.
+    pub fn sample_number(&self, fixed_block_size: u32) -> u64 {
+        if self.variable_block_size {
+            self.number
+        } else {
+            self.number * fixed_block_size as u64
+        }
+    }
+}
+
+/// Reads the UTF-8 like coded frame or sample number and advances `pos` by its length.
+fn read_utf8_number(data: &[u8], pos: &mut usize) -> Option<u64> {
+    let first = *data.first()?;
+    let len = match first.leading_ones() {
+        0 => 1,
+        n @ 2..=7 => n as usize,
+        _ => return None,
+    };
+
+    let bytes = data.get(..len)?;
+    let mut number = if len == 1 {
+        first as u64
+    } else {
+        first as u64 & (0xff >> (len + 1))
+    };
+    for b in &bytes[1..] {
+        if b & 0xc0 != 0x80 {
+            return None;
+        }
+        number = (number << 6) | (b & 0x3f) as u64;
+    }
+
+    *pos += len;
+
+    Some(number)
+}
+
+/// Returns the size of the frame at the start of `data`.
+///
+/// The end of the frame is found by looking for the next valid frame header, or the stream
+/// marker of a chained stream, such that the CRC-16 of the data in between matches. If none is
+/// found and `at_eos` is set, the frame extends to the end of `data`.
+///
+/// A corrupt frame whose CRC-16 never matches ends at the first frame header after it, or at
+/// the largest possible size of the frame if there is none.
+pub fn frame_size(data: &[u8], at_eos: bool) -> Option<usize> {
+    let header = FrameHeader::parse(data)?;
+    let max_size = max_frame_size(&header);
+
+    let mut crc = Crc16::default();
+    crc.update(&data[..header.length]);
+
+    let mut checked = header.length;
+    let mut pos = header.length;
+    let mut first_boundary = None;
+    while let Some(next) = find_boundary(&data[pos..]).map(|next| pos + next) {
+        if !at_eos && data.len() < next + MAX_FRAME_HEADER_SIZE {
+            return None;
+        }
+
+        if data[next..].starts_with(STREAM_MARKER) || FrameHeader::parse(&data[next..]).is_some() {
+            crc.update(&data[checked..next]);
+            checked = next;
+            if crc.value() == 0 {
+                return Some(next);
+            }
+            if next > max_size {
+                return Some(first_boundary.unwrap_or(max_size));
+            }
+            first_boundary.get_or_insert(next);
+        }
+
+        pos = next + 1;
+    }
+
+    if at_eos {
+        Some(data.len())
+    } else if data.len() > max_size {
+        Some(first_boundary.unwrap_or(max_size))
+    } else {
+        None
+    }
+}
+
+/// Returns the largest possible size of the frame with `header`, with verbatim subframes of
+/// 33 bit samples and the largest number of wasted bits.
+fn max_frame_size(header: &FrameHeader) -> usize {
+    let subframe_size = 5 + (header.block_size as usize * 33 + 7) / 8;
+
+    header.length + header.channels as usize * subframe_size + 2
+}
+
+/// Checks the CRC-8 of the frame header and the CRC-16 of the whole frame in `data`.
+pub fn check_frame_crcs(data: &[u8]) -> Result<(), &'static str> {
+    let header = FrameHeader::parse_unchecked(data).ok_or("invalid frame header")?;
+    let crc_pos = header.length - 1;
+    if crc8(&data[..crc_pos]) != data[crc_pos] {
+        return Err("frame header CRC-8 mismatch");
+    }
+
+    let mut crc = Crc16::default();
+    crc.update(data);
+    if crc.value() != 0 {
+        return Err("frame CRC-16 mismatch");
+    }
+
+    Ok(())
+}
+
+/// Returns the position of the next potential frame sync code or stream marker in `data`.
+fn find_boundary(data: &[u8]) -> Option<usize> {
+    data.windows(2)
+        .position(|w| (w[0] == 0xff && w[1] & 0xfe == 0xf8) || w == &STREAM_MARKER[..2])
+}
+
+/// Returns the position of the next potential frame sync code in `data`.
+pub fn find_sync(data: &[u8]) -> Option<usize> {
+    data.windows(2)
+        .position(|w| w[0] == 0xff && w[1] & 0xfe == 0xf8)
+}
+
+/// Returns the position of the next valid frame header in `data`.
+pub fn find_frame(data: &[u8]) -> Option<usize> {
+    let mut pos = 0;
+    while let Some(start) = find_sync(&data[pos..]).map(|start| pos + start) {
+        if FrameHeader::parse(&data[start..]).is_some() {
+            return Some(start);
+        }
+
+        pos = start + 1;
+    }
+
+    None
+}
+
+pub fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |mut crc, b| {
+        crc ^= b;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+/// CRC-16 of a frame, which is zero when including the CRC at the end of the frame.
+#[derive(Default)]
+pub struct Crc16(u16);
+
+impl Crc16 {
+    pub fn update(&mut self, data: &[u8]) {
+        for b in data {
+            self.0 ^= (*b as u16) << 8;
+            for _ in 0..8 {
+                self.0 = if self.0 & 0x8000 != 0 {
+                    (self.0 << 1) ^ 0x8005
+                } else {
+                    self.0 << 1
+                };
+            }
+        }
+    }
+
+    pub fn value(&self) -> u16 {
+        self.0
+    }
+}
+
+// http://www.xiph.org/vorbis/doc/Vorbis_I_spec.html#x1-800004.3.9
+// http://flac.sourceforge.net/format.html#frame_header
+/// Channel positions of the FLAC channel layouts, indexed by the number of channels minus one.
+pub const CHANNEL_POSITIONS: [[gst_audio::AudioChannelPosition; 8]; 8] = [
+    [
+        gst_audio::AudioChannelPosition::Mono,
+        gst_audio::AudioChannelPosition::Invalid,
+        gst_audio::AudioChannelPosition::Invalid,
+        gst_audio::AudioChannelPosition::Invalid,
+        gst_audio::AudioChannelPosition::Invalid,
+        gst_audio::AudioChannelPosition::Invalid,
+        gst_audio::AudioChannelPosition::Invalid,
+        gst_audio::AudioChannelPosition::Invalid,
+    ],
+    [
+        gst_audio::AudioChannelPosition::FrontLeft,
+        gst_audio::AudioChannelPosition::FrontRight,
+        gst_audio::AudioChannelPosition::Invalid,
+        gst_audio::AudioChannelPosition::Invalid,
+        gst_audio::AudioChannelPosition::Invalid,
+        gst_audio::AudioChannelPosition::Invalid,
+        gst_audio::AudioChannelPosition::Invalid,
+        gst_audio::AudioChannelPosition::Invalid,
+    ],
+    [
+        gst_audio::AudioChannelPosition::FrontLeft,
+        gst_audio::AudioChannelPosition::FrontCenter,
+        gst_audio::AudioChannelPosition::FrontRight,
+        gst_audio::AudioChannelPosition::Invalid,
+        gst_audio::AudioChannelPosition::Invalid,
+        gst_audio::AudioChannelPosition::Invalid,
+        gst_audio::AudioChannelPosition::Invalid,
+        gst_audio::AudioChannelPosition::Invalid,
+    ],
+    [
+        gst_audio::AudioChannelPosition::FrontLeft,
+        gst_audio::AudioChannelPosition::FrontRight,
+        gst_audio::AudioChannelPosition::RearLeft,
+        gst_audio::AudioChannelPosition::RearRight,
+        gst_audio::AudioChannelPosition::Invalid,
+        gst_audio::AudioChannelPosition::Invalid,
+        gst_audio::AudioChannelPosition::Invalid,
+        gst_audio::AudioChannelPosition::Invalid,
+    ],
+    [
+        gst_audio::AudioChannelPosition::FrontLeft,
+        gst_audio::AudioChannelPosition::FrontCenter,
+        gst_audio::AudioChannelPosition::FrontRight,
+        gst_audio::AudioChannelPosition::RearLeft,
+        gst_audio::AudioChannelPosition::RearRight,
+        gst_audio::AudioChannelPosition::Invalid,
+        gst_audio::AudioChannelPosition::Invalid,
+        gst_audio::AudioChannelPosition::Invalid,
+    ],
+    [
+        gst_audio::AudioChannelPosition::FrontLeft,
+        gst_audio::AudioChannelPosition::FrontCenter,
+        gst_audio::AudioChannelPosition::FrontRight,
+        gst_audio::AudioChannelPosition::RearLeft,
+        gst_audio::AudioChannelPosition::RearRight,
+        gst_audio::AudioChannelPosition::Lfe1,
+        gst_audio::AudioChannelPosition::Invalid,
+        gst_audio::AudioChannelPosition::Invalid,
+    ],
+    // https://www.rfc-editor.org/rfc/rfc9639.html#name-channels-bits
+    [
+        gst_audio::AudioChannelPosition::FrontLeft,
+        gst_audio::AudioChannelPosition::FrontRight,
+        gst_audio::AudioChannelPosition::FrontCenter,
+        gst_audio::AudioChannelPosition::Lfe1,
+        gst_audio::AudioChannelPosition::RearCenter,
+        gst_audio::AudioChannelPosition::SideLeft,
+        gst_audio::AudioChannelPosition::SideRight,
+        gst_audio::AudioChannelPosition::Invalid,
+    ],
+    [
+        gst_audio::AudioChannelPosition::FrontLeft,
+        gst_audio::AudioChannelPosition::FrontRight,
+        gst_audio::AudioChannelPosition::FrontCenter,
+        gst_audio::AudioChannelPosition::Lfe1,
+        gst_audio::AudioChannelPosition::RearLeft,
+        gst_audio::AudioChannelPosition::RearRight,
+        gst_audio::AudioChannelPosition::SideLeft,
+        gst_audio::AudioChannelPosition::SideRight,
+    ],
+];
+
+/// 7 and 8 channel layouts that were used before they were defined by the FLAC specification.
+pub const LEGACY_CHANNEL_POSITIONS: [[gst_audio::AudioChannelPosition; 8]; 2] = [
+    [
+        gst_audio::AudioChannelPosition::FrontLeft,
+        gst_audio::AudioChannelPosition::FrontCenter,
+        gst_audio::AudioChannelPosition::FrontRight,
+        gst_audio::AudioChannelPosition::SideLeft,
+        gst_audio::AudioChannelPosition::SideRight,
+        gst_audio::AudioChannelPosition::RearCenter,
+        gst_audio::AudioChannelPosition::Lfe1,
+        gst_audio::AudioChannelPosition::Invalid,
+    ],
+    [
+        gst_audio::AudioChannelPosition::FrontLeft,
+        gst_audio::AudioChannelPosition::FrontCenter,
+        gst_audio::AudioChannelPosition::FrontRight,
+        gst_audio::AudioChannelPosition::SideLeft,
+        gst_audio::AudioChannelPosition::SideRight,
+        gst_audio::AudioChannelPosition::RearLeft,
+        gst_audio::AudioChannelPosition::RearRight,
+        gst_audio::AudioChannelPosition::Lfe1,
+    ],
+];
+
+/// Channel positions of the FLAC channel layout for `channels` channels, which must be between
+/// 1 and 8. `legacy` selects the layouts of `LEGACY_CHANNEL_POSITIONS` for 7 and 8 channels.
+pub fn channel_positions(
+    channels: usize,
+    legacy: bool,
+) -> &'static [gst_audio::AudioChannelPosition] {
+    if legacy && channels >= 7 {
+        &LEGACY_CHANNEL_POSITIONS[channels - 7][..channels]
+    } else {
+        &CHANNEL_POSITIONS[channels - 1][..channels]
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Position {
+    #[default]
+    StreamMarker,
+    Metadata,
+    Frames,
+}
+
+/// Splits an unframed FLAC stream into the stream marker, the metadata blocks and the frames.
+#[derive(Debug, Default)]
+pub struct Framer {
+    position: Position,
+}
+
+impl Framer {
+    /// Resets the framer to the start of a new stream.
+    pub fn reset(&mut self) {
+        self.position = Position::StreamMarker;
+    }
+
+    /// Continues with frames after a discontinuity, e.g. after seeking.
+    pub fn resync(&mut self) {
+        self.position = Position::Frames;
+    }
+
+    /// Returns the offset and size of the next packet in `data`.
+    ///
+    /// The bytes before the offset are garbage that has to be skipped. If the size is 0, no
+    /// complete packet was found yet but the garbage can already be skipped. `None` is returned
+    /// if more data is needed.
+    pub fn next_packet(&mut self, data: &[u8], at_eos: bool) -> Option<(usize, usize)> {
+        if self.position == Position::StreamMarker {
+            if data.len() < STREAM_MARKER.len() {
+                return None;
+            }
+
+            if data.starts_with(STREAM_MARKER) {
+                self.position = Position::Metadata;
+                return Some((0, STREAM_MARKER.len()));
+            }
+
+            // Stream without marker and metadata, e.g. when starting in the middle
+            self.position = Position::Frames;
+        }
+
+        if self.position == Position::Metadata {
+            let header = MetadataBlockHeader::parse(data)?;
+            let size = 4 + header.length;
+            if data.len() < size {
+                return None;
+            }
+
+            if header.is_last {
+                self.position = Position::Frames;
+            }
+
+            return Some((0, size));
+        }
+
+        // Next stream of a chained stream
+        if data.starts_with(STREAM_MARKER) {
+            self.position = Position::Metadata;
+            return Some((0, STREAM_MARKER.len()));
+        }
+
+        let mut pos = 0;
+        while let Some(start) = find_boundary(&data[pos..]).map(|start| pos + start) {
+            if !at_eos && data.len() < start + MAX_FRAME_HEADER_SIZE {
+                return Some((start, 0));
+            }
+
+            if data[start..].starts_with(STREAM_MARKER) {
+                return Some((start, 0));
+            }
+            if FrameHeader::parse(&data[start..]).is_some() {
+                return Some((start, frame_size(&data[start..], at_eos).unwrap_or(0)));
+            }
+
+            pos = start + 1;
+        }
+
+        // Everything is garbage, except for the last byte that might start the next frame
+        if at_eos {
+            Some((data.len(), 0))
+        } else {
+            Some((data.len().saturating_sub(1), 0))
+        }
+    }
+}
@@ -0,0 +1,324 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::prelude::*;
+use gst_base::subclass::prelude::*;
+
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+
+use crate::flac;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "rsflacparse",
+        gst::DebugColorFlags::empty(),
+        Some("FLAC parser"),
+    )
+});
+
+#[derive(Default)]
+struct State {
+    framer: flac::Framer,
+    /// Metadata blocks in stream order, including their headers
+    metadata_blocks: Vec<Vec<u8>>,
+    streaminfo: Option<flac::StreamInfo>,
+    caps_sent: bool,
+    /// Set when the base class drains the remaining data after an EOS event
+    eos: bool,
+}
+
+#[derive(Default)]
+pub struct FlacParse {
+    state: Mutex<State>,
+}
+
+impl FlacParse {
+    /// Whether `size` bytes from the start of `frame` reach the end of the upstream data.
+    ///
+    /// In pull mode, the base class drains the remaining data without an EOS event.
+    fn at_upstream_end(&self, frame: &gst_base::BaseParseFrame, size: usize) -> bool {
+        let Some(offset) = frame.offset() else {
+            return false;
+        };
+
+        self.obj()
+            .sink_pad()
+            .peer_query_duration::<gst::format::Bytes>()
+            .is_some_and(|duration| offset + size as u64 >= *duration)
+    }
+
+    fn handle_metadata_block(&self, state: &mut State, data: &[u8]) {
+        let Some(header) = flac::MetadataBlockHeader::parse(data) else {
+            return;
+        };
+
+        gst::debug!(
+            CAT,
+            imp: self,
+            "Metadata block of type {} with {} bytes",
+            header.block_type,
+            header.length
+        );
+
+        if header.block_type == flac::METADATA_STREAMINFO {
+            // New caps with the new headers are needed for the following frames
+            state.metadata_blocks.clear();
+            state.caps_sent = false;
+
+            state.streaminfo = flac::StreamInfo::parse(&data[4..]);
+            gst::debug!(CAT, imp: self, "Got STREAMINFO {:?}", state.streaminfo);
+
+            if let Some(total_samples) = state.streaminfo.and_then(|info| info.total_samples) {
+                let duration = total_samples
+                    .mul_div_floor(
+                        *gst::ClockTime::SECOND,
+                        state.streaminfo.unwrap().sample_rate as u64,
+                    )
+                    .map(gst::ClockTime::from_nseconds);
+                self.obj().set_duration(duration, 0);
+            }
+        }
+
+        state.metadata_blocks.push(data.to_vec());
+    }
+
+    fn caps(&self, state: &State, header: &flac::FrameHeader) -> gst::Caps {
+        let streaminfo = state.streaminfo.as_ref();
+        let rate = streaminfo.map_or(header.sample_rate.unwrap_or(0), |info| info.sample_rate);
+        let channels = streaminfo.map_or(header.channels as u32, |info| info.channels);
+
+        let mut builder = gst::Caps::builder("audio/x-flac")
+            .field("framed", true)
+            .field("rate", rate as i32)
+            .field("channels", channels as i32);
+
+        // The STREAMINFO has to be the first metadata block
+        if let Some((streaminfo, others)) = state
+            .metadata_blocks
+            .split_first()
+            .filter(|_| state.streaminfo.is_some())
+        {
+            let ident = flac::ident_header(streaminfo, others.len() as u16);
+            let headers = std::iter::once(ident)
+                .chain(others.iter().cloned())
+                .map(|data| {
+                    let mut buffer = gst::Buffer::from_mut_slice(data);
+                    buffer
+                        .get_mut()
+                        .unwrap()
+                        .set_flags(gst::BufferFlags::HEADER);
+                    buffer
+                })
+                .collect::<Vec<_>>();
+
+            builder = builder.field("streamheader", gst::Array::new(headers));
+        }
+
+        builder.build()
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for FlacParse {
+    const NAME: &'static str = "GstRsFlacParse";
+    type Type = super::FlacParse;
+    type ParentType = gst_base::BaseParse;
+}
+
+impl ObjectImpl for FlacParse {}
+
+impl GstObjectImpl for FlacParse {}
+
+impl ElementImpl for FlacParse {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "FLAC parser",
+                "Codec/Parser/Audio",
+                "Parses FLAC streams into frames",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let sink_caps = gst::Caps::builder("audio/x-flac").build();
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &sink_caps,
+            )
+            .unwrap();
+
+            let src_caps = gst::Caps::builder("audio/x-flac")
+                .field("framed", true)
+                .field("rate", gst::IntRange::new(1, 655_350))
+                .field("channels", gst::IntRange::new(1, 8))
+                .build();
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &src_caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseParseImpl for FlacParse {
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.lock().unwrap() = State::default();
+
+        self.obj().set_has_timing_info(true);
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.lock().unwrap() = State::default();
+
+        Ok(())
+    }
+
+    fn sink_event(&self, event: gst::Event) -> bool {
+        match event.view() {
+            gst::EventView::Eos(_) => self.state.lock().unwrap().eos = true,
+            gst::EventView::FlushStop(_) | gst::EventView::StreamStart(_) => {
+                self.state.lock().unwrap().eos = false
+            }
+            _ => (),
+        }
+
+        self.parent_sink_event(event)
+    }
+
+    fn handle_frame(
+        &self,
+        mut frame: gst_base::BaseParseFrame,
+    ) -> Result<(gst::FlowSuccess, u32), gst::FlowError> {
+        let input = frame.buffer().unwrap();
+        let map = input.map_readable().map_err(|_| {
+            gst::element_imp_error!(
+                self,
+                gst::CoreError::Failed,
+                ["Failed to map input buffer readable"]
+            );
+            gst::FlowError::Error
+        })?;
+
+        let mut state = self.state.lock().unwrap();
+        let mut packet = state.framer.next_packet(&map, state.eos);
+        if !state.eos
+            && packet.map_or(true, |(_, size)| size == 0)
+            && self.at_upstream_end(&frame, map.len())
+        {
+            packet = state.framer.next_packet(&map, true);
+        }
+        let Some((skip, size)) = packet else {
+            // Need more data
+            return Ok((gst::FlowSuccess::Ok, 0));
+        };
+
+        if skip != 0 {
+            gst::debug!(CAT, imp: self, "Skipping {} bytes to the next frame", skip);
+            return Ok((gst::FlowSuccess::Ok, skip as u32));
+        }
+        if size == 0 {
+            // Need more data for the frame
+            return Ok((gst::FlowSuccess::Ok, 0));
+        }
+
+        let data = &map[..size];
+        let Some(header) = flac::FrameHeader::parse(data) else {
+            // Stream marker or metadata block, sent before the first frame
+            if data != flac::STREAM_MARKER {
+                self.handle_metadata_block(&mut state, data);
+            } else if state.caps_sent {
+                gst::debug!(CAT, imp: self, "Next stream of a chained stream");
+                state.metadata_blocks.clear();
+                state.streaminfo = None;
+                state.caps_sent = false;
+            }
+            drop(state);
+            drop(map);
+
+            frame
+                .buffer_mut()
+                .unwrap()
+                .set_flags(gst::BufferFlags::HEADER);
+            frame.set_flags(gst_base::BaseParseFrameFlags::QUEUE);
+            self.obj().finish_frame(frame, size as u32)?;
+
+            return Ok((gst::FlowSuccess::Ok, 0));
+        };
+
+        let caps = if state.caps_sent {
+            None
+        } else {
+            state.caps_sent = true;
+            Some(self.caps(&state, &header))
+        };
+
+        let streaminfo = state.streaminfo;
+        let rate = header
+            .sample_rate
+            .or(streaminfo.map(|info| info.sample_rate))
+            .filter(|rate| *rate > 0);
+        let sample_number =
+            header.sample_number(streaminfo.map_or(header.block_size, |info| info.max_block_size));
+        drop(state);
+        drop(map);
+
+        if let Some(caps) = caps {
+            gst::debug!(CAT, imp: self, "Setting caps {:?}", caps);
+            self.obj()
+                .src_pad()
+                .push_event(gst::event::Caps::new(&caps));
+        }
+
+        let buffer = frame.buffer_mut().unwrap();
+        if let Some(rate) = rate {
+            let to_time = |samples: u64| {
+                samples
+                    .mul_div_floor(*gst::ClockTime::SECOND, rate as u64)
+                    .map(gst::ClockTime::from_nseconds)
+            };
+            buffer.set_pts(to_time(sample_number));
+            buffer.set_duration(to_time(header.block_size as u64));
+        }
+
+        gst::trace!(
+            CAT,
+            imp: self,
+            "Found frame of {} bytes with {} samples at sample {}",
+            size,
+            header.block_size,
+            sample_number
+        );
+
+        self.obj().finish_frame(frame, size as u32)?;
+
+        Ok((gst::FlowSuccess::Ok, 0))
+    }
+}
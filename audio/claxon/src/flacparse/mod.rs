@@ -0,0 +1,46 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+/**
+ * element-rsflacparse:
+ *
+ * Splits a FLAC byte stream into the stream marker, the metadata blocks and
+ * the frames. Frames are found by their sync code and validated with the
+ * CRCs of the frame header and of the whole frame.
+ *
+ * The headers are sent in-band before the first frame and as `streamheader`
+ * in the caps. Frames are timestamped from the frame or sample number in
+ * their header, and the duration is taken from the STREAMINFO if it contains
+ * the total number of samples.
+ *
+ * ## Example launch line
+ * ```
+ * gst-launch-1.0 filesrc location=test.flac ! rsflacparse ! claxondec ! autoaudiosink
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct FlacParse(ObjectSubclass<imp::FlacParse>) @extends gst_base::BaseParse, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "rsflacparse",
+        gst::Rank::MARGINAL,
+        FlacParse::static_type(),
+    )
+}
@@ -16,10 +16,127 @@
  */
 use gst::glib;
 
+#[cfg(feature = "claxondec")]
 mod claxondec;
+#[cfg(feature = "claxonenc")]
+mod claxonenc;
+#[cfg(any(feature = "claxondec", feature = "claxonenc", feature = "flacparse"))]
+mod flac;
+#[cfg(feature = "flacparse")]
+mod flacparse;
 
-fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
-    claxondec::register(plugin)
+/// Size of the data that is looked at for frames
+#[cfg(any(feature = "claxondec", feature = "claxonenc", feature = "flacparse"))]
+const TYPEFIND_FRAME_SIZE: u32 = 4096;
+
+#[cfg(any(feature = "claxondec", feature = "claxonenc", feature = "flacparse"))]
+fn typefind_register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    use gst::{Caps, TypeFind, TypeFindProbability};
+
+    TypeFind::register(
+        Some(plugin),
+        "rsflac_typefind",
+        gst::Rank::MARGINAL,
+        Some("flac"),
+        Some(&Caps::builder("audio/x-flac").build()),
+        |typefind| {
+            let Some(data) = typefind.peek(0, flac::STREAM_MARKER.len() as u32) else {
+                return;
+            };
+            if data == flac::STREAM_MARKER {
+                typefind.suggest(
+                    TypeFindProbability::Maximum,
+                    &Caps::builder("audio/x-flac").build(),
+                );
+                return;
+            }
+
+            // Bare frames without the stream marker and metadata blocks, which is less certain
+            // as the frame header only has an 8 bit CRC
+            let max_size = typefind.length().map_or(TYPEFIND_FRAME_SIZE, |length| {
+                length.min(TYPEFIND_FRAME_SIZE as u64) as u32
+            });
+            // Less data might be available if the length is unknown, down to the largest
+            // possible frame header
+            let Some(size) = [max_size, 512, 16]
+                .into_iter()
+                .find(|size| typefind.peek(0, *size).is_some())
+            else {
+                return;
+            };
+            let data = typefind.peek(0, size).unwrap();
+            let Some(header) = flac::FrameHeader::parse(data) else {
+                return;
+            };
+            let probability = if flac::find_frame(&data[header.length..]).is_some() {
+                TypeFindProbability::Likely
+            } else {
+                TypeFindProbability::Possible
+            };
+            typefind.suggest(probability, &Caps::builder("audio/x-flac").build());
+        },
+    )
+}
+
+fn plugin_init(_plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    #[cfg(feature = "claxondec")]
+    {
+        claxondec::register(_plugin)?;
+    }
+    #[cfg(feature = "claxonenc")]
+    {
+        claxonenc::register(_plugin)?;
+    }
+    #[cfg(feature = "flacparse")]
+    {
+        flacparse::register(_plugin)?;
+    }
+    #[cfg(any(feature = "claxondec", feature = "claxonenc", feature = "flacparse"))]
+    {
+        typefind_register(_plugin)?;
+    }
+
+    Ok(())
+}
+
+/// Build information of the plugin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PluginInfo {
+    /// Version of the plugin crate
+    pub version: &'static str,
+    /// Short git commit id the plugin was built from, `RELEASE` for release builds
+    pub commit_id: &'static str,
+    /// Date of the commit or release
+    pub release_date: &'static str,
+    /// Date the plugin was built
+    pub build_date: &'static str,
+    /// Comma separated list of the enabled Cargo features
+    pub features: &'static str,
+}
+
+/// Returns the build information of the plugin
+///
+/// This allows to verify at runtime which exact build of the plugin is used.
+pub fn plugin_info() -> PluginInfo {
+    PluginInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        commit_id: env!("COMMIT_ID"),
+        release_date: env!("BUILD_REL_DATE"),
+        build_date: env!("BUILD_DATE"),
+        features: env!("BUILD_FEATURES"),
+    }
+}
+
+/// Registers the plugin from C when it is linked statically into the application
+///
+/// Unlike `gst_plugin_claxon_register()` this reports whether registration succeeded. Declared
+/// in `gstclaxon.h`.
+#[cfg(feature = "capi")]
+#[no_mangle]
+pub extern "C" fn gst_plugin_claxon_register_static() -> glib::ffi::gboolean {
+    use glib::translate::IntoGlib;
+
+    plugin_register_static().is_ok().into_glib()
 }
 
 gst::plugin_define!(
@@ -8,8 +8,11 @@
 //
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
+use gst::glib;
 use gst::prelude::*;
 
+use byte_slice_cast::*;
+
 fn init() {
     use std::sync::Once;
     static INIT: Once = Once::new();
@@ -59,44 +62,2055 @@ fn test_stereo_s32() {
     );
 }
 
-fn do_test(data: &'static [u8], packet_sizes: &[usize], decoded_samples: &[usize]) -> gst::Caps {
-    let packet_offsets = packet_sizes
+#[test]
+fn test_golden_mono_s16() {
+    let data = include_bytes!("test_mono_s16.flac");
+    let wav = include_bytes!("test_mono_s16.wav");
+
+    do_golden_test(data, &[4, 38, 66, 18], wav);
+}
+
+#[test]
+fn test_golden_stereo_s32() {
+    let data = include_bytes!("test_stereo_s32.flac");
+    // 24 bit samples, decoded as S24_32
+    let wav = include_bytes!("test_stereo_s32.wav");
+
+    do_golden_test(data, &[4, 38, 17465], wav);
+}
+
+/// Decodes the packets and compares the output with the samples of the golden WAV file, which
+/// was created with the claxon reference decoder.
+fn do_golden_test(data: &'static [u8], packet_sizes: &[usize], wav: &[u8]) {
+    init();
+
+    let mut h = gst_check::Harness::new("claxondec");
+    h.play();
+    h.set_src_caps(
+        gst::Caps::builder("audio/x-flac")
+            .field("framed", true)
+            .build(),
+    );
+
+    gst_plugin_test_utils::push_packets(&mut h, data, packet_sizes).unwrap();
+    h.push_event(gst::event::Eos::new());
+
+    // Lossless, so no tolerance
+    let wav = gst_plugin_test_utils::Wav::from_bytes(wav).unwrap();
+    gst_plugin_test_utils::assert_decoded_eq(&mut h, &wav, 0);
+}
+
+#[test]
+fn test_channel_mask() {
+    init();
+
+    let mut h = gst_check::Harness::new("claxondec");
+    h.element().unwrap().set_property("channel-mask", 0x30u64);
+    h.play();
+    h.set_src_caps(gst::Caps::builder("audio/x-flac").build());
+
+    let data = include_bytes!("test_stereo_s32.flac");
+    h.push(gst::Buffer::from_slice(data.to_vec())).unwrap();
+    h.push_event(gst::event::Eos::new());
+    assert_eq!(gst_plugin_test_utils::pull_all(&mut h).len(), 1);
+
+    // Rear left and rear right instead of the FLAC stereo layout
+    assert_eq!(
+        h.sinkpad()
+            .expect("harness has no sinkpad")
+            .current_caps()
+            .expect("pad has no caps"),
+        gst_audio::AudioCapsBuilder::new_interleaved()
+            .format(gst_audio::AUDIO_FORMAT_S2432)
+            .rate(44100)
+            .channels(2)
+            .channel_mask(0x30)
+            .build()
+    );
+}
+
+#[test]
+fn test_channel_layouts() {
+    init();
+
+    let file = include_bytes!("test_mono_s16.flac");
+
+    let negotiate = |channels: u8, legacy: bool| {
+        // Replace the number of channels in the STREAMINFO
+        let mut streaminfo = file[4..42].to_vec();
+        streaminfo[16] = (streaminfo[16] & 0xF1) | ((channels - 1) << 1);
+
+        let mut h = gst_check::Harness::new("claxondec");
+        h.element()
+            .unwrap()
+            .set_property("legacy-channel-layout", legacy);
+        h.play();
+        h.set_src_caps(
+            gst::Caps::builder("audio/x-flac")
+                .field("framed", true)
+                .build(),
+        );
+        h.push(gst::Buffer::from_slice(file[..4].to_vec())).unwrap();
+        h.push(gst::Buffer::from_mut_slice(streaminfo)).unwrap();
+
+        h.sinkpad()
+            .expect("harness has no sinkpad")
+            .current_caps()
+            .expect("pad has no caps")
+    };
+
+    // Both layouts have the same channels, in a different order
+    for legacy in [false, true] {
+        assert_eq!(
+            negotiate(7, legacy),
+            gst_audio::AudioCapsBuilder::new_interleaved()
+                .format(gst_audio::AUDIO_FORMAT_S16)
+                .rate(44100)
+                .channels(7)
+                .channel_mask(0xD0F)
+                .build()
+        );
+        assert_eq!(
+            negotiate(8, legacy),
+            gst_audio::AudioCapsBuilder::new_interleaved()
+                .format(gst_audio::AUDIO_FORMAT_S16)
+                .rate(44100)
+                .channels(8)
+                .channel_mask(0xC3F)
+                .build()
+        );
+    }
+}
+
+#[test]
+fn test_legacy_channel_order() {
+    init();
+
+    // 7 channels of 16 samples, each channel's samples are its number * 1000 + the sample number
+    let data = include_bytes!("test_7ch_s16.flac");
+
+    let decode = |legacy: bool| {
+        let mut h = gst_check::Harness::new("claxondec");
+        h.element()
+            .unwrap()
+            .set_property("legacy-channel-layout", legacy);
+        h.play();
+        h.set_src_caps(gst::Caps::builder("audio/x-flac").build());
+        h.push(gst::Buffer::from_slice(data)).unwrap();
+        h.push_event(gst::event::Eos::new());
+
+        let buffers = gst_plugin_test_utils::pull_all(&mut h);
+        assert_eq!(buffers.len(), 1);
+        let map = buffers[0].map_readable().unwrap();
+        map.as_slice_of::<i16>().unwrap()[..7].to_vec()
+    };
+
+    // FL, FR, FC, LFE1, RC, SL, SR
+    assert_eq!(decode(false), [1000, 2000, 3000, 4000, 5000, 6000, 7000]);
+    // FL, FC, FR, SL, SR, RC, LFE1 reordered to the above
+    assert_eq!(decode(true), [1000, 3000, 2000, 7000, 6000, 4000, 5000]);
+}
+
+#[test]
+fn test_check_crc() {
+    init();
+
+    // Flip a bit in the middle of the only frame
+    let mut data = include_bytes!("test_mono_s16.flac").to_vec();
+    data[120] ^= 0x01;
+
+    let mut h = gst_check::Harness::new("claxondec");
+    h.element().unwrap().set_property("check-crc", true);
+    let bus = gst::Bus::new();
+    h.element().unwrap().set_bus(Some(&bus));
+    h.play();
+    h.set_src_caps(
+        gst::Caps::builder("audio/x-flac")
+            .field("framed", true)
+            .build(),
+    );
+
+    let mut offset = 0;
+    for size in [4, 38, 66, 18] {
+        h.push(gst::Buffer::from_slice(
+            data[offset..offset + size].to_vec(),
+        ))
+        .unwrap();
+        offset += size;
+    }
+    h.push_event(gst::event::Eos::new());
+
+    assert!(gst_plugin_test_utils::pull_all(&mut h).is_empty());
+
+    let msg = bus
         .iter()
-        .scan(0, |state, &size| {
-            *state += size;
-            Some(*state)
-        })
-        .collect::<Vec<usize>>();
+        .find(|msg| msg.type_() == gst::MessageType::Warning)
+        .expect("no warning posted");
+    let gst::MessageView::Warning(warning) = msg.view() else {
+        unreachable!();
+    };
+    assert!(warning.error().matches(gst::StreamError::Decode));
+}
+
+#[test]
+fn test_check_md5() {
+    init();
+
+    // 16 samples of (i - 8) * 250, which are hashed as 16 bit little endian
+    let mut md5 = glib::Checksum::new(glib::ChecksumType::Md5).unwrap();
+    for i in 0..16i16 {
+        md5.update(&((i - 8) * 250).to_le_bytes());
+    }
+    let md5 = md5.digest();
+
+    let check = |md5: &[u8]| {
+        // The MD5 is at the end of the STREAMINFO
+        let mut data = include_bytes!("test_mono_s12.flac").to_vec();
+        data[26..42].copy_from_slice(md5);
+
+        let mut h = gst_check::Harness::new("claxondec");
+        h.element().unwrap().set_property("check-md5", true);
+        let bus = gst::Bus::new();
+        h.element().unwrap().set_bus(Some(&bus));
+        h.play();
+        h.set_src_caps(gst::Caps::builder("audio/x-flac").build());
+        h.push(gst::Buffer::from_mut_slice(data)).unwrap();
+        h.push_event(gst::event::Eos::new());
+        assert_eq!(gst_plugin_test_utils::pull_all(&mut h).len(), 1);
+
+        bus.iter()
+            .any(|msg| msg.type_() == gst::MessageType::Warning)
+    };
 
+    assert!(!check(&md5));
+
+    let mut wrong_md5 = md5.clone();
+    wrong_md5[0] ^= 0x01;
+    assert!(check(&wrong_md5));
+}
+
+#[test]
+fn test_streaminfo_property() {
     init();
 
+    let mut data = include_bytes!("test_mono_s12.flac").to_vec();
+    // The MD5 is at the end of the STREAMINFO
+    for (i, b) in data[26..42].iter_mut().enumerate() {
+        *b = i as u8;
+    }
+
     let mut h = gst_check::Harness::new("claxondec");
+    let element = h.element().unwrap();
+    assert!(element
+        .property::<Option<gst::Structure>>("streaminfo")
+        .is_none());
+
     h.play();
+    h.set_src_caps(gst::Caps::builder("audio/x-flac").build());
+    h.push(gst::Buffer::from_mut_slice(data)).unwrap();
+    h.push_event(gst::event::Eos::new());
+    assert_eq!(gst_plugin_test_utils::pull_all(&mut h).len(), 1);
 
-    let caps = gst::Caps::builder("audio/x-flac")
-        .field("framed", true)
+    let s = element
+        .property::<Option<gst::Structure>>("streaminfo")
+        .unwrap();
+    assert_eq!(s.name(), "streaminfo");
+    assert_eq!(s.get::<u32>("rate").unwrap(), 44_100);
+    assert_eq!(s.get::<u32>("channels").unwrap(), 1);
+    assert_eq!(s.get::<u32>("depth").unwrap(), 12);
+    assert_eq!(s.get::<u32>("min-block-size").unwrap(), 16);
+    assert_eq!(s.get::<u32>("max-block-size").unwrap(), 16);
+    assert_eq!(s.get::<u64>("total-samples").unwrap(), 16);
+    assert_eq!(
+        s.get::<&str>("md5").unwrap(),
+        "000102030405060708090a0b0c0d0e0f"
+    );
+}
+
+#[test]
+fn test_stream_caps() {
+    init();
+
+    let mut h = gst_check::Harness::new("claxondec");
+    let srcpad = h.element().unwrap().static_pad("src").unwrap();
+    let template_caps = srcpad.query_caps(None);
+    assert!(template_caps
+        .structure(0)
+        .unwrap()
+        .get::<i32>("rate")
+        .is_err());
+
+    h.play();
+    h.set_src_caps(gst::Caps::builder("audio/x-flac").build());
+    h.push(gst::Buffer::from_slice(include_bytes!(
+        "test_seektable_s16.flac"
+    )))
+    .unwrap();
+    h.push_event(gst::event::Eos::new());
+    assert_eq!(gst_plugin_test_utils::pull_all(&mut h).len(), 4);
+
+    // Only the parameters of the stream are possible now
+    let caps = srcpad.query_caps(None);
+    let expected = gst_audio::AudioCapsBuilder::new()
+        .layout_list([
+            gst_audio::AudioLayout::Interleaved,
+            gst_audio::AudioLayout::NonInterleaved,
+        ])
+        .format(gst_audio::AUDIO_FORMAT_S16)
+        .rate(16_000)
+        .channels(1)
         .build();
-    h.set_src_caps(caps);
+    assert!(caps.is_equal(&expected), "{caps:?}");
+
+    // Filters are still applied
+    let filter = gst_audio::AudioCapsBuilder::new()
+        .format(gst_audio::AudioFormat::S32le)
+        .build();
+    assert!(srcpad.query_caps(Some(&filter)).is_empty());
+}
+
+#[test]
+fn test_concealment() {
+    init();
 
-    let packet_offsets_iter = std::iter::once(&0).chain(packet_offsets.iter());
-    let skip = 0;
+    // The only frame of the file three times, with a bit flipped in the second one
+    let file = include_bytes!("test_mono_s16.flac");
+    let frame = &file[108..];
+    let mut corrupt_frame = frame.to_vec();
+    corrupt_frame[12] ^= 0x01;
+    let packets = [
+        &file[..4],
+        &file[4..42],
+        &file[42..108],
+        frame,
+        corrupt_frame.as_slice(),
+        frame,
+    ];
 
-    for (offset_start, offset_end) in packet_offsets_iter
-        .clone()
-        .skip(skip)
-        .zip(packet_offsets_iter.clone().skip(skip + 1))
-    {
-        let buffer = gst::Buffer::from_slice(&data[*offset_start..*offset_end]);
-        h.push(buffer).unwrap();
+    let decode = |concealment: &str| {
+        let mut h = gst_check::Harness::new("claxondec");
+        h.element()
+            .unwrap()
+            .set_property_from_str("concealment", concealment);
+        h.play();
+        h.set_src_caps(
+            gst::Caps::builder("audio/x-flac")
+                .field("framed", true)
+                .build(),
+        );
+
+        for packet in packets {
+            h.push(gst::Buffer::from_slice(packet.to_vec())).unwrap();
+        }
+        h.push_event(gst::event::Eos::new());
+
+        gst_plugin_test_utils::pull_all(&mut h)
+            .into_iter()
+            .map(|buffer| buffer.map_readable().unwrap().to_vec())
+            .collect::<Vec<_>>()
+    };
+
+    let buffers = decode("silence");
+    assert_eq!(buffers.len(), 3);
+    assert_eq!(buffers[1], vec![0; 8]);
+    assert_eq!(buffers[0], buffers[2]);
+
+    let buffers = decode("repeat");
+    assert_eq!(buffers.len(), 3);
+    assert_eq!(buffers[0], buffers[1]);
+    assert_eq!(buffers[0], buffers[2]);
+}
+
+#[test]
+fn test_packet_loss_concealment() {
+    init();
+
+    let file = include_bytes!("test_mono_s16.flac");
+    let frame = &file[108..];
+
+    let mut h = gst_check::Harness::new("claxondec");
+    h.element().unwrap().set_property("plc", true);
+    h.play();
+    h.set_src_caps(
+        gst::Caps::builder("audio/x-flac")
+            .field("framed", true)
+            .build(),
+    );
+
+    for packet in [&file[..4], &file[4..42], &file[42..108], frame] {
+        h.push(gst::Buffer::from_slice(packet.to_vec())).unwrap();
+    }
+    // 1ms at 44.1kHz are rounded to 44 samples
+    h.push_event(
+        gst::event::Gap::builder(gst::ClockTime::SECOND)
+            .duration(gst::ClockTime::MSECOND)
+            .build(),
+    );
+    let mut buffer = gst::Buffer::from_slice(frame.to_vec());
+    buffer
+        .get_mut()
+        .unwrap()
+        .set_flags(gst::BufferFlags::DISCONT);
+    h.push(buffer).unwrap();
+    h.push_event(gst::event::Eos::new());
+
+    let buffers = gst_plugin_test_utils::pull_all(&mut h);
+    assert_eq!(buffers.len(), 3);
+    assert_eq!(buffers[1].pts(), Some(gst::ClockTime::SECOND));
+    assert_eq!(buffers[1].map_readable().unwrap().as_slice(), &[0; 88][..]);
+    assert_eq!(
+        buffers[0].map_readable().unwrap().as_slice(),
+        buffers[2].map_readable().unwrap().as_slice()
+    );
+}
+
+#[test]
+fn test_max_errors() {
+    init();
+
+    // The only frame of the file three times, with a bit flipped in the second one
+    let file = include_bytes!("test_mono_s16.flac");
+    let frame = &file[108..];
+    let mut corrupt_frame = frame.to_vec();
+    corrupt_frame[12] ^= 0x01;
+
+    let decode = |max_errors: i32| {
+        let mut h = gst_check::Harness::new("claxondec");
+        h.element().unwrap().set_property("max-errors", max_errors);
+        h.play();
+        h.set_src_caps(
+            gst::Caps::builder("audio/x-flac")
+                .field("framed", true)
+                .build(),
+        );
+
+        for packet in [&file[..4], &file[4..42], &file[42..108], frame] {
+            h.push(gst::Buffer::from_slice(packet.to_vec())).unwrap();
+        }
+        let res = h.push(gst::Buffer::from_mut_slice(corrupt_frame.clone()));
+        if res.is_ok() {
+            h.push(gst::Buffer::from_slice(frame.to_vec())).unwrap();
+            h.push_event(gst::event::Eos::new());
+        }
+
+        (res, gst_plugin_test_utils::pull_all(&mut h).len())
+    };
+
+    // The corrupt frame is dropped and decoding continues with the next one
+    assert_eq!(decode(1), (Ok(gst::FlowSuccess::Ok), 2));
+    assert_eq!(decode(0), (Err(gst::FlowError::Error), 1));
+}
+
+#[test]
+fn test_channel_mismatch() {
+    init();
+
+    // A stereo frame in a mono stream, followed by a mono frame
+    let file = include_bytes!("test_mono_s16.flac");
+    let stereo_file = include_bytes!("test_stereo_s32.flac");
+
+    let mut h = gst_check::Harness::new("claxondec");
+    h.element().unwrap().set_property("max-errors", -1i32);
+    h.play();
+    h.set_src_caps(
+        gst::Caps::builder("audio/x-flac")
+            .field("framed", true)
+            .build(),
+    );
+
+    for packet in [
+        &file[..4],
+        &file[4..42],
+        &file[42..108],
+        &stereo_file[42..],
+        &file[108..],
+    ] {
+        h.push(gst::Buffer::from_slice(packet.to_vec())).unwrap();
+    }
+    h.push_event(gst::event::Eos::new());
+
+    // The stereo frame is dropped instead of overflowing the output buffer
+    let buffers = gst_plugin_test_utils::pull_all(&mut h);
+    assert_eq!(buffers.len(), 1);
+    assert_eq!(buffers[0].size(), 8);
+}
+
+#[test]
+fn test_stats() {
+    init();
+
+    // The only frame of the file three times
+    let file = include_bytes!("test_mono_s16.flac");
+    let mut data = file.to_vec();
+    data.extend_from_slice(&file[108..]);
+    data.extend_from_slice(&file[108..]);
+
+    let mut h = gst_check::Harness::new("claxondec");
+    let element = h.element().unwrap();
+    element.set_property("stats-interval", 1u64);
+    let bus = gst::Bus::new();
+    element.set_bus(Some(&bus));
+    h.play();
+    h.set_src_caps(gst::Caps::builder("audio/x-flac").build());
+    h.push(gst::Buffer::from_mut_slice(data)).unwrap();
+    h.push_event(gst::event::Eos::new());
+    assert_eq!(gst_plugin_test_utils::pull_all(&mut h).len(), 3);
+
+    let stats = element.property::<gst::Structure>("stats");
+    assert_eq!(stats.get::<u64>("frames-decoded").unwrap(), 3);
+    assert_eq!(stats.get::<u64>("samples-output").unwrap(), 12);
+    assert_eq!(stats.get::<u64>("decode-errors").unwrap(), 0);
+    assert_eq!(stats.get::<u32>("average-block-size").unwrap(), 4);
+    assert!(stats.get::<u64>("bitrate").unwrap() > 0);
+
+    // One message per frame
+    let messages = bus
+        .iter()
+        .filter(|msg| {
+            msg.type_() == gst::MessageType::Element
+                && msg.structure().is_some_and(|s| s.name() == "stats")
+        })
+        .count();
+    assert_eq!(messages, 3);
+}
+
+#[test]
+fn test_counters() {
+    init();
+
+    // The only frame of the file three times, with a bit flipped in the second one
+    let file = include_bytes!("test_mono_s16.flac");
+    let frame = &file[108..];
+    let mut corrupt_frame = frame.to_vec();
+    corrupt_frame[12] ^= 0x01;
+
+    let mut h = gst_check::Harness::new("claxondec");
+    let element = h.element().unwrap();
+    element.set_property("max-errors", -1i32);
+    element.set_property("notify-interval", 1u64);
+    let notified = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let notified_clone = notified.clone();
+    element.connect_notify(None, move |_, pspec| {
+        notified_clone
+            .lock()
+            .unwrap()
+            .push(pspec.name().to_string());
+    });
+    h.play();
+    h.set_src_caps(
+        gst::Caps::builder("audio/x-flac")
+            .field("framed", true)
+            .build(),
+    );
+
+    for packet in [
+        &file[..4],
+        &file[4..42],
+        &file[42..108],
+        frame,
+        corrupt_frame.as_slice(),
+        frame,
+    ] {
+        h.push(gst::Buffer::from_slice(packet.to_vec())).unwrap();
     }
+    h.push_event(gst::event::Eos::new());
+    assert_eq!(gst_plugin_test_utils::pull_all(&mut h).len(), 2);
+
+    assert_eq!(element.property::<u64>("frames-decoded"), 2);
+    assert_eq!(element.property::<u64>("frames-dropped"), 1);
+    assert_eq!(element.property::<u64>("decode-errors"), 1);
+
+    let notified = notified.lock().unwrap();
+    let count = |name: &str| notified.iter().filter(|n| *n == name).count();
+    assert_eq!(count("frames-decoded"), 2);
+    assert_eq!(count("frames-dropped"), 1);
+    assert_eq!(count("decode-errors"), 1);
+}
+
+/// Decodes a whole file pushed as a single buffer and returns the output and its caps.
+fn decode_file(data: &[u8]) -> (gst::Buffer, gst::Caps) {
+    let mut h = gst_check::Harness::new("claxondec");
+    h.play();
+    h.set_src_caps(gst::Caps::builder("audio/x-flac").build());
+    h.push(gst::Buffer::from_slice(data.to_vec())).unwrap();
+    h.push_event(gst::event::Eos::new());
+
+    let buffers = gst_plugin_test_utils::pull_all(&mut h);
+    assert_eq!(buffers.len(), 1);
+    let caps = h
+        .sinkpad()
+        .expect("harness has no sinkpad")
+        .current_caps()
+        .expect("pad has no caps");
+
+    (buffers.into_iter().next().unwrap(), caps)
+}
+
+#[test]
+fn test_mono_s12() {
+    init();
+
+    // 16 samples of (i - 8) * 250
+    let (buffer, caps) = decode_file(include_bytes!("test_mono_s12.flac"));
+
+    assert_eq!(
+        caps,
+        gst_audio::AudioCapsBuilder::new_interleaved()
+            .format(gst_audio::AUDIO_FORMAT_S16)
+            .rate(44_100)
+            .channels(1)
+            .build()
+    );
+
+    // Scaled to 16 bits
+    let map = buffer.map_readable().unwrap();
+    let samples = map.as_slice_of::<i16>().unwrap();
+    let expected = (0..16).map(|i| ((i - 8) * 250) << 4).collect::<Vec<i16>>();
+    assert_eq!(samples, expected.as_slice());
+}
+
+#[test]
+fn test_mono_s20() {
+    init();
+
+    // 16 samples of (i - 8) * 60000
+    let (buffer, caps) = decode_file(include_bytes!("test_mono_s20.flac"));
+
+    assert_eq!(
+        caps,
+        gst_audio::AudioCapsBuilder::new_interleaved()
+            .format(gst_audio::AUDIO_FORMAT_S2432)
+            .rate(44_100)
+            .channels(1)
+            .build()
+    );
+
+    // Scaled to 24 bits
+    let map = buffer.map_readable().unwrap();
+    let samples = map.as_slice_of::<i32>().unwrap();
+    let expected = (0..16)
+        .map(|i| ((i - 8) * 60000) << 4)
+        .collect::<Vec<i32>>();
+    assert_eq!(samples, expected.as_slice());
+}
+
+#[test]
+fn test_mono_s32() {
+    init();
+
+    // 16 samples of (i - 8) * 200000000 with 32 bits per sample as allowed by RFC 9639
+    let (buffer, caps) = decode_file(include_bytes!("test_mono_s32.flac"));
+
+    assert_eq!(
+        caps,
+        gst_audio::AudioCapsBuilder::new_interleaved()
+            .format(gst_audio::AUDIO_FORMAT_S32)
+            .rate(44_100)
+            .channels(1)
+            .build()
+    );
+
+    let map = buffer.map_readable().unwrap();
+    let samples = map.as_slice_of::<i32>().unwrap();
+    let expected = (0..16).map(|i| (i - 8) * 200_000_000).collect::<Vec<i32>>();
+    assert_eq!(samples, expected.as_slice());
+}
+
+#[test]
+fn test_stereo_s28() {
+    init();
+
+    // 16 samples with 28 bits per sample, which frame headers take from the STREAMINFO, coded as
+    // left and side channel
+    let (buffer, caps) = decode_file(include_bytes!("test_stereo_s28.flac"));
+
+    assert_eq!(
+        caps,
+        gst_audio::AudioCapsBuilder::new_interleaved()
+            .format(gst_audio::AUDIO_FORMAT_S32)
+            .rate(44_100)
+            .channels(2)
+            .channel_mask(0x3)
+            .build()
+    );
+
+    // Scaled to 32 bits
+    let map = buffer.map_readable().unwrap();
+    let samples = map.as_slice_of::<i32>().unwrap();
+    let expected = (0..16)
+        .flat_map(|i| {
+            let left = (i * 7919 % 61) * 3_000_000 - 90_000_000;
+            [left << 4, (left + 1000 * i) << 4]
+        })
+        .collect::<Vec<i32>>();
+    assert_eq!(samples, expected.as_slice());
+}
+
+#[test]
+fn test_unframed() {
+    init();
+
+    // Append the single frame of the file twice more to have multiple frames
+    let file = include_bytes!("test_mono_s16.flac");
+    let mut data = file.to_vec();
+    data.extend_from_slice(&file[108..]);
+    data.extend_from_slice(&file[108..]);
+
+    let mut h = gst_check::Harness::new("claxondec");
+    h.play();
+    h.set_src_caps(gst::Caps::builder("audio/x-flac").build());
+
+    // Push in small chunks that don't match the frame boundaries
+    for chunk in data.chunks(7) {
+        h.push(gst::Buffer::from_slice(chunk.to_vec())).unwrap();
+    }
+    h.push_event(gst::event::Eos::new());
+
+    let buffers = gst_plugin_test_utils::pull_all(&mut h);
+    assert_eq!(buffers.len(), 3);
+    for buffer in buffers {
+        assert_eq!(buffer.size(), 8);
+    }
+
+    assert_eq!(
+        h.sinkpad()
+            .expect("harness has no sinkpad")
+            .current_caps()
+            .expect("pad has no caps"),
+        gst_audio::AudioCapsBuilder::new_interleaved()
+            .format(gst_audio::AUDIO_FORMAT_S16)
+            .rate(44_100)
+            .channels(1)
+            .build()
+    );
+}
+
+#[test]
+fn test_unframed_leading_garbage() {
+    init();
+
+    // Garbage without anything that looks like a frame header or stream marker
+    let mut data = (0..3000).map(|i| (i % 200) as u8).collect::<Vec<_>>();
+    data.extend_from_slice(include_bytes!("test_mono_s16.flac"));
+
+    let mut h = gst_check::Harness::new("claxondec");
+    h.play();
+    h.set_src_caps(gst::Caps::builder("audio/x-flac").build());
+
+    for chunk in data.chunks(7) {
+        h.push(gst::Buffer::from_slice(chunk.to_vec())).unwrap();
+    }
+    h.push_event(gst::event::Eos::new());
+
+    let buffers = gst_plugin_test_utils::pull_all(&mut h);
+    assert_eq!(buffers.len(), 1);
+    assert_eq!(buffers[0].size(), 8);
+}
+
+#[test]
+fn test_unframed_flush_while_streaming() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    init();
+
+    let file = include_bytes!("test_mono_s16.flac");
+    let mut data = file.to_vec();
+    for _ in 0..10 {
+        data.extend_from_slice(&file[108..]);
+    }
+
+    let mut h = gst_check::Harness::new("claxondec");
+    h.play();
+    h.set_src_caps(gst::Caps::builder("audio/x-flac").build());
+
+    // Keep data flowing from another thread while flushing from this one, like a seek from the
+    // application thread would
+    let srcpad = h.srcpad().unwrap().clone();
+    let running = Arc::new(AtomicBool::new(true));
+    let streaming = std::thread::spawn({
+        let running = running.clone();
+        move || {
+            for chunk in data.chunks(7).cycle() {
+                if !running.load(Ordering::SeqCst) {
+                    break;
+                }
+                // Fails while flushing
+                let _ = srcpad.push(gst::Buffer::from_slice(chunk.to_vec()));
+            }
+        }
+    });
+
+    let sinkpad = h.element().unwrap().static_pad("sink").unwrap();
+    for _ in 0..50 {
+        std::thread::sleep(std::time::Duration::from_millis(1));
+
+        assert!(h.push_event(gst::event::FlushStart::new()));
+
+        // No data must get in between the flush stop and the new segment
+        let _stream_lock = sinkpad.stream_lock();
+        assert!(h.push_event(gst::event::FlushStop::new(true)));
+        let segment = gst::FormattedSegment::<gst::ClockTime>::new();
+        assert!(h.push_event(gst::event::Segment::new(&segment)));
+    }
+
+    running.store(false, Ordering::SeqCst);
+    streaming.join().unwrap();
+
+    // Decoding still works afterwards, starting at the next frame
+    gst_plugin_test_utils::flush_seek(&mut h, gst::ClockTime::ZERO);
+    h.push(gst::Buffer::from_slice(file[108..].to_vec()))
+        .unwrap();
+    h.push_event(gst::event::Eos::new());
+
+    let buffers = gst_plugin_test_utils::pull_all(&mut h);
+    assert_eq!(buffers.len(), 1);
+    assert_eq!(buffers[0].size(), 8);
+}
+
+#[test]
+fn test_flush() {
+    init();
+
+    let file = include_bytes!("test_mono_s16.flac");
+
+    let mut h = gst_check::Harness::new("claxondec");
+    h.play();
+    h.set_src_caps(gst::Caps::builder("audio/x-flac").build());
+
+    // Flush in the middle of the only frame, after which the stream restarts at the frame
+    h.push(gst::Buffer::from_slice(file[..116].to_vec()))
+        .unwrap();
+    gst_plugin_test_utils::flush_seek(&mut h, gst::ClockTime::ZERO);
+    h.push(gst::Buffer::from_slice(file[108..].to_vec()))
+        .unwrap();
+    h.push_event(gst::event::Eos::new());
+
+    let buffers = gst_plugin_test_utils::pull_all(&mut h);
+    assert_eq!(buffers.len(), 1);
+    assert_eq!(buffers[0].size(), 8);
+}
+
+#[test]
+fn test_chained() {
+    init();
+
+    // Stereo S24 followed by mono S16
+    let mut data = include_bytes!("test_stereo_s32.flac").to_vec();
+    data.extend_from_slice(include_bytes!("test_mono_s16.flac"));
+
+    let mut h = gst_check::Harness::new("claxondec");
+    h.play();
+    h.set_src_caps(gst::Caps::builder("audio/x-flac").build());
+    h.push(gst::Buffer::from_mut_slice(data)).unwrap();
+    h.push_event(gst::event::Eos::new());
+
+    let buffers = gst_plugin_test_utils::pull_all(&mut h);
+    assert_eq!(buffers.len(), 2);
+    assert_eq!(buffers[0].size(), 4096 * 8);
+    assert_eq!(buffers[1].size(), 4 * 2);
+
+    assert_eq!(
+        h.sinkpad()
+            .expect("harness has no sinkpad")
+            .current_caps()
+            .expect("pad has no caps"),
+        gst_audio::AudioCapsBuilder::new_interleaved()
+            .format(gst_audio::AUDIO_FORMAT_S16)
+            .rate(44_100)
+            .channels(1)
+            .build()
+    );
+}
+
+#[test]
+fn test_framed_multiple_frames() {
+    init();
+
+    // 4 frames of 16 samples, each sample is its number * 100 - 3000
+    let data = include_bytes!("test_seektable_s16.flac");
+    let first_frame_offset = 82;
+    let frame_size = 17;
+
+    let mut h = gst_check::Harness::new("claxondec");
+    h.play();
+    h.set_src_caps(
+        gst::Caps::builder("audio/x-flac")
+            .field("framed", true)
+            .build(),
+    );
+
+    for header in [&data[..4], &data[4..42], &data[42..first_frame_offset]] {
+        h.push(gst::Buffer::from_slice(header.to_vec())).unwrap();
+    }
+    // Two frames per buffer
+    for frames in data[first_frame_offset..].chunks(2 * frame_size) {
+        h.push(gst::Buffer::from_slice(frames.to_vec())).unwrap();
+    }
+    h.push_event(gst::event::Eos::new());
+
+    let buffers = gst_plugin_test_utils::pull_all(&mut h);
+    assert_eq!(buffers.len(), 4);
+
+    let mut samples = Vec::new();
+    for buffer in &buffers {
+        samples.extend_from_slice(buffer.map_readable().unwrap().as_slice_of::<i16>().unwrap());
+    }
+    let expected = (0..64).map(|i| i * 100 - 3000).collect::<Vec<i16>>();
+    assert_eq!(samples, expected);
+}
+
+#[test]
+fn test_ogg_mapping() {
+    init();
+
+    // Packets of the Ogg FLAC mapping as output by oggdemux, with the stream marker and the
+    // STREAMINFO in the identification header
+    let file = include_bytes!("test_mono_s16.flac");
+    let mut ident = b"\x7fFLAC\x01\x00\x00\x02".to_vec();
+    ident.extend_from_slice(&file[..42]);
+    let packets = [
+        ident.as_slice(),
+        &file[42..64],
+        &file[64..108],
+        &file[108..],
+        &file[108..],
+    ];
+
+    let mut h = gst_check::Harness::new("claxondec");
+    h.play();
+    h.set_src_caps(gst::Caps::builder("audio/x-flac").build());
+
+    for packet in packets {
+        h.push(gst::Buffer::from_slice(packet.to_vec())).unwrap();
+    }
+    h.push_event(gst::event::Eos::new());
+
+    let buffers = gst_plugin_test_utils::pull_all(&mut h);
+    assert_eq!(buffers.len(), 2);
+    for buffer in buffers {
+        assert_eq!(buffer.size(), 8);
+    }
+}
+
+#[test]
+fn test_codec_data() {
+    init();
+
+    let file = include_bytes!("test_mono_s16.flac");
+    // The metadata blocks after the stream marker of Matroska, or after the version and flags of
+    // the dfLa box of ISOBMFF
+    let mut dfla = vec![0; 4];
+    dfla.extend_from_slice(&file[4..108]);
+    for codec_data in [&file[..108], dfla.as_slice()] {
+        let mut h = gst_check::Harness::new("claxondec");
+        h.play();
+        h.set_src_caps(
+            gst::Caps::builder("audio/x-flac")
+                .field("codec_data", gst::Buffer::from_slice(codec_data.to_vec()))
+                .build(),
+        );
+
+        // Only frames follow
+        h.push(gst::Buffer::from_slice(&file[108..])).unwrap();
+        h.push(gst::Buffer::from_slice(&file[108..])).unwrap();
+        h.push_event(gst::event::Eos::new());
+
+        let buffers = gst_plugin_test_utils::pull_all(&mut h);
+        assert_eq!(buffers.len(), 2);
+        for buffer in buffers {
+            assert_eq!(buffer.size(), 8);
+        }
+
+        let s = h
+            .element()
+            .unwrap()
+            .property::<Option<gst::Structure>>("streaminfo")
+            .unwrap();
+        assert_eq!(s.get::<u32>("channels").unwrap(), 1);
+    }
+}
+
+#[test]
+fn test_trim_to_total_samples() {
+    init();
+
+    // Pretend that the last sample of the frame is padding
+    let mut data = include_bytes!("test_mono_s16.flac").to_vec();
+    assert_eq!(data[25], 4);
+    data[25] = 3;
+
+    let mut h = gst_check::Harness::new("claxondec");
+    h.play();
+    h.set_src_caps(gst::Caps::builder("audio/x-flac").build());
+    h.push(gst::Buffer::from_mut_slice(data)).unwrap();
+    h.push_event(gst::event::Eos::new());
+
+    let buffers = gst_plugin_test_utils::pull_all(&mut h);
+    assert_eq!(buffers.len(), 1);
+    assert_eq!(buffers[0].size(), 3 * 2);
+}
+
+#[test]
+fn test_non_interleaved() {
+    init();
+
+    let data = include_bytes!("test_stereo_s32.flac");
+    let decode = |layout| {
+        let mut h = gst_check::Harness::new("claxondec");
+        h.set_sink_caps(
+            gst_audio::AudioCapsBuilder::new()
+                .layout(layout)
+                .format(gst_audio::AUDIO_FORMAT_S2432)
+                .build(),
+        );
+        h.play();
+        h.set_src_caps(gst::Caps::builder("audio/x-flac").build());
+        h.push(gst::Buffer::from_slice(data.to_vec())).unwrap();
+        h.push_event(gst::event::Eos::new());
+
+        let buffers = gst_plugin_test_utils::pull_all(&mut h);
+        assert_eq!(buffers.len(), 1);
+        let caps = h
+            .sinkpad()
+            .expect("harness has no sinkpad")
+            .current_caps()
+            .expect("pad has no caps");
+        (buffers.into_iter().next().unwrap(), caps)
+    };
+
+    let (interleaved, caps) = decode(gst_audio::AudioLayout::Interleaved);
+    assert_eq!(
+        gst_audio::AudioInfo::from_caps(&caps).unwrap().layout(),
+        gst_audio::AudioLayout::Interleaved
+    );
+    assert!(interleaved.meta::<gst_audio::AudioMeta>().is_none());
+
+    let (planar, caps) = decode(gst_audio::AudioLayout::NonInterleaved);
+    assert_eq!(
+        gst_audio::AudioInfo::from_caps(&caps).unwrap().layout(),
+        gst_audio::AudioLayout::NonInterleaved
+    );
+    let meta = planar.meta::<gst_audio::AudioMeta>().unwrap();
+    assert_eq!(meta.samples(), 4096);
+    assert_eq!(planar.size(), interleaved.size());
+
+    let interleaved = interleaved.map_readable().unwrap();
+    let interleaved = interleaved.as_slice_of::<i32>().unwrap();
+    let planar = planar.map_readable().unwrap();
+    let planar = planar.as_slice_of::<i32>().unwrap();
+    for (i, frame) in interleaved.chunks_exact(2).enumerate() {
+        assert_eq!(frame[0], planar[i]);
+        assert_eq!(frame[1], planar[4096 + i]);
+    }
+}
+
+#[test]
+fn test_packed_s24() {
+    init();
+
+    let data = include_bytes!("test_stereo_s32.flac");
+    let decode = |formats: &[gst_audio::AudioFormat]| {
+        let mut h = gst_check::Harness::new("claxondec");
+        h.set_sink_caps(
+            gst_audio::AudioCapsBuilder::new_interleaved()
+                .format_list(formats.iter().copied())
+                .build(),
+        );
+        h.play();
+        h.set_src_caps(gst::Caps::builder("audio/x-flac").build());
+        h.push(gst::Buffer::from_slice(data.to_vec())).unwrap();
+        h.push_event(gst::event::Eos::new());
+
+        let buffers = gst_plugin_test_utils::pull_all(&mut h);
+        assert_eq!(buffers.len(), 1);
+        let caps = h
+            .sinkpad()
+            .expect("harness has no sinkpad")
+            .current_caps()
+            .expect("pad has no caps");
+        (buffers.into_iter().next().unwrap(), caps)
+    };
+
+    // Downstream lists packed samples first
+    let (packed, caps) = decode(&[gst_audio::AudioFormat::S24le, gst_audio::AUDIO_FORMAT_S2432]);
+    assert_eq!(
+        gst_audio::AudioInfo::from_caps(&caps).unwrap().format(),
+        gst_audio::AudioFormat::S24le
+    );
+
+    let (unpacked, caps) = decode(&[gst_audio::AUDIO_FORMAT_S2432, gst_audio::AudioFormat::S24le]);
+    assert_eq!(
+        gst_audio::AudioInfo::from_caps(&caps).unwrap().format(),
+        gst_audio::AUDIO_FORMAT_S2432
+    );
+
+    let packed = packed.map_readable().unwrap();
+    let unpacked = unpacked.map_readable().unwrap();
+    let unpacked = unpacked.as_slice_of::<i32>().unwrap();
+    assert_eq!(packed.len(), unpacked.len() * 3);
+    for (bytes, sample) in packed.chunks_exact(3).zip(unpacked) {
+        assert_eq!(bytes, &sample.to_le_bytes()[..3]);
+    }
+}
+
+#[test]
+fn test_float_output() {
+    init();
+
+    let data = include_bytes!("test_stereo_s32.flac");
+    let decode = |formats: &[gst_audio::AudioFormat]| {
+        let mut h = gst_check::Harness::new("claxondec");
+        h.set_sink_caps(
+            gst_audio::AudioCapsBuilder::new_interleaved()
+                .format_list(formats.iter().copied())
+                .build(),
+        );
+        h.play();
+        h.set_src_caps(gst::Caps::builder("audio/x-flac").build());
+        h.push(gst::Buffer::from_slice(data.to_vec())).unwrap();
+        h.push_event(gst::event::Eos::new());
+
+        let buffers = gst_plugin_test_utils::pull_all(&mut h);
+        assert_eq!(buffers.len(), 1);
+        buffers.into_iter().next().unwrap()
+    };
+
+    // The 24 bit samples are normalized to [-1.0, 1.0]
+    let float = decode(&[gst_audio::AUDIO_FORMAT_F32]);
+    let int = decode(&[gst_audio::AUDIO_FORMAT_S2432]);
+
+    // Integer samples are output whenever downstream accepts them, even if it lists F32 first
+    let preferred = decode(&[gst_audio::AUDIO_FORMAT_F32, gst_audio::AUDIO_FORMAT_S2432]);
+    assert_eq!(preferred.size(), int.size());
+    assert_eq!(
+        *preferred.map_readable().unwrap(),
+        *int.map_readable().unwrap()
+    );
+
+    let float = float.map_readable().unwrap();
+    let float = float.as_slice_of::<f32>().unwrap();
+    let int = int.map_readable().unwrap();
+    let int = int.as_slice_of::<i32>().unwrap();
+    assert_eq!(float.len(), int.len());
+    assert!(int.iter().any(|sample| *sample != 0));
+    for (float, int) in float.iter().zip(int) {
+        assert_eq!(*float, *int as f32 / 8_388_608.0);
+    }
+}
+
+/// Builds the content of a VORBIS_COMMENT metadata block including its header.
+fn vorbis_comment_block(vendor: &str, comments: &[&str], is_last: bool) -> Vec<u8> {
+    let mut data = Vec::new();
+    data.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    data.extend_from_slice(vendor.as_bytes());
+    data.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+    for comment in comments {
+        data.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+        data.extend_from_slice(comment.as_bytes());
+    }
+
+    let mut block = vec![if is_last { 0x84 } else { 0x04 }];
+    block.extend_from_slice(&(data.len() as u32).to_be_bytes()[1..]);
+    block.extend_from_slice(&data);
+    block
+}
+
+/// Builds a PICTURE metadata block including its header.
+fn picture_block(picture_type: u32, mime_type: &str, picture: &[u8], is_last: bool) -> Vec<u8> {
+    let mut data = Vec::new();
+    data.extend_from_slice(&picture_type.to_be_bytes());
+    data.extend_from_slice(&(mime_type.len() as u32).to_be_bytes());
+    data.extend_from_slice(mime_type.as_bytes());
+    // Empty description
+    data.extend_from_slice(&0u32.to_be_bytes());
+    // Width, height, color depth and number of colors
+    for value in [16u32, 16, 24, 0] {
+        data.extend_from_slice(&value.to_be_bytes());
+    }
+    data.extend_from_slice(&(picture.len() as u32).to_be_bytes());
+    data.extend_from_slice(picture);
+
+    let mut block = vec![if is_last { 0x86 } else { 0x06 }];
+    block.extend_from_slice(&(data.len() as u32).to_be_bytes()[1..]);
+    block.extend_from_slice(&data);
+    block
+}
+
+/// Builds a CUESHEET metadata block including its header from the offset, number and ISRC of
+/// the tracks, with the lead-out track last.
+fn cuesheet_block(tracks: &[(u64, u8, &str)], is_last: bool) -> Vec<u8> {
+    // Media catalog number, lead-in samples, CD flag and reserved bytes
+    let mut data = vec![0; 395];
+    data.push(tracks.len() as u8);
+    for (offset, number, isrc) in tracks {
+        data.extend_from_slice(&offset.to_be_bytes());
+        data.push(*number);
+        let mut isrc = isrc.as_bytes().to_vec();
+        isrc.resize(12, 0);
+        data.extend_from_slice(&isrc);
+        data.extend_from_slice(&[0; 14]);
+        // A single index point at the start of the track, except for the lead-out
+        if *number == 255 {
+            data.push(0);
+        } else {
+            data.push(1);
+            data.extend_from_slice(&0u64.to_be_bytes());
+            data.extend_from_slice(&[1, 0, 0, 0]);
+        }
+    }
+
+    let mut block = vec![if is_last { 0x85 } else { 0x05 }];
+    block.extend_from_slice(&(data.len() as u32).to_be_bytes()[1..]);
+    block.extend_from_slice(&data);
+    block
+}
+
+/// Returns the tags of all tag events received by the harness, merged.
+fn pull_tags(h: &mut gst_check::Harness) -> gst::TagList {
+    let mut tags = gst::TagList::new();
+    while let Some(event) = h.try_pull_event() {
+        if let gst::EventView::Tag(ev) = event.view() {
+            tags = tags.merge(ev.tag(), gst::TagMergeMode::Append);
+        }
+    }
+    tags
+}
+
+#[test]
+fn test_codec_tags() {
+    init();
+
+    let mut h = gst_check::Harness::new("claxondec");
+    h.play();
+    h.set_src_caps(gst::Caps::builder("audio/x-flac").build());
+    h.push(gst::Buffer::from_slice(include_bytes!(
+        "test_mono_s16.flac"
+    )))
+    .unwrap();
+    h.push_event(gst::event::Eos::new());
+    assert_eq!(gst_plugin_test_utils::pull_all(&mut h).len(), 1);
+
+    // Upstream doesn't know the size of the stream to calculate the bitrate from
+    let tags = pull_tags(&mut h);
+    assert_eq!(tags.get::<gst::tags::AudioCodec>().unwrap().get(), "FLAC");
+    assert!(tags.get::<gst::tags::Bitrate>().is_none());
+}
+
+#[test]
+fn test_vorbis_comment() {
+    init();
+
+    // Replace the Vorbis comment of the file with one containing some tags
+    let file = include_bytes!("test_mono_s16.flac");
+    let comment = vorbis_comment_block(
+        "test vendor",
+        &[
+            "TITLE=Some Title",
+            "artist=Some Artist",
+            "ALBUM=Some Album",
+            "TRACKNUMBER=3/12",
+            "FOO=bar",
+        ],
+        true,
+    );
+    let packets = [
+        &file[..4],
+        &file[4..42],
+        &file[42..64],
+        comment.as_slice(),
+        &file[108..],
+    ];
+
+    let mut h = gst_check::Harness::new("claxondec");
+    h.play();
+    h.set_src_caps(
+        gst::Caps::builder("audio/x-flac")
+            .field("framed", true)
+            .build(),
+    );
+
+    for packet in packets {
+        h.push(gst::Buffer::from_slice(packet.to_vec())).unwrap();
+    }
+    h.push_event(gst::event::Eos::new());
+
+    let buffers = gst_plugin_test_utils::pull_all(&mut h);
+    assert_eq!(buffers.len(), 1);
+
+    let tags = pull_tags(&mut h);
+    assert_eq!(tags.get::<gst::tags::Title>().unwrap().get(), "Some Title");
+    assert_eq!(
+        tags.get::<gst::tags::Artist>().unwrap().get(),
+        "Some Artist"
+    );
+    assert_eq!(tags.get::<gst::tags::Album>().unwrap().get(), "Some Album");
+    assert_eq!(tags.get::<gst::tags::TrackNumber>().unwrap().get(), 3);
+    assert_eq!(tags.get::<gst::tags::TrackCount>().unwrap().get(), 12);
+    assert_eq!(
+        tags.get::<gst::tags::ExtendedComment>().unwrap().get(),
+        "FOO=bar"
+    );
+    assert_eq!(
+        tags.get::<gst::tags::Encoder>().unwrap().get(),
+        "test vendor"
+    );
+}
+
+#[test]
+fn test_application_blocks() {
+    init();
+
+    // Two APPLICATION blocks as written for foreign metadata
+    let application_block = |data: &[u8]| {
+        let mut block = vec![0x02];
+        block.extend_from_slice(&(data.len() as u32 + 4).to_be_bytes()[1..]);
+        block.extend_from_slice(b"riff");
+        block.extend_from_slice(data);
+        block
+    };
+    let file = include_bytes!("test_mono_s16.flac");
+    let first = application_block(b"RIFF\x24\x00\x00\x00WAVE");
+    let second = application_block(b"LIST\x00\x00\x00\x00");
+    let packets = [
+        &file[..4],
+        &file[4..42],
+        &file[42..64],
+        first.as_slice(),
+        second.as_slice(),
+        &file[64..],
+    ];
+
+    let mut h = gst_check::Harness::new("claxondec");
+    h.play();
+    h.set_src_caps(
+        gst::Caps::builder("audio/x-flac")
+            .field("framed", true)
+            .build(),
+    );
+    for packet in packets {
+        h.push(gst::Buffer::from_slice(packet.to_vec())).unwrap();
+    }
+    h.push_event(gst::event::Eos::new());
+
+    assert_eq!(gst_plugin_test_utils::pull_all(&mut h).len(), 1);
+
+    let mut events = Vec::new();
+    while let Some(event) = h.try_pull_event() {
+        if let gst::EventView::CustomDownstreamSticky(ev) = event.view() {
+            events.push(ev.structure().unwrap().to_owned());
+        }
+    }
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].name(), "flac-application-blocks");
+
+    let blocks = events[0].get::<gst::Array>("blocks").unwrap();
+    let blocks = blocks
+        .iter()
+        .map(|block| block.get::<gst::Structure>().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(blocks.len(), 2);
+    for (block, data) in blocks.iter().zip([&first, &second]) {
+        assert_eq!(block.get::<&str>("id").unwrap(), "riff");
+        let buffer = block.get::<gst::Buffer>("data").unwrap();
+        assert_eq!(buffer.map_readable().unwrap().as_slice(), &data[8..]);
+    }
+}
+
+/// Decodes the 12 bit file with a Vorbis comment and ReplayGain applied.
+fn decode_replaygain(comments: &[&str]) -> (Vec<i16>, gst::TagList) {
+    // The STREAMINFO isn't the last metadata block anymore
+    let file = include_bytes!("test_mono_s12.flac");
+    let mut data = file[..42].to_vec();
+    data[4] = 0x00;
+    data.extend_from_slice(&vorbis_comment_block("test vendor", comments, true));
+    data.extend_from_slice(&file[42..]);
+
+    let mut h = gst_check::Harness::new("claxondec");
+    h.element().unwrap().set_property("apply-replaygain", true);
+    h.play();
+    h.set_src_caps(gst::Caps::builder("audio/x-flac").build());
+    h.push(gst::Buffer::from_mut_slice(data)).unwrap();
+    h.push_event(gst::event::Eos::new());
+
+    let buffers = gst_plugin_test_utils::pull_all(&mut h);
+    assert_eq!(buffers.len(), 1);
+    let map = buffers[0].map_readable().unwrap();
+    let samples = map.as_slice_of::<i16>().unwrap().to_vec();
+
+    (samples, pull_tags(&mut h))
+}
+
+#[test]
+fn test_replaygain() {
+    init();
+
+    // The track gain is preferred
+    let (samples, tags) = decode_replaygain(&[
+        "REPLAYGAIN_TRACK_GAIN=-6.0206 dB",
+        "REPLAYGAIN_TRACK_PEAK=0.976563",
+        "REPLAYGAIN_ALBUM_GAIN=+3.00 dB",
+        "replaygain_reference_loudness=89.0 dB",
+    ]);
+    assert_eq!(tags.get::<gst::tags::TrackGain>().unwrap().get(), -6.0206);
+    assert_eq!(tags.get::<gst::tags::TrackPeak>().unwrap().get(), 0.976563);
+    assert_eq!(tags.get::<gst::tags::AlbumGain>().unwrap().get(), 3.0);
+    assert_eq!(tags.get::<gst::tags::ReferenceLevel>().unwrap().get(), 89.0);
+
+    // Half of (i - 8) * 250, scaled to 16 bits
+    let expected = (0..16).map(|i| ((i - 8) * 125) << 4).collect::<Vec<i16>>();
+    assert_eq!(samples, expected);
+
+    // The gain of 20 dB is limited to 4 by the peak, and the samples are clipped to 12 bits
+    let (samples, _) =
+        decode_replaygain(&["REPLAYGAIN_ALBUM_GAIN=20 dB", "REPLAYGAIN_ALBUM_PEAK=0.25"]);
+    let expected = (0..16)
+        .map(|i| ((i - 8) * 1000).clamp(-2048, 2047) << 4)
+        .collect::<Vec<i16>>();
+    assert_eq!(samples, expected);
+}
+
+#[test]
+fn test_picture() {
+    init();
+
+    let file = include_bytes!("test_mono_s16.flac");
+    let comment = vorbis_comment_block("test vendor", &["TITLE=Some Title"], false);
+    let cover = picture_block(3, "image/png", b"cover", false);
+    let icon = picture_block(1, "image/png", b"icon", true);
+    let packets = [
+        &file[..4],
+        &file[4..42],
+        comment.as_slice(),
+        cover.as_slice(),
+        icon.as_slice(),
+        &file[108..],
+    ];
+
+    let mut h = gst_check::Harness::new("claxondec");
+    h.play();
+    h.set_src_caps(
+        gst::Caps::builder("audio/x-flac")
+            .field("framed", true)
+            .build(),
+    );
+
+    for packet in packets {
+        h.push(gst::Buffer::from_slice(packet.to_vec())).unwrap();
+    }
+    h.push_event(gst::event::Eos::new());
+
+    let buffers = gst_plugin_test_utils::pull_all(&mut h);
+    assert_eq!(buffers.len(), 1);
+
+    let tags = pull_tags(&mut h);
+    assert_eq!(tags.get::<gst::tags::Title>().unwrap().get(), "Some Title");
+
+    let image = tags.get::<gst::tags::Image>().unwrap();
+    let image = image.get();
+    assert_eq!(
+        image.caps().unwrap().structure(0).unwrap().name(),
+        "image/png"
+    );
+    assert_eq!(
+        image.buffer().unwrap().map_readable().unwrap().as_slice(),
+        b"cover"
+    );
+
+    let preview = tags.get::<gst::tags::PreviewImage>().unwrap();
+    assert_eq!(
+        preview
+            .get()
+            .buffer()
+            .unwrap()
+            .map_readable()
+            .unwrap()
+            .as_slice(),
+        b"icon"
+    );
+}
+
+#[test]
+fn test_cuesheet() {
+    init();
+
+    let file = include_bytes!("test_mono_s16.flac");
+    let cuesheet = cuesheet_block(&[(0, 1, "ABCDE1234567"), (2, 2, ""), (4, 255, "")], true);
+    let packets = [&file[..4], &file[4..42], cuesheet.as_slice(), &file[108..]];
+
+    let mut h = gst_check::Harness::new("claxondec");
+    h.play();
+    h.set_src_caps(
+        gst::Caps::builder("audio/x-flac")
+            .field("framed", true)
+            .build(),
+    );
+
+    for packet in packets {
+        h.push(gst::Buffer::from_slice(packet.to_vec())).unwrap();
+    }
+    h.push_event(gst::event::Eos::new());
+
+    let buffers = gst_plugin_test_utils::pull_all(&mut h);
+    assert_eq!(buffers.len(), 1);
+
+    let toc = std::iter::from_fn(|| h.try_pull_event())
+        .find_map(|event| match event.view() {
+            gst::EventView::Toc(ev) => Some(ev.toc_owned().0),
+            _ => None,
+        })
+        .expect("no TOC event");
+
+    let entries = toc.entries();
+    assert_eq!(entries.len(), 2);
+
+    assert_eq!(entries[0].uid(), "track-01");
+    assert_eq!(entries[0].start_stop_times(), Some((0, 45_351)));
+    let tags = entries[0].tags().unwrap();
+    assert_eq!(tags.get::<gst::tags::TrackNumber>().unwrap().get(), 1);
+    assert_eq!(tags.get::<gst::tags::Isrc>().unwrap().get(), "ABCDE1234567");
+
+    assert_eq!(entries[1].uid(), "track-02");
+    assert_eq!(entries[1].start_stop_times(), Some((45_351, 90_702)));
+    let tags = entries[1].tags().unwrap();
+    assert_eq!(tags.get::<gst::tags::TrackNumber>().unwrap().get(), 2);
+    assert!(tags.get::<gst::tags::Isrc>().is_none());
+}
+
+#[test]
+fn test_n_threads() {
+    init();
+
+    // 4 frames of 16 samples, each sample is its number * 100 - 3000
+    let data = include_bytes!("test_seektable_s16.flac");
+
+    for n_threads in [1u32, 3] {
+        let mut h = gst_check::Harness::new("claxondec");
+        h.element().unwrap().set_property("n-threads", n_threads);
+        h.play();
+        h.set_src_caps(gst::Caps::builder("audio/x-flac").build());
+        h.push(gst::Buffer::from_slice(data)).unwrap();
+        h.push_event(gst::event::Eos::new());
+
+        let buffers = gst_plugin_test_utils::pull_all(&mut h);
+        assert_eq!(buffers.len(), 4);
+
+        let mut samples = Vec::new();
+        for (i, buffer) in buffers.iter().enumerate() {
+            assert_eq!(buffer.pts(), Some(gst::ClockTime::from_mseconds(i as u64)));
+            samples.extend_from_slice(buffer.map_readable().unwrap().as_slice_of::<i16>().unwrap());
+        }
+        let expected = (0..64).map(|i| i * 100 - 3000).collect::<Vec<i16>>();
+        assert_eq!(samples, expected);
+    }
+}
+
+#[test]
+fn test_caps_change_with_pending_frames() {
+    init();
+
+    // 4 frames of 16 samples after the metadata blocks
+    let data = include_bytes!("test_seektable_s16.flac");
+    let first_frame_offset = 82;
+    let frame_size = 17;
+
+    let mut h = gst_check::Harness::new("claxondec");
+    h.element().unwrap().set_property("n-threads", 3u32);
+    h.play();
+    h.set_src_caps(
+        gst::Caps::builder("audio/x-flac")
+            .field("framed", true)
+            .build(),
+    );
+
+    for header in [&data[..4], &data[4..42], &data[42..first_frame_offset]] {
+        h.push(gst::Buffer::from_slice(header.to_vec())).unwrap();
+    }
+    for frame in data[first_frame_offset..].chunks(frame_size) {
+        h.push(gst::Buffer::from_slice(frame.to_vec())).unwrap();
+    }
+
+    // The frames that are still being decoded are output before switching to the new format
+    h.set_src_caps(
+        gst::Caps::builder("audio/x-flac")
+            .field("framed", true)
+            .field("rate", 48_000)
+            .build(),
+    );
+    h.push_event(gst::event::Eos::new());
+
+    let buffers = gst_plugin_test_utils::pull_all(&mut h);
+    assert_eq!(buffers.len(), 4);
+
+    let mut samples = Vec::new();
+    for buffer in &buffers {
+        samples.extend_from_slice(buffer.map_readable().unwrap().as_slice_of::<i16>().unwrap());
+    }
+    let expected = (0..64).map(|i| i * 100 - 3000).collect::<Vec<i16>>();
+    assert_eq!(samples, expected);
+}
+
+#[test]
+fn test_drain_at_eos() {
+    init();
+
+    // 4 frames of 16 samples, each sample is its number * 100 - 3000
+    let data = include_bytes!("test_seektable_s16.flac");
+
+    for n_threads in [1u32, 3] {
+        let mut h = gst_check::Harness::new("claxondec");
+        h.element().unwrap().set_property("n-threads", n_threads);
+        h.play();
+        h.set_src_caps(gst::Caps::builder("audio/x-flac").build());
+
+        // The end of the last frame is only known at EOS, and with multiple threads the frames
+        // before it are still being decoded then
+        for chunk in data.chunks(5) {
+            h.push(gst::Buffer::from_slice(chunk.to_vec())).unwrap();
+        }
+        h.push_event(gst::event::Eos::new());
+
+        let buffers = gst_plugin_test_utils::pull_all(&mut h);
+        assert_eq!(buffers.len(), 4);
+
+        let last = buffers.last().unwrap();
+        let map = last.map_readable().unwrap();
+        let expected = (48..64).map(|i| i * 100 - 3000).collect::<Vec<i16>>();
+        assert_eq!(map.as_slice_of::<i16>().unwrap(), expected.as_slice());
+    }
+}
+
+#[test]
+fn test_metas() {
+    init();
+
+    // 4 frames of 16 samples after the metadata blocks
+    let data = include_bytes!("test_seektable_s16.flac");
+    let first_frame_offset = 82;
+    let frame_size = 17;
+    let reference = gst::Caps::builder("timestamp/x-test").build();
+
+    let mut h = gst_check::Harness::new("claxondec");
+    h.element().unwrap().set_property("n-threads", 3u32);
+    h.play();
+    h.set_src_caps(
+        gst::Caps::builder("audio/x-flac")
+            .field("framed", true)
+            .build(),
+    );
+
+    for header in [&data[..4], &data[4..42], &data[42..first_frame_offset]] {
+        h.push(gst::Buffer::from_slice(header.to_vec())).unwrap();
+    }
+    for (i, frame) in data[first_frame_offset..].chunks(frame_size).enumerate() {
+        let mut buffer = gst::Buffer::from_slice(frame.to_vec());
+        gst::ReferenceTimestampMeta::add(
+            buffer.get_mut().unwrap(),
+            &reference,
+            gst::ClockTime::from_seconds(i as u64),
+            gst::ClockTime::NONE,
+        );
+        h.push(buffer).unwrap();
+    }
+    h.push_event(gst::event::Eos::new());
+
+    // Each output buffer has the meta of its frame, also when decoding in parallel
+    let buffers = gst_plugin_test_utils::pull_all(&mut h);
+    assert_eq!(buffers.len(), 4);
+    for (i, buffer) in buffers.iter().enumerate() {
+        let meta = buffer.meta::<gst::ReferenceTimestampMeta>().unwrap();
+        assert_eq!(meta.timestamp(), gst::ClockTime::from_seconds(i as u64));
+    }
+}
+
+#[test]
+fn test_timestamps_from_headers() {
+    init();
+
+    // 4 frames of 1ms, without the second one
+    let file = include_bytes!("test_seektable_s16.flac");
+    let first_frame_offset = 82;
+    let frame_size = 17;
+    let mut data = file[..first_frame_offset + frame_size].to_vec();
+    data.extend_from_slice(&file[first_frame_offset + 2 * frame_size..]);
+
+    let mut h = gst_check::Harness::new("claxondec");
+    h.play();
+    h.set_src_caps(gst::Caps::builder("audio/x-flac").build());
+    h.push(gst::Buffer::from_mut_slice(data)).unwrap();
+    h.push_event(gst::event::Eos::new());
+
+    // The timestamps continue after the gap instead of being interpolated
+    let buffers = gst_plugin_test_utils::pull_all(&mut h);
+    let timestamps = buffers
+        .iter()
+        .map(|buffer| {
+            (
+                buffer.pts().unwrap().mseconds(),
+                buffer.duration().unwrap().mseconds(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(timestamps, [(0, 1), (2, 1), (3, 1)]);
+}
+
+#[test]
+fn test_position_and_convert() {
+    init();
+
+    // 4 frames of 1ms at 16kHz, without the second one
+    let file = include_bytes!("test_seektable_s16.flac");
+    let first_frame_offset = 82;
+    let frame_size = 17;
+    let mut data = file[..first_frame_offset + frame_size].to_vec();
+    data.extend_from_slice(&file[first_frame_offset + 2 * frame_size..]);
+
+    let mut h = gst_check::Harness::new("claxondec");
+    let srcpad = h.element().unwrap().static_pad("src").unwrap();
+    h.play();
+    h.set_src_caps(gst::Caps::builder("audio/x-flac").build());
+    h.push(gst::Buffer::from_mut_slice(data)).unwrap();
+
+    // The end of the last frame is only known at EOS, so the third frame was the last output
+    let buffers = std::iter::from_fn(|| h.try_pull()).collect::<Vec<_>>();
+    assert_eq!(buffers.len(), 2);
+    assert_eq!(
+        srcpad.query_position::<gst::ClockTime>(),
+        Some(gst::ClockTime::from_mseconds(3))
+    );
+    assert_eq!(
+        srcpad.query_position::<gst::format::Default>(),
+        Some(gst::format::Default::from_u64(48))
+    );
+
+    assert_eq!(
+        srcpad.query_convert::<gst::ClockTime>(gst::format::Default::from_u64(8_000)),
+        Some(gst::ClockTime::from_mseconds(500))
+    );
+    assert_eq!(
+        srcpad.query_convert::<gst::format::Default>(gst::ClockTime::from_mseconds(2)),
+        Some(gst::format::Default::from_u64(32))
+    );
+}
+
+#[test]
+fn test_segment_discontinuity() {
+    init();
+
+    // 4 frames of 16 samples at 16kHz, each sample is its number * 100 - 3000
+    let data = include_bytes!("test_seektable_s16.flac");
+    let first_frame_offset = 82;
+    let frame_size = 17;
+
+    let mut h = gst_check::Harness::new("claxondec");
+    h.play();
+    h.set_src_caps(gst::Caps::builder("audio/x-flac").build());
+    h.push(gst::Buffer::from_slice(
+        data[..first_frame_offset + 2 * frame_size].to_vec(),
+    ))
+    .unwrap();
+
+    // Continue with the last frame without flushing
+    let offset = first_frame_offset + 3 * frame_size;
+    let mut segment = gst::FormattedSegment::<gst::format::Bytes>::new();
+    segment.set_start(gst::format::Bytes::from_u64(offset as u64));
+    h.push_event(gst::event::Segment::new(&segment));
+    h.push(gst::Buffer::from_slice(data[offset..].to_vec()))
+        .unwrap();
+    h.push_event(gst::event::Eos::new());
+
+    let buffers = gst_plugin_test_utils::pull_all(&mut h);
+    assert_eq!(buffers.len(), 3);
+
+    let last = buffers.last().unwrap();
+    assert_eq!(last.pts(), Some(gst::ClockTime::from_mseconds(3)));
+    let map = last.map_readable().unwrap();
+    let expected = (48..64).map(|i| i * 100 - 3000).collect::<Vec<i16>>();
+    assert_eq!(map.as_slice_of::<i16>().unwrap(), expected.as_slice());
+}
+
+#[test]
+fn test_discont() {
+    init();
+
+    // 4 frames of 16 samples at 16kHz, each sample is its number * 100 - 3000
+    let data = include_bytes!("test_seektable_s16.flac");
+    let first_frame_offset = 82;
+    let frame_size = 17;
+
+    let mut h = gst_check::Harness::new("claxondec");
+    h.play();
+    h.set_src_caps(gst::Caps::builder("audio/x-flac").build());
+    h.push(gst::Buffer::from_slice(
+        data[..first_frame_offset + 2 * frame_size + 8].to_vec(),
+    ))
+    .unwrap();
+
+    // Data was lost until the middle of the third frame
+    let mut buffer =
+        gst::Buffer::from_slice(data[first_frame_offset + 2 * frame_size + 10..].to_vec());
+    buffer
+        .get_mut()
+        .unwrap()
+        .set_flags(gst::BufferFlags::DISCONT);
+    h.push(buffer).unwrap();
+    h.push_event(gst::event::Eos::new());
+
+    // The incomplete second frame is dropped and decoding continues with the last frame
+    let buffers = gst_plugin_test_utils::pull_all(&mut h);
+    assert_eq!(buffers.len(), 2);
+
+    let last = buffers.last().unwrap();
+    assert!(last.flags().contains(gst::BufferFlags::DISCONT));
+    assert_eq!(last.pts(), Some(gst::ClockTime::from_mseconds(3)));
+    let map = last.map_readable().unwrap();
+    let expected = (48..64).map(|i| i * 100 - 3000).collect::<Vec<i16>>();
+    assert_eq!(map.as_slice_of::<i16>().unwrap(), expected.as_slice());
+}
+
+#[test]
+fn test_duration() {
+    init();
+
+    let mut h = gst_check::Harness::new("claxondec");
+    let srcpad = h.element().unwrap().static_pad("src").unwrap();
+    assert_eq!(srcpad.query_duration::<gst::ClockTime>(), None);
+
+    // 64 samples at 16kHz in the STREAMINFO, and upstream doesn't know the duration
+    h.play();
+    h.set_src_caps(gst::Caps::builder("audio/x-flac").build());
+    h.push(gst::Buffer::from_slice(include_bytes!(
+        "test_seektable_s16.flac"
+    )))
+    .unwrap();
+    assert_eq!(
+        srcpad.query_duration::<gst::ClockTime>(),
+        Some(gst::ClockTime::from_mseconds(4))
+    );
+}
+
+#[test]
+fn test_seek_unframed() {
+    init();
+
+    // 4 frames of 16 samples at 16kHz, with a seek point at the third frame. Each sample is
+    // its number * 100 - 3000
+    let data = include_bytes!("test_seektable_s16.flac");
+    let first_frame_offset = 82;
+    let frame_size = 17;
+
+    let mut h = gst_check::Harness::new("claxondec");
+    h.play();
+    h.set_src_caps(gst::Caps::builder("audio/x-flac").build());
+    h.push(gst::Buffer::from_slice(data)).unwrap();
+    h.push_event(gst::event::Eos::new());
+
+    let buffers = gst_plugin_test_utils::pull_all(&mut h);
+    assert_eq!(buffers.len(), 4);
+
+    // Seek to sample 40, inside of the third frame
+    let position = gst::ClockTime::from_useconds(2500);
+    let seek = gst::event::Seek::new(
+        1.0,
+        gst::SeekFlags::FLUSH | gst::SeekFlags::ACCURATE,
+        gst::SeekType::Set,
+        position,
+        gst::SeekType::None,
+        gst::ClockTime::NONE,
+    );
+    assert!(h.push_upstream_event(seek));
+
+    let upstream_seek = loop {
+        let event = h.pull_upstream_event().unwrap();
+        if let gst::EventView::Seek(ev) = event.view() {
+            break ev.get();
+        }
+    };
+    let offset = first_frame_offset + 2 * frame_size;
+    assert_eq!(
+        upstream_seek.3,
+        gst::GenericFormattedValue::Bytes(Some(gst::format::Bytes::from_u64(offset as u64)))
+    );
+
+    // Handle the seek like a byte based source
+    h.push_event(gst::event::FlushStart::new());
+    h.push_event(gst::event::FlushStop::new(true));
+    let mut segment = gst::FormattedSegment::<gst::format::Bytes>::new();
+    segment.set_start(gst::format::Bytes::from_u64(offset as u64));
+    h.push_event(gst::event::Segment::new(&segment));
+    h.push(gst::Buffer::from_slice(&data[offset..])).unwrap();
+    h.push_event(gst::event::Eos::new());
+
+    let buffers = gst_plugin_test_utils::pull_all(&mut h);
+    let samples = buffers
+        .iter()
+        .flat_map(|buffer| {
+            let map = buffer.map_readable().unwrap();
+            map.chunks_exact(2)
+                .map(|s| i16::from_ne_bytes([s[0], s[1]]))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        samples,
+        (40..64).map(|i| i * 100 - 3000).collect::<Vec<_>>()
+    );
+
+    let mut segment = None;
+    while let Some(event) = h.try_pull_event() {
+        if let gst::EventView::Segment(ev) = event.view() {
+            segment = Some(ev.segment().clone());
+        }
+    }
+    let segment = segment
+        .unwrap()
+        .downcast::<gst::ClockTime>()
+        .expect("time segment");
+    assert_eq!(segment.start(), Some(position));
+}
+
+#[test]
+fn test_seek_unframed_reverse() {
+    init();
+
+    // 4 frames of 16 samples at 16kHz, each sample is its number * 100 - 3000
+    let data = include_bytes!("test_seektable_s16.flac");
+    let first_frame_offset = 82;
+
+    let mut h = gst_check::Harness::new("claxondec");
+    h.play();
+    h.set_src_caps(gst::Caps::builder("audio/x-flac").build());
+    h.push(gst::Buffer::from_slice(data)).unwrap();
+    h.push_event(gst::event::Eos::new());
+
+    let buffers = gst_plugin_test_utils::pull_all(&mut h);
+    assert_eq!(buffers.len(), 4);
+
+    // Play the whole stream backwards
+    let seek = gst::event::Seek::new(
+        -1.0,
+        gst::SeekFlags::FLUSH,
+        gst::SeekType::Set,
+        gst::ClockTime::ZERO,
+        gst::SeekType::None,
+        gst::ClockTime::NONE,
+    );
+    assert!(h.push_upstream_event(seek));
+
+    let upstream_seek = loop {
+        let event = h.pull_upstream_event().unwrap();
+        if let gst::EventView::Seek(ev) = event.view() {
+            break ev.get();
+        }
+    };
+    // The segment is read forwards from the first frame
+    assert_eq!(upstream_seek.0, 1.0);
+    assert_eq!(
+        upstream_seek.3,
+        gst::GenericFormattedValue::Bytes(Some(gst::format::Bytes::from_u64(
+            first_frame_offset as u64
+        )))
+    );
+
+    h.push_event(gst::event::FlushStart::new());
+    h.push_event(gst::event::FlushStop::new(true));
+    let mut segment = gst::FormattedSegment::<gst::format::Bytes>::new();
+    segment.set_start(gst::format::Bytes::from_u64(first_frame_offset as u64));
+    h.push_event(gst::event::Segment::new(&segment));
+    let mut buffer = gst::Buffer::from_slice(&data[first_frame_offset..]);
+    buffer
+        .get_mut()
+        .unwrap()
+        .set_flags(gst::BufferFlags::DISCONT);
+    h.push(buffer).unwrap();
+    h.push_event(gst::event::Eos::new());
+
+    // The frames are output from the last to the first one
+    let buffers = gst_plugin_test_utils::pull_all(&mut h);
+    assert_eq!(buffers.len(), 4);
+    for (buffer, frame) in buffers.iter().zip((0..4).rev()) {
+        assert_eq!(buffer.pts(), Some(gst::ClockTime::from_mseconds(frame)));
+        let map = buffer.map_readable().unwrap();
+        let expected = (frame as i16 * 16..(frame as i16 + 1) * 16)
+            .map(|i| i * 100 - 3000)
+            .collect::<Vec<i16>>();
+        assert_eq!(map.as_slice_of::<i16>().unwrap(), expected.as_slice());
+    }
+
+    let mut segment = None;
+    while let Some(event) = h.try_pull_event() {
+        if let gst::EventView::Segment(ev) = event.view() {
+            segment = Some(ev.segment().clone());
+        }
+    }
+    let segment = segment
+        .unwrap()
+        .downcast::<gst::ClockTime>()
+        .expect("time segment");
+    assert_eq!(segment.rate(), -1.0);
+    assert_eq!(segment.stop(), Some(gst::ClockTime::from_mseconds(4)));
+}
+
+#[test]
+fn test_typefind() {
+    init();
+
+    let factory = gst::TypeFindFactory::factories()
+        .into_iter()
+        .find(|factory| factory.name() == "rsflac_typefind")
+        .unwrap();
+    let typefind = |data: &[u8]| {
+        let mut typefind = gst::typefind::SliceTypeFind::new(data);
+        factory.call_function(&mut typefind);
+        typefind.probability
+    };
+
+    // 4 frames of 16 samples after the metadata blocks
+    let data = include_bytes!("test_seektable_s16.flac");
+    let first_frame_offset = 82;
+    let frame_size = 17;
+
+    assert_eq!(typefind(data), Some(gst::TypeFindProbability::Maximum));
+    assert_eq!(
+        typefind(&data[first_frame_offset..]),
+        Some(gst::TypeFindProbability::Likely)
+    );
+    assert_eq!(
+        typefind(&data[first_frame_offset + 3 * frame_size..]),
+        Some(gst::TypeFindProbability::Possible)
+    );
+    assert_eq!(typefind(&data[first_frame_offset + 1..]), None);
+}
+
+#[test]
+fn test_plugin_info() {
+    let info = gstclaxon::plugin_info();
+
+    assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+    assert!(!info.commit_id.is_empty());
+    assert!(info.features.split(',').any(|f| f == "claxondec"));
+}
+
+fn do_test(data: &'static [u8], packet_sizes: &[usize], decoded_samples: &[usize]) -> gst::Caps {
+    init();
+
+    let mut h = gst_check::Harness::new("claxondec");
+    h.play();
+
+    let caps = gst::Caps::builder("audio/x-flac")
+        .field("framed", true)
+        .build();
+    h.set_src_caps(caps);
 
+    gst_plugin_test_utils::push_packets(&mut h, data, packet_sizes).unwrap();
     h.push_event(gst::event::Eos::new());
 
+    let mut buffers = gst_plugin_test_utils::pull_all(&mut h).into_iter();
     for samples in decoded_samples {
         if *samples == 0 {
             continue;
         }
-        let buffer = h.pull().unwrap();
+        let buffer = buffers.next().unwrap();
         assert_eq!(buffer.size(), 4 * samples);
     }
 
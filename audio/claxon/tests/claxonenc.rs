@@ -0,0 +1,185 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use gst::prelude::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstclaxon::plugin_register_static().expect("claxon test");
+    });
+}
+
+fn width(format: gst_audio::AudioFormat) -> usize {
+    match format {
+        gst_audio::AudioFormat::S8 => 1,
+        gst_audio::AUDIO_FORMAT_S16 => 2,
+        _ => 4,
+    }
+}
+
+fn to_bytes(samples: &[i32], format: gst_audio::AudioFormat) -> Vec<u8> {
+    let width = width(format);
+    samples
+        .iter()
+        .flat_map(|s| s.to_ne_bytes()[..width].to_vec())
+        .collect()
+}
+
+fn from_bytes(data: &[u8], format: gst_audio::AudioFormat) -> Vec<i32> {
+    data.chunks_exact(width(format))
+        .map(|chunk| match chunk.len() {
+            1 => chunk[0] as i8 as i32,
+            2 => i16::from_ne_bytes([chunk[0], chunk[1]]) as i32,
+            _ => i32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]),
+        })
+        .collect()
+}
+
+/// Encodes and decodes `samples` and checks that the decoded samples are identical.
+fn do_roundtrip(format: gst_audio::AudioFormat, channels: u32, samples: &[i32]) {
+    init();
+
+    let mut h = gst_check::Harness::new_parse("claxonenc block-size=1000 ! claxondec");
+    let info = gst_audio::AudioInfo::builder(format, 44_100, channels)
+        .build()
+        .unwrap();
+    h.set_src_caps(info.to_caps().unwrap());
+    h.play();
+
+    let data = to_bytes(samples, format);
+    // Push in buffers that don't match the block size
+    for chunk in data.chunks(777 * info.bpf() as usize) {
+        h.push(gst::Buffer::from_slice(chunk.to_vec())).unwrap();
+    }
+    h.push_event(gst::event::Eos::new());
+
+    let mut decoded = Vec::new();
+    for buffer in gst_plugin_test_utils::pull_all(&mut h) {
+        let map = buffer.map_readable().unwrap();
+        decoded.extend(from_bytes(&map, format));
+    }
+
+    assert_eq!(decoded.len(), samples.len());
+    assert!(decoded == samples, "decoded samples differ");
+}
+
+fn sine(len: usize, channels: usize, amplitude: f64) -> Vec<i32> {
+    (0..len)
+        .flat_map(|i| {
+            (0..channels)
+                .map(move |c| ((i as f64 * (0.01 + c as f64 * 0.003)).sin() * amplitude) as i32)
+        })
+        .collect()
+}
+
+#[test]
+fn test_roundtrip_s16_stereo() {
+    do_roundtrip(
+        gst_audio::AUDIO_FORMAT_S16,
+        2,
+        &sine(4321, 2, i16::MAX as f64),
+    );
+}
+
+#[test]
+fn test_roundtrip_s8_mono() {
+    do_roundtrip(gst_audio::AudioFormat::S8, 1, &sine(2500, 1, 100.0));
+}
+
+#[test]
+fn test_roundtrip_s24_stereo() {
+    do_roundtrip(
+        gst_audio::AUDIO_FORMAT_S2432,
+        2,
+        &sine(3000, 2, 8_000_000.0),
+    );
+}
+
+#[test]
+fn test_roundtrip_s32_stereo() {
+    do_roundtrip(
+        gst_audio::AUDIO_FORMAT_S32,
+        2,
+        &sine(3000, 2, i32::MAX as f64),
+    );
+}
+
+#[test]
+fn test_streamheader() {
+    init();
+
+    let mut h = gst_check::Harness::new("claxonenc");
+    let info = gst_audio::AudioInfo::builder(gst_audio::AUDIO_FORMAT_S16, 48_000, 1)
+        .build()
+        .unwrap();
+    h.set_src_caps(info.to_caps().unwrap());
+    h.play();
+
+    h.push(gst::Buffer::from_slice(vec![0u8; 2 * 100])).unwrap();
+    h.push_event(gst::event::Eos::new());
+
+    let caps = h.sinkpad().unwrap().current_caps().unwrap();
+    let s = caps.structure(0).unwrap();
+    assert!(s.get::<bool>("framed").unwrap());
+    assert_eq!(s.get::<i32>("rate").unwrap(), 48_000);
+    assert_eq!(s.get::<i32>("channels").unwrap(), 1);
+
+    let streamheader = s.get::<gst::ArrayRef>("streamheader").unwrap();
+    let headers = streamheader
+        .as_slice()
+        .iter()
+        .map(|v| v.get::<gst::Buffer>().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(headers.len(), 2);
+    let ident = headers[0].map_readable().unwrap();
+    assert_eq!(&ident[..13], b"\x7fFLAC\x01\x00\x00\x01fLaC");
+
+    // Headers in-band, followed by one frame with all samples and the updated STREAMINFO
+    let buffers = gst_plugin_test_utils::pull_all(&mut h);
+    assert_eq!(buffers.len(), 4);
+    assert!(buffers[0].flags().contains(gst::BufferFlags::HEADER));
+    assert!(buffers[1].flags().contains(gst::BufferFlags::HEADER));
+    assert_eq!(
+        buffers[2].duration(),
+        Some(gst::ClockTime::from_nseconds(2_083_333))
+    );
+    assert!(buffers[3].flags().contains(gst::BufferFlags::HEADER));
+
+    // The STREAMINFO is rewritten in place after seeking back to it
+    let mut byte_segment = None;
+    while let Some(event) = h.try_pull_event() {
+        if let gst::EventView::Segment(ev) = event.view() {
+            if ev.segment().format() == gst::Format::Bytes {
+                byte_segment = Some(ev.segment().start().value());
+            }
+        }
+    }
+    assert_eq!(byte_segment, Some(13));
+
+    let frame_size = buffers[2].size();
+    let streaminfo = buffers[3].map_readable().unwrap();
+    assert_eq!(streaminfo.len(), 38);
+    assert_eq!(&streaminfo[..4], &[0x00, 0x00, 0x00, 34]);
+    assert_eq!(&ident[13..21], &streaminfo[..8]);
+    let min_frame_size = u32::from_be_bytes([0, streaminfo[8], streaminfo[9], streaminfo[10]]);
+    let max_frame_size = u32::from_be_bytes([0, streaminfo[11], streaminfo[12], streaminfo[13]]);
+    assert_eq!(min_frame_size as usize, frame_size);
+    assert_eq!(max_frame_size as usize, frame_size);
+    let total_samples = u64::from_be_bytes(streaminfo[14..22].try_into().unwrap()) & 0xf_ffff_ffff;
+    assert_eq!(total_samples, 100);
+
+    let mut md5 = gst::glib::Checksum::new(gst::glib::ChecksumType::Md5).unwrap();
+    md5.update(&[0u8; 2 * 100]);
+    assert_eq!(&streaminfo[22..], &md5.digest()[..]);
+}
@@ -0,0 +1,126 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use gst::prelude::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstclaxon::plugin_register_static().expect("claxon test");
+    });
+}
+
+/// Pushes `data` in small chunks that don't match the frame boundaries, followed by EOS.
+fn push_chunked(h: &mut gst_check::Harness, data: &[u8]) {
+    h.set_src_caps(gst::Caps::builder("audio/x-flac").build());
+    for chunk in data.chunks(7) {
+        h.push(gst::Buffer::from_slice(chunk.to_vec())).unwrap();
+    }
+    h.push_event(gst::event::Eos::new());
+}
+
+#[test]
+fn test_parse_mono_s16() {
+    init();
+
+    let data = include_bytes!("test_mono_s16.flac");
+
+    let mut h = gst_check::Harness::new("rsflacparse");
+    h.play();
+    push_chunked(&mut h, data);
+
+    // fLaC marker, STREAMINFO, SEEKTABLE, VORBIS_COMMENT and one frame
+    let buffers = gst_plugin_test_utils::pull_all(&mut h);
+    let sizes = buffers.iter().map(|b| b.size()).collect::<Vec<_>>();
+    assert_eq!(sizes, [4, 38, 22, 44, 18]);
+    for buffer in &buffers[..4] {
+        assert!(buffer.flags().contains(gst::BufferFlags::HEADER));
+    }
+    assert_eq!(buffers[4].pts(), Some(gst::ClockTime::ZERO));
+    assert_eq!(
+        buffers[4].duration(),
+        Some(gst::ClockTime::from_nseconds(90_702))
+    );
+
+    let caps = h.sinkpad().unwrap().current_caps().unwrap();
+    let s = caps.structure(0).unwrap();
+    assert!(s.get::<bool>("framed").unwrap());
+    assert_eq!(s.get::<i32>("rate").unwrap(), 44_100);
+    assert_eq!(s.get::<i32>("channels").unwrap(), 1);
+
+    let streamheader = s.get::<gst::ArrayRef>("streamheader").unwrap();
+    let headers = streamheader
+        .as_slice()
+        .iter()
+        .map(|v| v.get::<gst::Buffer>().unwrap())
+        .collect::<Vec<_>>();
+    let sizes = headers.iter().map(|b| b.size()).collect::<Vec<_>>();
+    assert_eq!(sizes, [13 + 38, 22, 44]);
+    let ident = headers[0].map_readable().unwrap();
+    assert_eq!(&ident[..13], b"\x7fFLAC\x01\x00\x00\x02fLaC");
+}
+
+#[test]
+fn test_parse_and_decode() {
+    init();
+
+    let data = include_bytes!("test_stereo_s32.flac");
+
+    let mut h = gst_check::Harness::new_parse("rsflacparse ! claxondec");
+    h.play();
+    push_chunked(&mut h, data);
+
+    let buffers = gst_plugin_test_utils::pull_all(&mut h);
+    assert_eq!(buffers.len(), 1);
+    assert_eq!(buffers[0].size(), 4 * 8192);
+    assert_eq!(buffers[0].pts(), Some(gst::ClockTime::ZERO));
+}
+
+#[test]
+fn test_chained_streams() {
+    init();
+
+    let first = include_bytes!("test_mono_s16.flac");
+    let second = include_bytes!("test_stereo_s32.flac");
+    let data = [&first[..], &second[..]].concat();
+
+    let mut h = gst_check::Harness::new("rsflacparse");
+    h.play();
+    push_chunked(&mut h, &data);
+
+    let buffers = gst_plugin_test_utils::pull_all(&mut h);
+    let sizes = buffers.iter().map(|b| b.size()).collect::<Vec<_>>();
+    assert_eq!(sizes, [4, 38, 22, 44, 18, 4, 38, second.len() - 42]);
+
+    // New caps with the headers of the second stream before its frame
+    let mut caps = Vec::new();
+    while let Some(event) = h.try_pull_event() {
+        if let gst::EventView::Caps(ev) = event.view() {
+            caps.push(ev.caps_owned());
+        }
+    }
+    assert_eq!(caps.len(), 2);
+
+    let s = caps[1].structure(0).unwrap();
+    assert_eq!(s.get::<i32>("channels").unwrap(), 2);
+    let streamheader = s.get::<gst::ArrayRef>("streamheader").unwrap();
+    let headers = streamheader
+        .as_slice()
+        .iter()
+        .map(|v| v.get::<gst::Buffer>().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(headers.len(), 1);
+    let ident = headers[0].map_readable().unwrap();
+    assert_eq!(&ident[..13], b"\x7fFLAC\x01\x00\x00\x00fLaC");
+    assert_eq!(&ident[13..], &second[4..42]);
+}
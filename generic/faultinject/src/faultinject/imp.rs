@@ -0,0 +1,577 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+use once_cell::sync::Lazy;
+
+use rand::prelude::*;
+
+use std::sync::Mutex;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "faultinject",
+        gst::DebugColorFlags::empty(),
+        Some("Fault Injection"),
+    )
+});
+
+const DEFAULT_SEED: u64 = 0;
+const DEFAULT_DROP_PROBABILITY: f64 = 0.0;
+const DEFAULT_TRUNCATE_PROBABILITY: f64 = 0.0;
+const DEFAULT_CORRUPT_PROBABILITY: f64 = 0.0;
+const DEFAULT_CORRUPT_BYTES: u32 = 1;
+const DEFAULT_DUPLICATE_PROBABILITY: f64 = 0.0;
+const DEFAULT_DELAY_PROBABILITY: f64 = 0.0;
+const DEFAULT_DELAY: gst::ClockTime = gst::ClockTime::from_mseconds(100);
+const DEFAULT_PROTECT_HEADERS: bool = true;
+
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    seed: u64,
+    drop_probability: f64,
+    truncate_probability: f64,
+    corrupt_probability: f64,
+    corrupt_bytes: u32,
+    duplicate_probability: f64,
+    delay_probability: f64,
+    delay: gst::ClockTime,
+    protect_headers: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            seed: DEFAULT_SEED,
+            drop_probability: DEFAULT_DROP_PROBABILITY,
+            truncate_probability: DEFAULT_TRUNCATE_PROBABILITY,
+            corrupt_probability: DEFAULT_CORRUPT_PROBABILITY,
+            corrupt_bytes: DEFAULT_CORRUPT_BYTES,
+            duplicate_probability: DEFAULT_DUPLICATE_PROBABILITY,
+            delay_probability: DEFAULT_DELAY_PROBABILITY,
+            delay: DEFAULT_DELAY,
+            protect_headers: DEFAULT_PROTECT_HEADERS,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Stats {
+    processed: u64,
+    dropped: u64,
+    truncated: u64,
+    corrupted: u64,
+    duplicated: u64,
+    delayed: u64,
+}
+
+impl Stats {
+    fn to_structure(self) -> gst::Structure {
+        gst::Structure::builder("application/x-faultinject-stats")
+            .field("num-processed", self.processed)
+            .field("num-dropped", self.dropped)
+            .field("num-truncated", self.truncated)
+            .field("num-corrupted", self.corrupted)
+            .field("num-duplicated", self.duplicated)
+            .field("num-delayed", self.delayed)
+            .build()
+    }
+}
+
+struct State {
+    rng: StdRng,
+    stats: Stats,
+}
+
+impl State {
+    fn new(seed: u64) -> Self {
+        State {
+            rng: StdRng::seed_from_u64(seed),
+            stats: Stats::default(),
+        }
+    }
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State::new(DEFAULT_SEED)
+    }
+}
+
+#[derive(Default)]
+struct ClockWait {
+    clock_id: Option<gst::SingleShotClockId>,
+    flushing: bool,
+}
+
+/// Faults to apply to a single buffer
+#[derive(Debug, Default)]
+struct Faults {
+    drop: bool,
+    truncate: Option<usize>,
+    corrupt: Vec<(usize, u8)>,
+    duplicate: bool,
+    delay: bool,
+}
+
+pub struct FaultInject {
+    srcpad: gst::Pad,
+    sinkpad: gst::Pad,
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+    clock_wait: Mutex<ClockWait>,
+}
+
+impl FaultInject {
+    /// Decides which faults to apply to a buffer of `size` bytes.
+    ///
+    /// The same number of random values is drawn for each fault independent
+    /// of the probabilities, so that changing one probability does not change
+    /// the decisions for the other faults.
+    fn decide(settings: &Settings, state: &mut State, size: usize) -> Faults {
+        let rng = &mut state.rng;
+
+        let drop = rng.gen_bool(settings.drop_probability);
+
+        let truncate = rng.gen_bool(settings.truncate_probability);
+        let truncate_size = rng.gen_range(0..size.max(1));
+
+        let corrupt = rng.gen_bool(settings.corrupt_probability);
+        let num_corrupt = rng.gen_range(1..=settings.corrupt_bytes.max(1));
+        let corrupt_positions = (0..num_corrupt)
+            .map(|_| (rng.gen_range(0..size.max(1)), rng.gen_range(1..=u8::MAX)))
+            .collect::<Vec<_>>();
+
+        let duplicate = rng.gen_bool(settings.duplicate_probability);
+        let delay = rng.gen_bool(settings.delay_probability);
+
+        let truncate = (truncate && size > 0).then_some(truncate_size);
+        let size = truncate.unwrap_or(size);
+
+        Faults {
+            drop,
+            truncate,
+            corrupt: if corrupt {
+                corrupt_positions
+                    .into_iter()
+                    .filter(|(pos, _)| *pos < size)
+                    .collect()
+            } else {
+                vec![]
+            },
+            duplicate,
+            delay,
+        }
+    }
+
+    fn wait(&self, delay: gst::ClockTime) -> Result<(), gst::FlowError> {
+        let clock = self.obj().clock().unwrap_or_else(gst::SystemClock::obtain);
+
+        let mut clock_wait = self.clock_wait.lock().unwrap();
+        if clock_wait.flushing {
+            gst::debug!(CAT, imp: self, "Flushing");
+            return Err(gst::FlowError::Flushing);
+        }
+
+        let id = clock.new_single_shot_id(clock.time().unwrap() + delay);
+        clock_wait.clock_id = Some(id.clone());
+        drop(clock_wait);
+
+        gst::log!(CAT, imp: self, "Delaying buffer by {}", delay);
+        let (res, _) = id.wait();
+        self.clock_wait.lock().unwrap().clock_id.take();
+
+        if res == Err(gst::ClockError::Unscheduled) {
+            gst::debug!(CAT, imp: self, "Flushing");
+            return Err(gst::FlowError::Flushing);
+        }
+
+        Ok(())
+    }
+
+    fn set_flushing(&self, flushing: bool) {
+        let mut clock_wait = self.clock_wait.lock().unwrap();
+        if flushing {
+            if let Some(clock_id) = clock_wait.clock_id.take() {
+                clock_id.unschedule();
+            }
+        }
+        clock_wait.flushing = flushing;
+    }
+
+    fn sink_chain(
+        &self,
+        pad: &gst::Pad,
+        mut buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::log!(CAT, obj: pad, "Handling buffer {:?}", buffer);
+
+        let settings = *self.settings.lock().unwrap();
+
+        if settings.protect_headers && buffer.flags().contains(gst::BufferFlags::HEADER) {
+            gst::trace!(CAT, obj: pad, "Passing through header buffer");
+            return self.srcpad.push(buffer);
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let faults = Self::decide(&settings, &mut state, buffer.size());
+        state.stats.processed += 1;
+
+        if faults.drop {
+            gst::debug!(CAT, obj: pad, "Dropping buffer {:?}", buffer);
+            state.stats.dropped += 1;
+            return Ok(gst::FlowSuccess::Ok);
+        }
+
+        if let Some(size) = faults.truncate {
+            gst::debug!(
+                CAT,
+                obj: pad,
+                "Truncating buffer from {} to {} bytes",
+                buffer.size(),
+                size
+            );
+            state.stats.truncated += 1;
+            buffer.make_mut().set_size(size);
+        }
+
+        if !faults.corrupt.is_empty() {
+            gst::debug!(
+                CAT,
+                obj: pad,
+                "Corrupting {} bytes of buffer",
+                faults.corrupt.len()
+            );
+            state.stats.corrupted += 1;
+
+            let buffer = buffer.make_mut();
+            let mut map = buffer.map_writable().map_err(|_| {
+                gst::element_imp_error!(
+                    self,
+                    gst::CoreError::Failed,
+                    ["Failed to map buffer writable"]
+                );
+                gst::FlowError::Error
+            })?;
+            for (pos, mask) in faults.corrupt {
+                map[pos] ^= mask;
+            }
+        }
+
+        if faults.duplicate {
+            state.stats.duplicated += 1;
+        }
+        if faults.delay {
+            state.stats.delayed += 1;
+        }
+        drop(state);
+
+        if faults.delay {
+            self.wait(settings.delay)?;
+        }
+
+        if faults.duplicate {
+            gst::debug!(CAT, obj: pad, "Duplicating buffer");
+            self.srcpad.push(buffer.clone())?;
+        }
+
+        self.srcpad.push(buffer)
+    }
+
+    fn sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::log!(CAT, obj: pad, "Handling event {:?}", event);
+
+        match event.view() {
+            EventView::FlushStart(_) => self.set_flushing(true),
+            EventView::FlushStop(_) => self.set_flushing(false),
+            _ => (),
+        }
+
+        gst::Pad::event_default(pad, Some(&*self.obj()), event)
+    }
+
+    fn src_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::log!(CAT, obj: pad, "Handling event {:?}", event);
+
+        if let EventView::FlushStart(_) = event.view() {
+            self.set_flushing(true);
+        }
+
+        gst::Pad::event_default(pad, Some(&*self.obj()), event)
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for FaultInject {
+    const NAME: &'static str = "GstFaultInject";
+    type Type = super::FaultInject;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("sink").unwrap();
+        let sinkpad = gst::Pad::builder_from_template(&templ)
+            .chain_function(|pad, parent, buffer| {
+                FaultInject::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |inject| inject.sink_chain(pad, buffer),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                FaultInject::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |inject| inject.sink_event(pad, event),
+                )
+            })
+            .flags(
+                gst::PadFlags::PROXY_CAPS
+                    | gst::PadFlags::PROXY_ALLOCATION
+                    | gst::PadFlags::PROXY_SCHEDULING,
+            )
+            .build();
+
+        let templ = klass.pad_template("src").unwrap();
+        let srcpad = gst::Pad::builder_from_template(&templ)
+            .event_function(|pad, parent, event| {
+                FaultInject::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |inject| inject.src_event(pad, event),
+                )
+            })
+            .flags(
+                gst::PadFlags::PROXY_CAPS
+                    | gst::PadFlags::PROXY_ALLOCATION
+                    | gst::PadFlags::PROXY_SCHEDULING,
+            )
+            .build();
+
+        Self {
+            srcpad,
+            sinkpad,
+            settings: Mutex::default(),
+            state: Mutex::default(),
+            clock_wait: Mutex::default(),
+        }
+    }
+}
+
+impl ObjectImpl for FaultInject {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecUInt64::builder("seed")
+                    .nick("Seed")
+                    .blurb("Seed for the random fault decisions")
+                    .default_value(DEFAULT_SEED)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecDouble::builder("drop-probability")
+                    .nick("Drop Probability")
+                    .blurb("Probability of dropping a buffer")
+                    .minimum(0.0)
+                    .maximum(1.0)
+                    .default_value(DEFAULT_DROP_PROBABILITY)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecDouble::builder("truncate-probability")
+                    .nick("Truncate Probability")
+                    .blurb("Probability of truncating a buffer to a random size")
+                    .minimum(0.0)
+                    .maximum(1.0)
+                    .default_value(DEFAULT_TRUNCATE_PROBABILITY)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecDouble::builder("corrupt-probability")
+                    .nick("Corrupt Probability")
+                    .blurb("Probability of corrupting bytes of a buffer")
+                    .minimum(0.0)
+                    .maximum(1.0)
+                    .default_value(DEFAULT_CORRUPT_PROBABILITY)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt::builder("corrupt-bytes")
+                    .nick("Corrupt Bytes")
+                    .blurb("Maximum number of bytes to change in a corrupted buffer")
+                    .minimum(1)
+                    .default_value(DEFAULT_CORRUPT_BYTES)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecDouble::builder("duplicate-probability")
+                    .nick("Duplicate Probability")
+                    .blurb("Probability of pushing a buffer twice")
+                    .minimum(0.0)
+                    .maximum(1.0)
+                    .default_value(DEFAULT_DUPLICATE_PROBABILITY)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecDouble::builder("delay-probability")
+                    .nick("Delay Probability")
+                    .blurb("Probability of delaying a buffer")
+                    .minimum(0.0)
+                    .maximum(1.0)
+                    .default_value(DEFAULT_DELAY_PROBABILITY)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt64::builder("delay")
+                    .nick("Delay")
+                    .blurb("Time by which delayed buffers are held back in nanoseconds")
+                    .maximum(u64::MAX - 1)
+                    .default_value(DEFAULT_DELAY.nseconds())
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecBoolean::builder("protect-headers")
+                    .nick("Protect Headers")
+                    .blurb("Pass buffers flagged as headers through unmodified")
+                    .default_value(DEFAULT_PROTECT_HEADERS)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecBoxed::builder::<gst::Structure>("stats")
+                    .nick("Statistics")
+                    .blurb("Number of processed buffers and injected faults")
+                    .read_only()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "seed" => {
+                settings.seed = value.get().expect("type checked upstream");
+            }
+            "drop-probability" => {
+                settings.drop_probability = value.get().expect("type checked upstream");
+            }
+            "truncate-probability" => {
+                settings.truncate_probability = value.get().expect("type checked upstream");
+            }
+            "corrupt-probability" => {
+                settings.corrupt_probability = value.get().expect("type checked upstream");
+            }
+            "corrupt-bytes" => {
+                settings.corrupt_bytes = value.get().expect("type checked upstream");
+            }
+            "duplicate-probability" => {
+                settings.duplicate_probability = value.get().expect("type checked upstream");
+            }
+            "delay-probability" => {
+                settings.delay_probability = value.get().expect("type checked upstream");
+            }
+            "delay" => {
+                settings.delay =
+                    gst::ClockTime::from_nseconds(value.get().expect("type checked upstream"));
+            }
+            "protect-headers" => {
+                settings.protect_headers = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "seed" => settings.seed.to_value(),
+            "drop-probability" => settings.drop_probability.to_value(),
+            "truncate-probability" => settings.truncate_probability.to_value(),
+            "corrupt-probability" => settings.corrupt_probability.to_value(),
+            "corrupt-bytes" => settings.corrupt_bytes.to_value(),
+            "duplicate-probability" => settings.duplicate_probability.to_value(),
+            "delay-probability" => settings.delay_probability.to_value(),
+            "delay" => settings.delay.nseconds().to_value(),
+            "protect-headers" => settings.protect_headers.to_value(),
+            "stats" => self.state.lock().unwrap().stats.to_structure().to_value(),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.sinkpad).unwrap();
+        obj.add_pad(&self.srcpad).unwrap();
+    }
+}
+
+impl GstObjectImpl for FaultInject {}
+
+impl ElementImpl for FaultInject {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Fault Injection",
+                "Generic",
+                "Randomly drops, truncates, corrupts, duplicates or delays buffers",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst::Caps::new_any();
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![sink_pad_template, src_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        gst::trace!(CAT, imp: self, "Changing state {:?}", transition);
+
+        match transition {
+            gst::StateChange::ReadyToPaused => {
+                let seed = self.settings.lock().unwrap().seed;
+                *self.state.lock().unwrap() = State::new(seed);
+                self.set_flushing(false);
+            }
+            gst::StateChange::PausedToReady => {
+                self.set_flushing(true);
+            }
+            _ => (),
+        }
+
+        self.parent_change_state(transition)
+    }
+}
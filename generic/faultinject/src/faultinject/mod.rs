@@ -0,0 +1,53 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * element-faultinject:
+ *
+ * Pass-through element that randomly damages the stream flowing through it, to
+ * exercise the error handling of downstream decoders and parsers.
+ *
+ * For every buffer it is decided independently, with the configured
+ * probabilities, whether the buffer is
+ *
+ * - dropped (`drop-probability`),
+ * - truncated to a random size (`truncate-probability`),
+ * - corrupted by changing up to `corrupt-bytes` random bytes (`corrupt-probability`),
+ * - pushed twice (`duplicate-probability`),
+ * - or delayed by `delay` (`delay-probability`).
+ *
+ * All random decisions are derived from `seed`, so the same input always
+ * results in the same faults. Buffers flagged as headers are passed through
+ * unmodified unless `protect-headers` is disabled.
+ *
+ * The number of faults injected so far is available from the `stats` property.
+ *
+ * ## Example launch line
+ * ```
+ * gst-launch-1.0 filesrc location=test.flac ! flacparse ! faultinject seed=42 corrupt-probability=0.05 ! claxondec ! audioconvert ! autoaudiosink
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct FaultInject(ObjectSubclass<imp::FaultInject>) @extends gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "faultinject",
+        gst::Rank::NONE,
+        FaultInject::static_type(),
+    )
+}
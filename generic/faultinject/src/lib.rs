@@ -0,0 +1,44 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+#![allow(clippy::non_send_fields_in_send_ty, unused_doc_comments)]
+
+/**
+ * plugin-faultinject:
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+
+#[cfg(feature = "faultinject")]
+mod faultinject;
+#[cfg(feature = "ratelimit")]
+mod ratelimit;
+
+fn plugin_init(_plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    #[cfg(feature = "faultinject")]
+    {
+        faultinject::register(_plugin)?;
+    }
+    #[cfg(feature = "ratelimit")]
+    {
+        ratelimit::register(_plugin)?;
+    }
+    Ok(())
+}
+
+gst::plugin_define!(
+    faultinject,
+    env!("CARGO_PKG_DESCRIPTION"),
+    plugin_init,
+    concat!(env!("CARGO_PKG_VERSION"), "-", env!("COMMIT_ID")),
+    "MPL-2.0",
+    env!("CARGO_PKG_NAME"),
+    env!("CARGO_PKG_NAME"),
+    env!("CARGO_PKG_REPOSITORY"),
+    env!("BUILD_REL_DATE")
+);
@@ -0,0 +1,424 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+use once_cell::sync::Lazy;
+
+use std::sync::Mutex;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "ratelimit",
+        gst::DebugColorFlags::empty(),
+        Some("Rate Limiter"),
+    )
+});
+
+const DEFAULT_BITRATE: u64 = 0;
+const DEFAULT_BURST: u32 = 65536;
+const DEFAULT_LATENCY: Option<gst::ClockTime> = None;
+
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    bitrate: u64,
+    burst: u32,
+    latency: Option<gst::ClockTime>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            bitrate: DEFAULT_BITRATE,
+            burst: DEFAULT_BURST,
+            latency: DEFAULT_LATENCY,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Available tokens in bytes
+    tokens: f64,
+    /// Clock time at which `tokens` was last updated
+    last_update: Option<gst::ClockTime>,
+    num_dropped: u64,
+}
+
+#[derive(Default)]
+struct ClockWait {
+    clock_id: Option<gst::SingleShotClockId>,
+    flushing: bool,
+}
+
+pub struct RateLimit {
+    srcpad: gst::Pad,
+    sinkpad: gst::Pad,
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+    clock_wait: Mutex<ClockWait>,
+}
+
+impl RateLimit {
+    fn wait_until(&self, clock: &gst::Clock, time: gst::ClockTime) -> Result<(), gst::FlowError> {
+        let mut clock_wait = self.clock_wait.lock().unwrap();
+        if clock_wait.flushing {
+            gst::debug!(CAT, imp: self, "Flushing");
+            return Err(gst::FlowError::Flushing);
+        }
+
+        let id = clock.new_single_shot_id(time);
+        clock_wait.clock_id = Some(id.clone());
+        drop(clock_wait);
+
+        let (res, _) = id.wait();
+        self.clock_wait.lock().unwrap().clock_id.take();
+
+        if res == Err(gst::ClockError::Unscheduled) {
+            gst::debug!(CAT, imp: self, "Flushing");
+            return Err(gst::FlowError::Flushing);
+        }
+
+        Ok(())
+    }
+
+    fn set_flushing(&self, flushing: bool) {
+        let mut clock_wait = self.clock_wait.lock().unwrap();
+        if flushing {
+            if let Some(clock_id) = clock_wait.clock_id.take() {
+                clock_id.unschedule();
+            }
+        }
+        clock_wait.flushing = flushing;
+    }
+
+    fn sink_chain(
+        &self,
+        pad: &gst::Pad,
+        buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::log!(CAT, obj: pad, "Handling buffer {:?}", buffer);
+
+        let settings = *self.settings.lock().unwrap();
+        if settings.bitrate == 0 {
+            return self.srcpad.push(buffer);
+        }
+
+        let clock = self.obj().clock().unwrap_or_else(gst::SystemClock::obtain);
+        let now = clock.time().unwrap();
+        let burst = settings.burst as f64;
+        let bytes_per_ns = settings.bitrate as f64 / 8.0 / gst::ClockTime::SECOND.nseconds() as f64;
+
+        let mut state = self.state.lock().unwrap();
+
+        // Refill the bucket for the time that passed since the last update
+        match state.last_update {
+            Some(last_update) if now > last_update => {
+                let elapsed = (now - last_update).nseconds() as f64;
+                state.tokens = f64::min(burst, state.tokens + elapsed * bytes_per_ns);
+                state.last_update = Some(now);
+            }
+            Some(_) => (),
+            None => {
+                state.tokens = burst;
+                state.last_update = Some(now);
+            }
+        }
+
+        let size = buffer.size() as f64;
+        if state.tokens >= size {
+            state.tokens -= size;
+            drop(state);
+
+            return self.srcpad.push(buffer);
+        }
+
+        let delay =
+            gst::ClockTime::from_nseconds(((size - state.tokens) / bytes_per_ns).ceil() as u64);
+        if settings.latency.map_or(false, |latency| delay > latency) {
+            state.num_dropped += 1;
+            gst::debug!(
+                CAT,
+                obj: pad,
+                "Dropping buffer of {} bytes that would be delayed by {}",
+                buffer.size(),
+                delay,
+            );
+            return Ok(gst::FlowSuccess::Ok);
+        }
+
+        // Wait until the bucket contains enough tokens for this buffer. The
+        // tokens are consumed right away so that buffers arriving during the
+        // wait are queued behind this one.
+        let deadline = state.last_update.unwrap() + delay;
+        state.tokens = 0.0;
+        state.last_update = Some(deadline);
+        drop(state);
+
+        gst::log!(CAT, obj: pad, "Delaying buffer by {}", delay);
+        self.wait_until(&clock, deadline)?;
+
+        self.srcpad.push(buffer)
+    }
+
+    fn sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::log!(CAT, obj: pad, "Handling event {:?}", event);
+
+        match event.view() {
+            EventView::FlushStart(_) => self.set_flushing(true),
+            EventView::FlushStop(_) => self.set_flushing(false),
+            _ => (),
+        }
+
+        gst::Pad::event_default(pad, Some(&*self.obj()), event)
+    }
+
+    fn src_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::log!(CAT, obj: pad, "Handling event {:?}", event);
+
+        if let EventView::FlushStart(_) = event.view() {
+            self.set_flushing(true);
+        }
+
+        gst::Pad::event_default(pad, Some(&*self.obj()), event)
+    }
+
+    fn src_query(&self, pad: &gst::Pad, query: &mut gst::QueryRef) -> bool {
+        use gst::QueryViewMut;
+
+        gst::log!(CAT, obj: pad, "Handling query {:?}", query);
+
+        match query.view_mut() {
+            QueryViewMut::Latency(q) => {
+                let mut peer_query = gst::query::Latency::new();
+
+                let ret = self.sinkpad.peer_query(&mut peer_query);
+
+                if ret {
+                    // Buffers are held back for up to the configured latency
+                    let (live, min, max) = peer_query.result();
+                    let latency = self.settings.lock().unwrap().latency;
+                    q.set(live, min, latency.and_then(|latency| max.opt_add(latency)));
+                }
+                ret
+            }
+            _ => gst::Pad::query_default(pad, Some(&*self.obj()), query),
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for RateLimit {
+    const NAME: &'static str = "GstRateLimit";
+    type Type = super::RateLimit;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("sink").unwrap();
+        let sinkpad = gst::Pad::builder_from_template(&templ)
+            .chain_function(|pad, parent, buffer| {
+                RateLimit::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |ratelimit| ratelimit.sink_chain(pad, buffer),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                RateLimit::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |ratelimit| ratelimit.sink_event(pad, event),
+                )
+            })
+            .flags(
+                gst::PadFlags::PROXY_CAPS
+                    | gst::PadFlags::PROXY_ALLOCATION
+                    | gst::PadFlags::PROXY_SCHEDULING,
+            )
+            .build();
+
+        let templ = klass.pad_template("src").unwrap();
+        let srcpad = gst::Pad::builder_from_template(&templ)
+            .event_function(|pad, parent, event| {
+                RateLimit::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |ratelimit| ratelimit.src_event(pad, event),
+                )
+            })
+            .query_function(|pad, parent, query| {
+                RateLimit::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |ratelimit| ratelimit.src_query(pad, query),
+                )
+            })
+            .flags(
+                gst::PadFlags::PROXY_CAPS
+                    | gst::PadFlags::PROXY_ALLOCATION
+                    | gst::PadFlags::PROXY_SCHEDULING,
+            )
+            .build();
+
+        Self {
+            srcpad,
+            sinkpad,
+            settings: Mutex::default(),
+            state: Mutex::default(),
+            clock_wait: Mutex::default(),
+        }
+    }
+}
+
+impl ObjectImpl for RateLimit {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecUInt64::builder("bitrate")
+                    .nick("Bitrate")
+                    .blurb("Maximum throughput in bits per second (0 = unlimited)")
+                    .default_value(DEFAULT_BITRATE)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt::builder("burst")
+                    .nick("Burst")
+                    .blurb("Size of the token bucket in bytes")
+                    .minimum(1)
+                    .default_value(DEFAULT_BURST)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt64::builder("latency")
+                    .nick("Latency")
+                    .blurb(
+                        "Maximum time a buffer is held back before it is dropped instead \
+                         in nanoseconds (-1 = unlimited)",
+                    )
+                    .default_value(DEFAULT_LATENCY.map_or(u64::MAX, gst::ClockTime::nseconds))
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt64::builder("num-dropped")
+                    .nick("Number Dropped")
+                    .blurb("Number of buffers dropped because of the latency limit")
+                    .read_only()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "bitrate" => {
+                settings.bitrate = value.get().expect("type checked upstream");
+            }
+            "burst" => {
+                settings.burst = value.get().expect("type checked upstream");
+            }
+            "latency" => {
+                let latency = value.get::<u64>().expect("type checked upstream");
+                settings.latency =
+                    (latency != u64::MAX).then(|| gst::ClockTime::from_nseconds(latency));
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "bitrate" => settings.bitrate.to_value(),
+            "burst" => settings.burst.to_value(),
+            "latency" => settings
+                .latency
+                .map_or(u64::MAX, gst::ClockTime::nseconds)
+                .to_value(),
+            "num-dropped" => self.state.lock().unwrap().num_dropped.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.sinkpad).unwrap();
+        obj.add_pad(&self.srcpad).unwrap();
+    }
+}
+
+impl GstObjectImpl for RateLimit {}
+
+impl ElementImpl for RateLimit {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Rate Limiter",
+                "Generic",
+                "Limits the byte throughput of a stream with a token bucket",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst::Caps::new_any();
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![sink_pad_template, src_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        gst::trace!(CAT, imp: self, "Changing state {:?}", transition);
+
+        match transition {
+            gst::StateChange::ReadyToPaused => {
+                *self.state.lock().unwrap() = State::default();
+                self.set_flushing(false);
+            }
+            gst::StateChange::PausedToReady => {
+                self.set_flushing(true);
+            }
+            _ => (),
+        }
+
+        self.parent_change_state(transition)
+    }
+}
@@ -0,0 +1,46 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * element-ratelimit:
+ *
+ * Pass-through element that limits the byte throughput of a stream to
+ * `bitrate`, for simulating constrained network links in front of network
+ * sinks.
+ *
+ * The limit is implemented as a token bucket of `burst` bytes that is
+ * refilled at `bitrate`, which allows short bursts above the configured
+ * bitrate. Buffers for which not enough tokens are available are held back
+ * until the bucket has been refilled sufficiently. Buffers that would have to
+ * be held back for longer than `latency` are dropped instead, similar to a
+ * router whose queue is full.
+ *
+ * ## Example launch line
+ * ```
+ * gst-launch-1.0 videotestsrc is-live=true ! x264enc tune=zerolatency ! rtph264pay ! ratelimit bitrate=1000000 burst=10000 latency=200000000 ! udpsink host=127.0.0.1 port=5000
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct RateLimit(ObjectSubclass<imp::RateLimit>) @extends gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "ratelimit",
+        gst::Rank::NONE,
+        RateLimit::static_type(),
+    )
+}
@@ -0,0 +1,170 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstfaultinject::plugin_register_static().expect("Failed to register faultinject plugin");
+    });
+}
+
+fn setup(props: &[(&str, &dyn ToValue)]) -> gst_check::Harness {
+    init();
+
+    let mut h = gst_check::Harness::new("faultinject");
+    {
+        let element = h.element().unwrap();
+        for (name, value) in props {
+            element.set_property_from_value(name, &value.to_value());
+        }
+    }
+    h.set_src_caps_str("application/x-test");
+    h.play();
+
+    h
+}
+
+fn buffer(i: u8) -> gst::Buffer {
+    let mut buffer = gst::Buffer::from_slice([i; 64]);
+    buffer
+        .get_mut()
+        .unwrap()
+        .set_pts(gst::ClockTime::from_mseconds(i as u64 * 10));
+    buffer
+}
+
+fn stats(h: &gst_check::Harness) -> gst::Structure {
+    h.element().unwrap().property::<gst::Structure>("stats")
+}
+
+/// Pushes 100 buffers and returns the contents of all output buffers
+fn run(h: &mut gst_check::Harness) -> Vec<Vec<u8>> {
+    for i in 0..100 {
+        h.push(buffer(i)).unwrap();
+    }
+
+    let mut out = vec![];
+    while let Some(buffer) = h.try_pull() {
+        out.push(buffer.map_readable().unwrap().to_vec());
+    }
+    out
+}
+
+#[test]
+fn test_passthrough() {
+    let mut h = setup(&[]);
+
+    let out = run(&mut h);
+    assert_eq!(out.len(), 100);
+    for (i, data) in out.iter().enumerate() {
+        assert_eq!(data, &[i as u8; 64]);
+    }
+
+    let stats = stats(&h);
+    assert_eq!(stats.get::<u64>("num-processed").unwrap(), 100);
+    assert_eq!(stats.get::<u64>("num-dropped").unwrap(), 0);
+    assert_eq!(stats.get::<u64>("num-corrupted").unwrap(), 0);
+}
+
+#[test]
+fn test_drop() {
+    let mut h = setup(&[("drop-probability", &1.0f64)]);
+
+    assert!(run(&mut h).is_empty());
+    assert_eq!(stats(&h).get::<u64>("num-dropped").unwrap(), 100);
+}
+
+#[test]
+fn test_corrupt() {
+    let mut h = setup(&[("corrupt-probability", &1.0f64), ("corrupt-bytes", &1u32)]);
+
+    let out = run(&mut h);
+    assert_eq!(out.len(), 100);
+    for (i, data) in out.iter().enumerate() {
+        assert_eq!(data.len(), 64);
+        assert_eq!(data.iter().filter(|b| **b != i as u8).count(), 1);
+    }
+    assert_eq!(stats(&h).get::<u64>("num-corrupted").unwrap(), 100);
+}
+
+#[test]
+fn test_truncate() {
+    let mut h = setup(&[("truncate-probability", &1.0f64)]);
+
+    let out = run(&mut h);
+    assert_eq!(out.len(), 100);
+    for (i, data) in out.iter().enumerate() {
+        assert!(data.len() < 64);
+        assert!(data.iter().all(|b| *b == i as u8));
+    }
+    assert_eq!(stats(&h).get::<u64>("num-truncated").unwrap(), 100);
+}
+
+#[test]
+fn test_duplicate() {
+    let mut h = setup(&[("duplicate-probability", &1.0f64)]);
+
+    let out = run(&mut h);
+    assert_eq!(out.len(), 200);
+    for (i, data) in out.chunks(2).enumerate() {
+        assert_eq!(data[0], [i as u8; 64]);
+        assert_eq!(data[1], [i as u8; 64]);
+    }
+    assert_eq!(stats(&h).get::<u64>("num-duplicated").unwrap(), 100);
+}
+
+#[test]
+fn test_seed() {
+    let run_with_seed = |seed: u64| {
+        let mut h = setup(&[
+            ("seed", &seed),
+            ("drop-probability", &0.2f64),
+            ("truncate-probability", &0.2f64),
+            ("corrupt-probability", &0.2f64),
+        ]);
+        run(&mut h)
+    };
+
+    let out = run_with_seed(1);
+    assert!(out.len() < 100);
+
+    // The same seed results in the same faults
+    assert_eq!(run_with_seed(1), out);
+    assert_ne!(run_with_seed(2), out);
+}
+
+#[test]
+fn test_protect_headers() {
+    let mut h = setup(&[("drop-probability", &1.0f64)]);
+
+    let mut header = buffer(0);
+    header
+        .get_mut()
+        .unwrap()
+        .set_flags(gst::BufferFlags::HEADER);
+    h.push(header).unwrap();
+    h.push(buffer(1)).unwrap();
+
+    let out = h.pull().unwrap();
+    assert!(out.flags().contains(gst::BufferFlags::HEADER));
+    assert!(h.try_pull().is_none());
+
+    h.element().unwrap().set_property("protect-headers", false);
+    let mut header = buffer(2);
+    header
+        .get_mut()
+        .unwrap()
+        .set_flags(gst::BufferFlags::HEADER);
+    h.push(header).unwrap();
+    assert!(h.try_pull().is_none());
+}
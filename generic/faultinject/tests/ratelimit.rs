@@ -0,0 +1,90 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstfaultinject::plugin_register_static().expect("Failed to register faultinject plugin");
+    });
+}
+
+/// 1000 bytes per second with a bucket of 1000 bytes
+fn setup(latency: Option<gst::ClockTime>) -> gst_check::Harness {
+    init();
+
+    let mut h = gst_check::Harness::new("ratelimit");
+    {
+        let element = h.element().unwrap();
+        element.set_property("bitrate", 8000u64);
+        element.set_property("burst", 1000u32);
+        element.set_property(
+            "latency",
+            latency.map_or(u64::MAX, gst::ClockTime::nseconds),
+        );
+    }
+    h.set_src_caps_str("application/x-test");
+    h.play();
+
+    h
+}
+
+#[test]
+fn test_drop() {
+    let mut h = setup(Some(gst::ClockTime::ZERO));
+    let testclock = h.testclock().unwrap();
+
+    // The bucket starts full
+    h.push(gst::Buffer::with_size(1000).unwrap()).unwrap();
+    assert_eq!(h.pull().unwrap().size(), 1000);
+
+    h.push(gst::Buffer::with_size(1).unwrap()).unwrap();
+    assert!(h.try_pull().is_none());
+
+    // Refilled by 500 bytes
+    testclock.set_time(testclock.time().unwrap() + gst::ClockTime::from_mseconds(500));
+    h.push(gst::Buffer::with_size(500).unwrap()).unwrap();
+    assert_eq!(h.pull().unwrap().size(), 500);
+    h.push(gst::Buffer::with_size(1).unwrap()).unwrap();
+    assert!(h.try_pull().is_none());
+
+    // Never refilled above the burst size
+    testclock.set_time(testclock.time().unwrap() + gst::ClockTime::from_seconds(10));
+    h.push(gst::Buffer::with_size(1001).unwrap()).unwrap();
+    assert!(h.try_pull().is_none());
+    h.push(gst::Buffer::with_size(1000).unwrap()).unwrap();
+    assert_eq!(h.pull().unwrap().size(), 1000);
+
+    assert_eq!(h.element().unwrap().property::<u64>("num-dropped"), 3);
+}
+
+#[test]
+fn test_delay() {
+    let mut h = setup(None);
+    let testclock = h.testclock().unwrap();
+    let start = testclock.time().unwrap();
+
+    h.push(gst::Buffer::with_size(1000).unwrap()).unwrap();
+    assert_eq!(h.pull().unwrap().size(), 1000);
+
+    // Pushing blocks until the bucket was refilled
+    let pad = h.srcpad().unwrap();
+    let push = std::thread::spawn(move || pad.push(gst::Buffer::with_size(250).unwrap()));
+
+    h.crank_single_clock_wait().unwrap();
+    push.join().unwrap().unwrap();
+    assert_eq!(h.pull().unwrap().size(), 250);
+    assert_eq!(
+        testclock.time(),
+        Some(start + gst::ClockTime::from_mseconds(250))
+    );
+}
@@ -16,25 +16,37 @@ use gst::subclass::prelude::*;
 use gst_base::prelude::*;
 use gst_base::subclass::prelude::*;
 
-use std::fs::File;
-use std::io::Write;
-use std::sync::Mutex;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
 use url::Url;
 
+use super::writer::{QueueError, WriteQueue, Writer};
 use crate::file_location::FileLocation;
 
 const DEFAULT_LOCATION: Option<FileLocation> = None;
+const DEFAULT_BUFFER_SIZE: u32 = 0;
+const DEFAULT_O_DIRECT: bool = false;
+const DEFAULT_FSYNC_ON_EOS: bool = false;
+const DEFAULT_ATOMIC_RENAME: bool = false;
 
 #[derive(Debug)]
 struct Settings {
     location: Option<FileLocation>,
+    buffer_size: u32,
+    o_direct: bool,
+    fsync_on_eos: bool,
+    atomic_rename: bool,
 }
 
 impl Default for Settings {
     fn default() -> Self {
         Settings {
             location: DEFAULT_LOCATION,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            o_direct: DEFAULT_O_DIRECT,
+            fsync_on_eos: DEFAULT_FSYNC_ON_EOS,
+            atomic_rename: DEFAULT_ATOMIC_RENAME,
         }
     }
 }
@@ -44,8 +56,13 @@ enum State {
     #[default]
     Stopped,
     Started {
-        file: File,
-        position: u64,
+        writer: Arc<Mutex<Writer>>,
+        /// Set if writing happens from a separate thread
+        queue: Option<Arc<WriteQueue>>,
+        location: PathBuf,
+        /// Set until the temporary file is renamed to `location`
+        temp_location: Option<PathBuf>,
+        fsync_on_eos: bool,
     },
 }
 
@@ -101,6 +118,91 @@ impl FileSink {
 
         Ok(())
     }
+
+    /// Writes out all pending data and moves the file into place
+    fn finish(&self) -> Result<(), gst::ErrorMessage> {
+        let queue = match *self.state.lock().unwrap() {
+            State::Started { ref queue, .. } => queue.clone(),
+            State::Stopped => return Ok(()),
+        };
+
+        // Not holding the state lock here, so that unlock() can interrupt us
+        if let Some(queue) = queue {
+            match queue.drain() {
+                Ok(()) => (),
+                Err(QueueError::Flushing) => {
+                    gst::debug!(CAT, imp: self, "Flushing while draining");
+                    return Ok(());
+                }
+                Err(QueueError::Io(err)) => {
+                    return Err(gst::error_msg!(
+                        gst::ResourceError::Write,
+                        ["Failed to write buffer: {}", err]
+                    ));
+                }
+            }
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let State::Started {
+            ref writer,
+            ref location,
+            ref mut temp_location,
+            fsync_on_eos,
+            ..
+        } = *state
+        else {
+            return Ok(());
+        };
+
+        let mut writer = writer.lock().unwrap();
+        writer.finish().map_err(|err| {
+            gst::error_msg!(
+                gst::ResourceError::Write,
+                ["Failed to write buffer: {}", err]
+            )
+        })?;
+
+        if fsync_on_eos {
+            writer.sync().map_err(|err| {
+                gst::error_msg!(gst::ResourceError::Sync, ["Failed to sync file: {}", err])
+            })?;
+        }
+
+        gst::debug!(CAT, imp: self, "Wrote {} bytes", writer.position());
+
+        if let Some(temp_location) = temp_location.take() {
+            std::fs::rename(&temp_location, location).map_err(|err| {
+                gst::error_msg!(
+                    gst::ResourceError::Write,
+                    [
+                        "Could not rename {} to {}: {}",
+                        temp_location.display(),
+                        location.display(),
+                        err
+                    ]
+                )
+            })?;
+            gst::debug!(CAT, imp: self, "Renamed {:?} to {:?}", temp_location, location);
+
+            // Make sure the rename itself is persisted too
+            #[cfg(unix)]
+            if fsync_on_eos {
+                if let Some(dir) = location.parent() {
+                    let dir = if dir.as_os_str().is_empty() {
+                        Path::new(".")
+                    } else {
+                        dir
+                    };
+                    if let Err(err) = std::fs::File::open(dir).and_then(|dir| dir.sync_all()) {
+                        gst::warning!(CAT, imp: self, "Failed to sync directory {:?}: {}", dir, err);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[glib::object_subclass]
@@ -120,10 +222,36 @@ impl ObjectImpl for FileSink {
 
     fn properties() -> &'static [glib::ParamSpec] {
         static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
-            vec![glib::ParamSpecString::builder("location")
-                .nick("File Location")
-                .blurb("Location of the file to write")
-                .build()]
+            vec![
+                glib::ParamSpecString::builder("location")
+                    .nick("File Location")
+                    .blurb("Location of the file to write")
+                    .build(),
+                glib::ParamSpecUInt::builder("buffer-size")
+                    .nick("Buffer Size")
+                    .blurb("Maximum number of bytes queued for writing from a separate thread (0 = write from the streaming thread)")
+                    .default_value(DEFAULT_BUFFER_SIZE)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("o-direct")
+                    .nick("O_DIRECT")
+                    .blurb("Open the file with O_DIRECT to bypass the page cache (Linux only)")
+                    .default_value(DEFAULT_O_DIRECT)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("fsync-on-eos")
+                    .nick("Fsync on EOS")
+                    .blurb("Sync the file to the storage device on EOS")
+                    .default_value(DEFAULT_FSYNC_ON_EOS)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("atomic-rename")
+                    .nick("Atomic Rename")
+                    .blurb("Write to a temporary file next to the location and rename it on EOS")
+                    .default_value(DEFAULT_ATOMIC_RENAME)
+                    .mutable_ready()
+                    .build(),
+            ]
         });
 
         PROPERTIES.as_ref()
@@ -143,6 +271,22 @@ impl ObjectImpl for FileSink {
                     gst::error!(CAT, imp: self, "Failed to set property `location`: {}", err);
                 }
             }
+            "buffer-size" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.buffer_size = value.get().expect("type checked upstream");
+            }
+            "o-direct" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.o_direct = value.get().expect("type checked upstream");
+            }
+            "fsync-on-eos" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.fsync_on_eos = value.get().expect("type checked upstream");
+            }
+            "atomic-rename" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.atomic_rename = value.get().expect("type checked upstream");
+            }
             _ => unimplemented!(),
         };
     }
@@ -158,6 +302,22 @@ impl ObjectImpl for FileSink {
 
                 location.to_value()
             }
+            "buffer-size" => {
+                let settings = self.settings.lock().unwrap();
+                settings.buffer_size.to_value()
+            }
+            "o-direct" => {
+                let settings = self.settings.lock().unwrap();
+                settings.o_direct.to_value()
+            }
+            "fsync-on-eos" => {
+                let settings = self.settings.lock().unwrap();
+                settings.fsync_on_eos.to_value()
+            }
+            "atomic-rename" => {
+                let settings = self.settings.lock().unwrap();
+                settings.atomic_rename.to_value()
+            }
             _ => unimplemented!(),
         }
     }
@@ -212,19 +372,59 @@ impl BaseSinkImpl for FileSink {
             )
         })?;
 
-        let file = File::create(location).map_err(|err| {
+        let temp_location = if settings.atomic_rename {
+            let file_name = location.file_name().ok_or_else(|| {
+                gst::error_msg!(
+                    gst::ResourceError::Settings,
+                    ["File location {} has no file name", location]
+                )
+            })?;
+            let mut temp_name = std::ffi::OsString::from(".");
+            temp_name.push(file_name);
+            temp_name.push(".tmp");
+
+            Some(location.with_file_name(temp_name))
+        } else {
+            None
+        };
+        let path: &Path = match temp_location {
+            Some(ref temp_location) => temp_location,
+            None => location,
+        };
+
+        let writer = Writer::create(path, settings.o_direct).map_err(|err| {
             gst::error_msg!(
                 gst::ResourceError::OpenWrite,
                 [
                     "Could not open file {} for writing: {}",
-                    location,
+                    path.display(),
                     err.to_string(),
                 ]
             )
         })?;
-        gst::debug!(CAT, imp: self, "Opened file {:?}", file);
+        gst::debug!(CAT, imp: self, "Opened file {:?}", path);
+        let writer = Arc::new(Mutex::new(writer));
+
+        let queue = if settings.buffer_size > 0 {
+            let queue =
+                WriteQueue::new(writer.clone(), settings.buffer_size as usize).map_err(|err| {
+                    gst::error_msg!(
+                        gst::CoreError::Failed,
+                        ["Could not start writer thread: {}", err]
+                    )
+                })?;
+            Some(Arc::new(queue))
+        } else {
+            None
+        };
 
-        *state = State::Started { file, position: 0 };
+        *state = State::Started {
+            writer,
+            queue,
+            location: location.to_path_buf(),
+            temp_location,
+            fsync_on_eos: settings.fsync_on_eos,
+        };
         gst::info!(CAT, imp: self, "Started");
 
         Ok(())
@@ -232,14 +432,51 @@ impl BaseSinkImpl for FileSink {
 
     fn stop(&self) -> Result<(), gst::ErrorMessage> {
         let mut state = self.state.lock().unwrap();
-        if let State::Stopped = *state {
+        let State::Started {
+            writer,
+            queue,
+            temp_location,
+            ..
+        } = std::mem::take(&mut *state)
+        else {
             return Err(gst::error_msg!(
                 gst::ResourceError::Settings,
                 ["FileSink not started"]
             ));
+        };
+
+        // Writes all queued buffers and waits for the thread
+        drop(queue);
+
+        let res = writer.lock().unwrap().finish();
+        drop(writer);
+
+        // Only complete files are moved into place, so an incomplete one is not needed anymore
+        if let Some(temp_location) = temp_location {
+            gst::debug!(
+                CAT,
+                imp: self,
+                "Stopped before EOS, removing incomplete file {:?}",
+                temp_location
+            );
+            if let Err(err) = std::fs::remove_file(&temp_location) {
+                gst::warning!(
+                    CAT,
+                    imp: self,
+                    "Failed to remove {:?}: {}",
+                    temp_location,
+                    err
+                );
+            }
         }
 
-        *state = State::Stopped;
+        res.map_err(|err| {
+            gst::error_msg!(
+                gst::ResourceError::Write,
+                ["Failed to write buffer: {}", err]
+            )
+        })?;
+
         gst::info!(CAT, imp: self, "Stopped");
 
         Ok(())
@@ -247,13 +484,48 @@ impl BaseSinkImpl for FileSink {
 
     // TODO: implement seek in BYTES format
 
+    fn event(&self, event: gst::Event) -> bool {
+        if let gst::EventView::Eos(_) = event.view() {
+            if let Err(err) = self.finish() {
+                self.post_error_message(err);
+                return false;
+            }
+        }
+
+        self.parent_event(event)
+    }
+
+    fn unlock(&self) -> Result<(), gst::ErrorMessage> {
+        if let State::Started {
+            queue: Some(ref queue),
+            ..
+        } = *self.state.lock().unwrap()
+        {
+            queue.set_flushing(true);
+        }
+
+        Ok(())
+    }
+
+    fn unlock_stop(&self) -> Result<(), gst::ErrorMessage> {
+        if let State::Started {
+            queue: Some(ref queue),
+            ..
+        } = *self.state.lock().unwrap()
+        {
+            queue.set_flushing(false);
+        }
+
+        Ok(())
+    }
+
     fn render(&self, buffer: &gst::Buffer) -> Result<gst::FlowSuccess, gst::FlowError> {
-        let mut state = self.state.lock().unwrap();
-        let (file, position) = match *state {
+        let (writer, queue) = match *self.state.lock().unwrap() {
             State::Started {
-                ref mut file,
-                ref mut position,
-            } => (file, position),
+                ref writer,
+                ref queue,
+                ..
+            } => (writer.clone(), queue.clone()),
             State::Stopped => {
                 gst::element_imp_error!(self, gst::CoreError::Failed, ["Not started yet"]);
                 return Err(gst::FlowError::Error);
@@ -261,12 +533,28 @@ impl BaseSinkImpl for FileSink {
         };
 
         gst::trace!(CAT, imp: self, "Rendering {:?}", buffer);
+
+        if let Some(queue) = queue {
+            return match queue.push(buffer.clone()) {
+                Ok(()) => Ok(gst::FlowSuccess::Ok),
+                Err(QueueError::Flushing) => Err(gst::FlowError::Flushing),
+                Err(QueueError::Io(err)) => {
+                    gst::element_imp_error!(
+                        self,
+                        gst::ResourceError::Write,
+                        ["Failed to write buffer: {}", err]
+                    );
+                    Err(gst::FlowError::Error)
+                }
+            };
+        }
+
         let map = buffer.map_readable().map_err(|_| {
             gst::element_imp_error!(self, gst::CoreError::Failed, ["Failed to map buffer"]);
             gst::FlowError::Error
         })?;
 
-        file.write_all(map.as_ref()).map_err(|err| {
+        writer.lock().unwrap().write(map.as_ref()).map_err(|err| {
             gst::element_imp_error!(
                 self,
                 gst::ResourceError::Write,
@@ -275,8 +563,6 @@ impl BaseSinkImpl for FileSink {
             gst::FlowError::Error
         })?;
 
-        *position += map.len() as u64;
-
         Ok(gst::FlowSuccess::Ok)
     }
 }
@@ -14,6 +14,7 @@ use gst::glib;
 use gst::prelude::*;
 
 mod imp;
+mod writer;
 
 glib::wrapper! {
     pub struct FileSink(ObjectSubclass<imp::FileSink>) @extends gst_base::BaseSink, gst::Element, gst::Object, @implements gst::URIHandler;
@@ -0,0 +1,298 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+/// Alignment of buffers, sizes and offsets for `O_DIRECT` writes
+const DIRECT_ALIGN: usize = 4096;
+/// Size of the staging buffer used for `O_DIRECT` writes
+const DIRECT_BUFFER_SIZE: usize = 256 * DIRECT_ALIGN;
+
+/// Block-aligned staging buffer, as needed for `O_DIRECT`
+struct DirectBuffer {
+    data: Vec<u8>,
+    offset: usize,
+    filled: usize,
+}
+
+impl DirectBuffer {
+    fn new() -> io::Result<Self> {
+        let data = vec![0; DIRECT_BUFFER_SIZE + DIRECT_ALIGN];
+        let offset = data.as_ptr().align_offset(DIRECT_ALIGN);
+        if offset >= DIRECT_ALIGN {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Failed to align O_DIRECT buffer",
+            ));
+        }
+
+        Ok(DirectBuffer {
+            data,
+            offset,
+            filled: 0,
+        })
+    }
+
+    fn block(&mut self) -> &mut [u8] {
+        &mut self.data[self.offset..][..DIRECT_BUFFER_SIZE]
+    }
+}
+
+/// Output file, handling the alignment requirements of `O_DIRECT` if enabled
+pub(super) struct Writer {
+    file: File,
+    position: u64,
+    direct: Option<DirectBuffer>,
+}
+
+impl Writer {
+    pub(super) fn create(path: &Path, direct: bool) -> io::Result<Self> {
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+
+        if direct {
+            #[cfg(target_os = "linux")]
+            {
+                use std::os::unix::fs::OpenOptionsExt;
+                options.custom_flags(libc::O_DIRECT);
+            }
+            #[cfg(not(target_os = "linux"))]
+            {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "O_DIRECT is not supported on this platform",
+                ));
+            }
+        }
+
+        let file = options.open(path)?;
+        let direct = if direct {
+            Some(DirectBuffer::new()?)
+        } else {
+            None
+        };
+
+        Ok(Writer {
+            file,
+            position: 0,
+            direct,
+        })
+    }
+
+    pub(super) fn position(&self) -> u64 {
+        self.position
+    }
+
+    pub(super) fn write(&mut self, mut data: &[u8]) -> io::Result<()> {
+        let Some(ref mut direct) = self.direct else {
+            self.file.write_all(data)?;
+            self.position += data.len() as u64;
+            return Ok(());
+        };
+
+        while !data.is_empty() {
+            let filled = direct.filled;
+            let len = data.len().min(DIRECT_BUFFER_SIZE - filled);
+            direct.block()[filled..][..len].copy_from_slice(&data[..len]);
+            direct.filled += len;
+            self.position += len as u64;
+            data = &data[len..];
+
+            if direct.filled == DIRECT_BUFFER_SIZE {
+                self.file.write_all(direct.block())?;
+                direct.filled = 0;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes out all pending data
+    pub(super) fn finish(&mut self) -> io::Result<()> {
+        let Some(ref mut direct) = self.direct else {
+            return self.file.flush();
+        };
+
+        if direct.filled == 0 {
+            return Ok(());
+        }
+
+        // Only whole blocks can be written, so pad the last one and cut the
+        // padding off again afterwards
+        let filled = direct.filled;
+        let padded = (filled + DIRECT_ALIGN - 1) / DIRECT_ALIGN * DIRECT_ALIGN;
+        direct.block()[filled..padded].fill(0);
+        self.file.write_all(&direct.block()[..padded])?;
+        direct.filled = 0;
+
+        self.file.set_len(self.position)
+    }
+
+    pub(super) fn sync(&mut self) -> io::Result<()> {
+        self.file.sync_all()
+    }
+}
+
+#[derive(Default)]
+struct QueueState {
+    buffers: VecDeque<gst::Buffer>,
+    queued: usize,
+    /// Set while the writer thread writes a buffer it removed from the queue
+    busy: bool,
+    /// Set once writing failed, after which the writer thread is gone
+    error: Option<io::Error>,
+    flushing: bool,
+    shutdown: bool,
+}
+
+#[derive(Default)]
+struct Queue {
+    state: Mutex<QueueState>,
+    cond: Condvar,
+}
+
+#[derive(Debug)]
+pub(super) enum QueueError {
+    Flushing,
+    Io(io::Error),
+}
+
+/// Writes buffers from a separate thread, so that slow storage only blocks
+/// the streaming thread once `max_size` bytes are pending
+pub(super) struct WriteQueue {
+    queue: Arc<Queue>,
+    max_size: usize,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl QueueState {
+    fn check(&self) -> Result<(), QueueError> {
+        if let Some(ref err) = self.error {
+            return Err(QueueError::Io(io::Error::new(err.kind(), err.to_string())));
+        }
+        if self.flushing {
+            return Err(QueueError::Flushing);
+        }
+
+        Ok(())
+    }
+}
+
+impl WriteQueue {
+    pub(super) fn new(writer: Arc<Mutex<Writer>>, max_size: usize) -> io::Result<Self> {
+        let queue = Arc::new(Queue::default());
+
+        let thread = thread::Builder::new()
+            .name("rsfilesink-writer".into())
+            .spawn({
+                let queue = queue.clone();
+                move || Self::run(&queue, &writer)
+            })?;
+
+        Ok(WriteQueue {
+            queue,
+            max_size,
+            thread: Some(thread),
+        })
+    }
+
+    fn run(queue: &Queue, writer: &Mutex<Writer>) {
+        let mut state = queue.state.lock().unwrap();
+        loop {
+            let Some(buffer) = state.buffers.pop_front() else {
+                if state.shutdown {
+                    return;
+                }
+                state = queue.cond.wait(state).unwrap();
+                continue;
+            };
+
+            state.busy = true;
+            drop(state);
+
+            let res = match buffer.map_readable() {
+                Ok(map) => writer.lock().unwrap().write(&map),
+                Err(_) => Err(io::Error::new(io::ErrorKind::Other, "Failed to map buffer")),
+            };
+
+            state = queue.state.lock().unwrap();
+            state.busy = false;
+            state.queued -= buffer.size();
+            if let Err(err) = res {
+                // Nothing can be written anymore after an error
+                state.buffers.clear();
+                state.queued = 0;
+                state.error = Some(err);
+                queue.cond.notify_all();
+                return;
+            }
+            queue.cond.notify_all();
+        }
+    }
+
+    /// Queues `buffer`, waiting for enough space first
+    pub(super) fn push(&self, buffer: gst::Buffer) -> Result<(), QueueError> {
+        let size = buffer.size();
+
+        let mut state = self.queue.state.lock().unwrap();
+        loop {
+            state.check()?;
+            // A buffer bigger than the queue is accepted once the queue is empty
+            if state.queued == 0 || state.queued + size <= self.max_size {
+                break;
+            }
+            state = self.queue.cond.wait(state).unwrap();
+        }
+
+        state.queued += size;
+        state.buffers.push_back(buffer);
+        self.queue.cond.notify_all();
+
+        Ok(())
+    }
+
+    /// Waits until all queued buffers are written
+    pub(super) fn drain(&self) -> Result<(), QueueError> {
+        let mut state = self.queue.state.lock().unwrap();
+        loop {
+            state.check()?;
+            if state.buffers.is_empty() && !state.busy {
+                return Ok(());
+            }
+            state = self.queue.cond.wait(state).unwrap();
+        }
+    }
+
+    /// Unblocks `push()` and `drain()` calls
+    pub(super) fn set_flushing(&self, flushing: bool) {
+        let mut state = self.queue.state.lock().unwrap();
+        state.flushing = flushing;
+        self.queue.cond.notify_all();
+    }
+}
+
+impl Drop for WriteQueue {
+    fn drop(&mut self) {
+        {
+            let mut state = self.queue.state.lock().unwrap();
+            state.shutdown = true;
+            self.queue.cond.notify_all();
+        }
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
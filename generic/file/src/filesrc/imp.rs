@@ -17,23 +17,29 @@ use gst_base::subclass::prelude::*;
 
 use std::fs::File;
 use std::io::{Read, Seek, SeekFrom};
-use std::sync::Mutex;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+
+use memmap2::Mmap;
 
 use url::Url;
 
 use crate::file_location::FileLocation;
 
 const DEFAULT_LOCATION: Option<FileLocation> = None;
+const DEFAULT_USE_MMAP: bool = false;
 
 #[derive(Debug)]
 struct Settings {
     location: Option<FileLocation>,
+    use_mmap: bool,
 }
 
 impl Default for Settings {
     fn default() -> Self {
         Settings {
             location: DEFAULT_LOCATION,
+            use_mmap: DEFAULT_USE_MMAP,
         }
     }
 }
@@ -45,9 +51,22 @@ enum State {
     Started {
         file: File,
         position: u64,
+        mmap: Option<Arc<Mmap>>,
     },
 }
 
+/// Part of the memory mapped file, wrapped by the output buffers
+struct MappedRegion {
+    mmap: Arc<Mmap>,
+    range: Range<usize>,
+}
+
+impl AsRef<[u8]> for MappedRegion {
+    fn as_ref(&self) -> &[u8] {
+        &self.mmap[self.range.clone()]
+    }
+}
+
 #[derive(Default)]
 pub struct FileSrc {
     settings: Mutex<Settings>,
@@ -127,11 +146,19 @@ impl ObjectSubclass for FileSrc {
 impl ObjectImpl for FileSrc {
     fn properties() -> &'static [glib::ParamSpec] {
         static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
-            vec![glib::ParamSpecString::builder("location")
-                .nick("File Location")
-                .blurb("Location of the file to read from")
-                .mutable_ready()
-                .build()]
+            vec![
+                glib::ParamSpecString::builder("location")
+                    .nick("File Location")
+                    .blurb("Location of the file to read from")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("use-mmap")
+                    .nick("Use mmap")
+                    .blurb("Memory map the file and output buffers without copying. The file must not be modified while it is mapped")
+                    .default_value(DEFAULT_USE_MMAP)
+                    .mutable_ready()
+                    .build(),
+            ]
         });
 
         PROPERTIES.as_ref()
@@ -151,6 +178,10 @@ impl ObjectImpl for FileSrc {
                     gst::error!(CAT, imp: self, "Failed to set property `location`: {}", err);
                 }
             }
+            "use-mmap" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.use_mmap = value.get().expect("type checked upstream");
+            }
             _ => unimplemented!(),
         };
     }
@@ -166,6 +197,10 @@ impl ObjectImpl for FileSrc {
 
                 location.to_value()
             }
+            "use-mmap" => {
+                let settings = self.settings.lock().unwrap();
+                settings.use_mmap.to_value()
+            }
             _ => unimplemented!(),
         }
     }
@@ -218,10 +253,13 @@ impl BaseSrcImpl for FileSrc {
 
     fn size(&self) -> Option<u64> {
         let state = self.state.lock().unwrap();
-        if let State::Started { ref file, .. } = *state {
-            file.metadata().ok().map(|m| m.len())
-        } else {
-            None
+        match *state {
+            State::Started {
+                mmap: Some(ref mmap),
+                ..
+            } => Some(mmap.len() as u64),
+            State::Started { ref file, .. } => file.metadata().ok().map(|m| m.len()),
+            State::Stopped => None,
         }
     }
 
@@ -252,7 +290,34 @@ impl BaseSrcImpl for FileSrc {
 
         gst::debug!(CAT, imp: self, "Opened file {:?}", file);
 
-        *state = State::Started { file, position: 0 };
+        // Empty files can't be mapped
+        let mmap = if settings.use_mmap && file.metadata().map_or(false, |m| m.len() > 0) {
+            // SAFETY: The file must not be modified while it is mapped, which is documented
+            // as a requirement of the `use-mmap` property.
+            let mmap = unsafe { Mmap::map(&file) }.map_err(|err| {
+                gst::error_msg!(
+                    gst::ResourceError::OpenRead,
+                    ["Could not map file {}: {}", location, err.to_string()]
+                )
+            })?;
+
+            #[cfg(unix)]
+            if let Err(err) = mmap.advise(memmap2::Advice::Sequential) {
+                gst::warning!(CAT, imp: self, "Failed to advise sequential access: {err}");
+            }
+
+            gst::debug!(CAT, imp: self, "Mapped {} bytes", mmap.len());
+
+            Some(Arc::new(mmap))
+        } else {
+            None
+        };
+
+        *state = State::Started {
+            file,
+            position: 0,
+            mmap,
+        };
 
         gst::info!(CAT, imp: self, "Started");
 
@@ -275,6 +340,51 @@ impl BaseSrcImpl for FileSrc {
         Ok(())
     }
 
+    fn create(
+        &self,
+        offset: u64,
+        buffer: Option<&mut gst::BufferRef>,
+        length: u32,
+    ) -> Result<gst_base::subclass::base_src::CreateSuccess, gst::FlowError> {
+        use gst_base::subclass::base_src::CreateSuccess;
+
+        let mmap = match *self.state.lock().unwrap() {
+            State::Started {
+                mmap: Some(ref mmap),
+                ..
+            } => mmap.clone(),
+            _ => return self.parent_create(offset, buffer, length),
+        };
+
+        let len = mmap.len() as u64;
+        if offset >= len {
+            gst::debug!(CAT, imp: self, "EOS at offset {}", offset);
+            return Err(gst::FlowError::Eos);
+        }
+        let range = offset as usize..(len.min(offset + length as u64)) as usize;
+
+        match buffer {
+            // Downstream provided a buffer, so we have to copy
+            Some(buffer) => {
+                let size = range.len();
+                buffer.copy_from_slice(0, &mmap[range]).map_err(|_| {
+                    gst::element_imp_error!(
+                        self,
+                        gst::LibraryError::Failed,
+                        ["Failed to fill buffer"]
+                    );
+                    gst::FlowError::Error
+                })?;
+                buffer.set_size(size);
+
+                Ok(CreateSuccess::FilledBuffer)
+            }
+            None => Ok(CreateSuccess::NewBuffer(gst::Buffer::from_slice(
+                MappedRegion { mmap, range },
+            ))),
+        }
+    }
+
     fn fill(
         &self,
         offset: u64,
@@ -287,6 +397,7 @@ impl BaseSrcImpl for FileSrc {
             State::Started {
                 ref mut file,
                 ref mut position,
+                ..
             } => (file, position),
             State::Stopped => {
                 gst::element_imp_error!(self, gst::CoreError::Failed, ["Not started yet"]);
@@ -0,0 +1,448 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::prelude::*;
+use gst_base::subclass::prelude::*;
+
+use std::fs::File;
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use once_cell::sync::Lazy;
+
+use super::watcher::Watcher;
+use crate::file_location::FileLocation;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "followfilesrc",
+        gst::DebugColorFlags::empty(),
+        Some("Follow File Source"),
+    )
+});
+
+const DEFAULT_POLL_INTERVAL: gst::ClockTime = gst::ClockTime::from_mseconds(100);
+const DEFAULT_IDLE_TIMEOUT: Option<gst::ClockTime> = gst::ClockTime::NONE;
+const DEFAULT_FINISHED: bool = false;
+
+#[derive(Debug)]
+struct Settings {
+    location: Option<FileLocation>,
+    poll_interval: gst::ClockTime,
+    idle_timeout: Option<gst::ClockTime>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            location: None,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            idle_timeout: DEFAULT_IDLE_TIMEOUT,
+        }
+    }
+}
+
+#[derive(Default)]
+enum State {
+    #[default]
+    Stopped,
+    Started {
+        file: File,
+        /// Last time data was read from the file
+        last_growth: Instant,
+    },
+}
+
+#[derive(Default)]
+pub struct FollowFileSrc {
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+    /// Kept outside the state so that `unlock()` can wake up a waiting `fill()`
+    watcher: Mutex<Option<Arc<Watcher>>>,
+    finished: AtomicBool,
+    flushing: AtomicBool,
+}
+
+impl FollowFileSrc {
+    fn set_location(&self, location: Option<FileLocation>) -> Result<(), glib::Error> {
+        let state = self.state.lock().unwrap();
+        if let State::Started { .. } = *state {
+            return Err(glib::Error::new(
+                gst::URIError::BadState,
+                "Changing the `location` property on a started `followfilesrc` is not supported",
+            ));
+        }
+
+        let mut settings = self.settings.lock().unwrap();
+        match location {
+            Some(ref location) => {
+                gst::info!(CAT, imp: self, "Setting `location` to {}", location);
+            }
+            None => {
+                gst::info!(CAT, imp: self, "Resetting `location` to None");
+            }
+        }
+        settings.location = location;
+
+        Ok(())
+    }
+
+    fn wake(&self) {
+        if let Some(ref watcher) = *self.watcher.lock().unwrap() {
+            watcher.wake();
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for FollowFileSrc {
+    const NAME: &'static str = "GstFollowFileSrc";
+    type Type = super::FollowFileSrc;
+    type ParentType = gst_base::BaseSrc;
+}
+
+impl ObjectImpl for FollowFileSrc {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecString::builder("location")
+                    .nick("File Location")
+                    .blurb("Location of the file to read from")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt64::builder("poll-interval")
+                    .nick("Poll Interval")
+                    .blurb(
+                        "Interval at which the file is checked for new data if it can't be watched",
+                    )
+                    .minimum(gst::ClockTime::MSECOND.nseconds())
+                    .maximum(u64::MAX - 1)
+                    .default_value(DEFAULT_POLL_INTERVAL.nseconds())
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt64::builder("idle-timeout")
+                    .nick("Idle Timeout")
+                    .blurb("Send EOS if the file did not grow for this long (-1 = never)")
+                    .maximum(u64::MAX)
+                    .default_value(DEFAULT_IDLE_TIMEOUT.nseconds())
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("finished")
+                    .nick("Finished")
+                    .blurb("Whether the file is complete and EOS should be sent at its end")
+                    .default_value(DEFAULT_FINISHED)
+                    .mutable_playing()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "location" => {
+                let res = match value.get::<Option<String>>() {
+                    Ok(Some(location)) => FileLocation::try_from_path_str(location)
+                        .and_then(|file_location| self.set_location(Some(file_location))),
+                    Ok(None) => self.set_location(None),
+                    Err(_) => unreachable!("type checked upstream"),
+                };
+
+                if let Err(err) = res {
+                    gst::error!(CAT, imp: self, "Failed to set property `location`: {}", err);
+                }
+            }
+            "poll-interval" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.poll_interval =
+                    gst::ClockTime::from_nseconds(value.get().expect("type checked upstream"));
+            }
+            "idle-timeout" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.idle_timeout = value
+                    .get::<Option<gst::ClockTime>>()
+                    .expect("type checked upstream");
+            }
+            "finished" => {
+                let finished = value.get().expect("type checked upstream");
+                gst::debug!(CAT, imp: self, "Setting `finished` to {}", finished);
+                self.finished.store(finished, Ordering::SeqCst);
+                if finished {
+                    self.wake();
+                }
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "location" => {
+                let settings = self.settings.lock().unwrap();
+                let location = settings
+                    .location
+                    .as_ref()
+                    .map(|location| location.to_string());
+
+                location.to_value()
+            }
+            "poll-interval" => {
+                let settings = self.settings.lock().unwrap();
+                settings.poll_interval.nseconds().to_value()
+            }
+            "idle-timeout" => {
+                let settings = self.settings.lock().unwrap();
+                settings.idle_timeout.nseconds().to_value()
+            }
+            "finished" => self.finished.load(Ordering::SeqCst).to_value(),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        self.obj().set_format(gst::Format::Bytes);
+    }
+}
+
+impl GstObjectImpl for FollowFileSrc {}
+
+impl ElementImpl for FollowFileSrc {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Follow File Source",
+                "Source/File",
+                "Read stream from a file that is still being written",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst::Caps::new_any();
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseSrcImpl for FollowFileSrc {
+    fn is_seekable(&self) -> bool {
+        false
+    }
+
+    fn size(&self) -> Option<u64> {
+        // The file is still growing
+        None
+    }
+
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        let mut state = self.state.lock().unwrap();
+        if let State::Started { .. } = *state {
+            unreachable!("FollowFileSrc already started");
+        }
+
+        let settings = self.settings.lock().unwrap();
+        let location = settings.location.as_ref().ok_or_else(|| {
+            gst::error_msg!(
+                gst::ResourceError::Settings,
+                ["File location is not defined"]
+            )
+        })?;
+
+        let file = File::open(location).map_err(|err| {
+            gst::error_msg!(
+                gst::ResourceError::OpenRead,
+                ["Could not open file {} for reading: {}", location, err]
+            )
+        })?;
+
+        let (watcher, err) = Watcher::new(location).map_err(|err| {
+            gst::error_msg!(
+                gst::ResourceError::OpenRead,
+                ["Could not create watcher for {}: {}", location, err]
+            )
+        })?;
+        if let Some(err) = err {
+            gst::warning!(
+                CAT,
+                imp: self,
+                "Failed to watch {}, falling back to polling: {}",
+                location,
+                err
+            );
+        }
+
+        gst::debug!(CAT, imp: self, "Opened file {:?}", file);
+
+        *self.watcher.lock().unwrap() = Some(Arc::new(watcher));
+        self.flushing.store(false, Ordering::SeqCst);
+        *state = State::Started {
+            file,
+            last_growth: Instant::now(),
+        };
+
+        gst::info!(CAT, imp: self, "Started");
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        let mut state = self.state.lock().unwrap();
+        if let State::Stopped = *state {
+            return Err(gst::error_msg!(
+                gst::ResourceError::Settings,
+                ["FollowFileSrc not started"]
+            ));
+        }
+
+        *state = State::Stopped;
+        *self.watcher.lock().unwrap() = None;
+
+        gst::info!(CAT, imp: self, "Stopped");
+
+        Ok(())
+    }
+
+    fn unlock(&self) -> Result<(), gst::ErrorMessage> {
+        gst::debug!(CAT, imp: self, "Unlocking");
+
+        self.flushing.store(true, Ordering::SeqCst);
+        self.wake();
+
+        Ok(())
+    }
+
+    fn unlock_stop(&self) -> Result<(), gst::ErrorMessage> {
+        gst::debug!(CAT, imp: self, "Stop unlocking");
+
+        self.flushing.store(false, Ordering::SeqCst);
+
+        Ok(())
+    }
+
+    fn fill(
+        &self,
+        offset: u64,
+        _length: u32,
+        buffer: &mut gst::BufferRef,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let (poll_interval, idle_timeout) = {
+            let settings = self.settings.lock().unwrap();
+            (
+                Duration::from(settings.poll_interval),
+                settings.idle_timeout.map(Duration::from),
+            )
+        };
+        let watcher = self.watcher.lock().unwrap().clone().ok_or_else(|| {
+            gst::element_imp_error!(self, gst::CoreError::Failed, ["Not started yet"]);
+            gst::FlowError::Error
+        })?;
+
+        loop {
+            if self.flushing.load(Ordering::SeqCst) {
+                gst::debug!(CAT, imp: self, "Flushing");
+                return Err(gst::FlowError::Flushing);
+            }
+
+            // Check this before reading so that all data written before `finished` was set is
+            // still output
+            let finished = self.finished.load(Ordering::SeqCst);
+
+            let mut state = self.state.lock().unwrap();
+            let (file, last_growth) = match *state {
+                State::Started {
+                    ref mut file,
+                    ref mut last_growth,
+                } => (file, last_growth),
+                State::Stopped => {
+                    gst::element_imp_error!(self, gst::CoreError::Failed, ["Not started yet"]);
+                    return Err(gst::FlowError::Error);
+                }
+            };
+
+            let size = {
+                let mut map = buffer.map_writable().map_err(|_| {
+                    gst::element_imp_error!(
+                        self,
+                        gst::LibraryError::Failed,
+                        ["Failed to map buffer"]
+                    );
+                    gst::FlowError::Error
+                })?;
+
+                file.read(map.as_mut()).map_err(|err| {
+                    gst::element_imp_error!(
+                        self,
+                        gst::LibraryError::Failed,
+                        ["Failed to read at {}: {}", offset, err]
+                    );
+                    gst::FlowError::Error
+                })?
+            };
+
+            if size > 0 {
+                *last_growth = Instant::now();
+                buffer.set_size(size);
+
+                return Ok(gst::FlowSuccess::Ok);
+            }
+
+            if finished {
+                gst::debug!(CAT, imp: self, "File finished, EOS at offset {}", offset);
+                return Err(gst::FlowError::Eos);
+            }
+
+            let mut timeout = poll_interval;
+            if let Some(idle_timeout) = idle_timeout {
+                let idle = last_growth.elapsed();
+                if idle >= idle_timeout {
+                    gst::debug!(
+                        CAT,
+                        imp: self,
+                        "File did not grow for {:?}, EOS at offset {}",
+                        idle,
+                        offset
+                    );
+                    return Err(gst::FlowError::Eos);
+                }
+                timeout = timeout.min(idle_timeout - idle);
+            }
+            drop(state);
+
+            gst::trace!(CAT, imp: self, "Waiting for new data at offset {}", offset);
+
+            watcher.wait(timeout);
+            // A wake-up from `unlock()` is handled by checking `flushing` above, and
+            // one from setting `finished` by checking that property
+            watcher.clear();
+        }
+    }
+}
@@ -0,0 +1,50 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+/**
+ * element-followfilesrc:
+ *
+ * Reads a file that is still being written, e.g. an in-progress recording.
+ * When the end of the file is reached, the element waits for more data to be
+ * appended instead of going EOS. On Linux the file is watched with inotify,
+ * on other platforms it is polled every `poll-interval`.
+ *
+ * EOS is only sent once the available data is consumed and either the
+ * `finished` property was set to %TRUE by the application or the file did not
+ * grow for `idle-timeout`.
+ *
+ * As the size of the file is not known, the element is not seekable and only
+ * operates in push mode.
+ *
+ * ## Example launch line
+ * ```
+ * gst-launch-1.0 followfilesrc location=recording.flac idle-timeout=5000000000 ! flacparse ! claxondec ! autoaudiosink
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+mod watcher;
+
+glib::wrapper! {
+    pub struct FollowFileSrc(ObjectSubclass<imp::FollowFileSrc>) @extends gst_base::BaseSrc, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "followfilesrc",
+        gst::Rank::NONE,
+        FollowFileSrc::static_type(),
+    )
+}
@@ -0,0 +1,181 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+/// Waits for modifications of a file.
+///
+/// On Linux this uses inotify, elsewhere it only waits for the timeout so that the caller
+/// falls back to polling the file. In both cases waiting can be interrupted with `wake()`.
+pub(super) struct Watcher {
+    #[cfg(target_os = "linux")]
+    inotify: Option<std::os::fd::OwnedFd>,
+    #[cfg(target_os = "linux")]
+    wakeup: std::os::fd::OwnedFd,
+    #[cfg(not(target_os = "linux"))]
+    woken: (std::sync::Mutex<bool>, std::sync::Condvar),
+}
+
+#[cfg(target_os = "linux")]
+impl Watcher {
+    /// Creates a new watcher for `path`.
+    ///
+    /// If the file can't be watched, e.g. because the inotify limits are reached, `wait()`
+    /// only waits for the timeout.
+    pub(super) fn new(path: &Path) -> io::Result<(Self, Option<io::Error>)> {
+        use std::os::fd::FromRawFd;
+
+        // SAFETY: Creates a new file descriptor that is owned from here on
+        let wakeup = unsafe { libc::eventfd(0, libc::EFD_NONBLOCK | libc::EFD_CLOEXEC) };
+        if wakeup < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: Valid file descriptor that is not owned by anything else
+        let wakeup = unsafe { std::os::fd::OwnedFd::from_raw_fd(wakeup) };
+
+        let (inotify, err) = match Self::watch(path) {
+            Ok(inotify) => (Some(inotify), None),
+            Err(err) => (None, Some(err)),
+        };
+
+        Ok((Watcher { inotify, wakeup }, err))
+    }
+
+    fn watch(path: &Path) -> io::Result<std::os::fd::OwnedFd> {
+        use std::os::fd::{AsRawFd, FromRawFd};
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = std::ffi::CString::new(path.as_os_str().as_bytes())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+        // SAFETY: Creates a new file descriptor that is owned from here on
+        let inotify = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        if inotify < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: Valid file descriptor that is not owned by anything else
+        let inotify = unsafe { std::os::fd::OwnedFd::from_raw_fd(inotify) };
+
+        // SAFETY: Valid inotify file descriptor and NUL-terminated path
+        let wd = unsafe {
+            libc::inotify_add_watch(
+                inotify.as_raw_fd(),
+                path.as_ptr(),
+                libc::IN_MODIFY
+                    | libc::IN_ATTRIB
+                    | libc::IN_CLOSE_WRITE
+                    | libc::IN_MOVE_SELF
+                    | libc::IN_DELETE_SELF,
+            )
+        };
+        if wd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(inotify)
+    }
+
+    /// Waits until the file was modified, `timeout` passed or `wake()` was called.
+    pub(super) fn wait(&self, timeout: Duration) {
+        use std::os::fd::AsRawFd;
+
+        let mut fds = [
+            libc::pollfd {
+                fd: self.wakeup.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            },
+            libc::pollfd {
+                fd: self.inotify.as_ref().map_or(-1, |fd| fd.as_raw_fd()),
+                events: libc::POLLIN,
+                revents: 0,
+            },
+        ];
+        let timeout = timeout.as_millis().min(i32::MAX as u128) as i32;
+
+        // SAFETY: Valid array of pollfds, negative file descriptors are ignored
+        let res = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout) };
+        if res <= 0 || fds[1].revents & libc::POLLIN == 0 {
+            return;
+        }
+
+        // Only the fact that the file was modified is relevant, so drain all events
+        let mut buf = [0u8; 4096];
+        loop {
+            // SAFETY: Valid inotify file descriptor and buffer of the given length
+            let res =
+                unsafe { libc::read(fds[1].fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+            if res <= 0 {
+                break;
+            }
+        }
+    }
+
+    /// Interrupts the current and all following `wait()` calls until `clear()` is called.
+    pub(super) fn wake(&self) {
+        use std::os::fd::AsRawFd;
+
+        let value = 1u64;
+        // SAFETY: Valid eventfd file descriptor and 8 byte value
+        unsafe {
+            libc::write(
+                self.wakeup.as_raw_fd(),
+                &value as *const u64 as *const libc::c_void,
+                std::mem::size_of::<u64>(),
+            );
+        }
+    }
+
+    pub(super) fn clear(&self) {
+        use std::os::fd::AsRawFd;
+
+        let mut value = 0u64;
+        // SAFETY: Valid eventfd file descriptor and 8 byte value. Fails without blocking if
+        // `wake()` wasn't called.
+        unsafe {
+            libc::read(
+                self.wakeup.as_raw_fd(),
+                &mut value as *mut u64 as *mut libc::c_void,
+                std::mem::size_of::<u64>(),
+            );
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+impl Watcher {
+    pub(super) fn new(_path: &Path) -> io::Result<(Self, Option<io::Error>)> {
+        Ok((
+            Watcher {
+                woken: Default::default(),
+            },
+            None,
+        ))
+    }
+
+    pub(super) fn wait(&self, timeout: Duration) {
+        let (woken, cond) = &self.woken;
+        let _ = cond
+            .wait_timeout_while(woken.lock().unwrap(), timeout, |woken| !*woken)
+            .unwrap();
+    }
+
+    pub(super) fn wake(&self) {
+        let (woken, cond) = &self.woken;
+        *woken.lock().unwrap() = true;
+        cond.notify_all();
+    }
+
+    pub(super) fn clear(&self) {
+        *self.woken.0.lock().unwrap() = false;
+    }
+}
@@ -19,10 +19,14 @@ use gst::glib;
 mod file_location;
 mod filesink;
 mod filesrc;
+mod followfilesrc;
+mod multifilesink;
 
 fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
     filesink::register(plugin)?;
     filesrc::register(plugin)?;
+    followfilesrc::register(plugin)?;
+    multifilesink::register(plugin)?;
     Ok(())
 }
 
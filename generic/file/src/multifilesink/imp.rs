@@ -0,0 +1,460 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::prelude::*;
+use gst_base::subclass::prelude::*;
+
+use std::fs::File;
+use std::io::Write;
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "rsmultifilesink",
+        gst::DebugColorFlags::empty(),
+        Some("Multi File Sink"),
+    )
+});
+
+const DEFAULT_LOCATION: &str = "%05d";
+const DEFAULT_USE_STRFTIME: bool = false;
+const DEFAULT_START_INDEX: u32 = 0;
+const DEFAULT_MAX_FILE_SIZE: u64 = 0;
+const DEFAULT_MAX_FILE_DURATION: u64 = 0;
+
+#[derive(Debug, Clone)]
+struct Settings {
+    location: String,
+    use_strftime: bool,
+    start_index: u32,
+    max_file_size: u64,
+    max_file_duration: Option<gst::ClockTime>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            location: DEFAULT_LOCATION.to_string(),
+            use_strftime: DEFAULT_USE_STRFTIME,
+            start_index: DEFAULT_START_INDEX,
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            max_file_duration: None,
+        }
+    }
+}
+
+/// Expands the strftime specifiers of `location` if requested, and then the
+/// printf style index specifier
+fn format_location(location: &str, use_strftime: bool, index: u32) -> Result<String, String> {
+    let location = if use_strftime {
+        use chrono::format::{Item, StrftimeItems};
+
+        // Formatting panics on invalid specifiers
+        if StrftimeItems::new(location).any(|item| matches!(item, Item::Error)) {
+            return Err(format!("Invalid strftime pattern {location}"));
+        }
+
+        chrono::Local::now().format(location).to_string()
+    } else {
+        location.to_string()
+    };
+
+    sprintf::sprintf!(&location, index)
+        .map_err(|err| format!("Invalid location {location}: {err:?}"))
+}
+
+struct OutputFile {
+    file: File,
+    location: String,
+    index: u32,
+    size: u64,
+    start: Option<gst::ClockTime>,
+    end: Option<gst::ClockTime>,
+}
+
+#[derive(Default)]
+struct State {
+    file: Option<OutputFile>,
+    next_index: u32,
+    /// Written at the start of every file
+    stream_headers: Vec<gst::Buffer>,
+}
+
+#[derive(Default)]
+pub struct MultiFileSink {
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+}
+
+impl MultiFileSink {
+    fn open_file(&self, state: &mut State, settings: &Settings) -> Result<(), gst::ErrorMessage> {
+        let index = state.next_index;
+        let location = format_location(&settings.location, settings.use_strftime, index)
+            .map_err(|err| gst::error_msg!(gst::ResourceError::Settings, ["{}", err]))?;
+
+        let mut file = File::create(&location).map_err(|err| {
+            gst::error_msg!(
+                gst::ResourceError::OpenWrite,
+                ["Could not open file {} for writing: {}", location, err]
+            )
+        })?;
+        gst::debug!(CAT, imp: self, "Opened file {} with index {}", location, index);
+
+        let mut size = 0;
+        for header in &state.stream_headers {
+            let map = header.map_readable().map_err(|_| {
+                gst::error_msg!(gst::CoreError::Failed, ["Failed to map stream header"])
+            })?;
+            file.write_all(&map).map_err(|err| {
+                gst::error_msg!(
+                    gst::ResourceError::Write,
+                    ["Failed to write stream header: {}", err]
+                )
+            })?;
+            size += map.len() as u64;
+        }
+
+        state.next_index = index.wrapping_add(1);
+        state.file = Some(OutputFile {
+            file,
+            location,
+            index,
+            size,
+            start: None,
+            end: None,
+        });
+
+        Ok(())
+    }
+
+    /// Closes the current file, if any, and announces it
+    fn close_file(&self, state: &mut State) -> Result<(), gst::ErrorMessage> {
+        let Some(mut file) = state.file.take() else {
+            return Ok(());
+        };
+
+        file.file.flush().map_err(|err| {
+            gst::error_msg!(
+                gst::ResourceError::Write,
+                ["Failed to flush {}: {}", file.location, err]
+            )
+        })?;
+        drop(file.file);
+
+        gst::debug!(
+            CAT,
+            imp: self,
+            "Closed file {} with {} bytes from {} to {}",
+            file.location,
+            file.size,
+            file.start.display(),
+            file.end.display(),
+        );
+
+        let s = gst::Structure::builder("rsmultifilesink-file-closed")
+            .field("location", &file.location)
+            .field("index", file.index)
+            .field("size", file.size)
+            .field_if_some("start-time", file.start)
+            .field_if_some("end-time", file.end)
+            .build();
+        let _ = self
+            .obj()
+            .post_message(gst::message::Element::builder(s).src(&*self.obj()).build());
+
+        Ok(())
+    }
+
+    fn needs_rotation(
+        &self,
+        file: &OutputFile,
+        settings: &Settings,
+        ts: Option<gst::ClockTime>,
+    ) -> bool {
+        if settings.max_file_size > 0 && file.size >= settings.max_file_size {
+            gst::debug!(CAT, imp: self, "File size limit reached");
+            return true;
+        }
+
+        if let (Some(max_duration), Some(start), Some(ts)) =
+            (settings.max_file_duration, file.start, ts)
+        {
+            if ts.saturating_sub(start) >= max_duration {
+                gst::debug!(CAT, imp: self, "File duration limit reached");
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for MultiFileSink {
+    const NAME: &'static str = "GstRsMultiFileSink";
+    type Type = super::MultiFileSink;
+    type ParentType = gst_base::BaseSink;
+}
+
+impl ObjectImpl for MultiFileSink {
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        self.obj().set_sync(false);
+    }
+
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecString::builder("location")
+                    .nick("File Location")
+                    .blurb("Location pattern of the files to write, with a printf style index specifier")
+                    .default_value(Some(DEFAULT_LOCATION))
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("use-strftime")
+                    .nick("Use strftime")
+                    .blurb("Expand strftime specifiers in the location with the local time before the index")
+                    .default_value(DEFAULT_USE_STRFTIME)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("start-index")
+                    .nick("Start Index")
+                    .blurb("Index of the first file")
+                    .default_value(DEFAULT_START_INDEX)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt64::builder("max-file-size")
+                    .nick("Max File Size")
+                    .blurb("Start a new file at the next keyframe once this many bytes were written (0 = unlimited)")
+                    .default_value(DEFAULT_MAX_FILE_SIZE)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt64::builder("max-file-duration")
+                    .nick("Max File Duration")
+                    .blurb("Start a new file at the next keyframe once the file covers this duration in nanoseconds (0 = unlimited)")
+                    .default_value(DEFAULT_MAX_FILE_DURATION)
+                    .mutable_playing()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "location" => {
+                settings.location = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .unwrap_or_else(|| DEFAULT_LOCATION.to_string());
+            }
+            "use-strftime" => {
+                settings.use_strftime = value.get().expect("type checked upstream");
+            }
+            "start-index" => {
+                settings.start_index = value.get().expect("type checked upstream");
+            }
+            "max-file-size" => {
+                settings.max_file_size = value.get().expect("type checked upstream");
+            }
+            "max-file-duration" => {
+                let duration = value.get::<u64>().expect("type checked upstream");
+                settings.max_file_duration =
+                    (duration > 0).then(|| gst::ClockTime::from_nseconds(duration));
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "location" => settings.location.to_value(),
+            "use-strftime" => settings.use_strftime.to_value(),
+            "start-index" => settings.start_index.to_value(),
+            "max-file-size" => settings.max_file_size.to_value(),
+            "max-file-duration" => settings
+                .max_file_duration
+                .map_or(0, gst::ClockTime::nseconds)
+                .to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for MultiFileSink {}
+
+impl ElementImpl for MultiFileSink {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Multi File Sink",
+                "Sink/File",
+                "Write stream to a sequence of files, starting new files at keyframes",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst::Caps::new_any();
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseSinkImpl for MultiFileSink {
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        let settings = self.settings.lock().unwrap();
+
+        // Catch invalid patterns early
+        format_location(
+            &settings.location,
+            settings.use_strftime,
+            settings.start_index,
+        )
+        .map_err(|err| gst::error_msg!(gst::ResourceError::Settings, ["{}", err]))?;
+
+        *self.state.lock().unwrap() = State {
+            next_index: settings.start_index,
+            ..Default::default()
+        };
+        gst::info!(CAT, imp: self, "Started");
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        let mut state = self.state.lock().unwrap();
+        self.close_file(&mut state)?;
+        *state = State::default();
+        gst::info!(CAT, imp: self, "Stopped");
+
+        Ok(())
+    }
+
+    fn set_caps(&self, caps: &gst::Caps) -> Result<(), gst::LoggableError> {
+        let stream_headers = caps
+            .structure(0)
+            .and_then(|s| s.get::<gst::ArrayRef>("streamheader").ok())
+            .map(|headers| {
+                headers
+                    .iter()
+                    .filter_map(|header| header.get::<gst::Buffer>().ok())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        gst::debug!(CAT, imp: self, "Got {} stream headers", stream_headers.len());
+        self.state.lock().unwrap().stream_headers = stream_headers;
+
+        Ok(())
+    }
+
+    fn event(&self, event: gst::Event) -> bool {
+        if let gst::EventView::Eos(_) = event.view() {
+            let mut state = self.state.lock().unwrap();
+            if let Err(err) = self.close_file(&mut state) {
+                drop(state);
+                self.post_error_message(err);
+                return false;
+            }
+        }
+
+        self.parent_event(event)
+    }
+
+    fn render(&self, buffer: &gst::Buffer) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let settings = self.settings.lock().unwrap().clone();
+        let mut state = self.state.lock().unwrap();
+
+        let ts = buffer.pts().or(buffer.dts());
+        let keyframe = !buffer.flags().contains(gst::BufferFlags::DELTA_UNIT);
+
+        // Headers from the caps are written to every file already
+        if buffer.flags().contains(gst::BufferFlags::HEADER) && !state.stream_headers.is_empty() {
+            gst::trace!(CAT, imp: self, "Skipping header {buffer:?}");
+            return Ok(gst::FlowSuccess::Ok);
+        }
+
+        let rotate = keyframe
+            && state
+                .file
+                .as_ref()
+                .map_or(false, |file| self.needs_rotation(file, &settings, ts));
+
+        let res = if rotate {
+            self.close_file(&mut state)
+                .and_then(|_| self.open_file(&mut state, &settings))
+        } else if state.file.is_none() {
+            self.open_file(&mut state, &settings)
+        } else {
+            Ok(())
+        };
+        if let Err(err) = res {
+            drop(state);
+            self.post_error_message(err);
+            return Err(gst::FlowError::Error);
+        }
+
+        let file = state.file.as_mut().unwrap();
+
+        gst::trace!(CAT, imp: self, "Rendering {:?} to {}", buffer, file.location);
+        let map = buffer.map_readable().map_err(|_| {
+            gst::element_imp_error!(self, gst::CoreError::Failed, ["Failed to map buffer"]);
+            gst::FlowError::Error
+        })?;
+
+        file.file.write_all(&map).map_err(|err| {
+            gst::element_imp_error!(
+                self,
+                gst::ResourceError::Write,
+                ["Failed to write buffer: {}", err]
+            );
+            gst::FlowError::Error
+        })?;
+        file.size += map.len() as u64;
+
+        if let Some(ts) = ts {
+            if file.start.map_or(true, |start| ts < start) {
+                file.start = Some(ts);
+            }
+            let end = ts + buffer.duration().unwrap_or(gst::ClockTime::ZERO);
+            if file.end.map_or(true, |cur| end > cur) {
+                file.end = Some(end);
+            }
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+}
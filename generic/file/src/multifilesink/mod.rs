@@ -0,0 +1,53 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+/**
+ * element-rsmultifilesink:
+ *
+ * Writes the incoming stream to a sequence of files. A new file is started
+ * at the next keyframe once the current file reached `max-file-size` bytes
+ * or covers `max-file-duration`.
+ *
+ * The `location` is a printf style pattern for the file index, e.g.
+ * `segment-%05d.ts`. With `use-strftime` the location is first expanded with
+ * the local time at which the file is opened, so the index specifier has to
+ * be escaped, e.g. `%Y%m%d-%H%M%S-%%05d.ts`.
+ *
+ * Stream headers from the caps are written at the start of every file.
+ *
+ * Whenever a file is closed, an element message `rsmultifilesink-file-closed`
+ * is posted with the fields `location` (string), `index` (u32), `size` (u64)
+ * and, if known, `start-time` and `end-time` (GstClockTime) of the data in
+ * the file.
+ *
+ * ## Example launch line
+ * ```
+ * gst-launch-1.0 videotestsrc ! x264enc ! mpegtsmux ! rsmultifilesink location=out-%03d.ts max-file-duration=10000000000
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct MultiFileSink(ObjectSubclass<imp::MultiFileSink>) @extends gst_base::BaseSink, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "rsmultifilesink",
+        gst::Rank::NONE,
+        MultiFileSink::static_type(),
+    )
+}
@@ -0,0 +1,206 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use gst::glib;
+use gst::prelude::*;
+
+use std::path::{Path, PathBuf};
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsfile::plugin_register_static().expect("rsfile test");
+    });
+}
+
+/// Returns a location for the output file of a test, in the target directory instead of the
+/// system one as that is often a tmpfs without O_DIRECT support.
+fn location(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!(
+        "rsfilesink-{}-{}",
+        name,
+        std::process::id()
+    ))
+}
+
+/// Location of the temporary file with `atomic-rename`
+fn temp_location(location: &Path) -> PathBuf {
+    let mut name = std::ffi::OsString::from(".");
+    name.push(location.file_name().unwrap());
+    name.push(".tmp");
+    location.with_file_name(name)
+}
+
+/// Buffers whose sizes are no multiple of the O_DIRECT block size
+fn content() -> Vec<Vec<u8>> {
+    [5000usize, 1, 9000, 4096]
+        .iter()
+        .enumerate()
+        .map(|(i, len)| (0..*len).map(|j| (i * 31 + j * 7) as u8).collect())
+        .collect()
+}
+
+/// Writes `content()` with the given properties and returns the still running harness and
+/// whether EOS was handled.
+fn write(location: &Path, properties: &[(&str, glib::Value)]) -> (gst_check::Harness, bool) {
+    // The file is opened when the harness starts the sink
+    let mut sink =
+        gst::ElementFactory::make("rsfilesink").property("location", location.to_str().unwrap());
+    for (name, value) in properties {
+        sink = sink.property(name, value.clone());
+    }
+    let sink = sink.build().unwrap();
+
+    let mut h = gst_check::Harness::with_element(&sink, Some("sink"), None);
+    h.set_src_caps_str("application/octet-stream");
+
+    for data in content() {
+        h.push(gst::Buffer::from_slice(data)).unwrap();
+    }
+
+    let eos = h.push_event(gst::event::Eos::new());
+    (h, eos)
+}
+
+#[test]
+fn test_write_modes() {
+    init();
+
+    let expected = content().concat();
+
+    let modes = [
+        ("default", vec![]),
+        ("write-back", vec![("buffer-size", 8192u32.to_value())]),
+        // Smaller than the buffers
+        ("write-back-small", vec![("buffer-size", 100u32.to_value())]),
+        ("fsync", vec![("fsync-on-eos", true.to_value())]),
+        ("atomic", vec![("atomic-rename", true.to_value())]),
+        (
+            "atomic-write-back",
+            vec![
+                ("atomic-rename", true.to_value()),
+                ("buffer-size", 8192u32.to_value()),
+                ("fsync-on-eos", true.to_value()),
+            ],
+        ),
+    ];
+
+    for (name, properties) in modes {
+        let location = location(name);
+        let (h, eos) = write(&location, &properties);
+        assert!(eos, "{name}");
+
+        // Everything is written and moved into place by EOS
+        assert_eq!(std::fs::read(&location).unwrap(), expected, "{name}");
+        assert!(!temp_location(&location).exists(), "{name}");
+
+        drop(h);
+        std::fs::remove_file(&location).unwrap();
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_o_direct() {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    init();
+
+    let location = location("o-direct");
+
+    // Not all filesystems support O_DIRECT
+    if let Err(err) = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .custom_flags(libc::O_DIRECT)
+        .open(&location)
+    {
+        println!("Skipping, O_DIRECT not supported: {err}");
+        let _ = std::fs::remove_file(&location);
+        return;
+    }
+
+    let expected = content().concat();
+
+    for properties in [
+        vec![("o-direct", true.to_value())],
+        vec![
+            ("o-direct", true.to_value()),
+            ("buffer-size", 8192u32.to_value()),
+        ],
+        vec![
+            ("o-direct", true.to_value()),
+            ("atomic-rename", true.to_value()),
+        ],
+    ] {
+        let (h, eos) = write(&location, &properties);
+        assert!(eos);
+
+        // The padding of the last block is cut off again
+        assert_eq!(std::fs::read(&location).unwrap(), expected);
+        assert!(!temp_location(&location).exists());
+        drop(h);
+    }
+
+    std::fs::remove_file(&location).unwrap();
+}
+
+#[test]
+fn test_atomic_rename_stopped() {
+    init();
+
+    let location = location("atomic-stopped");
+    let temp_location = temp_location(&location);
+
+    let sink = gst::ElementFactory::make("rsfilesink")
+        .property("location", location.to_str().unwrap())
+        .property("atomic-rename", true)
+        .build()
+        .unwrap();
+
+    let mut h = gst_check::Harness::with_element(&sink, Some("sink"), None);
+    h.set_src_caps_str("application/octet-stream");
+
+    for data in content() {
+        h.push(gst::Buffer::from_slice(data)).unwrap();
+    }
+
+    // Nothing is at the location until EOS
+    assert!(temp_location.exists());
+    assert!(!location.exists());
+
+    // Stopping without EOS removes the incomplete file
+    drop(h);
+    assert!(!temp_location.exists());
+    assert!(!location.exists());
+}
+
+#[test]
+fn test_atomic_rename_error() {
+    init();
+
+    // A non-empty directory can't be replaced by the file
+    let location = location("atomic-error");
+    std::fs::create_dir_all(location.join("dir")).unwrap();
+
+    let (h, eos) = write(&location, &[("atomic-rename", true.to_value())]);
+    assert!(!eos);
+
+    // The incomplete file is removed once stopped after the error
+    drop(h);
+    assert!(!temp_location(&location).exists());
+    assert!(location.join("dir").is_dir());
+
+    std::fs::remove_dir_all(&location).unwrap();
+}
@@ -0,0 +1,75 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use gst::prelude::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsfile::plugin_register_static().expect("rsfile test");
+    });
+}
+
+/// Reads the whole file, starting at `offset` if set.
+fn read_file(location: &std::path::Path, use_mmap: bool, offset: Option<u64>) -> Vec<u8> {
+    let mut h = gst_check::Harness::new("rsfilesrc");
+    {
+        let src = h.element().unwrap();
+        src.set_property("location", location.to_str().unwrap());
+        src.set_property("use-mmap", use_mmap);
+        src.set_property("blocksize", 4096u32);
+
+        // Seeks before starting are performed once the source is started
+        if let Some(offset) = offset {
+            assert!(src.send_event(gst::event::Seek::new(
+                1.0,
+                gst::SeekFlags::FLUSH,
+                gst::SeekType::Set,
+                gst::format::Bytes::from_u64(offset),
+                gst::SeekType::None,
+                None::<gst::format::Bytes>,
+            )));
+        }
+    }
+    h.play();
+
+    let mut data = Vec::new();
+    while let Some(buffer) = h.pull_until_eos().unwrap() {
+        assert!(buffer.size() <= 4096);
+        data.extend_from_slice(&buffer.map_readable().unwrap());
+    }
+
+    data
+}
+
+#[test]
+fn test_mmap() {
+    init();
+
+    let location = std::env::temp_dir().join(format!("rsfilesrc-mmap-{}", std::process::id()));
+    // Not a multiple of the blocksize
+    let content = (0..10_000u32)
+        .map(|i| (i * 7 % 251) as u8)
+        .collect::<Vec<_>>();
+    std::fs::write(&location, &content).unwrap();
+
+    let mapped = read_file(&location, true, None);
+    assert_eq!(mapped, read_file(&location, false, None));
+    assert_eq!(mapped, content);
+
+    let mapped = read_file(&location, true, Some(5000));
+    assert_eq!(mapped, read_file(&location, false, Some(5000)));
+    assert_eq!(mapped, &content[5000..]);
+
+    std::fs::remove_file(&location).unwrap();
+}
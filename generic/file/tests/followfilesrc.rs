@@ -0,0 +1,97 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use gst::prelude::*;
+
+use std::io::Write;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsfile::plugin_register_static().expect("rsfile test");
+    });
+}
+
+fn pull_data(h: &mut gst_check::Harness, len: usize) -> Vec<u8> {
+    let mut data = Vec::new();
+    while data.len() < len {
+        let buffer = h.pull().unwrap();
+        data.extend_from_slice(&buffer.map_readable().unwrap());
+    }
+    data
+}
+
+fn wait_for_eos(h: &mut gst_check::Harness) {
+    loop {
+        let event = h.pull_event().unwrap();
+        if event.type_() == gst::EventType::Eos {
+            break;
+        }
+    }
+}
+
+#[test]
+fn test_follow_until_finished() {
+    init();
+
+    let location =
+        std::env::temp_dir().join(format!("followfilesrc-finished-{}", std::process::id()));
+    let mut file = std::fs::File::create(&location).unwrap();
+    file.write_all(b"hello").unwrap();
+    file.flush().unwrap();
+
+    let mut h = gst_check::Harness::new("followfilesrc");
+    let src = h.element().unwrap();
+    src.set_property("location", location.to_str().unwrap());
+    h.play();
+
+    assert_eq!(pull_data(&mut h, 5), b"hello");
+
+    file.write_all(b" world").unwrap();
+    file.flush().unwrap();
+    assert_eq!(pull_data(&mut h, 6), b" world");
+
+    file.write_all(b"!").unwrap();
+    file.flush().unwrap();
+    src.set_property("finished", true);
+
+    // Data written before the file was finished is still output
+    assert_eq!(pull_data(&mut h, 1), b"!");
+    wait_for_eos(&mut h);
+    assert_eq!(h.buffers_in_queue(), 0);
+
+    drop(h);
+    std::fs::remove_file(&location).unwrap();
+}
+
+#[test]
+fn test_idle_timeout() {
+    init();
+
+    let location = std::env::temp_dir().join(format!("followfilesrc-idle-{}", std::process::id()));
+    let mut file = std::fs::File::create(&location).unwrap();
+    file.write_all(b"data").unwrap();
+    file.flush().unwrap();
+
+    let mut h = gst_check::Harness::new("followfilesrc");
+    let src = h.element().unwrap();
+    src.set_property("location", location.to_str().unwrap());
+    src.set_property("idle-timeout", 200_000_000u64);
+    h.play();
+
+    assert_eq!(pull_data(&mut h, 4), b"data");
+    wait_for_eos(&mut h);
+
+    drop(h);
+    std::fs::remove_file(&location).unwrap();
+}
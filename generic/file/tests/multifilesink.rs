@@ -0,0 +1,70 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsfile::plugin_register_static().expect("rsfile test");
+    });
+}
+
+fn buffer(data: &[u8], pts: gst::ClockTime, keyframe: bool) -> gst::Buffer {
+    let mut buffer = gst::Buffer::from_slice(data.to_vec());
+    {
+        let buffer = buffer.get_mut().unwrap();
+        buffer.set_pts(pts);
+        buffer.set_duration(gst::ClockTime::SECOND);
+        if !keyframe {
+            buffer.set_flags(gst::BufferFlags::DELTA_UNIT);
+        }
+    }
+    buffer
+}
+
+#[test]
+fn test_rotate_on_keyframe() {
+    init();
+
+    let dir = std::env::temp_dir().join(format!("rsmultifilesink-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let location = dir.join("out-%02d.bin");
+
+    let mut h = gst_check::Harness::new("rsmultifilesink");
+    {
+        let sink = h.element().unwrap();
+        sink.set_property("location", location.to_str().unwrap());
+        sink.set_property("max-file-size", 10u64);
+        sink.set_property("start-index", 1u32);
+    }
+    h.set_src_caps_str("application/octet-stream");
+
+    h.push(buffer(b"aaaa", gst::ClockTime::ZERO, true)).unwrap();
+    h.push(buffer(b"bbbbbbbb", gst::ClockTime::SECOND, false))
+        .unwrap();
+    // Over the limit but not a keyframe, must not rotate
+    h.push(buffer(b"cc", 2 * gst::ClockTime::SECOND, false))
+        .unwrap();
+    h.push(buffer(b"dddd", 3 * gst::ClockTime::SECOND, true))
+        .unwrap();
+    h.push_event(gst::event::Eos::new());
+
+    assert_eq!(
+        std::fs::read(dir.join("out-01.bin")).unwrap(),
+        b"aaaabbbbbbbbcc"
+    );
+    assert_eq!(std::fs::read(dir.join("out-02.bin")).unwrap(), b"dddd");
+    assert!(!dir.join("out-03.bin").exists());
+
+    drop(h);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
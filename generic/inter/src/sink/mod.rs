@@ -19,6 +19,11 @@ mod imp;
  * in separate pipelines:
  *
  * {{ generic/inter/examples/basic.rs }}
+ *
+ * Whenever an #intersrc starts or stops consuming from it, #intersink posts
+ * an element message named `intersink-consumer-linked` or
+ * `intersink-consumer-unlinked` respectively, with a `producer-name` string
+ * field and a `consumers` uint field holding the current number of consumers.
  */
 
 glib::wrapper! {
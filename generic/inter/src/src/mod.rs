@@ -18,6 +18,11 @@ mod imp;
  * in separate pipelines:
  *
  * {{ generic/inter/examples/basic.rs }}
+ *
+ * Whenever an #intersink with a matching #intersrc:producer-name starts or
+ * stops producing, #intersrc posts an element message named
+ * `intersrc-producer-linked` or `intersrc-producer-unlinked` respectively,
+ * with a `producer-name` string field.
  */
 
 glib::wrapper! {
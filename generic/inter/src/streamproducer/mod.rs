@@ -41,10 +41,74 @@ fn ensure_different_toplevel(producer: &gst_app::AppSink, consumer: &gst_app::Ap
     }
 }
 
+/// Messages to post once the producers lock is released, as bus sync
+/// handlers might call back into the elements
+#[derive(Default)]
+struct Notifications(Vec<(gst::Element, gst::Message)>);
+
+impl Notifications {
+    /// Notifies the intersrc containing `consumer` about its producer
+    fn producer(&mut self, name: &str, consumer: &gst_app::AppSrc, linked: bool) {
+        let element = outer_element(consumer.upcast_ref());
+        let s = gst::Structure::builder(if linked {
+            "intersrc-producer-linked"
+        } else {
+            "intersrc-producer-unlinked"
+        })
+        .field("producer-name", name)
+        .build();
+
+        let msg = gst::message::Element::builder(s).src(&element).build();
+        self.0.push((element, msg));
+    }
+
+    /// Notifies the intersink containing `appsink` about its consumers
+    fn consumer(&mut self, name: &str, appsink: &gst_app::AppSink, linked: bool, consumers: usize) {
+        let element = outer_element(appsink.upcast_ref());
+        let s = gst::Structure::builder(if linked {
+            "intersink-consumer-linked"
+        } else {
+            "intersink-consumer-unlinked"
+        })
+        .field("producer-name", name)
+        .field("consumers", consumers as u32)
+        .build();
+
+        let msg = gst::message::Element::builder(s).src(&element).build();
+        self.0.push((element, msg));
+    }
+
+    fn post(self) {
+        for (element, msg) in self.0 {
+            let _ = element.post_message(msg);
+        }
+    }
+}
+
+/// Returns the intersink / intersrc wrapping the appsink / appsrc
+fn outer_element(element: &gst::Element) -> gst::Element {
+    element
+        .parent()
+        .and_downcast::<gst::Element>()
+        .unwrap_or_else(|| element.clone())
+}
+
 impl InterStreamProducer {
     pub fn acquire(
         name: &str,
         appsink: &gst_app::AppSink,
+    ) -> Result<gst_utils::StreamProducer, Error> {
+        let mut notifications = Notifications::default();
+        let res = Self::acquire_locked(name, appsink, &mut notifications);
+        notifications.post();
+
+        res
+    }
+
+    fn acquire_locked(
+        name: &str,
+        appsink: &gst_app::AppSink,
+        notifications: &mut Notifications,
     ) -> Result<gst_utils::StreamProducer, Error> {
         let mut producers = PRODUCERS.lock().unwrap();
 
@@ -60,7 +124,9 @@ impl InterStreamProducer {
                         let link = producer
                             .add_consumer(&consumer)
                             .expect("consumer should not have already been added");
+                        notifications.producer(name, &consumer, true);
                         links.insert(consumer, link);
+                        notifications.consumer(name, appsink, true, links.len());
                     }
 
                     producers.insert(
@@ -98,12 +164,24 @@ impl InterStreamProducer {
     }
 
     pub fn release(name: &str) -> Option<gst_app::AppSink> {
+        let mut notifications = Notifications::default();
+        let res = Self::release_locked(name, &mut notifications);
+        notifications.post();
+
+        res
+    }
+
+    fn release_locked(name: &str, notifications: &mut Notifications) -> Option<gst_app::AppSink> {
         let mut producers = PRODUCERS.lock().unwrap();
 
         if let Some(producer) = producers.remove(name) {
             match producer {
                 InterStreamProducer::Pending { .. } => None,
                 InterStreamProducer::Active { links, producer } => {
+                    for consumer in links.keys() {
+                        notifications.producer(name, consumer, false);
+                    }
+
                     producers.insert(
                         name.to_string(),
                         InterStreamProducer::Pending {
@@ -120,6 +198,12 @@ impl InterStreamProducer {
     }
 
     pub fn subscribe(name: &str, consumer: &gst_app::AppSrc) {
+        let mut notifications = Notifications::default();
+        Self::subscribe_locked(name, consumer, &mut notifications);
+        notifications.post();
+    }
+
+    fn subscribe_locked(name: &str, consumer: &gst_app::AppSrc, notifications: &mut Notifications) {
         let mut producers = PRODUCERS.lock().unwrap();
 
         if let Some(producer) = producers.get_mut(name) {
@@ -134,6 +218,9 @@ impl InterStreamProducer {
                         .add_consumer(consumer)
                         .expect("consumer should not already have been added");
                     links.insert(consumer.clone(), link);
+
+                    notifications.producer(name, consumer, true);
+                    notifications.consumer(name, producer.appsink(), true, links.len());
                 }
             }
         } else {
@@ -145,12 +232,33 @@ impl InterStreamProducer {
     }
 
     pub fn unsubscribe(name: &str, consumer: &gst_app::AppSrc) -> bool {
+        let mut notifications = Notifications::default();
+        let res = Self::unsubscribe_locked(name, consumer, &mut notifications);
+        notifications.post();
+
+        res
+    }
+
+    fn unsubscribe_locked(
+        name: &str,
+        consumer: &gst_app::AppSrc,
+        notifications: &mut Notifications,
+    ) -> bool {
         let mut producers = PRODUCERS.lock().unwrap();
 
         if let Some(producer) = producers.get_mut(name) {
             match producer {
                 InterStreamProducer::Pending { consumers } => consumers.remove(consumer),
-                InterStreamProducer::Active { links, .. } => links.remove(consumer).is_some(),
+                InterStreamProducer::Active { producer, links } => {
+                    if links.remove(consumer).is_none() {
+                        return false;
+                    }
+
+                    notifications.producer(name, consumer, false);
+                    notifications.consumer(name, producer.appsink(), false, links.len());
+
+                    true
+                }
             }
         } else {
             false
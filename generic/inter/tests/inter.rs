@@ -136,3 +136,56 @@ fn test_change_producer_name() {
     element1.set_state(gst::State::Null).unwrap();
     element2.set_state(gst::State::Null).unwrap();
 }
+
+fn wait_for_element_message(bus: &gst::Bus, name: &str) -> gst::Structure {
+    loop {
+        let msg = bus
+            .timed_pop_filtered(
+                gst::ClockTime::from_seconds(5),
+                &[gst::MessageType::Element],
+            )
+            .expect("No element message");
+        let s = msg.structure().unwrap();
+        if s.name() == name {
+            return s.to_owned();
+        }
+    }
+}
+
+#[test]
+#[serial]
+fn test_discovery() {
+    init();
+
+    let consumer = gst::Pipeline::new();
+    let src = gst::ElementFactory::make("intersrc")
+        .property("producer-name", "p3")
+        .build()
+        .unwrap();
+    let sink = gst::ElementFactory::make("fakesink").build().unwrap();
+    consumer.add_many([&src, &sink]).unwrap();
+    src.link(&sink).unwrap();
+    consumer.set_state(gst::State::Playing).unwrap();
+
+    let producer = gst::Pipeline::new();
+    let intersink = gst::ElementFactory::make("intersink")
+        .property("producer-name", "p3")
+        .build()
+        .unwrap();
+    producer.add(&intersink).unwrap();
+    producer.set_state(gst::State::Playing).unwrap();
+
+    let s = wait_for_element_message(&consumer.bus().unwrap(), "intersrc-producer-linked");
+    assert_eq!(s.get::<&str>("producer-name").unwrap(), "p3");
+
+    let s = wait_for_element_message(&producer.bus().unwrap(), "intersink-consumer-linked");
+    assert_eq!(s.get::<&str>("producer-name").unwrap(), "p3");
+    assert_eq!(s.get::<u32>("consumers").unwrap(), 1);
+
+    producer.set_state(gst::State::Null).unwrap();
+
+    let s = wait_for_element_message(&consumer.bus().unwrap(), "intersrc-producer-unlinked");
+    assert_eq!(s.get::<&str>("producer-name").unwrap(), "p3");
+
+    consumer.set_state(gst::State::Null).unwrap();
+}
@@ -35,6 +35,8 @@ struct State {
     sinkpad_caps: CapsState,
     meta_caps: CapsState,
     sinkpad_segment: Option<gst::Event>,
+    /// Set once a buffer without original buffer meta was dropped
+    missing_meta_warned: bool,
 }
 
 pub struct OriginalBufferRestore {
@@ -44,7 +46,6 @@ pub struct OriginalBufferRestore {
 }
 
 use once_cell::sync::Lazy;
-#[cfg(unused_code)]
 static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
     gst::DebugCategory::new(
         "originalbufferrestore",
@@ -243,11 +244,23 @@ impl OriginalBufferRestore {
         _pad: &gst::Pad,
         inbuf: gst::Buffer,
     ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let mut state = self.state.borrow_mut();
         let Some(ometa) = inbuf.meta::<OriginalBufferMeta>() else {
-            //gst::element_warning!(self, gst::StreamError::Failed, ["Buffer {} is missing the GstOriginalBufferMeta, put originalbuffersave upstream in your pipeline", buffer]);
+            if !state.missing_meta_warned {
+                state.missing_meta_warned = true;
+                gst::element_imp_warning!(
+                    self,
+                    gst::StreamError::Failed,
+                    [
+                        "Buffer {:?} is missing the GstOriginalBufferMeta, put originalbuffersave upstream in your pipeline",
+                        inbuf
+                    ]
+                );
+            } else {
+                gst::debug!(CAT, imp: self, "Dropping {:?} without original buffer meta", inbuf);
+            }
             return Ok(gst::FlowSuccess::Ok);
         };
-        let mut state = self.state.borrow_mut();
         let meta_caps = &mut state.meta_caps;
         if &meta_caps.caps != ometa.caps() {
             if !self.src_pad.push_event(gst::event::Caps::new(ometa.caps())) {
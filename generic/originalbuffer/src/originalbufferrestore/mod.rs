@@ -10,7 +10,12 @@
 /**
  * SECTION:element-originalbufferrestore
  *
- * See originalbuffersave for details
+ * See originalbuffersave for details.
+ *
+ * Buffers without the meta added by #originalbuffersave are dropped, and a
+ * warning is posted for the first one.
+ *
+ * Since: plugins-rs-0.12.0
  */
 use gst::glib;
 use gst::prelude::*;
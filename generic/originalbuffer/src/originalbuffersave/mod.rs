@@ -21,6 +21,8 @@
  * `... ! originalbuffersave ! videoconvertscale ! video/x-raw, width=100, height=100 ! analysiselement ! originalbufferrestore ! ...`
  *
  * The originalbufferrestore element will "restore" the buffer that was entered to the "save" element, but will keep any metadata that was added later.
+ *
+ * Since: plugins-rs-0.12.0
  */
 use gst::glib;
 use gst::prelude::*;
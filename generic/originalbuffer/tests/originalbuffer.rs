@@ -0,0 +1,46 @@
+// Copyright (C) 2024 Collabora Ltd
+//   @author: Olivier Crête <olivier.crete@collabora.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstoriginalbuffer::plugin_register_static().expect("originalbuffer test");
+    });
+}
+
+#[test]
+fn test_restore_after_scaling() {
+    init();
+
+    let mut h = gst_check::Harness::new_parse(
+        "originalbuffersave ! videoscale ! video/x-raw,width=80,height=60 ! originalbufferrestore",
+    );
+    let caps = gst::Caps::builder("video/x-raw")
+        .field("format", "GRAY8")
+        .field("width", 160i32)
+        .field("height", 120i32)
+        .field("framerate", gst::Fraction::new(30, 1))
+        .build();
+    h.set_src_caps(caps.clone());
+
+    let mut buf = gst::Buffer::from_mut_slice(vec![42u8; 160 * 120]);
+    buf.get_mut().unwrap().set_pts(gst::ClockTime::SECOND);
+    assert_eq!(h.push(buf), Ok(gst::FlowSuccess::Ok));
+
+    // The full resolution buffer comes out again
+    let buf = h.pull().unwrap();
+    assert_eq!(buf.pts(), Some(gst::ClockTime::SECOND));
+    assert_eq!(buf.size(), 160 * 120);
+    assert_eq!(h.sinkpad().unwrap().current_caps(), Some(caps));
+}
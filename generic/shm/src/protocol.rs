@@ -0,0 +1,241 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Control messages exchanged between `rsshmsink` and `rsshmsrc`.
+//!
+//! Every message is a one byte type, followed by the payload size as 32 bit
+//! little endian integer and the payload itself.
+
+use std::ffi::OsStr;
+use std::io::{self, Read, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::path::PathBuf;
+
+const INIT: u8 = 1;
+const CAPS: u8 = 2;
+const BUFFER: u8 = 3;
+const EOS: u8 = 4;
+const RELEASE: u8 = 5;
+
+const HEADER_SIZE: usize = 5;
+const BUFFER_SIZE: usize = 7 * 8 + 4;
+const MAX_PAYLOAD_SIZE: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    /// Sent by the sink to new clients, location of the shared memory file
+    Init {
+        shm_path: PathBuf,
+    },
+    Caps(String),
+    Buffer {
+        id: u64,
+        offset: u64,
+        size: u64,
+        pts: Option<gst::ClockTime>,
+        dts: Option<gst::ClockTime>,
+        duration: Option<gst::ClockTime>,
+        flags: u32,
+    },
+    Eos,
+    /// Sent by the source once the buffer with `id` is not used anymore
+    Release {
+        id: u64,
+    },
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn clock_time_to_u64(t: Option<gst::ClockTime>) -> u64 {
+    t.map_or(u64::MAX, gst::ClockTime::nseconds)
+}
+
+fn clock_time_from_u64(t: u64) -> Option<gst::ClockTime> {
+    if t == u64::MAX {
+        None
+    } else {
+        Some(gst::ClockTime::from_nseconds(t))
+    }
+}
+
+fn read_u64(data: &[u8]) -> u64 {
+    u64::from_le_bytes(data[..8].try_into().unwrap())
+}
+
+impl Message {
+    pub fn write(&self, w: &mut impl Write) -> io::Result<()> {
+        let mut payload = Vec::new();
+
+        let msg_type = match self {
+            Message::Init { shm_path } => {
+                payload.extend_from_slice(shm_path.as_os_str().as_bytes());
+                INIT
+            }
+            Message::Caps(caps) => {
+                payload.extend_from_slice(caps.as_bytes());
+                CAPS
+            }
+            Message::Buffer {
+                id,
+                offset,
+                size,
+                pts,
+                dts,
+                duration,
+                flags,
+            } => {
+                for v in [
+                    *id,
+                    *offset,
+                    *size,
+                    clock_time_to_u64(*pts),
+                    clock_time_to_u64(*dts),
+                    clock_time_to_u64(*duration),
+                ] {
+                    payload.extend_from_slice(&v.to_le_bytes());
+                }
+                // Reserved
+                payload.extend_from_slice(&0u64.to_le_bytes());
+                payload.extend_from_slice(&flags.to_le_bytes());
+                BUFFER
+            }
+            Message::Eos => EOS,
+            Message::Release { id } => {
+                payload.extend_from_slice(&id.to_le_bytes());
+                RELEASE
+            }
+        };
+
+        let mut data = Vec::with_capacity(HEADER_SIZE + payload.len());
+        data.push(msg_type);
+        data.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        data.extend_from_slice(&payload);
+
+        w.write_all(&data)
+    }
+
+    fn parse(msg_type: u8, payload: &[u8]) -> io::Result<Message> {
+        match msg_type {
+            INIT => Ok(Message::Init {
+                shm_path: PathBuf::from(OsStr::from_bytes(payload)),
+            }),
+            CAPS => std::str::from_utf8(payload)
+                .map(|caps| Message::Caps(caps.to_string()))
+                .map_err(|_| invalid_data("Invalid caps")),
+            BUFFER if payload.len() == BUFFER_SIZE => Ok(Message::Buffer {
+                id: read_u64(&payload[0..]),
+                offset: read_u64(&payload[8..]),
+                size: read_u64(&payload[16..]),
+                pts: clock_time_from_u64(read_u64(&payload[24..])),
+                dts: clock_time_from_u64(read_u64(&payload[32..])),
+                duration: clock_time_from_u64(read_u64(&payload[40..])),
+                flags: u32::from_le_bytes(payload[56..60].try_into().unwrap()),
+            }),
+            EOS => Ok(Message::Eos),
+            RELEASE if payload.len() == 8 => Ok(Message::Release {
+                id: read_u64(payload),
+            }),
+            _ => Err(invalid_data("Invalid message")),
+        }
+    }
+}
+
+/// Collects data read from a stream until complete messages are available
+#[derive(Debug, Default)]
+pub struct MessageReader {
+    data: Vec<u8>,
+}
+
+impl MessageReader {
+    /// Reads once from `r`, returns `UnexpectedEof` if the peer closed the stream
+    pub fn read_from(&mut self, r: &mut impl Read) -> io::Result<()> {
+        let mut buf = [0u8; 4096];
+        let len = r.read(&mut buf)?;
+        if len == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        self.data.extend_from_slice(&buf[..len]);
+
+        Ok(())
+    }
+
+    /// Returns the next complete message, if any
+    pub fn next_message(&mut self) -> io::Result<Option<Message>> {
+        if self.data.len() < HEADER_SIZE {
+            return Ok(None);
+        }
+
+        let size = u32::from_le_bytes(self.data[1..HEADER_SIZE].try_into().unwrap()) as usize;
+        if size > MAX_PAYLOAD_SIZE {
+            return Err(invalid_data("Message too big"));
+        }
+        if self.data.len() < HEADER_SIZE + size {
+            return Ok(None);
+        }
+
+        let msg = Message::parse(self.data[0], &self.data[HEADER_SIZE..][..size]);
+        self.data.drain(..HEADER_SIZE + size);
+
+        msg.map(Some)
+    }
+
+    /// Blocks until the next complete message is available
+    pub fn read_message(&mut self, r: &mut impl Read) -> io::Result<Message> {
+        loop {
+            if let Some(msg) = self.next_message()? {
+                return Ok(msg);
+            }
+            self.read_from(r)?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let msgs = [
+            Message::Init {
+                shm_path: PathBuf::from("/dev/shm/test"),
+            },
+            Message::Caps("video/x-raw".to_string()),
+            Message::Buffer {
+                id: 1,
+                offset: 4096,
+                size: 100,
+                pts: Some(gst::ClockTime::SECOND),
+                dts: None,
+                duration: Some(gst::ClockTime::ZERO),
+                flags: 0x2000,
+            },
+            Message::Eos,
+            Message::Release { id: 1 },
+        ];
+
+        let mut data = Vec::new();
+        for msg in &msgs {
+            msg.write(&mut data).unwrap();
+        }
+
+        // Feed the data in small pieces to check partial messages
+        let mut reader = MessageReader::default();
+        let mut parsed = Vec::new();
+        for chunk in data.chunks(3) {
+            reader.read_from(&mut &chunk[..]).unwrap();
+            while let Some(msg) = reader.next_message().unwrap() {
+                parsed.push(msg);
+            }
+        }
+
+        assert_eq!(&parsed[..], &msgs[..]);
+    }
+}
@@ -0,0 +1,592 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::prelude::*;
+use gst_base::subclass::prelude::*;
+
+use memmap2::MmapMut;
+use once_cell::sync::Lazy;
+
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+use crate::protocol::{Message, MessageReader};
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "rsshmsink",
+        gst::DebugColorFlags::empty(),
+        Some("Shared Memory Sink"),
+    )
+});
+
+const DEFAULT_SOCKET_PATH: Option<String> = None;
+const DEFAULT_SHM_SIZE: u32 = 64 * 1024 * 1024;
+const DEFAULT_WAIT_FOR_CONNECTION: bool = false;
+
+#[derive(Debug, Clone)]
+struct Settings {
+    socket_path: Option<String>,
+    shm_size: u32,
+    wait_for_connection: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            socket_path: DEFAULT_SOCKET_PATH,
+            shm_size: DEFAULT_SHM_SIZE,
+            wait_for_connection: DEFAULT_WAIT_FOR_CONNECTION,
+        }
+    }
+}
+
+/// Part of the shared memory used by a buffer until all clients released it
+#[derive(Debug)]
+struct Region {
+    id: u64,
+    offset: u64,
+    size: u64,
+    clients: HashSet<u64>,
+}
+
+struct Inner {
+    mmap: MmapMut,
+    /// Sorted by offset
+    regions: Vec<Region>,
+    /// Allocations continue after the last one, like in a ring buffer
+    ring_pos: u64,
+    next_buffer_id: u64,
+    clients: HashMap<u64, UnixStream>,
+    next_client_id: u64,
+    caps: Option<String>,
+    flushing: bool,
+    shutdown: bool,
+}
+
+impl Inner {
+    fn allocate(&mut self, size: u64) -> Option<u64> {
+        let len = self.mmap.len() as u64;
+
+        let mut gaps = Vec::new();
+        let mut prev_end = 0;
+        for region in &self.regions {
+            if region.offset > prev_end {
+                gaps.push((prev_end, region.offset));
+            }
+            prev_end = prev_end.max(region.offset + region.size);
+        }
+        gaps.push((prev_end, len));
+
+        let offset = gaps
+            .iter()
+            .filter(|(_, end)| *end >= self.ring_pos)
+            .map(|(start, end)| ((*start).max(self.ring_pos), *end))
+            .chain(gaps.iter().copied())
+            .find(|(start, end)| end - start >= size)
+            .map(|(start, _)| start)?;
+
+        self.ring_pos = offset + size;
+
+        Some(offset)
+    }
+
+    fn send(&mut self, msg: &Message) {
+        self.clients.retain(|id, stream| match msg.write(stream) {
+            Ok(()) => true,
+            Err(err) => {
+                gst::debug!(CAT, "Dropping client {id}: {err}");
+                let _ = stream.shutdown(std::net::Shutdown::Both);
+                false
+            }
+        });
+    }
+
+    fn release(&mut self, client_id: u64, buffer_id: Option<u64>) {
+        self.regions.retain_mut(|region| {
+            if buffer_id.map_or(true, |id| id == region.id) {
+                region.clients.remove(&client_id);
+            }
+            !region.clients.is_empty()
+        });
+    }
+}
+
+struct Shared {
+    inner: Mutex<Inner>,
+    cond: Condvar,
+}
+
+struct State {
+    shared: Arc<Shared>,
+    socket_path: PathBuf,
+    shm_path: PathBuf,
+    accept_thread: Option<thread::JoinHandle<()>>,
+}
+
+#[derive(Default)]
+pub struct ShmSink {
+    settings: Mutex<Settings>,
+    state: Mutex<Option<State>>,
+}
+
+impl ShmSink {
+    fn shared(&self) -> Option<Arc<Shared>> {
+        self.state
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|state| state.shared.clone())
+    }
+
+    fn accept_loop(&self, listener: UnixListener, shared: Arc<Shared>, shm_path: PathBuf) {
+        for stream in listener.incoming() {
+            let mut stream = match stream {
+                Ok(stream) => stream,
+                Err(err) => {
+                    gst::warning!(CAT, imp: self, "Failed to accept connection: {err}");
+                    continue;
+                }
+            };
+
+            let mut inner = shared.inner.lock().unwrap();
+            if inner.shutdown {
+                break;
+            }
+
+            let client_id = inner.next_client_id;
+            inner.next_client_id += 1;
+
+            let res = Message::Init {
+                shm_path: shm_path.clone(),
+            }
+            .write(&mut stream)
+            .and_then(|_| match inner.caps {
+                Some(ref caps) => Message::Caps(caps.clone()).write(&mut stream),
+                None => Ok(()),
+            })
+            .and_then(|_| stream.try_clone());
+
+            let reader = match res {
+                Ok(reader) => reader,
+                Err(err) => {
+                    gst::warning!(CAT, imp: self, "Failed to set up client: {err}");
+                    continue;
+                }
+            };
+
+            gst::debug!(CAT, imp: self, "New client {client_id}");
+            inner.clients.insert(client_id, stream);
+            shared.cond.notify_all();
+            drop(inner);
+
+            let shared = shared.clone();
+            let obj = self.obj().clone();
+            let res = thread::Builder::new()
+                .name(format!("rsshmsink-client-{client_id}"))
+                .spawn(move || obj.imp().client_loop(client_id, reader, &shared));
+            if let Err(err) = res {
+                gst::warning!(CAT, imp: self, "Failed to spawn client thread: {err}");
+            }
+        }
+    }
+
+    fn client_loop(&self, client_id: u64, mut stream: UnixStream, shared: &Shared) {
+        let mut reader = MessageReader::default();
+
+        loop {
+            match reader.read_message(&mut stream) {
+                Ok(Message::Release { id }) => {
+                    let mut inner = shared.inner.lock().unwrap();
+                    inner.release(client_id, Some(id));
+                    shared.cond.notify_all();
+                }
+                Ok(msg) => {
+                    gst::warning!(CAT, imp: self, "Unexpected message {msg:?} from client {client_id}");
+                }
+                Err(err) => {
+                    if err.kind() != io::ErrorKind::UnexpectedEof {
+                        gst::warning!(CAT, imp: self, "Failed to read from client {client_id}: {err}");
+                    }
+                    break;
+                }
+            }
+        }
+
+        gst::debug!(CAT, imp: self, "Client {client_id} disconnected");
+
+        let mut inner = shared.inner.lock().unwrap();
+        inner.clients.remove(&client_id);
+        inner.release(client_id, None);
+        shared.cond.notify_all();
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for ShmSink {
+    const NAME: &'static str = "GstRsShmSink";
+    type Type = super::ShmSink;
+    type ParentType = gst_base::BaseSink;
+}
+
+impl ObjectImpl for ShmSink {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecString::builder("socket-path")
+                    .nick("Socket Path")
+                    .blurb("Path of the control socket to listen on")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("shm-size")
+                    .nick("Shared Memory Size")
+                    .blurb("Size of the shared memory area in bytes")
+                    .minimum(4096)
+                    .default_value(DEFAULT_SHM_SIZE)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("wait-for-connection")
+                    .nick("Wait For Connection")
+                    .blurb("Block the stream until at least one client is connected")
+                    .default_value(DEFAULT_WAIT_FOR_CONNECTION)
+                    .mutable_playing()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "socket-path" => {
+                settings.socket_path = value.get().expect("type checked upstream");
+            }
+            "shm-size" => {
+                settings.shm_size = value.get().expect("type checked upstream");
+            }
+            "wait-for-connection" => {
+                settings.wait_for_connection = value.get().expect("type checked upstream");
+                drop(settings);
+
+                if let Some(shared) = self.shared() {
+                    shared.cond.notify_all();
+                }
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "socket-path" => settings.socket_path.to_value(),
+            "shm-size" => settings.shm_size.to_value(),
+            "wait-for-connection" => settings.wait_for_connection.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        self.obj().set_sync(false);
+    }
+}
+
+impl GstObjectImpl for ShmSink {}
+
+impl ElementImpl for ShmSink {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Shared Memory Sink",
+                "Sink",
+                "Sends buffers to other processes through shared memory",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &gst::Caps::new_any(),
+            )
+            .unwrap();
+
+            vec![sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseSinkImpl for ShmSink {
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        static SHM_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+        let settings = self.settings.lock().unwrap().clone();
+        let socket_path = PathBuf::from(settings.socket_path.ok_or_else(|| {
+            gst::error_msg!(gst::ResourceError::Settings, ["No socket path set"])
+        })?);
+
+        let shm_dir = Path::new("/dev/shm");
+        let shm_dir = if shm_dir.is_dir() {
+            shm_dir.to_path_buf()
+        } else {
+            std::env::temp_dir()
+        };
+        let shm_path = shm_dir.join(format!(
+            "gst-rsshm-{}-{}",
+            std::process::id(),
+            SHM_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&shm_path)
+            .and_then(|file| file.set_len(settings.shm_size as u64).map(|_| file))
+            .map_err(|err| {
+                gst::error_msg!(
+                    gst::ResourceError::OpenWrite,
+                    ["Failed to create {}: {}", shm_path.display(), err]
+                )
+            })?;
+
+        // SAFETY: The file was created above and is only modified through this mapping
+        let mmap = unsafe { MmapMut::map_mut(&file) }.map_err(|err| {
+            let _ = fs::remove_file(&shm_path);
+            gst::error_msg!(
+                gst::ResourceError::OpenWrite,
+                ["Failed to map {}: {}", shm_path.display(), err]
+            )
+        })?;
+
+        // Remove stale sockets of previous runs
+        if socket_path.exists() {
+            let _ = fs::remove_file(&socket_path);
+        }
+        let listener = UnixListener::bind(&socket_path).map_err(|err| {
+            let _ = fs::remove_file(&shm_path);
+            gst::error_msg!(
+                gst::ResourceError::OpenWrite,
+                ["Failed to listen on {}: {}", socket_path.display(), err]
+            )
+        })?;
+
+        let shared = Arc::new(Shared {
+            inner: Mutex::new(Inner {
+                mmap,
+                regions: Vec::new(),
+                ring_pos: 0,
+                next_buffer_id: 0,
+                clients: HashMap::new(),
+                next_client_id: 0,
+                caps: None,
+                flushing: false,
+                shutdown: false,
+            }),
+            cond: Condvar::new(),
+        });
+
+        let accept_thread = thread::Builder::new()
+            .name("rsshmsink-accept".into())
+            .spawn({
+                let obj = self.obj().clone();
+                let shared = shared.clone();
+                let shm_path = shm_path.clone();
+                move || obj.imp().accept_loop(listener, shared, shm_path)
+            })
+            .map_err(|err| {
+                let _ = fs::remove_file(&shm_path);
+                let _ = fs::remove_file(&socket_path);
+                gst::error_msg!(
+                    gst::ResourceError::Failed,
+                    ["Failed to spawn thread: {}", err]
+                )
+            })?;
+
+        gst::debug!(
+            CAT,
+            imp: self,
+            "Listening on {:?}, shared memory {:?}",
+            socket_path,
+            shm_path
+        );
+
+        *self.state.lock().unwrap() = Some(State {
+            shared,
+            socket_path,
+            shm_path,
+            accept_thread: Some(accept_thread),
+        });
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        let Some(mut state) = self.state.lock().unwrap().take() else {
+            return Ok(());
+        };
+
+        {
+            let mut inner = state.shared.inner.lock().unwrap();
+            inner.shutdown = true;
+            for stream in inner.clients.values() {
+                let _ = stream.shutdown(std::net::Shutdown::Both);
+            }
+            inner.clients.clear();
+            state.shared.cond.notify_all();
+        }
+
+        // Wake up the accept thread
+        let _ = UnixStream::connect(&state.socket_path);
+        if let Some(accept_thread) = state.accept_thread.take() {
+            let _ = accept_thread.join();
+        }
+
+        let _ = fs::remove_file(&state.socket_path);
+        let _ = fs::remove_file(&state.shm_path);
+
+        gst::debug!(CAT, imp: self, "Stopped");
+
+        Ok(())
+    }
+
+    fn set_caps(&self, caps: &gst::Caps) -> Result<(), gst::LoggableError> {
+        let shared = self
+            .shared()
+            .ok_or_else(|| gst::loggable_error!(CAT, "Not started"))?;
+
+        let mut inner = shared.inner.lock().unwrap();
+        let caps = caps.to_string();
+        inner.send(&Message::Caps(caps.clone()));
+        inner.caps = Some(caps);
+
+        Ok(())
+    }
+
+    fn event(&self, event: gst::Event) -> bool {
+        if let gst::EventView::Eos(_) = event.view() {
+            if let Some(shared) = self.shared() {
+                shared.inner.lock().unwrap().send(&Message::Eos);
+            }
+        }
+
+        self.parent_event(event)
+    }
+
+    fn unlock(&self) -> Result<(), gst::ErrorMessage> {
+        if let Some(shared) = self.shared() {
+            shared.inner.lock().unwrap().flushing = true;
+            shared.cond.notify_all();
+        }
+
+        Ok(())
+    }
+
+    fn unlock_stop(&self) -> Result<(), gst::ErrorMessage> {
+        if let Some(shared) = self.shared() {
+            shared.inner.lock().unwrap().flushing = false;
+        }
+
+        Ok(())
+    }
+
+    fn render(&self, buffer: &gst::Buffer) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let shared = self.shared().ok_or(gst::FlowError::Flushing)?;
+
+        let map = buffer.map_readable().map_err(|_| {
+            gst::element_imp_error!(self, gst::CoreError::Failed, ["Failed to map buffer"]);
+            gst::FlowError::Error
+        })?;
+        let size = map.len() as u64;
+
+        let mut inner = shared.inner.lock().unwrap();
+        if size > inner.mmap.len() as u64 {
+            gst::element_imp_error!(
+                self,
+                gst::ResourceError::NoSpaceLeft,
+                ["Buffer of {} bytes doesn't fit into shared memory", size]
+            );
+            return Err(gst::FlowError::Error);
+        }
+
+        let offset = loop {
+            if inner.flushing || inner.shutdown {
+                return Err(gst::FlowError::Flushing);
+            }
+
+            if inner.clients.is_empty() {
+                if !self.settings.lock().unwrap().wait_for_connection {
+                    gst::trace!(CAT, imp: self, "No clients, dropping {buffer:?}");
+                    return Ok(gst::FlowSuccess::Ok);
+                }
+            } else if let Some(offset) = inner.allocate(size) {
+                break offset;
+            }
+
+            inner = shared.cond.wait(inner).unwrap();
+        };
+
+        inner.mmap[offset as usize..][..size as usize].copy_from_slice(&map);
+
+        let id = inner.next_buffer_id;
+        inner.next_buffer_id += 1;
+
+        gst::trace!(CAT, imp: self, "Sending {buffer:?} as {id} at offset {offset}");
+
+        inner.send(&Message::Buffer {
+            id,
+            offset,
+            size,
+            pts: buffer.pts(),
+            dts: buffer.dts(),
+            duration: buffer.duration(),
+            flags: buffer.flags().bits(),
+        });
+
+        let clients = inner.clients.keys().copied().collect::<HashSet<_>>();
+        if !clients.is_empty() {
+            let pos = inner
+                .regions
+                .partition_point(|region| region.offset <= offset);
+            inner.regions.insert(
+                pos,
+                Region {
+                    id,
+                    offset,
+                    size,
+                    clients,
+                },
+            );
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+}
@@ -0,0 +1,44 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * element-rsshmsink:
+ *
+ * Sends buffers to #rsshmsrc elements in other processes. The buffers are
+ * copied into a shared memory area and announced over a unix socket that
+ * clients connect to, and the memory is reused once all clients released
+ * the buffers again.
+ *
+ * The caps and the timestamps of the buffers are forwarded to the clients as
+ * they are. If no client is connected, buffers are dropped unless
+ * #rsshmsink:wait-for-connection is set.
+ *
+ * ## Example pipeline
+ * ```bash
+ * gst-launch-1.0 videotestsrc is-live=true ! video/x-raw,format=I420,width=640,height=480 ! rsshmsink socket-path=/tmp/video
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct ShmSink(ObjectSubclass<imp::ShmSink>) @extends gst_base::BaseSink, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "rsshmsink",
+        gst::Rank::NONE,
+        ShmSink::static_type(),
+    )
+}
@@ -0,0 +1,434 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::prelude::*;
+use gst_base::subclass::base_src::CreateSuccess;
+use gst_base::subclass::prelude::*;
+
+use memmap2::Mmap;
+use once_cell::sync::Lazy;
+
+use std::fs::File;
+use std::io;
+use std::ops::Range;
+use std::os::unix::net::UnixStream;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::protocol::{Message, MessageReader};
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "rsshmsrc",
+        gst::DebugColorFlags::empty(),
+        Some("Shared Memory Source"),
+    )
+});
+
+const DEFAULT_SOCKET_PATH: Option<String> = None;
+const DEFAULT_IS_LIVE: bool = false;
+
+/// How long to wait for the sink to describe the shared memory
+const INIT_TIMEOUT: Duration = Duration::from_secs(5);
+/// How often to check for unlock() while waiting for buffers
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone)]
+struct Settings {
+    socket_path: Option<String>,
+    is_live: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            socket_path: DEFAULT_SOCKET_PATH,
+            is_live: DEFAULT_IS_LIVE,
+        }
+    }
+}
+
+/// Tells the sink that a buffer is not used anymore once dropped
+struct Release {
+    id: u64,
+    stream: Arc<Mutex<UnixStream>>,
+}
+
+impl Drop for Release {
+    fn drop(&mut self) {
+        let mut stream = self.stream.lock().unwrap();
+        // Fails if the sink is gone already, nothing to release then
+        let _ = Message::Release { id: self.id }.write(&mut *stream);
+    }
+}
+
+/// Part of the shared memory, wrapped by the output buffers
+struct SharedRegion {
+    mmap: Arc<Mmap>,
+    range: Range<usize>,
+    _release: Release,
+}
+
+impl AsRef<[u8]> for SharedRegion {
+    fn as_ref(&self) -> &[u8] {
+        &self.mmap[self.range.clone()]
+    }
+}
+
+struct State {
+    stream: UnixStream,
+    reader: MessageReader,
+    writer: Arc<Mutex<UnixStream>>,
+    mmap: Arc<Mmap>,
+}
+
+#[derive(Default)]
+pub struct ShmSrc {
+    settings: Mutex<Settings>,
+    state: Mutex<Option<State>>,
+    flushing: AtomicBool,
+}
+
+impl ShmSrc {
+    fn read_message(&self, state: &mut State) -> Result<Message, gst::FlowError> {
+        loop {
+            if let Some(msg) = state.reader.next_message().map_err(|err| {
+                gst::element_imp_error!(
+                    self,
+                    gst::StreamError::Decode,
+                    ["Invalid message: {}", err]
+                );
+                gst::FlowError::Error
+            })? {
+                return Ok(msg);
+            }
+
+            if self.flushing.load(Ordering::SeqCst) {
+                return Err(gst::FlowError::Flushing);
+            }
+
+            match state.reader.read_from(&mut state.stream) {
+                Ok(()) => (),
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) => {}
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                    gst::debug!(CAT, imp: self, "Sink disconnected");
+                    return Err(gst::FlowError::Eos);
+                }
+                Err(err) => {
+                    gst::element_imp_error!(
+                        self,
+                        gst::ResourceError::Read,
+                        ["Failed to read from socket: {}", err]
+                    );
+                    return Err(gst::FlowError::Error);
+                }
+            }
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for ShmSrc {
+    const NAME: &'static str = "GstRsShmSrc";
+    type Type = super::ShmSrc;
+    type ParentType = gst_base::PushSrc;
+}
+
+impl ObjectImpl for ShmSrc {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecString::builder("socket-path")
+                    .nick("Socket Path")
+                    .blurb("Path of the control socket of the rsshmsink")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("is-live")
+                    .nick("Is Live")
+                    .blurb("Act as a live source")
+                    .default_value(DEFAULT_IS_LIVE)
+                    .mutable_ready()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "socket-path" => {
+                settings.socket_path = value.get().expect("type checked upstream");
+            }
+            "is-live" => {
+                settings.is_live = value.get().expect("type checked upstream");
+                let is_live = settings.is_live;
+                drop(settings);
+                self.obj().set_live(is_live);
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "socket-path" => settings.socket_path.to_value(),
+            "is-live" => settings.is_live.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.set_format(gst::Format::Time);
+        obj.set_live(DEFAULT_IS_LIVE);
+    }
+}
+
+impl GstObjectImpl for ShmSrc {}
+
+impl ElementImpl for ShmSrc {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Shared Memory Source",
+                "Source",
+                "Receives buffers from other processes through shared memory",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &gst::Caps::new_any(),
+            )
+            .unwrap();
+
+            vec![src_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseSrcImpl for ShmSrc {
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        let settings = self.settings.lock().unwrap().clone();
+        let socket_path = settings
+            .socket_path
+            .ok_or_else(|| gst::error_msg!(gst::ResourceError::Settings, ["No socket path set"]))?;
+
+        let mut stream = UnixStream::connect(&socket_path).map_err(|err| {
+            gst::error_msg!(
+                gst::ResourceError::OpenRead,
+                ["Failed to connect to {}: {}", socket_path, err]
+            )
+        })?;
+
+        let mut reader = MessageReader::default();
+        let shm_path = stream
+            .set_read_timeout(Some(INIT_TIMEOUT))
+            .and_then(|_| reader.read_message(&mut stream))
+            .and_then(|msg| match msg {
+                Message::Init { shm_path } => Ok(shm_path),
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Unexpected message",
+                )),
+            })
+            .map_err(|err| {
+                gst::error_msg!(
+                    gst::ResourceError::OpenRead,
+                    [
+                        "Failed to initialize connection to {}: {}",
+                        socket_path,
+                        err
+                    ]
+                )
+            })?;
+
+        let file = File::open(&shm_path).map_err(|err| {
+            gst::error_msg!(
+                gst::ResourceError::OpenRead,
+                ["Failed to open {}: {}", shm_path.display(), err]
+            )
+        })?;
+
+        // SAFETY: The sink only writes to parts of the shared memory that are
+        // not used by any output buffers
+        let mmap = unsafe { Mmap::map(&file) }.map_err(|err| {
+            gst::error_msg!(
+                gst::ResourceError::OpenRead,
+                ["Failed to map {}: {}", shm_path.display(), err]
+            )
+        })?;
+
+        let writer = stream
+            .try_clone()
+            .and_then(|writer| stream.set_read_timeout(Some(POLL_INTERVAL)).map(|_| writer))
+            .map_err(|err| {
+                gst::error_msg!(
+                    gst::ResourceError::OpenRead,
+                    ["Failed to set up socket: {}", err]
+                )
+            })?;
+
+        gst::debug!(
+            CAT,
+            imp: self,
+            "Connected to {}, shared memory {:?}",
+            socket_path,
+            shm_path
+        );
+
+        *self.state.lock().unwrap() = Some(State {
+            stream,
+            reader,
+            writer: Arc::new(Mutex::new(writer)),
+            mmap: Arc::new(mmap),
+        });
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        if let Some(state) = self.state.lock().unwrap().take() {
+            let _ = state.stream.shutdown(std::net::Shutdown::Both);
+        }
+
+        gst::debug!(CAT, imp: self, "Stopped");
+
+        Ok(())
+    }
+
+    fn is_seekable(&self) -> bool {
+        false
+    }
+
+    fn unlock(&self) -> Result<(), gst::ErrorMessage> {
+        self.flushing.store(true, Ordering::SeqCst);
+
+        Ok(())
+    }
+
+    fn unlock_stop(&self) -> Result<(), gst::ErrorMessage> {
+        self.flushing.store(false, Ordering::SeqCst);
+
+        Ok(())
+    }
+}
+
+impl PushSrcImpl for ShmSrc {
+    fn create(
+        &self,
+        _buffer: Option<&mut gst::BufferRef>,
+    ) -> Result<CreateSuccess, gst::FlowError> {
+        let mut state = self.state.lock().unwrap();
+        let state = state.as_mut().ok_or(gst::FlowError::Flushing)?;
+
+        loop {
+            match self.read_message(state)? {
+                Message::Caps(caps) => {
+                    let caps = gst::Caps::from_str(&caps).map_err(|_| {
+                        gst::element_imp_error!(
+                            self,
+                            gst::StreamError::Format,
+                            ["Invalid caps {}", caps]
+                        );
+                        gst::FlowError::NotNegotiated
+                    })?;
+
+                    gst::debug!(CAT, imp: self, "Received caps {caps}");
+                    self.obj().set_caps(&caps).map_err(|_| {
+                        gst::element_imp_error!(
+                            self,
+                            gst::CoreError::Negotiation,
+                            ["Failed to negotiate caps {}", caps]
+                        );
+                        gst::FlowError::NotNegotiated
+                    })?;
+                }
+                Message::Buffer {
+                    id,
+                    offset,
+                    size,
+                    pts,
+                    dts,
+                    duration,
+                    flags,
+                } => {
+                    let release = Release {
+                        id,
+                        stream: state.writer.clone(),
+                    };
+
+                    let end = offset.checked_add(size).unwrap_or(u64::MAX);
+                    if end > state.mmap.len() as u64 {
+                        gst::element_imp_error!(
+                            self,
+                            gst::StreamError::Decode,
+                            [
+                                "Buffer {} at {}-{} is outside of shared memory",
+                                id,
+                                offset,
+                                end
+                            ]
+                        );
+                        return Err(gst::FlowError::Error);
+                    }
+
+                    let mut buffer = gst::Buffer::from_slice(SharedRegion {
+                        mmap: state.mmap.clone(),
+                        range: offset as usize..end as usize,
+                        _release: release,
+                    });
+                    {
+                        let buffer = buffer.get_mut().unwrap();
+                        buffer.set_pts(pts);
+                        buffer.set_dts(dts);
+                        buffer.set_duration(duration);
+                        buffer.set_flags(gst::BufferFlags::from_bits_truncate(flags));
+                    }
+
+                    gst::trace!(CAT, imp: self, "Received {buffer:?} as {id}");
+
+                    return Ok(CreateSuccess::NewBuffer(buffer));
+                }
+                Message::Eos => {
+                    gst::debug!(CAT, imp: self, "Received EOS");
+                    return Err(gst::FlowError::Eos);
+                }
+                msg => {
+                    gst::warning!(CAT, imp: self, "Unexpected message {msg:?}");
+                }
+            }
+        }
+    }
+}
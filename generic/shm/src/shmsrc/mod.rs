@@ -0,0 +1,43 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * element-rsshmsrc:
+ *
+ * Receives buffers from an #rsshmsink in another process. The output buffers
+ * directly wrap the shared memory without copying, and the memory is handed
+ * back to the sink once the buffers are freed.
+ *
+ * Timestamps are forwarded unchanged from the sink. When receiving from a
+ * live pipeline, set #rsshmsrc:is-live and `do-timestamp=true` to timestamp
+ * the buffers based on the local clock instead.
+ *
+ * ## Example pipeline
+ * ```bash
+ * gst-launch-1.0 rsshmsrc socket-path=/tmp/video is-live=true do-timestamp=true ! videoconvert ! autovideosink
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct ShmSrc(ObjectSubclass<imp::ShmSrc>) @extends gst_base::PushSrc, gst_base::BaseSrc, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "rsshmsrc",
+        gst::Rank::NONE,
+        ShmSrc::static_type(),
+    )
+}
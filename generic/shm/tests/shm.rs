@@ -0,0 +1,70 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+#![cfg(unix)]
+
+use gst::prelude::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsshm::plugin_register_static().expect("shm test");
+    });
+}
+
+#[test]
+fn test_roundtrip() {
+    init();
+
+    let socket_path = std::env::temp_dir().join(format!("rsshm-test-{}", std::process::id()));
+    let socket_path = socket_path.to_str().unwrap();
+
+    let mut sink = gst_check::Harness::new("rsshmsink");
+    {
+        let sink = sink.element().unwrap();
+        sink.set_property("socket-path", socket_path);
+        sink.set_property("shm-size", 4096u32);
+        sink.set_property("wait-for-connection", true);
+    }
+    sink.play();
+    sink.set_src_caps_str("application/x-test");
+
+    let mut src = gst_check::Harness::new("rsshmsrc");
+    src.element()
+        .unwrap()
+        .set_property("socket-path", socket_path);
+    src.play();
+
+    // Each buffer only fits into the shared memory once the previous one
+    // was released again
+    for i in 0..5u8 {
+        let mut buf = gst::Buffer::from_mut_slice(vec![i; 3000]);
+        buf.get_mut()
+            .unwrap()
+            .set_pts(gst::ClockTime::from_seconds(i as u64));
+        assert_eq!(sink.push(buf), Ok(gst::FlowSuccess::Ok));
+
+        let buf = src.pull().unwrap();
+        assert_eq!(buf.pts(), Some(gst::ClockTime::from_seconds(i as u64)));
+        assert_eq!(&*buf.map_readable().unwrap(), &[i; 3000][..]);
+    }
+
+    let caps = src.sinkpad().unwrap().current_caps().unwrap();
+    assert_eq!(caps, gst::Caps::builder("application/x-test").build());
+
+    assert!(sink.push_event(gst::event::Eos::new()));
+    loop {
+        let event = src.pull_event().unwrap();
+        if event.type_() == gst::EventType::Eos {
+            break;
+        }
+    }
+}
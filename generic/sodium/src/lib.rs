@@ -37,8 +37,17 @@ const TYPEFIND_HEADER_SIZE: usize = 12;
 const HEADERS_SIZE: usize =
     TYPEFIND_HEADER_SIZE + sodiumoxide::crypto::box_::NONCEBYTES + std::mem::size_of::<u32>();
 
+const SECRETSTREAM_TYPEFIND_HEADER: &[u8; 12] = b"gst-sodiumss";
+/// Secret streams store the block_size and the secretstream header, which
+/// contains the nonce, at the start.
+const SECRETSTREAM_HEADERS_SIZE: usize = TYPEFIND_HEADER_SIZE
+    + std::mem::size_of::<u32>()
+    + sodiumoxide::crypto::secretstream::HEADERBYTES;
+
 mod decrypter;
 mod encrypter;
+mod sodiumdec;
+mod sodiumenc;
 
 fn typefind_register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
     use gst::{Caps, TypeFind, TypeFindProbability};
@@ -59,12 +68,32 @@ fn typefind_register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
                 }
             }
         },
+    )?;
+
+    TypeFind::register(
+        Some(plugin),
+        "sodium_secretstream_typefind",
+        gst::Rank::NONE,
+        None,
+        Some(&Caps::builder("application/x-sodium-secretstream").build()),
+        |typefind| {
+            if let Some(data) = typefind.peek(0, TYPEFIND_HEADER_SIZE as u32) {
+                if data == SECRETSTREAM_TYPEFIND_HEADER {
+                    typefind.suggest(
+                        TypeFindProbability::Maximum,
+                        &Caps::builder("application/x-sodium-secretstream").build(),
+                    );
+                }
+            }
+        },
     )
 }
 
 fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
     encrypter::register(plugin)?;
     decrypter::register(plugin)?;
+    sodiumenc::register(plugin)?;
+    sodiumdec::register(plugin)?;
     typefind_register(plugin)?;
     Ok(())
 }
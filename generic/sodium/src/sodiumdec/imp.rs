@@ -0,0 +1,457 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+//
+// SPDX-License-Identifier: MIT
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use smallvec::SmallVec;
+use sodiumoxide::crypto::secretstream::{self, Pull, Stream, Tag};
+
+type BufferVec = SmallVec<[gst::Buffer; 16]>;
+
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "sodiumdec",
+        gst::DebugColorFlags::empty(),
+        Some("Secret Stream Decrypter Element"),
+    )
+});
+
+#[derive(Debug, Clone, Default)]
+struct Props {
+    key: Option<glib::Bytes>,
+}
+
+struct State {
+    adapter: gst_base::UniqueAdapter,
+    key: secretstream::Key,
+    /// Stream and block size, once the headers were read
+    stream: Option<(Stream<Pull>, usize)>,
+    finalized: bool,
+}
+
+impl State {
+    fn from_props(props: &Props) -> Result<Self, gst::ErrorMessage> {
+        let key = props
+            .key
+            .as_ref()
+            .and_then(|k| secretstream::Key::from_slice(k))
+            .ok_or_else(|| {
+                gst::error_msg!(
+                    gst::ResourceError::NotFound,
+                    [
+                        "Failed to set Key from property, expected {} bytes",
+                        secretstream::KEYBYTES
+                    ]
+                )
+            })?;
+
+        Ok(Self {
+            adapter: gst_base::UniqueAdapter::new(),
+            key,
+            stream: None,
+            finalized: false,
+        })
+    }
+
+    /// Parses the headers once enough data is available
+    fn read_headers(&mut self) -> Result<bool, gst::ErrorMessage> {
+        if self.stream.is_some() {
+            return Ok(true);
+        }
+        if self.adapter.available() < crate::SECRETSTREAM_HEADERS_SIZE {
+            return Ok(false);
+        }
+
+        let headers = self
+            .adapter
+            .take_buffer(crate::SECRETSTREAM_HEADERS_SIZE)
+            .unwrap();
+        let map = headers.map_readable().unwrap();
+        let (magic, rest) = map.split_at(crate::TYPEFIND_HEADER_SIZE);
+        let (block_size, header) = rest.split_at(std::mem::size_of::<u32>());
+
+        if magic != crate::SECRETSTREAM_TYPEFIND_HEADER {
+            return Err(gst::error_msg!(
+                gst::StreamError::WrongType,
+                ["Not a secretstream encrypted stream"]
+            ));
+        }
+
+        let block_size = u32::from_le_bytes(block_size.try_into().unwrap()) as usize;
+        let header = secretstream::Header::from_slice(header).unwrap();
+        let stream = Stream::init_pull(&header, &self.key).map_err(|_| {
+            gst::error_msg!(
+                gst::StreamError::Decrypt,
+                ["Failed to initialize the decryption stream"]
+            )
+        })?;
+
+        self.stream = Some((stream, block_size));
+
+        Ok(true)
+    }
+
+    fn decrypt_message(&mut self, len: usize) -> Result<gst::Buffer, gst::ErrorMessage> {
+        if self.finalized {
+            return Err(gst::error_msg!(
+                gst::StreamError::Decrypt,
+                ["Data after the end of the stream"]
+            ));
+        }
+
+        let (stream, _) = self.stream.as_mut().unwrap();
+        let ciphertext = self.adapter.take_buffer(len).unwrap();
+        let map = ciphertext.map_readable().unwrap();
+
+        let (message, tag) = stream.pull(&map, None).map_err(|_| {
+            gst::error_msg!(
+                gst::StreamError::Decrypt,
+                ["Failed to decrypt, wrong key or corrupted data"]
+            )
+        })?;
+
+        if tag == Tag::Final {
+            self.finalized = true;
+        }
+
+        Ok(gst::Buffer::from_mut_slice(message))
+    }
+
+    fn decrypt_blocks(&mut self) -> Result<BufferVec, gst::ErrorMessage> {
+        let mut buffers = BufferVec::new();
+
+        if !self.read_headers()? {
+            return Ok(buffers);
+        }
+
+        let len = self.stream.as_ref().unwrap().1 + secretstream::ABYTES;
+        while self.adapter.available() >= len {
+            buffers.push(self.decrypt_message(len)?);
+        }
+
+        Ok(buffers)
+    }
+
+    fn decrypt_final(&mut self) -> Result<Option<gst::Buffer>, gst::ErrorMessage> {
+        let avail = self.adapter.available();
+
+        if self.stream.is_none() {
+            if avail == 0 {
+                // Nothing was encrypted
+                return Ok(None);
+            }
+
+            return Err(gst::error_msg!(
+                gst::StreamError::Decrypt,
+                ["Stream too short for the headers"]
+            ));
+        }
+
+        let buffer = if avail > 0 {
+            Some(self.decrypt_message(avail)?)
+        } else {
+            None
+        };
+
+        // Without the final message the stream was truncated
+        if !self.finalized {
+            return Err(gst::error_msg!(
+                gst::StreamError::Decrypt,
+                ["Stream is truncated"]
+            ));
+        }
+
+        Ok(buffer.filter(|buffer| buffer.size() > 0))
+    }
+}
+
+pub struct SodiumDec {
+    srcpad: gst::Pad,
+    sinkpad: gst::Pad,
+    props: Mutex<Props>,
+    state: Mutex<Option<State>>,
+}
+
+impl SodiumDec {
+    fn sink_chain(
+        &self,
+        pad: &gst::Pad,
+        buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::log!(CAT, obj: pad, "Handling buffer {:?}", buffer);
+
+        let mut state_guard = self.state.lock().unwrap();
+        let state = state_guard.as_mut().unwrap();
+
+        state.adapter.push(buffer);
+
+        let buffers = state.decrypt_blocks().map_err(|err| {
+            self.post_error_message(err);
+            gst::FlowError::Error
+        })?;
+
+        drop(state_guard);
+
+        for buffer in buffers {
+            self.srcpad.push(buffer).map_err(|err| {
+                gst::error!(CAT, imp: self, "Failed to push buffer {:?}", err);
+                err
+            })?;
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    fn sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::log!(CAT, obj: pad, "Handling event {:?}", event);
+
+        match event.view() {
+            EventView::Caps(_) => {
+                // The type of the decrypted stream is unknown, downstream has to typefind it
+                true
+            }
+            EventView::Eos(_) => {
+                let mut state_guard = self.state.lock().unwrap();
+                let state = state_guard.as_mut().unwrap();
+
+                let buffer = match state.decrypt_final() {
+                    Ok(buffer) => buffer,
+                    Err(err) => {
+                        drop(state_guard);
+                        self.post_error_message(err);
+                        return false;
+                    }
+                };
+
+                // drop the lock before pushing into the pad
+                drop(state_guard);
+
+                if let Some(buffer) = buffer {
+                    if let Err(err) = self.srcpad.push(buffer) {
+                        gst::error!(CAT, imp: self, "Failed to push buffer at EOS {:?}", err);
+                        return false;
+                    }
+                }
+
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            _ => gst::Pad::event_default(pad, Some(&*self.obj()), event),
+        }
+    }
+
+    fn src_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::log!(CAT, obj: pad, "Handling event {:?}", event);
+
+        match event.view() {
+            EventView::Seek(_) => false,
+            _ => gst::Pad::event_default(pad, Some(&*self.obj()), event),
+        }
+    }
+
+    fn src_query(&self, pad: &gst::Pad, query: &mut gst::QueryRef) -> bool {
+        use gst::QueryViewMut;
+
+        gst::log!(CAT, obj: pad, "Handling query {:?}", query);
+
+        match query.view_mut() {
+            QueryViewMut::Seeking(q) => {
+                let format = q.format();
+                q.set(
+                    false,
+                    gst::GenericFormattedValue::none_for_format(format),
+                    gst::GenericFormattedValue::none_for_format(format),
+                );
+                gst::log!(CAT, obj: pad, "Returning {:?}", q.query_mut());
+                true
+            }
+            // The size depends on the block size in the stream headers
+            QueryViewMut::Duration(q) if q.format() == gst::Format::Bytes => false,
+            _ => gst::Pad::query_default(pad, Some(&*self.obj()), query),
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for SodiumDec {
+    const NAME: &'static str = "GstSodiumDec";
+    type Type = super::SodiumDec;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("sink").unwrap();
+        let sinkpad = gst::Pad::builder_from_template(&templ)
+            .chain_function(|pad, parent, buffer| {
+                SodiumDec::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |dec| dec.sink_chain(pad, buffer),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                SodiumDec::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |dec| dec.sink_event(pad, event),
+                )
+            })
+            .build();
+
+        let templ = klass.pad_template("src").unwrap();
+        let srcpad = gst::Pad::builder_from_template(&templ)
+            .query_function(|pad, parent, query| {
+                SodiumDec::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |dec| dec.src_query(pad, query),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                SodiumDec::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |dec| dec.src_event(pad, event),
+                )
+            })
+            .build();
+
+        Self {
+            srcpad,
+            sinkpad,
+            props: Mutex::new(Props::default()),
+            state: Mutex::new(None),
+        }
+    }
+}
+
+impl ObjectImpl for SodiumDec {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![glib::ParamSpecBoxed::builder::<glib::Bytes>("key")
+                .nick("Key")
+                .blurb("The secret key, shared with the encrypter")
+                .write_only()
+                .mutable_ready()
+                .build()]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.sinkpad).unwrap();
+        obj.add_pad(&self.srcpad).unwrap();
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "key" => {
+                let mut props = self.props.lock().unwrap();
+                props.key = value.get().expect("type checked upstream");
+            }
+
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, _pspec: &glib::ParamSpec) -> glib::Value {
+        unimplemented!()
+    }
+}
+
+impl GstObjectImpl for SodiumDec {}
+
+impl ElementImpl for SodiumDec {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Secret Stream Decrypter",
+                "Generic",
+                "libsodium secretstream based stream decrypter",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &gst::Caps::new_any(),
+            )
+            .unwrap();
+
+            let sink_caps = gst::Caps::builder("application/x-sodium-secretstream").build();
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &sink_caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        gst::debug!(CAT, imp: self, "Changing state {:?}", transition);
+
+        if transition == gst::StateChange::ReadyToPaused {
+            let props = self.props.lock().unwrap().clone();
+
+            let state = State::from_props(&props).map_err(|err| {
+                self.post_error_message(err);
+                gst::StateChangeError
+            })?;
+
+            *self.state.lock().unwrap() = Some(state);
+        }
+
+        let success = self.parent_change_state(transition)?;
+
+        if transition == gst::StateChange::PausedToReady {
+            let _ = self.state.lock().unwrap().take();
+        }
+
+        Ok(success)
+    }
+}
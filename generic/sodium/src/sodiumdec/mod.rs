@@ -0,0 +1,58 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+//
+// SPDX-License-Identifier: MIT
+
+/**
+ * element-sodiumdec:
+ *
+ * `sodiumdec` decrypts streams encrypted by `sodiumenc` with the shared `key`.
+ *
+ * Every message of the stream is authenticated, and an error is posted if the stream
+ * was modified, encrypted with a different key or is truncated.
+ *
+ * ## Example
+ * ```rust,ignore
+ * let pipeline = gst::parse::launch(
+ *     "filesrc location=input.enc ! sodiumdec name=dec ! decodebin ! autovideosink",
+ * )?;
+ * let dec = pipeline.downcast_ref::<gst::Bin>().unwrap().by_name("dec").unwrap();
+ * dec.set_property("key", glib::Bytes::from(key.as_ref()));
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct SodiumDec(ObjectSubclass<imp::SodiumDec>) @extends gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "sodiumdec",
+        gst::Rank::NONE,
+        SodiumDec::static_type(),
+    )
+}
@@ -0,0 +1,487 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+//
+// SPDX-License-Identifier: MIT
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use smallvec::SmallVec;
+use sodiumoxide::crypto::secretstream::{self, Push, Stream, Tag};
+
+type BufferVec = SmallVec<[gst::Buffer; 16]>;
+
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "sodiumenc",
+        gst::DebugColorFlags::empty(),
+        Some("Secret Stream Encrypter Element"),
+    )
+});
+
+const DEFAULT_BLOCK_SIZE: u32 = 32768;
+
+#[derive(Debug, Clone)]
+struct Props {
+    key: Option<glib::Bytes>,
+    block_size: u32,
+}
+
+impl Default for Props {
+    fn default() -> Self {
+        Props {
+            key: None,
+            block_size: DEFAULT_BLOCK_SIZE,
+        }
+    }
+}
+
+struct State {
+    adapter: gst_base::UniqueAdapter,
+    stream: Stream<Push>,
+    header: secretstream::Header,
+    block_size: u32,
+    write_headers: bool,
+}
+
+impl State {
+    fn from_props(props: &Props) -> Result<Self, gst::ErrorMessage> {
+        let key = props
+            .key
+            .as_ref()
+            .and_then(|k| secretstream::Key::from_slice(k))
+            .ok_or_else(|| {
+                gst::error_msg!(
+                    gst::ResourceError::NotFound,
+                    [
+                        "Failed to set Key from property, expected {} bytes",
+                        secretstream::KEYBYTES
+                    ]
+                )
+            })?;
+
+        let (stream, header) = Stream::init_push(&key).map_err(|_| {
+            gst::error_msg!(
+                gst::LibraryError::Init,
+                ["Failed to initialize the encryption stream"]
+            )
+        })?;
+
+        Ok(Self {
+            adapter: gst_base::UniqueAdapter::new(),
+            stream,
+            header,
+            block_size: props.block_size,
+            write_headers: true,
+        })
+    }
+
+    fn encrypt_message(&mut self, message: &[u8], tag: Tag) -> Result<gst::Buffer, gst::FlowError> {
+        let ciphertext = self
+            .stream
+            .push(message, None, tag)
+            .map_err(|_| gst::FlowError::Error)?;
+
+        Ok(gst::Buffer::from_mut_slice(ciphertext))
+    }
+
+    fn encrypt_blocks(&mut self) -> Result<BufferVec, gst::FlowError> {
+        let block_size = self.block_size as usize;
+        let mut buffers = BufferVec::new();
+
+        // The leftover bytes on the adapter are pushed as the final message on EOS
+        while self.adapter.available() >= block_size {
+            let buffer = self.adapter.take_buffer(block_size).unwrap();
+            let map = buffer.map_readable().map_err(|_| gst::FlowError::Error)?;
+            buffers.push(self.encrypt_message(&map, Tag::Message)?);
+        }
+
+        Ok(buffers)
+    }
+
+    fn encrypt_final(&mut self) -> Result<gst::Buffer, gst::FlowError> {
+        let avail = self.adapter.available();
+        // logic error, all the complete blocks should have been pushed already
+        assert!(avail < self.block_size as usize);
+
+        let message = if avail > 0 {
+            self.adapter.take_buffer(avail).unwrap()
+        } else {
+            gst::Buffer::new()
+        };
+        let map = message.map_readable().map_err(|_| gst::FlowError::Error)?;
+
+        self.encrypt_message(&map, Tag::Final)
+    }
+}
+
+pub struct SodiumEnc {
+    srcpad: gst::Pad,
+    sinkpad: gst::Pad,
+    props: Mutex<Props>,
+    state: Mutex<Option<State>>,
+}
+
+impl SodiumEnc {
+    fn sink_chain(
+        &self,
+        pad: &gst::Pad,
+        buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::log!(CAT, obj: pad, "Handling buffer {:?}", buffer);
+
+        let mut buffers = BufferVec::new();
+        let mut state_guard = self.state.lock().unwrap();
+        let state = state_guard.as_mut().unwrap();
+
+        if state.write_headers {
+            let mut headers = Vec::with_capacity(crate::SECRETSTREAM_HEADERS_SIZE);
+            headers.extend_from_slice(crate::SECRETSTREAM_TYPEFIND_HEADER);
+            // Write the block_size into the stream
+            headers.extend_from_slice(&state.block_size.to_le_bytes());
+            // Write the stream header, which contains the nonce, into the stream
+            headers.extend_from_slice(state.header.as_ref());
+
+            buffers.push(gst::Buffer::from_mut_slice(headers));
+            state.write_headers = false;
+        }
+
+        state.adapter.push(buffer);
+
+        // Encrypt the whole blocks, if any, and push them.
+        buffers.extend(state.encrypt_blocks().map_err(|err| {
+            gst::element_imp_error!(self, gst::StreamError::Encrypt, ["Failed to encrypt"]);
+            err
+        })?);
+
+        drop(state_guard);
+
+        for buffer in buffers {
+            self.srcpad.push(buffer).map_err(|err| {
+                gst::error!(CAT, imp: self, "Failed to push buffer {:?}", err);
+                err
+            })?;
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    fn sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::log!(CAT, obj: pad, "Handling event {:?}", event);
+
+        match event.view() {
+            EventView::Caps(_) => {
+                // We send our own caps downstream
+                let caps = gst::Caps::builder("application/x-sodium-secretstream").build();
+                self.srcpad.push_event(gst::event::Caps::new(&caps))
+            }
+            EventView::Eos(_) => {
+                let mut state_guard = self.state.lock().unwrap();
+                let state = state_guard.as_mut().unwrap();
+
+                // An empty stream has no headers and no final message
+                let buffer = if state.write_headers {
+                    None
+                } else {
+                    match state.encrypt_final() {
+                        Ok(buffer) => Some(buffer),
+                        Err(_) => {
+                            gst::element_imp_error!(
+                                self,
+                                gst::StreamError::Encrypt,
+                                ["Failed to encrypt"]
+                            );
+                            return false;
+                        }
+                    }
+                };
+
+                // drop the lock before pushing into the pad
+                drop(state_guard);
+
+                if let Some(buffer) = buffer {
+                    if let Err(err) = self.srcpad.push(buffer) {
+                        gst::error!(CAT, imp: self, "Failed to push buffer at EOS {:?}", err);
+                        return false;
+                    }
+                }
+
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            _ => gst::Pad::event_default(pad, Some(&*self.obj()), event),
+        }
+    }
+
+    fn src_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::log!(CAT, obj: pad, "Handling event {:?}", event);
+
+        match event.view() {
+            EventView::Seek(_) => false,
+            _ => gst::Pad::event_default(pad, Some(&*self.obj()), event),
+        }
+    }
+
+    fn src_query(&self, pad: &gst::Pad, query: &mut gst::QueryRef) -> bool {
+        use gst::QueryViewMut;
+
+        gst::log!(CAT, obj: pad, "Handling query {:?}", query);
+
+        match query.view_mut() {
+            QueryViewMut::Seeking(q) => {
+                let format = q.format();
+                q.set(
+                    false,
+                    gst::GenericFormattedValue::none_for_format(format),
+                    gst::GenericFormattedValue::none_for_format(format),
+                );
+                gst::log!(CAT, obj: pad, "Returning {:?}", q.query_mut());
+                true
+            }
+            QueryViewMut::Duration(q) => {
+                if q.format() != gst::Format::Bytes {
+                    return gst::Pad::query_default(pad, Some(&*self.obj()), query);
+                }
+
+                let mut peer_query = gst::query::Duration::new(gst::Format::Bytes);
+
+                if !self.sinkpad.peer_query(&mut peer_query) {
+                    gst::error!(CAT, "Failed to query upstream duration");
+                    return false;
+                }
+
+                let size = match peer_query.result() {
+                    gst::GenericFormattedValue::Bytes(Some(size)) => *size,
+                    _ => {
+                        gst::error!(CAT, "Failed to query upstream duration");
+                        return false;
+                    }
+                };
+
+                let block_size = match self.state.lock().unwrap().as_ref() {
+                    // If state isn't set, it means that the
+                    // element hasn't been activated yet.
+                    None => return false,
+                    Some(state) => state.block_size as u64,
+                };
+
+                // All full blocks plus the final message, which may be empty
+                let total_chunks = size / block_size + 1;
+                // add the MAC and tag of each message
+                let size = size + total_chunks * secretstream::ABYTES as u64;
+
+                // add static offsets
+                let size = size + crate::SECRETSTREAM_HEADERS_SIZE as u64;
+
+                gst::debug!(CAT, obj: pad, "Setting duration bytes: {}", size);
+                q.set(size.bytes());
+
+                true
+            }
+            _ => gst::Pad::query_default(pad, Some(&*self.obj()), query),
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for SodiumEnc {
+    const NAME: &'static str = "GstSodiumEnc";
+    type Type = super::SodiumEnc;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("sink").unwrap();
+        let sinkpad = gst::Pad::builder_from_template(&templ)
+            .chain_function(|pad, parent, buffer| {
+                SodiumEnc::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |enc| enc.sink_chain(pad, buffer),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                SodiumEnc::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |enc| enc.sink_event(pad, event),
+                )
+            })
+            .build();
+
+        let templ = klass.pad_template("src").unwrap();
+        let srcpad = gst::Pad::builder_from_template(&templ)
+            .query_function(|pad, parent, query| {
+                SodiumEnc::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |enc| enc.src_query(pad, query),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                SodiumEnc::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |enc| enc.src_event(pad, event),
+                )
+            })
+            .build();
+
+        Self {
+            srcpad,
+            sinkpad,
+            props: Mutex::new(Props::default()),
+            state: Mutex::new(None),
+        }
+    }
+}
+
+impl ObjectImpl for SodiumEnc {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecBoxed::builder::<glib::Bytes>("key")
+                    .nick("Key")
+                    .blurb("The secret key, shared with the decrypter")
+                    .write_only()
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("block-size")
+                    .nick("Block Size")
+                    .blurb("The block-size of the chunks")
+                    .minimum(1024)
+                    .default_value(DEFAULT_BLOCK_SIZE)
+                    .mutable_ready()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.sinkpad).unwrap();
+        obj.add_pad(&self.srcpad).unwrap();
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "key" => {
+                let mut props = self.props.lock().unwrap();
+                props.key = value.get().expect("type checked upstream");
+            }
+
+            "block-size" => {
+                let mut props = self.props.lock().unwrap();
+                props.block_size = value.get().expect("type checked upstream");
+            }
+
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "block-size" => {
+                let props = self.props.lock().unwrap();
+                props.block_size.to_value()
+            }
+
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for SodiumEnc {}
+
+impl ElementImpl for SodiumEnc {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Secret Stream Encrypter",
+                "Generic",
+                "libsodium secretstream based stream encrypter",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let src_caps = gst::Caps::builder("application/x-sodium-secretstream").build();
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &src_caps,
+            )
+            .unwrap();
+
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &gst::Caps::new_any(),
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        gst::debug!(CAT, imp: self, "Changing state {:?}", transition);
+
+        if transition == gst::StateChange::ReadyToPaused {
+            let props = self.props.lock().unwrap().clone();
+
+            // Every stream gets a new header and thus nonce
+            let state = State::from_props(&props).map_err(|err| {
+                self.post_error_message(err);
+                gst::StateChangeError
+            })?;
+
+            *self.state.lock().unwrap() = Some(state);
+        }
+
+        let success = self.parent_change_state(transition)?;
+
+        if transition == gst::StateChange::PausedToReady {
+            let _ = self.state.lock().unwrap().take();
+        }
+
+        Ok(success)
+    }
+}
@@ -0,0 +1,71 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+//
+// SPDX-License-Identifier: MIT
+
+/**
+ * element-sodiumenc:
+ *
+ * `sodiumenc` encrypts an arbitrary byte stream with the libsodium
+ * [secretstream](https://doc.libsodium.org/secret-key_cryptography/secretstream) API,
+ * using the symmetric `key` that is shared with `sodiumdec`.
+ *
+ * The input is split into messages of `block-size` bytes that are each encrypted and
+ * authenticated. The last message is marked as final, which allows the decrypter to
+ * detect truncated streams.
+ *
+ * The encrypted stream starts with these headers:
+ *
+ * - the 12 bytes `gst-sodiumss`,
+ * - the block size as 32 bit little endian integer,
+ * - the 24 bytes secretstream header, which contains the random nonce of the stream.
+ *
+ * and is followed by the encrypted messages, each `block-size` plus 17 bytes long
+ * except for the final one which can be shorter.
+ *
+ * ## Example
+ * ```rust,ignore
+ * let key = sodiumoxide::crypto::secretstream::gen_key();
+ * let pipeline = gst::parse::launch(
+ *     "filesrc location=input.mkv ! sodiumenc name=enc ! filesink location=output.enc",
+ * )?;
+ * let enc = pipeline.downcast_ref::<gst::Bin>().unwrap().by_name("enc").unwrap();
+ * enc.set_property("key", glib::Bytes::from(key.as_ref()));
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct SodiumEnc(ObjectSubclass<imp::SodiumEnc>) @extends gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "sodiumenc",
+        gst::Rank::NONE,
+        SodiumEnc::static_type(),
+    )
+}
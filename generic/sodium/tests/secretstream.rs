@@ -0,0 +1,145 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to
+// deal in the Software without restriction, including without limitation the
+// rights to use, copy, modify, merge, publish, distribute, sublicense, and/or
+// sell copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS
+// IN THE SOFTWARE.
+//
+// SPDX-License-Identifier: MIT
+
+use gst::glib;
+use gst::prelude::*;
+
+use pretty_assertions::assert_eq;
+
+const KEY: [u8; 32] = [
+    103, 145, 12, 230, 93, 20, 7, 184, 61, 171, 9, 222, 44, 187, 105, 211, 31, 77, 140, 2, 59, 166,
+    250, 118, 17, 200, 91, 38, 149, 5, 70, 129,
+];
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstsodium::plugin_register_static().unwrap();
+    });
+}
+
+fn encrypt(input: &[u8]) -> Vec<u8> {
+    let mut h = gst_check::Harness::new("sodiumenc");
+    let enc = h.element().unwrap();
+    enc.set_property("key", glib::Bytes::from_static(&KEY));
+    enc.set_property("block-size", 1024u32);
+
+    h.play();
+    for chunk in input.chunks(777) {
+        h.push(gst::Buffer::from_slice(chunk.to_vec())).unwrap();
+    }
+    h.push_event(gst::event::Eos::new());
+
+    let mut output = vec![];
+    while let Some(buf) = h.try_pull() {
+        output.extend_from_slice(&buf.map_readable().unwrap());
+    }
+
+    output
+}
+
+fn decrypt(input: &[u8], key: &'static [u8]) -> Result<Vec<u8>, glib::Error> {
+    let mut h = gst_check::Harness::new("sodiumdec");
+    let dec = h.element().unwrap();
+    dec.set_property("key", glib::Bytes::from_static(key));
+
+    h.play();
+    let bus = gst::Bus::new();
+    dec.set_bus(Some(&bus));
+
+    for chunk in input.chunks(500) {
+        if h.push(gst::Buffer::from_slice(chunk.to_vec())).is_err() {
+            break;
+        }
+    }
+    h.push_event(gst::event::Eos::new());
+
+    if let Some(msg) = bus.pop_filtered(&[gst::MessageType::Error]) {
+        match msg.view() {
+            gst::MessageView::Error(err) => return Err(err.error()),
+            _ => unreachable!(),
+        }
+    }
+
+    let mut output = vec![];
+    while let Some(buf) = h.try_pull() {
+        output.extend_from_slice(&buf.map_readable().unwrap());
+    }
+
+    Ok(output)
+}
+
+#[test]
+fn test_roundtrip() {
+    init();
+
+    let input = include_bytes!("sample.mp3");
+    let encrypted = encrypt(input);
+
+    assert_eq!(&encrypted[..12], b"gst-sodiumss");
+    assert_eq!(&encrypted[12..16], &1024u32.to_le_bytes());
+    // Full blocks plus the final message, each with 17 bytes of overhead
+    let n_messages = input.len() / 1024 + 1;
+    assert_eq!(encrypted.len(), 40 + input.len() + n_messages * 17);
+
+    let decrypted = decrypt(&encrypted, &KEY).unwrap();
+    assert_eq!(decrypted.len(), input.len());
+    assert!(decrypted == input);
+}
+
+#[test]
+fn test_wrong_key() {
+    init();
+
+    let encrypted = encrypt(include_bytes!("sample.mp3"));
+
+    let err = decrypt(&encrypted, &[0; 32]).unwrap_err();
+    assert!(err.matches(gst::StreamError::Decrypt));
+}
+
+#[test]
+fn test_truncated() {
+    init();
+
+    let encrypted = encrypt(include_bytes!("sample.mp3"));
+
+    // Cutting off the final message must be detected
+    let err = decrypt(&encrypted[..encrypted.len() - 100], &KEY).unwrap_err();
+    assert!(err.matches(gst::StreamError::Decrypt));
+
+    let err = decrypt(&encrypted[..40 + 3 * (1024 + 17)], &KEY).unwrap_err();
+    assert!(err.matches(gst::StreamError::Decrypt));
+}
+
+#[test]
+fn test_modified() {
+    init();
+
+    let mut encrypted = encrypt(include_bytes!("sample.mp3"));
+    encrypted[2000] ^= 0x01;
+
+    let err = decrypt(&encrypted, &KEY).unwrap_err();
+    assert!(err.matches(gst::StreamError::Decrypt));
+}
@@ -123,6 +123,26 @@ impl DataQueue {
         self.0.lock().unwrap().state
     }
 
+    /// Returns the current level in buffers, bytes and time
+    pub fn level(&self) -> (u32, u32, gst::ClockTime) {
+        let inner = self.0.lock().unwrap();
+
+        // FIXME: Use running time
+        let mut timestamps = inner.queue.iter().filter_map(|i| i.timestamp());
+        let time = match (timestamps.next(), timestamps.last()) {
+            (Some(first), Some(last)) => {
+                if first > last {
+                    first - last
+                } else {
+                    last - first
+                }
+            }
+            _ => gst::ClockTime::ZERO,
+        };
+
+        (inner.cur_size_buffers, inner.cur_size_bytes, time)
+    }
+
     pub fn start(&self) {
         let mut inner = self.0.lock().unwrap();
         if inner.state == DataQueueState::Started {
@@ -161,6 +181,8 @@ impl DataQueue {
                 }
             }
         }
+        inner.cur_size_buffers = 0;
+        inner.cur_size_bytes = 0;
 
         gst::debug!(DATA_QUEUE_CAT, obj: inner.element, "Data queue cleared");
     }
@@ -677,6 +677,21 @@ impl ObjectImpl for Queue {
                     .maximum(u64::MAX - 1)
                     .default_value(DEFAULT_MAX_SIZE_TIME.nseconds())
                     .build(),
+                glib::ParamSpecUInt::builder("current-level-buffers")
+                    .nick("Current Level Buffers")
+                    .blurb("Current number of buffers in the queue")
+                    .read_only()
+                    .build(),
+                glib::ParamSpecUInt::builder("current-level-bytes")
+                    .nick("Current Level Bytes")
+                    .blurb("Current number of bytes in the queue")
+                    .read_only()
+                    .build(),
+                glib::ParamSpecUInt64::builder("current-level-time")
+                    .nick("Current Level Time")
+                    .blurb("Current amount of data in the queue in nanoseconds")
+                    .read_only()
+                    .build(),
             ]
         });
 
@@ -711,6 +726,22 @@ impl ObjectImpl for Queue {
     }
 
     fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        if let "current-level-buffers" | "current-level-bytes" | "current-level-time" = pspec.name()
+        {
+            let (buffers, bytes, time) = self
+                .dataqueue
+                .lock()
+                .unwrap()
+                .as_ref()
+                .map_or((0, 0, gst::ClockTime::ZERO), DataQueue::level);
+
+            return match pspec.name() {
+                "current-level-buffers" => buffers.to_value(),
+                "current-level-bytes" => bytes.to_value(),
+                _ => time.nseconds().to_value(),
+            };
+        }
+
         let settings = self.settings.lock().unwrap();
         match pspec.name() {
             "max-size-buffers" => settings.max_size_buffers.to_value(),
@@ -7,7 +7,7 @@
 //
 // SPDX-License-Identifier: MPL-2.0
 
-use crate::playlist::Playlist;
+use crate::playlist::{Part, Playlist};
 use chrono::{DateTime, Duration, Utc};
 use gio::prelude::*;
 use gst::glib;
@@ -18,7 +18,7 @@ use once_cell::sync::Lazy;
 use std::fs;
 use std::io::Write;
 use std::path;
-use std::sync::Mutex;
+use std::sync::{Condvar, Mutex};
 
 const DEFAULT_PLAYLIST_LOCATION: &str = "playlist.m3u8";
 const DEFAULT_MAX_NUM_SEGMENT_FILES: u32 = 10;
@@ -30,6 +30,7 @@ const DEFAULT_ENDLIST: bool = true;
 const SIGNAL_GET_PLAYLIST_STREAM: &str = "get-playlist-stream";
 const SIGNAL_GET_FRAGMENT_STREAM: &str = "get-fragment-stream";
 const SIGNAL_DELETE_FRAGMENT: &str = "delete-fragment";
+const SIGNAL_WAIT_FOR_PLAYLIST: &str = "wait-for-playlist";
 
 static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
     gst::DebugCategory::new(
@@ -63,11 +64,19 @@ impl Default for Settings {
     }
 }
 
+/// Locations of the file of a segment and of the files of its parts.
+struct SegmentLocations {
+    segment: String,
+    parts: Vec<String>,
+}
+
 pub struct PlaylistContext {
     pdt_base_utc: Option<DateTime<Utc>>,
     pdt_base_running_time: Option<gst::ClockTime>,
     playlist: Playlist,
-    old_segment_locations: Vec<String>,
+    old_segment_locations: Vec<SegmentLocations>,
+    /// Locations of the parts of the segment that is currently being written
+    pending_part_locations: Vec<String>,
     segment_template: String,
     playlist_location: String,
     max_num_segment_files: usize,
@@ -79,10 +88,38 @@ pub struct State {
     context: Option<PlaylistContext>,
 }
 
+/// Last written playlist, for blocking playlist reloads.
+#[derive(Default)]
+struct PublishedPlaylist {
+    content: Option<String>,
+    /// Media sequence number of the next segment and number of its parts in the playlist
+    next_position: (u64, usize),
+    /// Set once the playlist is not going to be updated anymore
+    finished: bool,
+}
+
+impl PublishedPlaylist {
+    /// Returns true if the playlist contains the segment with media sequence number `msn`, or
+    /// if `part` is set its part with that index.
+    fn contains(&self, msn: u64, part: Option<u64>) -> bool {
+        if self.finished {
+            return true;
+        }
+
+        let (next_msn, parts) = self.next_position;
+        match part {
+            None => msn < next_msn,
+            Some(part) => msn < next_msn || (msn == next_msn && part < parts as u64),
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct HlsBaseSink {
     settings: Mutex<Settings>,
     state: Mutex<State>,
+    published: Mutex<PublishedPlaylist>,
+    published_cond: Condvar,
 }
 
 #[glib::object_subclass]
@@ -250,6 +287,48 @@ impl ObjectImpl for HlsBaseSink {
                         false
                     })
                     .build(),
+                /**
+                 * GstHlsBaseSink::wait-for-playlist:
+                 * @msn: Media sequence number of the requested segment
+                 * @part: Index of the requested part of the segment, or -1
+                 * @timeout: Maximum time to wait in nanoseconds
+                 *
+                 * Blocks until the playlist contains the segment with media sequence number
+                 * @msn, or if @part is not -1 its part with that index, and returns the
+                 * playlist. This allows implementing blocking playlist reloads of Low-Latency
+                 * HLS, i.e. the `_HLS_msn` and `_HLS_part` query parameters, in an HTTP server.
+                 *
+                 * Returns: the playlist, or %NULL if it didn't contain the requested segment or
+                 * part before @timeout
+                 *
+                 * Since: plugins-rs-0.13.0
+                 */
+                glib::subclass::Signal::builder(SIGNAL_WAIT_FOR_PLAYLIST)
+                    .param_types([
+                        u64::static_type(),
+                        i64::static_type(),
+                        u64::static_type(),
+                    ])
+                    .return_type::<Option<String>>()
+                    .action()
+                    .class_handler(|_, args| {
+                        let elem = args[0].get::<super::HlsBaseSink>().expect("signal arg");
+                        let msn = args[1].get::<u64>().expect("signal arg");
+                        let part = args[2].get::<i64>().expect("signal arg");
+                        let timeout = args[3].get::<u64>().expect("signal arg");
+                        let imp = elem.imp();
+
+                        let part = u64::try_from(part).ok();
+                        Some(
+                            imp.wait_for_playlist(
+                                msn,
+                                part,
+                                std::time::Duration::from_nanos(timeout),
+                            )
+                            .to_value(),
+                        )
+                    })
+                    .build(),
             ]
         });
 
@@ -293,6 +372,8 @@ impl HlsBaseSinkImpl for HlsBaseSink {}
 
 impl HlsBaseSink {
     pub fn open_playlist(&self, playlist: Playlist, segment_template: String) {
+        *self.published.lock().unwrap() = PublishedPlaylist::default();
+
         let mut state = self.state.lock().unwrap();
         let settings = self.settings.lock().unwrap();
         state.context = Some(PlaylistContext {
@@ -300,6 +381,7 @@ impl HlsBaseSink {
             pdt_base_running_time: None,
             playlist,
             old_segment_locations: Vec::new(),
+            pending_part_locations: Vec::new(),
             segment_template,
             playlist_location: settings.playlist_location.clone(),
             max_num_segment_files: settings.max_num_segment_files,
@@ -317,6 +399,43 @@ impl HlsBaseSink {
                 let _ = self.write_playlist(&mut context);
             }
         }
+        drop(state);
+
+        // Wake up all waiters with the final playlist
+        self.published.lock().unwrap().finished = true;
+        self.published_cond.notify_all();
+    }
+
+    fn wait_for_playlist(
+        &self,
+        msn: u64,
+        part: Option<u64>,
+        timeout: std::time::Duration,
+    ) -> Option<String> {
+        let published = self.published.lock().unwrap();
+        let (published, _) = self
+            .published_cond
+            .wait_timeout_while(published, timeout, |published| {
+                !published.contains(msn, part)
+            })
+            .unwrap();
+
+        if !published.contains(msn, part) {
+            gst::debug!(
+                CAT,
+                imp: self,
+                "Timed out waiting for segment {msn} part {part:?}"
+            );
+            return None;
+        }
+
+        published.content.clone()
+    }
+
+    /// Returns a stream for writing a fragment or partial segment to `location`.
+    pub fn get_stream(&self, location: &str) -> Option<gio::OutputStream> {
+        self.obj()
+            .emit_by_name::<Option<gio::OutputStream>>(SIGNAL_GET_FRAGMENT_STREAM, &[&location])
     }
 
     pub fn get_fragment_stream(&self, fragment_id: u32) -> Option<(gio::OutputStream, String)> {
@@ -354,13 +473,7 @@ impl HlsBaseSink {
             location
         );
 
-        let stream = match self
-            .obj()
-            .emit_by_name::<Option<gio::OutputStream>>(SIGNAL_GET_FRAGMENT_STREAM, &[&location])
-        {
-            Some(stream) => stream,
-            None => return None,
-        };
+        let stream = self.get_stream(&location)?;
 
         Some((stream, location))
     }
@@ -448,13 +561,46 @@ impl HlsBaseSink {
 
         context.playlist.add_segment(segment);
 
+        let parts = std::mem::take(&mut context.pending_part_locations);
         if context.playlist.is_type_undefined() {
-            context.old_segment_locations.push(location.to_string());
+            context.old_segment_locations.push(SegmentLocations {
+                segment: location.to_string(),
+                parts,
+            });
         }
 
         self.write_playlist(context)
     }
 
+    /// Adds a part of the segment that is currently being written to the playlist, together
+    /// with the URI of the part that is written next.
+    pub fn add_part(
+        &self,
+        location: &str,
+        part: Part,
+        preload_hint: Option<String>,
+        map: Option<m3u8_rs::Map>,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let mut state = self.state.lock().unwrap();
+        let context = match state.context.as_mut() {
+            Some(context) => context,
+            None => {
+                gst::error!(
+                    CAT,
+                    imp: self,
+                    "Playlist is not configured",
+                );
+
+                return Err(gst::FlowError::Error);
+            }
+        };
+
+        context.playlist.add_part(part, preload_hint, map);
+        context.pending_part_locations.push(location.to_string());
+
+        self.write_playlist(context)
+    }
+
     fn write_playlist(
         &self,
         context: &mut PlaylistContext,
@@ -465,6 +611,17 @@ impl HlsBaseSink {
             .playlist
             .update_playlist_state(context.playlist_length as usize);
 
+        let mut content = Vec::new();
+        context.playlist.write_to(&mut content).map_err(|err| {
+            gst::error!(
+                CAT,
+                imp: self,
+                "Could not write new playlist: {}",
+                err.to_string()
+            );
+            gst::FlowError::Error
+        })?;
+
         // Acquires the playlist file handle so we can update it with new content. By default, this
         // is expected to be the same file every time.
         let mut playlist_stream = self
@@ -483,18 +640,15 @@ impl HlsBaseSink {
             })?
             .into_write();
 
-        context
-            .playlist
-            .write_to(&mut playlist_stream)
-            .map_err(|err| {
-                gst::error!(
-                    CAT,
-                    imp: self,
-                    "Could not write new playlist: {}",
-                    err.to_string()
-                );
-                gst::FlowError::Error
-            })?;
+        playlist_stream.write_all(&content).map_err(|err| {
+            gst::error!(
+                CAT,
+                imp: self,
+                "Could not write new playlist: {}",
+                err.to_string()
+            );
+            gst::FlowError::Error
+        })?;
         playlist_stream.flush().map_err(|err| {
             gst::error!(
                 CAT,
@@ -508,16 +662,29 @@ impl HlsBaseSink {
         if context.playlist.is_type_undefined() && context.max_num_segment_files > 0 {
             // Cleanup old segments from filesystem
             while context.old_segment_locations.len() > context.max_num_segment_files {
-                let old_segment_location = context.old_segment_locations.remove(0);
-                if !self
-                    .obj()
-                    .emit_by_name::<bool>(SIGNAL_DELETE_FRAGMENT, &[&old_segment_location])
+                let old_segment_locations = context.old_segment_locations.remove(0);
+                for location in old_segment_locations
+                    .parts
+                    .iter()
+                    .chain(Some(&old_segment_locations.segment))
                 {
-                    gst::error!(CAT, imp: self, "Could not delete fragment");
+                    if !self
+                        .obj()
+                        .emit_by_name::<bool>(SIGNAL_DELETE_FRAGMENT, &[location])
+                    {
+                        gst::error!(CAT, imp: self, "Could not delete fragment");
+                    }
                 }
             }
         }
 
+        {
+            let mut published = self.published.lock().unwrap();
+            published.content = Some(String::from_utf8(content).unwrap());
+            published.next_position = context.playlist.next_position();
+        }
+        self.published_cond.notify_all();
+
         gst::debug!(CAT, imp: self, "Wrote new playlist file!");
         Ok(gst::FlowSuccess::Ok)
     }
@@ -8,7 +8,7 @@
 
 use crate::hlsbasesink::HlsBaseSinkImpl;
 use crate::hlssink3::HlsSink3PlaylistType;
-use crate::playlist::Playlist;
+use crate::playlist::{Part, Playlist};
 use crate::HlsBaseSink;
 use gio::prelude::*;
 use gst::glib;
@@ -21,6 +21,8 @@ use std::sync::Mutex;
 
 const DEFAULT_INIT_LOCATION: &str = "init%05d.mp4";
 const DEFAULT_CMAF_LOCATION: &str = "segment%05d.m4s";
+const DEFAULT_PART_LOCATION: &str = "part%05d.%d.m4s";
+const DEFAULT_PART_DURATION: Option<gst::ClockTime> = None;
 const DEFAULT_TARGET_DURATION: u32 = 15;
 const DEFAULT_PLAYLIST_TYPE: HlsSink3PlaylistType = HlsSink3PlaylistType::Unspecified;
 const DEFAULT_SYNC: bool = true;
@@ -49,6 +51,9 @@ struct HlsCmafSinkSettings {
     playlist_type: Option<MediaPlaylistType>,
     sync: bool,
     latency: gst::ClockTime,
+    part_location: String,
+    part_duration: Option<gst::ClockTime>,
+    rendition_reports: Vec<String>,
 
     cmafmux: gst::Element,
     appsink: gst_app::AppSink,
@@ -78,18 +83,34 @@ impl Default for HlsCmafSinkSettings {
             playlist_type: None,
             sync: DEFAULT_SYNC,
             latency: DEFAULT_LATENCY,
+            part_location: String::from(DEFAULT_PART_LOCATION),
+            part_duration: DEFAULT_PART_DURATION,
+            rendition_reports: Vec::new(),
             cmafmux,
             appsink,
         }
     }
 }
 
+/// Segment that is written part by part in Low-Latency HLS mode.
+struct PendingSegment {
+    stream: gio::OutputStreamWrite<gio::OutputStream>,
+    location: String,
+    running_time: Option<gst::ClockTime>,
+    duration: gst::ClockTime,
+    /// Index of the segment in the part locations
+    idx: u32,
+    /// Number of parts written so far
+    parts: u32,
+}
+
 #[derive(Default)]
 struct HlsCmafSinkState {
     init_idx: u32,
     segment_idx: u32,
     init_segment: Option<m3u8_rs::Map>,
     new_header: bool,
+    pending_segment: Option<PendingSegment>,
 }
 
 #[derive(Default)]
@@ -144,6 +165,23 @@ impl ObjectImpl for HlsCmafSink {
                     .maximum(i64::MAX as u64)
                     .default_value(DEFAULT_LATENCY.nseconds())
                     .build(),
+                glib::ParamSpecString::builder("part-location")
+                    .nick("Part Location")
+                    .blurb("Location of the partial segment files to write, formatted with the segment and part index")
+                    .default_value(Some(DEFAULT_PART_LOCATION))
+                    .build(),
+                glib::ParamSpecUInt64::builder("part-duration")
+                    .nick("Part Duration")
+                    .blurb("Target duration of the partial segments of Low-Latency HLS in nanoseconds (-1 = disabled)")
+                    .default_value(DEFAULT_PART_DURATION.map(gst::ClockTime::nseconds).unwrap_or(u64::MAX))
+                    .mutable_ready()
+                    .build(),
+                gst::ParamSpecArray::builder("rendition-reports")
+                    .nick("Rendition Reports")
+                    .blurb("URIs of the playlists of other renditions with the same segmentation, for adding rendition reports in Low-Latency HLS mode")
+                    .element_spec(&glib::ParamSpecString::builder("rendition-report").build())
+                    .mutable_ready()
+                    .build(),
             ]
         });
 
@@ -186,6 +224,31 @@ impl ObjectImpl for HlsCmafSink {
                 settings.latency = value.get().expect("type checked upstream");
                 settings.cmafmux.set_property("latency", settings.latency);
             }
+            "part-location" => {
+                settings.part_location = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .unwrap_or_else(|| DEFAULT_PART_LOCATION.into());
+            }
+            "part-duration" => {
+                settings.part_duration = value.get().expect("type checked upstream");
+                settings
+                    .cmafmux
+                    .set_property("chunk-duration", settings.part_duration);
+            }
+            "rendition-reports" => {
+                settings.rendition_reports = value
+                    .get::<gst::ArrayRef>()
+                    .expect("type checked upstream")
+                    .as_slice()
+                    .iter()
+                    .map(|uri| {
+                        uri.get::<&str>()
+                            .expect("type checked upstream")
+                            .to_string()
+                    })
+                    .collect();
+            }
             _ => unimplemented!(),
         };
     }
@@ -202,6 +265,12 @@ impl ObjectImpl for HlsCmafSink {
             }
             "sync" => settings.sync.to_value(),
             "latency" => settings.latency.to_value(),
+            "part-location" => settings.part_location.to_value(),
+            "part-duration" => settings.part_duration.to_value(),
+            "rendition-reports" => {
+                gst::Array::new(settings.rendition_reports.iter().map(|uri| uri.as_str()))
+                    .to_value()
+            }
             _ => unimplemented!(),
         }
     }
@@ -255,6 +324,16 @@ impl ObjectImpl for HlsCmafSink {
                     let sample = sink.pull_sample().map_err(|_| gst::FlowError::Eos)?;
                     imp.on_new_sample(sample)
                 })
+                .eos({
+                    let self_weak = self.downgrade();
+                    move |_sink| {
+                        let Some(imp) = self_weak.upgrade() else {
+                            return;
+                        };
+
+                        let _ = imp.finish_segment();
+                    }
+                })
                 .build(),
         );
     }
@@ -318,16 +397,30 @@ impl ElementImpl for HlsCmafSink {
         transition: gst::StateChange,
     ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
         if transition == gst::StateChange::ReadyToPaused {
-            let (target_duration, playlist_type, segment_template) = {
+            let (
+                target_duration,
+                playlist_type,
+                segment_template,
+                part_duration,
+                rendition_reports,
+            ) = {
                 let settings = self.settings.lock().unwrap();
                 (
                     settings.target_duration,
                     settings.playlist_type.clone(),
                     settings.location.clone(),
+                    settings.part_duration,
+                    settings.rendition_reports.clone(),
                 )
             };
 
-            let playlist = self.start(target_duration, playlist_type);
+            let mut playlist = self.start(target_duration, playlist_type);
+            if let Some(part_duration) = part_duration {
+                playlist.enable_low_latency(
+                    part_duration.nseconds() as f32 / 1_000_000_000f32,
+                    rendition_reports,
+                );
+            }
             base_imp!(self).open_playlist(playlist, segment_template);
         }
 
@@ -445,6 +538,9 @@ impl HlsCmafSink {
             .flags()
             .contains(gst::BufferFlags::DISCONT | gst::BufferFlags::HEADER)
         {
+            // The new init segment only applies to the following segments
+            self.finish_segment()?;
+
             let mut stream = self.on_init_segment().map_err(|err| {
                 gst::error!(
                     CAT,
@@ -491,6 +587,12 @@ impl HlsCmafSink {
         let running_time = segment.to_running_time(first.pts().unwrap());
         let dur = first.duration().unwrap();
 
+        if self.settings.lock().unwrap().part_duration.is_some() {
+            // Only the chunk header of the first chunk of a fragment is not a delta unit
+            let independent = !first.flags().contains(gst::BufferFlags::DELTA_UNIT);
+            return self.on_new_part(&buffer_list, running_time, dur, independent);
+        }
+
         let (mut stream, location) = self.on_new_fragment().map_err(|err| {
             gst::error!(
                 CAT,
@@ -524,4 +626,165 @@ impl HlsCmafSink {
 
         self.add_segment(dur.mseconds() as f32 / 1_000f32, running_time, location)
     }
+
+    /// Writes a chunk of the current fragment as partial segment, and appends it to the
+    /// segment file.
+    fn on_new_part(
+        &self,
+        buffer_list: &gst::BufferListRef,
+        running_time: Option<gst::ClockTime>,
+        duration: gst::ClockTime,
+        independent: bool,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        if independent {
+            // This chunk starts a new fragment, so the previous one is complete
+            self.finish_segment()?;
+        }
+
+        let (part_location_template, part_duration, target_duration) = {
+            let settings = self.settings.lock().unwrap();
+            (
+                settings.part_location.clone(),
+                settings.part_duration.unwrap(),
+                gst::ClockTime::from_seconds(settings.target_duration as u64),
+            )
+        };
+
+        if self.state.lock().unwrap().pending_segment.is_none() {
+            let (stream, location) = self.on_new_fragment().map_err(|err| {
+                gst::error!(
+                    CAT,
+                    imp: self,
+                    "Couldn't get output stream for segment, {err}",
+                );
+                gst::FlowError::Error
+            })?;
+
+            let mut state = self.state.lock().unwrap();
+            state.pending_segment = Some(PendingSegment {
+                stream,
+                location,
+                running_time,
+                duration: gst::ClockTime::ZERO,
+                idx: state.segment_idx - 1,
+                parts: 0,
+            });
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let init_map = if state.new_header {
+            state.init_segment.clone()
+        } else {
+            None
+        };
+        let pending = state.pending_segment.as_mut().unwrap();
+
+        let part_location = sprintf::sprintf!(&part_location_template, pending.idx, pending.parts)
+            .map_err(|err| {
+                gst::error!(
+                    CAT,
+                    imp: self,
+                    "Couldn't build part file name, err: {:?}", err,
+                );
+                gst::FlowError::Error
+            })?;
+
+        let mut part_stream = base_imp!(self)
+            .get_stream(&part_location)
+            .ok_or_else(|| {
+                gst::error!(
+                    CAT,
+                    imp: self,
+                    "Couldn't get output stream for part",
+                );
+                gst::FlowError::Error
+            })?
+            .into_write();
+
+        for buffer in buffer_list {
+            let map = buffer.map_readable().unwrap();
+
+            pending.stream.write(&map).map_err(|_| {
+                gst::error!(
+                    CAT,
+                    imp: self,
+                    "Couldn't write segment to output stream",
+                );
+                gst::FlowError::Error
+            })?;
+
+            part_stream.write(&map).map_err(|_| {
+                gst::error!(
+                    CAT,
+                    imp: self,
+                    "Couldn't write part to output stream",
+                );
+                gst::FlowError::Error
+            })?;
+        }
+
+        part_stream.flush().map_err(|_| {
+            gst::error!(
+                CAT,
+                imp: self,
+                "Couldn't flush output stream",
+            );
+            gst::FlowError::Error
+        })?;
+
+        pending.duration += duration;
+        pending.parts += 1;
+
+        // The muxer starts a new fragment at the next keyframe once the target duration is
+        // reached, so the next part most likely belongs to the next segment in that case
+        let (next_idx, next_part) = if target_duration > gst::ClockTime::ZERO
+            && pending.duration + part_duration > target_duration
+        {
+            (pending.idx + 1, 0)
+        } else {
+            (pending.idx, pending.parts)
+        };
+        let preload_hint = sprintf::sprintf!(&part_location_template, next_idx, next_part)
+            .ok()
+            .map(|location| base_imp!(self).get_segment_uri(&location));
+        drop(state);
+
+        let part = Part {
+            uri: base_imp!(self).get_segment_uri(&part_location),
+            duration: duration.nseconds() as f32 / 1_000_000_000f32,
+            independent,
+        };
+
+        base_imp!(self).add_part(&part_location, part, preload_hint, init_map)
+    }
+
+    /// Adds the segment that was written part by part to the playlist.
+    fn finish_segment(&self) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let Some(PendingSegment {
+            mut stream,
+            location,
+            running_time,
+            duration,
+            ..
+        }) = self.state.lock().unwrap().pending_segment.take()
+        else {
+            return Ok(gst::FlowSuccess::Ok);
+        };
+
+        stream.flush().map_err(|_| {
+            gst::error!(
+                CAT,
+                imp: self,
+                "Couldn't flush output stream",
+            );
+            gst::FlowError::Error
+        })?;
+        drop(stream);
+
+        self.add_segment(
+            duration.nseconds() as f32 / 1_000_000_000f32,
+            running_time,
+            location,
+        )
+    }
 }
@@ -6,9 +6,43 @@
 //
 // SPDX-License-Identifier: MPL-2.0
 
-use m3u8_rs::{MediaPlaylist, MediaPlaylistType, MediaSegment};
+use m3u8_rs::{ExtTag, MediaPlaylist, MediaPlaylistType, MediaSegment};
 use std::io::Write;
 
+/// A partial segment of a Low-Latency HLS playlist.
+#[derive(Debug, Clone)]
+pub struct Part {
+    pub uri: String,
+    /// Duration in seconds.
+    pub duration: f32,
+    /// Whether the part starts with an independent frame.
+    pub independent: bool,
+}
+
+impl Part {
+    fn attributes(&self) -> String {
+        let mut attributes = format!("DURATION={:.5},URI=\"{}\"", self.duration, self.uri);
+        if self.independent {
+            attributes.push_str(",INDEPENDENT=YES");
+        }
+
+        attributes
+    }
+}
+
+/// Low-Latency HLS state of a playlist.
+#[derive(Debug, Clone)]
+struct LowLatency {
+    /// Parts of the segment that is currently being written.
+    pending_parts: Vec<Part>,
+    /// Initialization section of the segment that is currently being written, if it changed.
+    pending_map: Option<m3u8_rs::Map>,
+    /// URI of the part that is written next.
+    preload_hint: Option<String>,
+    /// URIs of the playlists of the other renditions.
+    rendition_reports: Vec<String>,
+}
+
 /// An HLS playlist.
 ///
 /// Controls the changes that needs to happen in the playlist as new segments are added. This
@@ -21,6 +55,7 @@ pub struct Playlist {
     status: PlaylistRenderState,
     turn_vod: bool,
     is_cmaf: bool,
+    low_latency: Option<LowLatency>,
 }
 
 impl Playlist {
@@ -31,13 +66,86 @@ impl Playlist {
             status: PlaylistRenderState::Init,
             turn_vod,
             is_cmaf,
+            low_latency: None,
         }
     }
 
+    /// Enables Low-Latency HLS with partial segments of the given target duration in seconds.
+    ///
+    /// The other renditions listed in `rendition_reports` are expected to be segmented in the
+    /// same way, so their reports use the same media sequence and part numbers as this playlist.
+    pub fn enable_low_latency(&mut self, part_target: f32, rendition_reports: Vec<String>) {
+        self.inner.unknown_tags.push(ExtTag {
+            tag: String::from("X-SERVER-CONTROL"),
+            rest: Some(format!(
+                "CAN-BLOCK-RELOAD=YES,PART-HOLD-BACK={:.5}",
+                3.0 * part_target
+            )),
+        });
+        self.inner.unknown_tags.push(ExtTag {
+            tag: String::from("X-PART-INF"),
+            rest: Some(format!("PART-TARGET={part_target:.5}")),
+        });
+
+        self.low_latency = Some(LowLatency {
+            pending_parts: Vec::new(),
+            pending_map: None,
+            preload_hint: None,
+            rendition_reports,
+        });
+    }
+
     /// Adds a new segment to the playlist.
-    pub fn add_segment(&mut self, segment: MediaSegment) {
+    ///
+    /// All parts added since the previous segment are considered to be parts of this segment.
+    pub fn add_segment(&mut self, mut segment: MediaSegment) {
         self.start();
+
+        if let Some(low_latency) = &mut self.low_latency {
+            segment
+                .unknown_tags
+                .extend(low_latency.pending_parts.drain(..).map(|part| ExtTag {
+                    tag: String::from("X-PART"),
+                    rest: Some(part.attributes()),
+                }));
+            low_latency.pending_map = None;
+        }
+
         self.inner.segments.push(segment);
+        self.playlist_index += 1;
+    }
+
+    /// Adds a new part of the segment that is currently being written, and the URI of the
+    /// part that will be written next.
+    ///
+    /// `map` is the initialization section of the segment if it differs from the one of the
+    /// previous segment.
+    pub fn add_part(
+        &mut self,
+        part: Part,
+        preload_hint: Option<String>,
+        map: Option<m3u8_rs::Map>,
+    ) {
+        self.start();
+
+        if let Some(low_latency) = &mut self.low_latency {
+            low_latency.pending_parts.push(part);
+            low_latency.pending_map = map;
+            low_latency.preload_hint = preload_hint;
+        }
+    }
+
+    /// Returns the media sequence number of the segment that is added next, and the number of
+    /// its parts that were already added.
+    pub fn next_position(&self) -> (u64, usize) {
+        let next_msn = self.playlist_index;
+        let parts = self
+            .low_latency
+            .as_ref()
+            .map(|low_latency| low_latency.pending_parts.len())
+            .unwrap_or(0);
+
+        (next_msn, parts)
     }
 
     /// Updates the playlist based on current state.
@@ -47,6 +155,8 @@ impl Playlist {
     /// playlist length value. The playlist index and current media sequence is also kept up
     /// to date.
     pub fn update_playlist_state(&mut self, max_playlist_length: usize) {
+        self.remove_old_parts();
+
         if !self.is_type_undefined() {
             return;
         }
@@ -68,10 +178,26 @@ impl Playlist {
             }
         }
 
-        self.playlist_index += 1;
         self.inner.media_sequence = self.playlist_index - self.inner.segments.len() as u64;
     }
 
+    /// Removes the parts of segments that are more than three target durations away from the
+    /// end of the playlist, as they are not useful for clients anymore.
+    fn remove_old_parts(&mut self) {
+        if self.low_latency.is_none() {
+            return;
+        }
+
+        let max_duration = 3.0 * self.inner.target_duration;
+        let mut duration = 0.0;
+        for segment in self.inner.segments.iter_mut().rev() {
+            if duration > max_duration {
+                segment.unknown_tags.retain(|tag| tag.tag != "X-PART");
+            }
+            duration += segment.duration;
+        }
+    }
+
     /// Sets the playlist to started state.
     fn start(&mut self) {
         self.status = PlaylistRenderState::Started;
@@ -80,6 +206,12 @@ impl Playlist {
 
     /// Sets the playlist to stopped state.
     pub fn stop(&mut self, write_endlist: bool) {
+        if let Some(low_latency) = &mut self.low_latency {
+            // Parts of an unfinished segment can't be listed anymore
+            low_latency.pending_parts.clear();
+            low_latency.preload_hint = None;
+        }
+
         self.inner.end_list = write_endlist;
         if self.turn_vod {
             self.inner.playlist_type = Some(MediaPlaylistType::Vod);
@@ -103,7 +235,58 @@ impl Playlist {
 
     /// Writes the playlist in textual format to the provided `Write` reference.
     pub fn write_to<T: Write>(&self, w: &mut T) -> std::io::Result<()> {
-        self.inner.write_to(w)
+        self.inner.write_to(w)?;
+
+        let Some(low_latency) = &self.low_latency else {
+            return Ok(());
+        };
+
+        if self.inner.end_list {
+            return Ok(());
+        }
+
+        // Parts of the segment that is being written, preload hints and rendition reports come
+        // after the last complete segment
+        if !low_latency.pending_parts.is_empty() {
+            if let Some(map) = &low_latency.pending_map {
+                writeln!(w, "#EXT-X-MAP:URI=\"{}\"", map.uri)?;
+            }
+        }
+
+        for part in &low_latency.pending_parts {
+            writeln!(w, "#EXT-X-PART:{}", part.attributes())?;
+        }
+
+        if let Some(uri) = &low_latency.preload_hint {
+            writeln!(w, "#EXT-X-PRELOAD-HINT:TYPE=PART,URI=\"{uri}\"")?;
+        }
+
+        if !low_latency.rendition_reports.is_empty() {
+            // Report the last part that is available, which is either one of the pending ones
+            // or the last part of the last complete segment
+            let (next_msn, parts) = self.next_position();
+            let (last_msn, last_part) = if parts > 0 {
+                (next_msn, parts as u64 - 1)
+            } else {
+                let last_parts = self.inner.segments.last().map_or(0, |segment| {
+                    segment
+                        .unknown_tags
+                        .iter()
+                        .filter(|tag| tag.tag == "X-PART")
+                        .count() as u64
+                });
+                (next_msn.saturating_sub(1), last_parts.saturating_sub(1))
+            };
+
+            for uri in &low_latency.rendition_reports {
+                writeln!(
+                    w,
+                    "#EXT-X-RENDITION-REPORT:URI=\"{uri}\",LAST-MSN={last_msn},LAST-PART={last_part}"
+                )?;
+            }
+        }
+
+        Ok(())
     }
 }
 
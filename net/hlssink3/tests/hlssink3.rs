@@ -235,7 +235,7 @@ fn test_hlssink3_element_with_video_content() -> Result<(), ()> {
         r###"#EXTM3U
 #EXT-X-VERSION:3
 #EXT-X-TARGETDURATION:2
-#EXT-X-MEDIA-SEQUENCE:4
+#EXT-X-MEDIA-SEQUENCE:3
 #EXTINF:2,
 segment00003.ts
 #EXTINF:0.3,
@@ -430,7 +430,6 @@ fn test_hlssink3_write_correct_playlist_content() -> Result<(), ()> {
         r###"#EXTM3U
 #EXT-X-VERSION:3
 #EXT-X-TARGETDURATION:15
-#EXT-X-MEDIA-SEQUENCE:1
 #EXTINF:1.633,
 segments/my-own-filename-000.ts
 #EXT-X-ENDLIST
@@ -440,3 +439,146 @@ segments/my-own-filename-000.ts
 
     Ok(())
 }
+
+#[test]
+fn test_hlscmafsink_low_latency() -> Result<(), ()> {
+    init();
+
+    const BUFFER_NB: i32 = 90;
+
+    let pipeline = gst::Pipeline::with_name("video_pipeline");
+
+    let video_src = try_create_element!("videotestsrc");
+    video_src.set_property("is-live", true);
+    video_src.set_property("num-buffers", BUFFER_NB);
+
+    let x264enc = try_create_element!("x264enc");
+    x264enc.set_property("key-int-max", 30u32);
+    x264enc.set_property_from_str("tune", "zerolatency");
+    let h264parse = try_create_element!("h264parse");
+
+    let hlscmafsink = gst::ElementFactory::make("hlscmafsink")
+        .name("test_hlscmafsink")
+        .property("target-duration", 1u32)
+        .property("part-duration", 250.mseconds())
+        .property("rendition-reports", gst::Array::new(["audio.m3u8"]))
+        .build()
+        .expect("Must be able to instantiate hlscmafsink");
+
+    let (hls_events_sender, hls_events_receiver) = mpsc::sync_channel(100);
+    let playlist_content = Arc::new(Mutex::new(String::from("")));
+
+    hlscmafsink.connect("get-playlist-stream", false, {
+        let playlist_content = playlist_content.clone();
+        move |_args| {
+            let playlist = MemoryPlaylistFile {
+                handler: Arc::clone(&playlist_content),
+            };
+            playlist.clear_content();
+            let output = gio::WriteOutputStream::new(playlist);
+            Some(output.to_value())
+        }
+    });
+
+    hlscmafsink.connect("get-init-stream", false, |_args| {
+        let stream = gio::MemoryOutputStream::new_resizable();
+        Some(stream.to_value())
+    });
+
+    hlscmafsink.connect("get-fragment-stream", false, {
+        let hls_events_sender = hls_events_sender.clone();
+        move |args| {
+            let location = args[1].get::<String>().expect("No location given");
+
+            hls_events_sender
+                .try_send(HlsSinkEvent::GetFragmentStream(location))
+                .expect("Send fragment event");
+
+            let stream = gio::MemoryOutputStream::new_resizable();
+            Some(stream.to_value())
+        }
+    });
+
+    hlscmafsink.connect("delete-fragment", false, move |args| {
+        let location = args[1].get::<String>().expect("No location given");
+        hls_events_sender
+            .try_send(HlsSinkEvent::DeleteFragment(location))
+            .expect("Send delete fragment event");
+        Some(true.to_value())
+    });
+
+    try_or_pause!(pipeline.add_many([&video_src, &x264enc, &h264parse, &hlscmafsink]));
+    try_or_pause!(gst::Element::link_many([
+        &video_src,
+        &x264enc,
+        &h264parse,
+        &hlscmafsink
+    ]));
+
+    // Blocking playlist reload of the second part of the second segment
+    let blocking_reload = std::thread::spawn({
+        let hlscmafsink = hlscmafsink.clone();
+        move || {
+            hlscmafsink.emit_by_name::<Option<String>>(
+                "wait-for-playlist",
+                &[&1u64, &1i64, &10.seconds().nseconds()],
+            )
+        }
+    });
+
+    pipeline.set_state(gst::State::Playing).unwrap();
+
+    let mut eos = false;
+    let bus = pipeline.bus().unwrap();
+    while let Some(msg) = bus.timed_pop(gst::ClockTime::NONE) {
+        use gst::MessageView;
+        match msg.view() {
+            MessageView::Eos(..) => {
+                eos = true;
+                break;
+            }
+            MessageView::Error(..) => unreachable!(),
+            _ => (),
+        }
+    }
+
+    pipeline.set_state(gst::State::Null).unwrap();
+    assert!(eos);
+
+    let playlist = blocking_reload
+        .join()
+        .unwrap()
+        .expect("Blocking playlist reload timed out");
+    assert!(
+        playlist.contains("#EXT-X-SERVER-CONTROL:CAN-BLOCK-RELOAD=YES,PART-HOLD-BACK=0.75000\n")
+    );
+    assert!(playlist.contains("#EXT-X-PART-INF:PART-TARGET=0.25000\n"));
+    assert!(playlist.contains("URI=\"part00000.0.m4s\",INDEPENDENT=YES\n"));
+    assert!(playlist.contains("segment00000.m4s\n"));
+    assert!(playlist.contains("URI=\"part00001.1.m4s\""));
+    assert!(playlist.contains("#EXT-X-PRELOAD-HINT:TYPE=PART,URI=\"part0000"));
+    assert!(playlist.contains("#EXT-X-RENDITION-REPORT:URI=\"audio.m3u8\",LAST-MSN="));
+
+    // Each part is written separately in addition to the complete segment
+    let mut actual_events = Vec::new();
+    while let Ok(event) = hls_events_receiver.recv_timeout(Duration::from_millis(1)) {
+        actual_events.push(event);
+    }
+    assert_eq!(
+        &actual_events[..3],
+        &[
+            HlsSinkEvent::GetFragmentStream("segment00000.m4s".to_string()),
+            HlsSinkEvent::GetFragmentStream("part00000.0.m4s".to_string()),
+            HlsSinkEvent::GetFragmentStream("part00000.1.m4s".to_string()),
+        ]
+    );
+
+    // The final playlist contains no pending parts or hints anymore
+    let contents = playlist_content.lock().unwrap();
+    assert!(contents.contains("#EXT-X-PART:"));
+    assert!(!contents.contains("#EXT-X-PRELOAD-HINT"));
+    assert!(!contents.contains("#EXT-X-RENDITION-REPORT"));
+    assert!(contents.ends_with("segment00002.m4s\n#EXT-X-ENDLIST\n"));
+
+    Ok(())
+}
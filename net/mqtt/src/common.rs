@@ -0,0 +1,222 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+
+use std::time::Duration;
+
+pub(crate) const DEFAULT_HOST: &str = "localhost";
+pub(crate) const DEFAULT_PORT: u32 = 1883;
+pub(crate) const DEFAULT_KEEP_ALIVE: u32 = 30;
+pub(crate) const DEFAULT_QOS: MqttQos = MqttQos::AtMostOnce;
+
+/// Capacity of the request channel between the client and the event loop.
+const REQUEST_CHANNEL_CAPACITY: usize = 64;
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstMqttQos")]
+pub enum MqttQos {
+    #[enum_value(name = "At most once delivery", nick = "at-most-once")]
+    AtMostOnce = 0,
+
+    #[enum_value(name = "At least once delivery", nick = "at-least-once")]
+    AtLeastOnce = 1,
+
+    #[enum_value(name = "Exactly once delivery", nick = "exactly-once")]
+    ExactlyOnce = 2,
+}
+
+impl From<MqttQos> for rumqttc::QoS {
+    fn from(qos: MqttQos) -> Self {
+        match qos {
+            MqttQos::AtMostOnce => rumqttc::QoS::AtMostOnce,
+            MqttQos::AtLeastOnce => rumqttc::QoS::AtLeastOnce,
+            MqttQos::ExactlyOnce => rumqttc::QoS::ExactlyOnce,
+        }
+    }
+}
+
+/// Broker connection settings shared by the source and the sink.
+#[derive(Debug, Clone)]
+pub(crate) struct ConnectionSettings {
+    pub host: String,
+    pub port: u32,
+    pub client_id: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub keep_alive: u32,
+}
+
+impl Default for ConnectionSettings {
+    fn default() -> Self {
+        ConnectionSettings {
+            host: DEFAULT_HOST.to_string(),
+            port: DEFAULT_PORT,
+            client_id: None,
+            username: None,
+            password: None,
+            keep_alive: DEFAULT_KEEP_ALIVE,
+        }
+    }
+}
+
+impl ConnectionSettings {
+    pub(crate) fn properties() -> Vec<glib::ParamSpec> {
+        vec![
+            glib::ParamSpecString::builder("host")
+                .nick("Host")
+                .blurb("Host name or address of the MQTT broker")
+                .default_value(Some(DEFAULT_HOST))
+                .mutable_ready()
+                .build(),
+            glib::ParamSpecUInt::builder("port")
+                .nick("Port")
+                .blurb("Port of the MQTT broker")
+                .maximum(u16::MAX as u32)
+                .default_value(DEFAULT_PORT)
+                .mutable_ready()
+                .build(),
+            glib::ParamSpecString::builder("client-id")
+                .nick("Client ID")
+                .blurb("MQTT client identifier (NULL = derived from the element name)")
+                .mutable_ready()
+                .build(),
+            glib::ParamSpecString::builder("username")
+                .nick("Username")
+                .blurb("Username for authenticating with the broker")
+                .mutable_ready()
+                .build(),
+            glib::ParamSpecString::builder("password")
+                .nick("Password")
+                .blurb("Password for authenticating with the broker")
+                .mutable_ready()
+                .build(),
+            glib::ParamSpecUInt::builder("keep-alive")
+                .nick("Keep Alive")
+                .blurb("Keep alive interval in seconds")
+                .minimum(5)
+                .default_value(DEFAULT_KEEP_ALIVE)
+                .mutable_ready()
+                .build(),
+        ]
+    }
+
+    /// Returns `true` if the property was handled.
+    pub(crate) fn set_property(&mut self, value: &glib::Value, pspec: &glib::ParamSpec) -> bool {
+        match pspec.name() {
+            "host" => {
+                self.host = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .unwrap_or_else(|| DEFAULT_HOST.to_string());
+            }
+            "port" => self.port = value.get().expect("type checked upstream"),
+            "client-id" => self.client_id = value.get().expect("type checked upstream"),
+            "username" => self.username = value.get().expect("type checked upstream"),
+            "password" => self.password = value.get().expect("type checked upstream"),
+            "keep-alive" => self.keep_alive = value.get().expect("type checked upstream"),
+            _ => return false,
+        }
+
+        true
+    }
+
+    pub(crate) fn property(&self, pspec: &glib::ParamSpec) -> Option<glib::Value> {
+        let value = match pspec.name() {
+            "host" => self.host.to_value(),
+            "port" => self.port.to_value(),
+            "client-id" => self.client_id.to_value(),
+            "username" => self.username.to_value(),
+            "password" => self.password.to_value(),
+            "keep-alive" => self.keep_alive.to_value(),
+            _ => return None,
+        };
+
+        Some(value)
+    }
+
+    pub(crate) fn connect(&self, element: &gst::Element) -> (rumqttc::Client, rumqttc::Connection) {
+        let client_id = self
+            .client_id
+            .clone()
+            .unwrap_or_else(|| format!("gst-{}-{}", std::process::id(), element.name()));
+
+        let mut options = rumqttc::MqttOptions::new(client_id, &self.host, self.port as u16);
+        options.set_keep_alive(Duration::from_secs(self.keep_alive.into()));
+        if let Some(ref username) = self.username {
+            options.set_credentials(username, self.password.as_deref().unwrap_or(""));
+        }
+
+        rumqttc::Client::new(options, REQUEST_CHANNEL_CAPACITY)
+    }
+}
+
+/// Converts a `GstStructure` into a JSON object, with the structure name
+/// stored in the `name` member.
+pub(crate) fn structure_to_json(s: &gst::StructureRef) -> serde_json::Value {
+    let mut map = serde_json::Map::new();
+
+    map.insert("name".to_string(), s.name().to_string().into());
+    for (field, value) in s.iter() {
+        map.insert(field.to_string(), value_to_json(value));
+    }
+
+    serde_json::Value::Object(map)
+}
+
+/// Converts a tag list into a JSON object, mapping each tag name to an
+/// array of its values.
+pub(crate) fn tags_to_json(tags: &gst::TagListRef) -> serde_json::Value {
+    let mut map = serde_json::Map::new();
+
+    for (tag, values) in tags.iter_generic() {
+        let values = values.map(|v| value_to_json(v)).collect::<Vec<_>>();
+        map.insert(tag.to_string(), serde_json::Value::Array(values));
+    }
+
+    serde_json::Value::Object(map)
+}
+
+fn value_to_json(value: &glib::Value) -> serde_json::Value {
+    use serde_json::Value;
+
+    if let Ok(v) = value.get::<bool>() {
+        Value::Bool(v)
+    } else if let Ok(v) = value.get::<i32>() {
+        v.into()
+    } else if let Ok(v) = value.get::<u32>() {
+        v.into()
+    } else if let Ok(v) = value.get::<i64>() {
+        v.into()
+    } else if let Ok(v) = value.get::<u64>() {
+        v.into()
+    } else if let Ok(v) = value.get::<f32>() {
+        f64::from(v).into()
+    } else if let Ok(v) = value.get::<f64>() {
+        v.into()
+    } else if let Ok(v) = value.get::<Option<String>>() {
+        v.map(Value::String).unwrap_or(Value::Null)
+    } else if let Ok(Some(v)) = value.get::<Option<gst::Structure>>() {
+        structure_to_json(&v)
+    } else if let Ok(v) = value.get::<gst::Array>() {
+        Value::Array(v.iter().map(|v| value_to_json(v)).collect())
+    } else if let Ok(v) = value.get::<gst::List>() {
+        Value::Array(v.iter().map(|v| value_to_json(v)).collect())
+    } else if let Ok(Some(v)) = value.get::<Option<gst::DateTime>>() {
+        v.to_iso8601_string()
+            .map(|s| Value::String(s.to_string()))
+            .unwrap_or(Value::Null)
+    } else {
+        value
+            .serialize()
+            .map(|s| Value::String(s.to_string()))
+            .unwrap_or(Value::Null)
+    }
+}
@@ -0,0 +1,395 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::subclass::prelude::*;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+
+use crate::common::*;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new("mqttsink", gst::DebugColorFlags::empty(), Some("MQTT Sink"))
+});
+
+const DEFAULT_TOPIC: &str = "gstreamer";
+const DEFAULT_RETAIN: bool = false;
+const DEFAULT_PUBLISH_BUFFERS: bool = true;
+const DEFAULT_FORWARD_MESSAGES: bool = false;
+
+#[derive(Debug, Clone)]
+struct Settings {
+    connection: ConnectionSettings,
+    topic: String,
+    qos: MqttQos,
+    retain: bool,
+    publish_buffers: bool,
+    forward_messages: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            connection: ConnectionSettings::default(),
+            topic: DEFAULT_TOPIC.to_string(),
+            qos: DEFAULT_QOS,
+            retain: DEFAULT_RETAIN,
+            publish_buffers: DEFAULT_PUBLISH_BUFFERS,
+            forward_messages: DEFAULT_FORWARD_MESSAGES,
+        }
+    }
+}
+
+struct State {
+    client: rumqttc::Client,
+    topic: String,
+    qos: rumqttc::QoS,
+    retain: bool,
+    publish_buffers: bool,
+    stop: Arc<AtomicBool>,
+    event_loop: Option<thread::JoinHandle<()>>,
+    message_forwarding: Option<(gst::Bus, glib::SignalHandlerId)>,
+}
+
+#[derive(Default)]
+pub struct MqttSink {
+    settings: Mutex<Settings>,
+    state: Mutex<Option<State>>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for MqttSink {
+    const NAME: &'static str = "GstMqttSink";
+    type Type = super::MqttSink;
+    type ParentType = gst_base::BaseSink;
+}
+
+impl MqttSink {
+    fn publish(&self, state: &State, topic: String, payload: Vec<u8>, retain: bool) {
+        gst::trace!(CAT, imp: self, "Publishing {} bytes to {topic}", payload.len());
+
+        if let Err(err) = state.client.try_publish(topic, state.qos, retain, payload) {
+            gst::warning!(CAT, imp: self, "Failed to publish message: {err}");
+        }
+    }
+
+    /// Publishes element messages posted on the bus of the top-level
+    /// pipeline.
+    fn forward_messages(
+        &self,
+        client: &rumqttc::Client,
+        topic: &str,
+        qos: rumqttc::QoS,
+    ) -> Option<(gst::Bus, glib::SignalHandlerId)> {
+        let mut toplevel = self.obj().clone().upcast::<gst::Object>();
+        while let Some(parent) = toplevel.parent() {
+            toplevel = parent;
+        }
+
+        let Some(bus) = toplevel
+            .downcast_ref::<gst::Element>()
+            .and_then(|element| element.bus())
+        else {
+            gst::warning!(CAT, imp: self, "No bus to forward messages from");
+            return None;
+        };
+
+        gst::debug!(CAT, imp: self, "Forwarding element messages from {toplevel:?}");
+
+        let client = client.clone();
+        let topic = format!("{topic}/messages");
+        bus.enable_sync_message_emission();
+        let handler_id = bus.connect_sync_message(Some("element"), move |_bus, msg| {
+            let Some(s) = msg.structure() else {
+                return;
+            };
+
+            let mut json = structure_to_json(s);
+            if let Some(src) = msg.src() {
+                json["source"] = src.path_string().to_string().into();
+            }
+
+            let _ = client.try_publish(
+                format!("{topic}/{}", s.name()),
+                qos,
+                false,
+                json.to_string().into_bytes(),
+            );
+        });
+
+        Some((bus, handler_id))
+    }
+}
+
+impl ObjectImpl for MqttSink {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            let mut props = ConnectionSettings::properties();
+            props.extend([
+                glib::ParamSpecString::builder("topic")
+                    .nick("Topic")
+                    .blurb("Topic to publish buffers on, and prefix for metadata topics")
+                    .default_value(Some(DEFAULT_TOPIC))
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("qos", DEFAULT_QOS)
+                    .nick("QoS")
+                    .blurb("Quality of service level for published messages")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("retain")
+                    .nick("Retain")
+                    .blurb("Ask the broker to retain the last published buffer and tags")
+                    .default_value(DEFAULT_RETAIN)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("publish-buffers")
+                    .nick("Publish Buffers")
+                    .blurb("Publish the content of received buffers")
+                    .default_value(DEFAULT_PUBLISH_BUFFERS)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("forward-messages")
+                    .nick("Forward Messages")
+                    .blurb("Publish element messages posted on the pipeline bus")
+                    .default_value(DEFAULT_FORWARD_MESSAGES)
+                    .mutable_ready()
+                    .build(),
+            ]);
+            props
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        if settings.connection.set_property(value, pspec) {
+            return;
+        }
+
+        match pspec.name() {
+            "topic" => {
+                settings.topic = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .unwrap_or_else(|| DEFAULT_TOPIC.to_string());
+            }
+            "qos" => settings.qos = value.get().expect("type checked upstream"),
+            "retain" => settings.retain = value.get().expect("type checked upstream"),
+            "publish-buffers" => {
+                settings.publish_buffers = value.get().expect("type checked upstream")
+            }
+            "forward-messages" => {
+                settings.forward_messages = value.get().expect("type checked upstream")
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+
+        if let Some(value) = settings.connection.property(pspec) {
+            return value;
+        }
+
+        match pspec.name() {
+            "topic" => settings.topic.to_value(),
+            "qos" => settings.qos.to_value(),
+            "retain" => settings.retain.to_value(),
+            "publish-buffers" => settings.publish_buffers.to_value(),
+            "forward-messages" => settings.forward_messages.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for MqttSink {}
+
+impl ElementImpl for MqttSink {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "MQTT Sink",
+                "Sink/Network/Metadata",
+                "Publish buffers, tags, events and messages to an MQTT broker",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &gst::Caps::new_any(),
+            )
+            .unwrap();
+
+            vec![sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseSinkImpl for MqttSink {
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        let settings = self.settings.lock().unwrap().clone();
+
+        let (client, mut connection) = settings
+            .connection
+            .connect(self.obj().upcast_ref::<gst::Element>());
+
+        // The connection makes progress, including reconnecting, only while it
+        // is polled.
+        let stop = Arc::new(AtomicBool::new(false));
+        let event_loop = {
+            let stop = stop.clone();
+            thread::Builder::new()
+                .name("mqttsink-event-loop".to_string())
+                .spawn(move || {
+                    while !stop.load(Ordering::SeqCst) {
+                        match connection.recv_timeout(Duration::from_secs(1)) {
+                            Ok(Ok(event)) => gst::trace!(CAT, "MQTT event {event:?}"),
+                            Ok(Err(err)) => {
+                                gst::warning!(CAT, "MQTT connection error: {err}");
+                                thread::sleep(Duration::from_millis(500));
+                            }
+                            Err(rumqttc::RecvTimeoutError::Timeout) => (),
+                            Err(rumqttc::RecvTimeoutError::Disconnected) => break,
+                        }
+                    }
+                })
+                .map_err(|err| {
+                    gst::error_msg!(
+                        gst::ResourceError::Failed,
+                        ["Failed to start MQTT event loop: {}", err]
+                    )
+                })?
+        };
+
+        let qos = settings.qos.into();
+        let message_forwarding = if settings.forward_messages {
+            self.forward_messages(&client, &settings.topic, qos)
+        } else {
+            None
+        };
+
+        gst::debug!(
+            CAT,
+            imp: self,
+            "Publishing to {}:{} on topic {}",
+            settings.connection.host,
+            settings.connection.port,
+            settings.topic
+        );
+
+        *self.state.lock().unwrap() = Some(State {
+            client,
+            topic: settings.topic,
+            qos,
+            retain: settings.retain,
+            publish_buffers: settings.publish_buffers,
+            stop,
+            event_loop: Some(event_loop),
+            message_forwarding,
+        });
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        let Some(mut state) = self.state.lock().unwrap().take() else {
+            return Ok(());
+        };
+
+        if let Some((bus, handler_id)) = state.message_forwarding.take() {
+            bus.disconnect(handler_id);
+            bus.disable_sync_message_emission();
+        }
+
+        let _ = state.client.try_disconnect();
+        state.stop.store(true, Ordering::SeqCst);
+        if let Some(event_loop) = state.event_loop.take() {
+            let _ = event_loop.join();
+        }
+
+        gst::debug!(CAT, imp: self, "Stopped");
+
+        Ok(())
+    }
+
+    fn render(&self, buffer: &gst::Buffer) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let state = self.state.lock().unwrap();
+        let state = state.as_ref().ok_or_else(|| {
+            gst::element_imp_error!(self, gst::CoreError::Failed, ["Not started yet"]);
+            gst::FlowError::Error
+        })?;
+
+        if !state.publish_buffers {
+            return Ok(gst::FlowSuccess::Ok);
+        }
+
+        let map = buffer.map_readable().map_err(|_| {
+            gst::element_imp_error!(self, gst::CoreError::Failed, ["Failed to map buffer"]);
+            gst::FlowError::Error
+        })?;
+
+        self.publish(state, state.topic.clone(), map.to_vec(), state.retain);
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    fn event(&self, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        if let Some(state) = self.state.lock().unwrap().as_ref() {
+            match event.view() {
+                EventView::Tag(ev) => {
+                    let json = tags_to_json(ev.tag());
+                    self.publish(
+                        state,
+                        format!("{}/tags", state.topic),
+                        json.to_string().into_bytes(),
+                        state.retain,
+                    );
+                }
+                EventView::CustomDownstream(_)
+                | EventView::CustomDownstreamOob(_)
+                | EventView::CustomDownstreamSticky(_) => {
+                    if let Some(s) = event.structure() {
+                        let json = structure_to_json(s);
+                        self.publish(
+                            state,
+                            format!("{}/events/{}", state.topic, s.name()),
+                            json.to_string().into_bytes(),
+                            false,
+                        );
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        self.parent_event(event)
+    }
+}
@@ -0,0 +1,54 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * element-mqttsink:
+ * @short-description: Publish buffers and pipeline metadata to an MQTT broker
+ *
+ * Publishes the received buffers as-is to `topic`, which is useful together
+ * with elements producing serialized metadata such as `jsongstenc`.
+ *
+ * In addition, metadata flowing through the pipeline is published as JSON:
+ *
+ * - tag events are published to `<topic>/tags`, as an object mapping each tag
+ *   name to an array of values.
+ * - custom downstream events are published to `<topic>/events/<name>`, where
+ *   `name` is the name of the event structure.
+ * - if `forward-messages` is enabled, element messages posted on the bus of
+ *   the pipeline containing the sink (e.g. the loudness measurements of
+ *   `ebur128level`) are published to `<topic>/messages/<name>`.
+ *
+ * Messages are dropped with a warning if the connection to the broker can't
+ * keep up, so the streaming thread is never blocked by the network.
+ *
+ * ## Example pipeline
+ * ```bash
+ * gst-launch-1.0 audiotestsrc is-live=true ! ebur128level post-messages=true ! \
+ *     fakeaudiosink mqttsink host=broker.local topic=studio/loudness \
+ *     forward-messages=true publish-buffers=false
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct MqttSink(ObjectSubclass<imp::MqttSink>) @extends gst_base::BaseSink, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "mqttsink",
+        gst::Rank::NONE,
+        MqttSink::static_type(),
+    )
+}
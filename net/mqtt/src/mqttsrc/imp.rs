@@ -0,0 +1,334 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::prelude::*;
+use gst_base::subclass::base_src::CreateSuccess;
+use gst_base::subclass::prelude::*;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+
+use crate::common::*;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "mqttsrc",
+        gst::DebugColorFlags::empty(),
+        Some("MQTT Source"),
+    )
+});
+
+const DEFAULT_TOPIC: &str = "gstreamer/#";
+const DEFAULT_EMIT_EVENTS: bool = true;
+
+/// Timeout for polling the connection so that flushing can be checked
+/// regularly.
+const POLL_TIMEOUT: Duration = Duration::from_millis(100);
+
+static DEFAULT_CAPS: Lazy<gst::Caps> =
+    Lazy::new(|| gst::Caps::builder("application/octet-stream").build());
+
+#[derive(Debug, Clone)]
+struct Settings {
+    connection: ConnectionSettings,
+    topic: String,
+    qos: MqttQos,
+    caps: gst::Caps,
+    emit_events: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            connection: ConnectionSettings::default(),
+            topic: DEFAULT_TOPIC.to_string(),
+            qos: DEFAULT_QOS,
+            caps: DEFAULT_CAPS.clone(),
+            emit_events: DEFAULT_EMIT_EVENTS,
+        }
+    }
+}
+
+struct State {
+    client: rumqttc::Client,
+    connection: rumqttc::Connection,
+    emit_events: bool,
+}
+
+#[derive(Default)]
+pub struct MqttSrc {
+    settings: Mutex<Settings>,
+    state: Mutex<Option<State>>,
+    flushing: AtomicBool,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for MqttSrc {
+    const NAME: &'static str = "GstMqttSrc";
+    type Type = super::MqttSrc;
+    type ParentType = gst_base::PushSrc;
+}
+
+impl ObjectImpl for MqttSrc {
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.set_live(true);
+        obj.set_format(gst::Format::Time);
+        obj.set_do_timestamp(true);
+    }
+
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            let mut props = ConnectionSettings::properties();
+            props.extend([
+                glib::ParamSpecString::builder("topic")
+                    .nick("Topic")
+                    .blurb("Topic filter to subscribe to, can contain MQTT wildcards")
+                    .default_value(Some(DEFAULT_TOPIC))
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("qos", DEFAULT_QOS)
+                    .nick("QoS")
+                    .blurb("Maximum quality of service level for the subscription")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoxed::builder::<gst::Caps>("caps")
+                    .nick("Caps")
+                    .blurb("Caps of the output buffers")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("emit-events")
+                    .nick("Emit Events")
+                    .blurb("Push a custom downstream event before each message payload")
+                    .default_value(DEFAULT_EMIT_EVENTS)
+                    .mutable_ready()
+                    .build(),
+            ]);
+            props
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        if settings.connection.set_property(value, pspec) {
+            return;
+        }
+
+        match pspec.name() {
+            "topic" => {
+                settings.topic = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .unwrap_or_else(|| DEFAULT_TOPIC.to_string());
+            }
+            "qos" => settings.qos = value.get().expect("type checked upstream"),
+            "caps" => {
+                settings.caps = value
+                    .get::<Option<gst::Caps>>()
+                    .expect("type checked upstream")
+                    .unwrap_or_else(|| DEFAULT_CAPS.clone());
+            }
+            "emit-events" => settings.emit_events = value.get().expect("type checked upstream"),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+
+        if let Some(value) = settings.connection.property(pspec) {
+            return value;
+        }
+
+        match pspec.name() {
+            "topic" => settings.topic.to_value(),
+            "qos" => settings.qos.to_value(),
+            "caps" => settings.caps.to_value(),
+            "emit-events" => settings.emit_events.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for MqttSrc {}
+
+impl ElementImpl for MqttSrc {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "MQTT Source",
+                "Source/Network/Metadata",
+                "Receive messages from an MQTT broker as buffers and custom events",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &gst::Caps::new_any(),
+            )
+            .unwrap();
+
+            vec![src_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseSrcImpl for MqttSrc {
+    fn is_seekable(&self) -> bool {
+        false
+    }
+
+    fn caps(&self, filter: Option<&gst::Caps>) -> Option<gst::Caps> {
+        let caps = self.settings.lock().unwrap().caps.clone();
+
+        Some(match filter {
+            Some(filter) => filter.intersect_with_mode(&caps, gst::CapsIntersectMode::First),
+            None => caps,
+        })
+    }
+
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        let settings = self.settings.lock().unwrap().clone();
+
+        let (client, connection) = settings
+            .connection
+            .connect(self.obj().upcast_ref::<gst::Element>());
+
+        // Queued until the connection is established
+        client
+            .subscribe(settings.topic.as_str(), settings.qos.into())
+            .map_err(|err| {
+                gst::error_msg!(
+                    gst::ResourceError::OpenRead,
+                    ["Failed to subscribe to {}: {}", settings.topic, err]
+                )
+            })?;
+
+        gst::debug!(
+            CAT,
+            imp: self,
+            "Subscribed to {} on {}:{}",
+            settings.topic,
+            settings.connection.host,
+            settings.connection.port
+        );
+
+        *self.state.lock().unwrap() = Some(State {
+            client,
+            connection,
+            emit_events: settings.emit_events,
+        });
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        if let Some(state) = self.state.lock().unwrap().take() {
+            let _ = state.client.try_disconnect();
+        }
+
+        gst::debug!(CAT, imp: self, "Stopped");
+
+        Ok(())
+    }
+
+    fn unlock(&self) -> Result<(), gst::ErrorMessage> {
+        self.flushing.store(true, Ordering::SeqCst);
+
+        Ok(())
+    }
+
+    fn unlock_stop(&self) -> Result<(), gst::ErrorMessage> {
+        self.flushing.store(false, Ordering::SeqCst);
+
+        Ok(())
+    }
+}
+
+impl PushSrcImpl for MqttSrc {
+    fn create(
+        &self,
+        _buffer: Option<&mut gst::BufferRef>,
+    ) -> Result<CreateSuccess, gst::FlowError> {
+        let mut state = self.state.lock().unwrap();
+        let state = state.as_mut().ok_or_else(|| {
+            gst::element_imp_error!(self, gst::CoreError::Failed, ["Not started yet"]);
+            gst::FlowError::Error
+        })?;
+
+        let publish = loop {
+            if self.flushing.load(Ordering::SeqCst) {
+                gst::debug!(CAT, imp: self, "Flushing");
+                return Err(gst::FlowError::Flushing);
+            }
+
+            match state.connection.recv_timeout(POLL_TIMEOUT) {
+                Ok(Ok(rumqttc::Event::Incoming(rumqttc::Packet::Publish(publish)))) => {
+                    break publish
+                }
+                Ok(Ok(event)) => gst::trace!(CAT, imp: self, "MQTT event {event:?}"),
+                Ok(Err(err)) => {
+                    // The next poll tries to reconnect
+                    gst::warning!(CAT, imp: self, "MQTT connection error: {err}");
+                    std::thread::sleep(POLL_TIMEOUT);
+                }
+                Err(rumqttc::RecvTimeoutError::Timeout) => (),
+                Err(rumqttc::RecvTimeoutError::Disconnected) => {
+                    gst::debug!(CAT, imp: self, "Connection closed");
+                    return Err(gst::FlowError::Eos);
+                }
+            }
+        };
+
+        gst::trace!(
+            CAT,
+            imp: self,
+            "Received {} bytes on {}",
+            publish.payload.len(),
+            publish.topic
+        );
+
+        let buffer = gst::Buffer::from_slice(publish.payload);
+
+        if state.emit_events {
+            let s = gst::Structure::builder("mqtt-message")
+                .field("topic", &publish.topic)
+                .field("qos", publish.qos as u32)
+                .field("retain", publish.retain)
+                .field("payload", &buffer)
+                .build();
+
+            // Serialized events sent to a source are pushed right before the
+            // next buffer
+            self.obj().send_event(gst::event::CustomDownstream::new(s));
+        }
+
+        Ok(CreateSuccess::NewBuffer(buffer))
+    }
+}
@@ -0,0 +1,46 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * element-mqttsrc:
+ * @short-description: Receive messages from an MQTT broker
+ *
+ * Subscribes to `topic` (which can contain MQTT wildcards) and outputs the
+ * payload of every received message as a buffer with the configured `caps`.
+ *
+ * If `emit-events` is enabled, each buffer is preceded by a serialized
+ * custom downstream event with a `mqtt-message` structure, containing the
+ * `topic`, `qos` and `retain` flag of the message and its `payload` as a
+ * buffer. This allows downstream elements and pad probes to react to
+ * messages coming from IoT devices in sync with the stream.
+ *
+ * ## Example pipeline
+ * ```bash
+ * gst-launch-1.0 mqttsrc host=broker.local topic="sensors/#" \
+ *     caps=application/x-json ! fakesink dump=true
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct MqttSrc(ObjectSubclass<imp::MqttSrc>) @extends gst_base::PushSrc, gst_base::BaseSrc, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "mqttsrc",
+        gst::Rank::NONE,
+        MqttSrc::static_type(),
+    )
+}
@@ -0,0 +1,73 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstmqtt::plugin_register_static().expect("mqtt test");
+    });
+}
+
+#[test]
+fn test_sink_does_not_block_without_broker() {
+    init();
+
+    // Nothing is listening on this port, publishing must not block the
+    // streaming thread nonetheless
+    let mut h = gst_check::Harness::new_empty();
+    h.add_parse("mqttsink host=127.0.0.1 port=1 forward-messages=true");
+    h.set_src_caps(gst::Caps::builder("application/x-json").build());
+    h.play();
+
+    let mut tags = gst::TagList::new();
+    tags.get_mut()
+        .unwrap()
+        .add::<gst::tags::Title>(&"test", gst::TagMergeMode::Append);
+    assert!(h.push_event(gst::event::Tag::new(tags)));
+    assert!(h.push_event(gst::event::CustomDownstream::new(
+        gst::Structure::builder("detection")
+            .field("label", "cat")
+            .field("confidence", 0.9f64)
+            .build()
+    )));
+
+    for i in 0..100u64 {
+        let mut buffer = gst::Buffer::from_slice(format!("{{\"frame\": {i}}}").into_bytes());
+        buffer
+            .get_mut()
+            .unwrap()
+            .set_pts(gst::ClockTime::from_mseconds(i * 40));
+        assert_eq!(h.push(buffer), Ok(gst::FlowSuccess::Ok));
+    }
+
+    h.element().unwrap().set_state(gst::State::Null).unwrap();
+}
+
+#[test]
+fn test_src_properties() {
+    init();
+
+    let src = gst::ElementFactory::make("mqttsrc")
+        .property("topic", "sensors/+/temperature")
+        .property("caps", gst::Caps::builder("application/x-json").build())
+        .build()
+        .unwrap();
+
+    assert_eq!(src.property::<String>("topic"), "sensors/+/temperature");
+    assert_eq!(src.property::<u32>("port"), 1883);
+    assert!(src.property::<bool>("emit-events"));
+    assert_eq!(
+        src.property::<gst::Caps>("caps"),
+        gst::Caps::builder("application/x-json").build()
+    );
+}
@@ -210,6 +210,22 @@ impl RtpBasePay2 {
             .saturating_sub(rtp_types::RtpPacket::MIN_RTP_PACKET_LEN as u32)
     }
 
+    pub(super) fn set_timestamp_offset(&self, timestamp_offset: u32) {
+        gst::debug!(CAT, imp: self, "Setting timestamp offset {timestamp_offset}");
+
+        let mut state = self.state.borrow_mut();
+        let Some(ref mut stream) = state.stream else {
+            gst::warning!(CAT, imp: self, "Can't set timestamp offset before starting");
+            return;
+        };
+        stream.timestamp_offset = timestamp_offset;
+        drop(state);
+
+        if let Some(ref mut stats) = *self.stats.lock().unwrap() {
+            stats.timestamp_offset = timestamp_offset;
+        }
+    }
+
     pub(super) fn set_src_caps(&self, src_caps: &gst::Caps) {
         gst::debug!(CAT, imp: self, "Setting src caps {src_caps:?}");
 
@@ -33,6 +33,17 @@ pub trait RtpBasePay2Ext: IsA<RtpBasePay2> {
             .set_src_caps(src_caps);
     }
 
+    /// Overrides the RTP timestamp offset of the stream.
+    ///
+    /// This replaces the random or configured offset that is selected when streaming starts and
+    /// applies to all packets that are queued afterwards. It must only be called from the
+    /// streaming thread, e.g. from `handle_buffer()`.
+    fn set_timestamp_offset(&self, timestamp_offset: u32) {
+        self.upcast_ref::<RtpBasePay2>()
+            .imp()
+            .set_timestamp_offset(timestamp_offset);
+    }
+
     /// Drop the buffers from the given buffer range.
     ///
     /// This should be called when input buffers are dropped because they are not included in any
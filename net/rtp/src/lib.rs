@@ -24,6 +24,7 @@ use gst::glib;
 mod utils;
 
 mod gcc;
+mod pcapsrc;
 mod rtpbin2;
 
 mod audio_discont;
@@ -35,6 +36,7 @@ mod ac3;
 mod av1;
 mod jpeg;
 mod klv;
+mod linear;
 mod mp2t;
 mod mp4a;
 mod mp4g;
@@ -48,6 +50,7 @@ mod tests;
 
 fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
     gcc::register(plugin)?;
+    pcapsrc::register(plugin)?;
     rtpbin2::register(plugin)?;
 
     #[cfg(feature = "doc")]
@@ -74,6 +77,9 @@ fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
     klv::depay::register(plugin)?;
     klv::pay::register(plugin)?;
 
+    linear::depay::register(plugin)?;
+    linear::pay::register(plugin)?;
+
     mp2t::depay::register(plugin)?;
     mp2t::pay::register(plugin)?;
 
@@ -0,0 +1,299 @@
+//
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use atomic_refcell::AtomicRefCell;
+use gst::{glib, prelude::*, subclass::prelude::*};
+
+use once_cell::sync::Lazy;
+
+use crate::basedepay::RtpBaseDepay2Ext;
+
+#[derive(Default)]
+pub struct RtpLinearDepay {
+    state: AtomicRefCell<State>,
+}
+
+#[derive(Default)]
+struct State {
+    clock_rate: Option<u32>,
+    bpf: usize,
+}
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "rtplineardepay2",
+        gst::DebugColorFlags::empty(),
+        Some("RTP L16/L24 Depayloader"),
+    )
+});
+
+#[glib::object_subclass]
+impl ObjectSubclass for RtpLinearDepay {
+    const ABSTRACT: bool = true;
+    const NAME: &'static str = "GstRtpLinearDepay2";
+    type Type = super::RtpLinearDepay;
+    type ParentType = crate::basedepay::RtpBaseDepay2;
+}
+
+impl ObjectImpl for RtpLinearDepay {}
+
+impl GstObjectImpl for RtpLinearDepay {}
+
+impl ElementImpl for RtpLinearDepay {}
+
+impl crate::basedepay::RtpBaseDepay2Impl for RtpLinearDepay {
+    const ALLOWED_META_TAGS: &'static [&'static str] = &["audio"];
+
+    fn set_sink_caps(&self, caps: &gst::Caps) -> bool {
+        let s = caps.structure(0).unwrap();
+
+        let Ok(clock_rate) = s.get::<i32>("clock-rate") else {
+            gst::error!(CAT, imp: self, "No clock-rate in caps {caps}");
+            return false;
+        };
+
+        // encoding-params is a string as per RFC 4566, channels is used by some senders
+        let channels = s
+            .get::<&str>("encoding-params")
+            .ok()
+            .and_then(|params| params.parse::<i32>().ok())
+            .or_else(|| s.get::<i32>("channels").ok())
+            .unwrap_or(1);
+        if channels <= 0 {
+            gst::error!(CAT, imp: self, "Invalid number of channels {channels}");
+            return false;
+        }
+
+        let (format, width) = if self.obj().type_().is_a(super::RtpL24Depay::static_type()) {
+            (gst_audio::AudioFormat::S24be, 3)
+        } else {
+            (gst_audio::AudioFormat::S16be, 2)
+        };
+
+        let mut src_caps = gst_audio::AudioCapsBuilder::new_interleaved()
+            .format(format)
+            .rate(clock_rate)
+            .channels(channels);
+        // The channel order is unknown without an out-of-band description
+        if channels > 2 {
+            src_caps = src_caps.channel_mask(0);
+        }
+
+        let mut state = self.state.borrow_mut();
+        state.clock_rate = Some(clock_rate as u32);
+        state.bpf = width * channels as usize;
+        drop(state);
+
+        self.obj().set_src_caps(&src_caps.build());
+
+        true
+    }
+
+    fn handle_packet(
+        &self,
+        packet: &crate::basedepay::Packet,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let mut buffer = packet.payload_buffer();
+
+        let state = self.state.borrow();
+        // Always set when caps are set
+        let clock_rate = state.clock_rate.unwrap();
+        let bpf = state.bpf;
+        drop(state);
+
+        let buffer_ref = buffer.get_mut().unwrap();
+
+        // Drop incomplete trailing samples
+        let size = buffer_ref.size();
+        if size % bpf != 0 {
+            gst::warning!(
+                CAT,
+                imp: self,
+                "Payload size {size} is not a multiple of the frame size {bpf}"
+            );
+            buffer_ref.set_size(size - size % bpf);
+        }
+
+        if buffer_ref.size() == 0 {
+            gst::trace!(CAT, imp: self, "Dropping empty packet");
+            self.obj().drop_packet(packet);
+            return Ok(gst::FlowSuccess::Ok);
+        }
+
+        buffer_ref.set_duration(
+            ((buffer_ref.size() / bpf) as u64)
+                .mul_div_floor(*gst::ClockTime::SECOND, clock_rate as u64)
+                .map(gst::ClockTime::from_nseconds),
+        );
+
+        // mark start of talkspurt with RESYNC
+        if packet.marker_bit() {
+            buffer_ref.set_flags(gst::BufferFlags::RESYNC);
+        }
+
+        gst::trace!(CAT, imp: self, "Finishing buffer {buffer:?}");
+
+        self.obj().queue_buffer(packet.into(), buffer)
+    }
+}
+
+/**
+ * SECTION:element-rtpL16depay2
+ * @see_also: rtpL16pay2, rtpL24pay2, rtpL24depay2
+ *
+ * Extracts 16 bit linear PCM audio from RTP packets as per [RFC 3551][rfc-3551], including
+ * [AES67] and [SMPTE ST 2110-30][st-2110-30] streams.
+ *
+ * For receiving AES67 streams with sample accurate timing, the pipeline clock should be a PTP
+ * clock synchronized to the sender's grandmaster and `rtpjitterbuffer` should be configured
+ * with `rfc7273-sync=true` together with the `a=ts-refclk` and `a=mediaclk` SDP attributes in
+ * the caps.
+ *
+ * [rfc-3551]: https://www.rfc-editor.org/rfc/rfc3551.html#section-4.5.11
+ * [AES67]: https://www.aes.org/publications/standards/search.cfm?docID=96
+ * [st-2110-30]: https://ieeexplore.ieee.org/document/8167392
+ *
+ * ## Example pipeline
+ *
+ * |[
+ * gst-launch-1.0 udpsrc address=239.69.0.1 port=5004 caps='application/x-rtp, media=audio, clock-rate=48000, encoding-name=L16, encoding-params=(string)2' ! rtpjitterbuffer latency=10 ! rtpL16depay2 ! audioconvert ! audioresample ! autoaudiosink
+ * ]| This will depayload an incoming RTP 16 bit stereo audio stream. You can use the
+ * #rtpL16pay2 element to create such an RTP stream.
+ *
+ * Since: plugins-rs-0.13.0
+ */
+
+#[derive(Default)]
+pub struct RtpL16Depay;
+
+#[glib::object_subclass]
+impl ObjectSubclass for RtpL16Depay {
+    const NAME: &'static str = "GstRtpL16Depay2";
+    type Type = super::RtpL16Depay;
+    type ParentType = super::RtpLinearDepay;
+}
+
+impl ObjectImpl for RtpL16Depay {}
+
+impl GstObjectImpl for RtpL16Depay {}
+
+impl ElementImpl for RtpL16Depay {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "RTP L16 Depayloader",
+                "Codec/Depayloader/Network/RTP",
+                "Depayload 16 bit linear audio from RTP packets (RFC 3551, AES67)",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> =
+            Lazy::new(|| pad_templates(gst_audio::AudioFormat::S16be, "L16"));
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl crate::basedepay::RtpBaseDepay2Impl for RtpL16Depay {}
+
+impl super::RtpLinearDepayImpl for RtpL16Depay {}
+
+/**
+ * SECTION:element-rtpL24depay2
+ * @see_also: rtpL24pay2, rtpL16pay2, rtpL16depay2
+ *
+ * Extracts 24 bit linear PCM audio from RTP packets as per [RFC 3190][rfc-3190], including
+ * [AES67] and [SMPTE ST 2110-30][st-2110-30] streams.
+ *
+ * See #rtpL16depay2 for details about receiving AES67 streams.
+ *
+ * [rfc-3190]: https://www.rfc-editor.org/rfc/rfc3190.html#section-4
+ * [AES67]: https://www.aes.org/publications/standards/search.cfm?docID=96
+ * [st-2110-30]: https://ieeexplore.ieee.org/document/8167392
+ *
+ * ## Example pipeline
+ *
+ * |[
+ * gst-launch-1.0 udpsrc address=239.69.0.1 port=5004 caps='application/x-rtp, media=audio, clock-rate=48000, encoding-name=L24, encoding-params=(string)8' ! rtpjitterbuffer latency=10 ! rtpL24depay2 ! audioconvert ! audioresample ! autoaudiosink
+ * ]| This will depayload an incoming RTP 24 bit audio stream with 8 channels. You can use the
+ * #rtpL24pay2 element to create such an RTP stream.
+ *
+ * Since: plugins-rs-0.13.0
+ */
+
+#[derive(Default)]
+pub struct RtpL24Depay;
+
+#[glib::object_subclass]
+impl ObjectSubclass for RtpL24Depay {
+    const NAME: &'static str = "GstRtpL24Depay2";
+    type Type = super::RtpL24Depay;
+    type ParentType = super::RtpLinearDepay;
+}
+
+impl ObjectImpl for RtpL24Depay {}
+
+impl GstObjectImpl for RtpL24Depay {}
+
+impl ElementImpl for RtpL24Depay {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "RTP L24 Depayloader",
+                "Codec/Depayloader/Network/RTP",
+                "Depayload 24 bit linear audio from RTP packets (RFC 3190, AES67)",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> =
+            Lazy::new(|| pad_templates(gst_audio::AudioFormat::S24be, "L24"));
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl crate::basedepay::RtpBaseDepay2Impl for RtpL24Depay {}
+
+impl super::RtpLinearDepayImpl for RtpL24Depay {}
+
+fn pad_templates(format: gst_audio::AudioFormat, encoding_name: &str) -> Vec<gst::PadTemplate> {
+    let sink_pad_template = gst::PadTemplate::new(
+        "sink",
+        gst::PadDirection::Sink,
+        gst::PadPresence::Always,
+        &gst::Caps::builder("application/x-rtp")
+            .field("media", "audio")
+            .field("clock-rate", gst::IntRange::new(1i32, i32::MAX))
+            .field("encoding-name", encoding_name)
+            .build(),
+    )
+    .unwrap();
+
+    let src_pad_template = gst::PadTemplate::new(
+        "src",
+        gst::PadDirection::Src,
+        gst::PadPresence::Always,
+        &gst_audio::AudioCapsBuilder::new_interleaved()
+            .format(format)
+            .build(),
+    )
+    .unwrap();
+
+    vec![src_pad_template, sink_pad_template]
+}
@@ -0,0 +1,59 @@
+//
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::{glib, prelude::*, subclass::prelude::*};
+
+pub mod imp;
+
+glib::wrapper! {
+    pub struct RtpLinearDepay(ObjectSubclass<imp::RtpLinearDepay>)
+        @extends crate::basedepay::RtpBaseDepay2, gst::Element, gst::Object;
+}
+
+pub trait RtpLinearDepayImpl: crate::basedepay::RtpBaseDepay2Impl {}
+
+unsafe impl<T: RtpLinearDepayImpl> IsSubclassable<T> for RtpLinearDepay {
+    fn class_init(class: &mut glib::Class<Self>) {
+        Self::parent_class_init::<T>(class);
+    }
+}
+
+glib::wrapper! {
+    pub struct RtpL16Depay(ObjectSubclass<imp::RtpL16Depay>)
+        @extends RtpLinearDepay, crate::basedepay::RtpBaseDepay2, gst::Element, gst::Object;
+}
+
+glib::wrapper! {
+    pub struct RtpL24Depay(ObjectSubclass<imp::RtpL24Depay>)
+        @extends RtpLinearDepay, crate::basedepay::RtpBaseDepay2, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    #[cfg(feature = "doc")]
+    {
+        use gst::prelude::*;
+
+        // Make internal base class available in docs
+        crate::linear::depay::RtpLinearDepay::static_type()
+            .mark_as_plugin_api(gst::PluginAPIFlags::empty());
+    }
+
+    gst::Element::register(
+        Some(plugin),
+        "rtpL16depay2",
+        gst::Rank::MARGINAL,
+        RtpL16Depay::static_type(),
+    )?;
+    gst::Element::register(
+        Some(plugin),
+        "rtpL24depay2",
+        gst::Rank::MARGINAL,
+        RtpL24Depay::static_type(),
+    )
+}
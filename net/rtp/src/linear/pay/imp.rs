@@ -0,0 +1,432 @@
+//
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::sync::Mutex;
+
+use gst::{glib, prelude::*, subclass::prelude::*};
+
+use once_cell::sync::Lazy;
+
+use crate::{
+    baseaudiopay::RtpBaseAudioPay2Ext,
+    basepay::{RtpBasePay2Ext, RtpBasePay2ImplExt},
+};
+
+use super::RtpLinearPayPacketTime;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "rtplinearpay2",
+        gst::DebugColorFlags::empty(),
+        Some("RTP L16/L24 Payloader"),
+    )
+});
+
+#[derive(Clone, Default)]
+struct Settings {
+    packet_time: RtpLinearPayPacketTime,
+    clock_aligned: bool,
+}
+
+#[derive(Default)]
+struct State {
+    /// Sample rate and number of channels of the current caps.
+    format: Option<(u32, u32)>,
+    /// Set once the RTP timestamps were aligned to the pipeline clock.
+    aligned: bool,
+}
+
+#[derive(Default)]
+pub struct RtpLinearPay {
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for RtpLinearPay {
+    const ABSTRACT: bool = true;
+    const NAME: &'static str = "GstRtpLinearPay2";
+    type Type = super::RtpLinearPay;
+    type ParentType = crate::baseaudiopay::RtpBaseAudioPay2;
+}
+
+impl ObjectImpl for RtpLinearPay {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecEnum::builder::<RtpLinearPayPacketTime>("packet-time")
+                    .nick("Packet Time")
+                    .blurb("Fixed duration of the packets as used by AES67 / ST 2110-30, overrides min-ptime and max-ptime")
+                    .default_value(Settings::default().packet_time)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("clock-aligned")
+                    .nick("Clock Aligned")
+                    .blurb("Align RTP timestamps to the pipeline clock, e.g. a PTP clock, as required by AES67 / ST 2110-30")
+                    .default_value(Settings::default().clock_aligned)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("sdp")
+                    .nick("SDP")
+                    .blurb("SDP media attributes describing the current stream")
+                    .read_only()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "packet-time" => {
+                let packet_time = value
+                    .get::<RtpLinearPayPacketTime>()
+                    .expect("type checked upstream");
+                self.settings.lock().unwrap().packet_time = packet_time;
+
+                if let Some(ptime) = packet_time.duration() {
+                    let obj = self.obj();
+                    obj.set_property("min-ptime", ptime.nseconds() as i64);
+                    obj.set_property("max-ptime", ptime.nseconds() as i64);
+                }
+            }
+            "clock-aligned" => {
+                self.settings.lock().unwrap().clock_aligned =
+                    value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "packet-time" => self.settings.lock().unwrap().packet_time.to_value(),
+            "clock-aligned" => self.settings.lock().unwrap().clock_aligned.to_value(),
+            "sdp" => self.sdp().to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for RtpLinearPay {}
+
+impl ElementImpl for RtpLinearPay {}
+
+impl crate::basepay::RtpBasePay2Impl for RtpLinearPay {
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.lock().unwrap() = State::default();
+        self.parent_start()
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.lock().unwrap() = State::default();
+        self.parent_stop()
+    }
+
+    fn set_sink_caps(&self, caps: &gst::Caps) -> bool {
+        let s = caps.structure(0).unwrap();
+
+        let rate = u32::try_from(s.get::<i32>("rate").unwrap()).unwrap();
+        let channels = u32::try_from(s.get::<i32>("channels").unwrap()).unwrap();
+
+        let src_caps = gst::Caps::builder("application/x-rtp")
+            .field("media", "audio")
+            .field("encoding-name", self.encoding_name())
+            .field("clock-rate", rate as i32)
+            .field("encoding-params", channels.to_string())
+            .field("channels", channels as i32)
+            .build();
+
+        self.state.lock().unwrap().format = Some((rate, channels));
+
+        self.obj().set_src_caps(&src_caps);
+        self.obj().set_bpf(self.sample_width() * channels as usize);
+
+        true
+    }
+
+    fn handle_buffer(
+        &self,
+        buffer: &gst::Buffer,
+        id: u64,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        if self.settings.lock().unwrap().clock_aligned {
+            self.align_to_clock();
+        }
+
+        self.parent_handle_buffer(buffer, id)
+    }
+}
+
+impl crate::baseaudiopay::RtpBaseAudioPay2Impl for RtpLinearPay {}
+
+impl RtpLinearPay {
+    fn is_l24(&self) -> bool {
+        self.obj().type_().is_a(super::RtpL24Pay::static_type())
+    }
+
+    fn encoding_name(&self) -> &'static str {
+        if self.is_l24() {
+            "L24"
+        } else {
+            "L16"
+        }
+    }
+
+    fn sample_width(&self) -> usize {
+        if self.is_l24() {
+            3
+        } else {
+            2
+        }
+    }
+
+    /// Selects the RTP timestamp offset so that the RTP timestamps are the clock time of the
+    /// pipeline clock in clock-rate units, i.e. with a media clock offset of 0.
+    ///
+    /// This is only possible once the base time is known in PLAYING.
+    fn align_to_clock(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.aligned {
+            return;
+        }
+        let Some((rate, _)) = state.format else {
+            return;
+        };
+
+        let obj = self.obj();
+        if obj.current_state() != gst::State::Playing || obj.clock().is_none() {
+            return;
+        }
+        let Some(base_time) = obj.base_time() else {
+            return;
+        };
+
+        let timestamp_offset = (base_time
+            .nseconds()
+            .mul_div_floor(rate as u64, *gst::ClockTime::SECOND)
+            .unwrap()
+            & 0xffff_ffff) as u32;
+
+        gst::debug!(
+            CAT,
+            imp: self,
+            "Aligning to clock with base time {base_time}, timestamp offset {timestamp_offset}"
+        );
+
+        obj.set_timestamp_offset(timestamp_offset);
+        state.aligned = true;
+    }
+
+    /// Creates the SDP media attributes for the current stream as per AES67 / RFC 7273.
+    fn sdp(&self) -> Option<String> {
+        use std::fmt::Write;
+
+        let (rate, channels) = self.state.lock().unwrap().format?;
+        let settings = self.settings.lock().unwrap().clone();
+        let obj = self.obj();
+
+        let mut sdp = String::new();
+        writeln!(
+            &mut sdp,
+            "a=rtpmap:{} {}/{rate}/{channels}\r",
+            obj.property::<u32>("pt"),
+            self.encoding_name(),
+        )
+        .unwrap();
+
+        if let Some(ptime) = settings.packet_time.duration() {
+            let ptime = format!("{:.3}", ptime.nseconds() as f64 / 1_000_000.0);
+            let ptime = ptime.trim_end_matches('0').trim_end_matches('.');
+            writeln!(&mut sdp, "a=ptime:{ptime}\r").unwrap();
+        }
+
+        // PTP clocks from gstreamer-net expose the grandmaster clock and domain
+        if let Some(clock) = obj.clock() {
+            if clock.has_property("grandmaster-clock-id", Some(u64::static_type()))
+                && clock.has_property("domain", Some(u32::static_type()))
+            {
+                let gmid = clock.property::<u64>("grandmaster-clock-id").to_be_bytes();
+                let domain = clock.property::<u32>("domain");
+                writeln!(
+                    &mut sdp,
+                    "a=ts-refclk:ptp=IEEE1588-2008:{:02X}-{:02X}-{:02X}-{:02X}-{:02X}-{:02X}-{:02X}-{:02X}:{domain}\r",
+                    gmid[0], gmid[1], gmid[2], gmid[3], gmid[4], gmid[5], gmid[6], gmid[7],
+                )
+                .unwrap();
+            }
+        }
+
+        if settings.clock_aligned {
+            writeln!(&mut sdp, "a=mediaclk:direct=0\r").unwrap();
+        }
+
+        Some(sdp)
+    }
+}
+
+/**
+ * SECTION:element-rtpL16pay2
+ * @see_also: rtpL16depay2, rtpL24pay2, rtpL24depay2
+ *
+ * Payloads 16 bit linear PCM audio into RTP packets as per [RFC 3551][rfc-3551], with support
+ * for the [AES67] and [SMPTE ST 2110-30][st-2110-30] profiles.
+ *
+ * With #rtpL16pay2:packet-time the packet duration can be fixed to one of the packet times
+ * defined by AES67, and with #rtpL16pay2:clock-aligned the RTP timestamps are aligned to the
+ * pipeline clock, which should be a PTP clock for interoperability. The #rtpL16pay2:sdp
+ * property provides the corresponding SDP media attributes.
+ *
+ * [rfc-3551]: https://www.rfc-editor.org/rfc/rfc3551.html#section-4.5.11
+ * [AES67]: https://www.aes.org/publications/standards/search.cfm?docID=96
+ * [st-2110-30]: https://ieeexplore.ieee.org/document/8167392
+ *
+ * ## Example pipeline
+ *
+ * |[
+ * gst-launch-1.0 audiotestsrc is-live=true ! audio/x-raw,format=S16BE,rate=48000,channels=2 ! rtpL16pay2 packet-time=1ms ! udpsink host=239.69.0.1 port=5004
+ * ]| This will generate a 16 bit audio test signal and send it out as RTP with 1ms packets to
+ * a multicast group.
+ *
+ * Since: plugins-rs-0.13.0
+ */
+
+#[derive(Default)]
+pub struct RtpL16Pay;
+
+#[glib::object_subclass]
+impl ObjectSubclass for RtpL16Pay {
+    const NAME: &'static str = "GstRtpL16Pay2";
+    type Type = super::RtpL16Pay;
+    type ParentType = super::RtpLinearPay;
+}
+
+impl ObjectImpl for RtpL16Pay {}
+
+impl GstObjectImpl for RtpL16Pay {}
+
+impl ElementImpl for RtpL16Pay {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "RTP L16 Payloader",
+                "Codec/Payloader/Network/RTP",
+                "Payload 16 bit linear audio into RTP packets (RFC 3551, AES67)",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> =
+            Lazy::new(|| pad_templates(gst_audio::AudioFormat::S16be, "L16"));
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl crate::basepay::RtpBasePay2Impl for RtpL16Pay {}
+
+impl crate::baseaudiopay::RtpBaseAudioPay2Impl for RtpL16Pay {}
+
+impl super::RtpLinearPayImpl for RtpL16Pay {}
+
+/**
+ * SECTION:element-rtpL24pay2
+ * @see_also: rtpL24depay2, rtpL16pay2, rtpL16depay2
+ *
+ * Payloads 24 bit linear PCM audio into RTP packets as per [RFC 3190][rfc-3190], with support
+ * for the [AES67] and [SMPTE ST 2110-30][st-2110-30] profiles.
+ *
+ * See #rtpL16pay2 for the AES67 specific properties.
+ *
+ * [rfc-3190]: https://www.rfc-editor.org/rfc/rfc3190.html#section-4
+ * [AES67]: https://www.aes.org/publications/standards/search.cfm?docID=96
+ * [st-2110-30]: https://ieeexplore.ieee.org/document/8167392
+ *
+ * ## Example pipeline
+ *
+ * |[
+ * gst-launch-1.0 audiotestsrc is-live=true ! audio/x-raw,format=S24BE,rate=48000,channels=8 ! rtpL24pay2 packet-time=125us clock-aligned=true ! udpsink host=239.69.0.1 port=5004
+ * ]| This will generate a 24 bit audio test signal with 8 channels and send it out as RTP with
+ * 125µs packets and clock-aligned RTP timestamps to a multicast group.
+ *
+ * Since: plugins-rs-0.13.0
+ */
+
+#[derive(Default)]
+pub struct RtpL24Pay;
+
+#[glib::object_subclass]
+impl ObjectSubclass for RtpL24Pay {
+    const NAME: &'static str = "GstRtpL24Pay2";
+    type Type = super::RtpL24Pay;
+    type ParentType = super::RtpLinearPay;
+}
+
+impl ObjectImpl for RtpL24Pay {}
+
+impl GstObjectImpl for RtpL24Pay {}
+
+impl ElementImpl for RtpL24Pay {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "RTP L24 Payloader",
+                "Codec/Payloader/Network/RTP",
+                "Payload 24 bit linear audio into RTP packets (RFC 3190, AES67)",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> =
+            Lazy::new(|| pad_templates(gst_audio::AudioFormat::S24be, "L24"));
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl crate::basepay::RtpBasePay2Impl for RtpL24Pay {}
+
+impl crate::baseaudiopay::RtpBaseAudioPay2Impl for RtpL24Pay {}
+
+impl super::RtpLinearPayImpl for RtpL24Pay {}
+
+fn pad_templates(format: gst_audio::AudioFormat, encoding_name: &str) -> Vec<gst::PadTemplate> {
+    let sink_pad_template = gst::PadTemplate::new(
+        "sink",
+        gst::PadDirection::Sink,
+        gst::PadPresence::Always,
+        &gst_audio::AudioCapsBuilder::new_interleaved()
+            .format(format)
+            .build(),
+    )
+    .unwrap();
+
+    let src_pad_template = gst::PadTemplate::new(
+        "src",
+        gst::PadDirection::Src,
+        gst::PadPresence::Always,
+        &gst::Caps::builder("application/x-rtp")
+            .field("media", "audio")
+            .field("encoding-name", encoding_name)
+            .field("clock-rate", gst::IntRange::new(1, i32::MAX))
+            .build(),
+    )
+    .unwrap();
+
+    vec![src_pad_template, sink_pad_template]
+}
@@ -0,0 +1,93 @@
+//
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::{glib, prelude::*, subclass::prelude::*};
+
+pub mod imp;
+
+/// Packet times as allowed by AES67 and SMPTE ST 2110-30.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum, Default)]
+#[repr(i32)]
+#[enum_type(name = "GstRtpLinearPayPacketTime")]
+#[non_exhaustive]
+pub(crate) enum RtpLinearPayPacketTime {
+    #[default]
+    #[enum_value(
+        name = "Automatic: packet time is configured via min-ptime / max-ptime and the caps",
+        nick = "auto"
+    )]
+    Auto = 0,
+    #[enum_value(name = "125µs", nick = "125us")]
+    Us125 = 125,
+    #[enum_value(name = "250µs", nick = "250us")]
+    Us250 = 250,
+    #[enum_value(name = "333µs", nick = "333us")]
+    Us333 = 333,
+    #[enum_value(name = "1ms", nick = "1ms")]
+    Ms1 = 1000,
+    #[enum_value(name = "4ms", nick = "4ms")]
+    Ms4 = 4000,
+}
+
+impl RtpLinearPayPacketTime {
+    pub(crate) fn duration(self) -> Option<gst::ClockTime> {
+        match self {
+            RtpLinearPayPacketTime::Auto => None,
+            // 16 samples at 48kHz, rounded up so that no sample is lost when converting back
+            RtpLinearPayPacketTime::Us333 => Some(gst::ClockTime::from_nseconds(333_334)),
+            _ => Some(gst::ClockTime::from_useconds(self as u64)),
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct RtpLinearPay(ObjectSubclass<imp::RtpLinearPay>)
+        @extends crate::baseaudiopay::RtpBaseAudioPay2, crate::basepay::RtpBasePay2, gst::Element, gst::Object;
+}
+
+pub trait RtpLinearPayImpl: crate::baseaudiopay::RtpBaseAudioPay2Impl {}
+
+unsafe impl<T: RtpLinearPayImpl> IsSubclassable<T> for RtpLinearPay {
+    fn class_init(class: &mut glib::Class<Self>) {
+        Self::parent_class_init::<T>(class);
+    }
+}
+
+glib::wrapper! {
+    pub struct RtpL16Pay(ObjectSubclass<imp::RtpL16Pay>)
+        @extends RtpLinearPay, crate::baseaudiopay::RtpBaseAudioPay2, crate::basepay::RtpBasePay2, gst::Element, gst::Object;
+}
+
+glib::wrapper! {
+    pub struct RtpL24Pay(ObjectSubclass<imp::RtpL24Pay>)
+        @extends RtpLinearPay, crate::baseaudiopay::RtpBaseAudioPay2, crate::basepay::RtpBasePay2, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    #[cfg(feature = "doc")]
+    {
+        // Make internal base class available in docs
+        crate::linear::pay::RtpLinearPay::static_type()
+            .mark_as_plugin_api(gst::PluginAPIFlags::empty());
+        RtpLinearPayPacketTime::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+    }
+
+    gst::Element::register(
+        Some(plugin),
+        "rtpL16pay2",
+        gst::Rank::MARGINAL,
+        RtpL16Pay::static_type(),
+    )?;
+    gst::Element::register(
+        Some(plugin),
+        "rtpL24pay2",
+        gst::Rank::MARGINAL,
+        RtpL24Pay::static_type(),
+    )
+}
@@ -0,0 +1,340 @@
+//
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::tests::{
+    run_test_pipeline, run_test_pipeline_full, ExpectedBuffer, ExpectedPacket, Liveness, Source,
+};
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        crate::plugin_register_static().expect("rtplinear test");
+    });
+}
+
+#[test]
+fn test_l16() {
+    init();
+
+    let src = "audiotestsrc num-buffers=100 samplesperbuffer=400 ! audio/x-raw,format=S16BE,rate=8000,channels=1";
+    let pay = "rtpL16pay2";
+    let depay = "rtpL16depay2";
+
+    let mut expected_pay = Vec::with_capacity(100);
+    for i in 0..100 {
+        expected_pay.push(vec![ExpectedPacket::builder()
+            .pts(gst::ClockTime::from_mseconds(i * 50))
+            .size(800 + 12)
+            .flags(if i == 0 {
+                gst::BufferFlags::DISCONT | gst::BufferFlags::MARKER
+            } else {
+                gst::BufferFlags::empty()
+            })
+            .pt(96)
+            .rtp_time(((i * 400) & 0xffff_ffff) as u32)
+            .marker_bit(i == 0)
+            .build()]);
+    }
+
+    let mut expected_depay = Vec::with_capacity(100);
+    for i in 0..100 {
+        expected_depay.push(vec![ExpectedBuffer::builder()
+            .pts(gst::ClockTime::from_mseconds(i * 50))
+            .size(800)
+            .flags(if i == 0 {
+                gst::BufferFlags::DISCONT | gst::BufferFlags::RESYNC
+            } else {
+                gst::BufferFlags::empty()
+            })
+            .build()]);
+    }
+
+    run_test_pipeline(Source::Bin(src), pay, depay, expected_pay, expected_depay);
+}
+
+#[test]
+fn test_l24_aes67_packet_time() {
+    init();
+
+    let src = "audiotestsrc num-buffers=10 samplesperbuffer=480 ! audio/x-raw,format=S24BE,rate=48000,channels=2";
+    let pay = "rtpL24pay2 packet-time=1ms";
+    let depay = "rtpL24depay2";
+
+    // Every input buffer is 10ms and is split into 1ms packets of 48 stereo samples
+    let mut expected_pay = Vec::with_capacity(100);
+    for i in 0..100 {
+        expected_pay.push(vec![ExpectedPacket::builder()
+            .pts(gst::ClockTime::from_mseconds(i))
+            .size(48 * 2 * 3 + 12)
+            .flags(if i == 0 {
+                gst::BufferFlags::DISCONT | gst::BufferFlags::MARKER
+            } else {
+                gst::BufferFlags::empty()
+            })
+            .pt(96)
+            .rtp_time(((i * 48) & 0xffff_ffff) as u32)
+            .marker_bit(i == 0)
+            .build()]);
+    }
+
+    let mut expected_depay = Vec::with_capacity(100);
+    for i in 0..100 {
+        expected_depay.push(vec![ExpectedBuffer::builder()
+            .pts(gst::ClockTime::from_mseconds(i))
+            .size(48 * 2 * 3)
+            .flags(if i == 0 {
+                gst::BufferFlags::DISCONT | gst::BufferFlags::RESYNC
+            } else {
+                gst::BufferFlags::empty()
+            })
+            .build()]);
+    }
+
+    run_test_pipeline(Source::Bin(src), pay, depay, expected_pay, expected_depay);
+}
+
+/// Round-trips 10 buffers of 10ms through the payloader with an MTU that allows for 4ms of
+/// audio, so every buffer is split into packets of 4ms, 4ms and 2ms.
+fn test_mtu(encoding_name: &str, format: gst_audio::AudioFormat, channels: u32) {
+    init();
+
+    let bpf = format.info().width() as usize / 8 * channels as usize;
+    let mtu = 192 * bpf + 12;
+
+    let src = format!(
+        "audiotestsrc num-buffers=10 samplesperbuffer=480 ! audio/x-raw,format={},rate=48000,channels={channels}",
+        format.to_str(),
+    );
+    let pay = format!("rtp{encoding_name}pay2 mtu={mtu}");
+    let depay = format!("rtp{encoding_name}depay2");
+
+    // (offset in ms, frames) of the packets of every buffer
+    let packets = [(0, 192), (4, 192), (8, 96)];
+
+    let mut expected_pay = Vec::with_capacity(30);
+    let mut expected_depay = Vec::with_capacity(30);
+    for i in 0..10 {
+        for (j, (offset, frames)) in packets.iter().enumerate() {
+            let first = i == 0 && j == 0;
+
+            expected_pay.push(vec![ExpectedPacket::builder()
+                .pts(gst::ClockTime::from_mseconds(i * 10 + offset))
+                .size(frames * bpf + 12)
+                .flags(if first {
+                    gst::BufferFlags::DISCONT | gst::BufferFlags::MARKER
+                } else {
+                    gst::BufferFlags::empty()
+                })
+                .pt(96)
+                .rtp_time((i * 480 + offset * 48) as u32)
+                .marker_bit(first)
+                .build()]);
+
+            expected_depay.push(vec![ExpectedBuffer::builder()
+                .pts(gst::ClockTime::from_mseconds(i * 10 + offset))
+                .size(frames * bpf)
+                .flags(if first {
+                    gst::BufferFlags::DISCONT | gst::BufferFlags::RESYNC
+                } else {
+                    gst::BufferFlags::empty()
+                })
+                .build()]);
+        }
+    }
+
+    let expected_depay_caps = gst_audio::AudioCapsBuilder::new_interleaved()
+        .format(format)
+        .rate(48000)
+        .channels(channels as i32)
+        .build();
+
+    run_test_pipeline_full(
+        Source::Bin(&src),
+        &pay,
+        &depay,
+        expected_pay,
+        expected_depay,
+        Some(expected_depay_caps),
+        Liveness::NonLive,
+    );
+}
+
+#[test]
+fn test_l16_mono_mtu() {
+    test_mtu("L16", gst_audio::AudioFormat::S16be, 1);
+}
+
+#[test]
+fn test_l16_stereo_mtu() {
+    test_mtu("L16", gst_audio::AudioFormat::S16be, 2);
+}
+
+#[test]
+fn test_l24_mono_mtu() {
+    test_mtu("L24", gst_audio::AudioFormat::S24be, 1);
+}
+
+#[test]
+fn test_l24_stereo_mtu() {
+    test_mtu("L24", gst_audio::AudioFormat::S24be, 2);
+}
+
+fn rtp_packet(seqnum: u16, rtp_time: u32, payload: &[u8]) -> gst::Buffer {
+    let packet = rtp_types::RtpPacketBuilder::new()
+        .payload_type(96)
+        .sequence_number(seqnum)
+        .timestamp(rtp_time)
+        .payload(payload)
+        .write_vec()
+        .unwrap();
+
+    let mut buffer = gst::Buffer::from_mut_slice(packet);
+    buffer
+        .get_mut()
+        .unwrap()
+        .set_pts(gst::ClockTime::from_nseconds(
+            rtp_time as u64 * 1_000_000 / 48,
+        ));
+    buffer
+}
+
+fn rtp_caps(encoding_name: &str, clock_rate: i32) -> gst::caps::Builder<gst::caps::NoFeature> {
+    gst::Caps::builder("application/x-rtp")
+        .field("media", "audio")
+        .field("payload", 96)
+        .field("clock-rate", clock_rate)
+        .field("encoding-name", encoding_name)
+}
+
+#[test]
+fn test_depay_incomplete_frames() {
+    init();
+
+    for (encoding_name, bpf) in [("L16", 4), ("L24", 6)] {
+        let mut h = gst_check::Harness::new(&format!("rtp{encoding_name}depay2"));
+        h.play();
+        h.set_src_caps(
+            rtp_caps(encoding_name, 48000)
+                .field("encoding-params", "2")
+                .build(),
+        );
+
+        let payload = (0..=255u8).collect::<Vec<_>>();
+
+        // Two complete frames plus one byte
+        h.push(rtp_packet(0, 0, &payload[..2 * bpf + 1])).unwrap();
+        // Less than a frame
+        h.push(rtp_packet(1, 96, &payload[..bpf - 1])).unwrap();
+        // One complete frame plus all but one byte of another one
+        h.push(rtp_packet(2, 192, &payload[..2 * bpf - 1])).unwrap();
+        h.push_event(gst::event::Eos::new());
+
+        // The incomplete trailing frames are cut off and the packet without any complete frame
+        // is dropped
+        for (rtp_time, frames) in [(0, 2), (192, 1)] {
+            let buffer = h.pull().unwrap();
+            assert_eq!(
+                buffer.pts(),
+                Some(gst::ClockTime::from_nseconds(rtp_time * 1_000_000 / 48))
+            );
+            assert_eq!(
+                buffer.duration(),
+                Some(gst::ClockTime::from_nseconds(frames * 1_000_000 / 48))
+            );
+            assert_eq!(
+                buffer.map_readable().unwrap().as_slice(),
+                &payload[..frames as usize * bpf]
+            );
+        }
+        assert_eq!(h.buffers_in_queue(), 0);
+    }
+}
+
+/// Returns the caps the depayloader outputs for the given RTP caps, or `None` if they are
+/// not accepted.
+fn depay_caps(depay: &str, caps: gst::Caps) -> Option<gst::Caps> {
+    let mut h = gst_check::Harness::new(depay);
+    h.play();
+    h.set_src_caps(caps);
+
+    // A multiple of the frame size for all channel configurations below
+    h.push(rtp_packet(0, 0, &[0; 48])).ok()?;
+    h.pull().unwrap();
+
+    h.sinkpad().unwrap().current_caps()
+}
+
+#[test]
+fn test_depay_caps() {
+    init();
+
+    for (encoding_name, format) in [
+        ("L16", gst_audio::AudioFormat::S16be),
+        ("L24", gst_audio::AudioFormat::S24be),
+    ] {
+        let depay = format!("rtp{encoding_name}depay2");
+        let audio_caps = |rate: i32, channels: i32| {
+            gst_audio::AudioCapsBuilder::new_interleaved()
+                .format(format)
+                .rate(rate)
+                .channels(channels)
+        };
+
+        // Mono without encoding-params
+        assert_eq!(
+            depay_caps(&depay, rtp_caps(encoding_name, 44100).build()),
+            Some(audio_caps(44100, 1).build())
+        );
+
+        // Channels from encoding-params
+        assert_eq!(
+            depay_caps(
+                &depay,
+                rtp_caps(encoding_name, 48000)
+                    .field("encoding-params", "2")
+                    .build()
+            ),
+            Some(audio_caps(48000, 2).build())
+        );
+
+        // Channels from the non-standard channels field
+        assert_eq!(
+            depay_caps(
+                &depay,
+                rtp_caps(encoding_name, 96000).field("channels", 2).build()
+            ),
+            Some(audio_caps(96000, 2).build())
+        );
+
+        // Unknown channel order for more than two channels
+        assert_eq!(
+            depay_caps(
+                &depay,
+                rtp_caps(encoding_name, 48000)
+                    .field("encoding-params", "8")
+                    .build()
+            ),
+            Some(audio_caps(48000, 8).channel_mask(0).build())
+        );
+
+        // Invalid number of channels
+        assert_eq!(
+            depay_caps(
+                &depay,
+                rtp_caps(encoding_name, 48000)
+                    .field("encoding-params", "0")
+                    .build()
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,480 @@
+// GStreamer PCAP / PCAPNG replay source
+//
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * SECTION:element-pcapsrc
+ * @see_also: udpsrc, rtpjitterbuffer
+ *
+ * Replays RTP packets from a PCAP or PCAPNG capture file, e.g. to reproduce
+ * issues seen in the field offline with the depayloaders of this plugin.
+ *
+ * UDP datagrams are extracted from Ethernet, Linux cooked, loopback and raw IP
+ * captures. Only datagrams to `port` are considered unless it is 0, and only
+ * RTP packets with the given `ssrc` unless it is -1. RTCP packets and
+ * fragmented IP datagrams are skipped.
+ *
+ * Packets are timestamped according to their capture time relative to the
+ * first packet, divided by `speed`. When `is-live` is set the source waits on
+ * the pipeline clock until each packet is due, otherwise the timing is only
+ * reflected in the timestamps and replay is as fast as downstream allows.
+ *
+ * The output caps are given by the `caps` property, which usually has to
+ * contain at least the clock rate and encoding name for the depayloader.
+ *
+ * ## Example pipeline
+ *
+ * |[
+ * gst-launch-1.0 pcapsrc location=capture.pcapng port=5004 is-live=true caps='application/x-rtp, media=(string)video, clock-rate=(int)90000, encoding-name=(string)VP8' ! rtpjitterbuffer ! rtpvp8depay2 ! vp8dec ! videoconvert ! autovideosink
+ * ]| This will replay a VP8 RTP stream sent to port 5004 with its original timing.
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::{glib, prelude::*, subclass::prelude::*};
+use gst_base::{prelude::*, subclass::base_src::CreateSuccess, subclass::prelude::*};
+
+use once_cell::sync::Lazy;
+
+use std::{fs::File, io::BufReader, sync::Mutex};
+
+use super::pcap;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "pcapsrc",
+        gst::DebugColorFlags::empty(),
+        Some("PCAP replay source"),
+    )
+});
+
+const DEFAULT_PORT: u32 = 0;
+const DEFAULT_SSRC: i64 = -1;
+const DEFAULT_SPEED: f64 = 1.0;
+const DEFAULT_IS_LIVE: bool = false;
+
+#[derive(Debug, Clone)]
+struct Settings {
+    location: Option<String>,
+    port: u32,
+    ssrc: Option<u32>,
+    speed: f64,
+    is_live: bool,
+    caps: gst::Caps,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            location: None,
+            port: DEFAULT_PORT,
+            ssrc: None,
+            speed: DEFAULT_SPEED,
+            is_live: DEFAULT_IS_LIVE,
+            caps: gst::Caps::new_empty_simple("application/x-rtp"),
+        }
+    }
+}
+
+struct State {
+    reader: pcap::Reader<BufReader<File>>,
+    /// Capture time of the first packet
+    first_timestamp: Option<gst::ClockTime>,
+    /// Running time at which the first packet was output when live
+    start_running_time: Option<gst::ClockTime>,
+    num_packets: u64,
+}
+
+#[derive(Default)]
+struct ClockWait {
+    clock_id: Option<gst::SingleShotClockId>,
+    flushing: bool,
+}
+
+#[derive(Default)]
+pub struct PcapSrc {
+    settings: Mutex<Settings>,
+    state: Mutex<Option<State>>,
+    clock_wait: Mutex<ClockWait>,
+}
+
+impl PcapSrc {
+    /// Returns the payload of the next packet matching the filters and its capture time
+    fn next_rtp_packet(
+        &self,
+        state: &mut State,
+        settings: &Settings,
+    ) -> Result<Option<(Vec<u8>, gst::ClockTime)>, gst::FlowError> {
+        loop {
+            let packet = match state.reader.next_packet() {
+                Ok(Some(packet)) => packet,
+                Ok(None) => return Ok(None),
+                Err(err) => {
+                    gst::element_imp_error!(
+                        self,
+                        gst::StreamError::Demux,
+                        ["Failed to read capture: {:#}", err]
+                    );
+                    return Err(gst::FlowError::Error);
+                }
+            };
+
+            let Some(udp) = pcap::extract_udp(packet.linktype, &packet.data) else {
+                continue;
+            };
+
+            if settings.port != 0 && udp.dst_port as u32 != settings.port {
+                continue;
+            }
+
+            let payload = udp.payload;
+            if payload.len() < 12 || payload[0] >> 6 != 2 {
+                gst::trace!(CAT, imp: self, "Skipping non-RTP datagram");
+                continue;
+            }
+
+            // RTCP packet types 200-204 overlap with payload types 72-76 and the marker bit
+            if (200..=204).contains(&payload[1]) {
+                gst::trace!(CAT, imp: self, "Skipping RTCP packet");
+                continue;
+            }
+
+            let ssrc = u32::from_be_bytes(payload[8..12].try_into().unwrap());
+            if settings.ssrc.map_or(false, |filter| filter != ssrc) {
+                continue;
+            }
+
+            return Ok(Some((payload.to_vec(), packet.timestamp)));
+        }
+    }
+
+    /// Waits on the clock until `running_time`
+    fn wait(&self, running_time: gst::ClockTime) -> Result<(), gst::FlowError> {
+        let obj = self.obj();
+        let Some((clock, base_time)) = Option::zip(obj.clock(), obj.base_time()) else {
+            return Ok(());
+        };
+
+        let mut clock_wait = self.clock_wait.lock().unwrap();
+        if clock_wait.flushing {
+            gst::debug!(CAT, imp: self, "Flushing");
+            return Err(gst::FlowError::Flushing);
+        }
+
+        let id = clock.new_single_shot_id(base_time + running_time);
+        clock_wait.clock_id = Some(id.clone());
+        drop(clock_wait);
+
+        gst::log!(
+            CAT,
+            imp: self,
+            "Waiting until {}, now {}",
+            base_time + running_time,
+            clock.time().display(),
+        );
+        let (res, _) = id.wait();
+        self.clock_wait.lock().unwrap().clock_id.take();
+
+        if res == Err(gst::ClockError::Unscheduled) {
+            gst::debug!(CAT, imp: self, "Flushing");
+            return Err(gst::FlowError::Flushing);
+        }
+
+        Ok(())
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for PcapSrc {
+    const NAME: &'static str = "GstPcapSrc";
+    type Type = super::PcapSrc;
+    type ParentType = gst_base::PushSrc;
+}
+
+impl ObjectImpl for PcapSrc {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecString::builder("location")
+                    .nick("File Location")
+                    .blurb("Location of the PCAP or PCAPNG file to read")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("port")
+                    .nick("Port")
+                    .blurb("UDP destination port of the packets to replay (0 = any)")
+                    .maximum(u16::MAX as u32)
+                    .default_value(DEFAULT_PORT)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecInt64::builder("ssrc")
+                    .nick("SSRC")
+                    .blurb("SSRC of the packets to replay (-1 = any)")
+                    .minimum(-1)
+                    .maximum(u32::MAX as i64)
+                    .default_value(DEFAULT_SSRC)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecDouble::builder("speed")
+                    .nick("Speed")
+                    .blurb("Replay speed relative to the original timing")
+                    .minimum(0.01)
+                    .maximum(100.0)
+                    .default_value(DEFAULT_SPEED)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("is-live")
+                    .nick("Is Live")
+                    .blurb("Wait on the clock until packets are due and act as a live source")
+                    .default_value(DEFAULT_IS_LIVE)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoxed::builder::<gst::Caps>("caps")
+                    .nick("Caps")
+                    .blurb("The caps of the RTP stream")
+                    .mutable_ready()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "location" => {
+                settings.location = value.get().expect("type checked upstream");
+            }
+            "port" => {
+                settings.port = value.get().expect("type checked upstream");
+            }
+            "ssrc" => {
+                let ssrc = value.get::<i64>().expect("type checked upstream");
+                settings.ssrc = u32::try_from(ssrc).ok();
+            }
+            "speed" => {
+                settings.speed = value.get().expect("type checked upstream");
+            }
+            "is-live" => {
+                let is_live = value.get().expect("type checked upstream");
+                settings.is_live = is_live;
+                drop(settings);
+                self.obj().set_live(is_live);
+            }
+            "caps" => {
+                settings.caps = value
+                    .get::<Option<gst::Caps>>()
+                    .expect("type checked upstream")
+                    .unwrap_or_else(|| gst::Caps::new_empty_simple("application/x-rtp"));
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "location" => settings.location.to_value(),
+            "port" => settings.port.to_value(),
+            "ssrc" => settings.ssrc.map_or(DEFAULT_SSRC, i64::from).to_value(),
+            "speed" => settings.speed.to_value(),
+            "is-live" => settings.is_live.to_value(),
+            "caps" => settings.caps.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.set_format(gst::Format::Time);
+        obj.set_live(DEFAULT_IS_LIVE);
+    }
+}
+
+impl GstObjectImpl for PcapSrc {}
+
+impl ElementImpl for PcapSrc {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "PCAP RTP Replay Source",
+                "Source/Network/RTP",
+                "Replays RTP packets from PCAP and PCAPNG capture files",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &gst::Caps::new_empty_simple("application/x-rtp"),
+            )
+            .unwrap();
+
+            vec![src_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseSrcImpl for PcapSrc {
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        let location = self.settings.lock().unwrap().location.clone();
+        let Some(location) = location else {
+            return Err(gst::error_msg!(
+                gst::ResourceError::Settings,
+                ["File location is not defined"]
+            ));
+        };
+
+        let file = File::open(&location).map_err(|err| {
+            gst::error_msg!(
+                gst::ResourceError::OpenRead,
+                ["Could not open file {} for reading: {}", location, err]
+            )
+        })?;
+
+        let reader = pcap::Reader::new(BufReader::new(file)).map_err(|err| {
+            gst::error_msg!(
+                gst::StreamError::WrongType,
+                ["Failed to read capture {}: {:#}", location, err]
+            )
+        })?;
+
+        *self.state.lock().unwrap() = Some(State {
+            reader,
+            first_timestamp: None,
+            start_running_time: None,
+            num_packets: 0,
+        });
+
+        gst::info!(CAT, imp: self, "Started reading {}", location);
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        if let Some(state) = self.state.lock().unwrap().take() {
+            gst::info!(CAT, imp: self, "Stopped after {} packets", state.num_packets);
+        }
+
+        Ok(())
+    }
+
+    fn is_seekable(&self) -> bool {
+        false
+    }
+
+    fn caps(&self, filter: Option<&gst::Caps>) -> Option<gst::Caps> {
+        let caps = self.settings.lock().unwrap().caps.clone();
+
+        Some(match filter {
+            Some(filter) => filter.intersect_with_mode(&caps, gst::CapsIntersectMode::First),
+            None => caps,
+        })
+    }
+
+    fn query(&self, query: &mut gst::QueryRef) -> bool {
+        if let gst::QueryViewMut::Latency(q) = query.view_mut() {
+            let is_live = self.settings.lock().unwrap().is_live;
+            q.set(is_live, gst::ClockTime::ZERO, gst::ClockTime::NONE);
+            return true;
+        }
+
+        BaseSrcImplExt::parent_query(self, query)
+    }
+
+    fn unlock(&self) -> Result<(), gst::ErrorMessage> {
+        let mut clock_wait = self.clock_wait.lock().unwrap();
+        if let Some(clock_id) = clock_wait.clock_id.take() {
+            clock_id.unschedule();
+        }
+        clock_wait.flushing = true;
+
+        Ok(())
+    }
+
+    fn unlock_stop(&self) -> Result<(), gst::ErrorMessage> {
+        self.clock_wait.lock().unwrap().flushing = false;
+
+        Ok(())
+    }
+}
+
+impl PushSrcImpl for PcapSrc {
+    fn create(
+        &self,
+        _buffer: Option<&mut gst::BufferRef>,
+    ) -> Result<CreateSuccess, gst::FlowError> {
+        let settings = self.settings.lock().unwrap().clone();
+        let mut state_guard = self.state.lock().unwrap();
+        let state = state_guard.as_mut().ok_or_else(|| {
+            gst::element_imp_error!(self, gst::CoreError::Negotiation, ["Have no state"]);
+            gst::FlowError::Error
+        })?;
+
+        let Some((payload, timestamp)) = self.next_rtp_packet(state, &settings)? else {
+            gst::debug!(CAT, imp: self, "End of capture after {} packets", state.num_packets);
+            return Err(gst::FlowError::Eos);
+        };
+
+        let first_timestamp = *state.first_timestamp.get_or_insert(timestamp);
+        let offset = timestamp.saturating_sub(first_timestamp);
+        let offset =
+            gst::ClockTime::from_nseconds((offset.nseconds() as f64 / settings.speed) as u64);
+
+        let pts = if settings.is_live {
+            let start = match state.start_running_time {
+                Some(start) => start,
+                None => {
+                    let obj = self.obj();
+                    let now = Option::zip(obj.clock(), obj.base_time())
+                        .and_then(|(clock, base_time)| clock.time()?.checked_sub(base_time))
+                        .unwrap_or(gst::ClockTime::ZERO);
+                    *state.start_running_time.insert(now)
+                }
+            };
+            start + offset
+        } else {
+            offset
+        };
+
+        state.num_packets += 1;
+        let discont = state.num_packets == 1;
+        drop(state_guard);
+
+        if settings.is_live {
+            self.wait(pts)?;
+        }
+
+        let mut buffer = gst::Buffer::from_mut_slice(payload);
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(pts);
+            if discont {
+                buffer.set_flags(gst::BufferFlags::DISCONT);
+            }
+        }
+
+        gst::log!(CAT, imp: self, "Producing {:?}", buffer);
+
+        Ok(CreateSuccess::NewBuffer(buffer))
+    }
+}
@@ -0,0 +1,32 @@
+// GStreamer PCAP / PCAPNG replay source
+//
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+mod pcap;
+
+#[cfg(test)]
+mod tests;
+
+glib::wrapper! {
+    pub struct PcapSrc(ObjectSubclass<imp::PcapSrc>)
+        @extends gst_base::PushSrc, gst_base::BaseSrc, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "pcapsrc",
+        gst::Rank::NONE,
+        PcapSrc::static_type(),
+    )
+}
@@ -0,0 +1,448 @@
+// GStreamer PCAP / PCAPNG replay source - capture file parsing
+//
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::io::{self, Read};
+
+use anyhow::{bail, Context};
+
+const PCAP_MAGIC_USEC: u32 = 0xa1b2_c3d4;
+const PCAP_MAGIC_NSEC: u32 = 0xa1b2_3c4d;
+
+const PCAPNG_SECTION_HEADER: u32 = 0x0a0d_0d0a;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1a2b_3c4d;
+const PCAPNG_INTERFACE_DESCRIPTION: u32 = 0x0000_0001;
+const PCAPNG_ENHANCED_PACKET: u32 = 0x0000_0006;
+const PCAPNG_OPTION_IF_TSRESOL: u16 = 9;
+
+const LINKTYPE_NULL: u32 = 0;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const LINKTYPE_IPV4: u32 = 228;
+const LINKTYPE_IPV6: u32 = 229;
+const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const ETHERTYPE_QINQ: u16 = 0x88a8;
+
+const IP_PROTOCOL_UDP: u8 = 17;
+
+/// Upper bound for a single block or packet, to not allocate arbitrary amounts
+/// of memory for corrupted files
+const MAX_BLOCK_SIZE: u32 = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Endianness {
+    Little,
+    Big,
+}
+
+impl Endianness {
+    fn u16(self, data: &[u8]) -> u16 {
+        let data = data[..2].try_into().unwrap();
+        match self {
+            Endianness::Little => u16::from_le_bytes(data),
+            Endianness::Big => u16::from_be_bytes(data),
+        }
+    }
+
+    fn u32(self, data: &[u8]) -> u32 {
+        let data = data[..4].try_into().unwrap();
+        match self {
+            Endianness::Little => u32::from_le_bytes(data),
+            Endianness::Big => u32::from_be_bytes(data),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Interface {
+    linktype: u32,
+    /// Timestamp units per second
+    ts_units: u64,
+}
+
+#[derive(Debug)]
+enum Format {
+    Pcap {
+        endianness: Endianness,
+        linktype: u32,
+        nanoseconds: bool,
+    },
+    PcapNg {
+        endianness: Endianness,
+        interfaces: Vec<Interface>,
+    },
+}
+
+/// A captured packet
+#[derive(Debug)]
+pub struct Packet {
+    /// Capture time since the UNIX epoch
+    pub timestamp: gst::ClockTime,
+    pub linktype: u32,
+    pub data: Vec<u8>,
+}
+
+/// Reads packets from a PCAP or PCAPNG capture
+pub struct Reader<R> {
+    reader: R,
+    format: Format,
+}
+
+fn read_exact_or_eof(r: &mut impl Read, buf: &mut [u8]) -> io::Result<bool> {
+    let mut read = 0;
+    while read < buf.len() {
+        match r.read(&mut buf[read..]) {
+            Ok(0) if read == 0 => return Ok(false),
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(len) => read += len,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(true)
+}
+
+impl<R: Read> Reader<R> {
+    pub fn new(mut reader: R) -> anyhow::Result<Self> {
+        let mut magic = [0u8; 4];
+        reader
+            .read_exact(&mut magic)
+            .context("Reading file magic")?;
+
+        let format = if u32::from_be_bytes(magic) == PCAPNG_SECTION_HEADER {
+            let mut header = [0u8; 8];
+            reader
+                .read_exact(&mut header)
+                .context("Reading section header")?;
+            let endianness = Self::read_section_header(&mut reader, header)?;
+            Format::PcapNg {
+                endianness,
+                interfaces: vec![],
+            }
+        } else {
+            let (endianness, nanoseconds) =
+                match (u32::from_le_bytes(magic), u32::from_be_bytes(magic)) {
+                    (PCAP_MAGIC_USEC, _) => (Endianness::Little, false),
+                    (PCAP_MAGIC_NSEC, _) => (Endianness::Little, true),
+                    (_, PCAP_MAGIC_USEC) => (Endianness::Big, false),
+                    (_, PCAP_MAGIC_NSEC) => (Endianness::Big, true),
+                    _ => bail!("Not a PCAP or PCAPNG file"),
+                };
+
+            let mut header = [0u8; 20];
+            reader
+                .read_exact(&mut header)
+                .context("Reading PCAP header")?;
+
+            Format::Pcap {
+                endianness,
+                // The upper bits can contain FCS information
+                linktype: endianness.u32(&header[16..]) & 0x0fff_ffff,
+                nanoseconds,
+            }
+        };
+
+        Ok(Reader { reader, format })
+    }
+
+    /// Parses a section header block from its length and byte order magic and
+    /// skips the remainder
+    fn read_section_header(reader: &mut R, header: [u8; 8]) -> anyhow::Result<Endianness> {
+        let endianness = match (
+            u32::from_le_bytes(header[4..].try_into().unwrap()),
+            u32::from_be_bytes(header[4..].try_into().unwrap()),
+        ) {
+            (PCAPNG_BYTE_ORDER_MAGIC, _) => Endianness::Little,
+            (_, PCAPNG_BYTE_ORDER_MAGIC) => Endianness::Big,
+            _ => bail!("Invalid PCAPNG byte order magic"),
+        };
+
+        let len = endianness.u32(&header[..4]);
+        if !(28..=MAX_BLOCK_SIZE).contains(&len) || len % 4 != 0 {
+            bail!("Invalid section header length {len}");
+        }
+
+        // Skip version, section length, options and trailing length
+        let skip = len as u64 - 12;
+        if io::copy(&mut reader.by_ref().take(skip), &mut io::sink())? != skip {
+            bail!("Truncated section header");
+        }
+
+        Ok(endianness)
+    }
+
+    /// Returns the next packet or `None` at the end of the capture
+    pub fn next_packet(&mut self) -> anyhow::Result<Option<Packet>> {
+        match self.format {
+            Format::Pcap {
+                endianness,
+                linktype,
+                nanoseconds,
+            } => {
+                let mut header = [0u8; 16];
+                if !read_exact_or_eof(&mut self.reader, &mut header)
+                    .context("Reading packet header")?
+                {
+                    return Ok(None);
+                }
+
+                let secs = endianness.u32(&header[0..]) as u64;
+                let frac = endianness.u32(&header[4..]) as u64;
+                let len = endianness.u32(&header[8..]);
+                if len > MAX_BLOCK_SIZE {
+                    bail!("Invalid packet length {len}");
+                }
+
+                let mut data = vec![0u8; len as usize];
+                self.reader
+                    .read_exact(&mut data)
+                    .context("Reading packet")?;
+
+                let timestamp = gst::ClockTime::from_seconds(secs)
+                    + if nanoseconds {
+                        gst::ClockTime::from_nseconds(frac)
+                    } else {
+                        gst::ClockTime::from_useconds(frac)
+                    };
+
+                Ok(Some(Packet {
+                    timestamp,
+                    linktype,
+                    data,
+                }))
+            }
+            Format::PcapNg { .. } => self.next_pcapng_packet(),
+        }
+    }
+
+    fn next_pcapng_packet(&mut self) -> anyhow::Result<Option<Packet>> {
+        loop {
+            let mut header = [0u8; 8];
+            if !read_exact_or_eof(&mut self.reader, &mut header).context("Reading block header")? {
+                return Ok(None);
+            }
+
+            if u32::from_be_bytes(header[..4].try_into().unwrap()) == PCAPNG_SECTION_HEADER {
+                // A new section starts, with its own byte order and interfaces
+                let mut magic = [0u8; 4];
+                self.reader
+                    .read_exact(&mut magic)
+                    .context("Reading section header")?;
+                let mut section_header = [0u8; 8];
+                section_header[..4].copy_from_slice(&header[4..]);
+                section_header[4..].copy_from_slice(&magic);
+                let endianness = Self::read_section_header(&mut self.reader, section_header)?;
+
+                self.format = Format::PcapNg {
+                    endianness,
+                    interfaces: vec![],
+                };
+                continue;
+            }
+
+            let Format::PcapNg {
+                endianness,
+                ref mut interfaces,
+            } = self.format
+            else {
+                unreachable!();
+            };
+
+            let block_type = endianness.u32(&header[..4]);
+            let len = endianness.u32(&header[4..]);
+            if !(12..=MAX_BLOCK_SIZE).contains(&len) || len % 4 != 0 {
+                bail!("Invalid block length {len}");
+            }
+
+            let mut body = vec![0u8; len as usize - 8];
+            self.reader.read_exact(&mut body).context("Reading block")?;
+            // Without the trailing block length
+            let body = &body[..body.len() - 4];
+
+            match block_type {
+                PCAPNG_INTERFACE_DESCRIPTION if body.len() >= 8 => {
+                    let linktype = endianness.u16(&body[0..]) as u32;
+                    let mut ts_units = 1_000_000;
+
+                    let mut options = &body[8..];
+                    while options.len() >= 4 {
+                        let code = endianness.u16(&options[0..]);
+                        let len = endianness.u16(&options[2..]) as usize;
+                        let value = options.get(4..4 + len).unwrap_or_default();
+
+                        if code == PCAPNG_OPTION_IF_TSRESOL && len == 1 {
+                            let resol = value[0];
+                            ts_units = if resol & 0x80 == 0 {
+                                10u64.checked_pow(resol as u32)
+                            } else {
+                                1u64.checked_shl((resol & 0x7f) as u32)
+                            }
+                            .context("Invalid timestamp resolution")?;
+                        } else if code == 0 {
+                            break;
+                        }
+
+                        options = options.get(4 + (len + 3) / 4 * 4..).unwrap_or_default();
+                    }
+
+                    interfaces.push(Interface { linktype, ts_units });
+                }
+                PCAPNG_ENHANCED_PACKET if body.len() >= 20 => {
+                    let interface = endianness.u32(&body[0..]) as usize;
+                    let Some(interface) = interfaces.get(interface) else {
+                        bail!("Packet for unknown interface {interface}");
+                    };
+
+                    let ts = ((endianness.u32(&body[4..]) as u64) << 32)
+                        | endianness.u32(&body[8..]) as u64;
+                    let len = endianness.u32(&body[12..]) as usize;
+                    let Some(data) = body.get(20..20 + len) else {
+                        bail!("Invalid packet length {len}");
+                    };
+
+                    let timestamp = gst::ClockTime::from_nseconds(
+                        (ts as u128 * 1_000_000_000 / interface.ts_units as u128) as u64,
+                    );
+
+                    return Ok(Some(Packet {
+                        timestamp,
+                        linktype: interface.linktype,
+                        data: data.to_vec(),
+                    }));
+                }
+                // Simple packet blocks have no timestamp and all other blocks
+                // contain no packets
+                _ => (),
+            }
+        }
+    }
+}
+
+/// A UDP datagram extracted from a captured packet
+#[derive(Debug, PartialEq, Eq)]
+pub struct UdpPacket<'a> {
+    pub src_port: u16,
+    pub dst_port: u16,
+    pub payload: &'a [u8],
+}
+
+fn ip_payload(ethertype: u16, data: &[u8]) -> Option<UdpPacket<'_>> {
+    match ethertype {
+        ETHERTYPE_IPV4 => ipv4_payload(data),
+        ETHERTYPE_IPV6 => ipv6_payload(data),
+        _ => None,
+    }
+}
+
+fn ipv4_payload(data: &[u8]) -> Option<UdpPacket<'_>> {
+    if data.len() < 20 || data[0] >> 4 != 4 {
+        return None;
+    }
+
+    let header_len = (data[0] & 0x0f) as usize * 4;
+    let total_len = u16::from_be_bytes([data[2], data[3]]) as usize;
+    let fragment = u16::from_be_bytes([data[6], data[7]]);
+    // Fragmented datagrams are not reassembled
+    if fragment & 0x3fff != 0 || data[9] != IP_PROTOCOL_UDP {
+        return None;
+    }
+
+    udp_payload(data.get(header_len..total_len.min(data.len()))?)
+}
+
+fn ipv6_payload(data: &[u8]) -> Option<UdpPacket<'_>> {
+    if data.len() < 40 || data[0] >> 4 != 6 {
+        return None;
+    }
+
+    let payload_len = u16::from_be_bytes([data[4], data[5]]) as usize;
+    let mut next_header = data[6];
+    let mut payload = data.get(40..(40 + payload_len).min(data.len()))?;
+
+    // Skip hop-by-hop, routing and destination options extension headers
+    while matches!(next_header, 0 | 43 | 60) {
+        if payload.len() < 8 {
+            return None;
+        }
+        next_header = payload[0];
+        payload = payload.get((payload[1] as usize + 1) * 8..)?;
+    }
+
+    if next_header != IP_PROTOCOL_UDP {
+        return None;
+    }
+
+    udp_payload(payload)
+}
+
+fn udp_payload(data: &[u8]) -> Option<UdpPacket<'_>> {
+    if data.len() < 8 {
+        return None;
+    }
+
+    let len = u16::from_be_bytes([data[4], data[5]]) as usize;
+    Some(UdpPacket {
+        src_port: u16::from_be_bytes([data[0], data[1]]),
+        dst_port: u16::from_be_bytes([data[2], data[3]]),
+        payload: data.get(8..len.max(8).min(data.len()))?,
+    })
+}
+
+/// Extracts the UDP datagram from a captured IPv4 or IPv6 packet, if any
+pub fn extract_udp(linktype: u32, data: &[u8]) -> Option<UdpPacket<'_>> {
+    match linktype {
+        LINKTYPE_ETHERNET => {
+            let mut ethertype = u16::from_be_bytes(data.get(12..14)?.try_into().unwrap());
+            let mut offset = 14;
+            while matches!(ethertype, ETHERTYPE_VLAN | ETHERTYPE_QINQ) {
+                ethertype =
+                    u16::from_be_bytes(data.get(offset + 2..offset + 4)?.try_into().unwrap());
+                offset += 4;
+            }
+            ip_payload(ethertype, data.get(offset..)?)
+        }
+        LINKTYPE_LINUX_SLL => {
+            let ethertype = u16::from_be_bytes(data.get(14..16)?.try_into().unwrap());
+            ip_payload(ethertype, data.get(16..)?)
+        }
+        LINKTYPE_LINUX_SLL2 => {
+            let ethertype = u16::from_be_bytes(data.get(0..2)?.try_into().unwrap());
+            ip_payload(ethertype, data.get(20..)?)
+        }
+        LINKTYPE_NULL => {
+            // The address family is in host byte order of the capturing machine
+            let family = data.get(0..4)?;
+            let family = if family[0] != 0 {
+                u32::from_le_bytes(family.try_into().unwrap())
+            } else {
+                u32::from_be_bytes(family.try_into().unwrap())
+            };
+            match family {
+                2 => ipv4_payload(data.get(4..)?),
+                // BSD variants use different values for AF_INET6
+                24 | 28 | 30 => ipv6_payload(data.get(4..)?),
+                _ => None,
+            }
+        }
+        LINKTYPE_RAW => match data.first()? >> 4 {
+            4 => ipv4_payload(data),
+            6 => ipv6_payload(data),
+            _ => None,
+        },
+        LINKTYPE_IPV4 => ipv4_payload(data),
+        LINKTYPE_IPV6 => ipv6_payload(data),
+        _ => None,
+    }
+}
@@ -0,0 +1,407 @@
+// GStreamer PCAP / PCAPNG replay source - tests
+//
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use super::pcap::{extract_udp, Reader};
+
+use gst::glib;
+use gst::prelude::*;
+
+fn rtp_packet(seqnum: u16, ssrc: u32) -> Vec<u8> {
+    let mut packet = vec![0x80, 96];
+    packet.extend_from_slice(&seqnum.to_be_bytes());
+    packet.extend_from_slice(&(seqnum as u32 * 3000).to_be_bytes());
+    packet.extend_from_slice(&ssrc.to_be_bytes());
+    packet.extend_from_slice(&[0xab; 16]);
+    packet
+}
+
+/// Wraps a UDP payload into an Ethernet / IPv4 / UDP frame
+fn ethernet_frame(dst_port: u16, payload: &[u8]) -> Vec<u8> {
+    let mut frame = vec![0u8; 12];
+    frame.extend_from_slice(&0x0800u16.to_be_bytes());
+
+    let total_len = 20 + 8 + payload.len() as u16;
+    frame.extend_from_slice(&[0x45, 0]);
+    frame.extend_from_slice(&total_len.to_be_bytes());
+    frame.extend_from_slice(&[0, 0, 0x40, 0, 64, 17, 0, 0]);
+    frame.extend_from_slice(&[192, 168, 0, 1, 192, 168, 0, 2]);
+
+    frame.extend_from_slice(&5000u16.to_be_bytes());
+    frame.extend_from_slice(&dst_port.to_be_bytes());
+    frame.extend_from_slice(&(8 + payload.len() as u16).to_be_bytes());
+    frame.extend_from_slice(&[0, 0]);
+    frame.extend_from_slice(payload);
+
+    frame
+}
+
+fn pcap_file(packets: &[(gst::ClockTime, Vec<u8>)]) -> Vec<u8> {
+    let mut data = vec![];
+    data.extend_from_slice(&0xa1b2_c3d4u32.to_le_bytes());
+    data.extend_from_slice(&2u16.to_le_bytes());
+    data.extend_from_slice(&4u16.to_le_bytes());
+    data.extend_from_slice(&[0; 8]);
+    data.extend_from_slice(&65535u32.to_le_bytes());
+    data.extend_from_slice(&1u32.to_le_bytes());
+
+    for (ts, packet) in packets {
+        data.extend_from_slice(&(ts.seconds() as u32).to_le_bytes());
+        data.extend_from_slice(&((ts.useconds() % 1_000_000) as u32).to_le_bytes());
+        data.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        data.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        data.extend_from_slice(packet);
+    }
+
+    data
+}
+
+fn pcapng_block(data: &mut Vec<u8>, block_type: u32, body: &[u8]) {
+    let padded_len = (body.len() + 3) / 4 * 4;
+    let len = 12 + padded_len as u32;
+    data.extend_from_slice(&block_type.to_be_bytes());
+    data.extend_from_slice(&len.to_be_bytes());
+    data.extend_from_slice(body);
+    data.resize(data.len() + padded_len - body.len(), 0);
+    data.extend_from_slice(&len.to_be_bytes());
+}
+
+/// Big endian PCAPNG file with nanosecond timestamp resolution
+fn pcapng_file(packets: &[(gst::ClockTime, Vec<u8>)]) -> Vec<u8> {
+    let mut data = vec![];
+
+    let mut shb = vec![];
+    shb.extend_from_slice(&0x1a2b_3c4du32.to_be_bytes());
+    shb.extend_from_slice(&[0, 1, 0, 0]);
+    shb.extend_from_slice(&u64::MAX.to_be_bytes());
+    pcapng_block(&mut data, 0x0a0d_0d0a, &shb);
+
+    let mut idb = vec![];
+    idb.extend_from_slice(&1u16.to_be_bytes());
+    idb.extend_from_slice(&[0, 0]);
+    idb.extend_from_slice(&0u32.to_be_bytes());
+    // if_tsresol = 9
+    idb.extend_from_slice(&[0, 9, 0, 1, 9, 0, 0, 0]);
+    idb.extend_from_slice(&[0, 0, 0, 0]);
+    pcapng_block(&mut data, 1, &idb);
+
+    // A name resolution block that has to be skipped
+    pcapng_block(&mut data, 4, &[0, 0, 0, 0]);
+
+    for (ts, packet) in packets {
+        let mut epb = vec![];
+        epb.extend_from_slice(&0u32.to_be_bytes());
+        epb.extend_from_slice(&((ts.nseconds() >> 32) as u32).to_be_bytes());
+        epb.extend_from_slice(&(ts.nseconds() as u32).to_be_bytes());
+        epb.extend_from_slice(&(packet.len() as u32).to_be_bytes());
+        epb.extend_from_slice(&(packet.len() as u32).to_be_bytes());
+        epb.extend_from_slice(packet);
+        pcapng_block(&mut data, 6, &epb);
+    }
+
+    data
+}
+
+fn test_packets() -> Vec<(gst::ClockTime, Vec<u8>)> {
+    let start = gst::ClockTime::from_seconds(1_700_000_000);
+
+    (0..10u16)
+        .map(|i| {
+            let port = if i % 2 == 0 { 5004 } else { 6000 };
+            let ssrc = if i % 3 == 0 { 0x1234 } else { 0x5678 };
+            (
+                start + gst::ClockTime::from_mseconds(20 * i as u64),
+                ethernet_frame(port, &rtp_packet(i, ssrc)),
+            )
+        })
+        .collect()
+}
+
+fn check_reader(data: &[u8]) {
+    let expected = test_packets();
+    let mut reader = Reader::new(data).unwrap();
+
+    for (ts, frame) in &expected {
+        let packet = reader.next_packet().unwrap().unwrap();
+        assert_eq!(packet.timestamp, *ts);
+        assert_eq!(packet.linktype, 1);
+        assert_eq!(&packet.data, frame);
+    }
+    assert!(reader.next_packet().unwrap().is_none());
+}
+
+#[test]
+fn test_read_pcap() {
+    check_reader(&pcap_file(&test_packets()));
+}
+
+#[test]
+fn test_read_pcapng() {
+    check_reader(&pcapng_file(&test_packets()));
+}
+
+#[test]
+fn test_invalid_file() {
+    assert!(Reader::new(&b"not a capture file"[..]).is_err());
+    assert!(Reader::new(&b""[..]).is_err());
+
+    // Truncated packet
+    let data = pcap_file(&test_packets());
+    let mut reader = Reader::new(&data[..data.len() - 10]).unwrap();
+    for _ in 0..9 {
+        reader.next_packet().unwrap().unwrap();
+    }
+    assert!(reader.next_packet().is_err());
+}
+
+#[test]
+fn test_extract_udp() {
+    let rtp = rtp_packet(1, 0x1234);
+    let frame = ethernet_frame(5004, &rtp);
+
+    let udp = extract_udp(1, &frame).unwrap();
+    assert_eq!(udp.src_port, 5000);
+    assert_eq!(udp.dst_port, 5004);
+    assert_eq!(udp.payload, &rtp[..]);
+
+    // Raw IPv4 without the Ethernet header
+    let udp = extract_udp(101, &frame[14..]).unwrap();
+    assert_eq!(udp.payload, &rtp[..]);
+
+    // Ethernet padding after the IP packet is ignored
+    let mut padded = frame.clone();
+    padded.extend_from_slice(&[0; 4]);
+    assert_eq!(extract_udp(1, &padded).unwrap().payload, &rtp[..]);
+
+    // VLAN tagged
+    let mut tagged = frame[..12].to_vec();
+    tagged.extend_from_slice(&[0x81, 0x00, 0x00, 0x64]);
+    tagged.extend_from_slice(&frame[12..]);
+    assert_eq!(extract_udp(1, &tagged).unwrap().payload, &rtp[..]);
+
+    // Fragmented
+    let mut fragmented = frame.clone();
+    fragmented[14 + 6] = 0x20;
+    assert!(extract_udp(1, &fragmented).is_none());
+
+    // TCP
+    let mut tcp = frame.clone();
+    tcp[14 + 9] = 6;
+    assert!(extract_udp(1, &tcp).is_none());
+
+    // Truncated
+    assert!(extract_udp(1, &frame[..20]).is_none());
+}
+
+fn write_capture(name: &str, data: &[u8]) -> std::path::PathBuf {
+    let mut path = std::env::temp_dir();
+    path.push(format!("pcapsrc-test-{}-{name}", std::process::id()));
+    std::fs::write(&path, data).unwrap();
+    path
+}
+
+/// Returns all buffers until EOS and the error posted by the source, if any.
+fn pull_all_and_error(
+    location: &std::path::Path,
+    props: &[(&str, &dyn ToValue)],
+) -> (Vec<gst::Buffer>, Option<glib::Error>) {
+    crate::test_init();
+
+    let bus = gst::Bus::new();
+    let mut h = gst_check::Harness::new("pcapsrc");
+    {
+        let src = h.element().unwrap();
+        src.set_bus(Some(&bus));
+        src.set_property("location", location.to_str().unwrap());
+        for (name, value) in props {
+            src.set_property_from_value(name, &value.to_value());
+        }
+    }
+    h.play();
+
+    let mut buffers = vec![];
+    loop {
+        match h.pull_until_eos() {
+            Ok(Some(buffer)) => buffers.push(buffer),
+            Ok(None) => break,
+            Err(err) => panic!("Failed to pull buffer: {err}"),
+        }
+    }
+
+    let error = bus.iter().find_map(|msg| match msg.view() {
+        gst::MessageView::Error(err) => Some(err.error()),
+        _ => None,
+    });
+
+    (buffers, error)
+}
+
+fn pull_all(location: &std::path::Path, props: &[(&str, &dyn ToValue)]) -> Vec<gst::Buffer> {
+    let (buffers, error) = pull_all_and_error(location, props);
+    assert!(error.is_none(), "{error:?}");
+
+    buffers
+}
+
+fn rtp_info(buffer: &gst::Buffer) -> (u16, u32) {
+    let map = buffer.map_readable().unwrap();
+    (
+        u16::from_be_bytes([map[2], map[3]]),
+        u32::from_be_bytes([map[8], map[9], map[10], map[11]]),
+    )
+}
+
+#[test]
+fn test_pcapsrc_filter() {
+    let path = write_capture("filter.pcap", &pcap_file(&test_packets()));
+
+    let buffers = pull_all(&path, &[]);
+    assert_eq!(buffers.len(), 10);
+    assert!(buffers[0].flags().contains(gst::BufferFlags::DISCONT));
+    for (i, buffer) in buffers.iter().enumerate() {
+        assert_eq!(
+            buffer.pts(),
+            Some(gst::ClockTime::from_mseconds(20 * i as u64))
+        );
+        assert_eq!(rtp_info(buffer).0, i as u16);
+    }
+
+    let buffers = pull_all(&path, &[("port", &5004u32)]);
+    let seqnums = buffers.iter().map(|b| rtp_info(b).0).collect::<Vec<_>>();
+    assert_eq!(seqnums, [0, 2, 4, 6, 8]);
+
+    let buffers = pull_all(&path, &[("port", &5004u32), ("ssrc", &0x1234i64)]);
+    let seqnums = buffers.iter().map(|b| rtp_info(b).0).collect::<Vec<_>>();
+    assert_eq!(seqnums, [0, 6]);
+    assert_eq!(buffers[1].pts(), Some(gst::ClockTime::from_mseconds(120)));
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_pcapsrc_speed() {
+    let path = write_capture("speed.pcapng", &pcapng_file(&test_packets()));
+
+    let buffers = pull_all(&path, &[("speed", &4.0f64)]);
+    assert_eq!(buffers.len(), 10);
+    for (i, buffer) in buffers.iter().enumerate() {
+        assert_eq!(
+            buffer.pts(),
+            Some(gst::ClockTime::from_mseconds(5 * i as u64))
+        );
+    }
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_pcapsrc_skip_rtcp() {
+    let start = gst::ClockTime::from_seconds(1_700_000_000);
+    let mut rtcp = vec![0x80, 200, 0, 6];
+    rtcp.extend_from_slice(&[0; 24]);
+
+    let packets = vec![
+        (start, ethernet_frame(5004, &rtp_packet(0, 1))),
+        (start, ethernet_frame(5004, &rtcp)),
+        (start, ethernet_frame(5004, b"not rtp")),
+        (start, ethernet_frame(5004, &rtp_packet(1, 1))),
+    ];
+    let path = write_capture("rtcp.pcap", &pcap_file(&packets));
+
+    let buffers = pull_all(&path, &[]);
+    let seqnums = buffers.iter().map(|b| rtp_info(b).0).collect::<Vec<_>>();
+    assert_eq!(seqnums, [0, 1]);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_pcapsrc_non_udp() {
+    let start = gst::ClockTime::from_seconds(1_700_000_000);
+    let rtp = [rtp_packet(0, 0x1234), rtp_packet(1, 0x1234)];
+
+    let mut tcp = ethernet_frame(5004, &rtp_packet(100, 0x1234));
+    tcp[14 + 9] = 6;
+    let mut arp = vec![0xff; 12];
+    arp.extend_from_slice(&0x0806u16.to_be_bytes());
+    arp.extend_from_slice(&[0; 28]);
+
+    let packets = vec![
+        (start, tcp),
+        (
+            start + gst::ClockTime::from_mseconds(10),
+            ethernet_frame(5004, &rtp[0]),
+        ),
+        (start + gst::ClockTime::from_mseconds(20), arp),
+        (
+            start + gst::ClockTime::from_mseconds(50),
+            ethernet_frame(5004, &rtp[1]),
+        ),
+    ];
+
+    for (name, data) in [
+        ("non-udp.pcap", pcap_file(&packets)),
+        ("non-udp.pcapng", pcapng_file(&packets)),
+    ] {
+        let path = write_capture(name, &data);
+
+        // Only the UDP payloads are output, timestamped relative to the first one
+        let buffers = pull_all(&path, &[]);
+        assert_eq!(buffers.len(), 2, "{name}");
+        for ((buffer, payload), pts) in buffers.iter().zip(&rtp).zip([0, 40]) {
+            assert_eq!(
+                buffer.map_readable().unwrap().as_slice(),
+                &payload[..],
+                "{name}"
+            );
+            assert_eq!(
+                buffer.pts(),
+                Some(gst::ClockTime::from_mseconds(pts)),
+                "{name}"
+            );
+        }
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn test_pcapsrc_truncated() {
+    let packets = test_packets();
+
+    for (name, data) in [
+        ("truncated.pcap", pcap_file(&packets)),
+        ("truncated.pcapng", pcapng_file(&packets)),
+    ] {
+        // Cut into the last record
+        let path = write_capture(name, &data[..data.len() - 10]);
+
+        // The complete records are output before the error
+        let (buffers, error) = pull_all_and_error(&path, &[]);
+        assert_eq!(buffers.len(), 9, "{name}");
+        for (i, buffer) in buffers.iter().enumerate() {
+            assert_eq!(
+                buffer.map_readable().unwrap().as_slice(),
+                &rtp_packet(i as u16, if i % 3 == 0 { 0x1234 } else { 0x5678 })[..],
+                "{name}"
+            );
+            assert_eq!(
+                buffer.pts(),
+                Some(gst::ClockTime::from_mseconds(20 * i as u64)),
+                "{name}"
+            );
+        }
+        assert!(
+            error.map_or(false, |err| err.matches(gst::StreamError::Demux)),
+            "{name}"
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,179 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+
+use std::str::FromStr;
+
+pub(crate) const DEFAULT_ENDPOINT: &str = "tcp://127.0.0.1:5555";
+pub(crate) const DEFAULT_MODE: ZmqMode = ZmqMode::PubSub;
+pub(crate) const DEFAULT_TOPIC: &str = "";
+
+/// Timeout used for blocking socket operations so that flushing can be
+/// checked regularly.
+pub(crate) const POLL_TIMEOUT_MS: i32 = 100;
+
+const HEADER_NAME: &str = "zmq-buffer";
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstZmqMode")]
+pub enum ZmqMode {
+    #[enum_value(
+        name = "Publish/Subscribe: Messages are broadcast to all subscribers",
+        nick = "pubsub"
+    )]
+    PubSub,
+
+    #[enum_value(
+        name = "Push/Pull: Messages are distributed to one of the pullers",
+        nick = "pushpull"
+    )]
+    PushPull,
+}
+
+impl ZmqMode {
+    pub(crate) fn sink_socket_type(self) -> zmq::SocketType {
+        match self {
+            ZmqMode::PubSub => zmq::PUB,
+            ZmqMode::PushPull => zmq::PUSH,
+        }
+    }
+
+    pub(crate) fn src_socket_type(self) -> zmq::SocketType {
+        match self {
+            ZmqMode::PubSub => zmq::SUB,
+            ZmqMode::PushPull => zmq::PULL,
+        }
+    }
+}
+
+/// Binds or connects `socket` to `endpoint`.
+pub(crate) fn open_socket(
+    socket: &zmq::Socket,
+    endpoint: &str,
+    bind: bool,
+) -> Result<(), gst::ErrorMessage> {
+    let res = if bind {
+        socket.bind(endpoint)
+    } else {
+        socket.connect(endpoint)
+    };
+
+    res.map_err(|err| {
+        gst::error_msg!(
+            gst::ResourceError::OpenReadWrite,
+            [
+                "Failed to {} to {}: {}",
+                if bind { "bind" } else { "connect" },
+                endpoint,
+                err
+            ]
+        )
+    })
+}
+
+/// Serializes the caps and timing metadata of `buffer` into the header frame
+/// that precedes the payload in each message.
+///
+/// The header is a serialized `GstStructure` so it stays human readable and
+/// can be extended without breaking older receivers.
+pub(crate) fn serialize_header(buffer: &gst::BufferRef, caps: Option<&gst::Caps>) -> String {
+    let mut s = gst::Structure::builder(HEADER_NAME)
+        .field("flags", buffer.flags().bits())
+        .build();
+
+    if let Some(pts) = buffer.pts() {
+        s.set("pts", pts.nseconds());
+    }
+    if let Some(dts) = buffer.dts() {
+        s.set("dts", dts.nseconds());
+    }
+    if let Some(duration) = buffer.duration() {
+        s.set("duration", duration.nseconds());
+    }
+    if buffer.offset() != gst::BUFFER_OFFSET_NONE {
+        s.set("offset", buffer.offset());
+    }
+    if buffer.offset_end() != gst::BUFFER_OFFSET_NONE {
+        s.set("offset-end", buffer.offset_end());
+    }
+    if let Some(caps) = caps {
+        s.set("caps", caps.to_string());
+    }
+
+    s.to_string()
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Header {
+    pub caps: Option<gst::Caps>,
+    pub flags: gst::BufferFlags,
+    pub pts: Option<gst::ClockTime>,
+    pub dts: Option<gst::ClockTime>,
+    pub duration: Option<gst::ClockTime>,
+    pub offset: Option<u64>,
+    pub offset_end: Option<u64>,
+}
+
+impl Header {
+    pub(crate) fn parse(data: &[u8]) -> Result<Self, String> {
+        let data = std::str::from_utf8(data).map_err(|err| format!("Invalid header: {err}"))?;
+        let s = gst::Structure::from_str(data)
+            .map_err(|err| format!("Invalid header structure: {err}"))?;
+
+        if s.name() != HEADER_NAME {
+            return Err(format!("Unexpected header name {}", s.name()));
+        }
+
+        let caps = s
+            .get_optional::<&str>("caps")
+            .map_err(|err| err.to_string())?
+            .map(|caps| {
+                gst::Caps::from_str(caps).map_err(|err| format!("Invalid caps {caps}: {err}"))
+            })
+            .transpose()?;
+
+        let time = |field| {
+            s.get_optional::<u64>(field)
+                .map(|v| v.map(gst::ClockTime::from_nseconds))
+                .map_err(|err| err.to_string())
+        };
+
+        Ok(Header {
+            caps,
+            flags: gst::BufferFlags::from_bits_truncate(
+                s.get_optional::<u32>("flags")
+                    .map_err(|err| err.to_string())?
+                    .unwrap_or(0),
+            ),
+            pts: time("pts")?,
+            dts: time("dts")?,
+            duration: time("duration")?,
+            offset: s
+                .get_optional::<u64>("offset")
+                .map_err(|err| err.to_string())?,
+            offset_end: s
+                .get_optional::<u64>("offset-end")
+                .map_err(|err| err.to_string())?,
+        })
+    }
+
+    pub(crate) fn apply(&self, buffer: &mut gst::BufferRef) {
+        buffer.set_flags(self.flags);
+        buffer.set_pts(self.pts);
+        buffer.set_dts(self.dts);
+        buffer.set_duration(self.duration);
+        if let Some(offset) = self.offset {
+            buffer.set_offset(offset);
+        }
+        if let Some(offset_end) = self.offset_end {
+            buffer.set_offset_end(offset_end);
+        }
+    }
+}
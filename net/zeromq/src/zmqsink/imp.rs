@@ -0,0 +1,309 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::subclass::prelude::*;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+
+use crate::common::*;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "zmqsink",
+        gst::DebugColorFlags::empty(),
+        Some("ZeroMQ Sink"),
+    )
+});
+
+const DEFAULT_BIND: bool = true;
+
+#[derive(Debug, Clone)]
+struct Settings {
+    endpoint: String,
+    mode: ZmqMode,
+    bind: bool,
+    topic: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            endpoint: DEFAULT_ENDPOINT.to_string(),
+            mode: DEFAULT_MODE,
+            bind: DEFAULT_BIND,
+            topic: DEFAULT_TOPIC.to_string(),
+        }
+    }
+}
+
+struct State {
+    // Keep the context alive for as long as the socket exists
+    _context: zmq::Context,
+    socket: zmq::Socket,
+    topic: Option<Vec<u8>>,
+    caps: Option<gst::Caps>,
+}
+
+#[derive(Default)]
+pub struct ZmqSink {
+    settings: Mutex<Settings>,
+    state: Mutex<Option<State>>,
+    flushing: AtomicBool,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for ZmqSink {
+    const NAME: &'static str = "GstZmqSink";
+    type Type = super::ZmqSink;
+    type ParentType = gst_base::BaseSink;
+}
+
+impl ObjectImpl for ZmqSink {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecString::builder("endpoint")
+                    .nick("Endpoint")
+                    .blurb("ZeroMQ endpoint to bind or connect to, e.g. tcp://127.0.0.1:5555")
+                    .default_value(Some(DEFAULT_ENDPOINT))
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("mode", DEFAULT_MODE)
+                    .nick("Mode")
+                    .blurb("Messaging pattern to use")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("bind")
+                    .nick("Bind")
+                    .blurb("Bind to the endpoint instead of connecting to it")
+                    .default_value(DEFAULT_BIND)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("topic")
+                    .nick("Topic")
+                    .blurb("Topic to publish messages on (pubsub mode only)")
+                    .default_value(Some(DEFAULT_TOPIC))
+                    .mutable_ready()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "endpoint" => {
+                settings.endpoint = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .unwrap_or_else(|| DEFAULT_ENDPOINT.to_string());
+            }
+            "mode" => {
+                settings.mode = value.get::<ZmqMode>().expect("type checked upstream");
+            }
+            "bind" => {
+                settings.bind = value.get::<bool>().expect("type checked upstream");
+            }
+            "topic" => {
+                settings.topic = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .unwrap_or_default();
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "endpoint" => settings.endpoint.to_value(),
+            "mode" => settings.mode.to_value(),
+            "bind" => settings.bind.to_value(),
+            "topic" => settings.topic.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for ZmqSink {}
+
+impl ElementImpl for ZmqSink {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "ZeroMQ Sink",
+                "Sink/Network",
+                "Send buffers with their caps and timing metadata over a ZeroMQ socket",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &gst::Caps::new_any(),
+            )
+            .unwrap();
+
+            vec![sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseSinkImpl for ZmqSink {
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        let settings = self.settings.lock().unwrap().clone();
+
+        let context = zmq::Context::new();
+        let socket = context
+            .socket(settings.mode.sink_socket_type())
+            .map_err(|err| {
+                gst::error_msg!(
+                    gst::ResourceError::Failed,
+                    ["Failed to create socket: {}", err]
+                )
+            })?;
+
+        // Don't block on shutdown because of messages that were not delivered yet
+        socket.set_linger(0).map_err(|err| {
+            gst::error_msg!(
+                gst::ResourceError::Settings,
+                ["Failed to configure socket: {}", err]
+            )
+        })?;
+        socket.set_sndtimeo(POLL_TIMEOUT_MS).map_err(|err| {
+            gst::error_msg!(
+                gst::ResourceError::Settings,
+                ["Failed to configure socket: {}", err]
+            )
+        })?;
+
+        open_socket(&socket, &settings.endpoint, settings.bind)?;
+
+        gst::debug!(
+            CAT,
+            imp: self,
+            "Opened {:?} socket on {}",
+            settings.mode,
+            settings.endpoint
+        );
+
+        let topic = match settings.mode {
+            ZmqMode::PubSub => Some(settings.topic.into_bytes()),
+            ZmqMode::PushPull => None,
+        };
+
+        *self.state.lock().unwrap() = Some(State {
+            _context: context,
+            socket,
+            topic,
+            caps: None,
+        });
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.lock().unwrap() = None;
+
+        gst::debug!(CAT, imp: self, "Stopped");
+
+        Ok(())
+    }
+
+    fn set_caps(&self, caps: &gst::Caps) -> Result<(), gst::LoggableError> {
+        let mut state = self.state.lock().unwrap();
+        let state = state
+            .as_mut()
+            .ok_or_else(|| gst::loggable_error!(CAT, "Not started yet"))?;
+
+        gst::debug!(CAT, imp: self, "Setting caps {caps:?}");
+        state.caps = Some(caps.clone());
+
+        Ok(())
+    }
+
+    fn render(&self, buffer: &gst::Buffer) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let state = self.state.lock().unwrap();
+        let state = state.as_ref().ok_or_else(|| {
+            gst::element_imp_error!(self, gst::CoreError::Failed, ["Not started yet"]);
+            gst::FlowError::Error
+        })?;
+
+        gst::trace!(CAT, imp: self, "Rendering {:?}", buffer);
+
+        let map = buffer.map_readable().map_err(|_| {
+            gst::element_imp_error!(self, gst::CoreError::Failed, ["Failed to map buffer"]);
+            gst::FlowError::Error
+        })?;
+
+        let header = serialize_header(buffer, state.caps.as_ref());
+
+        loop {
+            let mut parts = Vec::with_capacity(3);
+            if let Some(ref topic) = state.topic {
+                parts.push(zmq::Message::from(topic.as_slice()));
+            }
+            parts.push(zmq::Message::from(header.as_bytes()));
+            parts.push(zmq::Message::from(map.as_slice()));
+
+            match state.socket.send_multipart(parts, 0) {
+                Ok(()) => break,
+                Err(zmq::Error::EAGAIN) => {
+                    if self.flushing.load(Ordering::SeqCst) {
+                        gst::debug!(CAT, imp: self, "Flushing");
+                        return Err(gst::FlowError::Flushing);
+                    }
+
+                    gst::trace!(CAT, imp: self, "No peer ready yet, retrying");
+                }
+                Err(err) => {
+                    gst::element_imp_error!(
+                        self,
+                        gst::ResourceError::Write,
+                        ["Failed to send message: {}", err]
+                    );
+                    return Err(gst::FlowError::Error);
+                }
+            }
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    fn unlock(&self) -> Result<(), gst::ErrorMessage> {
+        self.flushing.store(true, Ordering::SeqCst);
+
+        Ok(())
+    }
+
+    fn unlock_stop(&self) -> Result<(), gst::ErrorMessage> {
+        self.flushing.store(false, Ordering::SeqCst);
+
+        Ok(())
+    }
+}
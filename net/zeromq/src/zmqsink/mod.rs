@@ -0,0 +1,43 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * element-zmqsink:
+ * @short-description: Send buffers over a ZeroMQ socket
+ *
+ * Every buffer is sent as a multipart message consisting of an optional
+ * topic frame (in `pubsub` mode), a header frame containing the caps and
+ * timing metadata of the buffer as a serialized `GstStructure`, and the
+ * buffer payload. `zmqsrc` can be used to receive such messages in another
+ * pipeline or process.
+ *
+ * ## Example pipeline
+ * ```bash
+ * gst-launch-1.0 -v audiotestsrc is-live=true ! audio/x-raw,rate=48000 ! \
+ *     zmqsink endpoint=tcp://127.0.0.1:5555 mode=pubsub topic=audio
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct ZmqSink(ObjectSubclass<imp::ZmqSink>) @extends gst_base::BaseSink, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "zmqsink",
+        gst::Rank::NONE,
+        ZmqSink::static_type(),
+    )
+}
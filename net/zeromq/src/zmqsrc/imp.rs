@@ -0,0 +1,334 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::prelude::*;
+use gst_base::subclass::base_src::CreateSuccess;
+use gst_base::subclass::prelude::*;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+
+use crate::common::*;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "zmqsrc",
+        gst::DebugColorFlags::empty(),
+        Some("ZeroMQ Source"),
+    )
+});
+
+const DEFAULT_BIND: bool = false;
+
+#[derive(Debug, Clone)]
+struct Settings {
+    endpoint: String,
+    mode: ZmqMode,
+    bind: bool,
+    topic: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            endpoint: DEFAULT_ENDPOINT.to_string(),
+            mode: DEFAULT_MODE,
+            bind: DEFAULT_BIND,
+            topic: DEFAULT_TOPIC.to_string(),
+        }
+    }
+}
+
+struct State {
+    // Keep the context alive for as long as the socket exists
+    _context: zmq::Context,
+    socket: zmq::Socket,
+    mode: ZmqMode,
+    caps: Option<gst::Caps>,
+}
+
+#[derive(Default)]
+pub struct ZmqSrc {
+    settings: Mutex<Settings>,
+    state: Mutex<Option<State>>,
+    flushing: AtomicBool,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for ZmqSrc {
+    const NAME: &'static str = "GstZmqSrc";
+    type Type = super::ZmqSrc;
+    type ParentType = gst_base::PushSrc;
+}
+
+impl ObjectImpl for ZmqSrc {
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.set_live(true);
+        obj.set_format(gst::Format::Time);
+    }
+
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecString::builder("endpoint")
+                    .nick("Endpoint")
+                    .blurb("ZeroMQ endpoint to bind or connect to, e.g. tcp://127.0.0.1:5555")
+                    .default_value(Some(DEFAULT_ENDPOINT))
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("mode", DEFAULT_MODE)
+                    .nick("Mode")
+                    .blurb("Messaging pattern to use")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("bind")
+                    .nick("Bind")
+                    .blurb("Bind to the endpoint instead of connecting to it")
+                    .default_value(DEFAULT_BIND)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("topic")
+                    .nick("Topic")
+                    .blurb("Topic prefix to subscribe to, empty for all topics (pubsub mode only)")
+                    .default_value(Some(DEFAULT_TOPIC))
+                    .mutable_ready()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "endpoint" => {
+                settings.endpoint = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .unwrap_or_else(|| DEFAULT_ENDPOINT.to_string());
+            }
+            "mode" => {
+                settings.mode = value.get::<ZmqMode>().expect("type checked upstream");
+            }
+            "bind" => {
+                settings.bind = value.get::<bool>().expect("type checked upstream");
+            }
+            "topic" => {
+                settings.topic = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .unwrap_or_default();
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "endpoint" => settings.endpoint.to_value(),
+            "mode" => settings.mode.to_value(),
+            "bind" => settings.bind.to_value(),
+            "topic" => settings.topic.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for ZmqSrc {}
+
+impl ElementImpl for ZmqSrc {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "ZeroMQ Source",
+                "Source/Network",
+                "Receive buffers with their caps and timing metadata from a ZeroMQ socket",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &gst::Caps::new_any(),
+            )
+            .unwrap();
+
+            vec![src_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseSrcImpl for ZmqSrc {
+    fn is_seekable(&self) -> bool {
+        false
+    }
+
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        let settings = self.settings.lock().unwrap().clone();
+
+        let context = zmq::Context::new();
+        let socket = context
+            .socket(settings.mode.src_socket_type())
+            .map_err(|err| {
+                gst::error_msg!(
+                    gst::ResourceError::Failed,
+                    ["Failed to create socket: {}", err]
+                )
+            })?;
+
+        let configure = |socket: &zmq::Socket| -> Result<(), zmq::Error> {
+            socket.set_linger(0)?;
+            socket.set_rcvtimeo(POLL_TIMEOUT_MS)?;
+            if settings.mode == ZmqMode::PubSub {
+                socket.set_subscribe(settings.topic.as_bytes())?;
+            }
+            Ok(())
+        };
+        configure(&socket).map_err(|err| {
+            gst::error_msg!(
+                gst::ResourceError::Settings,
+                ["Failed to configure socket: {}", err]
+            )
+        })?;
+
+        open_socket(&socket, &settings.endpoint, settings.bind)?;
+
+        gst::debug!(
+            CAT,
+            imp: self,
+            "Opened {:?} socket on {}",
+            settings.mode,
+            settings.endpoint
+        );
+
+        *self.state.lock().unwrap() = Some(State {
+            _context: context,
+            socket,
+            mode: settings.mode,
+            caps: None,
+        });
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.lock().unwrap() = None;
+
+        gst::debug!(CAT, imp: self, "Stopped");
+
+        Ok(())
+    }
+
+    fn unlock(&self) -> Result<(), gst::ErrorMessage> {
+        self.flushing.store(true, Ordering::SeqCst);
+
+        Ok(())
+    }
+
+    fn unlock_stop(&self) -> Result<(), gst::ErrorMessage> {
+        self.flushing.store(false, Ordering::SeqCst);
+
+        Ok(())
+    }
+}
+
+impl PushSrcImpl for ZmqSrc {
+    fn create(
+        &self,
+        _buffer: Option<&mut gst::BufferRef>,
+    ) -> Result<CreateSuccess, gst::FlowError> {
+        let mut state = self.state.lock().unwrap();
+        let state = state.as_mut().ok_or_else(|| {
+            gst::element_imp_error!(self, gst::CoreError::Failed, ["Not started yet"]);
+            gst::FlowError::Error
+        })?;
+
+        let mut parts = loop {
+            match state.socket.recv_multipart(0) {
+                Ok(parts) => break parts,
+                Err(zmq::Error::EAGAIN) => {
+                    if self.flushing.load(Ordering::SeqCst) {
+                        gst::debug!(CAT, imp: self, "Flushing");
+                        return Err(gst::FlowError::Flushing);
+                    }
+                }
+                Err(err) => {
+                    gst::element_imp_error!(
+                        self,
+                        gst::ResourceError::Read,
+                        ["Failed to receive message: {}", err]
+                    );
+                    return Err(gst::FlowError::Error);
+                }
+            }
+        };
+
+        // Drop the topic frame, subscription filtering was already done by ZeroMQ
+        if state.mode == ZmqMode::PubSub && !parts.is_empty() {
+            parts.remove(0);
+        }
+
+        if parts.len() != 2 {
+            gst::element_imp_error!(
+                self,
+                gst::StreamError::Format,
+                ["Unexpected number of message frames: {}", parts.len()]
+            );
+            return Err(gst::FlowError::Error);
+        }
+
+        let payload = parts.pop().unwrap();
+        let header = Header::parse(&parts[0]).map_err(|err| {
+            gst::element_imp_error!(self, gst::StreamError::Format, ["{}", err]);
+            gst::FlowError::Error
+        })?;
+
+        if let Some(ref caps) = header.caps {
+            if state.caps.as_ref() != Some(caps) {
+                gst::debug!(CAT, imp: self, "Caps changed to {caps:?}");
+                self.obj().set_caps(caps).map_err(|_| {
+                    gst::element_imp_error!(
+                        self,
+                        gst::CoreError::Negotiation,
+                        ["Failed to negotiate caps {:?}", caps]
+                    );
+                    gst::FlowError::NotNegotiated
+                })?;
+                state.caps = Some(caps.clone());
+            }
+        }
+
+        let mut buffer = gst::Buffer::from_mut_slice(payload);
+        header.apply(buffer.get_mut().unwrap());
+
+        gst::trace!(CAT, imp: self, "Received {:?}", buffer);
+
+        Ok(CreateSuccess::NewBuffer(buffer))
+    }
+}
@@ -0,0 +1,45 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * element-zmqsrc:
+ * @short-description: Receive buffers from a ZeroMQ socket
+ *
+ * Receives messages produced by `zmqsink` and outputs them as buffers with
+ * the caps, flags, offsets and timestamps of the original buffers. Caps
+ * are updated whenever a message carries different caps than the previous
+ * one.
+ *
+ * The original timestamps are kept as-is. Set `do-timestamp=true` to
+ * timestamp buffers with the running time of this pipeline instead.
+ *
+ * ## Example pipeline
+ * ```bash
+ * gst-launch-1.0 -v zmqsrc endpoint=tcp://127.0.0.1:5555 mode=pubsub topic=audio ! \
+ *     audioconvert ! autoaudiosink
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct ZmqSrc(ObjectSubclass<imp::ZmqSrc>) @extends gst_base::PushSrc, gst_base::BaseSrc, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "zmqsrc",
+        gst::Rank::NONE,
+        ZmqSrc::static_type(),
+    )
+}
@@ -0,0 +1,67 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstzeromq::plugin_register_static().expect("zeromq test");
+    });
+}
+
+#[test]
+fn test_push_pull_roundtrip() {
+    init();
+
+    let endpoint = "tcp://127.0.0.1:15555";
+
+    let mut src = gst_check::Harness::new_empty();
+    src.add_parse(&format!(
+        "zmqsrc mode=pushpull endpoint={endpoint} bind=true"
+    ));
+    src.play();
+
+    let mut sink = gst_check::Harness::new_empty();
+    sink.add_parse(&format!(
+        "zmqsink mode=pushpull endpoint={endpoint} bind=false"
+    ));
+    let caps = gst::Caps::builder("text/x-raw")
+        .field("format", "utf8")
+        .build();
+    sink.set_src_caps(caps.clone());
+    sink.play();
+
+    let mut buffer = gst::Buffer::from_slice(b"Hello, world!");
+    {
+        let buffer = buffer.get_mut().unwrap();
+        buffer.set_pts(gst::ClockTime::from_mseconds(200));
+        buffer.set_duration(gst::ClockTime::from_mseconds(40));
+        buffer.set_flags(gst::BufferFlags::DISCONT);
+    }
+    assert_eq!(sink.push(buffer), Ok(gst::FlowSuccess::Ok));
+
+    let received = src.pull().unwrap();
+    assert_eq!(
+        received.map_readable().unwrap().as_slice(),
+        b"Hello, world!"
+    );
+    assert_eq!(received.pts(), Some(gst::ClockTime::from_mseconds(200)));
+    assert_eq!(received.dts(), None);
+    assert_eq!(received.duration(), Some(gst::ClockTime::from_mseconds(40)));
+    assert!(received.flags().contains(gst::BufferFlags::DISCONT));
+
+    let srcpad = src.element().unwrap().static_pad("src").unwrap();
+    assert_eq!(srcpad.current_caps(), Some(caps));
+
+    sink.element().unwrap().set_state(gst::State::Null).unwrap();
+    src.element().unwrap().set_state(gst::State::Null).unwrap();
+}
@@ -0,0 +1,107 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Helpers for the integration tests of the audio decoder plugins in this repository.
+//!
+//! The helpers operate on a [`gst_check::Harness`] around the decoder and allow to feed a canned
+//! stream split into packets, to simulate flushing seeks and to compare the decoded samples with
+//! the samples of a golden WAV file.
+//!
+//! ```rust,ignore
+//! let mut h = gst_check::Harness::new("claxondec");
+//! h.play();
+//! h.set_src_caps_str("audio/x-flac, framed=true");
+//!
+//! gst_plugin_test_utils::push_packets(&mut h, data, &[4, 38, 17465]).unwrap();
+//! h.push_event(gst::event::Eos::new());
+//!
+//! let wav = gst_plugin_test_utils::Wav::from_bytes(include_bytes!("golden.wav")).unwrap();
+//! gst_plugin_test_utils::assert_decoded_eq(&mut h, &wav, 0);
+//! ```
+
+mod pcm;
+mod wav;
+
+pub use pcm::{compare_samples, samples, Mismatch};
+pub use wav::{Wav, WavError};
+
+/// Pushes `data` split into packets of the given sizes into the harness.
+///
+/// Every packet is pushed as a separate buffer. If the sizes don't add up to the size of `data`,
+/// the remaining data is pushed as a last packet.
+pub fn push_packets(
+    h: &mut gst_check::Harness,
+    data: &'static [u8],
+    packet_sizes: &[usize],
+) -> Result<gst::FlowSuccess, gst::FlowError> {
+    let mut offset = 0;
+    for size in packet_sizes {
+        let end = (offset + size).min(data.len());
+        h.push(gst::Buffer::from_slice(&data[offset..end]))?;
+        offset = end;
+    }
+
+    if offset < data.len() {
+        h.push(gst::Buffer::from_slice(&data[offset..]))?;
+    }
+
+    Ok(gst::FlowSuccess::Ok)
+}
+
+/// Pulls all buffers that are currently queued on the sink pad of the harness.
+pub fn pull_all(h: &mut gst_check::Harness) -> Vec<gst::Buffer> {
+    std::iter::from_fn(|| h.try_pull()).collect()
+}
+
+/// Simulates a flushing seek to `position` by sending a flush and a new segment to the harness.
+///
+/// All buffers that were queued on the sink pad of the harness before are dropped.
+pub fn flush_seek(h: &mut gst_check::Harness, position: gst::ClockTime) {
+    h.push_event(gst::event::FlushStart::new());
+    h.push_event(gst::event::FlushStop::new(true));
+
+    let mut segment = gst::FormattedSegment::<gst::ClockTime>::new();
+    segment.set_start(position);
+    segment.set_time(position);
+    h.push_event(gst::event::Segment::new(&segment));
+
+    while h.try_pull().is_some() {}
+}
+
+/// Pulls all queued decoded buffers from the harness and compares them with the samples of the
+/// golden WAV file.
+///
+/// Each sample may differ by up to `tolerance` from the expected value, scaled to the sample
+/// depth of the WAV file.
+///
+/// # Panics
+///
+/// Panics if the decoded audio doesn't match the WAV file or if the negotiated caps are not
+/// interleaved raw audio with the same rate and number of channels.
+pub fn assert_decoded_eq(h: &mut gst_check::Harness, wav: &Wav, tolerance: u32) {
+    let caps = h
+        .sinkpad()
+        .expect("harness has no sinkpad")
+        .current_caps()
+        .expect("pad has no caps");
+    let info = gst_audio::AudioInfo::from_caps(&caps).expect("invalid audio caps");
+
+    assert_eq!(info.rate(), wav.rate(), "sample rate differs");
+    assert_eq!(info.channels(), wav.channels(), "number of channels differs");
+
+    let mut decoded = Vec::new();
+    for buffer in pull_all(h) {
+        let map = buffer.map_readable().expect("buffer not readable");
+        decoded.extend(samples(&map, info.format(), wav.depth()));
+    }
+
+    let expected = samples(wav.data(), wav.format(), wav.depth());
+    if let Err(mismatch) = compare_samples(&decoded, &expected, tolerance) {
+        panic!("decoded audio differs from WAV file: {mismatch}");
+    }
+}
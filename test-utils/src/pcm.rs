@@ -0,0 +1,142 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::fmt;
+
+/// Difference between decoded and expected samples.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+    /// The number of samples differs.
+    Length { actual: usize, expected: usize },
+    /// The sample at `index` differs by more than the tolerance.
+    Sample {
+        index: usize,
+        actual: i32,
+        expected: i32,
+    },
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Mismatch::Length { actual, expected } => {
+                write!(f, "got {actual} samples but expected {expected}")
+            }
+            Mismatch::Sample {
+                index,
+                actual,
+                expected,
+            } => write!(f, "sample {index} is {actual} but expected {expected}"),
+        }
+    }
+}
+
+/// Converts interleaved raw audio in the given format to samples with `depth` bits.
+///
+/// Samples with a different depth are shifted accordingly, which allows to compare e.g. `S24_32`
+/// decoder output with packed 24 bit samples from a WAV file.
+///
+/// # Panics
+///
+/// Panics if the format is not a little endian integer format.
+pub fn samples(data: &[u8], format: gst_audio::AudioFormat, depth: u32) -> Vec<i32> {
+    use gst_audio::AudioFormat::*;
+
+    let (width, format_depth) = match format {
+        U8 => (1, 8),
+        S16le => (2, 16),
+        S2432le => (4, 24),
+        S24le => (3, 24),
+        S32le => (4, 32),
+        _ => panic!("Unsupported format {format:?}"),
+    };
+
+    data.chunks_exact(width)
+        .map(|chunk| {
+            let sample = match format {
+                U8 => chunk[0] as i32 - 128,
+                S16le => i16::from_le_bytes([chunk[0], chunk[1]]) as i32,
+                // Sign extend from 24 bits
+                S2432le | S24le => i32::from_le_bytes([0, chunk[0], chunk[1], chunk[2]]) >> 8,
+                S32le => i32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]),
+                _ => unreachable!(),
+            };
+
+            if format_depth > depth {
+                sample >> (format_depth - depth)
+            } else {
+                sample << (depth - format_depth)
+            }
+        })
+        .collect()
+}
+
+/// Compares two sample sequences, allowing each sample to differ by up to `tolerance`.
+pub fn compare_samples(actual: &[i32], expected: &[i32], tolerance: u32) -> Result<(), Mismatch> {
+    if actual.len() != expected.len() {
+        return Err(Mismatch::Length {
+            actual: actual.len(),
+            expected: expected.len(),
+        });
+    }
+
+    match actual
+        .iter()
+        .zip(expected)
+        .position(|(a, e)| (*a as i64 - *e as i64).unsigned_abs() > tolerance as u64)
+    {
+        Some(index) => Err(Mismatch::Sample {
+            index,
+            actual: actual[index],
+            expected: expected[index],
+        }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_samples_depth() {
+        let data = [0x00, 0x01, 0x00, 0x00, 0x00, 0x80, 0xff, 0xff];
+        assert_eq!(
+            samples(&data, gst_audio::AudioFormat::S2432le, 24),
+            [0x100, -0x8000]
+        );
+        assert_eq!(
+            samples(&data, gst_audio::AudioFormat::S2432le, 16),
+            [0x1, -0x80]
+        );
+        assert_eq!(
+            samples(&data[..6], gst_audio::AudioFormat::S24le, 32),
+            [0x10000, -0x800000]
+        );
+    }
+
+    #[test]
+    fn test_compare_samples() {
+        assert_eq!(compare_samples(&[1, 2, 3], &[1, 3, 3], 1), Ok(()));
+        assert_eq!(
+            compare_samples(&[1, 2, 3], &[1, 4, 3], 1),
+            Err(Mismatch::Sample {
+                index: 1,
+                actual: 2,
+                expected: 4
+            })
+        );
+        assert_eq!(
+            compare_samples(&[1, 2], &[1, 2, 3], 0),
+            Err(Mismatch::Length {
+                actual: 2,
+                expected: 3
+            })
+        );
+    }
+}
@@ -0,0 +1,171 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::fmt;
+
+const WAVE_FORMAT_PCM: u16 = 0x0001;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xfffe;
+
+/// Errors when parsing a WAV file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WavError {
+    /// The file is not a RIFF WAVE file or is truncated.
+    Invalid,
+    /// The file does not contain integer PCM samples.
+    UnsupportedFormat(u16),
+    /// The sample depth is not supported.
+    UnsupportedDepth(u16),
+}
+
+impl fmt::Display for WavError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WavError::Invalid => write!(f, "invalid WAV file"),
+            WavError::UnsupportedFormat(format) => write!(f, "unsupported format 0x{format:04x}"),
+            WavError::UnsupportedDepth(depth) => write!(f, "unsupported depth {depth}"),
+        }
+    }
+}
+
+impl std::error::Error for WavError {}
+
+/// Integer PCM samples of a WAV file.
+#[derive(Debug, Clone)]
+pub struct Wav {
+    format: gst_audio::AudioFormat,
+    depth: u32,
+    rate: u32,
+    channels: u32,
+    data: Vec<u8>,
+}
+
+impl Wav {
+    /// Parses a little endian integer PCM WAV file with 8, 16, 24 or 32 bits per sample.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, WavError> {
+        if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+            return Err(WavError::Invalid);
+        }
+
+        let mut fmt = None;
+        let mut chunks = &bytes[12..];
+        while chunks.len() >= 8 {
+            let id = &chunks[0..4];
+            let size = u32::from_le_bytes(chunks[4..8].try_into().unwrap()) as usize;
+            let body = chunks.get(8..8 + size).ok_or(WavError::Invalid)?;
+
+            match id {
+                b"fmt " => {
+                    if body.len() < 16 {
+                        return Err(WavError::Invalid);
+                    }
+                    let mut tag = u16::from_le_bytes([body[0], body[1]]);
+                    if tag == WAVE_FORMAT_EXTENSIBLE && body.len() >= 26 {
+                        // First two bytes of the sub-format GUID
+                        tag = u16::from_le_bytes([body[24], body[25]]);
+                    }
+                    if tag != WAVE_FORMAT_PCM {
+                        return Err(WavError::UnsupportedFormat(tag));
+                    }
+
+                    let channels = u16::from_le_bytes([body[2], body[3]]) as u32;
+                    let rate = u32::from_le_bytes(body[4..8].try_into().unwrap());
+                    let depth = u16::from_le_bytes([body[14], body[15]]);
+                    fmt = Some((channels, rate, depth));
+                }
+                b"data" => {
+                    let (channels, rate, depth) = fmt.ok_or(WavError::Invalid)?;
+                    let format = match depth {
+                        8 => gst_audio::AudioFormat::U8,
+                        16 => gst_audio::AudioFormat::S16le,
+                        24 => gst_audio::AudioFormat::S24le,
+                        32 => gst_audio::AudioFormat::S32le,
+                        _ => return Err(WavError::UnsupportedDepth(depth)),
+                    };
+
+                    return Ok(Wav {
+                        format,
+                        depth: depth as u32,
+                        rate,
+                        channels,
+                        data: body.to_vec(),
+                    });
+                }
+                _ => (),
+            }
+
+            // Chunks are padded to an even size
+            let next = (8 + size + 1) & !1;
+            chunks = chunks.get(next..).unwrap_or_default();
+        }
+
+        Err(WavError::Invalid)
+    }
+
+    pub fn format(&self) -> gst_audio::AudioFormat {
+        self.format
+    }
+
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    pub fn rate(&self) -> u32 {
+        self.rate
+    }
+
+    pub fn channels(&self) -> u32 {
+        self.channels
+    }
+
+    /// Interleaved raw sample data.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wav(depth: u16, data: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + data.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&WAVE_FORMAT_PCM.to_le_bytes());
+        bytes.extend_from_slice(&2u16.to_le_bytes());
+        bytes.extend_from_slice(&44_100u32.to_le_bytes());
+        bytes.extend_from_slice(&(44_100 * 2 * depth as u32 / 8).to_le_bytes());
+        bytes.extend_from_slice(&(2 * depth / 8).to_le_bytes());
+        bytes.extend_from_slice(&depth.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    #[test]
+    fn test_parse() {
+        let wav = Wav::from_bytes(&wav(16, &[1, 0, 2, 0])).unwrap();
+        assert_eq!(wav.format(), gst_audio::AudioFormat::S16le);
+        assert_eq!(wav.depth(), 16);
+        assert_eq!(wav.rate(), 44_100);
+        assert_eq!(wav.channels(), 2);
+        assert_eq!(wav.data(), &[1, 0, 2, 0]);
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert_eq!(Wav::from_bytes(b"RIFF").unwrap_err(), WavError::Invalid);
+        assert_eq!(
+            Wav::from_bytes(&wav(12, &[])).unwrap_err(),
+            WavError::UnsupportedDepth(12)
+        );
+    }
+}
@@ -16,6 +16,8 @@ use std::sync::Mutex;
 
 use serde::Serialize;
 
+use crate::buffer_flags_nicks;
+
 #[derive(Serialize, Debug)]
 enum Line<'a> {
     Header {
@@ -26,6 +28,8 @@ enum Line<'a> {
         duration: Option<gst::ClockTime>,
         #[serde(borrow)]
         data: &'a serde_json::value::RawValue,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        flags: Vec<String>,
     },
 }
 
@@ -124,6 +128,7 @@ impl JsonGstEnc {
             pts,
             duration,
             data,
+            flags: buffer_flags_nicks(buffer.flags()),
         };
 
         let mut json = serde_json::to_string(&line).map_err(|err| {
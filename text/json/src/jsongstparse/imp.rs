@@ -99,6 +99,8 @@ enum Line<'a> {
         duration: Option<gst::ClockTime>,
         #[serde(borrow)]
         data: &'a serde_json::value::RawValue,
+        #[serde(default)]
+        flags: Vec<String>,
     },
 }
 
@@ -179,6 +181,7 @@ impl State {
         buffer: &mut gst::buffer::Buffer,
         pts: Option<gst::ClockTime>,
         duration: Option<gst::ClockTime>,
+        flags: &[String],
     ) {
         let buffer = buffer.get_mut().unwrap();
 
@@ -186,6 +189,15 @@ impl State {
 
         buffer.set_pts(pts);
 
+        if !flags.is_empty() {
+            let class = glib::FlagsClass::with_type(gst::BufferFlags::static_type()).unwrap();
+            let bits = flags
+                .iter()
+                .filter_map(|nick| class.value_by_nick(nick))
+                .fold(0, |bits, v| bits | v.value());
+            buffer.set_flags(gst::BufferFlags::from_bits_truncate(bits));
+        }
+
         if self.discont {
             buffer.set_flags(gst::BufferFlags::DISCONT);
             self.discont = false;
@@ -233,6 +245,7 @@ impl JsonGstParse {
                     pts,
                     duration,
                     data,
+                    flags,
                 })) => {
                     gst::debug!(
                         CAT,
@@ -258,7 +271,7 @@ impl JsonGstParse {
                             }
                         }
 
-                        state.add_buffer_metadata(&mut buffer, pts, duration);
+                        state.add_buffer_metadata(&mut buffer, pts, duration, &flags);
 
                         let send_eos = buffer
                             .pts()
@@ -457,12 +470,7 @@ impl JsonGstParse {
             }
 
             while let Some(line) = reader.line_with_drain(true) {
-                if let Ok(Line::Buffer {
-                    pts,
-                    duration,
-                    data: _data,
-                }) = serde_json::from_slice(line)
-                {
+                if let Ok(Line::Buffer { pts, duration, .. }) = serde_json::from_slice(line) {
                     last_pts = pts.opt_add(duration);
                 }
             }
@@ -14,14 +14,30 @@
  * Since: plugins-rs-0.5.0
  */
 use gst::glib;
+use gst::prelude::*;
 
 mod jsongstenc;
 mod jsongstparse;
 mod line_reader;
+mod metajsonsink;
+
+/// Returns the nicks of all flags set in `flags`, as used in the JSON
+/// representation of buffers.
+pub(crate) fn buffer_flags_nicks(flags: gst::BufferFlags) -> Vec<String> {
+    let class = glib::FlagsClass::with_type(gst::BufferFlags::static_type()).unwrap();
+
+    class
+        .values()
+        .iter()
+        .filter(|v| v.value() != 0 && flags.bits() & v.value() == v.value())
+        .map(|v| v.nick().to_owned())
+        .collect()
+}
 
 fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
     jsongstparse::register(plugin)?;
     jsongstenc::register(plugin)?;
+    metajsonsink::register(plugin)?;
     Ok(())
 }
 
@@ -0,0 +1,418 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::prelude::*;
+use gst_base::subclass::prelude::*;
+
+use once_cell::sync::Lazy;
+
+use serde::Serialize;
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::buffer_flags_nicks;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "metajsonsink",
+        gst::DebugColorFlags::empty(),
+        Some("Meta JSON Sink"),
+    )
+});
+
+#[glib::flags(name = "GstMetaJsonSinkMetas")]
+pub(crate) enum Metas {
+    #[flags_value(name = "Timecode of the video timecode meta", nick = "timecode")]
+    TIMECODE = 0b00000001,
+    #[flags_value(name = "Region of interest metas", nick = "detections")]
+    DETECTIONS = 0b00000010,
+    #[flags_value(name = "Audio level meta", nick = "audio-level")]
+    AUDIO_LEVEL = 0b00000100,
+}
+
+const DEFAULT_METAS: Metas = Metas::all();
+
+#[derive(Serialize, Debug)]
+struct Line<'a> {
+    pts: Option<gst::ClockTime>,
+    dts: Option<gst::ClockTime>,
+    duration: Option<gst::ClockTime>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    flags: Vec<String>,
+    #[serde(skip_serializing_if = "serde_json::Map::is_empty")]
+    metas: &'a serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone)]
+struct Settings {
+    location: Option<PathBuf>,
+    metas: Metas,
+    custom_metas: Vec<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            location: None,
+            metas: DEFAULT_METAS,
+            custom_metas: Vec::new(),
+        }
+    }
+}
+
+struct State {
+    writer: BufWriter<File>,
+    metas: Metas,
+    custom_metas: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct MetaJsonSink {
+    settings: Mutex<Settings>,
+    state: Mutex<Option<State>>,
+}
+
+impl MetaJsonSink {
+    fn flush(&self) -> Result<(), gst::ErrorMessage> {
+        let mut state = self.state.lock().unwrap();
+        let Some(state) = state.as_mut() else {
+            return Ok(());
+        };
+
+        state.writer.flush().map_err(|err| {
+            gst::error_msg!(gst::ResourceError::Write, ["Failed to flush file: {}", err])
+        })
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for MetaJsonSink {
+    const NAME: &'static str = "GstMetaJsonSink";
+    type Type = super::MetaJsonSink;
+    type ParentType = gst_base::BaseSink;
+}
+
+impl ObjectImpl for MetaJsonSink {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecString::builder("location")
+                    .nick("File Location")
+                    .blurb("Location of the file to write")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecFlags::builder::<Metas>("metas")
+                    .nick("Metas")
+                    .blurb("Metas to record")
+                    .default_value(DEFAULT_METAS)
+                    .mutable_ready()
+                    .build(),
+                gst::ParamSpecArray::builder("custom-metas")
+                    .nick("Custom Metas")
+                    .blurb("Names of the custom metas to record with their structure")
+                    .element_spec(&glib::ParamSpecString::builder("custom-meta").build())
+                    .mutable_ready()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "location" => {
+                settings.location = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .map(PathBuf::from);
+            }
+            "metas" => {
+                settings.metas = value.get().expect("type checked upstream");
+            }
+            "custom-metas" => {
+                settings.custom_metas = value
+                    .get::<gst::ArrayRef>()
+                    .expect("type checked upstream")
+                    .as_slice()
+                    .iter()
+                    .map(|name| {
+                        name.get::<&str>()
+                            .expect("type checked upstream")
+                            .to_string()
+                    })
+                    .collect();
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "location" => settings
+                .location
+                .as_ref()
+                .and_then(|location| location.to_str())
+                .to_value(),
+            "metas" => settings.metas.to_value(),
+            "custom-metas" => {
+                gst::Array::new(settings.custom_metas.iter().map(|name| name.as_str())).to_value()
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        // Recording the trace should never hold back the pipeline
+        self.obj().set_sync(false);
+    }
+}
+
+impl GstObjectImpl for MetaJsonSink {}
+
+impl ElementImpl for MetaJsonSink {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Meta JSON Sink",
+                "Sink/File/Metadata",
+                "Records timestamps, flags and metas of buffers as newline-delimited JSON",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &gst::Caps::new_any(),
+            )
+            .unwrap();
+
+            vec![sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseSinkImpl for MetaJsonSink {
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        let settings = self.settings.lock().unwrap().clone();
+
+        let location = settings.location.ok_or_else(|| {
+            gst::error_msg!(
+                gst::ResourceError::Settings,
+                ["File location is not defined"]
+            )
+        })?;
+
+        let file = File::create(&location).map_err(|err| {
+            gst::error_msg!(
+                gst::ResourceError::OpenWrite,
+                [
+                    "Could not open file {} for writing: {}",
+                    location.display(),
+                    err
+                ]
+            )
+        })?;
+
+        gst::debug!(CAT, imp: self, "Opened file {}", location.display());
+
+        *self.state.lock().unwrap() = Some(State {
+            writer: BufWriter::new(file),
+            metas: settings.metas,
+            custom_metas: settings.custom_metas,
+        });
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        let res = self.flush();
+        *self.state.lock().unwrap() = None;
+
+        gst::debug!(CAT, imp: self, "Stopped");
+
+        res
+    }
+
+    fn event(&self, event: gst::Event) -> bool {
+        if let gst::EventView::Eos(_) = event.view() {
+            if let Err(err) = self.flush() {
+                self.post_error_message(err);
+                return false;
+            }
+        }
+
+        self.parent_event(event)
+    }
+
+    fn render(&self, buffer: &gst::Buffer) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let mut state = self.state.lock().unwrap();
+        let state = state.as_mut().ok_or_else(|| {
+            gst::element_imp_error!(self, gst::CoreError::Failed, ["Not started yet"]);
+            gst::FlowError::Error
+        })?;
+
+        let metas = metas_to_json(buffer, state.metas, &state.custom_metas);
+        let line = Line {
+            pts: buffer.pts(),
+            dts: buffer.dts(),
+            duration: buffer.duration(),
+            flags: buffer_flags_nicks(buffer.flags()),
+            metas: &metas,
+        };
+
+        let mut json = serde_json::to_string(&line).map_err(|err| {
+            gst::element_imp_error!(
+                self,
+                gst::ResourceError::Write,
+                ["Failed to serialize as json {}", err]
+            );
+
+            gst::FlowError::Error
+        })?;
+
+        json.push('\n');
+
+        gst::trace!(CAT, imp: self, "Writing {}", json.trim_end());
+
+        state.writer.write_all(json.as_bytes()).map_err(|err| {
+            gst::element_imp_error!(
+                self,
+                gst::ResourceError::Write,
+                ["Failed to write to file: {}", err]
+            );
+
+            gst::FlowError::Error
+        })?;
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+}
+
+fn metas_to_json(
+    buffer: &gst::BufferRef,
+    metas: Metas,
+    custom_metas: &[String],
+) -> serde_json::Map<String, serde_json::Value> {
+    let mut map = serde_json::Map::new();
+
+    if metas.contains(Metas::TIMECODE) {
+        if let Some(meta) = buffer.meta::<gst_video::VideoTimeCodeMeta>() {
+            map.insert("timecode".to_string(), meta.tc().to_string().into());
+        }
+    }
+
+    if metas.contains(Metas::DETECTIONS) {
+        let detections = buffer
+            .iter_meta::<gst_video::VideoRegionOfInterestMeta>()
+            .map(|meta| {
+                let (x, y, width, height) = meta.rect();
+
+                serde_json::json!({
+                    "type": meta.roi_type(),
+                    "id": meta.id(),
+                    "parent-id": meta.parent_id(),
+                    "x": x,
+                    "y": y,
+                    "width": width,
+                    "height": height,
+                    "params": meta.params().map(structure_to_json).collect::<Vec<_>>(),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        if !detections.is_empty() {
+            map.insert("detections".to_string(), detections.into());
+        }
+    }
+
+    if metas.contains(Metas::AUDIO_LEVEL) {
+        if let Some(meta) = buffer.meta::<gst_audio::AudioLevelMeta>() {
+            map.insert(
+                "audio-level".to_string(),
+                serde_json::json!({
+                    "level": meta.level(),
+                    "voice-activity": meta.voice_activity(),
+                }),
+            );
+        }
+    }
+
+    for name in custom_metas {
+        if let Ok(meta) = gst::meta::CustomMeta::from_buffer(buffer, name) {
+            map.insert(name.clone(), structure_to_json(meta.structure()));
+        }
+    }
+
+    map
+}
+
+fn structure_to_json(s: &gst::StructureRef) -> serde_json::Value {
+    let mut map = serde_json::Map::new();
+
+    map.insert("name".to_string(), s.name().to_string().into());
+    for (field, value) in s.iter() {
+        map.insert(field.to_string(), value_to_json(value));
+    }
+
+    serde_json::Value::Object(map)
+}
+
+fn value_to_json(value: &glib::Value) -> serde_json::Value {
+    use serde_json::Value;
+
+    if let Ok(v) = value.get::<bool>() {
+        Value::Bool(v)
+    } else if let Ok(v) = value.get::<i32>() {
+        v.into()
+    } else if let Ok(v) = value.get::<u32>() {
+        v.into()
+    } else if let Ok(v) = value.get::<i64>() {
+        v.into()
+    } else if let Ok(v) = value.get::<u64>() {
+        v.into()
+    } else if let Ok(v) = value.get::<f32>() {
+        f64::from(v).into()
+    } else if let Ok(v) = value.get::<f64>() {
+        v.into()
+    } else if let Ok(v) = value.get::<Option<String>>() {
+        v.map(Value::String).unwrap_or(Value::Null)
+    } else if let Ok(Some(v)) = value.get::<Option<gst::Structure>>() {
+        structure_to_json(&v)
+    } else if let Ok(v) = value.get::<gst::Array>() {
+        Value::Array(v.iter().map(value_to_json).collect())
+    } else if let Ok(v) = value.get::<gst::List>() {
+        Value::Array(v.iter().map(value_to_json).collect())
+    } else {
+        value
+            .serialize()
+            .map(|s| Value::String(s.to_string()))
+            .unwrap_or(Value::Null)
+    }
+}
@@ -0,0 +1,63 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * element-metajsonsink:
+ * @short-description: Records buffer timing and metadata as newline-delimited JSON
+ *
+ * Writes one JSON object per received buffer to the file at `location`, which
+ * gives a machine-readable trace of what a pipeline produced, for example as a
+ * sidecar file next to the recorded media. The buffer data itself is not
+ * written.
+ *
+ * Each line contains the `pts`, `dts` and `duration` of the buffer in
+ * nanoseconds (or `null` if unset), the nicks of its `flags` and, if any of
+ * the selected metas is present, a `metas` object with:
+ *
+ * - `timecode`: the timecode of the video timecode meta as a string.
+ * - `detections`: an array with one object per region of interest meta,
+ *   containing its `type`, `id`, `parent-id`, `x`, `y`, `width`, `height`
+ *   and `params`.
+ * - `audio-level`: the `level` in -dBov and `voice-activity` of the audio
+ *   level meta.
+ * - the structure of each custom meta listed in `custom-metas`, under the
+ *   name of the meta, e.g. the `VideoQualityMeta` of `videoquality`.
+ *
+ * ```json
+ * {"pts":0,"dts":null,"duration":40000000,"flags":["discont"],"metas":{"timecode":"10:00:00:00"}}
+ * ```
+ *
+ * ## Example pipeline
+ * ```bash
+ * gst-launch-1.0 videotestsrc num-buffers=250 ! timecodestamper ! tee name=t \
+ *     t. ! queue ! x264enc ! mp4mux ! filesink location=out.mp4 \
+ *     t. ! queue ! metajsonsink location=out.ndjson
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct MetaJsonSink(ObjectSubclass<imp::MetaJsonSink>) @extends gst_base::BaseSink, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    #[cfg(feature = "doc")]
+    imp::Metas::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+
+    gst::Element::register(
+        Some(plugin),
+        "metajsonsink",
+        gst::Rank::NONE,
+        MetaJsonSink::static_type(),
+    )
+}
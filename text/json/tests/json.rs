@@ -98,3 +98,124 @@ fn test_parse() {
     assert_eq!(buf.duration(), Some(2.seconds()));
     assert_eq!(std::str::from_utf8(map.as_ref()), Ok("{\"foo\":42}"));
 }
+
+#[test]
+fn test_flags_roundtrip() {
+    init();
+
+    let input = "{\"foo\":42}";
+
+    let mut h = gst_check::Harness::new_parse("jsongstenc ! jsongstparse");
+
+    h.set_src_caps_str("application/x-json, format=test");
+
+    let buf = {
+        let mut buf = gst::Buffer::from_mut_slice(Vec::from(input));
+        let buf_ref = buf.get_mut().unwrap();
+        buf_ref.set_pts(gst::ClockTime::ZERO);
+        buf_ref.set_duration(2.seconds());
+        buf_ref.set_flags(gst::BufferFlags::DELTA_UNIT | gst::BufferFlags::MARKER);
+        buf
+    };
+
+    assert_eq!(h.push(buf), Ok(gst::FlowSuccess::Ok));
+
+    let buf = h.pull().expect("Couldn't pull buffer");
+    assert_eq!(buf.pts(), Some(gst::ClockTime::ZERO));
+    assert_eq!(buf.duration(), Some(2.seconds()));
+    assert!(buf
+        .flags()
+        .contains(gst::BufferFlags::DELTA_UNIT | gst::BufferFlags::MARKER));
+    let map = buf.map_readable().expect("Couldn't map buffer readable");
+    assert_eq!(std::str::from_utf8(map.as_ref()), Ok(input));
+}
+
+#[test]
+fn test_meta_json_sink() {
+    init();
+
+    gst::meta::CustomMeta::register("TestMeta", &[]);
+
+    let location = std::env::temp_dir().join(format!("metajsonsink-{}.ndjson", std::process::id()));
+
+    let mut h = gst_check::Harness::new("metajsonsink");
+    h.element()
+        .unwrap()
+        .set_property("location", location.to_str().unwrap());
+    h.element()
+        .unwrap()
+        .set_property("custom-metas", gst::Array::new(["TestMeta"]));
+
+    h.set_src_caps_str("video/x-raw");
+
+    let buf = {
+        let mut buf = gst::Buffer::new();
+        let buf_ref = buf.get_mut().unwrap();
+        buf_ref.set_pts(gst::ClockTime::ZERO);
+        buf_ref.set_duration(40.mseconds());
+        buf_ref.set_flags(gst::BufferFlags::DISCONT);
+
+        let tc = gst_video::ValidVideoTimeCode::new(
+            gst::Fraction::new(25, 1),
+            None,
+            gst_video::VideoTimeCodeFlags::empty(),
+            10,
+            0,
+            0,
+            0,
+            0,
+        )
+        .unwrap();
+        gst_video::VideoTimeCodeMeta::add(buf_ref, &tc);
+        gst_video::VideoRegionOfInterestMeta::add(buf_ref, "face", (10, 20, 30, 40));
+        gst_audio::AudioLevelMeta::add(buf_ref, 30, true);
+        let mut meta = gst::meta::CustomMeta::add(buf_ref, "TestMeta").unwrap();
+        meta.mut_structure().set("score", 0.5f64);
+
+        buf
+    };
+    assert_eq!(h.push(buf), Ok(gst::FlowSuccess::Ok));
+
+    let buf = {
+        let mut buf = gst::Buffer::new();
+        let buf_ref = buf.get_mut().unwrap();
+        buf_ref.set_pts(40.mseconds());
+        buf_ref.set_dts(40.mseconds());
+        buf
+    };
+    assert_eq!(h.push(buf), Ok(gst::FlowSuccess::Ok));
+
+    assert!(h.push_event(gst::event::Eos::new()));
+
+    let contents = std::fs::read_to_string(&location).unwrap();
+    std::fs::remove_file(&location).unwrap();
+
+    let lines = contents
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2);
+
+    let line = &lines[0];
+    assert_eq!(line["pts"], 0);
+    assert_eq!(line["dts"], serde_json::Value::Null);
+    assert_eq!(line["duration"], 40_000_000);
+    assert_eq!(line["flags"], serde_json::json!(["discont"]));
+    let metas = &line["metas"];
+    assert_eq!(metas["timecode"], "10:00:00:00");
+    assert_eq!(metas["detections"][0]["type"], "face");
+    assert_eq!(metas["detections"][0]["x"], 10);
+    assert_eq!(metas["detections"][0]["y"], 20);
+    assert_eq!(metas["detections"][0]["width"], 30);
+    assert_eq!(metas["detections"][0]["height"], 40);
+    assert_eq!(metas["audio-level"]["level"], 30);
+    assert_eq!(metas["audio-level"]["voice-activity"], true);
+    assert_eq!(metas["TestMeta"]["score"], 0.5);
+
+    let line = &lines[1];
+    assert_eq!(line["pts"], 40_000_000);
+    assert_eq!(line["dts"], 40_000_000);
+    assert_eq!(line["duration"], serde_json::Value::Null);
+    assert!(line.get("flags").is_none());
+    assert!(line.get("metas").is_none());
+}
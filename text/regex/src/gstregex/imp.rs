@@ -26,6 +26,7 @@ static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
 
 enum Operation {
     ReplaceAll(String),
+    Classify(String),
 }
 
 struct Command {
@@ -71,6 +72,7 @@ impl RegEx {
             .to_string();
 
         let state = self.state.lock().unwrap();
+        let mut messages = vec![];
 
         for command in &state.commands {
             match &command.operation {
@@ -80,6 +82,41 @@ impl RegEx {
                         .replace_all(&data, replacement.as_str())
                         .to_string();
                 }
+                Operation::Classify(class) => {
+                    let matches = command
+                        .regex
+                        .find_iter(&data)
+                        .map(|m| m.as_str().to_string())
+                        .collect::<Vec<_>>();
+
+                    if matches.is_empty() {
+                        continue;
+                    }
+
+                    gst::debug!(
+                        CAT,
+                        imp: self,
+                        "Classified {:?} as {} ({} matches)",
+                        data,
+                        class,
+                        matches.len()
+                    );
+
+                    messages.push(
+                        gst::message::Element::builder(
+                            gst::Structure::builder("regex-classification")
+                                .field("class", class)
+                                .field("pattern", &command.pattern)
+                                .field("matches", gst::Array::new(matches))
+                                .field("text", &data)
+                                .field("pts", buffer.pts())
+                                .field("duration", buffer.duration())
+                                .build(),
+                        )
+                        .src(&*self.obj())
+                        .build(),
+                    );
+                }
             }
         }
 
@@ -98,6 +135,10 @@ impl RegEx {
 
         drop(state);
 
+        for message in messages {
+            let _ = self.obj().post_message(message);
+        }
+
         self.srcpad.push(outbuf)
     }
 }
@@ -246,6 +287,24 @@ impl ObjectImpl for RegEx {
                                 operation: Operation::ReplaceAll(replacement),
                             });
                         }
+                        "classify" => {
+                            let class = match s.get::<Option<String>>("class") {
+                                Ok(Some(class)) => class,
+                                Ok(None) | Err(_) => {
+                                    gst::error!(
+                                        CAT,
+                                        imp: self,
+                                        "Classify operations require a class field as a string"
+                                    );
+                                    continue;
+                                }
+                            };
+                            state.commands.push(Command {
+                                pattern,
+                                regex,
+                                operation: Operation::Classify(class),
+                            });
+                        }
                         val => {
                             gst::error!(CAT, imp: self, "Unknown operation {}", val);
                         }
@@ -271,6 +330,14 @@ impl ObjectImpl for RegEx {
                                     .build(),
                             );
                         }
+                        Operation::Classify(ref class) => {
+                            commands.append(
+                                gst::Structure::builder("classify")
+                                    .field("pattern", &command.pattern)
+                                    .field("class", class)
+                                    .build(),
+                            );
+                        }
                     }
                 }
                 commands.to_value()
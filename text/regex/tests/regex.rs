@@ -65,3 +65,69 @@ fn test_replace_all() {
         std::str::from_utf8(expected_output.as_ref())
     );
 }
+
+#[test]
+fn test_classify() {
+    init();
+
+    let input = b"crap that mothertrapper";
+
+    let mut h = gst_check::Harness::new("regex");
+    let bus = gst::Bus::new();
+
+    {
+        let regex = h.element().expect("Could not create regex");
+        regex.set_bus(Some(&bus));
+
+        let commands = gst::Array::new([
+            gst::Structure::builder("classify")
+                .field("pattern", "[a-z]*trap[a-z]*")
+                .field("class", "profanity")
+                .build(),
+            gst::Structure::builder("classify")
+                .field("pattern", "hello")
+                .field("class", "greeting")
+                .build(),
+        ]);
+
+        regex.set_property("commands", &commands);
+    }
+
+    h.set_src_caps_str("text/x-raw, format=utf8");
+
+    let buf = {
+        let mut buf = gst::Buffer::from_mut_slice(Vec::from(&input[..]));
+        let buf_ref = buf.get_mut().unwrap();
+        buf_ref.set_pts(gst::ClockTime::ZERO);
+        buf_ref.set_duration(2.seconds());
+        buf
+    };
+
+    assert_eq!(h.push(buf), Ok(gst::FlowSuccess::Ok));
+
+    // Classification doesn't modify the text
+    let buf = h.pull().expect("Couldn't pull buffer");
+    let map = buf.map_readable().expect("Couldn't map buffer readable");
+    assert_eq!(map.as_slice(), &input[..]);
+
+    let msg = bus
+        .pop_filtered(&[gst::MessageType::Element])
+        .expect("No classification message");
+    let s = msg.structure().unwrap();
+    assert_eq!(s.name(), "regex-classification");
+    assert_eq!(s.get::<&str>("class").unwrap(), "profanity");
+    assert_eq!(
+        s.get::<Option<gst::ClockTime>>("pts").unwrap(),
+        Some(gst::ClockTime::ZERO)
+    );
+
+    let matches = s.get::<gst::Array>("matches").unwrap();
+    let matches = matches
+        .iter()
+        .map(|v| v.get::<String>().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(matches, ["mothertrapper"]);
+
+    // Nothing matched the second command
+    assert!(bus.pop_filtered(&[gst::MessageType::Element]).is_none());
+}
@@ -0,0 +1,437 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+use encoding_rs::Encoding;
+use once_cell::sync::Lazy;
+
+use std::sync::{Mutex, MutexGuard};
+
+use super::parser::{convert_text, CueReader};
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "srtparse",
+        gst::DebugColorFlags::empty(),
+        Some("SRT Subtitle Parser Element"),
+    )
+});
+
+#[derive(Debug, Clone, Default)]
+struct Settings {
+    encoding: Option<String>,
+}
+
+struct State {
+    decoder: Option<encoding_rs::Decoder>,
+    reader: CueReader,
+    markup: Option<bool>,
+    need_segment: bool,
+    segment: gst::FormattedSegment<gst::ClockTime>,
+    last_position: Option<gst::ClockTime>,
+    discont: bool,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            decoder: None,
+            reader: CueReader::default(),
+            markup: None,
+            need_segment: true,
+            segment: gst::FormattedSegment::new(),
+            last_position: None,
+            discont: true,
+        }
+    }
+}
+
+pub struct SrtParse {
+    srcpad: gst::Pad,
+    sinkpad: gst::Pad,
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+}
+
+impl SrtParse {
+    fn create_decoder(&self, data: &[u8]) -> Result<encoding_rs::Decoder, gst::FlowError> {
+        let configured = self.settings.lock().unwrap().encoding.clone();
+
+        let encoding = if let Some((encoding, _)) = Encoding::for_bom(data) {
+            gst::debug!(CAT, imp: self, "Using encoding {} from BOM", encoding.name());
+            encoding
+        } else if let Some(label) = configured {
+            Encoding::for_label(label.as_bytes()).ok_or_else(|| {
+                gst::element_imp_error!(
+                    self,
+                    gst::StreamError::Format,
+                    ["Unknown encoding {}", label]
+                );
+                gst::FlowError::NotSupported
+            })?
+        } else if std::str::from_utf8(data)
+            .map(|_| true)
+            .unwrap_or_else(|err| err.error_len().is_none())
+        {
+            // Valid UTF-8, except for possibly a character cut off at the end
+            encoding_rs::UTF_8
+        } else {
+            let mut detector = chardetng::EncodingDetector::new();
+            detector.feed(data, false);
+            let encoding = detector.guess(None, true);
+            gst::debug!(CAT, imp: self, "Detected encoding {}", encoding.name());
+            encoding
+        };
+
+        Ok(encoding.new_decoder())
+    }
+
+    fn decode(&self, state: &mut State, data: &[u8], last: bool) {
+        let Some(decoder) = state.decoder.as_mut() else {
+            return;
+        };
+
+        let capacity = decoder
+            .max_utf8_buffer_length(data.len())
+            .unwrap_or(data.len() * 3 + 16);
+        let mut text = String::with_capacity(capacity);
+        let (_res, _read, had_errors) = decoder.decode_to_string(data, &mut text, last);
+
+        if had_errors {
+            gst::warning!(
+                CAT,
+                imp: self,
+                "Invalid {} data, replaced by replacement characters",
+                decoder.encoding().name()
+            );
+        }
+
+        state.reader.push_str(&text);
+    }
+
+    fn negotiate(&self, state: &mut State) -> Result<gst::Event, gst::FlowError> {
+        let mut caps = self
+            .srcpad
+            .allowed_caps()
+            .unwrap_or_else(|| self.srcpad.pad_template_caps());
+
+        if caps.is_empty() {
+            gst::error!(CAT, imp: self, "Empty downstream caps");
+            return Err(gst::FlowError::NotNegotiated);
+        }
+
+        caps.fixate();
+
+        let markup = caps
+            .structure(0)
+            .and_then(|s| s.get::<&str>("format").ok())
+            .map_or(true, |format| format == "pango-markup");
+
+        let caps = gst::Caps::builder("text/x-raw")
+            .field("format", if markup { "pango-markup" } else { "utf8" })
+            .build();
+
+        gst::debug!(CAT, imp: self, "Negotiated caps {caps}");
+
+        state.markup = Some(markup);
+
+        Ok(gst::event::Caps::new(&caps))
+    }
+
+    fn push_cues(
+        &self,
+        mut state: MutexGuard<State>,
+        drain: bool,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        while let Some(cue) = state.reader.next_cue(drain) {
+            gst::log!(CAT, imp: self, "Parsed cue {cue:?}");
+
+            let mut events = vec![];
+            if state.markup.is_none() || self.srcpad.check_reconfigure() {
+                events.push(self.negotiate(&mut state)?);
+            }
+            let markup = state.markup.unwrap();
+
+            if state.need_segment {
+                events.push(gst::event::Segment::new(&state.segment));
+                state.need_segment = false;
+                // Fill the time until the first cue with a gap
+                if state.last_position.is_none() {
+                    state.last_position = state.segment.start();
+                }
+            }
+
+            if let Some(last_position) = state.last_position {
+                if let Some(duration) = cue.start.checked_sub(last_position) {
+                    if !duration.is_zero() {
+                        events.push(
+                            gst::event::Gap::builder(last_position)
+                                .duration(duration)
+                                .build(),
+                        );
+                    }
+                }
+            }
+
+            let text = convert_text(&cue.text, markup);
+            let mut buffer = gst::Buffer::from_mut_slice(text.into_bytes());
+            {
+                let buffer = buffer.get_mut().unwrap();
+                buffer.set_pts(cue.start);
+                buffer.set_duration(cue.end - cue.start);
+                if state.discont {
+                    buffer.set_flags(gst::BufferFlags::DISCONT);
+                    state.discont = false;
+                }
+            }
+
+            state.last_position = Some(
+                state
+                    .last_position
+                    .map_or(cue.end, |last_position| last_position.max(cue.end)),
+            );
+
+            drop(state);
+
+            for event in events {
+                gst::debug!(CAT, imp: self, "Pushing event {event:?}");
+                self.srcpad.push_event(event);
+            }
+
+            self.srcpad.push(buffer)?;
+
+            state = self.state.lock().unwrap();
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    fn sink_chain(
+        &self,
+        pad: &gst::Pad,
+        buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::log!(CAT, obj: pad, "Handling buffer {:?}", buffer);
+
+        let map = buffer.map_readable().map_err(|_| {
+            gst::element_imp_error!(
+                self,
+                gst::ResourceError::Read,
+                ["Failed to map buffer readable"]
+            );
+
+            gst::FlowError::Error
+        })?;
+
+        let mut state = self.state.lock().unwrap();
+
+        if state.decoder.is_none() {
+            state.decoder = Some(self.create_decoder(&map)?);
+        }
+
+        self.decode(&mut state, &map, false);
+
+        self.push_cues(state, false)
+    }
+
+    fn sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::log!(CAT, obj: pad, "Handling event {:?}", event);
+
+        match event.view() {
+            EventView::Caps(_) => {
+                // We send our own caps downstream once we have a cue
+                true
+            }
+            EventView::Segment(e) => {
+                let mut state = self.state.lock().unwrap();
+
+                // Upstream usually works in bytes, in which case we create our own segment
+                state.segment = match e.segment().downcast_ref::<gst::ClockTime>() {
+                    Some(segment) => segment.clone(),
+                    None => gst::FormattedSegment::new(),
+                };
+                state.need_segment = true;
+
+                true
+            }
+            EventView::FlushStop(_) => {
+                let mut state = self.state.lock().unwrap();
+                state.reader.clear();
+                state.decoder = state
+                    .decoder
+                    .take()
+                    .map(|decoder| decoder.encoding().new_decoder_without_bom_handling());
+                state.need_segment = true;
+                state.last_position = None;
+                state.discont = true;
+                drop(state);
+
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            EventView::Eos(_) => {
+                gst::log!(CAT, obj: pad, "Draining");
+
+                let mut state = self.state.lock().unwrap();
+                self.decode(&mut state, &[], true);
+                if let Err(err) = self.push_cues(state, true) {
+                    gst::debug!(CAT, obj: pad, "Failed to push remaining cues: {err:?}");
+                }
+
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            _ => gst::Pad::event_default(pad, Some(&*self.obj()), event),
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for SrtParse {
+    const NAME: &'static str = "GstSrtParse";
+    type Type = super::SrtParse;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("sink").unwrap();
+        let sinkpad = gst::Pad::builder_from_template(&templ)
+            .chain_function(|pad, parent, buffer| {
+                SrtParse::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |parse| parse.sink_chain(pad, buffer),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                SrtParse::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |parse| parse.sink_event(pad, event),
+                )
+            })
+            .build();
+
+        let templ = klass.pad_template("src").unwrap();
+        let srcpad = gst::Pad::builder_from_template(&templ)
+            .flags(gst::PadFlags::FIXED_CAPS)
+            .build();
+
+        Self {
+            srcpad,
+            sinkpad,
+            settings: Mutex::new(Settings::default()),
+            state: Mutex::new(State::default()),
+        }
+    }
+}
+
+impl ObjectImpl for SrtParse {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![glib::ParamSpecString::builder("encoding")
+                .nick("Encoding")
+                .blurb(
+                    "Character encoding of the input if there is no byte order mark, \
+                     e.g. windows-1252 (NULL=detect)",
+                )
+                .mutable_ready()
+                .build()]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "encoding" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.encoding = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "encoding" => {
+                let settings = self.settings.lock().unwrap();
+                settings.encoding.to_value()
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.sinkpad).unwrap();
+        obj.add_pad(&self.srcpad).unwrap();
+    }
+}
+
+impl GstObjectImpl for SrtParse {}
+
+impl ElementImpl for SrtParse {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "SRT Subtitle Parser",
+                "Codec/Parser/Subtitle",
+                "Parses SubRip subtitles into timed text",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst::Caps::builder("text/x-raw")
+                .field("format", gst::List::new(["pango-markup", "utf8"]))
+                .build();
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let caps = gst::Caps::builder("application/x-subtitle").build();
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        gst::trace!(CAT, imp: self, "Changing state {:?}", transition);
+
+        if let gst::StateChange::ReadyToPaused | gst::StateChange::PausedToReady = transition {
+            *self.state.lock().unwrap() = State::default();
+        }
+
+        self.parent_change_state(transition)
+    }
+}
@@ -0,0 +1,43 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * element-srtparse:
+ *
+ * `srtparse` parses SubRip (`.srt`) subtitle files into timed `text/x-raw` buffers.
+ *
+ * The character encoding is taken from a byte order mark if present, otherwise it is
+ * detected from the data unless configured via the `encoding` property. Basic formatting
+ * tags (`<b>`, `<i>`, `<u>`, `<s>` and `<font>`) are converted to Pango markup, or
+ * removed if downstream only accepts plain UTF-8.
+ *
+ * ## Example pipeline
+ * ```bash
+ * gst-launch-1.0 filesrc location=input.srt ! srtparse ! fakesink dump=true
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+mod parser;
+
+glib::wrapper! {
+    pub struct SrtParse(ObjectSubclass<imp::SrtParse>) @extends gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "srtparse",
+        gst::Rank::NONE,
+        SrtParse::static_type(),
+    )
+}
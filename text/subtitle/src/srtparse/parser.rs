@@ -0,0 +1,408 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::mem;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cue {
+    pub start: gst::ClockTime,
+    pub end: gst::ClockTime,
+    pub text: String,
+}
+
+/// Parses a timestamp of the form `HH:MM:SS,mmm`.
+///
+/// Dots instead of commas, missing hours and fractional parts with fewer than three digits
+/// are accepted too as they are commonly found in the wild.
+pub fn parse_timestamp(s: &str) -> Option<gst::ClockTime> {
+    let s = s.trim();
+    let (hms, frac) = match s.find([',', '.']) {
+        Some(idx) => (&s[..idx], &s[idx + 1..]),
+        None => (s, ""),
+    };
+
+    let parts = hms
+        .split(':')
+        .map(|p| p.trim().parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    let (hours, minutes, seconds) = match parts[..] {
+        [h, m, s] => (h, m, s),
+        [m, s] => (0, m, s),
+        _ => return None,
+    };
+    if minutes >= 60 || seconds >= 60 {
+        return None;
+    }
+
+    if !frac.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let frac = &frac[..frac.len().min(3)];
+    let millis = if frac.is_empty() {
+        0
+    } else {
+        frac.parse::<u64>().ok()? * 10u64.pow(3 - frac.len() as u32)
+    };
+
+    Some(gst::ClockTime::from_mseconds(
+        ((hours * 60 + minutes) * 60 + seconds) * 1000 + millis,
+    ))
+}
+
+/// Parses a `start --> end` line, ignoring any coordinates following the end timestamp.
+pub fn parse_timing_line(line: &str) -> Option<(gst::ClockTime, gst::ClockTime)> {
+    let (start, rest) = line.split_once("-->")?;
+    let end = rest.split_whitespace().next()?;
+
+    Some((parse_timestamp(start)?, parse_timestamp(end)?))
+}
+
+/// Parses the lines of a single cue, without the separating blank line.
+pub fn parse_cue(lines: &[String]) -> Option<Cue> {
+    // Anything before the timing line is the cue number, which is not needed
+    let timing_idx = lines.iter().position(|l| l.contains("-->"))?;
+    let (start, end) = parse_timing_line(&lines[timing_idx])?;
+
+    Some(Cue {
+        start,
+        end: end.max(start),
+        text: lines[timing_idx + 1..].join("\n"),
+    })
+}
+
+/// Splits decoded SRT text into cues, independent of how the input is chunked.
+#[derive(Debug, Default)]
+pub struct CueReader {
+    pending: String,
+    block: Vec<String>,
+}
+
+impl CueReader {
+    pub fn push_str(&mut self, s: &str) {
+        self.pending.extend(s.chars().filter(|c| *c != '\r'));
+    }
+
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        self.block.clear();
+    }
+
+    /// Returns the next complete cue. With `drain` the remaining data is considered
+    /// complete, e.g. at EOS.
+    pub fn next_cue(&mut self, drain: bool) -> Option<Cue> {
+        loop {
+            let line = match self.pending.find('\n') {
+                Some(pos) => {
+                    let line = self.pending[..pos].to_string();
+                    self.pending.drain(..=pos);
+                    line
+                }
+                None if drain && !self.pending.is_empty() => mem::take(&mut self.pending),
+                None if drain && !self.block.is_empty() => {
+                    return parse_cue(&mem::take(&mut self.block));
+                }
+                None => return None,
+            };
+
+            if !line.trim().is_empty() {
+                self.block.push(line);
+                continue;
+            }
+
+            if self.block.is_empty() {
+                continue;
+            }
+
+            if let Some(cue) = parse_cue(&mem::take(&mut self.block)) {
+                return Some(cue);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tag {
+    Bold,
+    Italic,
+    Underline,
+    Strikethrough,
+    Font,
+}
+
+impl Tag {
+    fn from_name(name: &str) -> Option<Tag> {
+        match name.to_ascii_lowercase().as_str() {
+            "b" => Some(Tag::Bold),
+            "i" => Some(Tag::Italic),
+            "u" => Some(Tag::Underline),
+            "s" => Some(Tag::Strikethrough),
+            "font" => Some(Tag::Font),
+            _ => None,
+        }
+    }
+
+    fn closing_markup(self) -> &'static str {
+        match self {
+            Tag::Bold => "</b>",
+            Tag::Italic => "</i>",
+            Tag::Underline => "</u>",
+            Tag::Strikethrough => "</s>",
+            Tag::Font => "</span>",
+        }
+    }
+}
+
+fn escape_markup(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+}
+
+/// Parses `key="value"` / `key=value` pairs of a `<font>` tag.
+fn font_attributes(attrs: &str) -> Vec<(String, String)> {
+    let mut res = Vec::new();
+    let mut rest = attrs.trim();
+
+    while let Some((key, value)) = rest.split_once('=') {
+        let key = key.trim().to_ascii_lowercase();
+        let value = value.trim_start();
+        let (value, remaining) = if let Some(value) = value.strip_prefix('"') {
+            value.split_once('"').unwrap_or((value, ""))
+        } else if let Some(value) = value.strip_prefix('\'') {
+            value.split_once('\'').unwrap_or((value, ""))
+        } else {
+            value.split_once(char::is_whitespace).unwrap_or((value, ""))
+        };
+
+        res.push((key, value.to_string()));
+        rest = remaining.trim_start();
+    }
+
+    res
+}
+
+fn font_markup(out: &mut String, attrs: &str) {
+    out.push_str("<span");
+    for (key, value) in font_attributes(attrs) {
+        let attr = match key.as_str() {
+            "color" => "foreground",
+            "face" => "font_family",
+            _ => continue,
+        };
+
+        out.push(' ');
+        out.push_str(attr);
+        out.push_str("=\"");
+        escape_markup(out, &value);
+        out.push('"');
+    }
+    out.push('>');
+}
+
+/// Converts the basic HTML-like formatting tags of SRT to Pango markup, or removes them if
+/// `markup` is false. Unknown tags are kept as text and `{\...}` override blocks as
+/// produced by some authoring tools are dropped.
+pub fn convert_text(text: &str, markup: bool) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut stack: Vec<Tag> = Vec::new();
+    let mut rest = text;
+
+    let push_text = |out: &mut String, text: &str| {
+        if markup {
+            escape_markup(out, text);
+        } else {
+            out.push_str(text);
+        }
+    };
+
+    while !rest.is_empty() {
+        let Some(idx) = rest.find(['<', '{']) else {
+            push_text(&mut out, rest);
+            break;
+        };
+
+        push_text(&mut out, &rest[..idx]);
+        rest = &rest[idx..];
+
+        if rest.starts_with("{\\") {
+            if let Some(end) = rest.find('}') {
+                rest = &rest[end + 1..];
+                continue;
+            }
+        }
+
+        let tag = rest
+            .find('>')
+            .filter(|_| rest.starts_with('<'))
+            .and_then(|end| {
+                let content = rest[1..end].trim();
+                let (closing, content) = match content.strip_prefix('/') {
+                    Some(content) => (true, content.trim()),
+                    None => (false, content),
+                };
+                let (name, attrs) = content
+                    .split_once(char::is_whitespace)
+                    .unwrap_or((content, ""));
+
+                Tag::from_name(name).map(|tag| (end, closing, tag, attrs))
+            });
+
+        let Some((end, closing, tag, attrs)) = tag else {
+            // Not a tag we know about, output as is
+            push_text(&mut out, &rest[..1]);
+            rest = &rest[1..];
+            continue;
+        };
+
+        if closing {
+            if let Some(pos) = stack.iter().rposition(|t| *t == tag) {
+                for tag in stack.drain(pos..).rev() {
+                    if markup {
+                        out.push_str(tag.closing_markup());
+                    }
+                }
+            }
+        } else {
+            if markup {
+                match tag {
+                    Tag::Bold => out.push_str("<b>"),
+                    Tag::Italic => out.push_str("<i>"),
+                    Tag::Underline => out.push_str("<u>"),
+                    Tag::Strikethrough => out.push_str("<s>"),
+                    Tag::Font => font_markup(&mut out, attrs),
+                }
+            }
+            stack.push(tag);
+        }
+
+        rest = &rest[end + 1..];
+    }
+
+    if markup {
+        for tag in stack.into_iter().rev() {
+            out.push_str(tag.closing_markup());
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamp() {
+        assert_eq!(
+            parse_timestamp("01:02:03,456"),
+            Some(gst::ClockTime::from_mseconds(3_723_456))
+        );
+        assert_eq!(
+            parse_timestamp(" 00:00:01.5 "),
+            Some(gst::ClockTime::from_mseconds(1_500))
+        );
+        assert_eq!(
+            parse_timestamp("02:03"),
+            Some(gst::ClockTime::from_seconds(123))
+        );
+        assert_eq!(parse_timestamp("00:61:00,000"), None);
+        assert_eq!(parse_timestamp("00:00:00,abc"), None);
+        assert_eq!(parse_timestamp("foo"), None);
+    }
+
+    #[test]
+    fn test_timing_line() {
+        assert_eq!(
+            parse_timing_line("00:00:01,000 --> 00:00:02,500 X1:40 X2:600 Y1:20 Y2:50"),
+            Some((
+                gst::ClockTime::from_seconds(1),
+                gst::ClockTime::from_mseconds(2_500)
+            ))
+        );
+        assert_eq!(parse_timing_line("00:00:01,000 00:00:02,500"), None);
+    }
+
+    #[test]
+    fn test_reader() {
+        let mut reader = CueReader::default();
+
+        reader.push_str("1\r\n00:00:01,000 --> 00:00:02,000\r\nHello\r\n");
+        assert_eq!(reader.next_cue(false), None);
+        reader.push_str("World\r\n\r\n\r\n2\r\n00:00:03,000 --> 00:00:04,000\r\n");
+        assert_eq!(
+            reader.next_cue(false),
+            Some(Cue {
+                start: gst::ClockTime::from_seconds(1),
+                end: gst::ClockTime::from_seconds(2),
+                text: "Hello\nWorld".into(),
+            })
+        );
+        reader.push_str("Last");
+        assert_eq!(reader.next_cue(false), None);
+        assert_eq!(
+            reader.next_cue(true),
+            Some(Cue {
+                start: gst::ClockTime::from_seconds(3),
+                end: gst::ClockTime::from_seconds(4),
+                text: "Last".into(),
+            })
+        );
+        assert_eq!(reader.next_cue(true), None);
+    }
+
+    #[test]
+    fn test_reader_skips_invalid() {
+        let mut reader = CueReader::default();
+
+        reader.push_str("garbage\n\n1\n00:00:01,000 --> 00:00:02,000\nText\n\n");
+        assert_eq!(
+            reader.next_cue(false),
+            Some(Cue {
+                start: gst::ClockTime::from_seconds(1),
+                end: gst::ClockTime::from_seconds(2),
+                text: "Text".into(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_markup() {
+        assert_eq!(
+            convert_text("<i>Hello</i> & <B>bye</b>", true),
+            "<i>Hello</i> &amp; <b>bye</b>"
+        );
+        assert_eq!(
+            convert_text("<font color=\"#ff0000\" face=Serif>red</font>", true),
+            "<span foreground=\"#ff0000\" font_family=\"Serif\">red</span>"
+        );
+        // Unclosed and misnested tags are fixed up
+        assert_eq!(convert_text("<b><i>x</b>y", true), "<b><i>x</i></b>y");
+        assert_eq!(convert_text("<u>x", true), "<u>x</u>");
+        assert_eq!(convert_text("x</i>", true), "x");
+        // Unknown tags and override blocks
+        assert_eq!(convert_text("a <3 b", true), "a &lt;3 b");
+        assert_eq!(convert_text("{\\an8}Top", true), "Top");
+        assert_eq!(convert_text("{b>x", true), "{b&gt;x");
+    }
+
+    #[test]
+    fn test_strip() {
+        assert_eq!(
+            convert_text("<i>Hello</i> & <font color=red>bye</font>", false),
+            "Hello & bye"
+        );
+        assert_eq!(convert_text("a <3 b", false), "a <3 b");
+    }
+}
@@ -0,0 +1,367 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+use once_cell::sync::Lazy;
+
+use std::collections::VecDeque;
+use std::sync::{Mutex, MutexGuard};
+
+use super::ttml::{document_end, parse_document, Cue};
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "rsttmlparse",
+        gst::DebugColorFlags::empty(),
+        Some("TTML Subtitle Parser Element"),
+    )
+});
+
+struct State {
+    data: Vec<u8>,
+    cues: VecDeque<Cue>,
+    markup: Option<bool>,
+    need_segment: bool,
+    segment: gst::FormattedSegment<gst::ClockTime>,
+    last_position: Option<gst::ClockTime>,
+    discont: bool,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            data: vec![],
+            cues: VecDeque::new(),
+            markup: None,
+            need_segment: true,
+            segment: gst::FormattedSegment::new(),
+            last_position: None,
+            discont: true,
+        }
+    }
+}
+
+pub struct TtmlParse {
+    srcpad: gst::Pad,
+    sinkpad: gst::Pad,
+    state: Mutex<State>,
+}
+
+impl TtmlParse {
+    fn negotiate(&self, state: &mut State) -> Result<gst::Event, gst::FlowError> {
+        let mut caps = self
+            .srcpad
+            .allowed_caps()
+            .unwrap_or_else(|| self.srcpad.pad_template_caps());
+
+        if caps.is_empty() {
+            gst::error!(CAT, imp: self, "Empty downstream caps");
+            return Err(gst::FlowError::NotNegotiated);
+        }
+
+        caps.fixate();
+
+        let markup = caps
+            .structure(0)
+            .and_then(|s| s.get::<&str>("format").ok())
+            .map_or(true, |format| format == "pango-markup");
+
+        let caps = gst::Caps::builder("text/x-raw")
+            .field("format", if markup { "pango-markup" } else { "utf8" })
+            .build();
+
+        gst::debug!(CAT, imp: self, "Negotiated caps {caps}");
+
+        state.markup = Some(markup);
+
+        Ok(gst::event::Caps::new(&caps))
+    }
+
+    fn parse(&self, state: &mut State, data: &[u8]) {
+        let Ok(text) = std::str::from_utf8(data) else {
+            gst::element_imp_warning!(
+                self,
+                gst::StreamError::Decode,
+                ["Dropping document that is not valid UTF-8"]
+            );
+            return;
+        };
+
+        // The XML declaration must be at the very start of the document
+        match parse_document(text.trim_start()) {
+            Ok(cues) => {
+                gst::debug!(CAT, imp: self, "Parsed document with {} cues", cues.len());
+                state.cues.extend(cues);
+            }
+            Err(err) => {
+                gst::element_imp_warning!(
+                    self,
+                    gst::StreamError::Decode,
+                    ["Dropping invalid document: {}", err]
+                );
+            }
+        }
+    }
+
+    fn push_cues(&self, mut state: MutexGuard<State>) -> Result<gst::FlowSuccess, gst::FlowError> {
+        while let Some(cue) = state.cues.pop_front() {
+            gst::log!(CAT, imp: self, "Outputting cue {cue:?}");
+
+            let mut events = vec![];
+            if state.markup.is_none() || self.srcpad.check_reconfigure() {
+                events.push(self.negotiate(&mut state)?);
+            }
+            let text = if state.markup.unwrap() {
+                cue.markup
+            } else {
+                cue.text
+            };
+
+            if state.need_segment {
+                events.push(gst::event::Segment::new(&state.segment));
+                state.need_segment = false;
+                // Fill the time until the first cue with a gap
+                if state.last_position.is_none() {
+                    state.last_position = state.segment.start();
+                }
+            }
+
+            if let Some(last_position) = state.last_position {
+                if let Some(duration) = cue.start.checked_sub(last_position) {
+                    if !duration.is_zero() {
+                        events.push(
+                            gst::event::Gap::builder(last_position)
+                                .duration(duration)
+                                .build(),
+                        );
+                    }
+                }
+            }
+
+            let mut buffer = gst::Buffer::from_mut_slice(text.into_bytes());
+            {
+                let buffer = buffer.get_mut().unwrap();
+                buffer.set_pts(cue.start);
+                buffer.set_duration(cue.end - cue.start);
+                if state.discont {
+                    buffer.set_flags(gst::BufferFlags::DISCONT);
+                    state.discont = false;
+                }
+            }
+
+            state.last_position = Some(
+                state
+                    .last_position
+                    .map_or(cue.end, |last_position| last_position.max(cue.end)),
+            );
+
+            drop(state);
+
+            for event in events {
+                gst::debug!(CAT, imp: self, "Pushing event {event:?}");
+                self.srcpad.push_event(event);
+            }
+
+            self.srcpad.push(buffer)?;
+
+            state = self.state.lock().unwrap();
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    fn sink_chain(
+        &self,
+        pad: &gst::Pad,
+        buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::log!(CAT, obj: pad, "Handling buffer {:?}", buffer);
+
+        let map = buffer.map_readable().map_err(|_| {
+            gst::element_imp_error!(
+                self,
+                gst::ResourceError::Read,
+                ["Failed to map buffer readable"]
+            );
+
+            gst::FlowError::Error
+        })?;
+
+        let mut state = self.state.lock().unwrap();
+        state.data.extend_from_slice(&map);
+
+        while let Some(end) = document_end(&state.data) {
+            let data = state.data.drain(..end).collect::<Vec<_>>();
+            self.parse(&mut state, &data);
+        }
+
+        self.push_cues(state)
+    }
+
+    fn sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::log!(CAT, obj: pad, "Handling event {:?}", event);
+
+        match event.view() {
+            EventView::Caps(_) => {
+                // We send our own caps downstream once we have a cue
+                true
+            }
+            EventView::Segment(e) => {
+                let mut state = self.state.lock().unwrap();
+
+                // Upstream usually works in bytes, in which case we create our own segment
+                state.segment = match e.segment().downcast_ref::<gst::ClockTime>() {
+                    Some(segment) => segment.clone(),
+                    None => gst::FormattedSegment::new(),
+                };
+                state.need_segment = true;
+
+                true
+            }
+            EventView::FlushStop(_) => {
+                let mut state = self.state.lock().unwrap();
+                state.data.clear();
+                state.cues.clear();
+                state.need_segment = true;
+                state.last_position = None;
+                state.discont = true;
+                drop(state);
+
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            EventView::Eos(_) => {
+                gst::log!(CAT, obj: pad, "Draining");
+
+                let mut state = self.state.lock().unwrap();
+                let data = std::mem::take(&mut state.data);
+                if !data.iter().all(u8::is_ascii_whitespace) {
+                    // Incomplete documents are reported as invalid
+                    self.parse(&mut state, &data);
+                }
+
+                if let Err(err) = self.push_cues(state) {
+                    gst::debug!(CAT, obj: pad, "Failed to push remaining cues: {err:?}");
+                }
+
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            _ => gst::Pad::event_default(pad, Some(&*self.obj()), event),
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for TtmlParse {
+    const NAME: &'static str = "GstRsTtmlParse";
+    type Type = super::TtmlParse;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("sink").unwrap();
+        let sinkpad = gst::Pad::builder_from_template(&templ)
+            .chain_function(|pad, parent, buffer| {
+                TtmlParse::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |parse| parse.sink_chain(pad, buffer),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                TtmlParse::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |parse| parse.sink_event(pad, event),
+                )
+            })
+            .build();
+
+        let templ = klass.pad_template("src").unwrap();
+        let srcpad = gst::Pad::builder_from_template(&templ)
+            .flags(gst::PadFlags::FIXED_CAPS)
+            .build();
+
+        Self {
+            srcpad,
+            sinkpad,
+            state: Mutex::new(State::default()),
+        }
+    }
+}
+
+impl ObjectImpl for TtmlParse {
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.sinkpad).unwrap();
+        obj.add_pad(&self.srcpad).unwrap();
+    }
+}
+
+impl GstObjectImpl for TtmlParse {}
+
+impl ElementImpl for TtmlParse {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "TTML Subtitle Parser",
+                "Codec/Parser/Subtitle",
+                "Parses TTML and EBU-TT-D subtitles into timed text",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst::Caps::builder("text/x-raw")
+                .field("format", gst::List::new(["pango-markup", "utf8"]))
+                .build();
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let caps = gst::Caps::builder("application/ttml+xml").build();
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        gst::trace!(CAT, imp: self, "Changing state {:?}", transition);
+
+        if let gst::StateChange::ReadyToPaused | gst::StateChange::PausedToReady = transition {
+            *self.state.lock().unwrap() = State::default();
+        }
+
+        self.parent_change_state(transition)
+    }
+}
@@ -0,0 +1,46 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * element-rsttmlparse:
+ *
+ * `rsttmlparse` parses TTML documents, as used by EBU-TT-D and IMSC, into timed
+ * `text/x-raw` buffers. A new buffer is output whenever the displayed text changes.
+ *
+ * Styles referenced from content or regions are resolved and converted to Pango markup:
+ * colors, background colors, font families, italic, bold and underlined text. Text of
+ * different regions is ordered from top to bottom by the region origin. If downstream only
+ * accepts plain UTF-8 the styling is dropped.
+ *
+ * Multiple consecutive documents in the input are supported, e.g. for segmented streams.
+ *
+ * ## Example pipeline
+ * ```bash
+ * gst-launch-1.0 filesrc location=input.ttml ! rsttmlparse ! fakesink dump=true
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+mod ttml;
+
+glib::wrapper! {
+    pub struct TtmlParse(ObjectSubclass<imp::TtmlParse>) @extends gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "rsttmlparse",
+        gst::Rank::NONE,
+        TtmlParse::static_type(),
+    )
+}
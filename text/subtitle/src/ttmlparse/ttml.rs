@@ -0,0 +1,825 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use roxmltree::Node;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cue {
+    pub start: gst::ClockTime,
+    pub end: gst::ClockTime,
+    /// Plain text
+    pub text: String,
+    /// Text with Pango markup
+    pub markup: String,
+}
+
+/// Returns the position after the closing tag of the first complete document in `data`
+pub fn document_end(data: &[u8]) -> Option<usize> {
+    let mut pos = 0;
+
+    while let Some(offset) = data[pos..].windows(2).position(|w| w == b"</") {
+        let start = pos + offset + 2;
+        let end = start + data[start..].iter().position(|&b| b == b'>')?;
+
+        let name = data[start..end]
+            .split(|b| b.is_ascii_whitespace())
+            .next()
+            .unwrap_or_default();
+        let local_name = match name.iter().position(|&b| b == b':') {
+            Some(colon) => &name[colon + 1..],
+            None => name,
+        };
+
+        if local_name == b"tt" {
+            return Some(end + 1);
+        }
+
+        pos = end + 1;
+    }
+
+    None
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TimeBase {
+    frame_rate: f64,
+    tick_rate: f64,
+}
+
+impl TimeBase {
+    fn from_root(root: Node) -> Self {
+        let frame_rate = attribute(root, "frameRate")
+            .and_then(|rate| rate.trim().parse::<f64>().ok())
+            .filter(|rate| *rate > 0.0)
+            .unwrap_or(30.0);
+
+        let multiplier = attribute(root, "frameRateMultiplier")
+            .and_then(|multiplier| {
+                let (numer, denom) = multiplier.trim().split_once(char::is_whitespace)?;
+                Some(numer.trim().parse::<f64>().ok()? / denom.trim().parse::<f64>().ok()?)
+            })
+            .filter(|multiplier| multiplier.is_finite() && *multiplier > 0.0)
+            .unwrap_or(1.0);
+
+        let tick_rate = attribute(root, "tickRate")
+            .and_then(|rate| rate.trim().parse::<f64>().ok())
+            .filter(|rate| *rate > 0.0)
+            .unwrap_or(1.0);
+
+        TimeBase {
+            frame_rate: frame_rate * multiplier,
+            tick_rate,
+        }
+    }
+
+    /// Parses a clock time (`HH:MM:SS.mmm`, `HH:MM:SS:FF`) or offset time (`1.5s`, `10f`, ...)
+    fn parse(&self, s: &str) -> Option<gst::ClockTime> {
+        let s = s.trim();
+
+        let seconds = if s.contains(':') {
+            let parts = s.split(':').collect::<Vec<_>>();
+            let (hours, minutes, seconds, frames) = match parts.as_slice() {
+                [h, m, s] => (h, m, s, None),
+                [h, m, s, f] => (h, m, s, Some(f)),
+                _ => return None,
+            };
+
+            let hours = hours.parse::<u64>().ok()?;
+            let minutes = minutes.parse::<u64>().ok()?;
+            let seconds = seconds.parse::<f64>().ok()?;
+            // Sub-frames are ignored
+            let frames = match frames {
+                Some(frames) => frames.split('.').next()?.parse::<u64>().ok()? as f64,
+                None => 0.0,
+            };
+
+            (hours * 3600 + minutes * 60) as f64 + seconds + frames / self.frame_rate
+        } else {
+            let split = s.find(|c: char| c.is_ascii_alphabetic())?;
+            let (value, metric) = s.split_at(split);
+            let value = value.parse::<f64>().ok()?;
+
+            match metric {
+                "h" => value * 3600.0,
+                "m" => value * 60.0,
+                "s" => value,
+                "ms" => value / 1000.0,
+                "f" => value / self.frame_rate,
+                "t" => value / self.tick_rate,
+                _ => return None,
+            }
+        };
+
+        if !seconds.is_finite() || seconds < 0.0 {
+            return None;
+        }
+
+        Some(gst::ClockTime::from_nseconds(
+            (seconds * 1_000_000_000.0).round() as u64,
+        ))
+    }
+}
+
+/// Converts a TTML color to a Pango color and alpha value
+fn parse_color(color: &str) -> Option<(String, u8)> {
+    let color = color.trim();
+
+    if let Some(hex) = color.strip_prefix('#') {
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        return match hex.len() {
+            6 => Some((format!("#{hex}"), 0xff)),
+            8 => Some((
+                format!("#{}", &hex[..6]),
+                u8::from_str_radix(&hex[6..], 16).ok()?,
+            )),
+            _ => None,
+        };
+    }
+
+    if let Some(components) = color
+        .strip_prefix("rgba(")
+        .or_else(|| color.strip_prefix("rgb("))
+        .and_then(|components| components.strip_suffix(')'))
+    {
+        let components = components
+            .split(',')
+            .map(|c| c.trim().parse::<u8>().ok())
+            .collect::<Option<Vec<_>>>()?;
+
+        let (r, g, b, a) = match components.as_slice() {
+            [r, g, b] => (r, g, b, &0xff),
+            [r, g, b, a] => (r, g, b, a),
+            _ => return None,
+        };
+
+        return Some((format!("#{r:02x}{g:02x}{b:02x}"), *a));
+    }
+
+    match color {
+        "" => None,
+        "transparent" => Some(("black".to_string(), 0)),
+        name => Some((name.to_lowercase(), 0xff)),
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Style {
+    color: Option<String>,
+    background_color: Option<String>,
+    font_family: Option<String>,
+    italic: Option<bool>,
+    bold: Option<bool>,
+    underline: Option<bool>,
+}
+
+impl Style {
+    fn from_attributes(node: Node) -> Self {
+        let attr = |name: &str| attribute(node, name).map(str::trim);
+
+        Style {
+            color: attr("color").map(String::from),
+            background_color: attr("backgroundColor").map(String::from),
+            font_family: attr("fontFamily").map(String::from),
+            italic: attr("fontStyle").map(|v| v == "italic" || v == "oblique"),
+            bold: attr("fontWeight").map(|v| v == "bold"),
+            underline: attr("textDecoration")
+                .map(|v| v.split_whitespace().any(|v| v == "underline")),
+        }
+    }
+
+    fn apply(&mut self, other: &Style) {
+        if other.color.is_some() {
+            self.color.clone_from(&other.color);
+        }
+        if other.background_color.is_some() {
+            self.background_color.clone_from(&other.background_color);
+        }
+        if other.font_family.is_some() {
+            self.font_family.clone_from(&other.font_family);
+        }
+        self.italic = other.italic.or(self.italic);
+        self.bold = other.bold.or(self.bold);
+        self.underline = other.underline.or(self.underline);
+    }
+
+    fn markup_attributes(&self) -> String {
+        let mut attributes = String::new();
+
+        if let Some((color, alpha)) = self.color.as_deref().and_then(parse_color) {
+            write!(attributes, " foreground=\"{color}\"").unwrap();
+            if alpha != 0xff {
+                write!(
+                    attributes,
+                    " alpha=\"{}%\"",
+                    (alpha as u32 * 100 + 127) / 255
+                )
+                .unwrap();
+            }
+        }
+        if let Some((color, alpha)) = self.background_color.as_deref().and_then(parse_color) {
+            if alpha != 0 {
+                write!(attributes, " background=\"{color}\"").unwrap();
+                if alpha != 0xff {
+                    write!(
+                        attributes,
+                        " bgalpha=\"{}%\"",
+                        (alpha as u32 * 100 + 127) / 255
+                    )
+                    .unwrap();
+                }
+            }
+        }
+        if let Some(ref font_family) = self.font_family {
+            // Only use the first of a list of font families
+            let font_family = font_family.split(',').next().unwrap_or_default();
+            let font_family = font_family.trim().trim_matches(['"', '\'']);
+            let font_family = match font_family {
+                "default" | "proportionalSansSerif" => "sans-serif",
+                "proportionalSerif" => "serif",
+                "monospace" | "monospaceSansSerif" | "monospaceSerif" => "monospace",
+                font_family => font_family,
+            };
+            attributes.push_str(" font_family=\"");
+            escape_markup(&mut attributes, font_family);
+            attributes.push('"');
+        }
+        if self.italic == Some(true) {
+            attributes.push_str(" style=\"italic\"");
+        }
+        if self.bold == Some(true) {
+            attributes.push_str(" weight=\"bold\"");
+        }
+        if self.underline == Some(true) {
+            attributes.push_str(" underline=\"single\"");
+        }
+
+        attributes
+    }
+}
+
+fn escape_markup(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Region {
+    style: Style,
+    /// Vertical origin of the region in percent, used for ordering the text of regions
+    top: f64,
+}
+
+#[derive(Debug)]
+enum Content {
+    Text(String),
+    LineBreak,
+}
+
+#[derive(Debug)]
+struct Run {
+    begin: gst::ClockTime,
+    end: Option<gst::ClockTime>,
+    style: Style,
+    content: Content,
+}
+
+#[derive(Debug)]
+struct Paragraph {
+    top: f64,
+    runs: Vec<Run>,
+}
+
+/// Properties inherited from the parent element
+#[derive(Debug, Clone)]
+struct Inherited {
+    begin: gst::ClockTime,
+    end: Option<gst::ClockTime>,
+    style: Style,
+    region: Option<String>,
+}
+
+fn attribute<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    // Attributes are matched by their local name only, e.g. `tts:color` as `color`
+    node.attributes()
+        .find(|attr| attr.name() == name)
+        .map(|attr| attr.value())
+}
+
+fn local_name<'input>(node: Node<'_, 'input>) -> &'input str {
+    node.tag_name().name()
+}
+
+fn child_elements<'a, 'input>(node: Node<'a, 'input>) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children().filter(Node::is_element)
+}
+
+struct Document {
+    time_base: TimeBase,
+    styles: HashMap<String, Style>,
+    regions: HashMap<String, Region>,
+    paragraphs: Vec<Paragraph>,
+}
+
+impl Document {
+    fn resolve_style(id: &str, definitions: &HashMap<&str, Node>, depth: usize) -> Option<Style> {
+        let node = *definitions.get(id)?;
+
+        let mut style = Style::default();
+        // Guard against reference cycles
+        if depth < 8 {
+            for id in attribute(node, "style")
+                .map(str::split_whitespace)
+                .into_iter()
+                .flatten()
+            {
+                if let Some(referenced) = Self::resolve_style(id, definitions, depth + 1) {
+                    style.apply(&referenced);
+                }
+            }
+        }
+        style.apply(&Style::from_attributes(node));
+
+        Some(style)
+    }
+
+    fn referenced_styles(&self, node: Node) -> Style {
+        let mut style = Style::default();
+
+        for id in attribute(node, "style")
+            .map(str::split_whitespace)
+            .into_iter()
+            .flatten()
+        {
+            if let Some(referenced) = self.styles.get(id) {
+                style.apply(referenced);
+            }
+        }
+        style.apply(&Style::from_attributes(node));
+
+        style
+    }
+
+    fn parse_head(&mut self, head: Node) {
+        let mut definitions = HashMap::new();
+        for styling in child_elements(head).filter(|e| local_name(*e) == "styling") {
+            for style in child_elements(styling).filter(|e| local_name(*e) == "style") {
+                if let Some(id) = attribute(style, "id") {
+                    definitions.insert(id, style);
+                }
+            }
+        }
+
+        for id in definitions.keys() {
+            if let Some(style) = Self::resolve_style(id, &definitions, 0) {
+                self.styles.insert(id.to_string(), style);
+            }
+        }
+
+        for layout in child_elements(head).filter(|e| local_name(*e) == "layout") {
+            for region in child_elements(layout).filter(|e| local_name(*e) == "region") {
+                let Some(id) = attribute(region, "id") else {
+                    continue;
+                };
+
+                let top = attribute(region, "origin")
+                    .and_then(|origin| origin.split_whitespace().nth(1))
+                    .and_then(|top| top.strip_suffix('%'))
+                    .and_then(|top| top.parse::<f64>().ok())
+                    .unwrap_or(0.0);
+
+                // Only inheritable styles apply to the content, the region's styles can
+                // be given directly or inside nested style elements
+                let mut style = self.referenced_styles(region);
+                for nested in child_elements(region).filter(|e| local_name(*e) == "style") {
+                    style.apply(&Style::from_attributes(nested));
+                }
+                style.background_color = None;
+
+                self.regions.insert(id.to_string(), Region { style, top });
+            }
+        }
+    }
+
+    fn timing(&self, node: Node, parent: &Inherited) -> Inherited {
+        let attr = |name: &str| attribute(node, name).and_then(|t| self.time_base.parse(t));
+
+        let begin = parent.begin + attr("begin").unwrap_or(gst::ClockTime::ZERO);
+        let end = match (attr("end"), attr("dur")) {
+            (Some(end), _) => Some(parent.begin + end),
+            (None, Some(dur)) => Some(begin + dur),
+            (None, None) => parent.end,
+        };
+        // Children can't outlive their parents
+        let end = match (end, parent.end) {
+            (Some(end), Some(parent_end)) => Some(end.min(parent_end)),
+            (end, _) => end,
+        };
+
+        let region = attribute(node, "region")
+            .map(String::from)
+            .or_else(|| parent.region.clone());
+
+        let mut style = parent.style.clone();
+        if let Some(region) = attribute(node, "region").and_then(|region| self.regions.get(region))
+        {
+            style.apply(&region.style);
+        }
+        style.apply(&self.referenced_styles(node));
+
+        Inherited {
+            begin,
+            end,
+            style,
+            region,
+        }
+    }
+
+    fn parse_container(&mut self, node: Node, parent: &Inherited) {
+        let inherited = self.timing(node, parent);
+
+        for child in child_elements(node) {
+            match local_name(child) {
+                "div" => self.parse_container(child, &inherited),
+                "p" => {
+                    let inherited = self.timing(child, &inherited);
+                    let mut runs = vec![];
+                    self.parse_inline(child, &inherited, &mut runs);
+
+                    let top = inherited
+                        .region
+                        .as_ref()
+                        .and_then(|region| self.regions.get(region))
+                        .map_or(0.0, |region| region.top);
+
+                    self.paragraphs.push(Paragraph { top, runs });
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn parse_inline(&self, node: Node, inherited: &Inherited, runs: &mut Vec<Run>) {
+        for child in node.children() {
+            let content = if child.is_text() {
+                Content::Text(child.text().unwrap_or_default().to_string())
+            } else if child.has_tag_name("br") {
+                Content::LineBreak
+            } else {
+                if child.has_tag_name("span") {
+                    let inherited = self.timing(child, inherited);
+                    self.parse_inline(child, &inherited, runs);
+                }
+                continue;
+            };
+
+            runs.push(Run {
+                begin: inherited.begin,
+                end: inherited.end,
+                style: inherited.style.clone(),
+                content,
+            });
+        }
+    }
+}
+
+/// Renders the active runs of a paragraph, collapsing white space as TTML does by default
+fn render_paragraph<'a>(runs: impl Iterator<Item = &'a Run>, markup: bool) -> String {
+    let mut lines: Vec<Vec<(&Style, String)>> = vec![vec![]];
+
+    for run in runs {
+        match run.content {
+            Content::LineBreak => lines.push(vec![]),
+            Content::Text(ref text) => {
+                let line = lines.last_mut().unwrap();
+                let mut collapsed = String::with_capacity(text.len());
+
+                let mut previous_space = line
+                    .iter()
+                    .rev()
+                    .find_map(|(_, text)| text.chars().last())
+                    .map_or(true, |c| c == ' ');
+                for c in text.chars() {
+                    if c.is_whitespace() {
+                        if !previous_space {
+                            collapsed.push(' ');
+                        }
+                        previous_space = true;
+                    } else {
+                        collapsed.push(c);
+                        previous_space = false;
+                    }
+                }
+
+                line.push((&run.style, collapsed));
+            }
+        }
+    }
+
+    let mut out = String::new();
+    for (i, line) in lines.iter_mut().enumerate() {
+        // Remove trailing white space of the line
+        for (_, text) in line.iter_mut().rev() {
+            let len = text.trim_end().len();
+            text.truncate(len);
+            if len > 0 {
+                break;
+            }
+        }
+
+        if i > 0 {
+            out.push('\n');
+        }
+
+        for (style, text) in line.iter().filter(|(_, text)| !text.is_empty()) {
+            let attributes = if markup {
+                style.markup_attributes()
+            } else {
+                String::new()
+            };
+
+            if attributes.is_empty() {
+                if markup {
+                    escape_markup(&mut out, text);
+                } else {
+                    out.push_str(text);
+                }
+            } else {
+                write!(out, "<span{attributes}>").unwrap();
+                escape_markup(&mut out, text);
+                out.push_str("</span>");
+            }
+        }
+    }
+
+    out.trim_end_matches('\n').to_string()
+}
+
+/// Parses a TTML document into cues, one per change of the displayed text
+pub fn parse_document(text: &str) -> Result<Vec<Cue>, roxmltree::Error> {
+    let xml = roxmltree::Document::parse(text)?;
+    let root = xml.root_element();
+
+    let mut document = Document {
+        time_base: TimeBase::from_root(root),
+        styles: HashMap::new(),
+        regions: HashMap::new(),
+        paragraphs: vec![],
+    };
+
+    if let Some(head) = child_elements(root).find(|e| local_name(*e) == "head") {
+        document.parse_head(head);
+    }
+
+    let inherited = Inherited {
+        begin: gst::ClockTime::ZERO,
+        end: None,
+        style: Style::default(),
+        region: None,
+    };
+    if let Some(body) = child_elements(root).find(|e| local_name(*e) == "body") {
+        document.parse_container(body, &inherited);
+    }
+
+    // Content without an end is shown until the end of the document
+    let document_end = document
+        .paragraphs
+        .iter()
+        .flat_map(|p| &p.runs)
+        .map(|run| run.end.unwrap_or(run.begin))
+        .max()
+        .unwrap_or(gst::ClockTime::ZERO);
+    for run in document.paragraphs.iter_mut().flat_map(|p| &mut p.runs) {
+        run.end.get_or_insert(document_end);
+    }
+
+    // Paragraphs are ordered from top to bottom by their region, keeping the document order
+    // otherwise
+    document.paragraphs.sort_by(|a, b| {
+        a.top
+            .partial_cmp(&b.top)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let mut times = document
+        .paragraphs
+        .iter()
+        .flat_map(|p| &p.runs)
+        .flat_map(|run| [run.begin, run.end.unwrap()])
+        .collect::<Vec<_>>();
+    times.sort();
+    times.dedup();
+
+    let mut cues: Vec<Cue> = vec![];
+    for window in times.windows(2) {
+        let (start, end) = (window[0], window[1]);
+
+        let render = |markup| {
+            document
+                .paragraphs
+                .iter()
+                .map(|p| {
+                    render_paragraph(
+                        p.runs
+                            .iter()
+                            .filter(|run| run.begin <= start && run.end.unwrap() >= end),
+                        markup,
+                    )
+                })
+                .filter(|text| !text.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+
+        let text = render(false);
+        if text.is_empty() {
+            continue;
+        }
+        let markup = render(true);
+
+        match cues.last_mut() {
+            Some(cue) if cue.end == start && cue.markup == markup => cue.end = end,
+            _ => cues.push(Cue {
+                start,
+                end,
+                text,
+                markup,
+            }),
+        }
+    }
+
+    Ok(cues)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EBU_TT_D: &str = r##"<?xml version="1.0" encoding="UTF-8"?>
+<tt xmlns="http://www.w3.org/ns/ttml" xmlns:tts="http://www.w3.org/ns/ttml#styling"
+    xmlns:ttp="http://www.w3.org/ns/ttml#parameter" ttp:timeBase="media"
+    ttp:frameRate="25" xml:lang="en">
+  <head>
+    <styling>
+      <style xml:id="base" tts:fontFamily="proportionalSansSerif" tts:color="#ffffff"/>
+      <style xml:id="yellow" style="base" tts:color="#ffff00" tts:backgroundColor="#000000c2"/>
+      <style xml:id="italic" tts:fontStyle="italic"/>
+    </styling>
+    <layout>
+      <region xml:id="bottom" tts:origin="10% 80%" tts:extent="80% 15%" style="base"/>
+      <region xml:id="top" tts:origin="10% 5%" tts:extent="80% 15%"/>
+    </layout>
+  </head>
+  <body>
+    <div>
+      <p region="bottom" begin="00:00:01.000" end="00:00:03.000">
+        Hello
+        <span style="italic">world</span><br/>second  line
+      </p>
+      <p region="top" begin="00:00:02:00" end="00:00:04:00">
+        <span style="yellow">On &amp; top</span>
+      </p>
+    </div>
+  </body>
+</tt>"##;
+
+    #[test]
+    fn test_time() {
+        let time_base = TimeBase {
+            frame_rate: 25.0,
+            tick_rate: 10_000_000.0,
+        };
+
+        assert_eq!(
+            time_base.parse("01:02:03.5"),
+            Some(gst::ClockTime::from_mseconds(3_723_500))
+        );
+        assert_eq!(
+            time_base.parse("00:00:01:05"),
+            Some(gst::ClockTime::from_mseconds(1_200))
+        );
+        assert_eq!(
+            time_base.parse("1.5s"),
+            Some(gst::ClockTime::from_mseconds(1_500))
+        );
+        assert_eq!(
+            time_base.parse("250ms"),
+            Some(gst::ClockTime::from_mseconds(250))
+        );
+        assert_eq!(
+            time_base.parse("50f"),
+            Some(gst::ClockTime::from_seconds(2))
+        );
+        assert_eq!(
+            time_base.parse("20000000t"),
+            Some(gst::ClockTime::from_seconds(2))
+        );
+        assert_eq!(time_base.parse("1x"), None);
+        assert_eq!(time_base.parse("-1s"), None);
+    }
+
+    #[test]
+    fn test_document_end() {
+        let data = b"<tt:tt><tt:body></tt:body></tt:tt >next";
+        assert_eq!(document_end(data), Some(data.len() - 4));
+        assert_eq!(document_end(b"<tt><body></body>"), None);
+    }
+
+    #[test]
+    fn test_color() {
+        assert_eq!(parse_color("#FF0000"), Some(("#FF0000".to_string(), 0xff)));
+        assert_eq!(
+            parse_color("#ff000080"),
+            Some(("#ff0000".to_string(), 0x80))
+        );
+        assert_eq!(
+            parse_color("rgba(255, 0, 16, 0)"),
+            Some(("#ff0010".to_string(), 0))
+        );
+        assert_eq!(parse_color("Yellow"), Some(("yellow".to_string(), 0xff)));
+        assert_eq!(parse_color("#ff"), None);
+    }
+
+    #[test]
+    fn test_markup() {
+        let cues = parse_document(EBU_TT_D).unwrap();
+        let cues = cues
+            .iter()
+            .map(|cue| (cue.start.seconds(), cue.end.seconds(), cue.markup.as_str()))
+            .collect::<Vec<_>>();
+
+        let bottom = "<span foreground=\"#ffffff\" font_family=\"sans-serif\">Hello </span>\
+                      <span foreground=\"#ffffff\" font_family=\"sans-serif\" style=\"italic\">world</span>\n\
+                      <span foreground=\"#ffffff\" font_family=\"sans-serif\">second line</span>";
+        let top = "<span foreground=\"#ffff00\" background=\"#000000\" bgalpha=\"76%\" \
+                   font_family=\"sans-serif\">On &amp; top</span>";
+
+        assert_eq!(
+            cues,
+            [
+                (1, 2, bottom),
+                (2, 3, format!("{top}\n{bottom}").as_str()),
+                (3, 4, top),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plain() {
+        let cues = parse_document(EBU_TT_D).unwrap();
+
+        assert_eq!(
+            cues.iter().map(|cue| cue.text.as_str()).collect::<Vec<_>>(),
+            [
+                "Hello world\nsecond line",
+                "On & top\nHello world\nsecond line",
+                "On & top"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nested_timing() {
+        let document = r#"<tt xmlns="http://www.w3.org/ns/ttml">
+  <body begin="10s">
+    <div begin="1s" end="5s">
+      <p>Whole <span begin="2s" dur="1s">partial</span></p>
+      <p begin="3s" end="20s">Clipped</p>
+    </div>
+  </body>
+</tt>"#;
+
+        let cues = parse_document(document).unwrap();
+        let cues = cues
+            .iter()
+            .map(|cue| (cue.start.seconds(), cue.end.seconds(), cue.text.as_str()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            cues,
+            [
+                (11, 13, "Whole"),
+                (13, 14, "Whole partial"),
+                (14, 15, "Whole\nClipped"),
+            ]
+        );
+    }
+}
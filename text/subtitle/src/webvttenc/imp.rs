@@ -0,0 +1,543 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+use once_cell::sync::Lazy;
+
+use std::sync::Mutex;
+
+use super::writer::{cue_text, write_cue, write_header, Cue};
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "rswebvttenc",
+        gst::DebugColorFlags::empty(),
+        Some("WebVTT Encoder Element"),
+    )
+});
+
+const DEFAULT_SEGMENT_DURATION: gst::ClockTime = gst::ClockTime::from_seconds(15);
+const DEFAULT_TIMESTAMP_MAP: bool = true;
+const DEFAULT_MPEGTS_OFFSET: u64 = 0;
+
+#[derive(Debug, Clone)]
+struct Settings {
+    segment_duration: gst::ClockTime,
+    timestamp_map: bool,
+    mpegts_offset: u64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            segment_duration: DEFAULT_SEGMENT_DURATION,
+            timestamp_map: DEFAULT_TIMESTAMP_MAP,
+            mpegts_offset: DEFAULT_MPEGTS_OFFSET,
+        }
+    }
+}
+
+impl Settings {
+    fn header(&self) -> String {
+        let mut header = String::new();
+        write_header(
+            &mut header,
+            self.timestamp_map.then_some(self.mpegts_offset),
+        );
+        header
+    }
+}
+
+struct State {
+    segment: gst::FormattedSegment<gst::ClockTime>,
+    markup: bool,
+    /// Cues not completely output yet, in running time
+    cues: Vec<Cue>,
+    /// Running time of the next WebVTT segment
+    output_start: Option<gst::ClockTime>,
+    /// Running time up to which the input is known
+    last_position: Option<gst::ClockTime>,
+    need_header: bool,
+    discont: bool,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            segment: gst::FormattedSegment::new(),
+            markup: false,
+            cues: vec![],
+            output_start: None,
+            last_position: None,
+            need_header: true,
+            discont: true,
+        }
+    }
+}
+
+pub struct WebVttEnc {
+    srcpad: gst::Pad,
+    sinkpad: gst::Pad,
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+}
+
+impl WebVttEnc {
+    fn output_segment(
+        &self,
+        state: &mut State,
+        settings: &Settings,
+        start: gst::ClockTime,
+        duration: gst::ClockTime,
+    ) -> gst::Buffer {
+        let end = start + duration;
+
+        let mut text = settings.header();
+        for cue in state
+            .cues
+            .iter()
+            .filter(|cue| cue.start < end && cue.end > start)
+        {
+            write_cue(&mut text, cue);
+        }
+        // Cues spanning the segment boundary are repeated in the next segment
+        state.cues.retain(|cue| cue.end > end);
+
+        gst::debug!(
+            CAT,
+            imp: self,
+            "Outputting segment at {start} with duration {duration}"
+        );
+
+        let mut buffer = gst::Buffer::from_mut_slice(text.into_bytes());
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(state.segment.position_from_running_time(start));
+            buffer.set_duration(duration);
+            if state.discont {
+                buffer.set_flags(gst::BufferFlags::DISCONT);
+                state.discont = false;
+            }
+        }
+
+        buffer
+    }
+
+    /// Outputs all segments that end before `position`
+    fn advance(
+        &self,
+        state: &mut State,
+        settings: &Settings,
+        position: gst::ClockTime,
+    ) -> Vec<gst::Buffer> {
+        let duration = settings.segment_duration;
+
+        // Align the segments to multiples of the segment duration
+        let mut start = state.output_start.unwrap_or_else(|| {
+            gst::ClockTime::from_nseconds(
+                position.nseconds() - position.nseconds() % duration.nseconds(),
+            )
+        });
+
+        let mut buffers = vec![];
+        while start + duration <= position {
+            buffers.push(self.output_segment(state, settings, start, duration));
+            start += duration;
+        }
+
+        state.output_start = Some(start);
+        state.last_position = state.last_position.max(Some(position));
+
+        buffers
+    }
+
+    /// Outputs all remaining cues, with a shorter last segment if needed
+    fn drain(&self, state: &mut State, settings: &Settings) -> Vec<gst::Buffer> {
+        let Some(end) = state.last_position else {
+            return vec![];
+        };
+
+        let mut buffers = self.advance(state, settings, end);
+
+        let start = state.output_start.unwrap();
+        if start < end {
+            buffers.push(self.output_segment(state, settings, start, end - start));
+            state.output_start = Some(end);
+        }
+
+        buffers
+    }
+
+    fn push_buffers(&self, buffers: Vec<gst::Buffer>) -> Result<gst::FlowSuccess, gst::FlowError> {
+        for buffer in buffers {
+            self.srcpad.push(buffer)?;
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    fn sink_chain(
+        &self,
+        pad: &gst::Pad,
+        buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::log!(CAT, obj: pad, "Handling buffer {:?}", buffer);
+
+        let (Some(pts), Some(duration)) = (buffer.pts(), buffer.duration()) else {
+            gst::warning!(CAT, obj: pad, "Dropping buffer without timestamp or duration");
+            return Ok(gst::FlowSuccess::Ok);
+        };
+
+        let text = {
+            let map = buffer.map_readable().map_err(|_| {
+                gst::element_imp_error!(
+                    self,
+                    gst::ResourceError::Read,
+                    ["Failed to map buffer readable"]
+                );
+
+                gst::FlowError::Error
+            })?;
+
+            std::str::from_utf8(&map)
+                .map_err(|err| {
+                    gst::element_imp_error!(
+                        self,
+                        gst::StreamError::Format,
+                        ["Invalid UTF-8: {}", err]
+                    );
+
+                    gst::FlowError::Error
+                })?
+                .to_string()
+        };
+
+        let settings = self.settings.lock().unwrap().clone();
+        let mut state = self.state.lock().unwrap();
+
+        // Cue timestamps are in running time
+        let running_time = state
+            .segment
+            .clip(pts, pts + duration)
+            .and_then(|(start, end)| {
+                Some((
+                    state.segment.to_running_time(start?)?,
+                    state.segment.to_running_time(end?)?,
+                ))
+            });
+
+        let Some((start, end)) = running_time else {
+            gst::debug!(CAT, imp: self, "Dropping buffer outside segment");
+            return Ok(gst::FlowSuccess::Ok);
+        };
+
+        let cue = Cue {
+            start,
+            end,
+            text: cue_text(&text, state.markup),
+        };
+
+        let mut buffers = vec![];
+
+        if settings.segment_duration.is_zero() {
+            if state.need_header {
+                let mut header = gst::Buffer::from_mut_slice(settings.header().into_bytes());
+                header
+                    .get_mut()
+                    .unwrap()
+                    .set_flags(gst::BufferFlags::HEADER | gst::BufferFlags::DISCONT);
+                buffers.push(header);
+                state.need_header = false;
+            }
+
+            if !cue.text.is_empty() {
+                let mut text = String::new();
+                write_cue(&mut text, &cue);
+
+                let mut buffer = gst::Buffer::from_mut_slice(text.into_bytes());
+                {
+                    let buffer = buffer.get_mut().unwrap();
+                    buffer.set_pts(pts);
+                    buffer.set_duration(duration);
+                }
+                buffers.push(buffer);
+            }
+        } else {
+            buffers = self.advance(&mut state, &settings, start);
+
+            if !cue.text.is_empty() && end > start {
+                state.cues.push(cue);
+                state.last_position = state.last_position.max(Some(end));
+            }
+        }
+
+        drop(state);
+
+        self.push_buffers(buffers)
+    }
+
+    fn sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::log!(CAT, obj: pad, "Handling event {:?}", event);
+
+        match event.view() {
+            EventView::Caps(e) => {
+                let markup = e
+                    .caps()
+                    .structure(0)
+                    .and_then(|s| s.get::<&str>("format").ok())
+                    == Some("pango-markup");
+                self.state.lock().unwrap().markup = markup;
+
+                let caps = self.srcpad.pad_template_caps();
+                self.srcpad.push_event(
+                    gst::event::Caps::builder(&caps)
+                        .seqnum(event.seqnum())
+                        .build(),
+                )
+            }
+            EventView::Segment(e) => {
+                let Some(segment) = e.segment().downcast_ref::<gst::ClockTime>() else {
+                    gst::element_imp_error!(
+                        self,
+                        gst::StreamError::Format,
+                        ["Only TIME segments are supported"]
+                    );
+                    return false;
+                };
+
+                self.state.lock().unwrap().segment = segment.clone();
+
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            EventView::Gap(e) => {
+                let settings = self.settings.lock().unwrap().clone();
+                if settings.segment_duration.is_zero() {
+                    return gst::Pad::event_default(pad, Some(&*self.obj()), event);
+                }
+
+                // Gaps are filled by segments without cues
+                let (timestamp, duration) = e.get();
+                let mut state = self.state.lock().unwrap();
+                let Some(position) = state
+                    .segment
+                    .to_running_time(timestamp + duration.unwrap_or(gst::ClockTime::ZERO))
+                else {
+                    return true;
+                };
+
+                let buffers = self.advance(&mut state, &settings, position);
+                drop(state);
+
+                if let Err(err) = self.push_buffers(buffers) {
+                    gst::debug!(CAT, obj: pad, "Failed to push segments: {err:?}");
+                }
+
+                true
+            }
+            EventView::FlushStop(_) => {
+                let mut state = self.state.lock().unwrap();
+                state.cues.clear();
+                state.output_start = None;
+                state.last_position = None;
+                state.discont = true;
+                drop(state);
+
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            EventView::Eos(_) => {
+                let settings = self.settings.lock().unwrap().clone();
+                let mut state = self.state.lock().unwrap();
+                let buffers = self.drain(&mut state, &settings);
+                drop(state);
+
+                if let Err(err) = self.push_buffers(buffers) {
+                    gst::debug!(CAT, obj: pad, "Failed to push remaining segments: {err:?}");
+                }
+
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            _ => gst::Pad::event_default(pad, Some(&*self.obj()), event),
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for WebVttEnc {
+    const NAME: &'static str = "GstRsWebVttEnc";
+    type Type = super::WebVttEnc;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("sink").unwrap();
+        let sinkpad = gst::Pad::builder_from_template(&templ)
+            .chain_function(|pad, parent, buffer| {
+                WebVttEnc::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |enc| enc.sink_chain(pad, buffer),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                WebVttEnc::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |enc| enc.sink_event(pad, event),
+                )
+            })
+            .build();
+
+        let templ = klass.pad_template("src").unwrap();
+        let srcpad = gst::Pad::builder_from_template(&templ)
+            .flags(gst::PadFlags::FIXED_CAPS)
+            .build();
+
+        Self {
+            srcpad,
+            sinkpad,
+            settings: Mutex::new(Settings::default()),
+            state: Mutex::new(State::default()),
+        }
+    }
+}
+
+impl ObjectImpl for WebVttEnc {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecUInt64::builder("segment-duration")
+                    .nick("Segment Duration")
+                    .blurb(
+                        "Duration of the WebVTT segments in nanoseconds, should match the \
+                         target duration of the HLS sink (0 = a single WebVTT stream)",
+                    )
+                    .maximum(u64::MAX - 1)
+                    .default_value(DEFAULT_SEGMENT_DURATION.nseconds())
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("timestamp-map")
+                    .nick("Timestamp Map")
+                    .blurb("Add an X-TIMESTAMP-MAP header for use in HLS")
+                    .default_value(DEFAULT_TIMESTAMP_MAP)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt64::builder("mpegts-offset")
+                    .nick("MPEG-TS Offset")
+                    .blurb(
+                        "MPEG-TS timestamp (in 90kHz units) of the media corresponding \
+                         to a running time of zero, used in the X-TIMESTAMP-MAP header",
+                    )
+                    .maximum((1 << 33) - 1)
+                    .default_value(DEFAULT_MPEGTS_OFFSET)
+                    .mutable_ready()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "segment-duration" => {
+                settings.segment_duration =
+                    gst::ClockTime::from_nseconds(value.get().expect("type checked upstream"));
+            }
+            "timestamp-map" => {
+                settings.timestamp_map = value.get().expect("type checked upstream");
+            }
+            "mpegts-offset" => {
+                settings.mpegts_offset = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "segment-duration" => settings.segment_duration.nseconds().to_value(),
+            "timestamp-map" => settings.timestamp_map.to_value(),
+            "mpegts-offset" => settings.mpegts_offset.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.sinkpad).unwrap();
+        obj.add_pad(&self.srcpad).unwrap();
+    }
+}
+
+impl GstObjectImpl for WebVttEnc {}
+
+impl ElementImpl for WebVttEnc {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "WebVTT Encoder",
+                "Codec/Encoder/Subtitle",
+                "Encodes timed text into (segmented) WebVTT",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst::Caps::builder("application/x-subtitle-vtt").build();
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let caps = gst::Caps::builder("text/x-raw")
+                .field("format", gst::List::new(["utf8", "pango-markup"]))
+                .build();
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        gst::trace!(CAT, imp: self, "Changing state {:?}", transition);
+
+        if let gst::StateChange::ReadyToPaused | gst::StateChange::PausedToReady = transition {
+            *self.state.lock().unwrap() = State::default();
+        }
+
+        self.parent_change_state(transition)
+    }
+}
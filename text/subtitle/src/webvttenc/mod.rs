@@ -0,0 +1,47 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * element-rswebvttenc:
+ *
+ * `rswebvttenc` encodes timed `text/x-raw` buffers into WebVTT.
+ *
+ * By default the output is split into self-contained WebVTT documents of
+ * `segment-duration` each, as needed for HLS subtitle renditions. Every segment starts
+ * with an `X-TIMESTAMP-MAP` header that maps the cue timestamps, which are in running
+ * time, to the MPEG-TS timestamps of the media via `mpegts-offset`. Cues spanning a
+ * segment boundary are repeated in both segments and gaps produce segments without cues.
+ *
+ * With `segment-duration` set to 0 a single WebVTT stream is produced instead, with the
+ * header in the first buffer followed by one buffer per cue.
+ *
+ * ## Example pipeline
+ * ```bash
+ * gst-launch-1.0 filesrc location=input.srt ! srtparse ! rswebvttenc segment-duration=6000000000 ! fakesink dump=true
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+mod writer;
+
+glib::wrapper! {
+    pub struct WebVttEnc(ObjectSubclass<imp::WebVttEnc>) @extends gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "rswebvttenc",
+        gst::Rank::NONE,
+        WebVttEnc::static_type(),
+    )
+}
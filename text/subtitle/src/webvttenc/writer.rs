@@ -0,0 +1,228 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::fmt::Write;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cue {
+    pub start: gst::ClockTime,
+    pub end: gst::ClockTime,
+    pub text: String,
+}
+
+/// MPEG-TS timestamps are 33 bit wide
+const MPEGTS_MASK: u64 = (1 << 33) - 1;
+
+pub fn format_timestamp(out: &mut String, t: gst::ClockTime) {
+    let ms = t.mseconds();
+
+    write!(
+        out,
+        "{:02}:{:02}:{:02}.{:03}",
+        ms / 3_600_000,
+        (ms / 60_000) % 60,
+        (ms / 1_000) % 60,
+        ms % 1_000
+    )
+    .unwrap();
+}
+
+/// Writes the WebVTT file header, optionally mapping a local time of zero to the
+/// given MPEG-TS timestamp (in 90kHz units) as needed for HLS.
+pub fn write_header(out: &mut String, mpegts: Option<u64>) {
+    out.push_str("WEBVTT\n");
+    if let Some(mpegts) = mpegts {
+        writeln!(
+            out,
+            "X-TIMESTAMP-MAP=MPEGTS:{},LOCAL:00:00:00.000",
+            mpegts & MPEGTS_MASK
+        )
+        .unwrap();
+    }
+    out.push('\n');
+}
+
+pub fn write_cue(out: &mut String, cue: &Cue) {
+    format_timestamp(out, cue.start);
+    out.push_str(" --> ");
+    format_timestamp(out, cue.end);
+    out.push('\n');
+    out.push_str(&cue.text);
+    out.push_str("\n\n");
+}
+
+fn escape(out: &mut String, c: char) {
+    match c {
+        '&' => out.push_str("&amp;"),
+        '<' => out.push_str("&lt;"),
+        '>' => out.push_str("&gt;"),
+        c => out.push(c),
+    }
+}
+
+fn char_reference(entity: &str) -> Option<char> {
+    let entity = entity.strip_prefix('#')?;
+    let c = match entity.strip_prefix(['x', 'X']) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+        None => entity.parse().ok()?,
+    };
+
+    char::from_u32(c)
+}
+
+/// Converts Pango markup to WebVTT cue text, keeping only the tags both support
+fn convert_markup(out: &mut String, text: &str) {
+    let mut rest = text;
+
+    while let Some(pos) = rest.find(['<', '&']) {
+        for c in rest[..pos].chars() {
+            escape(out, c);
+        }
+        rest = &rest[pos..];
+
+        if rest.starts_with('<') {
+            let Some(end) = rest.find('>') else {
+                // Not valid markup, keep the remainder as text
+                break;
+            };
+
+            let tag = &rest[1..end];
+            let (closing, tag) = match tag.strip_prefix('/') {
+                Some(tag) => (true, tag),
+                None => (false, tag),
+            };
+            let name = tag.split_whitespace().next().unwrap_or_default();
+
+            if let "b" | "i" | "u" = name {
+                out.push('<');
+                if closing {
+                    out.push('/');
+                }
+                out.push_str(name);
+                out.push('>');
+            }
+
+            rest = &rest[end + 1..];
+        } else {
+            let Some(entity) = rest[1..]
+                .find(';')
+                .map(|end| &rest[1..end + 1])
+                .filter(|entity| {
+                    !entity.is_empty()
+                        && entity.len() <= 8
+                        && entity
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '#')
+                })
+            else {
+                out.push_str("&amp;");
+                rest = &rest[1..];
+                continue;
+            };
+
+            match entity {
+                "amp" | "lt" | "gt" => {
+                    out.push('&');
+                    out.push_str(entity);
+                    out.push(';');
+                }
+                "quot" => out.push('"'),
+                "apos" => out.push('\''),
+                _ => match char_reference(entity) {
+                    Some(c) => escape(out, c),
+                    None => {
+                        out.push_str("&amp;");
+                        out.push_str(entity);
+                        out.push(';');
+                    }
+                },
+            }
+
+            rest = &rest[entity.len() + 2..];
+        }
+    }
+
+    for c in rest.chars() {
+        escape(out, c);
+    }
+}
+
+/// Converts text/x-raw content to WebVTT cue text. Empty lines would end the cue
+/// and are removed.
+pub fn cue_text(text: &str, markup: bool) -> String {
+    let mut out = String::with_capacity(text.len());
+
+    if markup {
+        convert_markup(&mut out, text);
+    } else {
+        for c in text.chars() {
+            escape(&mut out, c);
+        }
+    }
+
+    out.lines()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamp() {
+        let mut out = String::new();
+        format_timestamp(&mut out, gst::ClockTime::from_mseconds(3_723_004));
+        assert_eq!(out, "01:02:03.004");
+
+        out.clear();
+        format_timestamp(&mut out, gst::ClockTime::from_seconds(360_000));
+        assert_eq!(out, "100:00:00.000");
+    }
+
+    #[test]
+    fn test_header() {
+        let mut out = String::new();
+        write_header(&mut out, None);
+        assert_eq!(out, "WEBVTT\n\n");
+
+        out.clear();
+        write_header(&mut out, Some((1 << 33) + 900_000));
+        assert_eq!(
+            out,
+            "WEBVTT\nX-TIMESTAMP-MAP=MPEGTS:900000,LOCAL:00:00:00.000\n\n"
+        );
+    }
+
+    #[test]
+    fn test_utf8() {
+        assert_eq!(cue_text("a < b & c", false), "a &lt; b &amp; c");
+        assert_eq!(cue_text("one\n\n two \r\n", false), "one\n two");
+        assert_eq!(cue_text("a --> b", false), "a --&gt; b");
+    }
+
+    #[test]
+    fn test_markup() {
+        assert_eq!(
+            cue_text("<b>bold</b> <span foreground=\"red\">red</span>", true),
+            "<b>bold</b> red"
+        );
+        assert_eq!(
+            cue_text("<i>x &amp; y &lt;z&gt;</i> &quot;q&apos;", true),
+            "<i>x &amp; y &lt;z&gt;</i> \"q'"
+        );
+        assert_eq!(
+            cue_text("&#60;&#x41; & &foo;", true),
+            "&lt;A &amp; &amp;foo;"
+        );
+        assert_eq!(cue_text("a <b", true), "a &lt;b");
+        assert_eq!(cue_text("a --> b", true), "a --&gt; b");
+    }
+}
@@ -0,0 +1,126 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstsubtitle::plugin_register_static().expect("subtitle test");
+    });
+}
+
+fn pull_text(h: &mut gst_check::Harness) -> (gst::ClockTime, gst::ClockTime, String) {
+    let buf = h.pull().expect("Couldn't pull buffer");
+    let map = buf.map_readable().expect("Couldn't map buffer readable");
+
+    (
+        buf.pts().unwrap(),
+        buf.duration().unwrap(),
+        std::str::from_utf8(map.as_ref()).unwrap().to_string(),
+    )
+}
+
+#[test]
+fn test_parse_markup() {
+    init();
+
+    let mut h = gst_check::Harness::new("srtparse");
+    h.set_src_caps_str("application/x-subtitle");
+
+    // UTF-8 BOM, CRLF line endings and a cue split over two buffers
+    let input: [&[u8]; 2] = [
+        b"\xef\xbb\xbf1\r\n00:00:01,000 --> 00:00:02,500\r\n<i>Hello</i> & ",
+        "g\u{e9}n\u{e9}ral\r\n\r\n2\r\n00:00:03,000 --> 00:00:04,000\r\n<b>Bye".as_bytes(),
+    ];
+
+    for input in input {
+        assert_eq!(
+            h.push(gst::Buffer::from_slice(input)),
+            Ok(gst::FlowSuccess::Ok)
+        );
+    }
+    h.push_event(gst::event::Eos::new());
+
+    assert_eq!(
+        pull_text(&mut h),
+        (
+            gst::ClockTime::from_seconds(1),
+            gst::ClockTime::from_mseconds(1_500),
+            "<i>Hello</i> &amp; g\u{e9}n\u{e9}ral".to_string()
+        )
+    );
+    assert_eq!(
+        pull_text(&mut h),
+        (
+            gst::ClockTime::from_seconds(3),
+            gst::ClockTime::from_seconds(1),
+            "<b>Bye</b>".to_string()
+        )
+    );
+
+    let caps = h
+        .sinkpad()
+        .expect("harness has no sinkpad")
+        .current_caps()
+        .expect("pad has no caps");
+    assert_eq!(
+        caps,
+        gst::Caps::builder("text/x-raw")
+            .field("format", "pango-markup")
+            .build()
+    );
+
+    // Check that the time between the cues was filled with gaps
+    let mut gaps = vec![];
+    while let Some(event) = h.try_pull_event() {
+        if let gst::EventView::Gap(gap) = event.view() {
+            gaps.push(gap.get());
+        }
+    }
+    assert_eq!(
+        gaps,
+        [
+            (gst::ClockTime::ZERO, Some(gst::ClockTime::from_seconds(1))),
+            (
+                gst::ClockTime::from_mseconds(2_500),
+                Some(gst::ClockTime::from_mseconds(500))
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_parse_utf8_encoding_property() {
+    init();
+
+    let mut h = gst_check::Harness::new("srtparse");
+    h.element()
+        .unwrap()
+        .set_property("encoding", "windows-1252");
+    h.set_src_caps_str("application/x-subtitle");
+    h.set_sink_caps_str("text/x-raw, format=utf8");
+
+    let input = b"1\n00:00:00,500 --> 00:00:01,000\n<font color=\"red\">caf\xe9</font>\n\n";
+    assert_eq!(
+        h.push(gst::Buffer::from_slice(input)),
+        Ok(gst::FlowSuccess::Ok)
+    );
+
+    assert_eq!(
+        pull_text(&mut h),
+        (
+            gst::ClockTime::from_mseconds(500),
+            gst::ClockTime::from_mseconds(500),
+            "caf\u{e9}".to_string()
+        )
+    );
+}
@@ -0,0 +1,131 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstsubtitle::plugin_register_static().expect("subtitle test");
+    });
+}
+
+const DOCUMENT: &str = r##"<?xml version="1.0" encoding="UTF-8"?>
+<tt:tt xmlns:tt="http://www.w3.org/ns/ttml" xmlns:tts="http://www.w3.org/ns/ttml#styling"
+    xmlns:ttp="http://www.w3.org/ns/ttml#parameter" ttp:timeBase="media" xml:lang="en">
+  <tt:head>
+    <tt:styling>
+      <tt:style xml:id="yellow" tts:color="#ffff00"/>
+    </tt:styling>
+    <tt:layout>
+      <tt:region xml:id="bottom" tts:origin="10% 80%" tts:extent="80% 15%"/>
+    </tt:layout>
+  </tt:head>
+  <tt:body>
+    <tt:div>
+      <tt:p region="bottom" begin="00:00:01.000" end="00:00:02.500">
+        <tt:span style="yellow">First</tt:span> <tt:span>cue</tt:span>
+      </tt:p>
+      <tt:p region="bottom" begin="00:00:03.000" end="00:00:04.000">Second</tt:p>
+    </tt:div>
+  </tt:body>
+</tt:tt>
+"##;
+
+fn pull_text(h: &mut gst_check::Harness) -> (gst::ClockTime, gst::ClockTime, String) {
+    let buf = h.pull().expect("Couldn't pull buffer");
+    let map = buf.map_readable().expect("Couldn't map buffer readable");
+
+    (
+        buf.pts().unwrap(),
+        buf.duration().unwrap(),
+        std::str::from_utf8(map.as_ref()).unwrap().to_string(),
+    )
+}
+
+#[test]
+fn test_parse_markup() {
+    init();
+
+    let mut h = gst_check::Harness::new("rsttmlparse");
+    h.set_src_caps_str("application/ttml+xml");
+
+    // Split the document over two buffers
+    let (first, second) = DOCUMENT.as_bytes().split_at(DOCUMENT.len() / 2);
+    for data in [first, second] {
+        assert_eq!(
+            h.push(gst::Buffer::from_slice(data)),
+            Ok(gst::FlowSuccess::Ok)
+        );
+    }
+
+    assert_eq!(
+        pull_text(&mut h),
+        (
+            gst::ClockTime::from_seconds(1),
+            gst::ClockTime::from_mseconds(1_500),
+            "<span foreground=\"#ffff00\">First</span> cue".to_string()
+        )
+    );
+    assert_eq!(
+        pull_text(&mut h),
+        (
+            gst::ClockTime::from_seconds(3),
+            gst::ClockTime::from_seconds(1),
+            "Second".to_string()
+        )
+    );
+
+    let caps = h
+        .sinkpad()
+        .expect("harness has no sinkpad")
+        .current_caps()
+        .expect("pad has no caps");
+    assert_eq!(
+        caps,
+        gst::Caps::builder("text/x-raw")
+            .field("format", "pango-markup")
+            .build()
+    );
+}
+
+#[test]
+fn test_parse_utf8_multiple_documents() {
+    init();
+
+    let mut h = gst_check::Harness::new("rsttmlparse");
+    h.set_src_caps_str("application/ttml+xml");
+    h.set_sink_caps_str("text/x-raw, format=utf8");
+
+    let documents = format!("{DOCUMENT}{}", DOCUMENT.replace("00:00:0", "00:00:1"));
+    assert_eq!(
+        h.push(gst::Buffer::from_slice(documents.into_bytes())),
+        Ok(gst::FlowSuccess::Ok)
+    );
+
+    let expected = [
+        (1_000, 1_500, "First cue"),
+        (3_000, 1_000, "Second"),
+        (11_000, 1_500, "First cue"),
+        (13_000, 1_000, "Second"),
+    ];
+
+    for (pts, duration, text) in expected {
+        assert_eq!(
+            pull_text(&mut h),
+            (
+                gst::ClockTime::from_mseconds(pts),
+                gst::ClockTime::from_mseconds(duration),
+                text.to_string()
+            )
+        );
+    }
+}
@@ -0,0 +1,134 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstsubtitle::plugin_register_static().expect("subtitle test");
+    });
+}
+
+fn new_text_buffer(text: &str, pts: gst::ClockTime, duration: gst::ClockTime) -> gst::Buffer {
+    let mut buf = gst::Buffer::from_mut_slice(text.as_bytes().to_vec());
+    {
+        let buf = buf.get_mut().unwrap();
+        buf.set_pts(pts);
+        buf.set_duration(duration);
+    }
+    buf
+}
+
+fn pull_text(h: &mut gst_check::Harness) -> (gst::Buffer, String) {
+    let buf = h.pull().expect("Couldn't pull buffer");
+    let text = {
+        let map = buf.map_readable().expect("Couldn't map buffer readable");
+        std::str::from_utf8(map.as_ref()).unwrap().to_string()
+    };
+
+    (buf, text)
+}
+
+#[test]
+fn test_segmented() {
+    init();
+
+    let mut h = gst_check::Harness::new("rswebvttenc");
+    {
+        let enc = h.element().unwrap();
+        enc.set_property("segment-duration", 2_000_000_000u64);
+        enc.set_property("mpegts-offset", 900_000u64);
+    }
+    h.set_src_caps_str("text/x-raw, format=utf8");
+
+    for (text, pts) in [("Hello", 500), ("World & co", 1_500)] {
+        assert_eq!(
+            h.push(new_text_buffer(
+                text,
+                gst::ClockTime::from_mseconds(pts),
+                gst::ClockTime::from_seconds(1)
+            )),
+            Ok(gst::FlowSuccess::Ok)
+        );
+    }
+    h.push_event(
+        gst::event::Gap::builder(gst::ClockTime::from_mseconds(2_500))
+            .duration(gst::ClockTime::from_seconds(3))
+            .build(),
+    );
+    h.push_event(gst::event::Eos::new());
+
+    let header = "WEBVTT\nX-TIMESTAMP-MAP=MPEGTS:900000,LOCAL:00:00:00.000\n\n";
+    let hello = "00:00:00.500 --> 00:00:01.500\nHello\n\n";
+    let world = "00:00:01.500 --> 00:00:02.500\nWorld &amp; co\n\n";
+
+    // The second cue spans the segment boundary and is repeated, the gap produces an
+    // empty segment and the last segment is cut short at EOS
+    let expected = [
+        (0, 2_000, format!("{header}{hello}{world}")),
+        (2_000, 2_000, format!("{header}{world}")),
+        (4_000, 1_500, header.to_string()),
+    ];
+
+    for (pts, duration, expected_text) in expected {
+        let (buf, text) = pull_text(&mut h);
+        assert_eq!(buf.pts(), Some(gst::ClockTime::from_mseconds(pts)));
+        assert_eq!(
+            buf.duration(),
+            Some(gst::ClockTime::from_mseconds(duration))
+        );
+        assert_eq!(text, expected_text);
+    }
+
+    assert!(h.try_pull().is_none());
+
+    let caps = h
+        .sinkpad()
+        .expect("harness has no sinkpad")
+        .current_caps()
+        .expect("pad has no caps");
+    assert_eq!(
+        caps,
+        gst::Caps::builder("application/x-subtitle-vtt").build()
+    );
+}
+
+#[test]
+fn test_stream() {
+    init();
+
+    let mut h = gst_check::Harness::new("rswebvttenc");
+    {
+        let enc = h.element().unwrap();
+        enc.set_property("segment-duration", 0u64);
+        enc.set_property("timestamp-map", false);
+    }
+    h.set_src_caps_str("text/x-raw, format=pango-markup");
+
+    assert_eq!(
+        h.push(new_text_buffer(
+            "<span foreground=\"red\"><i>Hi</i></span>\n\nthere",
+            gst::ClockTime::from_seconds(1),
+            gst::ClockTime::from_seconds(2)
+        )),
+        Ok(gst::FlowSuccess::Ok)
+    );
+
+    let (buf, text) = pull_text(&mut h);
+    assert!(buf.flags().contains(gst::BufferFlags::HEADER));
+    assert_eq!(text, "WEBVTT\n\n");
+
+    let (buf, text) = pull_text(&mut h);
+    assert_eq!(buf.pts(), Some(gst::ClockTime::from_seconds(1)));
+    assert_eq!(buf.duration(), Some(gst::ClockTime::from_seconds(2)));
+    assert_eq!(text, "00:00:01.000 --> 00:00:03.000\n<i>Hi</i>\nthere\n\n");
+}
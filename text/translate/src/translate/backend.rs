@@ -0,0 +1,146 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use anyhow::{anyhow, bail, Error};
+use serde::{Deserialize, Serialize};
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+pub trait Backend: Send {
+    /// Translates `text` from `source` (`None` for detecting it) to the `target` language
+    fn translate(
+        &mut self,
+        text: &str,
+        source: Option<&str>,
+        target: &str,
+    ) -> Result<String, Error>;
+}
+
+/// Backend for LibreTranslate compatible HTTP APIs
+pub struct HttpBackend {
+    client: reqwest::blocking::Client,
+    url: String,
+    api_key: Option<String>,
+}
+
+#[derive(Serialize)]
+struct HttpRequest<'a> {
+    q: &'a str,
+    source: &'a str,
+    target: &'a str,
+    format: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    api_key: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HttpResponse {
+    translated_text: String,
+}
+
+impl HttpBackend {
+    pub fn new(url: &str, api_key: Option<&str>, timeout: Duration) -> Result<Self, Error> {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(timeout)
+            .build()?;
+
+        Ok(HttpBackend {
+            client,
+            url: url.to_string(),
+            api_key: api_key.map(String::from),
+        })
+    }
+}
+
+impl Backend for HttpBackend {
+    fn translate(
+        &mut self,
+        text: &str,
+        source: Option<&str>,
+        target: &str,
+    ) -> Result<String, Error> {
+        let request = HttpRequest {
+            q: text,
+            source: source.unwrap_or("auto"),
+            target,
+            format: "text",
+            api_key: self.api_key.as_deref(),
+        };
+
+        let response = self
+            .client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(serde_json::to_vec(&request)?)
+            .send()?
+            .error_for_status()?;
+
+        let response = serde_json::from_slice::<HttpResponse>(&response.bytes()?)?;
+
+        Ok(response.translated_text)
+    }
+}
+
+/// Backend running a command for each translation, e.g. a wrapper around a local model.
+///
+/// The text is written to the standard input of the command and the translation read
+/// from its standard output. `{source}` and `{target}` in the arguments are replaced
+/// by the language codes.
+pub struct CommandBackend {
+    command: Vec<String>,
+}
+
+impl CommandBackend {
+    pub fn new(command: &str) -> Result<Self, Error> {
+        let command = command
+            .split_whitespace()
+            .map(String::from)
+            .collect::<Vec<_>>();
+
+        if command.is_empty() {
+            bail!("Empty command");
+        }
+
+        Ok(CommandBackend { command })
+    }
+}
+
+impl Backend for CommandBackend {
+    fn translate(
+        &mut self,
+        text: &str,
+        source: Option<&str>,
+        target: &str,
+    ) -> Result<String, Error> {
+        let mut args = self.command.iter().map(|arg| {
+            arg.replace("{source}", source.unwrap_or("auto"))
+                .replace("{target}", target)
+        });
+        let program = args.next().unwrap();
+
+        let mut child = Command::new(&program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|err| anyhow!("Failed to run {program}: {err}"))?;
+
+        // Dropping stdin afterwards signals the end of the text
+        child.stdin.take().unwrap().write_all(text.as_bytes())?;
+
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            bail!("{program} failed: {}", output.status);
+        }
+
+        Ok(String::from_utf8(output.stdout)?.trim_end().to_string())
+    }
+}
@@ -0,0 +1,595 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+use anyhow::{anyhow, Error};
+use once_cell::sync::Lazy;
+
+use std::mem;
+use std::sync::Mutex;
+
+use super::backend::{Backend, CommandBackend, HttpBackend};
+use super::TranslateBackend;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "translate",
+        gst::DebugColorFlags::empty(),
+        Some("Text translation element"),
+    )
+});
+
+const DEFAULT_BACKEND: TranslateBackend = TranslateBackend::Http;
+const DEFAULT_URL: &str = "http://localhost:5000/translate";
+const DEFAULT_TARGET_LANGUAGE: &str = "en";
+const DEFAULT_ACCUMULATE: gst::ClockTime = gst::ClockTime::from_seconds(3);
+const DEFAULT_LATENCY: gst::ClockTime = gst::ClockTime::from_seconds(2);
+
+#[derive(Debug, Clone)]
+struct Settings {
+    backend: TranslateBackend,
+    url: String,
+    api_key: Option<String>,
+    command: Option<String>,
+    source_language: Option<String>,
+    target_language: String,
+    accumulate_time: gst::ClockTime,
+    latency: gst::ClockTime,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            backend: DEFAULT_BACKEND,
+            url: DEFAULT_URL.to_string(),
+            api_key: None,
+            command: None,
+            source_language: None,
+            target_language: DEFAULT_TARGET_LANGUAGE.to_string(),
+            accumulate_time: DEFAULT_ACCUMULATE,
+            latency: DEFAULT_LATENCY,
+        }
+    }
+}
+
+impl Settings {
+    fn create_backend(&self) -> Result<Box<dyn Backend>, Error> {
+        Ok(match self.backend {
+            TranslateBackend::Http => Box::new(HttpBackend::new(
+                &self.url,
+                self.api_key.as_deref(),
+                self.latency.into(),
+            )?),
+            TranslateBackend::Command => Box::new(CommandBackend::new(
+                self.command
+                    .as_deref()
+                    .ok_or_else(|| anyhow!("No command configured"))?,
+            )?),
+        })
+    }
+}
+
+#[derive(Default)]
+struct State {
+    backend: Option<Box<dyn Backend>>,
+
+    text: String,
+    start_ts: Option<gst::ClockTime>,
+    end_ts: Option<gst::ClockTime>,
+}
+
+/// Translated text of a batch of buffers
+struct Item {
+    pts: gst::ClockTime,
+    duration: gst::ClockTime,
+    text: String,
+}
+
+pub struct Translate {
+    srcpad: gst::Pad,
+    sinkpad: gst::Pad,
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+}
+
+fn ends_sentence(text: &str) -> bool {
+    text.trim_end()
+        .ends_with(['.', '!', '?', '…', '。', '！', '？'])
+}
+
+impl Translate {
+    fn translate_pending(&self, state: &mut State, settings: &Settings) -> Option<Item> {
+        let pts = state.start_ts.take()?;
+        let end = state.end_ts.take().unwrap_or(pts);
+        let text = mem::take(&mut state.text);
+
+        let text = match state.backend.as_mut() {
+            Some(backend) if !text.is_empty() => {
+                gst::debug!(CAT, imp: self, "Translating {text:?}");
+
+                match backend.translate(
+                    &text,
+                    settings.source_language.as_deref(),
+                    &settings.target_language,
+                ) {
+                    Ok(translation) => {
+                        gst::debug!(CAT, imp: self, "Translated to {translation:?}");
+                        translation
+                    }
+                    Err(err) => {
+                        gst::element_imp_warning!(
+                            self,
+                            gst::ResourceError::Failed,
+                            ["Failed to translate text: {}", err]
+                        );
+                        text
+                    }
+                }
+            }
+            _ => text,
+        };
+
+        Some(Item {
+            pts,
+            duration: end.saturating_sub(pts),
+            text,
+        })
+    }
+
+    fn push_item(&self, item: Item) -> Result<gst::FlowSuccess, gst::FlowError> {
+        if item.text.is_empty() {
+            gst::log!(CAT, imp: self, "Nothing to translate, pushing gap");
+            self.srcpad.push_event(
+                gst::event::Gap::builder(item.pts)
+                    .duration(item.duration)
+                    .build(),
+            );
+
+            return Ok(gst::FlowSuccess::Ok);
+        }
+
+        let mut buf = gst::Buffer::from_mut_slice(item.text.into_bytes());
+        {
+            let buf = buf.get_mut().unwrap();
+            buf.set_pts(item.pts);
+            buf.set_duration(item.duration);
+        }
+
+        self.srcpad.push(buf)
+    }
+
+    fn drain(&self) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let settings = self.settings.lock().unwrap().clone();
+        let mut state = self.state.lock().unwrap();
+        let item = self.translate_pending(&mut state, &settings);
+        drop(state);
+
+        match item {
+            Some(item) => self.push_item(item),
+            None => Ok(gst::FlowSuccess::Ok),
+        }
+    }
+
+    fn sink_chain(
+        &self,
+        pad: &gst::Pad,
+        buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::log!(CAT, obj: pad, "Handling buffer {:?}", buffer);
+
+        let Some(pts) = buffer.pts() else {
+            gst::element_imp_error!(self, gst::StreamError::Format, ["Need timestamped buffers"]);
+            return Err(gst::FlowError::Error);
+        };
+        let end = buffer.duration().map_or(pts, |duration| pts + duration);
+
+        let data = buffer.map_readable().map_err(|_| {
+            gst::element_imp_error!(
+                self,
+                gst::ResourceError::Read,
+                ["Failed to map buffer readable"]
+            );
+
+            gst::FlowError::Error
+        })?;
+
+        let text = std::str::from_utf8(&data).map_err(|err| {
+            gst::element_imp_error!(
+                self,
+                gst::StreamError::Format,
+                ["Can't decode utf8: {}", err]
+            );
+
+            gst::FlowError::NotNegotiated
+        })?;
+
+        let settings = self.settings.lock().unwrap().clone();
+        let mut state = self.state.lock().unwrap();
+
+        let trimmed = text.trim();
+        if !trimmed.is_empty() {
+            if !state.text.is_empty() {
+                state.text.push(' ');
+            }
+            state.text.push_str(trimmed);
+        }
+
+        let start_ts = *state.start_ts.get_or_insert(pts);
+        let end_ts = end.max(state.end_ts.unwrap_or(end));
+        state.end_ts = Some(end_ts);
+
+        if !ends_sentence(&state.text) && end_ts.saturating_sub(start_ts) < settings.accumulate_time
+        {
+            return Ok(gst::FlowSuccess::Ok);
+        }
+
+        let item = self.translate_pending(&mut state, &settings);
+        drop(state);
+
+        match item {
+            Some(item) => self.push_item(item),
+            None => Ok(gst::FlowSuccess::Ok),
+        }
+    }
+
+    fn language_tags(&self) -> gst::TagList {
+        let target_language = self.settings.lock().unwrap().target_language.clone();
+
+        let mut tags = gst::TagList::new();
+        tags.get_mut()
+            .unwrap()
+            .add::<gst::tags::LanguageCode>(&target_language.as_str(), gst::TagMergeMode::Replace);
+
+        tags
+    }
+
+    fn sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::log!(CAT, obj: pad, "Handling event {:?}", event);
+
+        match event.view() {
+            EventView::Caps(_) => {
+                if !gst::Pad::event_default(pad, Some(&*self.obj()), event) {
+                    return false;
+                }
+
+                self.srcpad
+                    .push_event(gst::event::Tag::new(self.language_tags()))
+            }
+            EventView::Tag(e) => {
+                // The stream is in the target language now
+                let mut tags = e.tag_owned();
+                tags.make_mut()
+                    .insert(&self.language_tags(), gst::TagMergeMode::Replace);
+
+                self.srcpad.push_event(
+                    gst::event::Tag::builder(tags)
+                        .seqnum(event.seqnum())
+                        .build(),
+                )
+            }
+            EventView::FlushStop(_) => {
+                let mut state = self.state.lock().unwrap();
+                state.text.clear();
+                state.start_ts = None;
+                state.end_ts = None;
+                drop(state);
+
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            _ => {
+                if event.is_serialized() {
+                    if let Err(err) = self.drain() {
+                        gst::debug!(CAT, obj: pad, "Failed to push translation: {err:?}");
+                    }
+                }
+
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+        }
+    }
+
+    fn src_query(&self, pad: &gst::Pad, query: &mut gst::QueryRef) -> bool {
+        use gst::QueryViewMut;
+
+        gst::log!(CAT, obj: pad, "Handling query {:?}", query);
+
+        match query.view_mut() {
+            QueryViewMut::Latency(q) => {
+                let mut peer_query = gst::query::Latency::new();
+
+                let ret = self.sinkpad.peer_query(&mut peer_query);
+
+                if ret {
+                    let (live, min, _) = peer_query.result();
+                    let our_latency = {
+                        let settings = self.settings.lock().unwrap();
+                        settings.accumulate_time + settings.latency
+                    };
+                    gst::info!(
+                        CAT,
+                        imp: self,
+                        "Reporting our latency {} + {}",
+                        our_latency,
+                        min
+                    );
+                    q.set(live, our_latency + min, gst::ClockTime::NONE);
+                }
+                ret
+            }
+            _ => gst::Pad::query_default(pad, Some(&*self.obj()), query),
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for Translate {
+    const NAME: &'static str = "GstTranslate";
+    type Type = super::Translate;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("sink").unwrap();
+        let sinkpad = gst::Pad::builder_from_template(&templ)
+            .chain_function(|pad, parent, buffer| {
+                Translate::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |translate| translate.sink_chain(pad, buffer),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                Translate::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |translate| translate.sink_event(pad, event),
+                )
+            })
+            .flags(gst::PadFlags::PROXY_CAPS)
+            .flags(gst::PadFlags::FIXED_CAPS)
+            .build();
+
+        let templ = klass.pad_template("src").unwrap();
+        let srcpad = gst::Pad::builder_from_template(&templ)
+            .query_function(|pad, parent, query| {
+                Translate::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |translate| translate.src_query(pad, query),
+                )
+            })
+            .flags(gst::PadFlags::PROXY_CAPS)
+            .flags(gst::PadFlags::FIXED_CAPS)
+            .build();
+
+        Self {
+            srcpad,
+            sinkpad,
+            settings: Mutex::new(Settings::default()),
+            state: Mutex::new(State::default()),
+        }
+    }
+}
+
+impl ObjectImpl for Translate {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecEnum::builder_with_default("backend", DEFAULT_BACKEND)
+                    .nick("Backend")
+                    .blurb("Backend used for translating")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("url")
+                    .nick("URL")
+                    .blurb("URL of the LibreTranslate compatible API for the http backend")
+                    .default_value(Some(DEFAULT_URL))
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("api-key")
+                    .nick("API Key")
+                    .blurb("API key for the http backend")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("command")
+                    .nick("Command")
+                    .blurb(
+                        "Command for the command backend, {source} and {target} are \
+                         replaced by the language codes",
+                    )
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("source-language")
+                    .nick("Source Language")
+                    .blurb("Language code of the input (NULL=detect)")
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecString::builder("target-language")
+                    .nick("Target Language")
+                    .blurb("Language code to translate to")
+                    .default_value(Some(DEFAULT_TARGET_LANGUAGE))
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt64::builder("accumulate-time")
+                    .nick("accumulate-time")
+                    .blurb(
+                        "Cut-off time for text accumulation if no sentence ended \
+                         (0=translate every buffer)",
+                    )
+                    .maximum(u64::MAX - 1)
+                    .default_value(DEFAULT_ACCUMULATE.nseconds())
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt64::builder("latency")
+                    .nick("Latency")
+                    .blurb("Amount of time to allow for translating, in nanoseconds")
+                    .maximum(u64::MAX - 1)
+                    .default_value(DEFAULT_LATENCY.nseconds())
+                    .mutable_ready()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "backend" => {
+                settings.backend = value.get().expect("type checked upstream");
+            }
+            "url" => {
+                settings.url = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .unwrap_or_else(|| DEFAULT_URL.to_string());
+            }
+            "api-key" => {
+                settings.api_key = value.get().expect("type checked upstream");
+            }
+            "command" => {
+                settings.command = value.get().expect("type checked upstream");
+            }
+            "source-language" => {
+                settings.source_language = value.get().expect("type checked upstream");
+            }
+            "target-language" => {
+                settings.target_language = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .unwrap_or_else(|| DEFAULT_TARGET_LANGUAGE.to_string());
+            }
+            "accumulate-time" | "latency" => {
+                let time = value.get::<u64>().unwrap().nseconds();
+                let old_time = if pspec.name() == "latency" {
+                    mem::replace(&mut settings.latency, time)
+                } else {
+                    mem::replace(&mut settings.accumulate_time, time)
+                };
+
+                if time != old_time {
+                    drop(settings);
+                    let _ = self
+                        .obj()
+                        .post_message(gst::message::Latency::builder().src(&*self.obj()).build());
+                }
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "backend" => settings.backend.to_value(),
+            "url" => settings.url.to_value(),
+            "api-key" => settings.api_key.to_value(),
+            "command" => settings.command.to_value(),
+            "source-language" => settings.source_language.to_value(),
+            "target-language" => settings.target_language.to_value(),
+            "accumulate-time" => settings.accumulate_time.nseconds().to_value(),
+            "latency" => settings.latency.nseconds().to_value(),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.sinkpad).unwrap();
+        obj.add_pad(&self.srcpad).unwrap();
+    }
+}
+
+impl GstObjectImpl for Translate {}
+
+impl ElementImpl for Translate {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Text Translator",
+                "Text/Filter",
+                "Translates timed text into another language",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst::Caps::builder("text/x-raw")
+                .field("format", "utf8")
+                .build();
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        gst::trace!(CAT, imp: self, "Changing state {:?}", transition);
+
+        match transition {
+            gst::StateChange::ReadyToPaused => {
+                let backend = self
+                    .settings
+                    .lock()
+                    .unwrap()
+                    .create_backend()
+                    .map_err(|err| {
+                        gst::element_imp_error!(
+                            self,
+                            gst::LibraryError::Settings,
+                            ["Failed to create translation backend: {}", err]
+                        );
+                        gst::StateChangeError
+                    })?;
+
+                *self.state.lock().unwrap() = State {
+                    backend: Some(backend),
+                    ..Default::default()
+                };
+            }
+            gst::StateChange::PausedToReady => {
+                *self.state.lock().unwrap() = State::default();
+            }
+            _ => (),
+        }
+
+        self.parent_change_state(transition)
+    }
+}
@@ -0,0 +1,68 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * element-translate:
+ *
+ * `translate` translates timed text, e.g. the output of a transcriber, into another
+ * language.
+ *
+ * Incoming buffers are accumulated until a sentence ends or `accumulate-time` is reached,
+ * and the batch is then translated as a whole. The translation covers the time of all
+ * buffers of the batch, and the target language is added as language code tag to the
+ * stream.
+ *
+ * The translation is done by a pluggable backend:
+ *
+ * - `http`: a [LibreTranslate](https://libretranslate.com) compatible HTTP API at `url`.
+ *   This can be a public service or a locally running model.
+ * - `command`: runs `command` for each batch, with the text on its standard input and
+ *   the translation expected on its standard output. `{source}` and `{target}` in the
+ *   command are replaced by the language codes.
+ *
+ * If the translation fails, the original text is output and a warning is posted.
+ *
+ * ## Example pipeline
+ * ```bash
+ * gst-launch-1.0 filesrc location=input.srt ! srtparse ! translate target-language=fr ! fakesink dump=true
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod backend;
+mod imp;
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstTranslateBackend")]
+#[non_exhaustive]
+pub enum TranslateBackend {
+    #[enum_value(name = "Http: LibreTranslate compatible HTTP API", nick = "http")]
+    Http = 0,
+    #[enum_value(name = "Command: run a command for each translation", nick = "command")]
+    Command = 1,
+}
+
+glib::wrapper! {
+    pub struct Translate(ObjectSubclass<imp::Translate>) @extends gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    #[cfg(feature = "doc")]
+    TranslateBackend::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+
+    gst::Element::register(
+        Some(plugin),
+        "translate",
+        gst::Rank::NONE,
+        Translate::static_type(),
+    )
+}
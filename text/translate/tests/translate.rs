@@ -0,0 +1,73 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gsttranslate::plugin_register_static().expect("translate test");
+    });
+}
+
+fn push_text(h: &mut gst_check::Harness, text: &str, pts: gst::ClockTime) {
+    let mut buf = gst::Buffer::from_mut_slice(text.as_bytes().to_vec());
+    {
+        let buf = buf.get_mut().unwrap();
+        buf.set_pts(pts);
+        buf.set_duration(gst::ClockTime::SECOND);
+    }
+    assert_eq!(h.push(buf), Ok(gst::FlowSuccess::Ok));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_command_backend() {
+    init();
+
+    let translate = gst::ElementFactory::make("translate")
+        .property_from_str("backend", "command")
+        .property("command", "tr a-z A-Z")
+        .property("target-language", "xx")
+        .property("accumulate-time", 5 * gst::ClockTime::SECOND.nseconds())
+        .build()
+        .unwrap();
+    let mut h = gst_check::Harness::with_element(&translate, Some("sink"), Some("src"));
+    h.set_src_caps_str("text/x-raw, format=utf8");
+
+    // The first sentence spans two buffers
+    push_text(&mut h, "hello", gst::ClockTime::ZERO);
+    push_text(&mut h, "world.", gst::ClockTime::SECOND);
+    // The second one is cut off at EOS
+    push_text(&mut h, "goodbye", 3 * gst::ClockTime::SECOND);
+    h.push_event(gst::event::Eos::new());
+
+    let buf = h.pull().expect("Couldn't pull buffer");
+    assert_eq!(buf.pts(), Some(gst::ClockTime::ZERO));
+    assert_eq!(buf.duration(), Some(2 * gst::ClockTime::SECOND));
+    assert_eq!(&*buf.map_readable().unwrap(), b"HELLO WORLD.");
+
+    let buf = h.pull().expect("Couldn't pull buffer");
+    assert_eq!(buf.pts(), Some(3 * gst::ClockTime::SECOND));
+    assert_eq!(buf.duration(), Some(gst::ClockTime::SECOND));
+    assert_eq!(&*buf.map_readable().unwrap(), b"GOODBYE");
+
+    let mut language = None;
+    while let Some(event) = h.try_pull_event() {
+        if let gst::EventView::Tag(e) = event.view() {
+            language = e
+                .tag()
+                .get::<gst::tags::LanguageCode>()
+                .map(|v| v.get().to_string());
+        }
+    }
+    assert_eq!(language.as_deref(), Some("xx"));
+}
@@ -281,14 +281,15 @@ impl TextWrap {
 
             gst::log!(CAT, imp: self, "fill result: {data}");
 
+            let num_words = data.split_whitespace().count() as u64;
+
             // If the lines property was set, we want to split the result into buffers
             // of at most N lines. We compute the duration for each of those based on
             // the total number of words, and the number of words in each of the split-up
-            // buffers.
-            if lines > 0 {
+            // buffers. Buffers without any words are forwarded as is.
+            if lines > 0 && num_words > 0 {
                 let mut bufferlist = gst::BufferList::new();
-                let duration_per_word: gst::ClockTime =
-                    duration / data.split_whitespace().count() as u64;
+                let duration_per_word: gst::ClockTime = duration / num_words;
 
                 for chunk in data.lines().collect::<Vec<&str>>().chunks(lines as usize) {
                     let data = chunk.join("\n");
@@ -112,3 +112,34 @@ fn test_lines() {
         std::str::from_utf8(expected_output.as_ref())
     );
 }
+
+#[test]
+fn test_lines_empty_buffer() {
+    init();
+
+    let mut h = gst_check::Harness::new("textwrap");
+
+    {
+        let wrap = h.element().expect("Could not create textwrap");
+        wrap.set_property("columns", 5u32);
+        wrap.set_property("lines", 2u32);
+    }
+
+    h.set_src_caps_str("text/x-raw, format=utf8");
+
+    let buf = {
+        let mut buf = gst::Buffer::new();
+        let buf_ref = buf.get_mut().unwrap();
+        buf_ref.set_pts(gst::ClockTime::ZERO);
+        buf_ref.set_duration(2.seconds());
+        buf
+    };
+
+    assert_eq!(h.push(buf), Ok(gst::FlowSuccess::Ok));
+
+    let buf = h.pull().expect("Couldn't pull buffer");
+
+    assert_eq!(buf.pts(), Some(gst::ClockTime::ZERO));
+    assert_eq!(buf.duration(), Some(2.seconds()));
+    assert_eq!(buf.size(), 0);
+}
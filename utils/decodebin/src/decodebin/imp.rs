@@ -0,0 +1,454 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "rsdecodebin",
+        gst::DebugColorFlags::empty(),
+        Some("Rust Decodebin"),
+    )
+});
+
+static DEFAULT_CAPS: Lazy<gst::Caps> = Lazy::new(|| {
+    gst::Caps::builder_full()
+        .structure_with_any_features(gst::Structure::new_empty("video/x-raw"))
+        .structure_with_any_features(gst::Structure::new_empty("audio/x-raw"))
+        .structure_with_any_features(gst::Structure::new_empty("text/x-raw"))
+        .build()
+});
+
+#[derive(Debug, Clone)]
+struct Settings {
+    caps: gst::Caps,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            caps: DEFAULT_CAPS.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Elements plugged since the last state change to READY
+    elements: Vec<gst::Element>,
+    /// Factories that may be plugged, sorted by descending rank
+    factories: Option<Vec<gst::ElementFactory>>,
+    /// Elements waiting for `no-more-pads` and pads waiting for caps
+    pending: u32,
+    num_srcpads: u32,
+}
+
+pub struct DecodeBin {
+    sinkpad: gst::GhostPad,
+    typefind: gst::Element,
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+}
+
+/// Returns all demuxers, parsers, depayloaders and decoders from this plugin collection
+fn collection_factories() -> Vec<gst::ElementFactory> {
+    let mut factories = gst::ElementFactory::factories_with_type(
+        gst::ElementFactoryType::DEMUXER
+            | gst::ElementFactoryType::PARSER
+            | gst::ElementFactoryType::DEPAYLOADER
+            | gst::ElementFactoryType::DECODER,
+        gst::Rank::NONE,
+    )
+    .into_iter()
+    .filter(|factory| {
+        factory.plugin().map_or(false, |plugin| {
+            plugin.origin().as_str() == env!("CARGO_PKG_REPOSITORY")
+        })
+    })
+    // Skip ourselves and other generic bins accepting anything
+    .filter(|factory| {
+        factory
+            .static_pad_templates()
+            .iter()
+            .all(|templ| templ.direction() != gst::PadDirection::Sink || !templ.caps().is_any())
+    })
+    .collect::<Vec<_>>();
+
+    // Parsers have to come before decoders of the same rank as decoders usually
+    // also accept unparsed caps
+    let is_decoder = |factory: &gst::ElementFactory| {
+        factory.has_type(gst::ElementFactoryType::DECODER)
+            && !factory.has_type(gst::ElementFactoryType::PARSER)
+    };
+    factories.sort_by(|a, b| {
+        b.rank()
+            .cmp(&a.rank())
+            .then_with(|| is_decoder(a).cmp(&is_decoder(b)))
+            .then_with(|| a.name().cmp(&b.name()))
+    });
+
+    factories
+}
+
+impl DecodeBin {
+    /// Called once the pending counter goes down
+    fn finish_pending(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.pending = state.pending.saturating_sub(1);
+        let done = state.pending == 0 && state.num_srcpads > 0;
+        drop(state);
+
+        if done {
+            gst::debug!(CAT, imp: self, "All streams exposed");
+            self.obj().no_more_pads();
+        }
+    }
+
+    /// Plugs elements after `pad` once its caps are known
+    fn handle_pad(&self, pad: &gst::Pad) {
+        if let Some(caps) = pad.current_caps() {
+            self.analyze_pad(pad, &caps, false);
+            return;
+        }
+
+        gst::debug!(CAT, imp: self, "Waiting for caps on {}", pad.name());
+        self.state.lock().unwrap().pending += 1;
+
+        let obj = self.obj().downgrade();
+        pad.add_probe(gst::PadProbeType::EVENT_DOWNSTREAM, move |pad, info| {
+            let Some(gst::EventView::Caps(ev)) = info.event().map(|event| event.view()) else {
+                return gst::PadProbeReturn::Ok;
+            };
+            let Some(obj) = obj.upgrade() else {
+                return gst::PadProbeReturn::Remove;
+            };
+
+            let caps = ev.caps_owned();
+            obj.imp().analyze_pad(pad, &caps, false);
+            obj.imp().finish_pending();
+
+            gst::PadProbeReturn::Remove
+        });
+    }
+
+    /// Exposes `pad` if `caps` are final, otherwise tries to plug an element
+    /// that can handle them
+    fn analyze_pad(&self, pad: &gst::Pad, caps: &gst::Caps, is_input: bool) {
+        gst::debug!(CAT, imp: self, "Analyzing {} with caps {}", pad.name(), caps);
+
+        let final_caps = self.settings.lock().unwrap().caps.clone();
+        if caps.can_intersect(&final_caps) {
+            self.expose_pad(pad);
+            return;
+        }
+
+        // Don't plug the same parser again after itself
+        let upstream_factory = pad.parent_element().and_then(|element| element.factory());
+
+        let factories = {
+            let mut state = self.state.lock().unwrap();
+            state
+                .factories
+                .get_or_insert_with(collection_factories)
+                .iter()
+                .filter(|factory| Some(*factory) != upstream_factory.as_ref())
+                .filter(|factory| factory.can_sink_any_caps(caps))
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+
+        for factory in factories {
+            if self.try_factory(pad, &factory) {
+                return;
+            }
+        }
+
+        if is_input {
+            gst::element_imp_error!(
+                self,
+                gst::StreamError::CodecNotFound,
+                ["No element from this plugin collection can handle {}", caps]
+            );
+        } else {
+            gst::element_imp_warning!(
+                self,
+                gst::StreamError::CodecNotFound,
+                ["Ignoring stream with unsupported caps {}", caps]
+            );
+        }
+    }
+
+    /// Plugs an element from `factory` after `pad`
+    fn try_factory(&self, pad: &gst::Pad, factory: &gst::ElementFactory) -> bool {
+        let obj = self.obj();
+
+        gst::debug!(CAT, imp: self, "Trying {} for {}", factory.name(), pad.name());
+
+        let element = match factory.create().build() {
+            Ok(element) => element,
+            Err(err) => {
+                gst::warning!(CAT, imp: self, "Failed to create {}: {}", factory.name(), err);
+                return false;
+            }
+        };
+
+        let Some(sinkpad) = element.static_pad("sink") else {
+            gst::debug!(CAT, imp: self, "{} has no sink pad", factory.name());
+            return false;
+        };
+
+        if obj.add(&element).is_err() {
+            return false;
+        }
+
+        let has_sometimes_pads = factory.static_pad_templates().iter().any(|templ| {
+            templ.direction() == gst::PadDirection::Src
+                && templ.presence() == gst::PadPresence::Sometimes
+        });
+        if has_sometimes_pads {
+            self.state.lock().unwrap().pending += 1;
+
+            let weak_obj = obj.downgrade();
+            element.connect_pad_added(move |_, pad| {
+                if pad.direction() != gst::PadDirection::Src {
+                    return;
+                }
+                if let Some(obj) = weak_obj.upgrade() {
+                    obj.imp().handle_pad(pad);
+                }
+            });
+
+            let weak_obj = obj.downgrade();
+            element.connect_no_more_pads(move |_| {
+                if let Some(obj) = weak_obj.upgrade() {
+                    obj.imp().finish_pending();
+                }
+            });
+        }
+
+        if element.sync_state_with_parent().is_err() || pad.link(&sinkpad).is_err() {
+            gst::debug!(CAT, imp: self, "Failed to plug {}", factory.name());
+
+            if has_sometimes_pads {
+                let mut state = self.state.lock().unwrap();
+                state.pending = state.pending.saturating_sub(1);
+            }
+            let _ = element.set_state(gst::State::Null);
+            let _ = obj.remove(&element);
+
+            return false;
+        }
+
+        gst::info!(CAT, imp: self, "Plugged {} after {}", element.name(), pad.name());
+        self.state.lock().unwrap().elements.push(element.clone());
+
+        for srcpad in element.src_pads() {
+            self.handle_pad(&srcpad);
+        }
+
+        true
+    }
+
+    fn expose_pad(&self, pad: &gst::Pad) {
+        let obj = self.obj();
+
+        let name = {
+            let mut state = self.state.lock().unwrap();
+            let name = format!("src_{}", state.num_srcpads);
+            state.num_srcpads += 1;
+            name
+        };
+
+        gst::info!(CAT, imp: self, "Exposing {} as {}", pad.name(), name);
+
+        let templ = obj.pad_template("src_%u").unwrap();
+        let ghostpad = gst::GhostPad::builder_from_template(&templ)
+            .name(name.as_str())
+            .build();
+        ghostpad.set_target(Some(pad)).unwrap();
+        ghostpad.set_active(true).unwrap();
+        obj.add_pad(&ghostpad).unwrap();
+    }
+
+    fn reset(&self) {
+        let obj = self.obj();
+        let state = std::mem::take(&mut *self.state.lock().unwrap());
+
+        for element in state.elements {
+            let _ = element.set_state(gst::State::Null);
+            let _ = obj.remove(&element);
+        }
+
+        for pad in obj.src_pads() {
+            let _ = obj.remove_pad(&pad);
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for DecodeBin {
+    const NAME: &'static str = "GstRsDecodeBin";
+    type Type = super::DecodeBin;
+    type ParentType = gst::Bin;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("sink").unwrap();
+        let sinkpad = gst::GhostPad::from_template(&templ);
+        let typefind = gst::ElementFactory::make("typefind")
+            .name("typefind")
+            .build()
+            .expect("typefind not available");
+
+        Self {
+            sinkpad,
+            typefind,
+            settings: Mutex::new(Settings::default()),
+            state: Mutex::new(State::default()),
+        }
+    }
+}
+
+impl ObjectImpl for DecodeBin {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![glib::ParamSpecBoxed::builder::<gst::Caps>("caps")
+                .nick("Caps")
+                .blurb("The caps on which to stop decoding")
+                .mutable_ready()
+                .build()]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "caps" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.caps = value
+                    .get::<Option<gst::Caps>>()
+                    .expect("type checked upstream")
+                    .unwrap_or_else(|| DEFAULT_CAPS.clone());
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "caps" => {
+                let settings = self.settings.lock().unwrap();
+                settings.caps.to_value()
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add(&self.typefind).unwrap();
+        self.sinkpad
+            .set_target(Some(&self.typefind.static_pad("sink").unwrap()))
+            .unwrap();
+        obj.add_pad(&self.sinkpad).unwrap();
+
+        self.typefind.connect(
+            "have-type",
+            false,
+            glib::clone!(
+                #[weak]
+                obj,
+                #[upgrade_or]
+                None,
+                move |args| {
+                    let typefind = args[0].get::<gst::Element>().unwrap();
+                    let probability = args[1].get::<u32>().unwrap();
+                    let caps = args[2].get::<gst::Caps>().unwrap();
+
+                    gst::debug!(
+                        CAT,
+                        obj: obj,
+                        "Found type {} with probability {}",
+                        caps,
+                        probability
+                    );
+
+                    let srcpad = typefind.static_pad("src").unwrap();
+                    obj.imp().analyze_pad(&srcpad, &caps, true);
+
+                    None
+                }
+            ),
+        );
+    }
+}
+
+impl GstObjectImpl for DecodeBin {}
+
+impl ElementImpl for DecodeBin {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Rust Decoder Bin",
+                "Generic/Bin/Decoder",
+                "Autoplug and decode to raw media using only elements from gst-plugins-rs",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst::Caps::new_any();
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let src_pad_template = gst::PadTemplate::new(
+                "src_%u",
+                gst::PadDirection::Src,
+                gst::PadPresence::Sometimes,
+                &caps,
+            )
+            .unwrap();
+
+            vec![sink_pad_template, src_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        let res = self.parent_change_state(transition)?;
+
+        if transition == gst::StateChange::PausedToReady {
+            self.reset();
+        }
+
+        Ok(res)
+    }
+}
+
+impl BinImpl for DecodeBin {}
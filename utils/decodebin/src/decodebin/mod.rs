@@ -0,0 +1,49 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * element-rsdecodebin:
+ *
+ * Autoplugging bin that decodes its input using only elements from the
+ * gst-plugins-rs collection.
+ *
+ * The input is typefound and then demuxers, parsers, depayloaders and decoders
+ * are plugged until the stream matches `caps`, which by default are raw audio,
+ * video and text. Only factories of plugins that originate from this
+ * collection are considered, e.g. `rsflvdemux`, `claxondec`, `lewtondec` or
+ * `dav1ddec`, so applications can rely on the Rust implementations without
+ * other elements sneaking in. Factories are tried by descending rank.
+ *
+ * Each decoded stream is exposed on a `src_%u` pad. If no element can handle
+ * the typefound input, an error is posted. Streams of a demuxer that can't be
+ * decoded are ignored with a warning.
+ *
+ * ## Example launch line
+ * ```
+ * gst-launch-1.0 filesrc location=test.flac ! rsdecodebin ! audioconvert ! autoaudiosink
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct DecodeBin(ObjectSubclass<imp::DecodeBin>) @extends gst::Bin, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "rsdecodebin",
+        gst::Rank::NONE,
+        DecodeBin::static_type(),
+    )
+}
@@ -0,0 +1,95 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+
+use std::path::PathBuf;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsdecodebin::plugin_register_static().expect("rsdecodebin test");
+        gstcdg::plugin_register_static().expect("rsdecodebin test");
+    });
+}
+
+#[test]
+fn test_decode_cdg() {
+    init();
+
+    let input_path = {
+        let mut r = PathBuf::new();
+        r.push(env!("CARGO_MANIFEST_DIR"));
+        r.push("..");
+        r.push("..");
+        r.push("video");
+        r.push("cdg");
+        r.push("tests");
+        r.push("BrotherJohn");
+        r.set_extension("cdg");
+        r
+    };
+
+    let pipeline = gst::Pipeline::new();
+    let filesrc = gst::ElementFactory::make("filesrc")
+        .property("location", input_path.to_str().unwrap())
+        .build()
+        .unwrap();
+    let decodebin = gst::ElementFactory::make("rsdecodebin").build().unwrap();
+    let sink = gst_app::AppSink::builder().sync(false).build();
+
+    pipeline
+        .add_many([&filesrc, &decodebin, sink.upcast_ref()])
+        .unwrap();
+    filesrc.link(&decodebin).unwrap();
+
+    let sinkpad = sink.static_pad("sink").unwrap();
+    decodebin.connect_pad_added(move |_, pad| {
+        pad.link(&sinkpad).unwrap();
+    });
+
+    pipeline.set_state(gst::State::Playing).unwrap();
+
+    let sample = sink.pull_sample().unwrap();
+    let s = sample.caps().unwrap().structure(0).unwrap();
+    assert_eq!(s.name(), "video/x-raw");
+
+    pipeline.set_state(gst::State::Null).unwrap();
+}
+
+#[test]
+fn test_unsupported_input() {
+    init();
+
+    let pipeline = gst::Pipeline::new();
+    let src = gst_app::AppSrc::builder()
+        .caps(&gst::Caps::builder("application/x-unsupported").build())
+        .build();
+    let decodebin = gst::ElementFactory::make("rsdecodebin").build().unwrap();
+
+    pipeline.add_many([src.upcast_ref(), &decodebin]).unwrap();
+    src.link(&decodebin).unwrap();
+
+    pipeline.set_state(gst::State::Playing).unwrap();
+    src.push_buffer(gst::Buffer::with_size(16).unwrap())
+        .unwrap();
+
+    let bus = pipeline.bus().unwrap();
+    let msg = bus
+        .timed_pop_filtered(gst::ClockTime::from_seconds(5), &[gst::MessageType::Error])
+        .expect("No error");
+    let gst::MessageView::Error(err) = msg.view() else {
+        unreachable!();
+    };
+    assert!(err.error().matches(gst::StreamError::CodecNotFound));
+
+    pipeline.set_state(gst::State::Null).unwrap();
+}
@@ -267,7 +267,7 @@ impl ObjectImpl for FallbackSrc {
                     .mutable_ready()
                     .build(),
                 glib::ParamSpecUInt64::builder("restart-timeout")
-                    .nick("Timeout")
+                    .nick("Restart Timeout")
                     .blurb("Timeout for restarting an active source")
                     .maximum(u64::MAX - 1)
                     .default_value(5 * *gst::ClockTime::SECOND)
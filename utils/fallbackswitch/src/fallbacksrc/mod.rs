@@ -6,6 +6,28 @@
 //
 // SPDX-License-Identifier: MPL-2.0
 
+/**
+ * element-fallbacksrc:
+ *
+ * `fallbacksrc` is a source bin that plays back `uri` (or a custom `source`) and makes
+ * sure that audio and video are output continuously, which is useful for 24/7 playout.
+ *
+ * If the source stops producing data for `timeout`, the output switches to the
+ * `fallback-uri` or, if none is configured, to a placeholder stream of black video and
+ * silence. The source is then restarted after `restart-timeout` until it works again,
+ * or for at most `retry-timeout` after which an error is posted. The current state of
+ * the source is reported via the `status` property and details are available via the
+ * `statistics` property.
+ *
+ * ## Example pipeline
+ * ```bash
+ * gst-launch-1.0 fallbacksrc uri=rtsp://127.0.0.1:8554/stream name=s \
+ *   s.video ! videoconvert ! autovideosink \
+ *   s.audio ! audioconvert ! autoaudiosink
+ * ```
+ *
+ * Since: plugins-rs-0.6.0
+ */
 use gst::glib;
 use gst::prelude::*;
 
@@ -38,6 +60,12 @@ glib::wrapper! {
 }
 
 pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    #[cfg(feature = "doc")]
+    {
+        RetryReason::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+        Status::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+    }
+
     gst::Element::register(
         Some(plugin),
         "fallbacksrc",
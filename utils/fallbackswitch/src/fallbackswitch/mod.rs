@@ -9,6 +9,24 @@
 //
 // SPDX-License-Identifier: MPL-2.0
 
+/**
+ * element-fallbackswitch:
+ *
+ * `fallbackswitch` is a priority based input selector. It forwards the stream of the
+ * sink pad with the lowest `priority` value that is healthy, i.e. that produced data
+ * within `timeout`, and switches back to higher priority pads once they are healthy
+ * again. The currently selected pad is available via the `active-pad` property.
+ *
+ * ## Example pipeline
+ * ```bash
+ * gst-launch-1.0 videotestsrc is-live=true num-buffers=100 ! s.sink_0 \
+ *   videotestsrc is-live=true pattern=ball ! s.sink_1 \
+ *   fallbackswitch name=s sink_0::priority=0 sink_1::priority=1 timeout=1000000000 ! \
+ *   videoconvert ! autovideosink
+ * ```
+ *
+ * Since: plugins-rs-0.6.0
+ */
 use gst::glib;
 use gst::prelude::*;
 
@@ -92,6 +92,9 @@ struct State {
     /// See `PROP_SYNC`
     sync: bool,
 
+    /// See `PROP_GAP_EVENTS`
+    gap_events: bool,
+
     /// Latency reported by upstream
     upstream_latency: Option<gst::ClockTime>,
 
@@ -166,6 +169,7 @@ const PROP_LATENCY: &str = "latency";
 const PROP_LATE_THRESHOLD: &str = "late-threshold";
 const PROP_SINGLE_SEGMENT: &str = "single-segment";
 const PROP_SYNC: &str = "sync";
+const PROP_GAP_EVENTS: &str = "gap-events";
 
 const PROP_IN: &str = "in";
 const PROP_DROP: &str = "drop";
@@ -186,6 +190,7 @@ impl Default for State {
             late_threshold: DEFAULT_LATE_THRESHOLD,
             single_segment: false,
             sync: true,
+            gap_events: false,
             upstream_latency: None,
             playing: false,
             eos: false,
@@ -296,7 +301,7 @@ impl ObjectSubclass for LiveSync {
 
 impl ObjectImpl for LiveSync {
     fn properties() -> &'static [glib::ParamSpec] {
-        static PROPERTIES: Lazy<[glib::ParamSpec; 8]> = Lazy::new(|| {
+        static PROPERTIES: Lazy<[glib::ParamSpec; 9]> = Lazy::new(|| {
             [
                 glib::ParamSpecUInt64::builder(PROP_LATENCY)
                     .nick("Latency")
@@ -328,6 +333,11 @@ impl ObjectImpl for LiveSync {
                     .blurb("Synchronize buffers to the clock")
                     .mutable_ready()
                     .build(),
+                glib::ParamSpecBoolean::builder(PROP_GAP_EVENTS)
+                    .nick("Gap events")
+                    .blurb("Send gap events instead of repeating the last buffer when the input stalls")
+                    .mutable_playing()
+                    .build(),
                 glib::ParamSpecUInt64::builder(PROP_IN)
                     .nick("Frames input")
                     .blurb("Number of incoming frames accepted")
@@ -383,6 +393,10 @@ impl ObjectImpl for LiveSync {
                 state.sync = value.get().unwrap();
             }
 
+            PROP_GAP_EVENTS => {
+                state.gap_events = value.get().unwrap();
+            }
+
             _ => unimplemented!(),
         }
     }
@@ -394,6 +408,7 @@ impl ObjectImpl for LiveSync {
             PROP_LATE_THRESHOLD => state.late_threshold.to_value(),
             PROP_SINGLE_SEGMENT => state.single_segment.to_value(),
             PROP_SYNC => state.sync.to_value(),
+            PROP_GAP_EVENTS => state.gap_events.to_value(),
             PROP_IN => state.num_in.to_value(),
             PROP_DROP => state.num_drop.to_value(),
             PROP_OUT => state.num_out.to_value(),
@@ -1239,6 +1254,20 @@ impl LiveSync {
             state.out_segment = Some(segment);
         }
 
+        if state.out_buffer_duplicate && state.gap_events {
+            if let Some(timestamp) = buffer.pts().or(buffer.dts()) {
+                drop(state);
+
+                let event = gst::event::Gap::builder(timestamp)
+                    .duration(buffer.duration())
+                    .build();
+                gst::trace!(CAT, imp: self, "Pushing {event:?}");
+                self.srcpad.push_event(event);
+
+                return Ok(gst::FlowSuccess::Ok);
+            }
+        }
+
         state.num_out += 1;
 
         drop(state);
@@ -7,6 +7,25 @@
 //
 // SPDX-License-Identifier: MPL-2.0
 
+/**
+ * element-livesync:
+ *
+ * Outputs a continuous live stream from an unreliable input, e.g. a network
+ * source, at a fixed latency. Buffers arriving too early are held back and
+ * buffers arriving too late are dropped.
+ *
+ * When the input stalls, the last buffer is repeated with the `GAP` flag set
+ * (or replaced by silence for raw audio) until new input arrives. If
+ * #livesync:gap-events is set, gap events are sent instead of repeated
+ * buffers.
+ *
+ * ## Example pipeline
+ * ```bash
+ * gst-launch-1.0 udpsrc port=5000 caps=application/x-rtp,media=video,encoding-name=H264,clock-rate=90000 ! rtph264depay ! avdec_h264 ! livesync latency=200000000 ! x264enc ! fakesink
+ * ```
+ *
+ * Since: plugins-rs-0.9.0
+ */
 use gst::{glib, prelude::*};
 
 mod imp;
@@ -204,3 +204,46 @@ fn test_livesync(h: &mut gst_check::Harness, o: u64, singlesegment: bool) {
     assert_eq!(h.pull_event().unwrap().type_(), gst::EventType::Eos);
     assert_eq!(h.try_pull(), None);
 }
+
+#[test]
+fn test_video_gap_events() {
+    init();
+
+    let mut h = gst_check::Harness::new("livesync");
+    h.add_src_parse(
+        r"videotestsrc is-live=1
+          ! capsfilter caps=video/x-raw,framerate=10/1
+        ",
+        true,
+    );
+
+    let element = h.element().unwrap();
+    element.set_property("latency", LATENCY);
+    element.set_property("single-segment", true);
+    element.set_property("gap-events", true);
+
+    // Push frames 0-1, pull frames 0-1
+    h.push_from_src().unwrap();
+    h.push_from_src().unwrap();
+    assert_eq!(h.pull_event().unwrap().type_(), gst::EventType::StreamStart);
+    h.crank_single_clock_wait().unwrap();
+    assert_eq!(h.pull_event().unwrap().type_(), gst::EventType::Caps);
+    assert_eq!(h.pull_event().unwrap().type_(), gst::EventType::Segment);
+    assert_crank_pull(&mut h, 1, 0, 0, gst::BufferFlags::DISCONT, true);
+    assert_crank_pull(&mut h, 1, 1, 1, gst::BufferFlags::empty(), true);
+
+    // The input stalls, frames 2-4 are gaps
+    for i in 2..=4 {
+        h.crank_single_clock_wait().unwrap();
+        let event = h.pull_event().unwrap();
+        let gst::EventView::Gap(gap) = event.view() else {
+            panic!("Expected gap event, got {event:?}");
+        };
+        assert_eq!(
+            gap.get(),
+            (LATENCY + DURATION * i + SEGMENT_OFFSET, Some(DURATION))
+        );
+    }
+    assert_eq!(element.property::<u64>("out"), 2);
+    assert_eq!(element.property::<u64>("duplicate"), 3);
+}
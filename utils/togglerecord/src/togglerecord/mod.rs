@@ -6,6 +6,33 @@
 //
 // SPDX-License-Identifier: MPL-2.0
 
+/**
+ * element-togglerecord:
+ *
+ * `togglerecord` is a valve for multiple synchronized streams, e.g. the audio and video
+ * of a recording, that makes sure that all streams start and stop at the same time
+ * when the `record` property is toggled.
+ *
+ * The first stream (`sink`/`src`) is the main stream and decides on the exact start
+ * and stop times. Secondary streams (`sink_%u`/`src_%u`) are clipped to these times,
+ * so each recorded part contains all streams for the same time range.
+ *
+ * While not recording, live input is dropped and non-live input is blocked. If
+ * `is-live` is not set, the running time of the output is offset to collapse the
+ * time that was not recorded, resulting in a gapless file. Whether recording currently
+ * takes place is reported via the `recording` property.
+ *
+ * ## Example pipeline
+ * ```bash
+ * gst-launch-1.0 -e videotestsrc is-live=true ! x264enc tune=zerolatency ! t.sink \
+ *   audiotestsrc is-live=true ! opusenc ! t.sink_0 \
+ *   togglerecord name=t record=true \
+ *   t.src ! queue ! mux. t.src_0 ! queue ! mux. \
+ *   matroskamux name=mux ! filesink location=recording.mkv
+ * ```
+ *
+ * Since: plugins-rs-0.1.0
+ */
 use gst::glib;
 use gst::prelude::*;
 
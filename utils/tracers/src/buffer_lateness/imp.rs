@@ -32,6 +32,14 @@
  *
  * By default the file is written to `/tmp/buffer_lateness.log`.
  *
+ * ### `log`
+ *
+ * If set to `true`, each measurement is additionally logged as a structure with the
+ * same fields as the CSV file in the `buffer-lateness` debug category at `INFO` level while
+ * the pipeline is running, e.g. for collecting it with `GST_DEBUG=buffer-lateness:4`.
+ *
+ * By default this is `false`.
+ *
  * ### `include-filter`
  *
  * Specifies a regular expression for the `element:pad` names that should be included.
@@ -68,6 +76,7 @@ struct Settings {
     file: PathBuf,
     include_filter: Option<Regex>,
     exclude_filter: Option<Regex>,
+    log: bool,
 }
 
 impl Default for Settings {
@@ -79,6 +88,7 @@ impl Default for Settings {
             file,
             include_filter: None,
             exclude_filter: None,
+            log: false,
         }
     }
 }
@@ -131,6 +141,11 @@ impl Settings {
             };
             self.exclude_filter = filter;
         }
+
+        if let Ok(log) = s.get::<bool>("log") {
+            gst::log!(CAT, imp: imp, "log= {}", log);
+            self.log = log;
+        }
     }
 }
 
@@ -158,6 +173,20 @@ struct LogLine {
     min_latency: u64,
 }
 
+impl LogLine {
+    fn to_structure(&self) -> gst::Structure {
+        gst::Structure::builder("buffer-lateness")
+            .field("timestamp", self.timestamp)
+            .field("pad", format!("{}:{}", self.element_name, self.pad_name))
+            .field("ptr", format!("0x{:08x}", self.ptr))
+            .field("buffer-clock-time", self.buffer_clock_time)
+            .field("pipeline-clock-time", self.pipeline_clock_time)
+            .field("lateness", self.lateness)
+            .field("min-latency", self.min_latency)
+            .build()
+    }
+}
+
 #[derive(Default)]
 pub struct BufferLateness {
     state: Mutex<State>,
@@ -335,7 +364,7 @@ impl TracerImpl for BufferLateness {
                 None => return,
             };
 
-            log.push(LogLine {
+            let line = LogLine {
                 timestamp: ts,
                 element_name: element_name.clone(),
                 pad_name: pad.pad_name.clone(),
@@ -348,7 +377,13 @@ impl TracerImpl for BufferLateness {
                     (pipeline_clock_time.nseconds() - buffer_clock_time.nseconds()) as i64
                 },
                 min_latency: pad.latency,
-            });
+            };
+
+            if settings.log {
+                gst::info!(CAT, imp: self, "{}", line.to_structure());
+            }
+
+            log.push(line);
         }
     }
 
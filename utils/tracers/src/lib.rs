@@ -15,9 +15,14 @@
 use gst::glib;
 
 mod buffer_lateness;
+#[cfg(feature = "v1_22")]
+mod memory_usage;
+mod mini_object_leaks;
 mod pad_push_timings;
+mod pcap_writer;
 #[cfg(unix)]
 mod pipeline_snapshot;
+mod push_flamegraph;
 mod queue_levels;
 
 fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
@@ -26,6 +31,11 @@ fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
     queue_levels::register(plugin)?;
     buffer_lateness::register(plugin)?;
     pad_push_timings::register(plugin)?;
+    pcap_writer::register(plugin)?;
+    push_flamegraph::register(plugin)?;
+    mini_object_leaks::register(plugin)?;
+    #[cfg(feature = "v1_22")]
+    memory_usage::register(plugin)?;
     Ok(())
 }
 
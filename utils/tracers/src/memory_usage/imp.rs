@@ -0,0 +1,424 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * tracer-memory-usage:
+ *
+ * This tracer keeps track of all `GstMemory` that is allocated and freed, and attributes it to
+ * the element that was running in the current thread at allocation time. This allows finding
+ * leaks and oversized buffer pools in long-running pipelines.
+ *
+ * An allocation is attributed to the element whose chain function is currently called in the
+ * calling thread, or otherwise to the element that last pushed data from this thread, e.g. the
+ * source element that runs the streaming thread. Allocations that can't be attributed to any
+ * element are accounted for as `(unknown)`.
+ *
+ * Requires GStreamer 1.22 or newer.
+ *
+ * Example:
+ *
+ * ```console
+ * $ GST_TRACERS='memory-usage(file="/tmp/memory_usage.log")' gst-launch-1.0 videotestsrc num-buffers=100 ! queue ! fakesink
+ * ```
+ *
+ * When the tracer is destroyed a CSV file of the format
+ *
+ * ```csv
+ * element name,element pointer,live bytes,live memories,peak bytes,total bytes,total memories
+ * ```
+ *
+ * is written with one line per element. Memory that is still alive at that point has most likely
+ * leaked.
+ *
+ * ## Parameters
+ *
+ * ### `file`
+ *
+ * Specifies the path to the file that will collect the CSV file with the memory usage.
+ *
+ * By default the file is written to `/tmp/memory_usage.log`.
+ *
+ * ### `include-filter`
+ *
+ * Specifies a regular expression for the element names that should be included.
+ *
+ * By default this is not set.
+ *
+ * ### `exclude-filter`
+ *
+ * Specifies a regular expression for the element names that should **not** be included.
+ *
+ * By default this is not set.
+ */
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "memory-usage",
+        gst::DebugColorFlags::empty(),
+        Some("Tracer to collect memory usage per element"),
+    )
+});
+
+/// Index of the statistics for memory that can't be attributed to an element
+const UNKNOWN: usize = 0;
+
+#[derive(Debug)]
+struct Settings {
+    file: PathBuf,
+    include_filter: Option<Regex>,
+    exclude_filter: Option<Regex>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        let mut file = glib::tmp_dir();
+        file.push("memory_usage.log");
+
+        Self {
+            file,
+            include_filter: None,
+            exclude_filter: None,
+        }
+    }
+}
+
+impl Settings {
+    fn update_from_params(&mut self, imp: &MemoryUsage, params: String) {
+        let s = match gst::Structure::from_str(&format!("memory-usage,{params}")) {
+            Ok(s) => s,
+            Err(err) => {
+                gst::warning!(CAT, imp: imp, "failed to parse tracer parameters: {}", err);
+                return;
+            }
+        };
+
+        if let Ok(file) = s.get::<&str>("file") {
+            gst::log!(CAT, imp: imp, "file= {}", file);
+            self.file = PathBuf::from(file);
+        }
+
+        if let Ok(filter) = s.get::<&str>("include-filter") {
+            gst::log!(CAT, imp: imp, "include filter= {}", filter);
+            let filter = match Regex::new(filter) {
+                Ok(filter) => Some(filter),
+                Err(err) => {
+                    gst::error!(
+                        CAT,
+                        imp: imp,
+                        "Failed to compile include-filter regex: {}",
+                        err
+                    );
+                    None
+                }
+            };
+            self.include_filter = filter;
+        }
+
+        if let Ok(filter) = s.get::<&str>("exclude-filter") {
+            gst::log!(CAT, imp: imp, "exclude filter= {}", filter);
+            let filter = match Regex::new(filter) {
+                Ok(filter) => Some(filter),
+                Err(err) => {
+                    gst::error!(
+                        CAT,
+                        imp: imp,
+                        "Failed to compile exclude-filter regex: {}",
+                        err
+                    );
+                    None
+                }
+            };
+            self.exclude_filter = filter;
+        }
+    }
+}
+
+struct State {
+    /// Index into `stats` for each currently existing element
+    elements: HashMap<usize, usize>,
+    /// Statistics of all elements that ever allocated memory, including destroyed ones
+    stats: Vec<ElementStats>,
+    /// Index into `stats` and size of each live memory
+    memories: HashMap<usize, (usize, u64)>,
+    settings: Settings,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            elements: HashMap::new(),
+            stats: vec![ElementStats::new(String::from("(unknown)"), 0)],
+            memories: HashMap::new(),
+            settings: Settings::default(),
+        }
+    }
+}
+
+struct ElementStats {
+    name: String,
+    ptr: usize,
+    live_bytes: u64,
+    live_memories: u64,
+    peak_bytes: u64,
+    total_bytes: u64,
+    total_memories: u64,
+}
+
+impl ElementStats {
+    fn new(name: String, ptr: usize) -> Self {
+        Self {
+            name,
+            ptr,
+            live_bytes: 0,
+            live_memories: 0,
+            peak_bytes: 0,
+            total_bytes: 0,
+            total_memories: 0,
+        }
+    }
+}
+
+/// Elements currently running in a thread
+#[derive(Default)]
+struct ThreadElements {
+    /// Element that pushed the outermost buffer in this thread
+    owner: Option<usize>,
+    /// Elements whose chain functions are currently called, innermost last
+    stack: Vec<Option<usize>>,
+}
+
+impl ThreadElements {
+    fn current(&self) -> Option<usize> {
+        self.stack.iter().rev().find_map(|idx| *idx).or(self.owner)
+    }
+}
+
+thread_local! {
+    static THREAD_ELEMENTS: RefCell<ThreadElements> = RefCell::new(ThreadElements::default());
+}
+
+#[derive(Default)]
+pub struct MemoryUsage {
+    state: Mutex<State>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for MemoryUsage {
+    const NAME: &'static str = "GstMemoryUsage";
+    type Type = super::MemoryUsage;
+    type ParentType = gst::Tracer;
+}
+
+impl ObjectImpl for MemoryUsage {
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        if let Some(params) = self.obj().property::<Option<String>>("params") {
+            let mut state = self.state.lock().unwrap();
+            state.settings.update_from_params(self, params);
+        }
+
+        self.register_hook(TracerHook::PadPushPre);
+        self.register_hook(TracerHook::PadPushListPre);
+        self.register_hook(TracerHook::PadPushPost);
+        self.register_hook(TracerHook::PadPushListPost);
+        self.register_hook(TracerHook::MemoryInit);
+        self.register_hook(TracerHook::MemoryFreePre);
+        self.register_hook(TracerHook::ObjectDestroyed);
+    }
+
+    fn dispose(&self) {
+        use std::io::prelude::*;
+
+        let state = self.state.lock().unwrap();
+
+        let mut file = match std::fs::File::create(&state.settings.file) {
+            Ok(file) => file,
+            Err(err) => {
+                gst::error!(CAT, imp: self, "Failed to create file: {err}");
+                return;
+            }
+        };
+
+        gst::debug!(
+            CAT,
+            imp: self,
+            "Writing file {}",
+            state.settings.file.display()
+        );
+
+        for ElementStats {
+            name,
+            ptr,
+            live_bytes,
+            live_memories,
+            peak_bytes,
+            total_bytes,
+            total_memories,
+        } in &state.stats
+        {
+            if *total_memories == 0 {
+                continue;
+            }
+
+            if *live_memories > 0 {
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "{name} still has {live_memories} memories with {live_bytes} bytes alive"
+                );
+            }
+
+            if let Err(err) = writeln!(&mut file, "{name},0x{ptr:08x},{live_bytes},{live_memories},{peak_bytes},{total_bytes},{total_memories}") {
+                gst::error!(CAT, imp: self, "Failed to write to file: {err}");
+                return;
+            }
+        }
+    }
+}
+
+impl GstObjectImpl for MemoryUsage {}
+
+impl TracerImpl for MemoryUsage {
+    fn pad_push_pre(&self, _ts: u64, pad: &gst::Pad, _buffer: &gst::Buffer) {
+        self.push_pre(pad);
+    }
+
+    fn pad_push_list_pre(&self, _ts: u64, pad: &gst::Pad, _list: &gst::BufferList) {
+        self.push_pre(pad);
+    }
+
+    fn pad_push_post(
+        &self,
+        _ts: u64,
+        _pad: &gst::Pad,
+        _result: Result<gst::FlowSuccess, gst::FlowError>,
+    ) {
+        self.push_post();
+    }
+
+    fn pad_push_list_post(
+        &self,
+        _ts: u64,
+        _pad: &gst::Pad,
+        _result: Result<gst::FlowSuccess, gst::FlowError>,
+    ) {
+        self.push_post();
+    }
+
+    fn memory_init(&self, _ts: u64, mem: &gst::MemoryRef) {
+        // Shared sub-memories don't allocate anything themselves
+        if mem.parent().is_some() {
+            return;
+        }
+
+        let idx = THREAD_ELEMENTS
+            .with(|elements| elements.borrow().current())
+            .unwrap_or(UNKNOWN);
+        let size = mem.maxsize() as u64;
+
+        let mut state = self.state.lock().unwrap();
+        state.memories.insert(mem.as_ptr() as usize, (idx, size));
+
+        let stats = &mut state.stats[idx];
+        stats.live_bytes += size;
+        stats.live_memories += 1;
+        stats.peak_bytes = stats.peak_bytes.max(stats.live_bytes);
+        stats.total_bytes += size;
+        stats.total_memories += 1;
+    }
+
+    fn memory_free_pre(&self, _ts: u64, mem: &gst::MemoryRef) {
+        let mut state = self.state.lock().unwrap();
+        let Some((idx, size)) = state.memories.remove(&(mem.as_ptr() as usize)) else {
+            return;
+        };
+
+        let stats = &mut state.stats[idx];
+        stats.live_bytes -= size;
+        stats.live_memories -= 1;
+    }
+
+    fn object_destroyed(&self, _ts: u64, object: std::ptr::NonNull<gst::ffi::GstObject>) {
+        let ptr = object.as_ptr() as usize;
+        let mut state = self.state.lock().unwrap();
+        state.elements.remove(&ptr);
+    }
+}
+
+impl MemoryUsage {
+    /// Returns the index into the statistics for this element
+    fn element_idx(&self, element: &gst::Element) -> usize {
+        let ptr = element.as_ptr() as usize;
+        let mut state = self.state.lock().unwrap();
+
+        if let Some(idx) = state.elements.get(&ptr) {
+            return *idx;
+        }
+
+        let name = element.name();
+        let mut include = true;
+        if let Some(ref filter) = state.settings.include_filter {
+            if !filter.is_match(&name) {
+                include = false;
+            }
+        }
+        if let Some(ref filter) = state.settings.exclude_filter {
+            if filter.is_match(&name) {
+                include = false;
+            }
+        }
+
+        let idx = if include {
+            state.stats.push(ElementStats::new(name.to_string(), ptr));
+            state.stats.len() - 1
+        } else {
+            UNKNOWN
+        };
+        state.elements.insert(ptr, idx);
+
+        idx
+    }
+
+    fn push_pre(&self, pad: &gst::Pad) {
+        let pusher = pad
+            .parent_element()
+            .map(|element| self.element_idx(&element));
+        // The peer is a proxy pad of a ghost pad if it has no parent element, in which case the
+        // allocations stay attributed to the current element until the data reaches the next one
+        let receiver = pad
+            .peer()
+            .and_then(|peer| peer.parent_element())
+            .map(|element| self.element_idx(&element));
+
+        THREAD_ELEMENTS.with(|elements| {
+            let mut elements = elements.borrow_mut();
+            if elements.stack.is_empty() && pusher.is_some() {
+                elements.owner = pusher;
+            }
+            elements.stack.push(receiver);
+        });
+    }
+
+    fn push_post(&self) {
+        THREAD_ELEMENTS.with(|elements| {
+            elements.borrow_mut().stack.pop();
+        });
+    }
+}
@@ -0,0 +1,279 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * tracer-mini-object-leaks:
+ *
+ * This tracer keeps track of mini objects like buffers, events and caps, and reports the ones that
+ * were created while a pipeline was running and are still alive after the pipeline was stopped
+ * by shutting it down to the `READY` state or below. Such objects are most likely leaked because
+ * of a reference counting bug in an element or in the application.
+ *
+ * For each pipeline that is stopped, the leaked objects are logged as warnings in the
+ * `mini-object-leaks` debug category, including the backtrace of their creation if enabled, and
+ * an element message with a summary is posted on the pipeline's bus. The message is called
+ * `mini-object-leaks` and contains the total number of leaked objects in the `total` field and the
+ * number of leaked objects per type in a field named after the type, e.g. `GstBuffer`.
+ *
+ * Example:
+ *
+ * ```console
+ * $ GST_TRACERS='mini-object-leaks(backtraces=true)' GST_DEBUG=mini-object-leaks:2 gst-launch-1.0 audiotestsrc num-buffers=10 ! fakesink
+ * ```
+ *
+ * ## Parameters
+ *
+ * ### `filter`
+ *
+ * Specifies a comma separated list of the type names of the mini objects that should be tracked.
+ *
+ * By default this is `GstBuffer,GstEvent,GstCaps`.
+ *
+ * ### `backtraces`
+ *
+ * If set to `true`, a backtrace is captured when a tracked object is created. This is very
+ * expensive and considerably slows down the pipeline.
+ *
+ * By default this is `false`.
+ */
+use std::backtrace::Backtrace;
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use gst::glib;
+use gst::glib::translate::FromGlib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use once_cell::sync::Lazy;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "mini-object-leaks",
+        gst::DebugColorFlags::empty(),
+        Some("Tracer to find leaked mini objects"),
+    )
+});
+
+const DEFAULT_FILTER: &str = "GstBuffer,GstEvent,GstCaps";
+
+#[derive(Debug, Default)]
+struct Settings {
+    filter: Vec<glib::Type>,
+    backtraces: bool,
+}
+
+impl Settings {
+    fn update_from_params(&mut self, imp: &MiniObjectLeaks, params: Option<String>) {
+        let mut filter = DEFAULT_FILTER.to_string();
+
+        if let Some(params) = params {
+            let s = match gst::Structure::from_str(&format!("mini-object-leaks,{params}")) {
+                Ok(s) => Some(s),
+                Err(err) => {
+                    gst::warning!(CAT, imp: imp, "failed to parse tracer parameters: {}", err);
+                    None
+                }
+            };
+
+            if let Some(s) = s {
+                if let Ok(f) = s.get::<&str>("filter") {
+                    gst::log!(CAT, imp: imp, "filter= {}", f);
+                    filter = f.to_string();
+                }
+
+                if let Ok(backtraces) = s.get::<bool>("backtraces") {
+                    gst::log!(CAT, imp: imp, "backtraces= {}", backtraces);
+                    self.backtraces = backtraces;
+                }
+            }
+        }
+
+        self.filter = filter
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .filter_map(|name| {
+                let type_ = glib::Type::from_name(name);
+                if type_.is_none() {
+                    gst::warning!(CAT, imp: imp, "Unknown type {}", name);
+                }
+                type_
+            })
+            .collect();
+    }
+}
+
+struct LiveObject {
+    type_: glib::Type,
+    created: u64,
+    backtrace: Option<Backtrace>,
+}
+
+#[derive(Default)]
+struct State {
+    objects: HashMap<usize, LiveObject>,
+    /// Time at which each running pipeline was started
+    pipelines: HashMap<usize, u64>,
+    settings: Settings,
+}
+
+#[derive(Default)]
+pub struct MiniObjectLeaks {
+    state: Mutex<State>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for MiniObjectLeaks {
+    const NAME: &'static str = "GstMiniObjectLeaks";
+    type Type = super::MiniObjectLeaks;
+    type ParentType = gst::Tracer;
+}
+
+impl ObjectImpl for MiniObjectLeaks {
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        {
+            let params = self.obj().property::<Option<String>>("params");
+            let mut state = self.state.lock().unwrap();
+            state.settings.update_from_params(self, params);
+        }
+
+        self.register_hook(TracerHook::MiniObjectCreated);
+        self.register_hook(TracerHook::MiniObjectDestroyed);
+        self.register_hook(TracerHook::ElementChangeStatePost);
+        self.register_hook(TracerHook::ObjectDestroyed);
+    }
+
+    fn dispose(&self) {
+        let state = self.state.lock().unwrap();
+        gst::info!(
+            CAT,
+            imp: self,
+            "{} tracked objects still alive",
+            state.objects.len()
+        );
+    }
+}
+
+impl GstObjectImpl for MiniObjectLeaks {}
+
+impl TracerImpl for MiniObjectLeaks {
+    fn mini_object_created(&self, ts: u64, object: std::ptr::NonNull<gst::ffi::GstMiniObject>) {
+        // SAFETY: The type is set before the hook is called and never changes
+        let type_ = unsafe { glib::Type::from_glib((*object.as_ptr()).type_) };
+
+        let backtraces = {
+            let state = self.state.lock().unwrap();
+            if !state.settings.filter.contains(&type_) {
+                return;
+            }
+            state.settings.backtraces
+        };
+
+        // Captured without the lock as this is slow
+        let backtrace = backtraces.then(Backtrace::force_capture);
+
+        let mut state = self.state.lock().unwrap();
+        state.objects.insert(
+            object.as_ptr() as usize,
+            LiveObject {
+                type_,
+                created: ts,
+                backtrace,
+            },
+        );
+    }
+
+    fn mini_object_destroyed(&self, _ts: u64, object: std::ptr::NonNull<gst::ffi::GstMiniObject>) {
+        let mut state = self.state.lock().unwrap();
+        state.objects.remove(&(object.as_ptr() as usize));
+    }
+
+    fn element_change_state_post(
+        &self,
+        ts: u64,
+        element: &gst::Element,
+        change: gst::StateChange,
+        result: Result<gst::StateChangeSuccess, gst::StateChangeError>,
+    ) {
+        if result.is_err() || !element.is::<gst::Pipeline>() {
+            return;
+        }
+
+        let ptr = element.as_ptr() as usize;
+        match change {
+            gst::StateChange::ReadyToPaused => {
+                self.state.lock().unwrap().pipelines.insert(ptr, ts);
+            }
+            gst::StateChange::PausedToReady => {
+                let Some(started) = self.state.lock().unwrap().pipelines.remove(&ptr) else {
+                    return;
+                };
+                self.report(element, started);
+            }
+            _ => (),
+        }
+    }
+
+    fn object_destroyed(&self, _ts: u64, object: std::ptr::NonNull<gst::ffi::GstObject>) {
+        let mut state = self.state.lock().unwrap();
+        state.pipelines.remove(&(object.as_ptr() as usize));
+    }
+}
+
+impl MiniObjectLeaks {
+    /// Reports all objects created since `started` that are still alive
+    fn report(&self, pipeline: &gst::Element, started: u64) {
+        let mut counts = BTreeMap::<&'static str, u32>::new();
+
+        {
+            let state = self.state.lock().unwrap();
+            for (ptr, object) in state
+                .objects
+                .iter()
+                .filter(|(_, object)| object.created >= started)
+            {
+                *counts.entry(object.type_.name()).or_default() += 1;
+
+                if let Some(ref backtrace) = object.backtrace {
+                    gst::warning!(
+                        CAT,
+                        obj: pipeline,
+                        "Leaked {} 0x{:08x} created at\n{}",
+                        object.type_,
+                        ptr,
+                        backtrace
+                    );
+                } else {
+                    gst::warning!(CAT, obj: pipeline, "Leaked {} 0x{:08x}", object.type_, ptr);
+                }
+            }
+        }
+
+        let total = counts.values().sum::<u32>();
+        if total == 0 {
+            gst::debug!(CAT, obj: pipeline, "No leaked objects");
+            return;
+        }
+
+        gst::warning!(CAT, obj: pipeline, "{} leaked objects", total);
+
+        // Posted without the lock as the message itself is a mini object
+        let mut s = gst::Structure::builder("mini-object-leaks").field("total", total);
+        for (type_name, count) in counts {
+            s = s.field(type_name, count);
+        }
+        let _ = pipeline.post_message(
+            gst::message::Element::builder(s.build())
+                .src(pipeline)
+                .build(),
+        );
+    }
+}
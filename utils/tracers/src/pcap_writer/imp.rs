@@ -0,0 +1,490 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * tracer-pcap-writer:
+ *
+ * This tracer captures the data received by network source elements and sent by network sink
+ * elements into a pcapng file, which can then be inspected with tools like Wireshark. This
+ * allows wire-level debugging without the permissions that are needed for capturing on the
+ * network interfaces.
+ *
+ * Each element gets its own interface in the capture file, named after the element. For UDP
+ * elements the payloads are wrapped in synthesized IPv4 and UDP headers with the address and
+ * port configured on the element, for all other elements the payload is stored as is. The
+ * direction of each packet is stored in the packet flags.
+ *
+ * The timestamps are the system time at which the data was pushed out of a source element or
+ * into a sink element.
+ *
+ * Example:
+ *
+ * ```console
+ * $ GST_TRACERS='pcap-writer(file="/tmp/capture.pcapng")' gst-launch-1.0 udpsrc port=5000 ! fakesink
+ * ```
+ *
+ * ## Parameters
+ *
+ * ### `file`
+ *
+ * Specifies the path to the pcapng file.
+ *
+ * By default the file is written to `/tmp/capture.pcapng`.
+ *
+ * ### `elements`
+ *
+ * Specifies a regular expression for the element factory names whose data should be captured.
+ *
+ * By default the UDP, TCP and SRT sources and sinks, including the thread-sharing variants, are
+ * captured.
+ */
+use std::collections::HashMap;
+use std::io::{self, BufWriter, Write};
+use std::net::Ipv4Addr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "pcap-writer",
+        gst::DebugColorFlags::empty(),
+        Some("Tracer to capture network traffic into pcapng files"),
+    )
+});
+
+const DEFAULT_ELEMENTS: &str = "^(ts-)?(udpsrc|udpsink|multiudpsink|dynudpsink|tcpclientsrc|tcpclientsink|tcpserversrc|tcpserversink|srtsrc|srtsink|srtclientsrc|srtclientsink|srtserversrc|srtserversink)$";
+
+const LINKTYPE_USER0: u16 = 147;
+const LINKTYPE_IPV4: u16 = 228;
+
+const BLOCK_TYPE_SECTION_HEADER: u32 = 0x0a0d_0d0a;
+const BLOCK_TYPE_INTERFACE_DESCRIPTION: u32 = 0x0000_0001;
+const BLOCK_TYPE_ENHANCED_PACKET: u32 = 0x0000_0006;
+
+const OPTION_END_OF_OPTIONS: u16 = 0;
+const OPTION_IF_NAME: u16 = 2;
+const OPTION_IF_TSRESOL: u16 = 9;
+const OPTION_EPB_FLAGS: u16 = 2;
+
+#[derive(Debug)]
+struct Settings {
+    file: PathBuf,
+    elements: Regex,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        let mut file = glib::tmp_dir();
+        file.push("capture.pcapng");
+
+        Self {
+            file,
+            elements: Regex::new(DEFAULT_ELEMENTS).unwrap(),
+        }
+    }
+}
+
+impl Settings {
+    fn update_from_params(&mut self, imp: &PcapWriter, params: String) {
+        let s = match gst::Structure::from_str(&format!("pcap-writer,{params}")) {
+            Ok(s) => s,
+            Err(err) => {
+                gst::warning!(CAT, imp: imp, "failed to parse tracer parameters: {}", err);
+                return;
+            }
+        };
+
+        if let Ok(file) = s.get::<&str>("file") {
+            gst::log!(CAT, imp: imp, "file= {}", file);
+            self.file = PathBuf::from(file);
+        }
+
+        if let Ok(elements) = s.get::<&str>("elements") {
+            gst::log!(CAT, imp: imp, "elements= {}", elements);
+            match Regex::new(elements) {
+                Ok(elements) => self.elements = elements,
+                Err(err) => {
+                    gst::error!(
+                        CAT,
+                        imp: imp,
+                        "Failed to compile elements regex: {}",
+                        err
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Inbound,
+    Outbound,
+}
+
+/// Addresses used for the synthesized IPv4 and UDP headers
+#[derive(Debug, Clone, Copy)]
+struct UdpEndpoints {
+    src: (Ipv4Addr, u16),
+    dst: (Ipv4Addr, u16),
+}
+
+struct Interface {
+    id: u32,
+    udp: Option<UdpEndpoints>,
+}
+
+#[derive(Default)]
+struct State {
+    settings: Settings,
+    writer: Option<BufWriter<std::fs::File>>,
+    next_interface_id: u32,
+    /// Interface for each captured element
+    interfaces: HashMap<usize, Interface>,
+    /// Captured element and direction for each pad, or `None` if the pad is not captured
+    pads: HashMap<usize, Option<(usize, Direction)>>,
+}
+
+#[derive(Default)]
+pub struct PcapWriter {
+    state: Mutex<State>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for PcapWriter {
+    const NAME: &'static str = "GstPcapWriter";
+    type Type = super::PcapWriter;
+    type ParentType = gst::Tracer;
+}
+
+impl ObjectImpl for PcapWriter {
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let mut state = self.state.lock().unwrap();
+        if let Some(params) = self.obj().property::<Option<String>>("params") {
+            state.settings.update_from_params(self, params);
+        }
+
+        let mut writer = match std::fs::File::create(&state.settings.file) {
+            Ok(file) => BufWriter::new(file),
+            Err(err) => {
+                gst::error!(CAT, imp: self, "Failed to create file: {err}");
+                return;
+            }
+        };
+
+        if let Err(err) = write_section_header(&mut writer) {
+            gst::error!(CAT, imp: self, "Failed to write to file: {err}");
+            return;
+        }
+
+        gst::debug!(
+            CAT,
+            imp: self,
+            "Writing file {}",
+            state.settings.file.display()
+        );
+        state.writer = Some(writer);
+        drop(state);
+
+        self.register_hook(TracerHook::PadPushPre);
+        self.register_hook(TracerHook::PadPushListPre);
+        self.register_hook(TracerHook::ObjectDestroyed);
+    }
+
+    fn dispose(&self) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(mut writer) = state.writer.take() {
+            if let Err(err) = writer.flush() {
+                gst::error!(CAT, imp: self, "Failed to write to file: {err}");
+            }
+        }
+    }
+}
+
+impl GstObjectImpl for PcapWriter {}
+
+impl TracerImpl for PcapWriter {
+    fn pad_push_pre(&self, _ts: u64, pad: &gst::Pad, buffer: &gst::Buffer) {
+        self.capture(pad, std::iter::once(&**buffer));
+    }
+
+    fn pad_push_list_pre(&self, _ts: u64, pad: &gst::Pad, list: &gst::BufferList) {
+        self.capture(pad, list.iter());
+    }
+
+    fn object_destroyed(&self, _ts: u64, object: std::ptr::NonNull<gst::ffi::GstObject>) {
+        let ptr = object.as_ptr() as usize;
+        let mut state = self.state.lock().unwrap();
+        state.pads.remove(&ptr);
+        state.interfaces.remove(&ptr);
+    }
+}
+
+impl PcapWriter {
+    /// Returns the captured element and the direction for data pushed from this pad
+    fn capture_point(&self, pad: &gst::Pad) -> Option<(usize, Direction)> {
+        let ptr = pad.as_ptr() as usize;
+        if let Some(capture_point) = self.state.lock().unwrap().pads.get(&ptr) {
+            return *capture_point;
+        }
+
+        let is_captured = |element: &gst::Element| {
+            element.factory().map_or(false, |factory| {
+                let state = self.state.lock().unwrap();
+                state.settings.elements.is_match(factory.name().as_str())
+            })
+        };
+
+        // Data leaving a source element was received, data entering a sink element is sent
+        let capture = if let Some(element) = pad.parent_element().filter(is_captured) {
+            Some((element, Direction::Inbound))
+        } else {
+            pad.peer()
+                .and_then(|peer| peer.parent_element())
+                .filter(is_captured)
+                .map(|element| (element, Direction::Outbound))
+        };
+
+        let capture_point = capture.map(|(element, direction)| {
+            let element_ptr = element.as_ptr() as usize;
+
+            // Read the properties before taking the lock
+            let udp = udp_endpoints(&element, direction);
+
+            let mut state = self.state.lock().unwrap();
+            if !state.interfaces.contains_key(&element_ptr) {
+                let id = state.next_interface_id;
+                state.next_interface_id += 1;
+
+                gst::debug!(
+                    CAT,
+                    imp: self,
+                    "Capturing {} as interface {}",
+                    element.name(),
+                    id
+                );
+
+                if let Some(writer) = state.writer.as_mut() {
+                    let link_type = if udp.is_some() {
+                        LINKTYPE_IPV4
+                    } else {
+                        LINKTYPE_USER0
+                    };
+                    if let Err(err) =
+                        write_interface_description(writer, link_type, element.name().as_str())
+                    {
+                        gst::error!(CAT, imp: self, "Failed to write to file: {err}");
+                        state.writer = None;
+                    }
+                }
+
+                state.interfaces.insert(element_ptr, Interface { id, udp });
+            }
+
+            (element_ptr, direction)
+        });
+
+        self.state.lock().unwrap().pads.insert(ptr, capture_point);
+
+        capture_point
+    }
+
+    fn capture<'a>(&self, pad: &gst::Pad, buffers: impl Iterator<Item = &'a gst::BufferRef>) {
+        let Some((element_ptr, direction)) = self.capture_point(pad) else {
+            return;
+        };
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64);
+
+        let mut state = self.state.lock().unwrap();
+        let State {
+            ref mut writer,
+            ref interfaces,
+            ..
+        } = &mut *state;
+        let (Some(w), Some(interface)) = (writer.as_mut(), interfaces.get(&element_ptr)) else {
+            return;
+        };
+
+        for buffer in buffers {
+            let Ok(map) = buffer.map_readable() else {
+                continue;
+            };
+
+            let res = if let Some(udp) = interface.udp {
+                let mut packet = udp_headers(udp, map.len());
+                packet.extend_from_slice(&map);
+                write_enhanced_packet(w, interface.id, timestamp, direction, &packet)
+            } else {
+                write_enhanced_packet(w, interface.id, timestamp, direction, &map)
+            };
+
+            if let Err(err) = res {
+                gst::error!(CAT, imp: self, "Failed to write to file: {err}");
+                *writer = None;
+                return;
+            }
+        }
+    }
+}
+
+/// Returns the addresses for the synthesized headers if this is a UDP element with a single
+/// configured address
+fn udp_endpoints(element: &gst::Element, direction: Direction) -> Option<UdpEndpoints> {
+    let factory = element.factory()?;
+    if !factory.name().contains("udp") {
+        return None;
+    }
+
+    let address_property = match direction {
+        Direction::Inbound => "address",
+        Direction::Outbound => "host",
+    };
+    element.find_property(address_property)?;
+    element.find_property("port")?;
+
+    let address = element
+        .property_value(address_property)
+        .get::<Option<String>>()
+        .ok()
+        .flatten()
+        .and_then(|address| Ipv4Addr::from_str(&address).ok())
+        .unwrap_or(Ipv4Addr::UNSPECIFIED);
+
+    let port = element.property_value("port");
+    let port = port
+        .get::<i32>()
+        .map(|port| port as u16)
+        .or_else(|_| port.get::<u32>().map(|port| port as u16))
+        .unwrap_or(0);
+
+    Some(UdpEndpoints {
+        src: (Ipv4Addr::UNSPECIFIED, 0),
+        dst: (address, port),
+    })
+}
+
+/// Creates IPv4 and UDP headers for a payload of the given size
+fn udp_headers(udp: UdpEndpoints, payload_size: usize) -> Vec<u8> {
+    let udp_len = u16::try_from(8 + payload_size).unwrap_or(u16::MAX);
+    let total_len = udp_len.saturating_add(20);
+
+    let mut headers = Vec::with_capacity(28 + payload_size);
+    // Version 4, header length 5 * 4 bytes, no TOS
+    headers.extend_from_slice(&[0x45, 0x00]);
+    headers.extend_from_slice(&total_len.to_be_bytes());
+    // Identification, don't fragment flag
+    headers.extend_from_slice(&[0x00, 0x00, 0x40, 0x00]);
+    // TTL 64, protocol UDP, checksum filled in below
+    headers.extend_from_slice(&[64, 17, 0x00, 0x00]);
+    headers.extend_from_slice(&udp.src.0.octets());
+    headers.extend_from_slice(&udp.dst.0.octets());
+
+    let mut sum = headers
+        .chunks(2)
+        .map(|word| u16::from_be_bytes([word[0], word[1]]) as u32)
+        .sum::<u32>();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    headers[10..12].copy_from_slice(&(!(sum as u16)).to_be_bytes());
+
+    headers.extend_from_slice(&udp.src.1.to_be_bytes());
+    headers.extend_from_slice(&udp.dst.1.to_be_bytes());
+    headers.extend_from_slice(&udp_len.to_be_bytes());
+    // No UDP checksum
+    headers.extend_from_slice(&[0x00, 0x00]);
+
+    headers
+}
+
+fn push_padded(body: &mut Vec<u8>, data: &[u8]) {
+    body.extend_from_slice(data);
+    body.resize(body.len() + (4 - data.len() % 4) % 4, 0);
+}
+
+fn push_option(body: &mut Vec<u8>, code: u16, value: &[u8]) {
+    body.extend_from_slice(&code.to_le_bytes());
+    body.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    push_padded(body, value);
+}
+
+fn write_block(writer: &mut impl Write, block_type: u32, body: &[u8]) -> io::Result<()> {
+    let len = (12 + body.len()) as u32;
+    writer.write_all(&block_type.to_le_bytes())?;
+    writer.write_all(&len.to_le_bytes())?;
+    writer.write_all(body)?;
+    writer.write_all(&len.to_le_bytes())
+}
+
+fn write_section_header(writer: &mut impl Write) -> io::Result<()> {
+    let mut body = Vec::new();
+    // Byte-order magic, version 1.0 and unknown section length
+    body.extend_from_slice(&0x1a2b_3c4du32.to_le_bytes());
+    body.extend_from_slice(&1u16.to_le_bytes());
+    body.extend_from_slice(&0u16.to_le_bytes());
+    body.extend_from_slice(&(-1i64).to_le_bytes());
+
+    write_block(writer, BLOCK_TYPE_SECTION_HEADER, &body)
+}
+
+fn write_interface_description(
+    writer: &mut impl Write,
+    link_type: u16,
+    name: &str,
+) -> io::Result<()> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&link_type.to_le_bytes());
+    body.extend_from_slice(&0u16.to_le_bytes());
+    // No snapshot length limit
+    body.extend_from_slice(&0u32.to_le_bytes());
+
+    push_option(&mut body, OPTION_IF_NAME, name.as_bytes());
+    // Nanosecond timestamps
+    push_option(&mut body, OPTION_IF_TSRESOL, &[9]);
+    push_option(&mut body, OPTION_END_OF_OPTIONS, &[]);
+
+    write_block(writer, BLOCK_TYPE_INTERFACE_DESCRIPTION, &body)
+}
+
+fn write_enhanced_packet(
+    writer: &mut impl Write,
+    interface_id: u32,
+    timestamp: u64,
+    direction: Direction,
+    data: &[u8],
+) -> io::Result<()> {
+    let mut body = Vec::with_capacity(data.len() + 40);
+    body.extend_from_slice(&interface_id.to_le_bytes());
+    body.extend_from_slice(&((timestamp >> 32) as u32).to_le_bytes());
+    body.extend_from_slice(&(timestamp as u32).to_le_bytes());
+    body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    push_padded(&mut body, data);
+
+    let flags: u32 = match direction {
+        Direction::Inbound => 0b01,
+        Direction::Outbound => 0b10,
+    };
+    push_option(&mut body, OPTION_EPB_FLAGS, &flags.to_le_bytes());
+    push_option(&mut body, OPTION_END_OF_OPTIONS, &[]);
+
+    write_block(writer, BLOCK_TYPE_ENHANCED_PACKET, &body)
+}
@@ -12,10 +12,11 @@
  * This tracer provides an easy way to take a snapshot of all the pipelines without
  * having to modify the application.
  * One just have to load the tracer and send the `SIGUSR1` UNIX signal to take snapshots.
+ * Alternatively snapshots can be triggered by creating a control file, see `control-file` below.
  * It currently only works on UNIX systems.
  *
- * When taking a snapshot pipelines are saved to DOT files, but the tracer may be
- * extended in the future to dump more information.
+ * When taking a snapshot pipelines are saved to DOT files including the current values of all
+ * element properties, but the tracer may be extended in the future to dump more information.
  *
  * Example:
  *
@@ -30,16 +31,25 @@
  * Parameters can be passed to configure the tracer:
  * - `dot-prefix` (string, default: "pipeline-snapshot-"): when dumping pipelines to a `dot` file each file is named `$prefix$pipeline_name.dot`.
  * - `dot-ts` (boolean, default: "true"): if the current timestamp should be added as a prefix to each pipeline `dot` file.
+ * - `control-file` (string, default: unset): path of a file that is polled regularly. When it exists, a
+ *   snapshot is taken and the file is removed again. This is useful if sending signals to the process is not possible.
  *
  * Example:
  *
  * ```console
  * $ GST_TRACERS="pipeline-snapshot(dot-prefix="badger-",dot-ts=false)" GST_DEBUG_DUMP_DOT_DIR=. gst-launch-1.0 audiotestsrc ! fakesink
  * ```
+ *
+ * ```console
+ * $ GST_TRACERS="pipeline-snapshot(control-file=/tmp/snapshot)" GST_DEBUG_DUMP_DOT_DIR=. gst-launch-1.0 audiotestsrc ! fakesink
+ * $ touch /tmp/snapshot
+ * ```
  */
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::str::FromStr;
-use std::sync::{Arc, Mutex};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
 
 use gst::glib;
 use gst::glib::translate::ToGlibPtr;
@@ -73,10 +83,13 @@ impl ElementPtr {
     }
 }
 
-#[derive(Debug)]
+const CONTROL_FILE_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone)]
 struct Settings {
     dot_prefix: String,
     dot_ts: bool,
+    control_file: Option<PathBuf>,
 }
 
 impl Default for Settings {
@@ -84,6 +97,7 @@ impl Default for Settings {
         Self {
             dot_prefix: "pipeline-snapshot-".to_string(),
             dot_ts: true,
+            control_file: None,
         }
     }
 }
@@ -107,6 +121,11 @@ impl Settings {
             gst::log!(CAT, imp: imp, "dot-ts = {}", dot_ts);
             self.dot_ts = dot_ts;
         }
+
+        if let Ok(control_file) = s.get::<&str>("control-file") {
+            gst::log!(CAT, imp: imp, "control-file = {}", control_file);
+            self.control_file = Some(PathBuf::from(control_file));
+        }
     }
 }
 
@@ -120,6 +139,13 @@ struct Handles {
     #[cfg(unix)]
     signal: signal_hook::iterator::Handle,
     thread: std::thread::JoinHandle<()>,
+    control_file: Option<ControlFileHandle>,
+}
+
+struct ControlFileHandle {
+    // Dropping the sender stops the polling thread
+    stop: mpsc::Sender<()>,
+    thread: std::thread::JoinHandle<()>,
 }
 
 #[glib::object_subclass]
@@ -141,7 +167,12 @@ impl ObjectImpl for PipelineSnapshot {
         self.register_hook(TracerHook::ElementNew);
         self.register_hook(TracerHook::ObjectDestroyed);
 
-        if let Err(err) = self.setup_signal(settings) {
+        let control_file = settings.control_file.clone().map(|path| {
+            let settings = settings.clone();
+            self.setup_control_file(path, settings)
+        });
+
+        if let Err(err) = self.setup_signal(settings, control_file) {
             gst::warning!(CAT, imp: self, "failed to setup UNIX signals: {}", err);
         }
     }
@@ -152,6 +183,11 @@ impl ObjectImpl for PipelineSnapshot {
             #[cfg(unix)]
             handles.signal.close();
             handles.thread.join().unwrap();
+
+            if let Some(control_file) = handles.control_file {
+                drop(control_file.stop);
+                control_file.thread.join().unwrap();
+            }
         }
     }
 }
@@ -177,8 +213,47 @@ impl TracerImpl for PipelineSnapshot {
 }
 
 impl PipelineSnapshot {
+    fn setup_control_file(&self, path: PathBuf, settings: Settings) -> ControlFileHandle {
+        let (stop, stopped) = mpsc::channel::<()>();
+
+        let tracer_weak = self.obj().downgrade();
+        let pipelines = self.pipelines.clone();
+
+        let thread = std::thread::spawn(move || {
+            while let Err(mpsc::RecvTimeoutError::Timeout) =
+                stopped.recv_timeout(CONTROL_FILE_POLL_INTERVAL)
+            {
+                if !path.exists() {
+                    continue;
+                }
+
+                let Some(tracer) = tracer_weak.upgrade() else {
+                    break;
+                };
+
+                if let Err(err) = std::fs::remove_file(&path) {
+                    gst::warning!(
+                        CAT,
+                        obj: tracer,
+                        "failed to remove control file {}: {}",
+                        path.display(),
+                        err
+                    );
+                }
+
+                snapshot(&tracer, &pipelines, &settings);
+            }
+        });
+
+        ControlFileHandle { stop, thread }
+    }
+
     #[cfg(unix)]
-    fn setup_signal(&self, settings: Settings) -> anyhow::Result<()> {
+    fn setup_signal(
+        &self,
+        settings: Settings,
+        control_file: Option<ControlFileHandle>,
+    ) -> anyhow::Result<()> {
         use signal_hook::consts::signal::*;
         use signal_hook::iterator::Signals;
 
@@ -196,30 +271,7 @@ impl PipelineSnapshot {
                             break;
                         };
 
-                        let pipelines = {
-                            let weaks = pipelines.lock().unwrap();
-                            weaks
-                                .values()
-                                .filter_map(|w| w.upgrade())
-                                .collect::<Vec<_>>()
-                        };
-
-                        for pipeline in pipelines.into_iter() {
-                            let pipeline = pipeline.downcast::<gst::Pipeline>().unwrap();
-                            gst::debug!(CAT, obj: tracer, "dump {}", pipeline.name());
-
-                            let dump_name = format!("{}{}", settings.dot_prefix, pipeline.name());
-
-                            if settings.dot_ts {
-                                pipeline.debug_to_dot_file_with_ts(
-                                    gst::DebugGraphDetails::all(),
-                                    &dump_name,
-                                );
-                            } else {
-                                pipeline
-                                    .debug_to_dot_file(gst::DebugGraphDetails::all(), &dump_name);
-                            }
-                        }
+                        snapshot(&tracer, &pipelines, &settings);
                     }
                     _ => unreachable!(),
                 }
@@ -230,13 +282,45 @@ impl PipelineSnapshot {
         *handles = Some(Handles {
             signal: signal_handle,
             thread: thread_handle,
+            control_file,
         });
 
         Ok(())
     }
 
     #[cfg(not(unix))]
-    fn setup_signal(&self) -> anyhow::Result<()> {
+    fn setup_signal(
+        &self,
+        _settings: Settings,
+        _control_file: Option<ControlFileHandle>,
+    ) -> anyhow::Result<()> {
         anyhow::bail!("only supported on UNIX system");
     }
 }
+
+fn snapshot(
+    tracer: &super::PipelineSnapshot,
+    pipelines: &Mutex<HashMap<ElementPtr, glib::WeakRef<gst::Element>>>,
+    settings: &Settings,
+) {
+    let pipelines = {
+        let weaks = pipelines.lock().unwrap();
+        weaks
+            .values()
+            .filter_map(|w| w.upgrade())
+            .collect::<Vec<_>>()
+    };
+
+    for pipeline in pipelines.into_iter() {
+        let pipeline = pipeline.downcast::<gst::Pipeline>().unwrap();
+        gst::debug!(CAT, obj: tracer, "dump {}", pipeline.name());
+
+        let dump_name = format!("{}{}", settings.dot_prefix, pipeline.name());
+
+        if settings.dot_ts {
+            pipeline.debug_to_dot_file_with_ts(gst::DebugGraphDetails::all(), &dump_name);
+        } else {
+            pipeline.debug_to_dot_file(gst::DebugGraphDetails::all(), &dump_name);
+        }
+    }
+}
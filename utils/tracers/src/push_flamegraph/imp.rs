@@ -0,0 +1,296 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * tracer-push-flamegraph:
+ *
+ * This tracer measures how much time each element spends processing the data that is pushed
+ * into it and aggregates it per call stack in the folded stack format that is understood by
+ * flamegraph tools like [inferno] or [FlameGraph].
+ *
+ * Whenever a buffer or buffer list is pushed into an element the element is added to the call
+ * stack of the current thread, and removed again once the push returns. The time between both
+ * minus the time spent in downstream elements is the processing time of the element. The bottom
+ * of each call stack is the element that pushed the first buffer in the thread, usually a source
+ * element or a queue.
+ *
+ * [inferno]: https://github.com/jonhoo/inferno
+ * [FlameGraph]: https://github.com/brendangregg/FlameGraph
+ *
+ * Example:
+ *
+ * ```console
+ * $ GST_TRACERS='push-flamegraph(file="/tmp/push_flamegraph.folded")' gst-launch-1.0 videotestsrc num-buffers=100 ! videoconvert ! queue ! fakesink
+ * $ inferno-flamegraph /tmp/push_flamegraph.folded > flamegraph.svg
+ * ```
+ *
+ * The generated file contains one line per call stack of the format
+ *
+ * ```text
+ * videotestsrc0;videoconvert0;queue0 123456
+ * ```
+ *
+ * with the accumulated processing time in nanoseconds of the last element in the stack.
+ *
+ * ## Parameters
+ *
+ * ### `file`
+ *
+ * Specifies the path to the file that will collect the folded stacks.
+ *
+ * By default the file is written to `/tmp/push_flamegraph.folded`.
+ */
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use once_cell::sync::Lazy;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "push-flamegraph",
+        gst::DebugColorFlags::empty(),
+        Some("Tracer to collect per element processing times as flamegraph"),
+    )
+});
+
+#[derive(Debug)]
+struct Settings {
+    file: PathBuf,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        let mut file = glib::tmp_dir();
+        file.push("push_flamegraph.folded");
+
+        Self { file }
+    }
+}
+
+impl Settings {
+    fn update_from_params(&mut self, imp: &PushFlamegraph, params: String) {
+        let s = match gst::Structure::from_str(&format!("push-flamegraph,{params}")) {
+            Ok(s) => s,
+            Err(err) => {
+                gst::warning!(CAT, imp: imp, "failed to parse tracer parameters: {}", err);
+                return;
+            }
+        };
+
+        if let Ok(file) = s.get::<&str>("file") {
+            gst::log!(CAT, imp: imp, "file= {}", file);
+            self.file = PathBuf::from(file);
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    /// Name of the parent element of each pad
+    pads: HashMap<usize, Option<Arc<str>>>,
+    /// Accumulated processing time per folded call stack
+    stacks: HashMap<String, u64>,
+    settings: Settings,
+}
+
+struct Frame {
+    /// `None` for pads without parent element, e.g. the internal pads of ghost pads
+    element_name: Option<Arc<str>>,
+    start: u64,
+    /// Time spent in downstream elements
+    children: u64,
+}
+
+#[derive(Default)]
+struct ThreadStack {
+    owner: Option<Arc<str>>,
+    frames: Vec<Frame>,
+}
+
+impl ThreadStack {
+    fn folded(&self) -> String {
+        let mut folded = String::new();
+        for name in self.owner.iter().chain(
+            self.frames
+                .iter()
+                .filter_map(|frame| frame.element_name.as_ref()),
+        ) {
+            if !folded.is_empty() {
+                folded.push(';');
+            }
+            folded.push_str(name);
+        }
+        folded
+    }
+}
+
+thread_local! {
+    static THREAD_STACK: RefCell<ThreadStack> = RefCell::new(ThreadStack::default());
+}
+
+#[derive(Default)]
+pub struct PushFlamegraph {
+    state: Mutex<State>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for PushFlamegraph {
+    const NAME: &'static str = "GstPushFlamegraph";
+    type Type = super::PushFlamegraph;
+    type ParentType = gst::Tracer;
+}
+
+impl ObjectImpl for PushFlamegraph {
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        if let Some(params) = self.obj().property::<Option<String>>("params") {
+            let mut state = self.state.lock().unwrap();
+            state.settings.update_from_params(self, params);
+        }
+
+        self.register_hook(TracerHook::PadPushPre);
+        self.register_hook(TracerHook::PadPushListPre);
+        self.register_hook(TracerHook::PadPushPost);
+        self.register_hook(TracerHook::PadPushListPost);
+        self.register_hook(TracerHook::ObjectDestroyed);
+    }
+
+    fn dispose(&self) {
+        use std::io::prelude::*;
+
+        let state = self.state.lock().unwrap();
+
+        let mut file = match std::fs::File::create(&state.settings.file) {
+            Ok(file) => file,
+            Err(err) => {
+                gst::error!(CAT, imp: self, "Failed to create file: {err}");
+                return;
+            }
+        };
+
+        gst::debug!(
+            CAT,
+            imp: self,
+            "Writing file {}",
+            state.settings.file.display()
+        );
+
+        let mut stacks = state.stacks.iter().collect::<Vec<_>>();
+        stacks.sort();
+
+        for (stack, duration) in stacks {
+            if let Err(err) = writeln!(&mut file, "{stack} {duration}") {
+                gst::error!(CAT, imp: self, "Failed to write to file: {err}");
+                return;
+            }
+        }
+    }
+}
+
+impl GstObjectImpl for PushFlamegraph {}
+
+impl TracerImpl for PushFlamegraph {
+    fn pad_push_pre(&self, ts: u64, pad: &gst::Pad, _buffer: &gst::Buffer) {
+        self.push_pre(ts, pad);
+    }
+
+    fn pad_push_list_pre(&self, ts: u64, pad: &gst::Pad, _list: &gst::BufferList) {
+        self.push_pre(ts, pad);
+    }
+
+    fn pad_push_post(
+        &self,
+        ts: u64,
+        _pad: &gst::Pad,
+        _result: Result<gst::FlowSuccess, gst::FlowError>,
+    ) {
+        self.push_post(ts);
+    }
+
+    fn pad_push_list_post(
+        &self,
+        ts: u64,
+        _pad: &gst::Pad,
+        _result: Result<gst::FlowSuccess, gst::FlowError>,
+    ) {
+        self.push_post(ts);
+    }
+
+    fn object_destroyed(&self, _ts: u64, object: std::ptr::NonNull<gst::ffi::GstObject>) {
+        let ptr = object.as_ptr() as usize;
+        let mut state = self.state.lock().unwrap();
+        state.pads.remove(&ptr);
+    }
+}
+
+impl PushFlamegraph {
+    fn element_name(&self, pad: &gst::Pad) -> Option<Arc<str>> {
+        let ptr = pad.as_ptr() as usize;
+        let mut state = self.state.lock().unwrap();
+
+        state
+            .pads
+            .entry(ptr)
+            .or_insert_with(|| {
+                pad.parent_element()
+                    .map(|element| Arc::from(element.name().as_str()))
+            })
+            .clone()
+    }
+
+    fn push_pre(&self, ts: u64, pad: &gst::Pad) {
+        let receiver = pad.peer().and_then(|peer| self.element_name(&peer));
+
+        THREAD_STACK.with(|stack| {
+            let mut stack = stack.borrow_mut();
+            if stack.frames.is_empty() {
+                stack.owner = self.element_name(pad);
+            }
+
+            stack.frames.push(Frame {
+                element_name: receiver,
+                start: ts,
+                children: 0,
+            });
+        });
+    }
+
+    fn push_post(&self, ts: u64) {
+        let folded = THREAD_STACK.with(|stack| {
+            let mut stack = stack.borrow_mut();
+            let folded = stack.folded();
+            let frame = stack.frames.pop()?;
+
+            let elapsed = ts.saturating_sub(frame.start);
+            if let Some(parent) = stack.frames.last_mut() {
+                // The time of pads without element is accounted to the upstream element
+                parent.children += if frame.element_name.is_some() {
+                    elapsed
+                } else {
+                    frame.children
+                };
+            }
+
+            frame
+                .element_name
+                .map(|_| (folded, elapsed.saturating_sub(frame.children)))
+        });
+
+        if let Some((folded, duration)) = folded {
+            let mut state = self.state.lock().unwrap();
+            *state.stacks.entry(folded).or_default() += duration;
+        }
+    }
+}
@@ -12,6 +12,9 @@
  * This tracer provides an easy way to collect queue levels over time of all queues inside a
  * pipeline.
  *
+ * Supported are `queue`, `queue2`, `multiqueue`, `appsrc` and the thread-sharing `ts-queue`.
+ * The levels are sampled whenever data enters or leaves a queue.
+ *
  * Example:
  *
  * ```console
@@ -32,6 +35,14 @@
  *
  * By default the file is written to `/tmp/queue_levels.log`.
  *
+ * ### `log`
+ *
+ * If set to `true`, each sample is additionally logged as a structure with the same fields as
+ * the CSV file in the `queue-levels` debug category at `INFO` level while the pipeline is
+ * running, e.g. for collecting it with `GST_DEBUG=queue-levels:4`.
+ *
+ * By default this is `false`.
+ *
  * ### `include-filter`
  *
  * Specifies a regular expression for the queue object names that should be included.
@@ -99,8 +110,25 @@ static APPSRC_TYPE: Lazy<glib::Type> = Lazy::new(|| {
     }
 });
 
+static TS_QUEUE_TYPE: Lazy<glib::Type> = Lazy::new(|| {
+    if let Some(queue) = gst::ElementFactory::find("ts-queue").and_then(|f| f.load().ok()) {
+        queue.element_type()
+    } else {
+        // The threadshare plugin is optional
+        gst::debug!(CAT, "Can't instantiate ts-queue element");
+        glib::Type::INVALID
+    }
+});
+
 fn is_queue_type(type_: glib::Type) -> bool {
-    [*QUEUE_TYPE, *QUEUE2_TYPE, *MULTIQUEUE_TYPE, *APPSRC_TYPE].contains(&type_)
+    [
+        *QUEUE_TYPE,
+        *QUEUE2_TYPE,
+        *MULTIQUEUE_TYPE,
+        *APPSRC_TYPE,
+        *TS_QUEUE_TYPE,
+    ]
+    .contains(&type_)
 }
 
 #[derive(Debug)]
@@ -108,6 +136,7 @@ struct Settings {
     file: PathBuf,
     include_filter: Option<Regex>,
     exclude_filter: Option<Regex>,
+    log: bool,
 }
 
 impl Default for Settings {
@@ -119,6 +148,7 @@ impl Default for Settings {
             file,
             include_filter: None,
             exclude_filter: None,
+            log: false,
         }
     }
 }
@@ -171,6 +201,11 @@ impl Settings {
             };
             self.exclude_filter = filter;
         }
+
+        if let Ok(log) = s.get::<bool>("log") {
+            gst::log!(CAT, imp: imp, "log= {}", log);
+            self.log = log;
+        }
     }
 }
 
@@ -194,6 +229,27 @@ struct LogLine {
     max_size_buffers: u64,
 }
 
+impl LogLine {
+    fn to_structure(&self) -> gst::Structure {
+        let name = match self.idx {
+            Some(idx) => format!("{}:{idx}", self.name),
+            None => self.name.to_string(),
+        };
+
+        gst::Structure::builder("queue-levels")
+            .field("timestamp", self.timestamp)
+            .field("name", name)
+            .field("ptr", format!("0x{:08x}", self.ptr))
+            .field("cur-level-bytes", self.cur_level_bytes)
+            .field("cur-level-time", self.cur_level_time)
+            .field("cur-level-buffers", self.cur_level_buffers)
+            .field("max-size-bytes", self.max_size_bytes)
+            .field("max-size-time", self.max_size_time)
+            .field("max-size-buffers", self.max_size_buffers)
+            .build()
+    }
+}
+
 #[derive(Default)]
 pub struct QueueLevels {
     state: Mutex<State>,
@@ -218,6 +274,7 @@ impl ObjectImpl for QueueLevels {
         Lazy::force(&QUEUE_TYPE);
         Lazy::force(&QUEUE2_TYPE);
         Lazy::force(&MULTIQUEUE_TYPE);
+        Lazy::force(&TS_QUEUE_TYPE);
 
         self.register_hook(TracerHook::ElementNew);
         self.register_hook(TracerHook::ObjectDestroyed);
@@ -439,6 +496,14 @@ impl TracerImpl for QueueLevels {
 }
 
 impl QueueLevels {
+    fn push_line(&self, state: &mut State, line: LogLine) {
+        if state.settings.log {
+            gst::info!(CAT, imp: self, "{}", line.to_structure());
+        }
+
+        state.log.push(line);
+    }
+
     fn log(&self, element: &gst::Element, pad: Option<&gst::Pad>, timestamp: u64) {
         let ptr = element.as_ptr() as usize;
 
@@ -484,27 +549,12 @@ impl QueueLevels {
                 let cur_level_bytes = pad.property::<u32>("current-level-bytes");
                 let cur_level_time = pad.property::<u64>("current-level-time");
                 let cur_level_buffers = pad.property::<u32>("current-level-buffers");
-                state.log.push(LogLine {
-                    timestamp,
-                    name,
-                    idx: Some(get_pad_idx(pad)),
-                    ptr,
-                    cur_level_bytes,
-                    cur_level_time,
-                    cur_level_buffers,
-                    max_size_bytes,
-                    max_size_time,
-                    max_size_buffers,
-                });
-            } else {
-                for pad in element.sink_pads() {
-                    let cur_level_bytes = pad.property::<u32>("current-level-bytes");
-                    let cur_level_time = pad.property::<u64>("current-level-time");
-                    let cur_level_buffers = pad.property::<u32>("current-level-buffers");
-                    state.log.push(LogLine {
+                self.push_line(
+                    &mut state,
+                    LogLine {
                         timestamp,
-                        name: name.clone(),
-                        idx: Some(get_pad_idx(&pad)),
+                        name,
+                        idx: Some(get_pad_idx(pad)),
                         ptr,
                         cur_level_bytes,
                         cur_level_time,
@@ -512,7 +562,28 @@ impl QueueLevels {
                         max_size_bytes,
                         max_size_time,
                         max_size_buffers,
-                    });
+                    },
+                );
+            } else {
+                for pad in element.sink_pads() {
+                    let cur_level_bytes = pad.property::<u32>("current-level-bytes");
+                    let cur_level_time = pad.property::<u64>("current-level-time");
+                    let cur_level_buffers = pad.property::<u32>("current-level-buffers");
+                    self.push_line(
+                        &mut state,
+                        LogLine {
+                            timestamp,
+                            name: name.clone(),
+                            idx: Some(get_pad_idx(&pad)),
+                            ptr,
+                            cur_level_bytes,
+                            cur_level_time,
+                            cur_level_buffers,
+                            max_size_bytes,
+                            max_size_time,
+                            max_size_buffers,
+                        },
+                    );
                 }
             }
         } else {
@@ -531,18 +602,21 @@ impl QueueLevels {
                     )
                 };
 
-            state.log.push(LogLine {
-                timestamp,
-                name,
-                idx: None,
-                ptr,
-                cur_level_bytes,
-                cur_level_time,
-                cur_level_buffers,
-                max_size_bytes,
-                max_size_time,
-                max_size_buffers,
-            });
+            self.push_line(
+                &mut state,
+                LogLine {
+                    timestamp,
+                    name,
+                    idx: None,
+                    ptr,
+                    cur_level_bytes,
+                    cur_level_time,
+                    cur_level_buffers,
+                    max_size_bytes,
+                    max_size_time,
+                    max_size_buffers,
+                },
+            );
         }
     }
 }
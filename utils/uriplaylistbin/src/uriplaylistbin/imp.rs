@@ -29,6 +29,8 @@ static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
 enum PlaylistError {
     #[error("plugin missing: {error}")]
     PluginMissing { error: anyhow::Error },
+    #[error("no URI to play")]
+    NoUris,
 }
 
 #[derive(Debug, Clone)]
@@ -411,6 +413,10 @@ impl UriPlaylistBin {
             let mut state_guard = self.state.lock().unwrap();
             assert!(state_guard.is_none());
 
+            if self.settings.lock().unwrap().uris.is_empty() {
+                return Err(PlaylistError::NoUris);
+            }
+
             let uridecodebin = gst::ElementFactory::make("uridecodebin3")
                 .name("playlist-uridecodebin")
                 .build()
@@ -624,6 +630,9 @@ impl UriPlaylistBin {
             PlaylistError::PluginMissing { .. } => {
                 gst::element_imp_error!(self, gst::CoreError::MissingPlugin, ["{}", &error_msg]);
             }
+            PlaylistError::NoUris => {
+                gst::element_imp_error!(self, gst::ResourceError::NotFound, ["{}", &error_msg]);
+            }
         }
 
         self.update_current(self.state.lock().unwrap(), None);
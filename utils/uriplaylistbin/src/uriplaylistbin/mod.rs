@@ -6,6 +6,25 @@
 //
 // SPDX-License-Identifier: MPL-2.0
 
+/**
+ * element-uriplaylistbin:
+ *
+ * `uriplaylistbin` sequentially plays the list of URIs set in `uris`, `iterations`
+ * times, exposing continuous audio, video and text pads.
+ *
+ * Each item is decoded by a `uridecodebin3` and the next item is pre-rolled while the
+ * current one is about to finish, so consecutive items play gaplessly. The position in
+ * the playlist is reported via the `current-iteration` and `current-uri-index`
+ * properties.
+ *
+ * ## Example pipeline
+ * ```bash
+ * gst-launch-1.0 uriplaylistbin uris="<file:///a.flac,file:///b.flac>" iterations=2 ! \
+ *   audioconvert ! autoaudiosink
+ * ```
+ *
+ * Since: plugins-rs-0.8.0
+ */
 use gst::glib;
 use gst::prelude::*;
 
@@ -418,3 +418,28 @@ fn infinite_to_finite() {
     assert_eq!(current_iteration, 3);
     assert_eq!(current_uri_index, 0);
 }
+
+#[test]
+/// an empty playlist fails to start instead of never producing anything
+fn no_uris() {
+    init();
+
+    let pipeline = gst::Pipeline::default();
+    let playlist = gst::ElementFactory::make("uriplaylistbin").build().unwrap();
+    pipeline.add(&playlist).unwrap();
+
+    assert!(pipeline.set_state(gst::State::Playing).is_err());
+
+    let bus = pipeline.bus().unwrap();
+    let msg = bus
+        .timed_pop_filtered(gst::ClockTime::ZERO, &[gst::MessageType::Error])
+        .expect("no error message");
+    match msg.view() {
+        MessageView::Error(err) => {
+            assert!(err.error().matches(gst::ResourceError::NotFound));
+        }
+        _ => unreachable!(),
+    }
+
+    pipeline.set_state(gst::State::Null).unwrap();
+}
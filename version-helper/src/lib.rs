@@ -60,6 +60,11 @@ use std::{env, fs, path};
 /// - If neither is possible, `COMMIT_ID` is set to the string `UNKNOWN` and `BUILD_REL_DATE` to the
 ///   current date.
 ///
+/// In addition, the `BUILD_DATE` environment variable is set to the date of the build, or to the
+/// date given by the `SOURCE_DATE_EPOCH` environment variable for reproducible builds, and the
+/// `BUILD_FEATURES` environment variable to a sorted, comma separated list of the Cargo features
+/// the crate is built with.
+///
 pub fn info() {
     let crate_dir =
         path::PathBuf::from(env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR not set"));
@@ -83,6 +88,33 @@ pub fn info() {
 
     println!("cargo:rustc-env=COMMIT_ID={commit_id}");
     println!("cargo:rustc-env=BUILD_REL_DATE={commit_date}");
+
+    let build_date = source_date_epoch().unwrap_or_else(chrono::Utc::now);
+    println!(
+        "cargo:rustc-env=BUILD_DATE={}",
+        build_date.format("%Y-%m-%d")
+    );
+    println!("cargo:rustc-env=BUILD_FEATURES={}", features().join(","));
+}
+
+fn source_date_epoch() -> Option<chrono::DateTime<chrono::Utc>> {
+    let epoch = env::var("SOURCE_DATE_EPOCH").ok()?.parse::<i64>().ok()?;
+    chrono::Utc.timestamp_opt(epoch, 0).single()
+}
+
+/// Enabled Cargo features of the crate that is built, as written in its `Cargo.toml`
+///
+/// Cargo only passes the features as upper-case `CARGO_FEATURE_*` environment variables with
+/// dashes replaced by underscores, so this is reversed here.
+fn features() -> Vec<String> {
+    let mut features = env::vars()
+        .filter_map(|(key, _)| {
+            key.strip_prefix("CARGO_FEATURE_")
+                .map(|feature| feature.to_lowercase().replace('_', "-"))
+        })
+        .collect::<Vec<_>>();
+    features.sort();
+    features
 }
 
 fn cargo_metadata_release_date(crate_dir: &path::Path) -> Option<chrono::DateTime<chrono::Utc>> {
@@ -0,0 +1,524 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use cea608_types::tables::Channel;
+use cea608_types::Cea608State;
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+use atomic_refcell::AtomicRefCell;
+
+use once_cell::sync::Lazy;
+
+use std::sync::Mutex;
+
+use crate::ccutils::extract_cdp;
+use crate::cea608utils::Cea608Frame;
+
+const DEFAULT_FIELD: i32 = -1;
+const DEFAULT_CHANNEL: u32 = 0;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum InputFormat {
+    Raw { field: u8 },
+    S334_1a,
+    CcData,
+    Cdp,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    field: i32,
+    channel: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            field: DEFAULT_FIELD,
+            channel: DEFAULT_CHANNEL,
+        }
+    }
+}
+
+struct State {
+    format: Option<InputFormat>,
+    selected_field: Option<u8>,
+    cea608_state: Cea608State,
+    frame: Cea608Frame,
+    current_text: Option<(gst::ClockTime, String)>,
+}
+
+impl State {
+    fn new(settings: &Settings) -> Self {
+        let mut frame = Cea608Frame::new();
+        match settings.channel {
+            1 => frame.set_channel(Channel::ONE),
+            2 => frame.set_channel(Channel::TWO),
+            _ => (),
+        }
+
+        State {
+            format: None,
+            selected_field: match settings.field {
+                -1 => None,
+                val => Some(val as u8),
+            },
+            cea608_state: Cea608State::default(),
+            frame,
+            current_text: None,
+        }
+    }
+}
+
+pub struct CcDec {
+    srcpad: gst::Pad,
+    sinkpad: gst::Pad,
+
+    settings: Mutex<Settings>,
+    state: AtomicRefCell<State>,
+}
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "ccdec",
+        gst::DebugColorFlags::empty(),
+        Some("Closed Caption Decoder"),
+    )
+});
+
+impl CcDec {
+    /// Decodes a single CEA-608 byte pair for the given field and returns whether the
+    /// caption memory was modified by it.
+    fn decode_pair(&self, state: &mut State, field: u8, pair: [u8; 2]) -> bool {
+        if state.selected_field.is_none() {
+            // Skip padding so that the first field actually carrying captions is selected
+            if pair == [0x80, 0x80] {
+                return false;
+            }
+            state.selected_field = Some(field);
+            gst::info!(CAT, imp: self, "Selected field {} automatically", field);
+        }
+
+        if Some(field) != state.selected_field {
+            return false;
+        }
+
+        match state.cea608_state.decode(pair) {
+            Err(e) => {
+                gst::warning!(CAT, imp: self, "Failed to decode closed caption packet {:x?}: {e:?}", pair);
+                false
+            }
+            Ok(Some(cea608)) => {
+                gst::trace!(CAT, imp: self, "received {:x?} cea608: {cea608:?}", pair);
+                state.frame.push_code(cea608)
+            }
+            Ok(None) => false,
+        }
+    }
+
+    fn decode_cc_data(&self, state: &mut State, data: &[u8]) -> bool {
+        if data.len() % 3 != 0 {
+            gst::warning!(CAT, imp: self, "cc_data length is not a multiple of 3, truncating");
+        }
+
+        let mut changed = false;
+        for triple in data.chunks_exact(3) {
+            let cc_valid = (triple[0] & 0x04) == 0x04;
+            let cc_type = triple[0] & 0x03;
+
+            if !cc_valid {
+                continue;
+            }
+
+            // CEA-608 pairs always come first, anything after is DTVCC data
+            if cc_type != 0x00 && cc_type != 0x01 {
+                break;
+            }
+
+            changed |= self.decode_pair(state, cc_type, [triple[1], triple[2]]);
+        }
+
+        changed
+    }
+
+    fn create_buffer(
+        timestamp: gst::ClockTime,
+        duration: Option<gst::ClockTime>,
+        text: String,
+    ) -> gst::Buffer {
+        let mut buffer = gst::Buffer::from_mut_slice(text.into_bytes());
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(timestamp);
+            buffer.set_duration(duration);
+        }
+
+        buffer
+    }
+
+    /// Outputs the currently displayed text, if any, without a known end time.
+    fn drain(&self) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let mut state = self.state.borrow_mut();
+        let Some((timestamp, text)) = state.current_text.take() else {
+            return Ok(gst::FlowSuccess::Ok);
+        };
+        drop(state);
+
+        gst::debug!(CAT, imp: self, "Draining text {text:?} at {timestamp}");
+
+        self.srcpad.push(Self::create_buffer(timestamp, None, text))
+    }
+
+    fn sink_chain(
+        &self,
+        pad: &gst::Pad,
+        buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::log!(CAT, obj: pad, "Handling buffer {:?}", buffer);
+
+        let mut state = self.state.borrow_mut();
+        let Some(format) = state.format else {
+            gst::error!(CAT, obj: pad, "Not negotiated yet");
+            return Err(gst::FlowError::NotNegotiated);
+        };
+
+        let pts = buffer.pts().ok_or_else(|| {
+            gst::error!(CAT, obj: pad, "Require timestamped buffers");
+            gst::FlowError::Error
+        })?;
+
+        let data = buffer.map_readable().map_err(|_| {
+            gst::error!(CAT, obj: pad, "Can't map buffer readable");
+
+            gst::FlowError::Error
+        })?;
+
+        let changed = match format {
+            InputFormat::Raw { field } => {
+                if data.len() % 2 != 0 {
+                    gst::warning!(CAT, obj: pad, "Invalid closed caption packet size, truncating");
+                }
+
+                let mut changed = false;
+                for pair in data.chunks_exact(2) {
+                    changed |= self.decode_pair(&mut state, field, [pair[0], pair[1]]);
+                }
+                changed
+            }
+            InputFormat::S334_1a => {
+                if data.len() % 3 != 0 {
+                    gst::warning!(CAT, obj: pad, "Invalid closed caption packet size, truncating");
+                }
+
+                let mut changed = false;
+                for triple in data.chunks_exact(3) {
+                    let field = if (triple[0] & 0x80) == 0x80 { 0 } else { 1 };
+                    changed |= self.decode_pair(&mut state, field, [triple[1], triple[2]]);
+                }
+                changed
+            }
+            InputFormat::CcData => self.decode_cc_data(&mut state, &data),
+            InputFormat::Cdp => match extract_cdp(&data) {
+                Ok(cc_data) => self.decode_cc_data(&mut state, cc_data),
+                Err(e) => {
+                    gst::warning!(CAT, obj: pad, "{e}");
+                    gst::element_imp_warning!(self, gst::StreamError::Decode, ["{e}"]);
+                    false
+                }
+            },
+        };
+
+        if !changed {
+            return Ok(gst::FlowSuccess::Ok);
+        }
+
+        // Only the displayed memory is taken into account here: for pop-on captions this
+        // only changes once EndOfCaption swaps the memories or the display is erased, while
+        // for roll-up and paint-on captions every new character shows up immediately.
+        let text = state.frame.get_text();
+        let current = state
+            .current_text
+            .as_ref()
+            .map(|(_, text)| text.as_str())
+            .unwrap_or("");
+        if text == current {
+            return Ok(gst::FlowSuccess::Ok);
+        }
+
+        gst::trace!(CAT, obj: pad, "Displayed text in mode {:?} changed to {text:?}", state.frame.mode());
+
+        let previous = if text.is_empty() {
+            state.current_text.take()
+        } else {
+            state.current_text.replace((pts, text))
+        };
+        drop(state);
+
+        let Some((timestamp, text)) = previous else {
+            return Ok(gst::FlowSuccess::Ok);
+        };
+
+        self.srcpad.push(Self::create_buffer(
+            timestamp,
+            Some(pts.saturating_sub(timestamp)),
+            text,
+        ))
+    }
+
+    fn sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::log!(CAT, obj: pad, "Handling event {:?}", event);
+        match event.view() {
+            EventView::Caps(c) => {
+                let caps = c.caps();
+                let s = caps.structure(0).unwrap();
+
+                let format = match (s.name().as_str(), s.get::<&str>("format")) {
+                    ("closedcaption/x-cea-608", Ok("raw")) => match s.get::<i32>("field") {
+                        Ok(1) => InputFormat::Raw { field: 1 },
+                        _ => InputFormat::Raw { field: 0 },
+                    },
+                    ("closedcaption/x-cea-608", Ok("s334-1a")) => InputFormat::S334_1a,
+                    ("closedcaption/x-cea-708", Ok("cc_data")) => InputFormat::CcData,
+                    ("closedcaption/x-cea-708", Ok("cdp")) => InputFormat::Cdp,
+                    _ => {
+                        gst::error!(CAT, obj: pad, "Unsupported caps {caps}");
+                        return false;
+                    }
+                };
+
+                gst::debug!(CAT, obj: pad, "Configured for input format {format:?}");
+
+                let mut state = self.state.borrow_mut();
+                let had_format = state.format.replace(format).is_some();
+                drop(state);
+
+                if had_format {
+                    return true;
+                }
+
+                let new_caps = gst::Caps::builder("text/x-raw")
+                    .field("format", "utf8")
+                    .build();
+
+                return self.srcpad.push_event(gst::event::Caps::new(&new_caps));
+            }
+            EventView::Gap(gap) => {
+                // The duration of the currently displayed text covers the gap
+                if self.state.borrow().current_text.is_some() {
+                    let (timestamp, _) = gap.get();
+                    gst::trace!(CAT, obj: pad, "Dropping gap at {timestamp} while text is displayed");
+                    return true;
+                }
+            }
+            EventView::Segment(..) => {
+                if self.drain().is_err() {
+                    return false;
+                }
+            }
+            EventView::FlushStop(..) => {
+                let settings = *self.settings.lock().unwrap();
+                let mut state = self.state.borrow_mut();
+                let format = state.format;
+                *state = State::new(&settings);
+                state.format = format;
+            }
+            EventView::Eos(..) => {
+                gst::debug!(CAT, obj: pad, "Outputting final text on EOS");
+                let _ = self.drain();
+            }
+            _ => (),
+        }
+
+        gst::Pad::event_default(pad, Some(&*self.obj()), event)
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for CcDec {
+    const NAME: &'static str = "GstCcDec";
+    type Type = super::CcDec;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("sink").unwrap();
+        let sinkpad = gst::Pad::builder_from_template(&templ)
+            .chain_function(|pad, parent, buffer| {
+                CcDec::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |this| this.sink_chain(pad, buffer),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                CcDec::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |this| this.sink_event(pad, event),
+                )
+            })
+            .flags(gst::PadFlags::FIXED_CAPS)
+            .build();
+
+        let templ = klass.pad_template("src").unwrap();
+        let srcpad = gst::Pad::builder_from_template(&templ)
+            .flags(gst::PadFlags::FIXED_CAPS)
+            .build();
+
+        let settings = Settings::default();
+
+        Self {
+            srcpad,
+            sinkpad,
+            state: AtomicRefCell::new(State::new(&settings)),
+            settings: Mutex::new(settings),
+        }
+    }
+}
+
+impl ObjectImpl for CcDec {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecInt::builder("field")
+                    .nick("Field")
+                    .blurb("The field to decode captions from when available, (-1=automatic)")
+                    .minimum(-1)
+                    .maximum(1)
+                    .default_value(DEFAULT_FIELD)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("channel")
+                    .nick("Channel")
+                    .blurb("The data channel of the selected field to decode, (0=automatic)")
+                    .maximum(2)
+                    .default_value(DEFAULT_CHANNEL)
+                    .mutable_ready()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "field" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.field = value.get().expect("type checked upstream");
+            }
+            "channel" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.channel = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "field" => {
+                let settings = self.settings.lock().unwrap();
+                settings.field.to_value()
+            }
+            "channel" => {
+                let settings = self.settings.lock().unwrap();
+                settings.channel.to_value()
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.sinkpad).unwrap();
+        obj.add_pad(&self.srcpad).unwrap();
+    }
+}
+
+impl GstObjectImpl for CcDec {}
+
+impl ElementImpl for CcDec {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Closed Caption Decoder",
+                "Decoder/ClosedCaption",
+                "Decodes CEA-608 closed captions, either raw or carried in CEA-708 cc_data, to timed text",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst::Caps::builder("text/x-raw")
+                .field("format", "utf8")
+                .build();
+
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let caps = gst::Caps::builder_full()
+                .structure(
+                    gst::Structure::builder("closedcaption/x-cea-608")
+                        .field("format", gst::List::new(["raw", "s334-1a"]))
+                        .build(),
+                )
+                .structure(
+                    gst::Structure::builder("closedcaption/x-cea-708")
+                        .field("format", gst::List::new(["cc_data", "cdp"]))
+                        .build(),
+                )
+                .build();
+
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    #[allow(clippy::single_match)]
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        gst::trace!(CAT, imp: self, "Changing state {:?}", transition);
+
+        match transition {
+            gst::StateChange::ReadyToPaused | gst::StateChange::PausedToReady => {
+                let settings = *self.settings.lock().unwrap();
+                let mut state = self.state.borrow_mut();
+                *state = State::new(&settings);
+            }
+            _ => (),
+        }
+
+        self.parent_change_state(transition)
+    }
+}
@@ -0,0 +1,25 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+// Example command-line:
+//
+// gst-launch-1.0 filesrc location=input.mcc ! mccparse ! ccconverter ! \
+//   closedcaption/x-cea-708,format=cc_data ! ccdec ! fakesink dump=true
+
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct CcDec(ObjectSubclass<imp::CcDec>) @extends gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(Some(plugin), "ccdec", gst::Rank::NONE, CcDec::static_type())
+}
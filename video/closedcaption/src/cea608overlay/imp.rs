@@ -6,6 +6,7 @@
 //
 // SPDX-License-Identifier: MPL-2.0
 
+use cea608_types::tables::Channel;
 use gst::glib;
 use gst::prelude::*;
 use gst::subclass::prelude::*;
@@ -27,11 +28,13 @@ static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
 });
 
 const DEFAULT_FIELD: i32 = -1;
+const DEFAULT_CHANNEL: u32 = 1;
 const DEFAULT_BLACK_BACKGROUND: bool = false;
 
 #[derive(Debug)]
 struct Settings {
     field: i32,
+    channel: u32,
     black_background: bool,
     timeout: Option<gst::ClockTime>,
 }
@@ -40,6 +43,7 @@ impl Default for Settings {
     fn default() -> Self {
         Settings {
             field: DEFAULT_FIELD,
+            channel: DEFAULT_CHANNEL,
             black_background: DEFAULT_BLACK_BACKGROUND,
             timeout: gst::ClockTime::NONE,
         }
@@ -52,6 +56,7 @@ struct State {
     composition: Option<gst_video::VideoOverlayComposition>,
     attach: bool,
     selected_field: Option<u8>,
+    selected_channel: Channel,
     last_cc_pts: Option<gst::ClockTime>,
 }
 
@@ -63,11 +68,19 @@ impl Default for State {
             composition: None,
             attach: false,
             selected_field: None,
+            selected_channel: Channel::ONE,
             last_cc_pts: gst::ClockTime::NONE,
         }
     }
 }
 
+fn channel_from_settings(settings: &Settings) -> Channel {
+    match settings.channel {
+        2 => Channel::TWO,
+        _ => Channel::ONE,
+    }
+}
+
 pub struct Cea608Overlay {
     srcpad: gst::Pad,
     sinkpad: gst::Pad,
@@ -122,9 +135,8 @@ impl Cea608Overlay {
         state
             .renderer
             .set_video_size(video_info.width(), video_info.height());
-        state
-            .renderer
-            .set_channel(cea608_types::tables::Channel::ONE);
+        let channel = state.selected_channel;
+        state.renderer.set_channel(channel);
 
         if !self.srcpad.push_event(gst::event::Caps::new(&caps)) {
             Err(gst::FlowError::NotNegotiated)
@@ -383,6 +395,14 @@ impl ObjectImpl for Cea608Overlay {
                     .default_value(DEFAULT_FIELD)
                     .mutable_playing()
                     .build(),
+                glib::ParamSpecUInt::builder("channel")
+                    .nick("Channel")
+                    .blurb("The data channel of the selected field to render the caption for")
+                    .minimum(1)
+                    .maximum(2)
+                    .default_value(DEFAULT_CHANNEL)
+                    .mutable_playing()
+                    .build(),
                 glib::ParamSpecBoolean::builder("black-background")
                     .nick("Black background")
                     .blurb("Whether a black background should be drawn behind text")
@@ -414,6 +434,16 @@ impl ObjectImpl for Cea608Overlay {
                     val => Some(val as u8),
                 };
             }
+            "channel" => {
+                let mut settings = self.settings.lock().unwrap();
+                let mut state = self.state.lock().unwrap();
+
+                settings.channel = value.get().expect("type checked upstream");
+                state.selected_channel = channel_from_settings(&settings);
+                let channel = state.selected_channel;
+                state.renderer.set_channel(channel);
+                state.composition.take();
+            }
             "black-background" => {
                 let mut settings = self.settings.lock().unwrap();
                 let mut state = self.state.lock().unwrap();
@@ -444,6 +474,10 @@ impl ObjectImpl for Cea608Overlay {
                 let settings = self.settings.lock().unwrap();
                 settings.field.to_value()
             }
+            "channel" => {
+                let settings = self.settings.lock().unwrap();
+                settings.channel.to_value()
+            }
             "black-background" => {
                 let settings = self.settings.lock().unwrap();
                 settings.black_background.to_value()
@@ -529,6 +563,7 @@ impl ElementImpl for Cea608Overlay {
                     -1 => None,
                     val => Some(val as u8),
                 };
+                state.selected_channel = channel_from_settings(&settings);
                 state
                     .renderer
                     .set_black_background(settings.black_background);
@@ -17,6 +17,7 @@ use gst::glib;
 #[cfg(feature = "doc")]
 use gst::prelude::*;
 
+mod ccdec;
 mod ccdetect;
 mod ccutils;
 mod cea608overlay;
@@ -34,6 +35,10 @@ mod mcc_parse;
 mod parser_utils;
 mod scc_enc;
 mod scc_parse;
+mod seiccextract;
+mod seiccinject;
+mod seiutils;
+mod teletextdec;
 mod transcriberbin;
 mod tttocea608;
 mod tttocea708;
@@ -62,6 +67,10 @@ fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
     cea708mux::register(plugin)?;
     tttocea708::register(plugin)?;
     cea708overlay::register(plugin)?;
+    ccdec::register(plugin)?;
+    seiccextract::register(plugin)?;
+    seiccinject::register(plugin)?;
+    teletextdec::register(plugin)?;
     Ok(())
 }
 
@@ -103,8 +103,18 @@ impl Default for State {
 fn parse_timecode(
     framerate: gst::Fraction,
     drop_frame: bool,
-    tc: TimeCode,
+    mut tc: TimeCode,
 ) -> Result<ValidVideoTimeCode, gst::FlowError> {
+    // Same workaround as in sccparse for files having invalid drop frame timecodes: every
+    // full minute except for every tenth minute the first two frames (four at 59.94fps)
+    // are skipped.
+    if drop_frame && tc.seconds == 0 && tc.minutes % 10 != 0 {
+        let dropped_frames = (framerate.numer() as u32 / 1000) / 30 * 2;
+        if tc.frames < dropped_frames {
+            tc.frames = dropped_frames;
+        }
+    }
+
     let timecode = gst_video::VideoTimeCode::new(
         framerate,
         None,
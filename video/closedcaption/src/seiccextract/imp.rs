@@ -0,0 +1,411 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+use once_cell::sync::Lazy;
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use crate::seiutils::{extract_cc_data, Codec, NalFormat};
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "seiccextract",
+        gst::DebugColorFlags::empty(),
+        Some("SEI Closed Caption Extractor"),
+    )
+});
+
+/// Captions of a frame, or lack thereof
+#[derive(Debug)]
+struct Captions {
+    pts: gst::ClockTime,
+    duration: Option<gst::ClockTime>,
+    cc_data: Vec<u8>,
+}
+
+#[derive(Default)]
+struct State {
+    format: Option<(Codec, NalFormat)>,
+    /// Captions sorted by PTS, waiting until no earlier frame can follow
+    pending: VecDeque<Captions>,
+    combiner: gst_base::UniqueFlowCombiner,
+}
+
+pub struct SeiCcExtract {
+    srcpad: gst::Pad,
+    captionpad: gst::Pad,
+    sinkpad: gst::Pad,
+    state: Mutex<State>,
+}
+
+impl SeiCcExtract {
+    /// Takes all pending captions up to the given time, or all if no time is given
+    fn take_pending(state: &mut State, until: Option<gst::ClockTime>) -> Vec<Captions> {
+        let n = match until {
+            Some(until) => state
+                .pending
+                .iter()
+                .position(|captions| captions.pts > until)
+                .unwrap_or(state.pending.len()),
+            None => state.pending.len(),
+        };
+
+        state.pending.drain(..n).collect()
+    }
+
+    fn push_captions(&self, captions: Vec<Captions>) -> Result<gst::FlowSuccess, gst::FlowError> {
+        for captions in captions {
+            if captions.cc_data.is_empty() {
+                self.captionpad.push_event(
+                    gst::event::Gap::builder(captions.pts)
+                        .duration(captions.duration)
+                        .build(),
+                );
+                continue;
+            }
+
+            gst::trace!(
+                CAT,
+                imp: self,
+                "Pushing {} cc_data triples at {}",
+                captions.cc_data.len() / 3,
+                captions.pts
+            );
+
+            let mut buffer = gst::Buffer::from_mut_slice(captions.cc_data);
+            {
+                let buffer = buffer.get_mut().unwrap();
+                buffer.set_pts(captions.pts);
+                buffer.set_duration(captions.duration);
+            }
+
+            let res = self.captionpad.push(buffer);
+            let res = self
+                .state
+                .lock()
+                .unwrap()
+                .combiner
+                .update_pad_flow(&self.captionpad, res);
+            res?;
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    fn sink_chain(
+        &self,
+        pad: &gst::Pad,
+        buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::log!(CAT, obj: pad, "Handling buffer {:?}", buffer);
+
+        let mut state = self.state.lock().unwrap();
+        let Some((codec, format)) = state.format else {
+            return Err(gst::FlowError::NotNegotiated);
+        };
+
+        if let Some(pts) = buffer.pts() {
+            let map = buffer.map_readable().map_err(|_| {
+                gst::element_imp_error!(
+                    self,
+                    gst::ResourceError::Read,
+                    ["Failed to map buffer readable"]
+                );
+
+                gst::FlowError::Error
+            })?;
+
+            let captions = Captions {
+                pts,
+                duration: buffer.duration(),
+                cc_data: extract_cc_data(codec, format, &map),
+            };
+
+            let pos = state
+                .pending
+                .iter()
+                .position(|pending| pending.pts > pts)
+                .unwrap_or(state.pending.len());
+            state.pending.insert(pos, captions);
+        } else {
+            gst::warning!(CAT, obj: pad, "Ignoring captions of buffer without PTS");
+        }
+
+        // All following frames have a PTS after this DTS
+        let captions = Self::take_pending(&mut state, buffer.dts_or_pts());
+        drop(state);
+
+        let res = self.srcpad.push(buffer);
+        let res = self
+            .state
+            .lock()
+            .unwrap()
+            .combiner
+            .update_pad_flow(&self.srcpad, res)?;
+
+        self.push_captions(captions)?;
+
+        Ok(res)
+    }
+
+    fn sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::log!(CAT, obj: pad, "Handling event {:?}", event);
+
+        match event.view() {
+            EventView::StreamStart(e) => {
+                let stream_start =
+                    gst::event::StreamStart::builder(&format!("{}/caption", e.stream_id()))
+                        .seqnum(e.seqnum())
+                        .flags(e.stream_flags())
+                        .group_id(e.group_id().unwrap_or_else(gst::GroupId::next))
+                        .build();
+                self.captionpad.push_event(stream_start);
+
+                self.srcpad.push_event(event)
+            }
+            EventView::Caps(e) => {
+                let s = e.caps().structure(0).unwrap();
+                let Some(format) = Codec::from_caps(s) else {
+                    gst::error!(CAT, obj: pad, "Unsupported caps {:?}", e.caps());
+                    return false;
+                };
+                gst::debug!(CAT, obj: pad, "Stream format {:?}", format);
+                self.state.lock().unwrap().format = Some(format);
+
+                let mut caps = gst::Caps::builder("closedcaption/x-cea-708")
+                    .field("format", "cc_data")
+                    .build();
+                if let Ok(framerate) = s.get::<gst::Fraction>("framerate") {
+                    caps.get_mut().unwrap().set("framerate", framerate);
+                }
+                self.captionpad.push_event(gst::event::Caps::new(&caps));
+
+                self.srcpad.push_event(event)
+            }
+            EventView::FlushStop(_) => {
+                let mut state = self.state.lock().unwrap();
+                state.pending.clear();
+                state.combiner.reset();
+                drop(state);
+
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            EventView::Eos(_) => {
+                let captions = Self::take_pending(&mut self.state.lock().unwrap(), None);
+                if let Err(err) = self.push_captions(captions) {
+                    gst::debug!(CAT, obj: pad, "Failed to push remaining captions: {err:?}");
+                }
+
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            _ => gst::Pad::event_default(pad, Some(&*self.obj()), event),
+        }
+    }
+
+    fn sink_query(&self, pad: &gst::Pad, query: &mut gst::QueryRef) -> bool {
+        use gst::QueryViewMut;
+
+        gst::log!(CAT, obj: pad, "Handling query {:?}", query);
+
+        match query.view_mut() {
+            // Only the video stream is relevant for upstream
+            QueryViewMut::Caps(_) | QueryViewMut::AcceptCaps(_) | QueryViewMut::Allocation(_) => {
+                self.srcpad.peer_query(query)
+            }
+            _ => gst::Pad::query_default(pad, Some(&*self.obj()), query),
+        }
+    }
+
+    fn caption_query(&self, pad: &gst::Pad, query: &mut gst::QueryRef) -> bool {
+        use gst::QueryViewMut;
+
+        gst::log!(CAT, obj: pad, "Handling query {:?}", query);
+
+        match query.view_mut() {
+            QueryViewMut::Caps(q) => {
+                let mut caps = pad.pad_template_caps();
+                if let Some(filter) = q.filter() {
+                    caps = filter.intersect_with_mode(&caps, gst::CapsIntersectMode::First);
+                }
+                q.set_result(&caps);
+                true
+            }
+            QueryViewMut::AcceptCaps(q) => {
+                let accepted = q.caps().can_intersect(&pad.pad_template_caps());
+                q.set_result(accepted);
+                true
+            }
+            _ => gst::Pad::query_default(pad, Some(&*self.obj()), query),
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for SeiCcExtract {
+    const NAME: &'static str = "GstSeiCcExtract";
+    type Type = super::SeiCcExtract;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("sink").unwrap();
+        let sinkpad = gst::Pad::builder_from_template(&templ)
+            .chain_function(|pad, parent, buffer| {
+                SeiCcExtract::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |extract| extract.sink_chain(pad, buffer),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                SeiCcExtract::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |extract| extract.sink_event(pad, event),
+                )
+            })
+            .query_function(|pad, parent, query| {
+                SeiCcExtract::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |extract| extract.sink_query(pad, query),
+                )
+            })
+            .build();
+
+        let templ = klass.pad_template("src").unwrap();
+        let srcpad = gst::Pad::builder_from_template(&templ)
+            .flags(gst::PadFlags::FIXED_CAPS)
+            .build();
+
+        let templ = klass.pad_template("caption").unwrap();
+        let captionpad = gst::Pad::builder_from_template(&templ)
+            .query_function(|pad, parent, query| {
+                SeiCcExtract::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |extract| extract.caption_query(pad, query),
+                )
+            })
+            .flags(gst::PadFlags::FIXED_CAPS)
+            .build();
+
+        Self {
+            srcpad,
+            captionpad,
+            sinkpad,
+            state: Mutex::new(State::default()),
+        }
+    }
+}
+
+impl ObjectImpl for SeiCcExtract {
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.sinkpad).unwrap();
+        obj.add_pad(&self.srcpad).unwrap();
+        obj.add_pad(&self.captionpad).unwrap();
+    }
+}
+
+impl GstObjectImpl for SeiCcExtract {}
+
+impl ElementImpl for SeiCcExtract {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "SEI Closed Caption Extractor",
+                "Codec/Video/Demuxer",
+                "Extracts CEA-708 closed captions from H.264/H.265 SEI messages",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst::Caps::builder_full()
+                .structure(
+                    gst::Structure::builder("video/x-h264")
+                        .field(
+                            "stream-format",
+                            gst::List::new(["byte-stream", "avc", "avc3"]),
+                        )
+                        .field("alignment", "au")
+                        .build(),
+                )
+                .structure(
+                    gst::Structure::builder("video/x-h265")
+                        .field(
+                            "stream-format",
+                            gst::List::new(["byte-stream", "hvc1", "hev1"]),
+                        )
+                        .field("alignment", "au")
+                        .build(),
+                )
+                .build();
+
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let caps = gst::Caps::builder("closedcaption/x-cea-708")
+                .field("format", "cc_data")
+                .build();
+            let caption_pad_template = gst::PadTemplate::new(
+                "caption",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![sink_pad_template, src_pad_template, caption_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        gst::trace!(CAT, imp: self, "Changing state {:?}", transition);
+
+        if let gst::StateChange::ReadyToPaused | gst::StateChange::PausedToReady = transition {
+            let mut state = self.state.lock().unwrap();
+            *state = State::default();
+            state.combiner.add_pad(&self.srcpad);
+            state.combiner.add_pad(&self.captionpad);
+        }
+
+        self.parent_change_state(transition)
+    }
+}
@@ -0,0 +1,36 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+// Extracts CEA-708 cc_data from the ATSC A/53 SEI messages of H.264/H.265 access units
+// into a separate stream in presentation order. Frames without captions are signalled
+// with gap events on the caption pad.
+//
+// Example command-line:
+//
+// gst-launch-1.0 filesrc location=input.ts ! tsdemux ! h264parse ! \
+//   video/x-h264,alignment=au ! seiccextract name=e \
+//   e.caption ! ccdec ! fakesink dump=true \
+//   e.src ! fakesink
+
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct SeiCcExtract(ObjectSubclass<imp::SeiCcExtract>) @extends gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "seiccextract",
+        gst::Rank::NONE,
+        SeiCcExtract::static_type(),
+    )
+}
@@ -0,0 +1,219 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::subclass::prelude::*;
+
+use once_cell::sync::Lazy;
+
+use std::sync::Mutex;
+
+use crate::ccutils::extract_cdp;
+use crate::seiutils::{insert_cc_data, Codec, NalFormat};
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "seiccinject",
+        gst::DebugColorFlags::empty(),
+        Some("SEI Closed Caption Injector"),
+    )
+});
+
+#[derive(Default)]
+pub struct SeiCcInject {
+    format: Mutex<Option<(Codec, NalFormat)>>,
+}
+
+impl SeiCcInject {
+    /// Converts all caption metas of the buffer to cc_data triples
+    fn cc_data(&self, buf: &gst::BufferRef) -> Vec<u8> {
+        use gst_video::VideoCaptionType::*;
+
+        let mut cc_data = vec![];
+
+        for meta in buf.iter_meta::<gst_video::VideoCaptionMeta>() {
+            let data = meta.data();
+
+            match meta.caption_type() {
+                Cea708Raw => cc_data.extend_from_slice(data),
+                Cea708Cdp => match extract_cdp(data) {
+                    Ok(data) => cc_data.extend_from_slice(data),
+                    Err(err) => {
+                        gst::warning!(CAT, imp: self, "Invalid CDP: {err}");
+                    }
+                },
+                Cea608Raw => {
+                    for pair in data.chunks_exact(2) {
+                        cc_data.extend_from_slice(&[0xfc, pair[0], pair[1]]);
+                    }
+                }
+                Cea608S3341a => {
+                    for triple in data.chunks_exact(3) {
+                        let field = if triple[0] & 0x80 != 0 { 0xfc } else { 0xfd };
+                        cc_data.extend_from_slice(&[field, triple[1], triple[2]]);
+                    }
+                }
+                caption_type => {
+                    gst::warning!(CAT, imp: self, "Unsupported caption type {caption_type:?}");
+                }
+            }
+        }
+
+        cc_data.truncate(cc_data.len() / 3 * 3);
+
+        cc_data
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for SeiCcInject {
+    const NAME: &'static str = "GstSeiCcInject";
+    type Type = super::SeiCcInject;
+    type ParentType = gst_base::BaseTransform;
+}
+
+impl ObjectImpl for SeiCcInject {}
+
+impl GstObjectImpl for SeiCcInject {}
+
+impl ElementImpl for SeiCcInject {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "SEI Closed Caption Injector",
+                "Codec/Video",
+                "Injects closed caption meta as CEA-708 SEI messages into H.264/H.265 streams",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst::Caps::builder_full()
+                .structure(
+                    gst::Structure::builder("video/x-h264")
+                        .field(
+                            "stream-format",
+                            gst::List::new(["byte-stream", "avc", "avc3"]),
+                        )
+                        .field("alignment", "au")
+                        .build(),
+                )
+                .structure(
+                    gst::Structure::builder("video/x-h265")
+                        .field(
+                            "stream-format",
+                            gst::List::new(["byte-stream", "hvc1", "hev1"]),
+                        )
+                        .field("alignment", "au")
+                        .build(),
+                )
+                .build();
+
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![sink_pad_template, src_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseTransformImpl for SeiCcInject {
+    const MODE: gst_base::subclass::BaseTransformMode =
+        gst_base::subclass::BaseTransformMode::AlwaysInPlace;
+    const PASSTHROUGH_ON_SAME_CAPS: bool = false;
+    const TRANSFORM_IP_ON_PASSTHROUGH: bool = false;
+
+    fn set_caps(&self, incaps: &gst::Caps, _outcaps: &gst::Caps) -> Result<(), gst::LoggableError> {
+        let s = incaps
+            .structure(0)
+            .ok_or_else(|| gst::loggable_error!(CAT, "Failed to parse input caps"))?;
+        let format = Codec::from_caps(s)
+            .ok_or_else(|| gst::loggable_error!(CAT, "Unsupported caps {incaps}"))?;
+
+        gst::debug!(CAT, imp: self, "Stream format {:?}", format);
+        *self.format.lock().unwrap() = Some(format);
+
+        Ok(())
+    }
+
+    fn transform_ip(&self, buf: &mut gst::BufferRef) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let Some((codec, format)) = *self.format.lock().unwrap() else {
+            return Err(gst::FlowError::NotNegotiated);
+        };
+
+        let cc_data = self.cc_data(buf);
+        if cc_data.is_empty() {
+            return Ok(gst::FlowSuccess::Ok);
+        }
+
+        let data = {
+            let map = buf.map_readable().map_err(|_| {
+                gst::element_imp_error!(
+                    self,
+                    gst::ResourceError::Read,
+                    ["Failed to map buffer readable"]
+                );
+
+                gst::FlowError::Error
+            })?;
+
+            insert_cc_data(codec, format, &map, &cc_data)
+        };
+
+        let Some(data) = data else {
+            gst::warning!(CAT, imp: self, "No slice in buffer {buf:?}, dropping captions");
+            return Ok(gst::FlowSuccess::Ok);
+        };
+
+        gst::trace!(
+            CAT,
+            imp: self,
+            "Injecting {} cc_data triples at {}",
+            cc_data.len() / 3,
+            buf.pts().display()
+        );
+
+        buf.replace_all_memory(gst::Memory::from_mut_slice(data));
+
+        // The captions are part of the bitstream now
+        while let Some(meta) = buf.meta_mut::<gst_video::VideoCaptionMeta>() {
+            if meta.remove().is_err() {
+                break;
+            }
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        *self.format.lock().unwrap() = None;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,37 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+// Injects the CEA-708 closed caption meta of H.264/H.265 access units as ATSC A/53 SEI
+// messages into the bitstream, e.g. after re-encoding video whose captions were kept as
+// meta or combined with cccombiner.
+//
+// Example command-line:
+//
+// gst-launch-1.0 filesrc location=input.ts ! tsdemux ! h264parse ! \
+//   video/x-h264,alignment=au ! seiccextract name=e \
+//   e.src ! decodebin ! x264enc ! video/x-h264,alignment=au ! c.sink \
+//   e.caption ! c.caption \
+//   cccombiner name=c ! seiccinject ! h264parse ! mpegtsmux ! filesink location=output.ts
+
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct SeiCcInject(ObjectSubclass<imp::SeiCcInject>) @extends gst_base::BaseTransform, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "seiccinject",
+        gst::Rank::NONE,
+        SeiCcInject::static_type(),
+    )
+}
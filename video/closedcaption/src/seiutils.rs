@@ -0,0 +1,399 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Helpers for carrying CEA-708 cc_data in H.264/H.265 SEI messages as specified by
+//! ATSC A/53 Part 4 and ANSI/SCTE 128-1.
+
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    H264,
+    H265,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NalFormat {
+    /// Annex B start codes
+    ByteStream,
+    /// Big endian length prefix of the given size
+    Length(usize),
+}
+
+/// SEI payload type of registered ITU-T T.35 user data
+const PAYLOAD_TYPE_T35: u8 = 4;
+/// ITU-T T.35 country code (USA), provider code (ATSC) and ATSC user identifier
+const ATSC_T35_HEADER: [u8; 7] = [0xb5, 0x00, 0x31, b'G', b'A', b'9', b'4'];
+/// ATSC user_data_type_code of cc_data
+const ATSC_CC_DATA: u8 = 0x03;
+/// Maximum number of cc_data triples in a single cc_data structure
+const MAX_CC_COUNT: usize = 31;
+
+impl Codec {
+    /// Determines codec and NAL unit framing from the caps, if supported
+    pub fn from_caps(s: &gst::StructureRef) -> Option<(Codec, NalFormat)> {
+        let codec = match s.name().as_str() {
+            "video/x-h264" => Codec::H264,
+            "video/x-h265" => Codec::H265,
+            _ => return None,
+        };
+
+        let stream_format = s.get::<&str>("stream-format").unwrap_or("byte-stream");
+        let format = match (codec, stream_format) {
+            (_, "byte-stream") => NalFormat::ByteStream,
+            (Codec::H264, "avc" | "avc3") | (Codec::H265, "hvc1" | "hev1") => {
+                let codec_data = s.get::<gst::Buffer>("codec_data").ok()?;
+                let map = codec_data.map_readable().ok()?;
+                let pos = if codec == Codec::H264 { 4 } else { 21 };
+                NalFormat::Length((*map.get(pos)? & 0x03) as usize + 1)
+            }
+            _ => return None,
+        };
+
+        Some((codec, format))
+    }
+
+    fn header_len(self) -> usize {
+        match self {
+            Codec::H264 => 1,
+            Codec::H265 => 2,
+        }
+    }
+
+    fn nal_type(self, nal: &[u8]) -> u8 {
+        match self {
+            Codec::H264 => nal[0] & 0x1f,
+            Codec::H265 => (nal[0] >> 1) & 0x3f,
+        }
+    }
+
+    fn is_vcl(self, nal: &[u8]) -> bool {
+        match self {
+            Codec::H264 => (1..=5).contains(&self.nal_type(nal)),
+            Codec::H265 => self.nal_type(nal) < 32,
+        }
+    }
+
+    fn is_sei(self, nal: &[u8]) -> bool {
+        match self {
+            Codec::H264 => self.nal_type(nal) == 6,
+            // Only prefix SEI can carry captions
+            Codec::H265 => self.nal_type(nal) == 39,
+        }
+    }
+
+    fn sei_header(self) -> &'static [u8] {
+        match self {
+            Codec::H264 => &[0x06],
+            Codec::H265 => &[39 << 1, 0x01],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Nal {
+    /// Start of the start code or length prefix
+    offset: usize,
+    /// The NAL unit itself
+    range: Range<usize>,
+}
+
+fn split_nals(data: &[u8], format: NalFormat) -> Vec<Nal> {
+    let mut nals = vec![];
+
+    match format {
+        NalFormat::ByteStream => {
+            let start_code = |pos: usize| data[pos..].starts_with(&[0, 0, 1]);
+
+            let mut offset = 0;
+            let mut pos = 0;
+            while pos + 3 <= data.len() && !start_code(pos) {
+                pos += 1;
+            }
+
+            while pos + 3 <= data.len() {
+                let start = pos + 3;
+                pos = start;
+                while pos + 3 <= data.len() && !start_code(pos) {
+                    pos += 1;
+                }
+                if pos + 3 > data.len() {
+                    pos = data.len();
+                }
+
+                // Drop trailing zero bytes, which belong to the next start code
+                let mut end = pos;
+                while end > start && data[end - 1] == 0 {
+                    end -= 1;
+                }
+
+                if end > start {
+                    nals.push(Nal {
+                        offset,
+                        range: start..end,
+                    });
+                }
+                offset = end;
+            }
+        }
+        NalFormat::Length(size) => {
+            let mut pos = 0;
+            while pos + size <= data.len() {
+                let len = data[pos..pos + size]
+                    .iter()
+                    .fold(0usize, |len, b| (len << 8) | *b as usize);
+                let start = pos + size;
+                if len == 0 || start + len > data.len() {
+                    break;
+                }
+
+                nals.push(Nal {
+                    offset: pos,
+                    range: start..start + len,
+                });
+                pos = start + len;
+            }
+        }
+    }
+
+    nals
+}
+
+/// Removes emulation prevention bytes
+fn unescape(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut zeroes = 0;
+
+    for &b in data {
+        if zeroes >= 2 && b == 0x03 {
+            zeroes = 0;
+            continue;
+        }
+
+        zeroes = if b == 0 { zeroes + 1 } else { 0 };
+        out.push(b);
+    }
+
+    out
+}
+
+/// Inserts emulation prevention bytes
+fn escape(out: &mut Vec<u8>, data: &[u8]) {
+    let mut zeroes = 0;
+
+    for &b in data {
+        if zeroes >= 2 && b <= 0x03 {
+            out.push(0x03);
+            zeroes = 0;
+        }
+
+        zeroes = if b == 0 { zeroes + 1 } else { 0 };
+        out.push(b);
+    }
+}
+
+/// Reads an SEI payload type or size
+fn read_sei_value(data: &mut &[u8]) -> Option<usize> {
+    let mut value = 0;
+
+    loop {
+        let (&b, rest) = data.split_first()?;
+        *data = rest;
+        value += b as usize;
+        if b != 0xff {
+            return Some(value);
+        }
+    }
+}
+
+fn write_sei_value(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0xff {
+        out.push(0xff);
+        value -= 0xff;
+    }
+    out.push(value as u8);
+}
+
+/// Returns the cc_data triples of registered ITU-T T.35 user data, if it contains
+/// ATSC captions
+fn parse_t35_cc_data(payload: &[u8]) -> Option<&[u8]> {
+    let data = payload.strip_prefix(&ATSC_T35_HEADER)?;
+    let data = data.strip_prefix(&[ATSC_CC_DATA])?;
+
+    // process_cc_data_flag, cc_count, em_data
+    let (&flags, data) = data.split_first()?;
+    if flags & 0x40 == 0 {
+        return None;
+    }
+    let len = 3 * (flags & 0x1f) as usize;
+
+    data.get(1..1 + len)
+}
+
+/// Returns the cc_data triples of all SEI messages of the access unit
+pub fn extract_cc_data(codec: Codec, format: NalFormat, data: &[u8]) -> Vec<u8> {
+    let mut cc_data = vec![];
+
+    for nal in split_nals(data, format) {
+        let nal = &data[nal.range];
+        if nal.len() <= codec.header_len() || !codec.is_sei(nal) {
+            continue;
+        }
+
+        let rbsp = unescape(&nal[codec.header_len()..]);
+        let mut data = rbsp.as_slice();
+
+        // The last byte is the RBSP trailing bits
+        while data.len() > 1 {
+            let (Some(payload_type), Some(payload_size)) =
+                (read_sei_value(&mut data), read_sei_value(&mut data))
+            else {
+                break;
+            };
+            if payload_size > data.len() {
+                break;
+            }
+
+            let (payload, rest) = data.split_at(payload_size);
+            if payload_type == PAYLOAD_TYPE_T35 as usize {
+                if let Some(triples) = parse_t35_cc_data(payload) {
+                    cc_data.extend_from_slice(triples);
+                }
+            }
+            data = rest;
+        }
+    }
+
+    cc_data
+}
+
+/// Creates an SEI NAL unit carrying the cc_data triples
+fn sei_nal(codec: Codec, cc_data: &[u8]) -> Vec<u8> {
+    let mut rbsp = vec![];
+
+    for triples in cc_data.chunks(3 * MAX_CC_COUNT) {
+        let cc_count = triples.len() / 3;
+
+        rbsp.push(PAYLOAD_TYPE_T35);
+        write_sei_value(&mut rbsp, ATSC_T35_HEADER.len() + 4 + 3 * cc_count);
+        rbsp.extend_from_slice(&ATSC_T35_HEADER);
+        rbsp.push(ATSC_CC_DATA);
+        // reserved, process_cc_data_flag, cc_count and em_data
+        rbsp.extend_from_slice(&[0x80 | 0x40 | cc_count as u8, 0xff]);
+        rbsp.extend_from_slice(&triples[..3 * cc_count]);
+        // marker_bits
+        rbsp.push(0xff);
+    }
+    rbsp.push(0x80);
+
+    let mut nal = codec.sei_header().to_vec();
+    escape(&mut nal, &rbsp);
+
+    nal
+}
+
+/// Inserts an SEI NAL unit with the cc_data triples in front of the first slice of the
+/// access unit. Returns `None` if the access unit contains no slice.
+pub fn insert_cc_data(
+    codec: Codec,
+    format: NalFormat,
+    data: &[u8],
+    cc_data: &[u8],
+) -> Option<Vec<u8>> {
+    let pos = split_nals(data, format)
+        .into_iter()
+        .find(|nal| codec.is_vcl(&data[nal.range.clone()]))?
+        .offset;
+
+    let nal = sei_nal(codec, cc_data);
+
+    let mut out = Vec::with_capacity(data.len() + nal.len() + 4);
+    out.extend_from_slice(&data[..pos]);
+    match format {
+        NalFormat::ByteStream => out.extend_from_slice(&[0, 0, 0, 1]),
+        NalFormat::Length(size) => {
+            out.extend_from_slice(&(nal.len() as u64).to_be_bytes()[8 - size..]);
+        }
+    }
+    out.extend_from_slice(&nal);
+    out.extend_from_slice(&data[pos..]);
+
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CC_DATA: [u8; 6] = [0xfc, 0x94, 0x20, 0xfd, 0x80, 0x80];
+
+    #[test]
+    fn test_escape() {
+        let data = [0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x03, 0xff];
+        let mut escaped = vec![];
+        escape(&mut escaped, &data);
+        assert_eq!(
+            escaped,
+            [0x00, 0x00, 0x03, 0x01, 0x00, 0x00, 0x03, 0x00, 0x00, 0x03, 0x03, 0xff]
+        );
+        assert_eq!(unescape(&escaped), data);
+    }
+
+    #[test]
+    fn test_h264_byte_stream() {
+        // AUD, SPS and an IDR slice
+        let au = [
+            0x00, 0x00, 0x00, 0x01, 0x09, 0xf0, 0x00, 0x00, 0x01, 0x67, 0x42, 0xc0, 0x00, 0x00,
+            0x00, 0x01, 0x65, 0x88, 0x84,
+        ];
+        assert!(extract_cc_data(Codec::H264, NalFormat::ByteStream, &au).is_empty());
+
+        let out = insert_cc_data(Codec::H264, NalFormat::ByteStream, &au, &CC_DATA).unwrap();
+        assert_eq!(&out[..12], &au[..12]);
+        assert_eq!(&out[12..18], &[0x00, 0x00, 0x00, 0x01, 0x06, 0x04]);
+        assert_eq!(&out[out.len() - 7..], &au[12..]);
+
+        let nals = split_nals(&out, NalFormat::ByteStream);
+        assert_eq!(nals.len(), 4);
+        assert_eq!(
+            extract_cc_data(Codec::H264, NalFormat::ByteStream, &out),
+            CC_DATA
+        );
+
+        // Without a slice there is nowhere to put the captions
+        assert!(insert_cc_data(Codec::H264, NalFormat::ByteStream, &au[..12], &CC_DATA).is_none());
+    }
+
+    #[test]
+    fn test_h265_length_prefixed() {
+        // A TRAIL_R slice with a two byte length prefix
+        let au = [0x00, 0x04, 0x02, 0x01, 0xd0, 0x10];
+        let out = insert_cc_data(Codec::H265, NalFormat::Length(2), &au, &CC_DATA).unwrap();
+
+        let nals = split_nals(&out, NalFormat::Length(2));
+        assert_eq!(nals.len(), 2);
+        assert_eq!(&out[nals[0].range.start..][..2], &[0x4e, 0x01]);
+        assert_eq!(&out[nals[1].range.clone()], &au[2..]);
+        assert_eq!(
+            extract_cc_data(Codec::H265, NalFormat::Length(2), &out),
+            CC_DATA
+        );
+    }
+
+    #[test]
+    fn test_many_triples() {
+        let cc_data = (0..40u8).flat_map(|i| [0xfe, i, 0x00]).collect::<Vec<_>>();
+        let au = [0x00, 0x00, 0x01, 0x41, 0x9a];
+
+        let out = insert_cc_data(Codec::H264, NalFormat::ByteStream, &au, &cc_data).unwrap();
+        assert_eq!(
+            extract_cc_data(Codec::H264, NalFormat::ByteStream, &out),
+            cc_data
+        );
+    }
+}
@@ -0,0 +1,528 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+use once_cell::sync::Lazy;
+
+use std::sync::Mutex;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "teletextdec",
+        gst::DebugColorFlags::empty(),
+        Some("Teletext Decoder Element"),
+    )
+});
+
+const DEFAULT_PAGE: u32 = 888;
+
+/// Number of displayed rows, excluding the page header and the navigation row
+const ROWS: usize = 23;
+
+/// Data unit ids of EBU Teletext non-subtitle and subtitle data (EN 300 472)
+const DATA_UNIT_EBU_TELETEXT: u8 = 0x02;
+const DATA_UNIT_EBU_TELETEXT_SUBTITLE: u8 = 0x03;
+const DATA_UNIT_LENGTH: usize = 44;
+/// Framing code after reversing the bit order
+const FRAMING_CODE: u8 = 0x27;
+
+/// Hamming 8/4 code words of the values 0 to 15
+const HAMMING_8_4: [u8; 16] = [
+    0x15, 0x02, 0x49, 0x5e, 0x64, 0x73, 0x38, 0x2f, 0xd0, 0xc7, 0x8c, 0x9b, 0xa1, 0xb6, 0xfd, 0xea,
+];
+
+/// Decodes a Hamming 8/4 protected nibble, correcting single bit errors
+fn unham_8_4(b: u8) -> Option<u8> {
+    HAMMING_8_4
+        .iter()
+        .position(|c| (c ^ b).count_ones() <= 1)
+        .map(|v| v as u8)
+}
+
+/// Decodes a character with odd parity into the G0 Latin character set with the English
+/// national option subset, replacing control codes and characters with parity errors by spaces
+fn decode_char(b: u8) -> char {
+    if b.count_ones() % 2 == 0 {
+        return ' ';
+    }
+
+    match b & 0x7f {
+        0x00..=0x1f => ' ',
+        0x23 => '£',
+        0x5b => '←',
+        0x5c => '½',
+        0x5d => '→',
+        0x5e => '↑',
+        0x5f => '#',
+        0x60 => '—',
+        0x7b => '¼',
+        0x7c => '‖',
+        0x7d => '¾',
+        0x7e => '÷',
+        0x7f => '■',
+        c => c as char,
+    }
+}
+
+/// Decodes a row of a subtitle page, only keeping the text between start box and end box codes
+fn decode_boxed_row(data: &[u8]) -> String {
+    let mut text = String::new();
+    let mut boxed = false;
+
+    for b in data {
+        match b & 0x7f {
+            // Start box, sent twice
+            0x0b => boxed = true,
+            // End box
+            0x0a => boxed = false,
+            _ if boxed => text.push(decode_char(*b)),
+            _ => (),
+        }
+    }
+
+    text
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PageNumber {
+    magazine: u8,
+    tens: u8,
+    units: u8,
+}
+
+impl PageNumber {
+    fn from_property(page: u32) -> Self {
+        PageNumber {
+            magazine: (page / 100) as u8 % 8,
+            tens: (page / 10 % 10) as u8,
+            units: (page % 10) as u8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    page: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings { page: DEFAULT_PAGE }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    /// Whether the selected page is currently transmitted
+    collecting: bool,
+    subtitle: bool,
+    rows: [Option<String>; ROWS],
+    last_pts: Option<gst::ClockTime>,
+    previous_text: Option<(gst::ClockTime, String)>,
+}
+
+impl State {
+    fn page_text(&self) -> String {
+        let mut text = String::new();
+        for row in self.rows.iter().flatten() {
+            let row = row.trim();
+            if row.is_empty() {
+                continue;
+            }
+            if !text.is_empty() {
+                text.push('\n');
+            }
+            text.push_str(row);
+        }
+        text
+    }
+
+    /// Handles the completion of the selected page and returns the previous text that ended now
+    fn finish_page(&mut self, pts: gst::ClockTime) -> Option<gst::Buffer> {
+        self.collecting = false;
+
+        let text = self.page_text();
+        let previous_text = if text.is_empty() {
+            self.previous_text.take()
+        } else if self
+            .previous_text
+            .as_ref()
+            .map_or(false, |(_, previous)| *previous == text)
+        {
+            return None;
+        } else {
+            self.previous_text.replace((pts, text))
+        };
+
+        previous_text.map(|(timestamp, text)| {
+            create_buffer(timestamp, Some(pts.saturating_sub(timestamp)), text)
+        })
+    }
+}
+
+fn create_buffer(
+    timestamp: gst::ClockTime,
+    duration: Option<gst::ClockTime>,
+    text: String,
+) -> gst::Buffer {
+    let mut buffer = gst::Buffer::from_mut_slice(text.into_bytes());
+    {
+        let buffer = buffer.get_mut().unwrap();
+        buffer.set_pts(timestamp);
+        buffer.set_duration(duration);
+    }
+
+    buffer
+}
+
+pub struct TeletextDec {
+    srcpad: gst::Pad,
+    sinkpad: gst::Pad,
+
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+}
+
+impl TeletextDec {
+    fn sink_chain(
+        &self,
+        pad: &gst::Pad,
+        buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::log!(CAT, obj: pad, "Handling buffer {:?}", buffer);
+
+        let pts = buffer.pts().ok_or_else(|| {
+            gst::error!(CAT, obj: pad, "Require timestamped buffers");
+            gst::FlowError::Error
+        })?;
+
+        let data = buffer.map_readable().map_err(|_| {
+            gst::error!(CAT, obj: pad, "Can't map buffer readable");
+            gst::FlowError::Error
+        })?;
+
+        // EBU data identifiers are in the range 0x10 to 0x1f
+        if data.is_empty() || !(0x10..=0x1f).contains(&data[0]) {
+            gst::warning!(CAT, obj: pad, "Not EBU Teletext data");
+            return Ok(gst::FlowSuccess::Ok);
+        }
+
+        let page = PageNumber::from_property(self.settings.lock().unwrap().page);
+
+        let mut outbufs = Vec::new();
+        {
+            let mut state = self.state.lock().unwrap();
+            state.last_pts = Some(pts);
+
+            let mut units = &data[1..];
+            while units.len() >= 2 {
+                let data_unit_id = units[0];
+                let len = units[1] as usize;
+                let Some(unit) = units.get(2..2 + len) else {
+                    gst::warning!(CAT, obj: pad, "Truncated data unit");
+                    break;
+                };
+                units = &units[2 + len..];
+
+                if !matches!(
+                    data_unit_id,
+                    DATA_UNIT_EBU_TELETEXT | DATA_UNIT_EBU_TELETEXT_SUBTITLE
+                ) || len != DATA_UNIT_LENGTH
+                {
+                    continue;
+                }
+
+                // Teletext bytes are transmitted with the least significant bit first
+                let mut packet = [0u8; DATA_UNIT_LENGTH];
+                for (p, u) in packet.iter_mut().zip(unit) {
+                    *p = u.reverse_bits();
+                }
+
+                if packet[1] != FRAMING_CODE {
+                    gst::trace!(CAT, obj: pad, "Invalid framing code");
+                    continue;
+                }
+
+                if let Some(outbuf) = self.handle_packet(&mut state, page, &packet[2..], pts) {
+                    outbufs.push(outbuf);
+                }
+            }
+        }
+
+        for outbuf in outbufs {
+            gst::log!(CAT, imp: self, "Outputting {:?}", outbuf);
+            self.srcpad.push(outbuf)?;
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    /// Handles a single packet consisting of the two address bytes and 40 data bytes
+    fn handle_packet(
+        &self,
+        state: &mut State,
+        page: PageNumber,
+        packet: &[u8],
+        pts: gst::ClockTime,
+    ) -> Option<gst::Buffer> {
+        let (Some(a0), Some(a1)) = (unham_8_4(packet[0]), unham_8_4(packet[1])) else {
+            gst::trace!(CAT, imp: self, "Uncorrectable packet address");
+            return None;
+        };
+        let magazine = a0 & 0x07;
+        let row = (a0 >> 3) | (a1 << 1);
+        let data = &packet[2..];
+
+        match row {
+            0 => {
+                let mut header = [0u8; 8];
+                for (h, d) in header.iter_mut().zip(data) {
+                    *h = unham_8_4(*d)?;
+                }
+
+                let serial = header[7] & 0x01 != 0;
+
+                // A page ends with the next header of its magazine, or of any magazine if all
+                // magazines are transmitted in sequence
+                let outbuf = if state.collecting && (magazine == page.magazine || serial) {
+                    state.finish_page(pts)
+                } else {
+                    None
+                };
+
+                if magazine == page.magazine && header[1] == page.tens && header[0] == page.units
+                {
+                    let erase = header[3] & 0x08 != 0;
+                    let subtitle = header[5] & 0x08 != 0;
+
+                    gst::trace!(
+                        CAT,
+                        imp: self,
+                        "Selected page started, erase {erase}, subtitle {subtitle}"
+                    );
+
+                    if erase || subtitle != state.subtitle {
+                        state.rows = Default::default();
+                    }
+                    state.collecting = true;
+                    state.subtitle = subtitle;
+                }
+
+                outbuf
+            }
+            1..=23 if state.collecting && magazine == page.magazine => {
+                let text = if state.subtitle {
+                    decode_boxed_row(data)
+                } else {
+                    data.iter().map(|b| decode_char(*b)).collect()
+                };
+                state.rows[row as usize - 1] = Some(text);
+
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::log!(CAT, obj: pad, "Handling event {:?}", event);
+        match event.view() {
+            EventView::Caps(..) => {
+                let caps = gst::Caps::builder("text/x-raw")
+                    .field("format", "utf8")
+                    .build();
+
+                return self.srcpad.push_event(gst::event::Caps::new(&caps));
+            }
+            EventView::FlushStop(..) => {
+                *self.state.lock().unwrap() = State::default();
+            }
+            EventView::Eos(..) => {
+                let outbufs = {
+                    let mut state = self.state.lock().unwrap();
+                    let mut outbufs = Vec::new();
+
+                    if let Some(pts) = state.last_pts.filter(|_| state.collecting) {
+                        outbufs.extend(state.finish_page(pts));
+                    }
+                    if let Some((timestamp, text)) = state.previous_text.take() {
+                        gst::debug!(CAT, obj: pad, "Outputting final text on EOS");
+                        outbufs.push(create_buffer(timestamp, None, text));
+                    }
+
+                    outbufs
+                };
+
+                for outbuf in outbufs {
+                    let _ = self.srcpad.push(outbuf);
+                }
+            }
+            _ => (),
+        }
+
+        gst::Pad::event_default(pad, Some(&*self.obj()), event)
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for TeletextDec {
+    const NAME: &'static str = "GstTeletextDec";
+    type Type = super::TeletextDec;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("sink").unwrap();
+        let sinkpad = gst::Pad::builder_from_template(&templ)
+            .chain_function(|pad, parent, buffer| {
+                TeletextDec::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |this| this.sink_chain(pad, buffer),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                TeletextDec::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |this| this.sink_event(pad, event),
+                )
+            })
+            .flags(gst::PadFlags::FIXED_CAPS)
+            .build();
+
+        let templ = klass.pad_template("src").unwrap();
+        let srcpad = gst::Pad::builder_from_template(&templ)
+            .flags(gst::PadFlags::FIXED_CAPS)
+            .build();
+
+        Self {
+            srcpad,
+            sinkpad,
+            settings: Mutex::new(Settings::default()),
+            state: Mutex::new(State::default()),
+        }
+    }
+}
+
+impl ObjectImpl for TeletextDec {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![glib::ParamSpecUInt::builder("page")
+                .nick("Page")
+                .blurb("Number of the page to decode")
+                .minimum(100)
+                .maximum(899)
+                .default_value(DEFAULT_PAGE)
+                .mutable_playing()
+                .build()]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "page" => {
+                let page = value.get().expect("type checked upstream");
+                let mut settings = self.settings.lock().unwrap();
+                if settings.page != page {
+                    gst::debug!(CAT, imp: self, "Selecting page {page}");
+                    settings.page = page;
+                    let mut state = self.state.lock().unwrap();
+                    state.collecting = false;
+                    state.rows = Default::default();
+                }
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "page" => self.settings.lock().unwrap().page.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.sinkpad).unwrap();
+        obj.add_pad(&self.srcpad).unwrap();
+    }
+}
+
+impl GstObjectImpl for TeletextDec {}
+
+impl ElementImpl for TeletextDec {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Teletext Decoder",
+                "Decoder/Subtitle",
+                "Decodes EBU Teletext pages to timed text",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst::Caps::builder("text/x-raw")
+                .field("format", "utf8")
+                .build();
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let caps = gst::Caps::builder("application/x-teletext").build();
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        gst::trace!(CAT, imp: self, "Changing state {:?}", transition);
+
+        if transition == gst::StateChange::ReadyToPaused {
+            *self.state.lock().unwrap() = State::default();
+        }
+
+        let ret = self.parent_change_state(transition)?;
+
+        if transition == gst::StateChange::PausedToReady {
+            *self.state.lock().unwrap() = State::default();
+        }
+
+        Ok(ret)
+    }
+}
@@ -0,0 +1,46 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * element-teletextdec:
+ *
+ * Decodes EBU Teletext (EN 300 472) as carried in MPEG-TS into timed text.
+ *
+ * Only the page selected with the #teletextdec:page property is decoded. Each time the page
+ * content changes, the previous content is output as a text buffer whose duration lasts until
+ * the change. For subtitle pages only the boxed text is output, for other pages all rows except
+ * the page header.
+ *
+ * The text can be rendered on top of the video with `textoverlay` or `subtitleoverlay`:
+ *
+ * ```shell
+ * gst-launch-1.0 filesrc location=broadcast.ts ! tsdemux name=d \
+ *   d. ! queue ! decodebin ! videoconvert ! overlay.video_sink \
+ *   d. ! queue ! application/x-teletext ! teletextdec page=888 ! overlay.text_sink \
+ *   textoverlay name=overlay ! autovideosink
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct TeletextDec(ObjectSubclass<imp::TeletextDec>) @extends gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "teletextdec",
+        gst::Rank::NONE,
+        TeletextDec::static_type(),
+    )
+}
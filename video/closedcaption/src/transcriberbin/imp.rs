@@ -68,6 +68,23 @@ impl TranscriptionChannel {
     }
 }
 
+/* Transcribers other than awstranscriber don't necessarily implement
+ * all the properties we configure */
+fn set_transcriber_property<V: ToValue>(transcriber: &gst::Element, name: &str, value: V) {
+    let value = value.to_value();
+
+    if transcriber.has_property(name, Some(value.type_())) {
+        transcriber.set_property_from_value(name, &value);
+    } else {
+        gst::debug!(
+            CAT,
+            obj: transcriber,
+            "Transcriber has no {name} property of type {}",
+            value.type_()
+        );
+    }
+}
+
 /* Locking order: State, Settings, PadState, PadSettings */
 
 struct State {
@@ -497,14 +514,14 @@ impl TranscriberBin {
             let ps = pad.imp().state.lock().unwrap();
             let pad_state = ps.as_ref().unwrap();
             let latency_ms = settings.latency.mseconds() as u32;
-            pad_state
-                .transcriber
-                .set_property("transcribe-latency", latency_ms);
+            set_transcriber_property(&pad_state.transcriber, "transcribe-latency", latency_ms);
 
             let translate_latency_ms = settings.translate_latency.mseconds() as u32;
-            pad_state
-                .transcriber
-                .set_property("translate-latency", translate_latency_ms);
+            set_transcriber_property(
+                &pad_state.transcriber,
+                "translate-latency",
+                translate_latency_ms,
+            );
             pad_state
                 .queue_passthrough
                 .set_property("max-size-bytes", 0u32);
@@ -810,9 +827,11 @@ impl TranscriberBin {
                 .set_state(gst::State::Null)
                 .unwrap();
 
-            pad_state
-                .transcriber
-                .set_property("language-code", &pad_settings.language_code);
+            set_transcriber_property(
+                &pad_state.transcriber,
+                "language-code",
+                &pad_settings.language_code,
+            );
 
             if lang_code_only {
                 if !settings.passthrough {
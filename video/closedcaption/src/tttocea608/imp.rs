@@ -470,15 +470,18 @@ impl ObjectImpl for TtToCea608 {
                 state.force_clear = true;
             }
             "origin-column" => {
-                let mut settings = self.settings.lock().unwrap();
                 let mut state = self.state.lock().unwrap();
+                let mut settings = self.settings.lock().unwrap();
                 settings.origin_column = value.get().expect("type checked upstream");
                 state.force_clear = true;
+                state
+                    .translator
+                    .set_origin_column(settings.origin_column as u8);
                 state.translator.set_column(settings.origin_column as u8);
             }
             "roll-up-timeout" => {
-                let mut settings = self.settings.lock().unwrap();
                 let mut state = self.state.lock().unwrap();
+                let mut settings = self.settings.lock().unwrap();
 
                 let timeout = match value.get().expect("type checked upstream") {
                     u64::MAX => gst::ClockTime::NONE,
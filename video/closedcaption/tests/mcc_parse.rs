@@ -202,3 +202,61 @@ fn test_pull() {
         }
     }
 }
+
+/// Test that invalid drop frame timecodes are moved to the next valid frame
+#[test]
+fn test_drop_frame_fixup() {
+    init();
+
+    let data = b"File Format=MacCaption_MCC V1.0\n\
+        \n\
+        // Drop frame timecodes around minute boundaries\n\
+        \n\
+        UUID=CA8BC94D-9931-4EEE-812F-2D68FA74F287\n\
+        Time Code Rate=30DF\n\
+        \n\
+        00:00:59:29\tT49S494F43Z0B72F4QROO74Z0BA9AB\n\
+        00:01:00:00\tT49S494F43Z0C72F4QROO74Z0CA7AB\n\
+        00:01:00:03\tT49S494F43Z0D72F4QROO74Z0DA5AB\n\
+        00:10:00:00\tT49S494F43Z0E72F4QROO74Z0EA3AB\n";
+
+    let mut h = gst_check::Harness::new("mccparse");
+    h.set_src_caps_str("application/x-mcc, version=(int) 1");
+    assert_eq!(
+        h.push(gst::Buffer::from_slice(data)),
+        Ok(gst::FlowSuccess::Ok)
+    );
+    h.push_event(gst::event::Eos::new());
+
+    // Timecode and frame number since midnight of each line, the first two frames of each
+    // minute except for every tenth one don't exist in drop frame mode
+    let expected = [
+        ((0, 0, 59, 29), 1799),
+        ((0, 1, 0, 2), 1800),
+        ((0, 1, 0, 3), 1801),
+        ((0, 10, 0, 0), 17982),
+    ];
+
+    for (timecode, frame) in expected {
+        let buf = h.pull().unwrap();
+
+        let tc_meta = buf
+            .meta::<gst_video::VideoTimeCodeMeta>()
+            .expect("No timecode meta");
+        let tc = tc_meta.tc();
+        assert!(tc
+            .flags()
+            .contains(gst_video::VideoTimeCodeFlags::DROP_FRAME));
+        assert_eq!(
+            (tc.hours(), tc.minutes(), tc.seconds(), tc.frames()),
+            timecode
+        );
+
+        assert_eq!(
+            buf.pts(),
+            gst::ClockTime::SECOND.mul_div_floor(frame * 1001, 30000)
+        );
+    }
+
+    assert!(h.try_pull().is_none());
+}
@@ -0,0 +1,119 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+use gst::ClockTime;
+
+use pretty_assertions::assert_eq;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsclosedcaption::plugin_register_static().unwrap();
+    });
+}
+
+// AUD, SPS and an IDR slice
+const AU: [u8; 19] = [
+    0x00, 0x00, 0x00, 0x01, 0x09, 0xf0, 0x00, 0x00, 0x01, 0x67, 0x42, 0xc0, 0x00, 0x00, 0x00, 0x01,
+    0x65, 0x88, 0x84,
+];
+
+fn new_au(pts: ClockTime, dts: ClockTime, cc_data: Option<&[u8]>) -> gst::Buffer {
+    let mut buf = gst::Buffer::from_slice(AU);
+    {
+        let buf = buf.get_mut().unwrap();
+        buf.set_pts(pts);
+        buf.set_dts(dts);
+        buf.set_duration(ClockTime::from_mseconds(40));
+        if let Some(cc_data) = cc_data {
+            gst_video::VideoCaptionMeta::add(buf, gst_video::VideoCaptionType::Cea708Raw, cc_data);
+        }
+    }
+    buf
+}
+
+/// Injects captions and extracts them again, in presentation order
+#[test]
+fn test_inject_extract() {
+    init();
+
+    let mut h = gst_check::Harness::new_parse("seiccinject ! seiccextract name=e");
+    h.set_src_caps_str("video/x-h264, stream-format=byte-stream, alignment=au");
+    let mut caption = gst_check::Harness::with_element(
+        &h.element().unwrap().by_name("e").unwrap(),
+        None,
+        Some("caption"),
+    );
+    caption.play();
+
+    // Decode order I P B, with captions only on the P and B frames
+    let frames = [
+        (40, 0, None),
+        (120, 40, Some([0xfc, 0x80, 0x80].as_slice())),
+        (
+            80,
+            80,
+            Some([0xfc, 0x94, 0x20, 0xfd, 0x80, 0x80].as_slice()),
+        ),
+    ];
+
+    for (pts, dts, cc_data) in frames {
+        let buf = new_au(
+            ClockTime::from_mseconds(pts),
+            ClockTime::from_mseconds(dts),
+            cc_data,
+        );
+        assert_eq!(h.push(buf), Ok(gst::FlowSuccess::Ok));
+
+        // The captions are moved from the meta into the bitstream
+        let out = h.pull().unwrap();
+        assert!(out.meta::<gst_video::VideoCaptionMeta>().is_none());
+        assert_eq!(out.size() > AU.len(), cc_data.is_some());
+    }
+    h.push_event(gst::event::Eos::new());
+
+    let caps = caption
+        .sinkpad()
+        .unwrap()
+        .current_caps()
+        .expect("No caption caps");
+    assert_eq!(
+        caps,
+        gst::Caps::builder("closedcaption/x-cea-708")
+            .field("format", "cc_data")
+            .build()
+    );
+
+    let mut gap = None;
+    while let Some(event) = caption.try_pull_event() {
+        if let gst::EventView::Gap(e) = event.view() {
+            gap = Some(e.get());
+        }
+    }
+    assert_eq!(
+        gap,
+        Some((
+            ClockTime::from_mseconds(40),
+            Some(ClockTime::from_mseconds(40))
+        ))
+    );
+
+    let expected: [(u64, &[u8]); 2] = [
+        (80, &[0xfc, 0x94, 0x20, 0xfd, 0x80, 0x80]),
+        (120, &[0xfc, 0x80, 0x80]),
+    ];
+    for (pts, cc_data) in expected {
+        let buf = caption.pull().unwrap();
+        assert_eq!(buf.pts(), Some(ClockTime::from_mseconds(pts)));
+        assert_eq!(&*buf.map_readable().unwrap(), cc_data);
+    }
+}
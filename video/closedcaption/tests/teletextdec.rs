@@ -0,0 +1,185 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+
+use pretty_assertions::assert_eq;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsclosedcaption::plugin_register_static().unwrap();
+    });
+}
+
+const HAMMING_8_4: [u8; 16] = [
+    0x15, 0x02, 0x49, 0x5e, 0x64, 0x73, 0x38, 0x2f, 0xd0, 0xc7, 0x8c, 0x9b, 0xa1, 0xb6, 0xfd, 0xea,
+];
+
+fn odd_parity(c: u8) -> u8 {
+    if c.count_ones() % 2 == 0 {
+        c | 0x80
+    } else {
+        c
+    }
+}
+
+/// Creates an EBU Teletext data unit, with the bit order reversed as transmitted
+fn data_unit(magazine: u8, row: u8, data: [u8; 40]) -> Vec<u8> {
+    let address = (magazine & 0x07) | (row << 3);
+
+    let mut packet = vec![0x00, 0x27];
+    packet.push(HAMMING_8_4[(address & 0x0f) as usize]);
+    packet.push(HAMMING_8_4[(address >> 4) as usize]);
+    packet.extend_from_slice(&data);
+
+    let mut unit = vec![0x03, 44];
+    unit.extend(packet.iter().map(|b| b.reverse_bits()));
+    unit
+}
+
+fn header(magazine: u8, tens: u8, units: u8) -> Vec<u8> {
+    let mut data = [odd_parity(b' '); 40];
+    data[0] = HAMMING_8_4[units as usize];
+    data[1] = HAMMING_8_4[tens as usize];
+    data[2] = HAMMING_8_4[0];
+    // Erase page
+    data[3] = HAMMING_8_4[0x08];
+    data[4] = HAMMING_8_4[0];
+    // Subtitle
+    data[5] = HAMMING_8_4[0x08];
+    data[6] = HAMMING_8_4[0];
+    data[7] = HAMMING_8_4[0];
+
+    data_unit(magazine, 0, data)
+}
+
+fn row(magazine: u8, row: u8, text: &str) -> Vec<u8> {
+    let mut data = [odd_parity(b' '); 40];
+    // Start box, sent twice, then the text and end box
+    data[0] = odd_parity(0x0b);
+    data[1] = odd_parity(0x0b);
+    for (d, c) in data[2..].iter_mut().zip(text.bytes()) {
+        *d = odd_parity(c);
+    }
+    data[2 + text.len()] = odd_parity(0x0a);
+
+    data_unit(magazine, row, data)
+}
+
+fn pes(units: &[Vec<u8>], pts: gst::ClockTime) -> gst::Buffer {
+    let mut data = vec![0x10];
+    for unit in units {
+        data.extend_from_slice(unit);
+    }
+
+    let mut buffer = gst::Buffer::from_mut_slice(data);
+    buffer.get_mut().unwrap().set_pts(pts);
+    buffer
+}
+
+#[test]
+fn test_decode_subtitle_page() {
+    init();
+
+    let mut h = gst_check::Harness::new("teletextdec");
+    h.element().unwrap().set_property("page", 888u32);
+    h.set_src_caps_str("application/x-teletext");
+
+    // Page 888 is in magazine 8, which is transmitted as 0
+    h.push(pes(
+        &[header(0, 8, 8), row(0, 20, "Hello"), row(0, 22, "World")],
+        gst::ClockTime::from_seconds(1),
+    ))
+    .unwrap();
+    // Another page of the same magazine ends the page
+    h.push(pes(&[header(0, 0, 1)], gst::ClockTime::from_seconds(2)))
+        .unwrap();
+    assert_eq!(h.buffers_in_queue(), 0);
+
+    // Updated page ends the previous text
+    h.push(pes(
+        &[header(0, 8, 8), row(0, 22, "Bye")],
+        gst::ClockTime::from_seconds(3),
+    ))
+    .unwrap();
+    // Page of a different magazine does not end the page
+    h.push(pes(&[header(1, 0, 0)], gst::ClockTime::from_seconds(4)))
+        .unwrap();
+    h.push(pes(&[header(0, 0, 1)], gst::ClockTime::from_seconds(5)))
+        .unwrap();
+
+    let buffer = h.pull().unwrap();
+    assert_eq!(buffer.pts(), Some(gst::ClockTime::from_seconds(2)));
+    assert_eq!(buffer.duration(), Some(gst::ClockTime::from_seconds(3)));
+    assert_eq!(&*buffer.map_readable().unwrap(), b"Hello\nWorld");
+
+    // Empty page ends the previous text
+    h.push(pes(&[header(0, 8, 8)], gst::ClockTime::from_seconds(6)))
+        .unwrap();
+    h.push(pes(&[header(0, 0, 1)], gst::ClockTime::from_seconds(7)))
+        .unwrap();
+
+    let buffer = h.pull().unwrap();
+    assert_eq!(buffer.pts(), Some(gst::ClockTime::from_seconds(5)));
+    assert_eq!(buffer.duration(), Some(gst::ClockTime::from_seconds(2)));
+    assert_eq!(&*buffer.map_readable().unwrap(), b"Bye");
+
+    let caps = h
+        .sinkpad()
+        .expect("harness has no sinkpad")
+        .current_caps()
+        .expect("pad has no caps");
+    assert_eq!(
+        caps,
+        gst::Caps::builder("text/x-raw")
+            .field("format", "utf8")
+            .build()
+    );
+}
+
+#[test]
+fn test_other_page_ignored() {
+    init();
+
+    let mut h = gst_check::Harness::new("teletextdec");
+    h.element().unwrap().set_property("page", 801u32);
+    h.set_src_caps_str("application/x-teletext");
+
+    h.push(pes(
+        &[header(0, 8, 8), row(0, 20, "Hello"), header(0, 0, 2)],
+        gst::ClockTime::from_seconds(1),
+    ))
+    .unwrap();
+    h.push_event(gst::event::Eos::new());
+
+    assert_eq!(h.buffers_in_queue(), 0);
+}
+
+#[test]
+fn test_eos() {
+    init();
+
+    let mut h = gst_check::Harness::new("teletextdec");
+    h.set_src_caps_str("application/x-teletext");
+
+    h.push(pes(
+        &[header(0, 8, 8), row(0, 21, "Hello")],
+        gst::ClockTime::from_seconds(1),
+    ))
+    .unwrap();
+    h.push_event(gst::event::Eos::new());
+
+    let buffer = h.pull().unwrap();
+    assert_eq!(buffer.pts(), Some(gst::ClockTime::from_seconds(1)));
+    assert_eq!(buffer.duration(), None);
+    assert_eq!(&*buffer.map_readable().unwrap(), b"Hello");
+}
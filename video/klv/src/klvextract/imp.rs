@@ -0,0 +1,349 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+use once_cell::sync::Lazy;
+
+use std::sync::Mutex;
+
+use crate::KLV_META_NAME;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "klvextract",
+        gst::DebugColorFlags::empty(),
+        Some("KLV metadata extractor"),
+    )
+});
+
+const DEFAULT_REMOVE_META: bool = false;
+
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    remove_meta: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            remove_meta: DEFAULT_REMOVE_META,
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    combiner: gst_base::UniqueFlowCombiner,
+}
+
+pub struct KlvExtract {
+    srcpad: gst::Pad,
+    klvpad: gst::Pad,
+    sinkpad: gst::Pad,
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+}
+
+impl KlvExtract {
+    fn push_klv(
+        &self,
+        units: Option<gst::BufferList>,
+        pts: Option<gst::ClockTime>,
+        duration: Option<gst::ClockTime>,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let Some(units) = units.filter(|units| !units.is_empty()) else {
+            if let Some(pts) = pts {
+                self.klvpad
+                    .push_event(gst::event::Gap::builder(pts).duration(duration).build());
+            }
+            return Ok(gst::FlowSuccess::Ok);
+        };
+
+        gst::trace!(CAT, imp: self, "Pushing {} KLV units", units.len());
+
+        let res = self.klvpad.push_list(units);
+        self.state
+            .lock()
+            .unwrap()
+            .combiner
+            .update_pad_flow(&self.klvpad, res)
+    }
+
+    fn sink_chain(
+        &self,
+        pad: &gst::Pad,
+        mut buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::log!(CAT, obj: pad, "Handling buffer {:?}", buffer);
+
+        let remove_meta = self.settings.lock().unwrap().remove_meta;
+
+        let units = gst::meta::CustomMeta::from_buffer(&buffer, KLV_META_NAME)
+            .ok()
+            .and_then(|meta| meta.structure().get::<gst::BufferList>("units").ok());
+
+        if units.is_some() && remove_meta {
+            let buffer = buffer.make_mut();
+            if let Ok(meta) = gst::meta::CustomMeta::from_mut_buffer(buffer, KLV_META_NAME) {
+                meta.remove().unwrap();
+            }
+        }
+
+        self.push_klv(units, buffer.pts(), buffer.duration())?;
+
+        let res = self.srcpad.push(buffer);
+        self.state
+            .lock()
+            .unwrap()
+            .combiner
+            .update_pad_flow(&self.srcpad, res)
+    }
+
+    fn sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::log!(CAT, obj: pad, "Handling event {:?}", event);
+
+        match event.view() {
+            EventView::StreamStart(e) => {
+                let stream_start =
+                    gst::event::StreamStart::builder(&format!("{}/klv", e.stream_id()))
+                        .seqnum(e.seqnum())
+                        .flags(e.stream_flags() | gst::StreamFlags::SPARSE)
+                        .group_id(e.group_id().unwrap_or_else(gst::GroupId::next))
+                        .build();
+                self.klvpad.push_event(stream_start);
+
+                self.srcpad.push_event(event)
+            }
+            EventView::Caps(_) => {
+                if self.klvpad.current_caps().is_none() {
+                    self.klvpad
+                        .push_event(gst::event::Caps::new(&self.klvpad.pad_template_caps()));
+                }
+
+                self.srcpad.push_event(event)
+            }
+            EventView::FlushStop(_) => {
+                self.state.lock().unwrap().combiner.reset();
+
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            _ => gst::Pad::event_default(pad, Some(&*self.obj()), event),
+        }
+    }
+
+    fn sink_query(&self, pad: &gst::Pad, query: &mut gst::QueryRef) -> bool {
+        use gst::QueryViewMut;
+
+        gst::log!(CAT, obj: pad, "Handling query {:?}", query);
+
+        match query.view_mut() {
+            // Only the media stream is relevant for upstream
+            QueryViewMut::Caps(_) | QueryViewMut::AcceptCaps(_) | QueryViewMut::Allocation(_) => {
+                self.srcpad.peer_query(query)
+            }
+            _ => gst::Pad::query_default(pad, Some(&*self.obj()), query),
+        }
+    }
+
+    fn klv_query(&self, pad: &gst::Pad, query: &mut gst::QueryRef) -> bool {
+        use gst::QueryViewMut;
+
+        gst::log!(CAT, obj: pad, "Handling query {:?}", query);
+
+        match query.view_mut() {
+            QueryViewMut::Caps(q) => {
+                let mut caps = pad.pad_template_caps();
+                if let Some(filter) = q.filter() {
+                    caps = filter.intersect_with_mode(&caps, gst::CapsIntersectMode::First);
+                }
+                q.set_result(&caps);
+                true
+            }
+            QueryViewMut::AcceptCaps(q) => {
+                let accepted = q.caps().can_intersect(&pad.pad_template_caps());
+                q.set_result(accepted);
+                true
+            }
+            _ => gst::Pad::query_default(pad, Some(&*self.obj()), query),
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for KlvExtract {
+    const NAME: &'static str = "GstKlvExtract";
+    type Type = super::KlvExtract;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("sink").unwrap();
+        let sinkpad = gst::Pad::builder_from_template(&templ)
+            .chain_function(|pad, parent, buffer| {
+                KlvExtract::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |extract| extract.sink_chain(pad, buffer),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                KlvExtract::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |extract| extract.sink_event(pad, event),
+                )
+            })
+            .query_function(|pad, parent, query| {
+                KlvExtract::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |extract| extract.sink_query(pad, query),
+                )
+            })
+            .build();
+
+        let templ = klass.pad_template("src").unwrap();
+        let srcpad = gst::Pad::builder_from_template(&templ)
+            .flags(gst::PadFlags::FIXED_CAPS)
+            .build();
+
+        let templ = klass.pad_template("klv").unwrap();
+        let klvpad = gst::Pad::builder_from_template(&templ)
+            .query_function(|pad, parent, query| {
+                KlvExtract::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |extract| extract.klv_query(pad, query),
+                )
+            })
+            .flags(gst::PadFlags::FIXED_CAPS)
+            .build();
+
+        Self {
+            srcpad,
+            klvpad,
+            sinkpad,
+            settings: Mutex::default(),
+            state: Mutex::default(),
+        }
+    }
+}
+
+impl ObjectImpl for KlvExtract {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![glib::ParamSpecBoolean::builder("remove-meta")
+                .nick("Remove Meta")
+                .blurb("Remove the KLV meta from the media buffers")
+                .default_value(DEFAULT_REMOVE_META)
+                .mutable_playing()
+                .build()]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "remove-meta" => {
+                self.settings.lock().unwrap().remove_meta =
+                    value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "remove-meta" => self.settings.lock().unwrap().remove_meta.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.sinkpad).unwrap();
+        obj.add_pad(&self.srcpad).unwrap();
+        obj.add_pad(&self.klvpad).unwrap();
+    }
+}
+
+impl GstObjectImpl for KlvExtract {}
+
+impl ElementImpl for KlvExtract {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "KLV metadata extractor",
+                "Video/Metadata/Demuxer",
+                "Extracts KLV metadata units attached to media buffers into a separate stream",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst::Caps::new_any();
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let caps = gst::Caps::builder("meta/x-klv")
+                .field("parsed", true)
+                .build();
+            let klv_pad_template = gst::PadTemplate::new(
+                "klv",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![sink_pad_template, src_pad_template, klv_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        gst::trace!(CAT, imp: self, "Changing state {:?}", transition);
+
+        if let gst::StateChange::ReadyToPaused | gst::StateChange::PausedToReady = transition {
+            let mut state = self.state.lock().unwrap();
+            *state = State::default();
+            state.combiner.add_pad(&self.srcpad);
+            state.combiner.add_pad(&self.klvpad);
+        }
+
+        self.parent_change_state(transition)
+    }
+}
@@ -0,0 +1,53 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * element-klvextract:
+ * @see_also: klvinject
+ *
+ * Extracts the KLV metadata units attached to media buffers by `klvinject` into
+ * a separate `meta/x-klv` stream on the `klv` pad.
+ *
+ * The KLV units are output before the media buffer they were attached to. For
+ * media buffers without KLV units a gap event is sent on the `klv` pad so that
+ * downstream aggregators, e.g. a muxer, don't wait for metadata that never
+ * comes.
+ *
+ * The `klv` pad can be linked directly to `mpegtsmux`, which muxes the units as
+ * a KLV metadata stream next to the video as expected by MISB ST 1402 players.
+ *
+ * ## Example launch line
+ * ```
+ * gst-launch-1.0 filesrc location=input.ts ! tsdemux name=d \
+ *   d. ! queue ! h264parse ! i.media \
+ *   d. ! queue ! meta/x-klv ! i.klv \
+ *   klvinject name=i ! queue ! klvextract name=e remove-meta=true \
+ *   e.src ! queue ! h264parse ! m. \
+ *   e.klv ! queue ! m. \
+ *   mpegtsmux name=m ! filesink location=output.ts
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct KlvExtract(ObjectSubclass<imp::KlvExtract>) @extends gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "klvextract",
+        gst::Rank::NONE,
+        KlvExtract::static_type(),
+    )
+}
@@ -0,0 +1,468 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::prelude::*;
+use gst_base::subclass::prelude::*;
+use gst_base::AGGREGATOR_FLOW_NEED_DATA;
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
+
+use crate::KLV_META_NAME;
+
+/// Start of the SMPTE Universal Label keys of KLV units
+const UL_PREFIX: [u8; 4] = [0x06, 0x0e, 0x2b, 0x34];
+
+#[derive(Default)]
+struct State {
+    // KLV units consumed for the next media buffer
+    units: Vec<gst::Buffer>,
+}
+
+pub struct KlvInject {
+    // Input media stream, usually video
+    media_sink_pad: gst_base::AggregatorPad,
+    // Input KLV stream, each buffer containing complete KLV units
+    klv_sink_pad: gst_base::AggregatorPad,
+    state: Mutex<State>,
+}
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "klvinject",
+        gst::DebugColorFlags::empty(),
+        Some("KLV metadata injector"),
+    )
+});
+
+#[glib::object_subclass]
+impl ObjectSubclass for KlvInject {
+    const NAME: &'static str = "GstKlvInject";
+    type Type = super::KlvInject;
+    type ParentType = gst_base::Aggregator;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("media").unwrap();
+        let media_sink_pad =
+            gst::PadBuilder::<gst_base::AggregatorPad>::from_template(&templ).build();
+
+        let templ = klass.pad_template("klv").unwrap();
+        let klv_sink_pad =
+            gst::PadBuilder::<gst_base::AggregatorPad>::from_template(&templ).build();
+
+        Self {
+            media_sink_pad,
+            klv_sink_pad,
+            state: Mutex::default(),
+        }
+    }
+}
+
+impl ObjectImpl for KlvInject {
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.media_sink_pad).unwrap();
+        obj.add_pad(&self.klv_sink_pad).unwrap();
+    }
+}
+
+impl GstObjectImpl for KlvInject {}
+
+impl ElementImpl for KlvInject {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "KLV metadata injector",
+                "Video/Metadata/Combiner",
+                "Attaches KLV metadata units to the buffers of a media stream",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let media_caps = gst::Caps::new_any();
+            let media_sink_pad_template = gst::PadTemplate::with_gtype(
+                "media",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &media_caps,
+                gst_base::AggregatorPad::static_type(),
+            )
+            .unwrap();
+
+            let klv_caps = gst::Caps::builder("meta/x-klv")
+                .field("parsed", true)
+                .build();
+            let klv_sink_pad_template = gst::PadTemplate::with_gtype(
+                "klv",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &klv_caps,
+                gst_base::AggregatorPad::static_type(),
+            )
+            .unwrap();
+
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &media_caps,
+            )
+            .unwrap();
+
+            vec![
+                media_sink_pad_template,
+                klv_sink_pad_template,
+                src_pad_template,
+            ]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn request_new_pad(
+        &self,
+        _templ: &gst::PadTemplate,
+        _name: Option<&str>,
+        _caps: Option<&gst::Caps>,
+    ) -> Option<gst::Pad> {
+        gst::error!(CAT, imp: self, "klvinject doesn't expose request pads");
+
+        None
+    }
+
+    fn release_pad(&self, _pad: &gst::Pad) {
+        gst::error!(CAT, imp: self, "klvinject doesn't expose request pads");
+    }
+}
+
+impl KlvInject {
+    /// Consumes all KLV units before the running time `end`, or up to and
+    /// including it if `inclusive`. Returns `true` if the KLV stream is known
+    /// to have no further units for that range.
+    fn consume_klv(
+        &self,
+        state: &mut State,
+        media_segment: &gst::FormattedSegment<gst::ClockTime>,
+        end: gst::ClockTime,
+        inclusive: bool,
+    ) -> Result<bool, gst::FlowError> {
+        let before_end =
+            |running_time: gst::ClockTime| running_time < end || (inclusive && running_time == end);
+
+        let klv_segment = self.klv_sink_pad.segment();
+        let Some(klv_segment) = klv_segment.downcast_ref::<gst::ClockTime>() else {
+            gst::element_imp_error!(
+                self,
+                gst::StreamError::Format,
+                ["KLV stream must be in time format"]
+            );
+            return Err(gst::FlowError::Error);
+        };
+
+        while let Some(buffer) = self.klv_sink_pad.peek_buffer() {
+            let Some(running_time) = klv_segment.to_running_time(buffer.pts()) else {
+                gst::warning!(
+                    CAT,
+                    obj: self.klv_sink_pad,
+                    "Dropping KLV buffer outside the segment or without PTS"
+                );
+                self.klv_sink_pad.drop_buffer();
+                continue;
+            };
+
+            if !before_end(running_time) {
+                gst::trace!(
+                    CAT,
+                    imp: self,
+                    "Consumed all KLV units before the media end running time {}",
+                    end
+                );
+                return Ok(true);
+            }
+
+            // Gap events are queued as empty gap buffers
+            if buffer.flags().contains(gst::BufferFlags::GAP)
+                && buffer.flags().contains(gst::BufferFlags::DROPPABLE)
+                && buffer.size() == 0
+            {
+                self.klv_sink_pad.drop_buffer();
+
+                if buffer
+                    .duration()
+                    .map_or(false, |duration| !before_end(running_time + duration))
+                {
+                    gst::trace!(CAT, imp: self, "KLV gap until after {}", end);
+                    return Ok(true);
+                }
+                continue;
+            }
+
+            let mut buffer = self.klv_sink_pad.pop_buffer().unwrap();
+
+            if !buffer
+                .map_readable()
+                .map_or(false, |map| map.starts_with(&UL_PREFIX))
+            {
+                gst::warning!(
+                    CAT,
+                    obj: self.klv_sink_pad,
+                    "KLV buffer does not start with a Universal Label key"
+                );
+            }
+
+            gst::trace!(
+                CAT,
+                imp: self,
+                "Consuming KLV unit at running time {} before the media end running time {}",
+                running_time,
+                end
+            );
+
+            {
+                let buffer = buffer.make_mut();
+                buffer.set_pts(media_segment.position_from_running_time(running_time));
+                buffer.set_dts(gst::ClockTime::NONE);
+            }
+            state.units.push(buffer);
+        }
+
+        let is_eos = self.klv_sink_pad.is_eos();
+        if is_eos {
+            gst::debug!(CAT, imp: self, "KLV pad is EOS");
+        } else {
+            gst::trace!(CAT, imp: self, "Need more KLV until running time {}", end);
+        }
+
+        Ok(is_eos)
+    }
+}
+
+impl AggregatorImpl for KlvInject {
+    fn aggregate(&self, timeout: bool) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::trace!(CAT, imp: self, "aggregate, timeout: {}", timeout);
+
+        let Some(media_buffer) = self.media_sink_pad.peek_buffer() else {
+            if self.media_sink_pad.is_eos() {
+                gst::debug!(CAT, imp: self, "EOS");
+                return Err(gst::FlowError::Eos);
+            }
+
+            gst::trace!(CAT, imp: self, "Need more data");
+            return Err(AGGREGATOR_FLOW_NEED_DATA);
+        };
+
+        let media_segment = self.media_sink_pad.segment();
+        let Some(media_segment) = media_segment.downcast_ref::<gst::ClockTime>() else {
+            gst::element_imp_error!(
+                self,
+                gst::StreamError::Format,
+                ["Media stream must be in time format"]
+            );
+            return Err(gst::FlowError::Error);
+        };
+
+        let end = media_buffer
+            .pts()
+            .opt_add(media_buffer.duration())
+            .or(media_buffer.pts());
+        let end = media_segment.to_running_time(end);
+
+        let mut state = self.state.lock().unwrap();
+
+        if let Some(end) = end {
+            let inclusive = media_buffer.duration().is_none();
+            if self.consume_klv(&mut state, media_segment, end, inclusive)? {
+                gst::trace!(
+                    CAT,
+                    imp: self,
+                    "Consumed all KLV for media buffer ending at running time {}",
+                    end
+                );
+            } else if timeout {
+                gst::warning!(
+                    CAT,
+                    imp: self,
+                    "Timed out but did not receive all KLV for media buffer ending at running time {} yet",
+                    end
+                );
+            } else {
+                return Err(AGGREGATOR_FLOW_NEED_DATA);
+            }
+        } else {
+            gst::trace!(
+                CAT,
+                imp: self,
+                "Returning media buffer without running time"
+            );
+        }
+
+        let units = std::mem::take(&mut state.units);
+        drop(state);
+
+        let mut buffer = self.media_sink_pad.pop_buffer().unwrap();
+
+        if !units.is_empty() {
+            gst::log!(CAT, imp: self, "Attaching {} KLV units", units.len());
+
+            let mut buflist = gst::BufferList::new_sized(units.len());
+            {
+                let buflist_mut = buflist.get_mut().unwrap();
+                for unit in units {
+                    buflist_mut.add(unit);
+                }
+            }
+
+            let buf = buffer.make_mut();
+            let mut meta = gst::meta::CustomMeta::add(buf, KLV_META_NAME).unwrap();
+            meta.mut_structure().set("units", buflist);
+        }
+
+        let position = buffer
+            .pts()
+            .opt_add(buffer.duration().unwrap_or(gst::ClockTime::ZERO));
+
+        gst::log!(CAT, imp: self, "Updating position: {:?}", position);
+
+        self.obj().set_position(position);
+
+        self.finish_buffer(buffer)
+    }
+
+    fn src_query(&self, query: &mut gst::QueryRef) -> bool {
+        use gst::QueryViewMut;
+
+        match query.view_mut() {
+            QueryViewMut::Position(..)
+            | QueryViewMut::Duration(..)
+            | QueryViewMut::Uri(..)
+            | QueryViewMut::Caps(..)
+            | QueryViewMut::Allocation(..) => self.media_sink_pad.peer_query(query),
+            QueryViewMut::AcceptCaps(q) => {
+                let caps = q.caps_owned();
+                let aggregator = self.obj();
+                let class = aggregator.class();
+                let templ = class.pad_template("media").unwrap();
+                let templ_caps = templ.caps();
+
+                q.set_result(caps.is_subset(templ_caps));
+
+                true
+            }
+            _ => self.parent_src_query(query),
+        }
+    }
+
+    fn sink_event(&self, aggregator_pad: &gst_base::AggregatorPad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        match event.view() {
+            EventView::Caps(e) => {
+                if aggregator_pad == &self.media_sink_pad {
+                    gst::info!(CAT, imp: self, "Pushing caps {}", e.caps());
+                    self.obj().set_src_caps(&e.caps_owned());
+                }
+
+                true
+            }
+            EventView::Segment(e) => {
+                if aggregator_pad == &self.media_sink_pad {
+                    self.obj().update_segment(e.segment());
+                }
+                self.parent_sink_event(aggregator_pad, event)
+            }
+            _ => self.parent_sink_event(aggregator_pad, event),
+        }
+    }
+
+    fn sink_query(
+        &self,
+        aggregator_pad: &gst_base::AggregatorPad,
+        query: &mut gst::QueryRef,
+    ) -> bool {
+        use gst::QueryViewMut;
+
+        match query.view_mut() {
+            QueryViewMut::Position(..)
+            | QueryViewMut::Duration(..)
+            | QueryViewMut::Uri(..)
+            | QueryViewMut::Allocation(..) => {
+                if aggregator_pad == &self.media_sink_pad {
+                    self.obj().src_pad().peer_query(query)
+                } else {
+                    self.parent_sink_query(aggregator_pad, query)
+                }
+            }
+            QueryViewMut::Caps(q) => {
+                if aggregator_pad == &self.media_sink_pad {
+                    self.obj().src_pad().peer_query(query)
+                } else {
+                    let filter = q.filter_owned();
+                    let aggregator = self.obj();
+                    let class = aggregator.class();
+                    let templ = class.pad_template("klv").unwrap();
+                    let templ_caps = templ.caps();
+
+                    if let Some(filter) = filter {
+                        q.set_result(
+                            &filter.intersect_with_mode(templ_caps, gst::CapsIntersectMode::First),
+                        );
+                    } else {
+                        q.set_result(templ_caps);
+                    }
+
+                    true
+                }
+            }
+            QueryViewMut::AcceptCaps(q) => {
+                if aggregator_pad == &self.media_sink_pad {
+                    self.obj().src_pad().peer_query(query);
+                } else {
+                    let caps = q.caps_owned();
+                    let aggregator = self.obj();
+                    let class = aggregator.class();
+                    let templ = class.pad_template("klv").unwrap();
+                    let templ_caps = templ.caps();
+
+                    q.set_result(caps.is_subset(templ_caps));
+                }
+
+                true
+            }
+            _ => self.parent_sink_query(aggregator_pad, query),
+        }
+    }
+
+    fn flush(&self) -> Result<gst::FlowSuccess, gst::FlowError> {
+        self.state.lock().unwrap().units.clear();
+
+        self.parent_flush()
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.lock().unwrap() = State::default();
+
+        self.parent_stop()
+    }
+
+    fn next_time(&self) -> Option<gst::ClockTime> {
+        self.obj().simple_get_next_time()
+    }
+
+    fn negotiate(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,53 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * element-klvinject:
+ * @see_also: klvextract
+ *
+ * Attaches the KLV metadata packets of a `meta/x-klv` stream, e.g. MISB ST 0601
+ * UAS Datalink Local Sets, to the buffers of a media stream.
+ *
+ * Every KLV unit is attached to the first media buffer ending after its running
+ * time, via a `KLVMeta` custom meta whose `units` field holds a buffer list with
+ * the KLV units retimestamped into the segment of the media stream. Media
+ * buffers without duration only get the KLV units up to their own running time.
+ *
+ * Media buffers are held back until the KLV stream has advanced past their end,
+ * signalled by a KLV unit or gap event, or until the KLV stream is EOS. In live
+ * pipelines they are output once the configured latency expired.
+ *
+ * ## Example launch line
+ * ```
+ * gst-launch-1.0 filesrc location=input.ts ! tsdemux name=d \
+ *   d. ! queue ! h264parse ! i.media \
+ *   d. ! queue ! meta/x-klv ! i.klv \
+ *   klvinject name=i ! klvextract name=e \
+ *   e.src ! queue ! fakesink \
+ *   e.klv ! queue ! fakesink dump=true
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct KlvInject(ObjectSubclass<imp::KlvInject>) @extends gst_base::Aggregator, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "klvinject",
+        gst::Rank::NONE,
+        KlvInject::static_type(),
+    )
+}
@@ -0,0 +1,45 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+#![allow(clippy::non_send_fields_in_send_ty, unused_doc_comments)]
+
+/**
+ * plugin-rsklv:
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+
+mod klvextract;
+mod klvinject;
+
+/// Name of the custom meta carrying KLV units on media buffers.
+///
+/// The `units` field of its structure is a `GstBufferList` with one buffer per
+/// KLV metadata packet, timestamped in the segment of the media stream.
+pub const KLV_META_NAME: &str = "KLVMeta";
+
+fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    klvinject::register(plugin)?;
+    klvextract::register(plugin)?;
+
+    gst::meta::CustomMeta::register(KLV_META_NAME, &[]);
+
+    Ok(())
+}
+
+gst::plugin_define!(
+    rsklv,
+    env!("CARGO_PKG_DESCRIPTION"),
+    plugin_init,
+    concat!(env!("CARGO_PKG_VERSION"), "-", env!("COMMIT_ID")),
+    "MPL-2.0",
+    env!("CARGO_PKG_NAME"),
+    env!("CARGO_PKG_NAME"),
+    env!("CARGO_PKG_REPOSITORY"),
+    env!("BUILD_REL_DATE")
+);
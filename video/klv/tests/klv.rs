@@ -0,0 +1,213 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+use gst::ClockTime;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsklv::plugin_register_static().unwrap();
+    });
+}
+
+/// Minimal KLV unit with a 16 byte Universal Label key and a 1 byte value
+fn klv_unit(value: u8) -> [u8; 18] {
+    let mut unit = [
+        0x06, 0x0e, 0x2b, 0x34, 0x02, 0x0b, 0x01, 0x01, 0x0e, 0x01, 0x03, 0x01, 0x01, 0x00, 0x00,
+        0x00, 0x01, 0x00,
+    ];
+    unit[17] = value;
+    unit
+}
+
+fn klv_buffer(pts: ClockTime, value: u8) -> gst::Buffer {
+    let mut buf = gst::Buffer::from_slice(klv_unit(value));
+    buf.get_mut().unwrap().set_pts(pts);
+    buf
+}
+
+fn media_buffer(pts: ClockTime, duration: ClockTime) -> gst::Buffer {
+    let mut buf = gst::Buffer::with_size(4).unwrap();
+    {
+        let buf = buf.get_mut().unwrap();
+        buf.set_pts(pts);
+        buf.set_duration(duration);
+    }
+    buf
+}
+
+/// Returns the PTS and values of the KLV units attached to the buffer
+fn attached_units(buffer: &gst::BufferRef) -> Vec<(ClockTime, u8)> {
+    let Ok(meta) = gst::meta::CustomMeta::from_buffer(buffer, gstrsklv::KLV_META_NAME) else {
+        return vec![];
+    };
+
+    let units = meta.structure().get::<gst::BufferList>("units").unwrap();
+    units
+        .iter()
+        .map(|unit| {
+            let map = unit.map_readable().unwrap();
+            (unit.pts().unwrap(), map[17])
+        })
+        .collect()
+}
+
+#[test]
+fn test_inject() {
+    init();
+
+    let mut h = gst_check::Harness::with_padnames("klvinject", Some("media"), Some("src"));
+    let mut klv = gst_check::Harness::with_element(&h.element().unwrap(), Some("klv"), None);
+    h.set_src_caps_str("video/x-raw");
+    klv.set_src_caps_str("meta/x-klv, parsed=true");
+
+    for (pts, value) in [(0, 1), (30, 2), (50, 3)] {
+        klv.push(klv_buffer(ClockTime::from_mseconds(pts), value))
+            .unwrap();
+    }
+
+    h.push(media_buffer(ClockTime::ZERO, ClockTime::from_mseconds(40)))
+        .unwrap();
+    let out = h.pull().unwrap();
+    assert_eq!(out.pts(), Some(ClockTime::ZERO));
+    assert_eq!(
+        attached_units(&out),
+        [(ClockTime::ZERO, 1), (ClockTime::from_mseconds(30), 2)]
+    );
+
+    // Held back until the KLV stream has advanced past the end of the buffer
+    h.push(media_buffer(
+        ClockTime::from_mseconds(40),
+        ClockTime::from_mseconds(40),
+    ))
+    .unwrap();
+    assert!(h.try_pull().is_none());
+    klv.push_event(
+        gst::event::Gap::builder(ClockTime::from_mseconds(60))
+            .duration(ClockTime::from_mseconds(40))
+            .build(),
+    );
+    let out = h.pull().unwrap();
+    assert_eq!(out.pts(), Some(ClockTime::from_mseconds(40)));
+    assert_eq!(attached_units(&out), [(ClockTime::from_mseconds(50), 3)]);
+
+    klv.push_event(gst::event::Eos::new());
+    h.push(media_buffer(
+        ClockTime::from_mseconds(80),
+        ClockTime::from_mseconds(40),
+    ))
+    .unwrap();
+    let out = h.pull().unwrap();
+    assert_eq!(out.pts(), Some(ClockTime::from_mseconds(80)));
+    assert!(attached_units(&out).is_empty());
+}
+
+#[test]
+fn test_inject_segment() {
+    init();
+
+    let mut h = gst_check::Harness::with_padnames("klvinject", Some("media"), Some("src"));
+    let mut klv = gst_check::Harness::with_element(&h.element().unwrap(), Some("klv"), None);
+    h.set_src_caps_str("video/x-raw");
+    klv.set_src_caps_str("meta/x-klv, parsed=true");
+
+    // The KLV stream starts at 10s, the media stream at 0s
+    let mut segment = gst::FormattedSegment::<ClockTime>::new();
+    segment.set_start(ClockTime::from_seconds(10));
+    segment.set_time(ClockTime::from_seconds(10));
+    klv.push_event(gst::event::Segment::new(&segment));
+
+    klv.push(klv_buffer(ClockTime::from_mseconds(10_020), 1))
+        .unwrap();
+    klv.push_event(gst::event::Eos::new());
+
+    h.push(media_buffer(ClockTime::ZERO, ClockTime::from_mseconds(40)))
+        .unwrap();
+    let out = h.pull().unwrap();
+    assert_eq!(attached_units(&out), [(ClockTime::from_mseconds(20), 1)]);
+}
+
+fn media_buffer_with_units(pts: ClockTime, units: &[(ClockTime, u8)]) -> gst::Buffer {
+    let mut buffer = media_buffer(pts, ClockTime::from_mseconds(40));
+    {
+        let buffer = buffer.get_mut().unwrap();
+        let mut list = gst::BufferList::new();
+        for (pts, value) in units {
+            list.get_mut().unwrap().add(klv_buffer(*pts, *value));
+        }
+
+        let mut meta = gst::meta::CustomMeta::add(buffer, gstrsklv::KLV_META_NAME).unwrap();
+        meta.mut_structure().set("units", list);
+    }
+    buffer
+}
+
+#[test]
+fn test_extract() {
+    init();
+
+    let mut h = gst_check::Harness::new("klvextract");
+    let mut klv = gst_check::Harness::with_element(&h.element().unwrap(), None, Some("klv"));
+    h.set_src_caps_str("video/x-raw");
+    klv.play();
+
+    let units = [(ClockTime::ZERO, 1), (ClockTime::from_mseconds(30), 2)];
+    h.push(media_buffer_with_units(ClockTime::ZERO, &units))
+        .unwrap();
+    let out = h.pull().unwrap();
+    assert_eq!(attached_units(&out), units);
+
+    h.element().unwrap().set_property("remove-meta", true);
+    let units = [(ClockTime::from_mseconds(50), 3)];
+    h.push(media_buffer_with_units(
+        ClockTime::from_mseconds(40),
+        &units,
+    ))
+    .unwrap();
+    let out = h.pull().unwrap();
+    assert!(attached_units(&out).is_empty());
+
+    h.push(media_buffer(
+        ClockTime::from_mseconds(80),
+        ClockTime::from_mseconds(40),
+    ))
+    .unwrap();
+    h.pull().unwrap();
+
+    let caps = klv.sinkpad().unwrap().current_caps().expect("No KLV caps");
+    assert_eq!(
+        caps,
+        gst::Caps::builder("meta/x-klv")
+            .field("parsed", true)
+            .build()
+    );
+
+    for (pts, value) in [(0, 1), (30, 2), (50, 3)] {
+        let buf = klv.pull().unwrap();
+        assert_eq!(buf.pts(), Some(ClockTime::from_mseconds(pts)));
+        assert_eq!(&*buf.map_readable().unwrap(), &klv_unit(value));
+    }
+
+    let mut gap = None;
+    while let Some(event) = klv.try_pull_event() {
+        if let gst::EventView::Gap(e) = event.view() {
+            gap = Some(e.get());
+        }
+    }
+    assert_eq!(
+        gap,
+        Some((
+            ClockTime::from_mseconds(80),
+            Some(ClockTime::from_mseconds(40))
+        ))
+    );
+}
@@ -19,16 +19,25 @@ use gst::prelude::*;
 mod border;
 mod colordetect;
 mod videocompare;
+mod videoquality;
 
 pub use videocompare::{HashAlgorithm, PadDistance, VideoCompareMessage};
 
+/// Name of the custom meta carrying the scores of a frame measured by `videoquality`.
+pub const VIDEO_QUALITY_META_NAME: &str = "VideoQualityMeta";
+
 fn plugin_init(plugin: &gst::Plugin) -> Result<(), gst::glib::BoolError> {
     #[cfg(feature = "doc")]
     HashAlgorithm::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
 
     border::register(plugin)?;
     colordetect::register(plugin)?;
-    videocompare::register(plugin)
+    videocompare::register(plugin)?;
+    videoquality::register(plugin)?;
+
+    gst::meta::CustomMeta::register(VIDEO_QUALITY_META_NAME, &[]);
+
+    Ok(())
 }
 
 gst::plugin_define!(
@@ -41,6 +41,9 @@ use std::fmt::Debug;
 mod hashed_image;
 mod imp;
 
+#[cfg(feature = "dssim")]
+pub(crate) use hashed_image::HasherEngine;
+
 glib::wrapper! {
     pub struct VideoCompare(ObjectSubclass<imp::VideoCompare>) @extends gst_video::VideoAggregator, gst_base::Aggregator, gst::Element, gst::Object;
 }
@@ -0,0 +1,649 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::subclass::prelude::*;
+use gst::{glib, prelude::*};
+use gst_base::prelude::*;
+use gst_video::prelude::*;
+use gst_video::subclass::prelude::*;
+use gst_video::subclass::AggregateFramesToken;
+use gst_video::VideoFormat;
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
+
+use crate::VIDEO_QUALITY_META_NAME;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "videoquality",
+        gst::DebugColorFlags::empty(),
+        Some("Video quality measurement"),
+    )
+});
+
+/// PSNR reported for identical frames
+const MAX_PSNR: f64 = 100.0;
+const SSIM_BLOCK_SIZE: usize = 8;
+const SSIM_C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+const SSIM_C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+const DEFAULT_PSNR: bool = true;
+const DEFAULT_SSIM: bool = true;
+#[cfg(feature = "dssim")]
+const DEFAULT_DSSIM: bool = false;
+const DEFAULT_INTERVAL: gst::ClockTime = gst::ClockTime::ZERO;
+
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    psnr: bool,
+    ssim: bool,
+    #[cfg(feature = "dssim")]
+    dssim: bool,
+    interval: gst::ClockTime,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            psnr: DEFAULT_PSNR,
+            ssim: DEFAULT_SSIM,
+            #[cfg(feature = "dssim")]
+            dssim: DEFAULT_DSSIM,
+            interval: DEFAULT_INTERVAL,
+        }
+    }
+}
+
+/// Average and worst value of a metric
+#[derive(Debug, Clone, Copy)]
+struct Statistic {
+    sum: f64,
+    worst: f64,
+}
+
+impl Statistic {
+    fn add(stat: &mut Option<Statistic>, value: f64, is_worse: fn(f64, f64) -> bool) {
+        match stat {
+            Some(stat) => {
+                stat.sum += value;
+                if is_worse(value, stat.worst) {
+                    stat.worst = value;
+                }
+            }
+            None => {
+                *stat = Some(Statistic {
+                    sum: value,
+                    worst: value,
+                });
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    frames: u64,
+    psnr: Option<Statistic>,
+    ssim: Option<Statistic>,
+    dssim: Option<Statistic>,
+    last_running_time: Option<gst::ClockTime>,
+    last_report: Option<gst::ClockTime>,
+}
+
+/// Scores of a single frame
+#[derive(Debug, Default)]
+struct Scores {
+    psnr: Option<(f64, Vec<f64>)>,
+    ssim: Option<(f64, Vec<f64>)>,
+    dssim: Option<f64>,
+}
+
+pub struct VideoQuality {
+    reference_pad: gst_video::VideoAggregatorPad,
+    distorted_pad: gst_video::VideoAggregatorPad,
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for VideoQuality {
+    const NAME: &'static str = "GstVideoQuality";
+    type Type = super::VideoQuality;
+    type ParentType = gst_video::VideoAggregator;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("reference").unwrap();
+        let reference_pad =
+            gst::PadBuilder::<gst_video::VideoAggregatorPad>::from_template(&templ).build();
+
+        let templ = klass.pad_template("distorted").unwrap();
+        let distorted_pad =
+            gst::PadBuilder::<gst_video::VideoAggregatorPad>::from_template(&templ).build();
+
+        Self {
+            reference_pad,
+            distorted_pad,
+            settings: Mutex::default(),
+            state: Mutex::default(),
+        }
+    }
+}
+
+impl ObjectImpl for VideoQuality {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            #[allow(unused_mut)]
+            let mut properties = vec![
+                glib::ParamSpecBoolean::builder("psnr")
+                    .nick("PSNR")
+                    .blurb("Calculate the peak signal-to-noise ratio")
+                    .default_value(DEFAULT_PSNR)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecBoolean::builder("ssim")
+                    .nick("SSIM")
+                    .blurb("Calculate the structural similarity index")
+                    .default_value(DEFAULT_SSIM)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt64::builder("interval")
+                    .nick("Interval")
+                    .blurb("Interval in nanoseconds for posting statistics messages (0 = only at the end)")
+                    .maximum(u64::MAX - 1)
+                    .default_value(DEFAULT_INTERVAL.nseconds())
+                    .mutable_playing()
+                    .build(),
+            ];
+
+            #[cfg(feature = "dssim")]
+            properties.push(
+                glib::ParamSpecBoolean::builder("dssim")
+                    .nick("DSSIM")
+                    .blurb("Calculate the perceptual DSSIM score of RGB frames")
+                    .default_value(DEFAULT_DSSIM)
+                    .mutable_playing()
+                    .build(),
+            );
+
+            properties
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.reference_pad).unwrap();
+        obj.add_pad(&self.distorted_pad).unwrap();
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+        match pspec.name() {
+            "psnr" => {
+                settings.psnr = value.get().expect("type checked upstream");
+            }
+            "ssim" => {
+                settings.ssim = value.get().expect("type checked upstream");
+            }
+            #[cfg(feature = "dssim")]
+            "dssim" => {
+                settings.dssim = value.get().expect("type checked upstream");
+            }
+            "interval" => {
+                settings.interval =
+                    gst::ClockTime::from_nseconds(value.get().expect("type checked upstream"));
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+        match pspec.name() {
+            "psnr" => settings.psnr.to_value(),
+            "ssim" => settings.ssim.to_value(),
+            #[cfg(feature = "dssim")]
+            "dssim" => settings.dssim.to_value(),
+            "interval" => settings.interval.nseconds().to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for VideoQuality {}
+
+impl ElementImpl for VideoQuality {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Video quality measurement",
+                "Filter/Analyzer/Video",
+                "Measures the quality of a video stream compared to a reference stream",
+                "niroosh1997 <niroosh1997@users.noreply.github.com>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst_video::VideoCapsBuilder::new()
+                .format_list([
+                    VideoFormat::I420,
+                    VideoFormat::Yv12,
+                    VideoFormat::Nv12,
+                    VideoFormat::Nv21,
+                    VideoFormat::Y42b,
+                    VideoFormat::Y444,
+                    VideoFormat::Gray8,
+                    VideoFormat::Rgb,
+                    VideoFormat::Bgr,
+                    VideoFormat::Rgba,
+                    VideoFormat::Bgra,
+                    VideoFormat::Rgbx,
+                    VideoFormat::Bgrx,
+                ])
+                .build();
+
+            let reference_pad_template = gst::PadTemplate::with_gtype(
+                "reference",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+                gst_video::VideoAggregatorPad::static_type(),
+            )
+            .unwrap();
+
+            let distorted_pad_template = gst::PadTemplate::with_gtype(
+                "distorted",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+                gst_video::VideoAggregatorPad::static_type(),
+            )
+            .unwrap();
+
+            let src_pad_template = gst::PadTemplate::with_gtype(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+                gst_video::VideoAggregatorPad::static_type(),
+            )
+            .unwrap();
+
+            vec![
+                reference_pad_template,
+                distorted_pad_template,
+                src_pad_template,
+            ]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl AggregatorImpl for VideoQuality {
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.lock().unwrap() = State::default();
+
+        self.parent_start()
+    }
+
+    fn sink_event(&self, pad: &gst_base::AggregatorPad, event: gst::Event) -> bool {
+        if let gst::EventView::Eos(_) = event.view() {
+            if pad == self.distorted_pad.upcast_ref::<gst_base::AggregatorPad>() {
+                gst::debug!(CAT, imp: self, "Distorted stream finished");
+                self.post_report();
+            }
+        }
+
+        self.parent_sink_event(pad, event)
+    }
+
+    fn update_src_caps(&self, caps: &gst::Caps) -> Result<gst::Caps, gst::FlowError> {
+        // Output the caps of the distorted stream
+        let sink_caps = self
+            .distorted_pad
+            .current_caps()
+            .unwrap_or_else(|| caps.to_owned());
+
+        if !sink_caps.can_intersect(caps) {
+            gst::error!(
+                CAT,
+                imp: self,
+                "Proposed src caps ({:?}) not supported, needs to intersect with the distorted sink caps ({:?})",
+                caps,
+                sink_caps
+            );
+            return Err(gst::FlowError::NotNegotiated);
+        }
+
+        Ok(sink_caps)
+    }
+}
+
+impl VideoAggregatorImpl for VideoQuality {
+    fn aggregate_frames(
+        &self,
+        token: &AggregateFramesToken,
+        outbuf: &mut gst::BufferRef,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let Some(distorted_frame) = self.distorted_pad.prepared_frame(token) else {
+            if self.distorted_pad.is_eos() {
+                return Err(gst::FlowError::Eos);
+            }
+
+            gst::debug!(CAT, imp: self, "No distorted frame, outputting gap");
+            outbuf.set_flags(gst::BufferFlags::GAP);
+            return Ok(gst::FlowSuccess::Ok);
+        };
+
+        let running_time = self
+            .distorted_pad
+            .segment()
+            .downcast::<gst::ClockTime>()
+            .ok()
+            .and_then(|segment| segment.to_running_time(distorted_frame.buffer().pts()));
+
+        // Output the distorted frame, keeping the timestamps selected by the aggregator
+        outbuf.remove_all_memory();
+        distorted_frame
+            .buffer()
+            .copy_into(
+                outbuf,
+                gst::BufferCopyFlags::MEMORY | gst::BufferCopyFlags::META,
+                ..,
+            )
+            .map_err(|_| gst::FlowError::Error)?;
+
+        let Some(reference_frame) = self.reference_pad.prepared_frame(token) else {
+            gst::debug!(CAT, imp: self, "No reference frame, not measuring quality");
+            return Ok(gst::FlowSuccess::Ok);
+        };
+
+        if reference_frame.format() != distorted_frame.format()
+            || reference_frame.width() != distorted_frame.width()
+            || reference_frame.height() != distorted_frame.height()
+        {
+            gst::element_imp_error!(
+                self,
+                gst::StreamError::Format,
+                ["Reference and distorted streams must have the same format and size"]
+            );
+            return Err(gst::FlowError::NotNegotiated);
+        }
+
+        let settings = *self.settings.lock().unwrap();
+        let scores = self.measure(&settings, &reference_frame, &distorted_frame)?;
+
+        gst::trace!(CAT, imp: self, "Frame at {}: {:?}", running_time.display(), scores);
+
+        {
+            let mut meta = gst::meta::CustomMeta::add(outbuf, VIDEO_QUALITY_META_NAME).unwrap();
+            let s = meta.mut_structure();
+            if let Some((psnr, ref components)) = scores.psnr {
+                s.set("psnr", psnr);
+                s.set(
+                    "psnr-components",
+                    gst::Array::new(components.iter().copied()),
+                );
+            }
+            if let Some((ssim, ref components)) = scores.ssim {
+                s.set("ssim", ssim);
+                s.set(
+                    "ssim-components",
+                    gst::Array::new(components.iter().copied()),
+                );
+            }
+            if let Some(dssim) = scores.dssim {
+                s.set("dssim", dssim);
+            }
+        }
+
+        let mut state = self.state.lock().unwrap();
+        state.frames += 1;
+        state.last_running_time = running_time;
+        if let Some((psnr, _)) = scores.psnr {
+            Statistic::add(&mut state.psnr, psnr, |a, b| a < b);
+        }
+        if let Some((ssim, _)) = scores.ssim {
+            Statistic::add(&mut state.ssim, ssim, |a, b| a < b);
+        }
+        if let Some(dssim) = scores.dssim {
+            Statistic::add(&mut state.dssim, dssim, |a, b| a > b);
+        }
+
+        let report_due = !settings.interval.is_zero()
+            && match (running_time, state.last_report) {
+                (Some(running_time), Some(last_report)) => {
+                    running_time >= last_report + settings.interval
+                }
+                (Some(running_time), None) => {
+                    state.last_report = Some(running_time);
+                    false
+                }
+                _ => false,
+            };
+        if report_due {
+            state.last_report = running_time;
+        }
+        drop(state);
+
+        if report_due {
+            self.post_report();
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+}
+
+impl VideoQuality {
+    fn measure(
+        &self,
+        settings: &Settings,
+        reference: &gst_video::VideoFrameRef<&gst::BufferRef>,
+        distorted: &gst_video::VideoFrameRef<&gst::BufferRef>,
+    ) -> Result<Scores, gst::FlowError> {
+        let mut scores = Scores::default();
+
+        if settings.psnr || settings.ssim {
+            // Alpha is always the last component
+            let n_components = if reference.format_info().has_alpha() {
+                reference.n_components() - 1
+            } else {
+                reference.n_components()
+            };
+
+            let mut total_samples = 0;
+            let mut total_sse = 0.0;
+            let mut total_ssim = 0.0;
+            let mut psnr_components = Vec::with_capacity(n_components as usize);
+            let mut ssim_components = Vec::with_capacity(n_components as usize);
+
+            for component in 0..n_components {
+                let reference = Plane::new(reference, component)?;
+                let distorted = Plane::new(distorted, component)?;
+                let samples = reference.width * reference.height;
+                total_samples += samples;
+
+                if settings.psnr {
+                    let sse = reference.sse(&distorted);
+                    total_sse += sse;
+                    psnr_components.push(psnr(sse, samples));
+                }
+
+                if settings.ssim {
+                    let ssim = reference.ssim(&distorted);
+                    total_ssim += ssim * samples as f64;
+                    ssim_components.push(ssim);
+                }
+            }
+
+            if settings.psnr {
+                scores.psnr = Some((psnr(total_sse, total_samples), psnr_components));
+            }
+            if settings.ssim {
+                scores.ssim = Some((total_ssim / total_samples as f64, ssim_components));
+            }
+        }
+
+        #[cfg(feature = "dssim")]
+        if settings.dssim {
+            scores.dssim = dssim(reference, distorted);
+        }
+
+        Ok(scores)
+    }
+
+    /// Posts the statistics over all frames so far
+    fn post_report(&self) {
+        let state = self.state.lock().unwrap();
+        if state.frames == 0 {
+            return;
+        }
+
+        let frames = state.frames as f64;
+        let mut s = gst::Structure::builder("videoquality")
+            .field("frames", state.frames)
+            .field("running-time", state.last_running_time)
+            .build();
+        if let Some(psnr) = state.psnr {
+            s.set("psnr-average", psnr.sum / frames);
+            s.set("psnr-min", psnr.worst);
+        }
+        if let Some(ssim) = state.ssim {
+            s.set("ssim-average", ssim.sum / frames);
+            s.set("ssim-min", ssim.worst);
+        }
+        if let Some(dssim) = state.dssim {
+            s.set("dssim-average", dssim.sum / frames);
+            s.set("dssim-max", dssim.worst);
+        }
+        drop(state);
+
+        gst::debug!(CAT, imp: self, "Posting report {}", s);
+
+        let element = self.obj();
+        let _ = element.post_message(gst::message::Element::builder(s).src(&*element).build());
+    }
+}
+
+/// One 8 bit color component of a frame
+struct Plane<'a> {
+    data: &'a [u8],
+    width: usize,
+    height: usize,
+    stride: usize,
+    pixel_stride: usize,
+}
+
+impl<'a> Plane<'a> {
+    fn new(
+        frame: &'a gst_video::VideoFrameRef<&gst::BufferRef>,
+        component: u32,
+    ) -> Result<Self, gst::FlowError> {
+        let data = frame
+            .comp_data(component)
+            .map_err(|_| gst::FlowError::Error)?;
+
+        Ok(Plane {
+            data,
+            width: frame.comp_width(component) as usize,
+            height: frame.comp_height(component) as usize,
+            stride: frame.comp_stride(component) as usize,
+            pixel_stride: frame.format_info().pixel_stride()[component as usize] as usize,
+        })
+    }
+
+    fn sample(&self, x: usize, y: usize) -> f64 {
+        self.data[y * self.stride + x * self.pixel_stride] as f64
+    }
+
+    /// Sum of squared errors
+    fn sse(&self, other: &Plane) -> f64 {
+        let mut sse = 0.0;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let diff = self.sample(x, y) - other.sample(x, y);
+                sse += diff * diff;
+            }
+        }
+
+        sse
+    }
+
+    /// Mean SSIM over blocks of `SSIM_BLOCK_SIZE`, or the whole plane if it is smaller
+    fn ssim(&self, other: &Plane) -> f64 {
+        let block_width = SSIM_BLOCK_SIZE.min(self.width);
+        let block_height = SSIM_BLOCK_SIZE.min(self.height);
+
+        let mut sum = 0.0;
+        let mut blocks = 0;
+        for by in (0..=self.height - block_height).step_by(block_height) {
+            for bx in (0..=self.width - block_width).step_by(block_width) {
+                let (mut sum_a, mut sum_b) = (0.0, 0.0);
+                let (mut sum_aa, mut sum_bb, mut sum_ab) = (0.0, 0.0, 0.0);
+                for y in by..by + block_height {
+                    for x in bx..bx + block_width {
+                        let a = self.sample(x, y);
+                        let b = other.sample(x, y);
+                        sum_a += a;
+                        sum_b += b;
+                        sum_aa += a * a;
+                        sum_bb += b * b;
+                        sum_ab += a * b;
+                    }
+                }
+
+                let n = (block_width * block_height) as f64;
+                let (mean_a, mean_b) = (sum_a / n, sum_b / n);
+                let var_a = sum_aa / n - mean_a * mean_a;
+                let var_b = sum_bb / n - mean_b * mean_b;
+                let covar = sum_ab / n - mean_a * mean_b;
+
+                sum += ((2.0 * mean_a * mean_b + SSIM_C1) * (2.0 * covar + SSIM_C2))
+                    / ((mean_a * mean_a + mean_b * mean_b + SSIM_C1) * (var_a + var_b + SSIM_C2));
+                blocks += 1;
+            }
+        }
+
+        sum / blocks as f64
+    }
+}
+
+fn psnr(sse: f64, samples: usize) -> f64 {
+    if sse == 0.0 {
+        return MAX_PSNR;
+    }
+
+    let mse = sse / samples as f64;
+    (10.0 * (255.0 * 255.0 / mse).log10()).min(MAX_PSNR)
+}
+
+#[cfg(feature = "dssim")]
+fn dssim(
+    reference: &gst_video::VideoFrameRef<&gst::BufferRef>,
+    distorted: &gst_video::VideoFrameRef<&gst::BufferRef>,
+) -> Option<f64> {
+    use crate::videocompare::{HashAlgorithm, HasherEngine};
+
+    if !matches!(reference.format(), VideoFormat::Rgb | VideoFormat::Rgba) {
+        return None;
+    }
+
+    let hasher = HasherEngine::from(HashAlgorithm::Dssim);
+    let reference = hasher.hash_image(reference).ok()?;
+    let distorted = hasher.hash_image(distorted).ok()?;
+
+    Some(hasher.compare(&reference, &distorted))
+}
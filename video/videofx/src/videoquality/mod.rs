@@ -0,0 +1,64 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+/**
+ * element-videoquality:
+ * @short_description: Measures the quality of a video stream compared to a reference stream.
+ *
+ * Compares the frames of the `distorted` sink pad, e.g. the output of an encoder and decoder,
+ * with the frames of the `reference` sink pad that are at the same running time. Both streams
+ * must have the same format and size.
+ *
+ * The distorted frames are output on the src pad, each with a custom meta called
+ * `VideoQualityMeta` carrying the scores of the frame in its structure:
+ *
+ *  - `psnr` (double): PSNR in dB over all color components, weighted by their number of samples.
+ *    Identical frames have a PSNR of 100dB.
+ *  - `psnr-components` (array of double): PSNR in dB of each color component.
+ *  - `ssim` (double): SSIM over all color components, weighted by their number of samples.
+ *  - `ssim-components` (array of double): SSIM of each color component.
+ *  - `dssim` (double): [DSSIM] score, 0 for identical frames, if enabled and the frames are RGB
+ *    or RGBA.
+ *
+ * PSNR and SSIM are only included if enabled with the corresponding properties. SSIM is
+ * calculated on non-overlapping blocks of 8x8 samples. The DSSIM metric is only available if the
+ * plugin was built with the `dssim` feature. Alpha components are ignored.
+ *
+ * A `videoquality` element message with the statistics over all frames since starting is posted
+ * every `interval` and when the distorted stream ends. It contains the number of `frames` and
+ * the `running-time` of the last frame, and for each enabled metric the average and worst score,
+ * e.g. `psnr-average` and `psnr-min`, `ssim-average` and `ssim-min`, or `dssim-average` and
+ * `dssim-max`.
+ *
+ * [DSSIM]: https://github.com/kornelski/dssim
+ *
+ * ## Example pipeline
+ * ```bash
+ * gst-launch-1.0 -m videotestsrc num-buffers=100 ! video/x-raw,format=I420 ! tee name=t \
+ *   t. ! queue ! videoquality name=q ! fakesink \
+ *   t. ! queue ! x264enc ! avdec_h264 ! q.distorted
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct VideoQuality(ObjectSubclass<imp::VideoQuality>) @extends gst_video::VideoAggregator, gst_base::Aggregator, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "videoquality",
+        gst::Rank::NONE,
+        VideoQuality::static_type(),
+    )
+}
@@ -0,0 +1,136 @@
+// Copyright (C) 2024 niroosh1997 <niroosh1997@users.noreply.github.com>
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+use std::sync::{Arc, Mutex};
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsvideofx::plugin_register_static().expect("Failed to register videofx plugin");
+    });
+}
+
+/// Runs the pipeline and returns the meta structures of all output frames and the final report
+fn run(reference: &str, distorted: &str, caps: &str) -> (Vec<gst::Structure>, gst::Structure) {
+    init();
+
+    let pipeline = gst::parse::launch(&format!(
+        "videotestsrc num-buffers=10 pattern={reference} ! {caps} ! videoquality name=q ! fakesink name=sink \
+         videotestsrc num-buffers=10 pattern={distorted} ! {caps} ! q.distorted"
+    ))
+    .unwrap()
+    .downcast::<gst::Pipeline>()
+    .unwrap();
+
+    let metas = Arc::new(Mutex::new(vec![]));
+    let sinkpad = pipeline
+        .by_name("sink")
+        .unwrap()
+        .static_pad("sink")
+        .unwrap();
+    sinkpad.add_probe(gst::PadProbeType::BUFFER, {
+        let metas = metas.clone();
+        move |_pad, info| {
+            let buffer = info.buffer().unwrap();
+            let meta =
+                gst::meta::CustomMeta::from_buffer(buffer, gstrsvideofx::VIDEO_QUALITY_META_NAME)
+                    .expect("no quality meta");
+            metas.lock().unwrap().push(meta.structure().to_owned());
+            gst::PadProbeReturn::Ok
+        }
+    });
+
+    pipeline.set_state(gst::State::Playing).unwrap();
+
+    let mut report = None;
+    let bus = pipeline.bus().unwrap();
+    for msg in bus.iter_timed(gst::ClockTime::NONE) {
+        match msg.view() {
+            gst::MessageView::Element(elt) => {
+                if let Some(s) = elt.structure() {
+                    if s.name() == "videoquality" {
+                        report = Some(s.to_owned());
+                    }
+                }
+            }
+            gst::MessageView::Eos(..) => break,
+            gst::MessageView::Error(err) => panic!("{}", err.error()),
+            _ => (),
+        }
+    }
+
+    pipeline.set_state(gst::State::Null).unwrap();
+
+    let metas = metas.lock().unwrap().clone();
+    (metas, report.expect("no report"))
+}
+
+#[test]
+fn test_identical() {
+    let (metas, report) = run(
+        "smpte",
+        "smpte",
+        "video/x-raw,format=I420,width=320,height=240",
+    );
+
+    assert_eq!(metas.len(), 10);
+    for meta in metas {
+        assert_eq!(meta.get::<f64>("psnr").unwrap(), 100.0);
+        assert_eq!(meta.get::<f64>("ssim").unwrap(), 1.0);
+        assert_eq!(meta.get::<gst::Array>("psnr-components").unwrap().len(), 3);
+        assert_eq!(meta.get::<gst::Array>("ssim-components").unwrap().len(), 3);
+    }
+
+    assert_eq!(report.get::<u64>("frames").unwrap(), 10);
+    assert_eq!(report.get::<f64>("psnr-average").unwrap(), 100.0);
+    assert_eq!(report.get::<f64>("psnr-min").unwrap(), 100.0);
+    assert_eq!(report.get::<f64>("ssim-average").unwrap(), 1.0);
+    assert_eq!(report.get::<f64>("ssim-min").unwrap(), 1.0);
+}
+
+#[test]
+fn test_different() {
+    let (metas, report) = run(
+        "smpte",
+        "smpte75",
+        "video/x-raw,format=RGBA,width=320,height=240",
+    );
+
+    assert_eq!(metas.len(), 10);
+    for meta in metas {
+        let psnr = meta.get::<f64>("psnr").unwrap();
+        assert!(psnr > 0.0 && psnr < 100.0, "{psnr}");
+        let ssim = meta.get::<f64>("ssim").unwrap();
+        assert!(ssim > 0.0 && ssim < 1.0, "{ssim}");
+        // Alpha is ignored
+        assert_eq!(meta.get::<gst::Array>("psnr-components").unwrap().len(), 3);
+    }
+
+    assert_eq!(report.get::<u64>("frames").unwrap(), 10);
+    assert!(report.get::<f64>("psnr-average").unwrap() < 100.0);
+    assert!(report.get::<f64>("ssim-average").unwrap() < 1.0);
+}
+
+#[test]
+fn test_worst_case() {
+    let (metas, _report) = run(
+        "black",
+        "white",
+        "video/x-raw,format=GRAY8,width=64,height=48",
+    );
+
+    assert_eq!(metas.len(), 10);
+    for meta in metas {
+        assert_eq!(meta.get::<f64>("psnr").unwrap(), 0.0);
+        assert!(meta.get::<f64>("ssim").unwrap() < 0.001);
+    }
+}